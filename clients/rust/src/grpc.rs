@@ -0,0 +1,251 @@
+//! gRPC client for the service's `ObjectStore` API. Needs the `grpc` feature
+//! and a server with `[grpc]` configured.
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use std::collections::HashMap;
+use tonic::transport::Channel;
+use tonic::{Code, Status};
+
+use crate::{Bucket, Error, ObjectData, ObjectMetadata, Result};
+
+pub mod proto {
+    tonic::include_proto!("object_store.v1");
+}
+
+use proto::object_store_client::ObjectStoreClient as RawClient;
+use proto::{get_object_response, put_object_request};
+
+/// Size of the chunks `put_object` sends.
+const UPLOAD_CHUNK_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct GrpcClient {
+    inner: RawClient<Channel>,
+}
+
+impl GrpcClient {
+    /// Connects to a gRPC endpoint such as `http://localhost:9090`.
+    pub async fn connect(url: impl Into<String>) -> Result<Self> {
+        let channel = Channel::from_shared(url.into())
+            .map_err(|e| Error::BadRequest(e.to_string()))?
+            .connect()
+            .await?;
+        Ok(Self {
+            inner: RawClient::new(channel),
+        })
+    }
+
+    pub async fn create_bucket(&self, name: &str) -> Result<Bucket> {
+        let bucket = self
+            .inner
+            .clone()
+            .create_bucket(proto::CreateBucketRequest {
+                name: name.to_string(),
+            })
+            .await
+            .map_err(status_error)?
+            .into_inner();
+        Ok(bucket.into())
+    }
+
+    pub async fn list_buckets(&self) -> Result<Vec<Bucket>> {
+        let response = self
+            .inner
+            .clone()
+            .list_buckets(proto::ListBucketsRequest {})
+            .await
+            .map_err(status_error)?
+            .into_inner();
+        Ok(response.buckets.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: impl Into<Bytes>,
+        content_type: Option<&str>,
+        metadata: Option<HashMap<String, String>>,
+    ) -> Result<ObjectMetadata> {
+        let mut data: Bytes = data.into();
+        let mut chunks = Vec::with_capacity(data.len() / UPLOAD_CHUNK_BYTES + 1);
+        while data.len() > UPLOAD_CHUNK_BYTES {
+            chunks.push(data.split_to(UPLOAD_CHUNK_BYTES));
+        }
+        chunks.push(data);
+
+        self.put_object_stream(
+            bucket,
+            key,
+            futures::stream::iter(chunks),
+            content_type,
+            metadata,
+        )
+        .await
+    }
+
+    /// Uploads the chunks of `stream` as they are produced.
+    pub async fn put_object_stream(
+        &self,
+        bucket: &str,
+        key: &str,
+        stream: impl Stream<Item = Bytes> + Send + 'static,
+        content_type: Option<&str>,
+        metadata: Option<HashMap<String, String>>,
+    ) -> Result<ObjectMetadata> {
+        let header = proto::PutObjectRequest {
+            part: Some(put_object_request::Part::Header(proto::PutObjectHeader {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                content_type: content_type.map(|ct| ct.to_string()),
+                metadata: metadata.unwrap_or_default(),
+            })),
+        };
+        let chunks = stream.map(|chunk| proto::PutObjectRequest {
+            part: Some(put_object_request::Part::Chunk(chunk)),
+        });
+
+        let metadata = self
+            .inner
+            .clone()
+            .put_object(futures::stream::once(async { header }).chain(chunks))
+            .await
+            .map_err(status_error)?
+            .into_inner();
+        Ok(metadata.into())
+    }
+
+    pub async fn get_object(&self, bucket: &str, key: &str) -> Result<ObjectData> {
+        let (metadata, mut chunks) = self.get_object_stream(bucket, key).await?;
+        let mut data = Vec::with_capacity(metadata.size as usize);
+        while let Some(chunk) = chunks.next().await {
+            data.extend_from_slice(&chunk?);
+        }
+        Ok(ObjectData {
+            metadata,
+            data: data.into(),
+        })
+    }
+
+    /// Returns the object's metadata and a stream of its contents.
+    pub async fn get_object_stream(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<(ObjectMetadata, impl Stream<Item = Result<Bytes>>)> {
+        let mut responses = self
+            .inner
+            .clone()
+            .get_object(proto::GetObjectRequest {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            })
+            .await
+            .map_err(status_error)?
+            .into_inner();
+
+        let metadata = match responses.message().await.map_err(status_error)? {
+            Some(proto::GetObjectResponse {
+                part: Some(get_object_response::Part::Metadata(metadata)),
+            }) => metadata,
+            _ => {
+                return Err(Error::ServerError(
+                    "GetObject response did not start with metadata".to_string(),
+                ))
+            }
+        };
+
+        let chunks = responses.map(|response| match response.map_err(status_error)?.part {
+            Some(get_object_response::Part::Chunk(chunk)) => Ok(chunk),
+            _ => Err(Error::ServerError(
+                "Unexpected message in GetObject response".to_string(),
+            )),
+        });
+        Ok((metadata.into(), chunks))
+    }
+
+    pub async fn head_object(&self, bucket: &str, key: &str) -> Result<ObjectMetadata> {
+        let metadata = self
+            .inner
+            .clone()
+            .head_object(proto::HeadObjectRequest {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            })
+            .await
+            .map_err(status_error)?
+            .into_inner();
+        Ok(metadata.into())
+    }
+
+    pub async fn delete_object(&self, bucket: &str, key: &str) -> Result<()> {
+        self.inner
+            .clone()
+            .delete_object(proto::DeleteObjectRequest {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            })
+            .await
+            .map_err(status_error)?;
+        Ok(())
+    }
+
+    pub async fn list_objects(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        max_keys: Option<u32>,
+    ) -> Result<Vec<ObjectMetadata>> {
+        let mut objects = self
+            .inner
+            .clone()
+            .list_objects(proto::ListObjectsRequest {
+                bucket: bucket.to_string(),
+                prefix: prefix.map(|p| p.to_string()),
+                max_keys,
+            })
+            .await
+            .map_err(status_error)?
+            .into_inner();
+
+        let mut result = Vec::new();
+        while let Some(metadata) = objects.message().await.map_err(status_error)? {
+            result.push(metadata.into());
+        }
+        Ok(result)
+    }
+}
+
+fn status_error(status: Status) -> Error {
+    match status.code() {
+        Code::NotFound => Error::NotFound(status.message().to_string()),
+        Code::AlreadyExists => Error::AlreadyExists(status.message().to_string()),
+        Code::InvalidArgument => Error::BadRequest(status.message().to_string()),
+        _ => Error::Grpc(Box::new(status)),
+    }
+}
+
+impl From<proto::Bucket> for Bucket {
+    fn from(bucket: proto::Bucket) -> Self {
+        Self {
+            id: bucket.id,
+            name: bucket.name,
+            created_at: bucket.created_at,
+        }
+    }
+}
+
+impl From<proto::ObjectMetadata> for ObjectMetadata {
+    fn from(metadata: proto::ObjectMetadata) -> Self {
+        Self {
+            key: metadata.key,
+            size: metadata.size,
+            content_type: metadata.content_type,
+            etag: metadata.etag,
+            last_modified: crate::parse_timestamp(&metadata.last_modified)
+                .unwrap_or(chrono::DateTime::UNIX_EPOCH),
+            metadata: metadata.metadata,
+        }
+    }
+}
@@ -1,8 +1,22 @@
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::{Future, Stream, StreamExt, TryStreamExt};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::header::HeaderMap;
 use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWriteExt};
+use tokio_util::io::{ReaderStream, StreamReader};
+use tracing::warn;
+
+#[cfg(feature = "grpc")]
+pub mod grpc;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -20,10 +34,47 @@ pub enum Error {
 
     #[error("Server error: {0}")]
     ServerError(String),
+
+    /// The API key may not perform `operation`, e.g. `write_object`, on
+    /// `resource`, a bucket name or `bucket/key`.
+    #[error("Permission denied: {operation} on {resource}")]
+    PermissionDenied { operation: String, resource: String },
+
+    #[error("Invalid response: {0}")]
+    InvalidResponse(#[from] serde_json::Error),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[cfg(feature = "grpc")]
+    #[error("gRPC error: {0}")]
+    Grpc(Box<tonic::Status>),
+
+    #[cfg(feature = "grpc")]
+    #[error("gRPC transport error: {0}")]
+    GrpcTransport(#[from] tonic::transport::Error),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Why [`ObjectStoreClientBuilder::try_build`] could not set up the HTTP client.
+#[derive(Error, Debug)]
+pub enum BuildError {
+    #[error("Invalid certificate: {0}")]
+    InvalidCertificate(reqwest::Error),
+
+    #[error("Failed to build HTTP client: {0}")]
+    Client(reqwest::Error),
+}
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Default granularity of progress reports, in bytes.
+pub const DEFAULT_PROGRESS_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Objects requested per page by the paged listing methods.
+pub const DEFAULT_LIST_PAGE_SIZE: usize = 1000;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bucket {
     pub id: String,
@@ -37,16 +88,88 @@ pub struct ObjectMetadata {
     pub size: u64,
     pub content_type: Option<String>,
     pub etag: String,
-    pub last_modified: String,
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub last_modified: DateTime<Utc>,
     pub metadata: HashMap<String, String>,
 }
 
+impl ObjectMetadata {
+    /// `last_modified` in the RFC 2822 format of HTTP dates.
+    pub fn last_modified_rfc2822(&self) -> String {
+        self.last_modified.to_rfc2822()
+    }
+}
+
+/// Parses an RFC 3339 timestamp, as the server sends, or an RFC 2822 date, as
+/// older servers sent in `Last-Modified`.
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_rfc2822(value))
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Characters left as they are in object keys: unreserved ones, plus `/` so
+/// nested keys keep their path segments.
+const KEY_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~')
+    .remove(b'/');
+
+/// Percent-encodes an object key for use in a request path, so keys with
+/// spaces, `#`, `?`, `%` or non-ASCII characters reach the server unchanged.
+fn url_encode_key(key: &str) -> String {
+    utf8_percent_encode(key, KEY_ENCODE_SET).to_string()
+}
+
+/// RFC 3339 in UTC with a `Z` suffix, which needs no escaping in a query string.
+fn query_timestamp(value: DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+}
+
+fn deserialize_timestamp<'de, D>(deserializer: D) -> std::result::Result<DateTime<Utc>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    parse_timestamp(&value)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid timestamp '{}'", value)))
+}
+
 #[derive(Debug, Clone)]
 pub struct ObjectData {
     pub metadata: ObjectMetadata,
     pub data: Bytes,
 }
 
+/// Reported by [`ObjectStoreClient::download_with_progress`]. `total_bytes` and
+/// `fraction` are only known when the server sent a `Content-Length`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DownloadProgress {
+    pub bytes_received: u64,
+    pub total_bytes: Option<u64>,
+    pub fraction: Option<f64>,
+}
+
+/// Reported by [`ObjectStoreClient::upload_with_progress`] as each part of the
+/// body is handed to the connection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UploadProgress {
+    pub bytes_sent: u64,
+    pub total_bytes: u64,
+    pub fraction: f64,
+}
+
+fn progress_fraction(done: u64, total: u64) -> f64 {
+    if total == 0 {
+        1.0
+    } else {
+        done as f64 / total as f64
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 struct CreateBucketRequest {
     name: String,
@@ -60,6 +183,132 @@ struct ListBucketsResponse {
 #[derive(Debug, Deserialize)]
 struct ListObjectsResponse {
     objects: Vec<ObjectMetadata>,
+    #[serde(default)]
+    total_count: Option<u64>,
+}
+
+/// A listing with its total, from [`ObjectStoreClient::list_objects_with_count`].
+#[derive(Debug, Clone)]
+pub struct ObjectListing {
+    pub objects: Vec<ObjectMetadata>,
+    /// Objects matching the prefix and filters across every page, ignoring
+    /// `max_keys` and `start_after`.
+    pub total_count: Option<u64>,
+}
+
+/// One page of a key-ordered listing, from [`ObjectStoreClient::list_objects_page`].
+#[derive(Debug, Clone)]
+pub struct ListObjectsPage {
+    pub objects: Vec<ObjectMetadata>,
+    /// Continues the listing after this page; `None` once this page was the last.
+    pub continuation_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    Key,
+    Size,
+    LastModified,
+}
+
+impl SortField {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SortField::Key => "key",
+            SortField::Size => "size",
+            SortField::LastModified => "last_modified",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BucketSortField {
+    Name,
+    CreatedAt,
+}
+
+impl BucketSortField {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BucketSortField::Name => "name",
+            BucketSortField::CreatedAt => "created_at",
+        }
+    }
+}
+
+/// Ordering and filtering for [`ObjectStoreClient::list_buckets_with_options`].
+/// Unset fields use the server's defaults: newest buckets first, and names
+/// ascending when sorting by name.
+#[derive(Debug, Clone, Default)]
+pub struct ListBucketsOptions {
+    pub sort_by: Option<BucketSortField>,
+    pub sort_order: Option<SortOrder>,
+    /// Only buckets whose name starts with this
+    pub name_prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ListObjectsOptions {
+    pub prefix: Option<String>,
+    pub max_keys: Option<usize>,
+    pub sort_by: Option<SortField>,
+    pub sort_order: Option<SortOrder>,
+    /// `key=value` match against custom metadata
+    pub metadata_filter: Option<(String, String)>,
+    /// Only objects modified strictly after this time
+    pub modified_after: Option<DateTime<Utc>>,
+    /// Only objects modified strictly before this time
+    pub modified_before: Option<DateTime<Utc>>,
+    /// Only keys that sort after this one. The server then lists in key order.
+    pub start_after: Option<String>,
+    /// Ask the server for the total count, which can be slow on large buckets.
+    pub include_count: bool,
+}
+
+impl ListObjectsOptions {
+    fn new(prefix: Option<&str>, max_keys: Option<usize>) -> Self {
+        Self {
+            prefix: prefix.map(|p| p.to_string()),
+            max_keys,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum InventoryFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl InventoryFormat {
+    fn as_str(&self) -> &'static str {
+        match self {
+            InventoryFormat::Json => "json",
+            InventoryFormat::Csv => "csv",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
@@ -76,35 +325,429 @@ pub struct PublicUrlResponse {
     pub expires_in: u64,
 }
 
-pub struct ObjectStoreClient {
-    client: Client,
+/// How a [`FalloverClient`] orders its URLs for each request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FalloverStrategy {
+    /// Always start with the primary URL, then the fallbacks in order.
+    #[default]
+    Sequential,
+    /// Start each request with the next URL in turn, spreading load across all of them.
+    RoundRobin,
+}
+
+/// How long a URL that just failed is passed over in favour of healthy ones.
+const UNHEALTHY_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Request outcomes for one of a [`FalloverClient`]'s URLs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlHealth {
+    pub url: String,
+    pub successes: u64,
+    pub failures: u64,
+}
+
+impl UrlHealth {
+    /// Share of requests that succeeded, `1.0` before the first request.
+    pub fn success_rate(&self) -> f64 {
+        let attempts = self.successes + self.failures;
+        if attempts == 0 {
+            1.0
+        } else {
+            self.successes as f64 / attempts as f64
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct UrlState {
+    successes: u64,
+    failures: u64,
+    consecutive_failures: u32,
+    last_failure: Option<Instant>,
+}
+
+impl UrlState {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.consecutive_failures == 0
+            || self
+                .last_failure
+                .is_none_or(|at| now.duration_since(at) >= UNHEALTHY_RETRY_INTERVAL)
+    }
+}
+
+/// The server URLs an [`ObjectStoreClient`] sends requests to. A request moves
+/// on to the next URL only when a server is unreachable or answers with a 5xx;
+/// any other response, including 404 or 409, is returned as is. URLs that just
+/// failed are tried after the healthy ones for the next 30 seconds.
+#[derive(Debug)]
+pub struct FalloverClient {
+    urls: Vec<String>,
+    strategy: FalloverStrategy,
+    state: Mutex<Vec<UrlState>>,
+    next_start: AtomicUsize,
+    last_used: AtomicUsize,
+}
+
+impl FalloverClient {
+    pub fn new(urls: Vec<String>, strategy: FalloverStrategy) -> Self {
+        assert!(!urls.is_empty(), "FalloverClient needs at least one URL");
+        // Normalized the way reqwest prints request URLs, so `retarget` can
+        // recognise the primary URL's prefix
+        let urls: Vec<String> = urls
+            .into_iter()
+            .map(|url| {
+                let url = url::Url::parse(&url).map_or(url, |parsed| parsed.to_string());
+                url.trim_end_matches('/').to_string()
+            })
+            .collect();
+        Self {
+            state: Mutex::new(urls.iter().map(|_| UrlState::default()).collect()),
+            urls,
+            strategy,
+            next_start: AtomicUsize::new(0),
+            last_used: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    /// The URL that answered the most recent request, or the primary URL before
+    /// the first one.
+    pub fn last_used_url(&self) -> &str {
+        &self.urls[self.last_used.load(Ordering::Relaxed)]
+    }
+
+    pub fn health(&self) -> Vec<UrlHealth> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        self.urls
+            .iter()
+            .zip(state.iter())
+            .map(|(url, state)| UrlHealth {
+                url: url.clone(),
+                successes: state.successes,
+                failures: state.failures,
+            })
+            .collect()
+    }
+
+    /// Indices of the URLs to try for one request, healthy ones first.
+    fn candidates(&self) -> Vec<usize> {
+        let start = match self.strategy {
+            FalloverStrategy::Sequential => 0,
+            FalloverStrategy::RoundRobin => {
+                self.next_start.fetch_add(1, Ordering::Relaxed) % self.urls.len()
+            }
+        };
+        let mut order: Vec<usize> = (0..self.urls.len())
+            .map(|i| (start + i) % self.urls.len())
+            .collect();
+
+        let now = Instant::now();
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        order.sort_by_key(|&i| !state[i].is_healthy(now));
+        order
+    }
+
+    fn record(&self, index: usize, success: bool) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let url = &mut state[index];
+        if success {
+            url.successes += 1;
+            url.consecutive_failures = 0;
+        } else {
+            url.failures += 1;
+            url.consecutive_failures += 1;
+            url.last_failure = Some(Instant::now());
+        }
+    }
+
+    /// Points a request built against the primary URL at the URL at `index`.
+    fn retarget(&self, request: &mut reqwest::Request, index: usize) -> Result<()> {
+        if index == 0 {
+            return Ok(());
+        }
+        let Some(rest) = request.url().as_str().strip_prefix(self.urls[0].as_str()) else {
+            return Ok(());
+        };
+        let target = format!("{}{}", self.urls[index], rest);
+        *request.url_mut() = url::Url::parse(&target)
+            .map_err(|e| Error::BadRequest(format!("Invalid URL {}: {}", target, e)))?;
+        Ok(())
+    }
+}
+
+/// Adjusts every request before it is sent, for example to add auth or
+/// tracing headers.
+pub type BeforeRequestHook =
+    Arc<dyn Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder + Send + Sync>;
+
+/// Sees every response before the client maps it to a result, for example to
+/// log it or record metrics.
+pub type AfterResponseHook = Arc<dyn Fn(&reqwest::Response) + Send + Sync>;
+
+/// Builds an [`ObjectStoreClient`] with fallback servers or a custom HTTP client.
+pub struct ObjectStoreClientBuilder {
     base_url: String,
+    fallback_urls: Vec<String>,
+    strategy: FalloverStrategy,
+    client: Option<Client>,
+    root_certificates: Vec<reqwest::Certificate>,
+    accept_invalid_certs: bool,
+    progress_buffer_size: usize,
+    validate_content_type: bool,
+    before_request: Vec<BeforeRequestHook>,
+    after_response: Vec<AfterResponseHook>,
 }
 
-impl ObjectStoreClient {
+impl ObjectStoreClientBuilder {
     pub fn new(base_url: impl Into<String>) -> Self {
         Self {
-            client: Client::new(),
             base_url: base_url.into(),
+            fallback_urls: Vec::new(),
+            strategy: FalloverStrategy::default(),
+            client: None,
+            root_certificates: Vec::new(),
+            accept_invalid_certs: false,
+            progress_buffer_size: DEFAULT_PROGRESS_BUFFER_SIZE,
+            validate_content_type: false,
+            before_request: Vec::new(),
+            after_response: Vec::new(),
         }
     }
 
-    pub fn with_client(base_url: impl Into<String>, client: Client) -> Self {
-        Self {
+    /// Servers to try, in order, when the base URL is unreachable or failing.
+    pub fn fallback_urls(mut self, urls: Vec<String>) -> Self {
+        self.fallback_urls = urls;
+        self
+    }
+
+    pub fn fallover_strategy(mut self, strategy: FalloverStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sends requests through `client`. Its own TLS settings apply, so
+    /// certificates added to this builder are ignored.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Trusts servers whose certificate chains up to `cert`, such as a
+    /// private corporate CA, on top of the system trust store.
+    pub fn add_root_certificate(mut self, cert: reqwest::Certificate) -> Self {
+        self.root_certificates.push(cert);
+        self
+    }
+
+    /// [`add_root_certificate`](Self::add_root_certificate) with a
+    /// PEM-encoded certificate.
+    pub fn with_certificate_pem(self, pem: &[u8]) -> std::result::Result<Self, BuildError> {
+        let cert = reqwest::Certificate::from_pem(pem).map_err(BuildError::InvalidCertificate)?;
+        Ok(self.add_root_certificate(cert))
+    }
+
+    /// Accepts any server certificate, including expired, self-signed and
+    /// wrong-host ones. Anyone on the network path can then read and change
+    /// traffic, so only use this in development.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// See [`ObjectStoreClient::with_progress_buffer_size`].
+    pub fn progress_buffer_size(mut self, bytes: usize) -> Self {
+        self.progress_buffer_size = bytes.max(1);
+        self
+    }
+
+    /// Rejects uploads whose content type is not a valid MIME type with
+    /// [`Error::BadRequest`] before anything is sent, instead of leaving it to
+    /// the server, which only checks when configured to.
+    pub fn validate_content_type(mut self, validate: bool) -> Self {
+        self.validate_content_type = validate;
+        self
+    }
+
+    /// Passes every request through `hook` before it is sent. Hooks run in the
+    /// order they were added.
+    pub fn before_request(mut self, hook: BeforeRequestHook) -> Self {
+        self.before_request.push(hook);
+        self
+    }
+
+    /// Shows every response to `hook` before it is mapped to a result. With
+    /// fallback servers only the response that is used is shown, not those
+    /// of servers that failed.
+    pub fn after_response(mut self, hook: AfterResponseHook) -> Self {
+        self.after_response.push(hook);
+        self
+    }
+
+    /// # Panics
+    ///
+    /// When the HTTP client can't be built, as with [`Client::new`]. Use
+    /// [`try_build`](Self::try_build) to handle that instead.
+    pub fn build(self) -> ObjectStoreClient {
+        self.try_build().expect("Failed to build HTTP client")
+    }
+
+    pub fn try_build(self) -> std::result::Result<ObjectStoreClient, BuildError> {
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                let mut builder = Client::builder();
+                for cert in self.root_certificates {
+                    builder = builder.add_root_certificate(cert);
+                }
+                if self.accept_invalid_certs {
+                    warn!("TLS certificate validation is disabled; only use this in development");
+                    builder = builder.danger_accept_invalid_certs(true);
+                }
+                builder.build().map_err(BuildError::Client)?
+            }
+        };
+
+        let mut urls = vec![self.base_url];
+        urls.extend(self.fallback_urls);
+        let fallover = FalloverClient::new(urls, self.strategy);
+        Ok(ObjectStoreClient {
             client,
-            base_url: base_url.into(),
+            base_url: fallover.urls()[0].clone(),
+            fallover,
+            last_request_id: Mutex::new(None),
+            progress_buffer_size: self.progress_buffer_size,
+            validate_content_type: self.validate_content_type,
+            before_request: self.before_request,
+            after_response: self.after_response,
+        })
+    }
+}
+
+pub struct ObjectStoreClient {
+    client: Client,
+    base_url: String,
+    fallover: FalloverClient,
+    last_request_id: Mutex<Option<String>>,
+    progress_buffer_size: usize,
+    validate_content_type: bool,
+    before_request: Vec<BeforeRequestHook>,
+    after_response: Vec<AfterResponseHook>,
+}
+
+impl ObjectStoreClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::builder(base_url).build()
+    }
+
+    pub fn with_client(base_url: impl Into<String>, client: Client) -> Self {
+        Self::builder(base_url).client(client).build()
+    }
+
+    pub fn builder(base_url: impl Into<String>) -> ObjectStoreClientBuilder {
+        ObjectStoreClientBuilder::new(base_url)
+    }
+
+    /// Sets how many bytes pass between progress callbacks (the last one may
+    /// cover fewer). Defaults to [`DEFAULT_PROGRESS_BUFFER_SIZE`].
+    pub fn with_progress_buffer_size(mut self, bytes: usize) -> Self {
+        self.progress_buffer_size = bytes.max(1);
+        self
+    }
+
+    /// The `X-Request-Id` of the most recent response, for correlating a call
+    /// with server logs.
+    pub fn last_request_id(&self) -> Option<String> {
+        self.last_request_id
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// The server that answered the most recent request.
+    pub fn last_used_url(&self) -> &str {
+        self.fallover.last_used_url()
+    }
+
+    /// Per-URL request outcomes, primary URL first.
+    pub fn url_health(&self) -> Vec<UrlHealth> {
+        self.fallover.health()
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let request = self
+            .before_request
+            .iter()
+            .fold(request, |request, hook| hook(request));
+        let response = self.send_with_fallover(request.build()?).await?;
+        for hook in &self.after_response {
+            hook(&response);
+        }
+        let request_id = response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        if request_id.is_some() {
+            *self
+                .last_request_id
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()) = request_id;
+        }
+        Ok(response)
+    }
+
+    /// Sends `request` to each candidate URL until one is reachable and does
+    /// not answer with a 5xx. Streaming bodies cannot be replayed, so those
+    /// requests only go to the first candidate.
+    async fn send_with_fallover(&self, request: reqwest::Request) -> Result<reqwest::Response> {
+        let candidates = self.fallover.candidates();
+        let mut pending = Some(request);
+
+        for (attempt, &index) in candidates.iter().enumerate() {
+            let Some(mut request) = pending.take() else {
+                break;
+            };
+            if attempt + 1 < candidates.len() {
+                pending = request.try_clone();
+            }
+            self.fallover.retarget(&mut request, index)?;
+
+            match self.client.execute(request).await {
+                Ok(response) if response.status().is_server_error() => {
+                    self.fallover.record(index, false);
+                    if pending.is_none() {
+                        self.fallover.last_used.store(index, Ordering::Relaxed);
+                        return Ok(response);
+                    }
+                }
+                Ok(response) => {
+                    self.fallover.record(index, true);
+                    self.fallover.last_used.store(index, Ordering::Relaxed);
+                    return Ok(response);
+                }
+                Err(e) if e.is_connect() || e.is_timeout() => {
+                    self.fallover.record(index, false);
+                    if pending.is_none() {
+                        return Err(e.into());
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
         }
+
+        unreachable!("the last candidate always returns")
     }
 
     pub async fn ping(&self) -> Result<()> {
         let url = format!("{}/ping", self.base_url);
-        let response = self.client.get(&url).send().await?;
+        let response = self.send(self.client.get(&url)).await?;
 
         match response.status() {
             StatusCode::OK => Ok(()),
-            _ => Err(Error::ServerError(
-                response.text().await.unwrap_or_default(),
-            )),
+            _ => Err(unexpected_response(response).await),
         }
     }
 
@@ -114,7 +757,7 @@ impl ObjectStoreClient {
             name: name.to_string(),
         };
 
-        let response = self.client.post(&url).json(&req).send().await?;
+        let response = self.send(self.client.post(&url).json(&req)).await?;
 
         match response.status() {
             StatusCode::OK => Ok(response.json().await?),
@@ -122,9 +765,7 @@ impl ObjectStoreClient {
             StatusCode::BAD_REQUEST => {
                 Err(Error::BadRequest(response.text().await.unwrap_or_default()))
             }
-            _ => Err(Error::ServerError(
-                response.text().await.unwrap_or_default(),
-            )),
+            _ => Err(unexpected_response(response).await),
         }
     }
 
@@ -134,50 +775,70 @@ impl ObjectStoreClient {
             name: name.to_string(),
         };
 
-        let response = self.client.put(&url).json(&req).send().await?;
+        let response = self.send(self.client.put(&url).json(&req)).await?;
 
         match response.status() {
             StatusCode::OK => Ok(response.json().await?),
             StatusCode::BAD_REQUEST => {
                 Err(Error::BadRequest(response.text().await.unwrap_or_default()))
             }
-            _ => Err(Error::ServerError(
-                response.text().await.unwrap_or_default(),
-            )),
+            _ => Err(unexpected_response(response).await),
         }
     }
 
     pub async fn get_bucket(&self, id: &str) -> Result<Bucket> {
         let url = format!("{}/buckets/{}", self.base_url, id);
-        let response = self.client.get(&url).send().await?;
+        let response = self.send(self.client.get(&url)).await?;
 
         match response.status() {
             StatusCode::OK => Ok(response.json().await?),
             StatusCode::NOT_FOUND => Err(Error::NotFound(id.to_string())),
-            _ => Err(Error::ServerError(
-                response.text().await.unwrap_or_default(),
-            )),
+            _ => Err(unexpected_response(response).await),
         }
     }
 
     pub async fn list_buckets(&self) -> Result<Vec<Bucket>> {
-        let url = format!("{}/buckets", self.base_url);
-        let response = self.client.get(&url).send().await?;
+        self.list_buckets_with_options(&ListBucketsOptions::default())
+            .await
+    }
+
+    pub async fn list_buckets_with_options(
+        &self,
+        options: &ListBucketsOptions,
+    ) -> Result<Vec<Bucket>> {
+        let mut url = format!("{}/buckets", self.base_url);
+        let mut params = vec![];
+        if let Some(field) = options.sort_by {
+            params.push(format!("sort_by={}", field.as_str()));
+        }
+        if let Some(order) = options.sort_order {
+            params.push(format!("order={}", order.as_str()));
+        }
+        if let Some(prefix) = &options.name_prefix {
+            params.push(format!(
+                "prefix={}",
+                url::form_urlencoded::byte_serialize(prefix.as_bytes()).collect::<String>()
+            ));
+        }
+        if !params.is_empty() {
+            url.push('?');
+            url.push_str(&params.join("&"));
+        }
+
+        let response = self.send(self.client.get(&url)).await?;
 
         match response.status() {
             StatusCode::OK => {
                 let resp: ListBucketsResponse = response.json().await?;
                 Ok(resp.buckets)
             }
-            _ => Err(Error::ServerError(
-                response.text().await.unwrap_or_default(),
-            )),
+            _ => Err(unexpected_response(response).await),
         }
     }
 
     pub async fn delete_bucket(&self, name: &str) -> Result<()> {
         let url = format!("{}/buckets/{}", self.base_url, name);
-        let response = self.client.delete(&url).send().await?;
+        let response = self.send(self.client.delete(&url)).await?;
 
         match response.status() {
             StatusCode::NO_CONTENT => Ok(()),
@@ -185,9 +846,7 @@ impl ObjectStoreClient {
             StatusCode::BAD_REQUEST => {
                 Err(Error::BadRequest(response.text().await.unwrap_or_default()))
             }
-            _ => Err(Error::ServerError(
-                response.text().await.unwrap_or_default(),
-            )),
+            _ => Err(unexpected_response(response).await),
         }
     }
 
@@ -199,20 +858,15 @@ impl ObjectStoreClient {
         content_type: Option<&str>,
         metadata: Option<HashMap<String, String>>,
     ) -> Result<ObjectMetadata> {
-        let url = format!("{}/buckets/{}/objects/{}", self.base_url, bucket, key);
-        let mut request = self.client.put(&url);
-
-        if let Some(ct) = content_type {
-            request = request.header("content-type", ct);
-        }
-
-        if let Some(meta) = metadata {
-            for (k, v) in meta {
-                request = request.header(format!("x-object-meta-{}", k), v);
-            }
-        }
-
-        let response = request.body(data.into()).send().await?;
+        self.check_content_type(content_type)?;
+        let url = format!(
+            "{}/buckets/{}/objects/{}",
+            self.base_url,
+            bucket,
+            url_encode_key(key)
+        );
+        let request = Self::upload_request(self.client.put(&url), data, content_type, metadata);
+        let response = self.send(request).await?;
 
         match response.status() {
             StatusCode::OK => Ok(response.json().await?),
@@ -220,193 +874,680 @@ impl ObjectStoreClient {
             StatusCode::BAD_REQUEST => {
                 Err(Error::BadRequest(response.text().await.unwrap_or_default()))
             }
-            _ => Err(Error::ServerError(
-                response.text().await.unwrap_or_default(),
-            )),
+            _ => Err(unexpected_response(response).await),
         }
     }
 
-    pub async fn get_object(&self, bucket: &str, key: &str) -> Result<ObjectData> {
-        let url = format!("{}/buckets/{}/objects/{}", self.base_url, bucket, key);
-        let response = self.client.get(&url).send().await?;
+    /// Uploads an object unless one already exists under `key`. Returns the
+    /// stored metadata and `true` if the object was newly created, or the
+    /// existing object's metadata and `false`.
+    pub async fn upsert_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: impl Into<Bytes>,
+        content_type: Option<&str>,
+        metadata: Option<HashMap<String, String>>,
+    ) -> Result<(ObjectMetadata, bool)> {
+        self.check_content_type(content_type)?;
+        let url = format!(
+            "{}/buckets/{}/upsert/{}",
+            self.base_url,
+            bucket,
+            url_encode_key(key)
+        );
+        let request = Self::upload_request(self.client.put(&url), data, content_type, metadata);
+        let response = self.send(request).await?;
 
         match response.status() {
-            StatusCode::OK => {
-                let etag = response
-                    .headers()
-                    .get("etag")
-                    .and_then(|v| v.to_str().ok())
-                    .unwrap_or("")
-                    .to_string();
-
-                let last_modified = response
-                    .headers()
-                    .get("last-modified")
-                    .and_then(|v| v.to_str().ok())
-                    .unwrap_or("")
-                    .to_string();
-
-                let content_type = response
-                    .headers()
-                    .get("content-type")
-                    .and_then(|v| v.to_str().ok())
-                    .map(|s| s.to_string());
-
-                let size = response
-                    .headers()
-                    .get("content-length")
-                    .and_then(|v| v.to_str().ok())
-                    .and_then(|s| s.parse().ok())
-                    .unwrap_or(0);
-
-                // Extract custom metadata from x-object-meta-* headers
-                let mut metadata = HashMap::new();
-                for (header_name, header_value) in response.headers().iter() {
-                    if let Some(meta_key) = header_name.as_str().strip_prefix("x-object-meta-") {
-                        if let Ok(meta_value) = header_value.to_str() {
-                            metadata.insert(meta_key.to_string(), meta_value.to_string());
-                        }
-                    }
-                }
-
-                let data = response.bytes().await?;
-
-                Ok(ObjectData {
-                    metadata: ObjectMetadata {
-                        key: key.to_string(),
-                        size,
-                        content_type,
-                        etag,
-                        last_modified,
-                        metadata,
-                    },
-                    data,
-                })
+            StatusCode::CREATED => Ok((response.json().await?, true)),
+            StatusCode::OK => Ok((response.json().await?, false)),
+            StatusCode::NOT_FOUND => Err(Error::NotFound(bucket.to_string())),
+            StatusCode::BAD_REQUEST => {
+                Err(Error::BadRequest(response.text().await.unwrap_or_default()))
             }
-            StatusCode::NOT_FOUND => Err(Error::NotFound(format!("{}/{}", bucket, key))),
-            _ => Err(Error::ServerError(
-                response.text().await.unwrap_or_default(),
-            )),
+            _ => Err(unexpected_response(response).await),
         }
     }
 
-    pub async fn head_object(&self, bucket: &str, key: &str) -> Result<ObjectMetadata> {
-        let url = format!("{}/buckets/{}/objects/{}", self.base_url, bucket, key);
-        let response = self.client.head(&url).send().await?;
+    /// Uploads the body read from `reader` as it is produced, without buffering
+    /// the whole object. The length isn't known up front, so the request is sent
+    /// with chunked transfer encoding.
+    pub async fn put_object_stream<R: AsyncRead + Unpin + Send + 'static>(
+        &self,
+        bucket: &str,
+        key: &str,
+        reader: R,
+        content_type: Option<&str>,
+        metadata: Option<HashMap<String, String>>,
+    ) -> Result<ObjectMetadata> {
+        self.put_reader(bucket, key, reader, None, content_type, metadata)
+            .await
+    }
 
-        match response.status() {
-            StatusCode::OK => {
-                let etag = response
-                    .headers()
-                    .get("etag")
-                    .and_then(|v| v.to_str().ok())
-                    .unwrap_or("")
-                    .to_string();
-
-                let last_modified = response
-                    .headers()
-                    .get("last-modified")
-                    .and_then(|v| v.to_str().ok())
-                    .unwrap_or("")
-                    .to_string();
-
-                let content_type = response
-                    .headers()
-                    .get("content-type")
-                    .and_then(|v| v.to_str().ok())
-                    .map(|s| s.to_string());
-
-                let size = response
-                    .headers()
-                    .get("content-length")
-                    .and_then(|v| v.to_str().ok())
-                    .and_then(|s| s.parse().ok())
-                    .unwrap_or(0);
-
-                // Extract custom metadata from x-object-meta-* headers
-                let mut metadata = HashMap::new();
-                for (header_name, header_value) in response.headers().iter() {
-                    if let Some(meta_key) = header_name.as_str().strip_prefix("x-object-meta-") {
-                        if let Ok(meta_value) = header_value.to_str() {
-                            metadata.insert(meta_key.to_string(), meta_value.to_string());
-                        }
-                    }
+    /// Streams the file at `path` into an object, sending its size as
+    /// `Content-Length`.
+    pub async fn upload_from_file(
+        &self,
+        bucket: &str,
+        key: &str,
+        path: impl AsRef<Path>,
+        content_type: Option<&str>,
+        metadata: Option<HashMap<String, String>>,
+    ) -> Result<ObjectMetadata> {
+        let file = tokio::fs::File::open(path).await?;
+        let size = file.metadata().await?.len();
+        self.put_reader(bucket, key, file, Some(size), content_type, metadata)
+            .await
+    }
+
+    async fn put_reader<R: AsyncRead + Unpin + Send + 'static>(
+        &self,
+        bucket: &str,
+        key: &str,
+        reader: R,
+        content_length: Option<u64>,
+        content_type: Option<&str>,
+        metadata: Option<HashMap<String, String>>,
+    ) -> Result<ObjectMetadata> {
+        self.check_content_type(content_type)?;
+        let url = format!(
+            "{}/buckets/{}/objects/{}",
+            self.base_url,
+            bucket,
+            url_encode_key(key)
+        );
+        let mut request = Self::upload_headers(self.client.put(&url), content_type, metadata);
+        if let Some(length) = content_length {
+            request = request.header("content-length", length);
+        }
+        let request = request.body(reqwest::Body::wrap_stream(ReaderStream::new(reader)));
+        let response = self.send(request).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json().await?),
+            StatusCode::NOT_FOUND => Err(Error::NotFound(bucket.to_string())),
+            StatusCode::BAD_REQUEST => {
+                Err(Error::BadRequest(response.text().await.unwrap_or_default()))
+            }
+            _ => Err(unexpected_response(response).await),
+        }
+    }
+
+    /// Like [`put_object`](Self::put_object), calling `progress` after every
+    /// `progress_buffer_size` bytes of the body are sent.
+    pub async fn upload_with_progress<P>(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: impl Into<Bytes>,
+        content_type: Option<&str>,
+        metadata: Option<HashMap<String, String>>,
+        progress: P,
+    ) -> Result<ObjectMetadata>
+    where
+        P: Fn(UploadProgress) + Send + Sync + 'static,
+    {
+        self.check_content_type(content_type)?;
+        let data: Bytes = data.into();
+        let total_bytes = data.len() as u64;
+        let chunk_size = self.progress_buffer_size;
+        let chunks = (0..data.len())
+            .step_by(chunk_size)
+            .map(move |start| data.slice(start..(start + chunk_size).min(data.len())));
+
+        let mut bytes_sent = 0u64;
+        let body = futures::stream::iter(chunks).map(move |chunk| {
+            bytes_sent += chunk.len() as u64;
+            progress(UploadProgress {
+                bytes_sent,
+                total_bytes,
+                fraction: progress_fraction(bytes_sent, total_bytes),
+            });
+            Ok::<_, std::io::Error>(chunk)
+        });
+
+        let url = format!(
+            "{}/buckets/{}/objects/{}",
+            self.base_url,
+            bucket,
+            url_encode_key(key)
+        );
+        let request = Self::upload_headers(self.client.put(&url), content_type, metadata)
+            .header("content-length", total_bytes)
+            .body(reqwest::Body::wrap_stream(body));
+        let response = self.send(request).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json().await?),
+            StatusCode::NOT_FOUND => Err(Error::NotFound(bucket.to_string())),
+            StatusCode::BAD_REQUEST => {
+                Err(Error::BadRequest(response.text().await.unwrap_or_default()))
+            }
+            _ => Err(unexpected_response(response).await),
+        }
+    }
+
+    fn check_content_type(&self, content_type: Option<&str>) -> Result<()> {
+        let Some(ct) = content_type.filter(|_| self.validate_content_type) else {
+            return Ok(());
+        };
+        // Same check as the server's; the parser alone accepts `text/`
+        match ct.parse::<mime::Mime>() {
+            Ok(mime) if !mime.subtype().as_str().is_empty() => Ok(()),
+            _ => Err(Error::BadRequest(format!(
+                "Invalid content type '{}': not a MIME type",
+                ct
+            ))),
+        }
+    }
+
+    fn upload_request(
+        request: reqwest::RequestBuilder,
+        data: impl Into<Bytes>,
+        content_type: Option<&str>,
+        metadata: Option<HashMap<String, String>>,
+    ) -> reqwest::RequestBuilder {
+        Self::upload_headers(request, content_type, metadata).body(data.into())
+    }
+
+    fn upload_headers(
+        mut request: reqwest::RequestBuilder,
+        content_type: Option<&str>,
+        metadata: Option<HashMap<String, String>>,
+    ) -> reqwest::RequestBuilder {
+        if let Some(ct) = content_type {
+            request = request.header("content-type", ct);
+        }
+
+        if let Some(meta) = metadata {
+            for (k, v) in meta {
+                request = request.header(format!("x-object-meta-{}", k), v);
+            }
+        }
+
+        request
+    }
+
+    pub async fn get_object(&self, bucket: &str, key: &str) -> Result<ObjectData> {
+        let url = format!(
+            "{}/buckets/{}/objects/{}",
+            self.base_url,
+            bucket,
+            url_encode_key(key)
+        );
+        let response = self.send(self.client.get(&url)).await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let metadata = object_metadata_from_headers(key, response.headers());
+                let data = response.bytes().await?;
+
+                Ok(ObjectData { metadata, data })
+            }
+            StatusCode::NOT_FOUND => Err(Error::NotFound(format!("{}/{}", bucket, key))),
+            _ => Err(unexpected_response(response).await),
+        }
+    }
+
+    /// Like [`get_object`](Self::get_object), calling `progress` after every
+    /// `progress_buffer_size` bytes received and once more when the body ends.
+    pub async fn download_with_progress<P: Fn(DownloadProgress)>(
+        &self,
+        bucket: &str,
+        key: &str,
+        progress: P,
+    ) -> Result<ObjectData> {
+        let url = format!(
+            "{}/buckets/{}/objects/{}",
+            self.base_url,
+            bucket,
+            url_encode_key(key)
+        );
+        let response = self.send(self.client.get(&url)).await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let metadata = object_metadata_from_headers(key, response.headers());
+                let total_bytes = response.content_length();
+                let report = |bytes_received: u64| {
+                    progress(DownloadProgress {
+                        bytes_received,
+                        total_bytes,
+                        fraction: total_bytes.map(|total| progress_fraction(bytes_received, total)),
+                    })
+                };
+
+                let mut data = Vec::with_capacity(total_bytes.unwrap_or(0) as usize);
+                let mut reported = 0;
+                let mut body = response.bytes_stream();
+                while let Some(chunk) = body.next().await {
+                    data.extend_from_slice(&chunk?);
+                    while data.len() - reported >= self.progress_buffer_size {
+                        reported += self.progress_buffer_size;
+                        report(reported as u64);
+                    }
+                }
+                if reported < data.len() || data.is_empty() {
+                    report(data.len() as u64);
                 }
 
-                Ok(ObjectMetadata {
-                    key: key.to_string(),
-                    size,
-                    content_type,
-                    etag,
-                    last_modified,
+                Ok(ObjectData {
                     metadata,
+                    data: data.into(),
                 })
             }
             StatusCode::NOT_FOUND => Err(Error::NotFound(format!("{}/{}", bucket, key))),
-            _ => Err(Error::ServerError(
-                response.text().await.unwrap_or_default(),
-            )),
+            _ => Err(unexpected_response(response).await),
+        }
+    }
+
+    /// Like [`get_object`](Self::get_object), but hands back the body as it
+    /// arrives instead of buffering it, for objects too large to hold in memory.
+    pub async fn get_object_stream(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<(ObjectMetadata, impl AsyncRead + Unpin + Send)> {
+        let url = format!(
+            "{}/buckets/{}/objects/{}",
+            self.base_url,
+            bucket,
+            url_encode_key(key)
+        );
+        let response = self.send(self.client.get(&url)).await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let metadata = object_metadata_from_headers(key, response.headers());
+                let body = StreamReader::new(
+                    response
+                        .bytes_stream()
+                        .map(|r| r.map_err(std::io::Error::other)),
+                );
+                Ok((metadata, body))
+            }
+            StatusCode::NOT_FOUND => Err(Error::NotFound(format!("{}/{}", bucket, key))),
+            _ => Err(unexpected_response(response).await),
+        }
+    }
+
+    /// Streams an object into a file at `path`, creating or truncating it.
+    pub async fn download_to_file(
+        &self,
+        bucket: &str,
+        key: &str,
+        path: impl AsRef<Path>,
+    ) -> Result<ObjectMetadata> {
+        let (metadata, mut body) = self.get_object_stream(bucket, key).await?;
+
+        let mut file = tokio::fs::File::create(path).await?;
+        tokio::io::copy(&mut body, &mut file).await?;
+        file.flush().await?;
+
+        Ok(metadata)
+    }
+
+    pub async fn head_object(&self, bucket: &str, key: &str) -> Result<ObjectMetadata> {
+        let url = format!(
+            "{}/buckets/{}/objects/{}",
+            self.base_url,
+            bucket,
+            url_encode_key(key)
+        );
+        let response = self.send(self.client.head(&url)).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(object_metadata_from_headers(key, response.headers())),
+            StatusCode::NOT_FOUND => Err(Error::NotFound(format!("{}/{}", bucket, key))),
+            _ => Err(unexpected_response(response).await),
+        }
+    }
+
+    /// Checks for an object with a `HEAD` request. A missing object (or
+    /// bucket) is `Ok(false)`; any other failure is an error.
+    pub async fn object_exists(&self, bucket: &str, key: &str) -> Result<bool> {
+        let url = format!(
+            "{}/buckets/{}/objects/{}",
+            self.base_url,
+            bucket,
+            url_encode_key(key)
+        );
+        let response = self.send(self.client.head(&url)).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(unexpected_response(response).await),
+        }
+    }
+
+    /// Like [`get_object`](Self::get_object), but a missing object is `Ok(None)`.
+    pub async fn get_object_if_exists(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Option<ObjectData>> {
+        match self.get_object(bucket, key).await {
+            Ok(object) => Ok(Some(object)),
+            Err(Error::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
         }
     }
 
     pub async fn get_object_info(&self, bucket: &str, key: &str) -> Result<ObjectMetadata> {
-        let url = format!("{}/buckets/{}/object-info/{}", self.base_url, bucket, key);
-        let response = self.client.get(&url).send().await?;
+        let url = format!(
+            "{}/buckets/{}/object-info/{}",
+            self.base_url,
+            bucket,
+            url_encode_key(key)
+        );
+        let response = self.send(self.client.get(&url)).await?;
 
         match response.status() {
             StatusCode::OK => Ok(response.json().await?),
             StatusCode::NOT_FOUND => Err(Error::NotFound(format!("{}/{}", bucket, key))),
-            _ => Err(Error::ServerError(
-                response.text().await.unwrap_or_default(),
-            )),
+            _ => Err(unexpected_response(response).await),
         }
     }
 
     pub async fn delete_object(&self, bucket: &str, key: &str) -> Result<()> {
-        let url = format!("{}/buckets/{}/objects/{}", self.base_url, bucket, key);
-        let response = self.client.delete(&url).send().await?;
+        let url = format!(
+            "{}/buckets/{}/objects/{}",
+            self.base_url,
+            bucket,
+            url_encode_key(key)
+        );
+        let response = self.send(self.client.delete(&url)).await?;
 
         match response.status() {
             StatusCode::NO_CONTENT => Ok(()),
             StatusCode::NOT_FOUND => Err(Error::NotFound(format!("{}/{}", bucket, key))),
-            _ => Err(Error::ServerError(
-                response.text().await.unwrap_or_default(),
-            )),
+            _ => Err(unexpected_response(response).await),
         }
     }
 
-    pub async fn list_objects(
-        &self,
-        bucket: &str,
-        prefix: Option<&str>,
-        max_keys: Option<usize>,
-    ) -> Result<Vec<ObjectMetadata>> {
+    fn list_objects_url(&self, bucket: &str, options: &ListObjectsOptions) -> String {
         let mut url = format!("{}/buckets/{}/objects", self.base_url, bucket);
         let mut params = vec![];
 
-        if let Some(p) = prefix {
+        if let Some(p) = &options.prefix {
             params.push(format!("prefix={}", p));
         }
-        if let Some(m) = max_keys {
+        if let Some(m) = options.max_keys {
             params.push(format!("max_keys={}", m));
         }
+        if let Some(field) = options.sort_by {
+            params.push(format!("sort_by={}", field.as_str()));
+        }
+        if let Some(order) = options.sort_order {
+            params.push(format!("sort_order={}", order.as_str()));
+        }
+        if let Some((key, value)) = &options.metadata_filter {
+            let filter = format!("{}={}", key, value);
+            params.push(format!(
+                "metadata_filter={}",
+                url::form_urlencoded::byte_serialize(filter.as_bytes()).collect::<String>()
+            ));
+        }
+        if let Some(after) = options.modified_after {
+            params.push(format!("modified_after={}", query_timestamp(after)));
+        }
+        if let Some(before) = options.modified_before {
+            params.push(format!("modified_before={}", query_timestamp(before)));
+        }
+        if let Some(start_after) = &options.start_after {
+            params.push(format!(
+                "start_after={}",
+                url::form_urlencoded::byte_serialize(start_after.as_bytes()).collect::<String>()
+            ));
+        }
+
+        if options.include_count {
+            params.push("include_count=true".to_string());
+        }
 
         if !params.is_empty() {
             url.push('?');
             url.push_str(&params.join("&"));
         }
 
-        let response = self.client.get(&url).send().await?;
+        url
+    }
+
+    /// Lists up to `max_keys` objects, or all of them, in key order. Large
+    /// listings are fetched in pages of at most [`DEFAULT_LIST_PAGE_SIZE`].
+    pub async fn list_objects(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        max_keys: Option<usize>,
+    ) -> Result<Vec<ObjectMetadata>> {
+        let page_size = max_keys.map_or(DEFAULT_LIST_PAGE_SIZE, |max| {
+            max.min(DEFAULT_LIST_PAGE_SIZE)
+        });
+        self.list_objects_stream(bucket, prefix, Some(page_size))
+            .await?
+            .take(max_keys.unwrap_or(usize::MAX))
+            .try_collect()
+            .await
+    }
+
+    /// Lists one page of up to `page_size` objects in key order, starting after
+    /// the previous page's `continuation_token`.
+    pub async fn list_objects_page(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        page_size: usize,
+        continuation_token: Option<&str>,
+    ) -> Result<ListObjectsPage> {
+        let options = ListObjectsOptions {
+            sort_by: Some(SortField::Key),
+            start_after: continuation_token.map(str::to_string),
+            ..ListObjectsOptions::new(prefix, Some(page_size))
+        };
+        let objects = self.list_objects_with_options(bucket, &options).await?;
+
+        // A short page is the last one; a full one may be followed by more
+        let continuation_token = if objects.len() < page_size {
+            None
+        } else {
+            objects.last().map(|obj| obj.key.clone())
+        };
+
+        Ok(ListObjectsPage {
+            objects,
+            continuation_token,
+        })
+    }
+
+    /// Lists objects in key order, requesting the next page of `max_keys`
+    /// (default [`DEFAULT_LIST_PAGE_SIZE`]) only once the current one has been
+    /// consumed. The first page is fetched before returning, so a missing bucket
+    /// fails here; a later page that fails ends the stream with its error.
+    pub async fn list_objects_stream<'a>(
+        &'a self,
+        bucket: &str,
+        prefix: Option<&str>,
+        max_keys: Option<usize>,
+    ) -> Result<impl Stream<Item = Result<ObjectMetadata>> + 'a> {
+        let page_size = max_keys.unwrap_or(DEFAULT_LIST_PAGE_SIZE);
+        let first = self
+            .list_objects_page(bucket, prefix, page_size, None)
+            .await?;
+
+        let state = (
+            bucket.to_string(),
+            prefix.map(str::to_string),
+            first.objects.into_iter(),
+            first.continuation_token,
+        );
+
+        Ok(futures::stream::unfold(
+            state,
+            move |(bucket, prefix, mut objects, mut token)| async move {
+                loop {
+                    if let Some(obj) = objects.next() {
+                        return Some((Ok(obj), (bucket, prefix, objects, token)));
+                    }
+
+                    let after = token.take()?;
+                    match self
+                        .list_objects_page(&bucket, prefix.as_deref(), page_size, Some(&after))
+                        .await
+                    {
+                        Ok(page) => {
+                            objects = page.objects.into_iter();
+                            token = page.continuation_token;
+                        }
+                        Err(e) => return Some((Err(e), (bucket, prefix, objects, None))),
+                    }
+                }
+            },
+        ))
+    }
+
+    /// Calls `f` with every object under `prefix` in key order, waiting for
+    /// each call to finish before the next. Stops at the first failed page.
+    pub async fn for_each_object<F, Fut>(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        mut f: F,
+    ) -> Result<()>
+    where
+        F: FnMut(ObjectMetadata) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let objects = self.list_objects_stream(bucket, prefix, None).await?;
+        let mut objects = std::pin::pin!(objects);
+        while let Some(obj) = objects.next().await {
+            f(obj?).await;
+        }
+        Ok(())
+    }
+
+    pub async fn list_objects_with_options(
+        &self,
+        bucket: &str,
+        options: &ListObjectsOptions,
+    ) -> Result<Vec<ObjectMetadata>> {
+        Ok(self.list_objects_response(bucket, options).await?.objects)
+    }
+
+    /// Like [`list_objects_with_options`](Self::list_objects_with_options),
+    /// also returning how many objects match across all pages, e.g. to show
+    /// the number of pages in a UI.
+    pub async fn list_objects_with_count(
+        &self,
+        bucket: &str,
+        options: &ListObjectsOptions,
+    ) -> Result<ObjectListing> {
+        let options = ListObjectsOptions {
+            include_count: true,
+            ..options.clone()
+        };
+        let resp = self.list_objects_response(bucket, &options).await?;
+        Ok(ObjectListing {
+            objects: resp.objects,
+            total_count: resp.total_count,
+        })
+    }
+
+    async fn list_objects_response(
+        &self,
+        bucket: &str,
+        options: &ListObjectsOptions,
+    ) -> Result<ListObjectsResponse> {
+        let url = self.list_objects_url(bucket, options);
+
+        let response = self.send(self.client.get(&url)).await?;
 
         match response.status() {
-            StatusCode::OK => {
-                let resp: ListObjectsResponse = response.json().await?;
-                Ok(resp.objects)
-            }
+            StatusCode::OK => Ok(response.json().await?),
             StatusCode::NOT_FOUND => Err(Error::NotFound(bucket.to_string())),
-            _ => Err(Error::ServerError(
-                response.text().await.unwrap_or_default(),
+            _ => Err(unexpected_response(response).await),
+        }
+    }
+
+    /// Lists objects whose custom metadata matches `metadata_filter`. The server
+    /// currently supports a single key-value pair; an empty map lists everything.
+    pub async fn list_objects_filtered(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        metadata_filter: HashMap<String, String>,
+    ) -> Result<Vec<ObjectMetadata>> {
+        if metadata_filter.len() > 1 {
+            return Err(Error::BadRequest(
+                "Only a single metadata filter is supported".to_string(),
+            ));
+        }
+
+        let options = ListObjectsOptions {
+            metadata_filter: metadata_filter.into_iter().next(),
+            ..ListObjectsOptions::new(prefix, None)
+        };
+        self.list_objects_with_options(bucket, &options).await
+    }
+
+    /// Lists objects as newline-delimited JSON, yielding each object as soon as
+    /// its line arrives instead of waiting for the whole listing.
+    pub fn list_objects_ndjson<'a>(
+        &'a self,
+        bucket: &'a str,
+        prefix: Option<&'a str>,
+        max_keys: Option<usize>,
+    ) -> impl Stream<Item = Result<ObjectMetadata>> + 'a {
+        let url = self.list_objects_url(bucket, &ListObjectsOptions::new(prefix, max_keys));
+
+        futures::stream::once(async move {
+            let response = self
+                .send(
+                    self.client
+                        .get(&url)
+                        .header("accept", "application/x-ndjson"),
+                )
+                .await?;
+
+            match response.status() {
+                StatusCode::OK => Ok(response),
+                StatusCode::NOT_FOUND => Err(Error::NotFound(bucket.to_string())),
+                _ => Err(unexpected_response(response).await),
+            }
+        })
+        .map(|result| match result {
+            Ok(response) => ndjson_lines(response).left_stream(),
+            Err(e) => futures::stream::once(async move { Err(e) }).right_stream(),
+        })
+        .flatten()
+    }
+
+    /// Downloads the full bucket inventory as a JSON array or CSV file. The body
+    /// is read incrementally, so large inventories can be piped straight to disk.
+    pub async fn download_inventory(
+        &self,
+        bucket: &str,
+        format: InventoryFormat,
+    ) -> Result<impl AsyncRead + Unpin> {
+        let url = format!(
+            "{}/buckets/{}/inventory?format={}",
+            self.base_url,
+            bucket,
+            format.as_str()
+        );
+
+        let response = self.send(self.client.get(&url)).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(StreamReader::new(
+                response
+                    .bytes_stream()
+                    .map(|r| r.map_err(std::io::Error::other)),
             )),
+            StatusCode::NOT_FOUND => Err(Error::NotFound(bucket.to_string())),
+            _ => Err(unexpected_response(response).await),
         }
     }
 
@@ -417,7 +1558,12 @@ impl ObjectStoreClient {
         expiration_secs: Option<u64>,
         purpose: Option<PublicUrlPurpose>,
     ) -> Result<PublicUrlResponse> {
-        let mut url = format!("{}/buckets/{}/public-url/{}", self.base_url, bucket, key);
+        let mut url = format!(
+            "{}/buckets/{}/public-url/{}",
+            self.base_url,
+            bucket,
+            url_encode_key(key)
+        );
         let mut params = vec![];
 
         if let Some(exp) = expiration_secs {
@@ -437,7 +1583,7 @@ impl ObjectStoreClient {
             url.push_str(&params.join("&"));
         }
 
-        let response = self.client.get(&url).send().await?;
+        let response = self.send(self.client.get(&url)).await?;
 
         match response.status() {
             StatusCode::OK => Ok(response.json().await?),
@@ -445,11 +1591,96 @@ impl ObjectStoreClient {
             StatusCode::BAD_REQUEST => {
                 Err(Error::BadRequest(response.text().await.unwrap_or_default()))
             }
-            _ => Err(Error::ServerError(
-                response.text().await.unwrap_or_default(),
-            )),
+            _ => Err(unexpected_response(response).await),
+        }
+    }
+}
+
+fn ndjson_lines(response: reqwest::Response) -> impl Stream<Item = Result<ObjectMetadata>> {
+    let chunks = response.bytes_stream().boxed();
+
+    futures::stream::unfold(
+        (chunks, Vec::new(), false),
+        |(mut chunks, mut buffer, mut done)| async move {
+            loop {
+                if let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=pos).collect();
+                    if line.iter().all(|b| b.is_ascii_whitespace()) {
+                        continue;
+                    }
+                    let item = serde_json::from_slice(&line).map_err(Error::from);
+                    return Some((item, (chunks, buffer, done)));
+                }
+
+                if done {
+                    if buffer.iter().all(|b| b.is_ascii_whitespace()) {
+                        return None;
+                    }
+                    let line = std::mem::take(&mut buffer);
+                    let item = serde_json::from_slice(&line).map_err(Error::from);
+                    return Some((item, (chunks, buffer, done)));
+                }
+
+                match chunks.next().await {
+                    Some(Ok(chunk)) => buffer.extend_from_slice(&chunk),
+                    Some(Err(e)) => return Some((Err(e.into()), (chunks, buffer, true))),
+                    None => done = true,
+                }
+            }
+        },
+    )
+}
+
+/// The error for a status a method has no case for. A 403 that names the
+/// refused operation is [`Error::PermissionDenied`], anything else a
+/// [`Error::ServerError`] with the response body.
+async fn unexpected_response(response: reqwest::Response) -> Error {
+    #[derive(Deserialize)]
+    struct Denied {
+        operation: String,
+        resource: String,
+    }
+
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if status == StatusCode::FORBIDDEN {
+        if let Ok(denied) = serde_json::from_str::<Denied>(&body) {
+            return Error::PermissionDenied {
+                operation: denied.operation,
+                resource: denied.resource,
+            };
+        }
+    }
+    Error::ServerError(body)
+}
+
+/// Reads object metadata from the headers of a `GET` or `HEAD` response.
+fn object_metadata_from_headers(key: &str, headers: &HeaderMap) -> ObjectMetadata {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    // Extract custom metadata from x-object-meta-* headers
+    let mut metadata = HashMap::new();
+    for (header_name, header_value) in headers.iter() {
+        if let Some(meta_key) = header_name.as_str().strip_prefix("x-object-meta-") {
+            if let Ok(meta_value) = header_value.to_str() {
+                metadata.insert(meta_key.to_string(), meta_value.to_string());
+            }
         }
     }
+
+    ObjectMetadata {
+        key: key.to_string(),
+        size: header("content-length")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0),
+        content_type: header("content-type").map(|s| s.to_string()),
+        etag: header("etag").unwrap_or("").to_string(),
+        // Only missing from responses that didn't come from the service
+        last_modified: header("last-modified")
+            .and_then(parse_timestamp)
+            .unwrap_or(DateTime::UNIX_EPOCH),
+        metadata,
+    }
 }
 
 #[cfg(test)]
@@ -523,12 +1754,42 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_delete_bucket() {
+    async fn test_list_buckets_with_options() {
         let mut server = Server::new_async().await;
         let _m = server
-            .mock("DELETE", "/buckets/test-bucket")
-            .with_status(204)
-            .create_async()
+            .mock("GET", "/buckets")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("sort_by".into(), "name".into()),
+                mockito::Matcher::UrlEncoded("order".into(), "asc".into()),
+                mockito::Matcher::UrlEncoded("prefix".into(), "prod-".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"buckets":[{"id":"bucket-1","name":"prod-a","created_at":"2024-01-02T00:00:00Z"},{"id":"bucket-2","name":"prod-b","created_at":"2024-01-01T00:00:00Z"}]}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        let options = ListBucketsOptions {
+            sort_by: Some(BucketSortField::Name),
+            sort_order: Some(SortOrder::Asc),
+            name_prefix: Some("prod-".to_string()),
+        };
+        let buckets = client.list_buckets_with_options(&options).await.unwrap();
+
+        let names: Vec<_> = buckets.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec!["prod-a", "prod-b"]);
+        _m.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_delete_bucket() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("DELETE", "/buckets/test-bucket")
+            .with_status(204)
+            .create_async()
             .await;
 
         let client = ObjectStoreClient::new(server.url());
@@ -584,6 +1845,156 @@ mod tests {
         assert_eq!(obj.key, "test-key");
         assert_eq!(obj.size, 13);
         assert_eq!(obj.etag, "abc123");
+        assert_eq!(
+            obj.last_modified,
+            "2024-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upsert_object() {
+        let mut server = Server::new_async().await;
+        let body = r#"{"key":"test-key","size":5,"content_type":"text/plain","etag":"abc123","last_modified":"2024-01-01T00:00:00Z","metadata":{}}"#;
+        let created = server
+            .mock("PUT", "/buckets/test-bucket/upsert/new-key")
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body(body)
+            .create_async()
+            .await;
+        let existing = server
+            .mock("PUT", "/buckets/test-bucket/upsert/old-key")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        let (obj, was_created) = client
+            .upsert_object("test-bucket", "new-key", "hello", Some("text/plain"), None)
+            .await
+            .unwrap();
+        assert!(was_created);
+        assert_eq!(obj.etag, "abc123");
+
+        let (_, was_created) = client
+            .upsert_object("test-bucket", "old-key", "hello", None, None)
+            .await
+            .unwrap();
+        assert!(!was_created);
+
+        created.assert_async().await;
+        existing.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_object_exists() {
+        let mut server = Server::new_async().await;
+        let _exists = server
+            .mock("HEAD", "/buckets/test-bucket/objects/present")
+            .with_status(200)
+            .create_async()
+            .await;
+        let _missing = server
+            .mock("HEAD", "/buckets/test-bucket/objects/absent")
+            .with_status(404)
+            .create_async()
+            .await;
+        let _failing = server
+            .mock("HEAD", "/buckets/test-bucket/objects/broken")
+            .with_status(500)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        assert!(client
+            .object_exists("test-bucket", "present")
+            .await
+            .unwrap());
+        assert!(!client.object_exists("test-bucket", "absent").await.unwrap());
+        assert!(matches!(
+            client.object_exists("test-bucket", "broken").await,
+            Err(Error::ServerError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_permission_denied() {
+        let mut server = Server::new_async().await;
+        let _denied = server
+            .mock("DELETE", "/buckets/reports/objects/old.txt")
+            .with_status(403)
+            .with_body(
+                r#"{"error":"Permission denied: 'dashboard' may not delete_object on 'reports/old.txt'","operation":"delete_object","resource":"reports/old.txt","principal":"dashboard"}"#,
+            )
+            .create_async()
+            .await;
+        let _forbidden = server
+            .mock("DELETE", "/buckets/reports/objects/hooked.txt")
+            .with_status(403)
+            .with_body(r#"{"error":"Rejected by hook: no"}"#)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        match client.delete_object("reports", "old.txt").await {
+            Err(Error::PermissionDenied {
+                operation,
+                resource,
+            }) => {
+                assert_eq!(operation, "delete_object");
+                assert_eq!(resource, "reports/old.txt");
+            }
+            other => panic!("expected PermissionDenied, got {:?}", other),
+        }
+        assert!(matches!(
+            client.delete_object("reports", "hooked.txt").await,
+            Err(Error::ServerError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_object_if_exists() {
+        let mut server = Server::new_async().await;
+        let _exists = server
+            .mock("GET", "/buckets/test-bucket/objects/present")
+            .with_status(200)
+            .with_header("etag", "abc123")
+            .with_body("Hello")
+            .create_async()
+            .await;
+        let _missing = server
+            .mock("GET", "/buckets/test-bucket/objects/absent")
+            .with_status(404)
+            .with_body(r#"{"error":"Object not found"}"#)
+            .create_async()
+            .await;
+        let _failing = server
+            .mock("GET", "/buckets/test-bucket/objects/broken")
+            .with_status(500)
+            .with_body(r#"{"error":"Backend error"}"#)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        let obj = client
+            .get_object_if_exists("test-bucket", "present")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(obj.data, Bytes::from("Hello"));
+        assert_eq!(obj.metadata.etag, "abc123");
+
+        assert!(client
+            .get_object_if_exists("test-bucket", "absent")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            client.get_object_if_exists("test-bucket", "broken").await,
+            Err(Error::ServerError(_))
+        ));
     }
 
     #[tokio::test]
@@ -606,6 +2017,10 @@ mod tests {
         assert_eq!(obj.metadata.key, "test-key");
         assert_eq!(obj.metadata.size, 13);
         assert_eq!(obj.metadata.etag, "abc123");
+        assert_eq!(
+            obj.metadata.last_modified,
+            "2024-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
         assert_eq!(obj.data, Bytes::from("Hello, World!"));
     }
 
@@ -635,7 +2050,8 @@ mod tests {
             .with_header("content-type", "text/plain")
             .with_header("content-length", "13")
             .with_header("etag", "abc123")
-            .with_header("last-modified", "2024-01-01T00:00:00Z")
+            // Older servers sent RFC 2822 dates
+            .with_header("last-modified", "Mon, 1 Jan 2024 00:00:00 +0000")
             .create_async()
             .await;
 
@@ -645,6 +2061,14 @@ mod tests {
         assert_eq!(obj.key, "test-key");
         assert_eq!(obj.size, 13);
         assert_eq!(obj.etag, "abc123");
+        assert_eq!(
+            obj.last_modified,
+            "2024-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(
+            obj.last_modified_rfc2822(),
+            "Mon, 1 Jan 2024 00:00:00 +0000"
+        );
     }
 
     #[tokio::test]
@@ -662,6 +2086,37 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_url_encode_key() {
+        assert_eq!(url_encode_key("a/b/c.txt"), "a/b/c.txt");
+        assert_eq!(url_encode_key("hello world.txt"), "hello%20world.txt");
+        assert_eq!(url_encode_key("a+b#c?d%e"), "a%2Bb%23c%3Fd%25e");
+        assert_eq!(url_encode_key("~x_y-z"), "~x_y-z");
+        assert_eq!(url_encode_key("café/ü.txt"), "caf%C3%A9/%C3%BC.txt");
+    }
+
+    #[tokio::test]
+    async fn test_object_key_is_percent_encoded_in_path() {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock(
+                "DELETE",
+                "/buckets/test-bucket/objects/dir/100%25%20done%3F%23.txt",
+            )
+            .with_status(204)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        client
+            .delete_object("test-bucket", "dir/100% done?#.txt")
+            .await
+            .unwrap();
+
+        m.assert_async().await;
+    }
+
     #[tokio::test]
     async fn test_list_objects() {
         let mut server = Server::new_async().await;
@@ -686,6 +2141,31 @@ mod tests {
         assert_eq!(objects.len(), 2);
         assert_eq!(objects[0].key, "prefix/obj1");
         assert_eq!(objects[1].key, "prefix/obj2");
+        assert!(objects[0].last_modified < objects[1].last_modified);
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_with_count() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/buckets/test-bucket/objects")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("max_keys".into(), "1".into()),
+                mockito::Matcher::UrlEncoded("include_count".into(), "true".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"objects":[{"key":"a.txt","size":1,"content_type":null,"etag":"e","last_modified":"2024-01-01T00:00:00Z","metadata":{}}],"total_count":42}"#)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        let listing = client
+            .list_objects_with_count("test-bucket", &ListObjectsOptions::new(None, Some(1)))
+            .await
+            .unwrap();
+        assert_eq!(listing.objects.len(), 1);
+        assert_eq!(listing.total_count, Some(42));
     }
 
     #[tokio::test]
@@ -693,6 +2173,9 @@ mod tests {
         let mut server = Server::new_async().await;
         let _m = server
             .mock("GET", "/buckets/test-bucket/objects")
+            .match_query(mockito::Matcher::Regex(
+                "^max_keys=1000&sort_by=key$".into(),
+            ))
             .with_status(200)
             .with_header("content-type", "application/json")
             .with_body(r#"{"objects":[]}"#)
@@ -708,6 +2191,97 @@ mod tests {
         assert_eq!(objects.len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_list_objects_stream_pages_in_order() {
+        let mut server = Server::new_async().await;
+        let key = |i: usize| format!("objects/{:03}", i);
+        let page_body = |keys: std::ops::Range<usize>| {
+            let objects: Vec<_> = keys
+                .map(|i| {
+                    serde_json::json!({
+                        "key": key(i),
+                        "size": i,
+                        "etag": format!("etag{}", i),
+                        "last_modified": "2024-01-01T00:00:00Z",
+                        "metadata": {},
+                    })
+                })
+                .collect();
+            serde_json::json!({ "objects": objects }).to_string()
+        };
+
+        let mut mocks = vec![
+            server
+                .mock("GET", "/buckets/test-bucket/objects")
+                .match_query(mockito::Matcher::Regex("^max_keys=25&sort_by=key$".into()))
+                .with_status(200)
+                .with_body(page_body(0..25))
+                .create_async()
+                .await,
+        ];
+        // Ten full pages, then an empty one after the last key
+        for start in (25..=250).step_by(25) {
+            let mock = server
+                .mock("GET", "/buckets/test-bucket/objects")
+                .match_query(mockito::Matcher::AllOf(vec![
+                    mockito::Matcher::UrlEncoded("max_keys".into(), "25".into()),
+                    mockito::Matcher::UrlEncoded("start_after".into(), key(start - 1)),
+                ]))
+                .with_status(200)
+                .with_body(page_body(start..(start + 25).min(250)))
+                .expect(1)
+                .create_async()
+                .await;
+            mocks.push(mock);
+        }
+
+        let client = ObjectStoreClient::new(server.url());
+        let objects: Vec<ObjectMetadata> = client
+            .list_objects_stream("test-bucket", None, Some(25))
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+
+        let keys: Vec<String> = objects.into_iter().map(|obj| obj.key).collect();
+        assert_eq!(keys, (0..250).map(key).collect::<Vec<_>>());
+        for mock in &mocks {
+            mock.assert_async().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_for_each_object() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/buckets/test-bucket/objects")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("prefix".into(), "logs/".into()),
+                mockito::Matcher::UrlEncoded("sort_by".into(), "key".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"objects":[{"key":"logs/a","size":1,"etag":"etag1","last_modified":"2024-01-01T00:00:00Z","metadata":{}},{"key":"logs/b","size":2,"etag":"etag2","last_modified":"2024-01-02T00:00:00Z","metadata":{}}]}"#)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        let mut visited = Vec::new();
+        client
+            .for_each_object("test-bucket", Some("logs/"), |obj| {
+                visited.push(obj.key);
+                async {}
+            })
+            .await
+            .unwrap();
+        assert_eq!(visited, ["logs/a", "logs/b"]);
+
+        let result = client
+            .for_each_object("missing-bucket", None, |_| async {})
+            .await;
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_get_public_url() {
         let mut server = Server::new_async().await;
@@ -782,4 +2356,657 @@ mod tests {
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), Error::NotFound(_)));
     }
+
+    #[tokio::test]
+    async fn test_list_objects_ndjson() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/buckets/test-bucket/objects")
+            .match_header("accept", "application/x-ndjson")
+            .with_status(200)
+            .with_header("content-type", "application/x-ndjson")
+            .with_body(concat!(
+                r#"{"key":"obj1","size":100,"etag":"etag1","last_modified":"2024-01-01T00:00:00Z","metadata":{}}"#,
+                "\n",
+                r#"{"key":"obj2","size":200,"etag":"etag2","last_modified":"2024-01-02T00:00:00Z","metadata":{}}"#,
+                "\n"
+            ))
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        let objects: Vec<_> = client
+            .list_objects_ndjson("test-bucket", None, None)
+            .collect()
+            .await;
+
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].as_ref().unwrap().key, "obj1");
+        assert_eq!(objects[1].as_ref().unwrap().size, 200);
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_ndjson_not_found() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/buckets/test-bucket/objects")
+            .with_status(404)
+            .with_body("Bucket not found")
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        let objects: Vec<_> = client
+            .list_objects_ndjson("test-bucket", None, None)
+            .collect()
+            .await;
+
+        assert_eq!(objects.len(), 1);
+        assert!(matches!(objects[0], Err(Error::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_sorted() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/buckets/test-bucket/objects")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("sort_by".into(), "last_modified".into()),
+                mockito::Matcher::UrlEncoded("sort_order".into(), "desc".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"objects":[{"key":"new","size":1,"etag":"e2","last_modified":"2024-01-02T00:00:00Z","metadata":{}},{"key":"old","size":1,"etag":"e1","last_modified":"2024-01-01T00:00:00Z","metadata":{}}]}"#)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        let options = ListObjectsOptions {
+            sort_by: Some(SortField::LastModified),
+            sort_order: Some(SortOrder::Desc),
+            ..Default::default()
+        };
+        let objects = client
+            .list_objects_with_options("test-bucket", &options)
+            .await
+            .unwrap();
+
+        assert_eq!(objects[0].key, "new");
+        assert_eq!(objects[1].key, "old");
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_modified_range() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/buckets/test-bucket/objects")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded(
+                    "modified_after".into(),
+                    "2024-01-01T00:00:00Z".into(),
+                ),
+                mockito::Matcher::UrlEncoded(
+                    "modified_before".into(),
+                    "2024-02-01T12:30:00.500Z".into(),
+                ),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"objects":[{"key":"jan","size":1,"etag":"e1","last_modified":"2024-01-15T00:00:00Z","metadata":{}}]}"#)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        let options = ListObjectsOptions {
+            modified_after: Some("2024-01-01T00:00:00Z".parse().unwrap()),
+            modified_before: Some("2024-02-01T12:30:00.5Z".parse().unwrap()),
+            ..Default::default()
+        };
+        let objects = client
+            .list_objects_with_options("test-bucket", &options)
+            .await
+            .unwrap();
+
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].key, "jan");
+    }
+
+    #[tokio::test]
+    async fn test_download_inventory_csv() {
+        use tokio::io::AsyncReadExt;
+
+        let mut server = Server::new_async().await;
+        let csv = "key,size,etag,content_type,last_modified,metadata\na.txt,4,e1,,2024-01-01T00:00:00+00:00,{}\n";
+        let _m = server
+            .mock("GET", "/buckets/test-bucket/inventory")
+            .match_query(mockito::Matcher::UrlEncoded("format".into(), "csv".into()))
+            .with_status(200)
+            .with_header("content-type", "text/csv")
+            .with_body(csv)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        let mut reader = client
+            .download_inventory("test-bucket", InventoryFormat::Csv)
+            .await
+            .unwrap();
+        let mut body = String::new();
+        reader.read_to_string(&mut body).await.unwrap();
+
+        assert_eq!(body, csv);
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_filtered() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/buckets/test-bucket/objects")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "metadata_filter".into(),
+                "env=prod".into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"objects":[{"key":"a","size":1,"etag":"e1","last_modified":"2024-01-01T00:00:00Z","metadata":{"env":"prod"}}]}"#)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        let filter = HashMap::from([("env".to_string(), "prod".to_string())]);
+        let objects = client
+            .list_objects_filtered("test-bucket", None, filter)
+            .await
+            .unwrap();
+
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].metadata["env"], "prod");
+    }
+
+    #[tokio::test]
+    async fn test_last_request_id() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/ping")
+            .with_status(200)
+            .with_header("x-request-id", "req-123")
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        assert_eq!(client.last_request_id(), None);
+
+        client.ping().await.unwrap();
+        assert_eq!(client.last_request_id().as_deref(), Some("req-123"));
+    }
+
+    #[tokio::test]
+    async fn test_request_and_response_hooks() {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/ping")
+            .match_header("x-custom", "tracing-42")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let statuses = Arc::new(Mutex::new(Vec::new()));
+        let seen = statuses.clone();
+        let client = ObjectStoreClient::builder(server.url())
+            .before_request(Arc::new(|request| request.header("X-Custom", "tracing-42")))
+            .after_response(Arc::new(move |response| {
+                seen.lock().unwrap().push(response.status());
+            }))
+            .build();
+
+        client.ping().await.unwrap();
+        m.assert_async().await;
+        assert_eq!(*statuses.lock().unwrap(), vec![StatusCode::OK]);
+    }
+
+    #[tokio::test]
+    async fn test_download_to_file_streams_body() {
+        use sha2::{Digest, Sha256};
+
+        // 10 MB body
+        let body: Vec<u8> = (0..10 * 1024 * 1024u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+        let etag = format!("{:x}", Sha256::digest(&body));
+
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/buckets/test-bucket/objects/large.bin")
+            .with_status(200)
+            .with_header("content-type", "application/octet-stream")
+            .with_header("etag", &etag)
+            .with_header("x-object-meta-source", "test")
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("large.bin");
+        let client = ObjectStoreClient::new(server.url());
+        let metadata = client
+            .download_to_file("test-bucket", "large.bin", &path)
+            .await
+            .unwrap();
+
+        assert_eq!(metadata.etag, etag);
+        assert_eq!(metadata.size, 10 * 1024 * 1024);
+        assert_eq!(metadata.metadata["source"], "test");
+        let written = tokio::fs::read(&path).await.unwrap();
+        assert_eq!(format!("{:x}", Sha256::digest(&written)), metadata.etag);
+    }
+
+    #[tokio::test]
+    async fn test_get_object_stream_not_found() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("GET", "/buckets/test-bucket/objects/missing")
+            .with_status(404)
+            .with_body(r#"{"error":"Object not found"}"#)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        assert!(matches!(
+            client.get_object_stream("test-bucket", "missing").await,
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    /// Answers an upload like the service does: size and SHA-256 ETag of the
+    /// bytes that actually arrived.
+    fn stored_metadata(request: &mockito::Request) -> Vec<u8> {
+        use sha2::{Digest, Sha256};
+
+        let body = request.body().unwrap();
+        serde_json::to_vec(&serde_json::json!({
+            "key": "large.bin",
+            "size": body.len(),
+            "content_type": "application/octet-stream",
+            "etag": format!("{:x}", Sha256::digest(body)),
+            "last_modified": "2024-01-15T10:30:00Z",
+            "metadata": {}
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_validate_content_type_before_sending() {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("PUT", "/buckets/test-bucket/objects/doc.json")
+            .match_header("content-type", "application/json; charset=utf-8")
+            .with_status(200)
+            .with_body_from_request(stored_metadata)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::builder(server.url())
+            .validate_content_type(true)
+            .build();
+        for invalid in ["foo", "", "not/valid/mime"] {
+            let result = client
+                .put_object("test-bucket", "doc.json", "{}", Some(invalid), None)
+                .await;
+            assert!(matches!(result, Err(Error::BadRequest(_))), "{:?}", invalid);
+        }
+        client
+            .put_object(
+                "test-bucket",
+                "doc.json",
+                "{}",
+                Some("application/json; charset=utf-8"),
+                None,
+            )
+            .await
+            .unwrap();
+        m.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_upload_from_file() {
+        use sha2::{Digest, Sha256};
+
+        let size = 50 * 1024 * 1024;
+        let data: Vec<u8> = (0..size as u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("large.bin");
+        tokio::fs::write(&path, &data).await.unwrap();
+
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("PUT", "/buckets/test-bucket/objects/large.bin")
+            .match_header("content-length", size.to_string().as_str())
+            .match_header("content-type", "application/octet-stream")
+            .with_status(200)
+            .with_body_from_request(stored_metadata)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        let metadata = client
+            .upload_from_file(
+                "test-bucket",
+                "large.bin",
+                &path,
+                Some("application/octet-stream"),
+                None,
+            )
+            .await
+            .unwrap();
+
+        m.assert_async().await;
+        assert_eq!(metadata.size, size);
+        assert_eq!(metadata.etag, format!("{:x}", Sha256::digest(&data)));
+    }
+
+    #[tokio::test]
+    async fn test_put_object_stream() {
+        let mut server = Server::new_async().await;
+        let _m = server
+            .mock("PUT", "/buckets/test-bucket/objects/large.bin")
+            .match_header("x-object-meta-source", "stream")
+            .with_status(200)
+            .with_body_from_request(stored_metadata)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url());
+        let reader = std::io::Cursor::new(b"streamed body".to_vec());
+        let metadata = client
+            .put_object_stream(
+                "test-bucket",
+                "large.bin",
+                reader,
+                None,
+                Some(HashMap::from([(
+                    "source".to_string(),
+                    "stream".to_string(),
+                )])),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(metadata.size, 13);
+    }
+
+    #[tokio::test]
+    async fn test_download_with_progress() {
+        let mut server = Server::new_async().await;
+        let _sized = server
+            .mock("GET", "/buckets/test-bucket/objects/sized.bin")
+            .with_status(200)
+            .with_body(vec![7u8; 100_000])
+            .create_async()
+            .await;
+        let _chunked = server
+            .mock("GET", "/buckets/test-bucket/objects/chunked.bin")
+            .with_status(200)
+            .with_chunked_body(|w| {
+                for i in 0..5u8 {
+                    w.write_all(&[i; 7_000])?;
+                }
+                Ok(())
+            })
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url()).with_progress_buffer_size(10_000);
+
+        let reports = std::cell::RefCell::new(Vec::new());
+        let object = client
+            .download_with_progress("test-bucket", "sized.bin", |p| reports.borrow_mut().push(p))
+            .await
+            .unwrap();
+        assert_eq!(object.data.len(), 100_000);
+        let reports = reports.into_inner();
+        assert_eq!(reports.len(), 10);
+        for (i, report) in reports.iter().enumerate() {
+            assert_eq!(report.bytes_received, (i as u64 + 1) * 10_000);
+            assert_eq!(report.total_bytes, Some(100_000));
+        }
+        assert_eq!(reports.last().unwrap().fraction, Some(1.0));
+
+        // Without a Content-Length only the byte count is known, and the
+        // final partial buffer is still reported
+        let reports = std::cell::RefCell::new(Vec::new());
+        let object = client
+            .download_with_progress("test-bucket", "chunked.bin", |p| {
+                reports.borrow_mut().push(p)
+            })
+            .await
+            .unwrap();
+        assert_eq!(object.data.len(), 35_000);
+        let received: Vec<u64> = reports.borrow().iter().map(|p| p.bytes_received).collect();
+        assert_eq!(received, [10_000, 20_000, 30_000, 35_000]);
+        assert!(reports
+            .borrow()
+            .iter()
+            .all(|p| p.total_bytes.is_none() && p.fraction.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_upload_with_progress() {
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("PUT", "/buckets/test-bucket/objects/large.bin")
+            .match_header("content-length", "25000")
+            .with_status(200)
+            .with_body_from_request(stored_metadata)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::new(server.url()).with_progress_buffer_size(10_000);
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let metadata = client
+            .upload_with_progress(
+                "test-bucket",
+                "large.bin",
+                vec![1u8; 25_000],
+                Some("application/octet-stream"),
+                None,
+                move |p| sink.lock().unwrap().push(p),
+            )
+            .await
+            .unwrap();
+
+        m.assert_async().await;
+        assert_eq!(metadata.size, 25_000);
+        let reports = reports.lock().unwrap();
+        let sent: Vec<u64> = reports.iter().map(|p| p.bytes_sent).collect();
+        assert_eq!(sent, [10_000, 20_000, 25_000]);
+        assert!(reports.iter().all(|p| p.total_bytes == 25_000));
+        assert_eq!(reports.last().unwrap().fraction, 1.0);
+    }
+
+    const BUCKETS_BODY: &str =
+        r#"{"buckets":[{"id":"bucket-1","name":"b1","created_at":"2024-01-01T00:00:00Z"}]}"#;
+
+    async fn mock_buckets(server: &mut Server, status: usize, hits: usize) -> mockito::Mock {
+        server
+            .mock("GET", "/buckets")
+            .with_status(status)
+            .with_header("content-type", "application/json")
+            .with_body(BUCKETS_BODY)
+            .expect(hits)
+            .create_async()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_fallover_on_server_error() {
+        let mut primary = Server::new_async().await;
+        let mut secondary = Server::new_async().await;
+        let failing = mock_buckets(&mut primary, 503, 1).await;
+        let healthy = mock_buckets(&mut secondary, 200, 2).await;
+
+        let client = ObjectStoreClient::builder(primary.url())
+            .fallback_urls(vec![secondary.url()])
+            .build();
+        assert_eq!(client.last_used_url(), primary.url());
+
+        let buckets = client.list_buckets().await.unwrap();
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(client.last_used_url(), secondary.url());
+
+        // The failing server is passed over while it is unhealthy
+        client.list_buckets().await.unwrap();
+        failing.assert_async().await;
+        healthy.assert_async().await;
+
+        let health = client.url_health();
+        assert_eq!((health[0].successes, health[0].failures), (0, 1));
+        assert_eq!((health[1].successes, health[1].failures), (2, 0));
+        assert_eq!(health[1].success_rate(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_fallover_on_connection_failure() {
+        // A port nothing listens on
+        let unreachable = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        let mut secondary = Server::new_async().await;
+        let healthy = mock_buckets(&mut secondary, 200, 1).await;
+
+        let client = ObjectStoreClient::builder(unreachable)
+            .fallback_urls(vec![secondary.url()])
+            .build();
+        client.list_buckets().await.unwrap();
+        assert_eq!(client.last_used_url(), secondary.url());
+        healthy.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_no_fallover_on_client_error() {
+        let mut primary = Server::new_async().await;
+        let mut secondary = Server::new_async().await;
+        let _missing = primary
+            .mock("GET", "/buckets/missing")
+            .with_status(404)
+            .create_async()
+            .await;
+        let untouched = secondary
+            .mock("GET", mockito::Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = ObjectStoreClient::builder(primary.url())
+            .fallback_urls(vec![secondary.url()])
+            .build();
+        let result = client.get_bucket("missing").await;
+        assert!(matches!(result, Err(Error::NotFound(_))));
+        assert_eq!(client.last_used_url(), primary.url());
+        untouched.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_fallover_round_robin() {
+        let mut first = Server::new_async().await;
+        let mut second = Server::new_async().await;
+        let first_mock = mock_buckets(&mut first, 200, 2).await;
+        let second_mock = mock_buckets(&mut second, 200, 2).await;
+
+        let client = ObjectStoreClient::builder(first.url())
+            .fallback_urls(vec![second.url()])
+            .fallover_strategy(FalloverStrategy::RoundRobin)
+            .build();
+        for _ in 0..4 {
+            client.list_buckets().await.unwrap();
+        }
+        first_mock.assert_async().await;
+        second_mock.assert_async().await;
+    }
+
+    /// Serves `GET /buckets` over TLS with a self-signed certificate for
+    /// `localhost`, returning the certificate's PEM and the server URL.
+    async fn self_signed_server() -> (String, String) {
+        use tokio::io::AsyncReadExt;
+
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert_pem = certified.cert.pem();
+        let identity = tokio_native_tls::native_tls::Identity::from_pkcs8(
+            cert_pem.as_bytes(),
+            certified.key_pair.serialize_pem().as_bytes(),
+        )
+        .unwrap();
+        let acceptor = tokio_native_tls::TlsAcceptor::from(
+            tokio_native_tls::native_tls::TlsAcceptor::new(identity).unwrap(),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                // Clients that reject the certificate abort the handshake
+                let Ok(mut tls) = acceptor.accept(socket).await else {
+                    continue;
+                };
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match tls.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let body = r#"{"buckets":[]}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = tls.write_all(response.as_bytes()).await;
+                let _ = tls.shutdown().await;
+            }
+        });
+
+        (cert_pem, format!("https://localhost:{}", port))
+    }
+
+    #[tokio::test]
+    async fn test_root_certificate_trusts_private_ca() {
+        let (cert_pem, url) = self_signed_server().await;
+
+        let untrusted = ObjectStoreClient::new(&url);
+        assert!(matches!(
+            untrusted.list_buckets().await,
+            Err(Error::Http(_))
+        ));
+
+        let trusted = ObjectStoreClient::builder(&url)
+            .with_certificate_pem(cert_pem.as_bytes())
+            .unwrap()
+            .build();
+        assert!(trusted.list_buckets().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_danger_accept_invalid_certs() {
+        let (_, url) = self_signed_server().await;
+
+        let client = ObjectStoreClient::builder(&url)
+            .danger_accept_invalid_certs(true)
+            .try_build()
+            .unwrap();
+        assert!(client.list_buckets().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_with_certificate_pem_rejects_invalid_pem() {
+        let result =
+            ObjectStoreClient::builder("https://localhost").with_certificate_pem(b"not a cert");
+        assert!(matches!(result, Err(BuildError::InvalidCertificate(_))));
+    }
 }
@@ -1,5 +1,7 @@
 use async_trait::async_trait;
 use azure_core::auth::Secret;
+use azure_core::error::ErrorKind;
+use azure_core::StatusCode;
 use azure_storage::prelude::*;
 use azure_storage_blobs::prelude::*;
 use bytes::Bytes;
@@ -9,15 +11,30 @@ use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use tracing::{debug, info, warn};
 
-use crate::backend::{Backend, ByteStream, ObjectData, ObjectMetadata, PublicUrlPurpose};
+use crate::backend::{
+    normalize_etag, Backend, ByteStream, ObjectData, ObjectMetadata, PublicUrlPurpose,
+    STREAMING_THRESHOLD_BYTES,
+};
 use crate::error::{BackendError, BackendResult};
 
+/// Largest page Azure returns from a single List Blobs call.
+const AZURE_MAX_PAGE_SIZE: usize = 5000;
+
+/// Size of each block staged by a block list upload.
+const AZURE_BLOCK_BYTES: usize = 4 * 1024 * 1024;
+
+/// Largest body Azure accepts in a single Put Blob request.
+const AZURE_MAX_PUT_BLOB_BYTES: u64 = 5000 * 1024 * 1024;
+
+/// Largest body Azure accepts in a single Put Block request.
+const AZURE_MAX_BLOCK_BYTES: u64 = 4000 * 1024 * 1024;
+
 pub struct AzureBackend {
     client: ContainerClient,
     container_name: String,
     account: String,
-    #[allow(dead_code)]
-    access_key: String,
+    /// Signs presigned URLs; absent when authenticating with Managed Identity.
+    access_key: Option<String>,
 }
 
 impl AzureBackend {
@@ -37,7 +54,53 @@ impl AzureBackend {
             client,
             container_name,
             account,
-            access_key,
+            access_key: Some(access_key),
+        })
+    }
+
+    /// Authenticates with [`azure_identity::DefaultAzureCredential`], which
+    /// picks up the Managed Identity of an Azure VM or AKS pod, or environment
+    /// and Azure CLI credentials elsewhere, so no key has to be configured.
+    ///
+    /// Without an account key the backend cannot sign presigned URLs.
+    ///
+    /// ```no_run
+    /// use object_store_backends::azure::AzureBackend;
+    ///
+    /// # fn main() -> object_store_backends::BackendResult<()> {
+    /// let backend =
+    ///     AzureBackend::new_with_managed_identity("myaccount".to_string(), "mycontainer".to_string())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "azure-managed-identity")]
+    pub fn new_with_managed_identity(
+        account: String,
+        container_name: String,
+    ) -> BackendResult<Self> {
+        let credential = azure_identity::DefaultAzureCredential::create(Default::default())
+            .map_err(|e| {
+                BackendError::Configuration(format!(
+                    "Failed to set up Azure Managed Identity credentials: {}",
+                    e
+                ))
+            })?;
+        let storage_credentials =
+            StorageCredentials::token_credential(std::sync::Arc::new(credential));
+
+        let client = ClientBuilder::new(account.clone(), storage_credentials)
+            .container_client(&container_name);
+
+        info!(
+            "Initialized Azure Blob Storage backend with container: {} using Managed Identity",
+            container_name
+        );
+
+        Ok(Self {
+            client,
+            container_name,
+            account,
+            access_key: None,
         })
     }
 
@@ -77,7 +140,7 @@ impl AzureBackend {
             client,
             container_name,
             account: account_name,
-            access_key,
+            access_key: Some(access_key),
         })
     }
 
@@ -105,7 +168,7 @@ impl AzureBackend {
             size,
             content_type,
             last_modified: last_modified_utc,
-            etag: etag.unwrap_or_else(|| {
+            etag: etag.map(|e| normalize_etag(&e)).unwrap_or_else(|| {
                 use sha2::{Digest, Sha256};
                 let mut hasher = Sha256::new();
                 hasher.update(name.as_bytes());
@@ -116,8 +179,110 @@ impl AzureBackend {
     }
 }
 
+impl AzureBackend {
+    /// Stages the body as blocks of [`AZURE_BLOCK_BYTES`] while reading the rest
+    /// of the stream, then commits them with Put Block List. Holds at most one
+    /// block plus the latest stream item in memory beyond what was buffered.
+    /// Returns the number of bytes uploaded.
+    async fn upload_blocks(
+        blob_client: &BlobClient,
+        mut buffer: Vec<u8>,
+        mut stream: ByteStream,
+        hasher: &mut Sha256,
+        content_type: Option<String>,
+        metadata: azure_core::request_options::Metadata,
+    ) -> BackendResult<u64> {
+        let key = blob_client.blob_name();
+        let mut block_list = BlockList::default();
+        let mut size = 0u64;
+        let mut exhausted = false;
+
+        loop {
+            while !exhausted && buffer.len() < AZURE_BLOCK_BYTES {
+                match stream.next().await {
+                    Some(chunk) => {
+                        let chunk = chunk.map_err(|e| BackendError::BodyStream(e.to_string()))?;
+                        hasher.update(&chunk);
+                        buffer.extend_from_slice(&chunk);
+                    }
+                    None => exhausted = true,
+                }
+            }
+
+            if buffer.is_empty() {
+                break;
+            }
+
+            let rest = buffer.split_off(buffer.len().min(AZURE_BLOCK_BYTES));
+            let block = std::mem::replace(&mut buffer, rest);
+            // Block IDs must all have the same length within a blob
+            let index = block_list.blocks.len();
+            let block_id = BlockId::new(format!("{:08}", index));
+            let block_len = block.len() as u64;
+            size += block_len;
+            blob_client
+                .put_block(block_id.clone(), Bytes::from(block))
+                .await
+                .map_err(|e| {
+                    too_large(&e, block_len, AZURE_MAX_BLOCK_BYTES).unwrap_or_else(|| {
+                        upload_failed(key, format!("block {} failed: {}", index, e))
+                    })
+                })?;
+            block_list
+                .blocks
+                .push(BlobBlockType::new_uncommitted(block_id));
+        }
+
+        let blocks = block_list.blocks.len();
+        let mut request = blob_client.put_block_list(block_list).metadata(metadata);
+        if let Some(ct) = content_type {
+            request = request.content_type(ct);
+        }
+        request.await.map_err(|e| {
+            upload_failed(key, format!("failed to commit {} blocks: {}", blocks, e))
+        })?;
+
+        Ok(size)
+    }
+}
+
+fn upload_failed(key: &str, detail: String) -> BackendError {
+    BackendError::Provider(format!("Failed to upload blob '{}': {}", key, detail))
+}
+
+/// Azure answers `404 Not Found` for a missing blob. Get Blob sends the
+/// `BlobNotFound` error code too, but Get Blob Properties is a HEAD request and
+/// only has the status.
+fn is_not_found(e: &azure_core::Error) -> bool {
+    matches!(
+        e.kind(),
+        ErrorKind::HttpResponse {
+            status: StatusCode::NotFound,
+            ..
+        }
+    )
+}
+
+/// Azure answers `413 Request Entity Too Large`, with the `RequestBodyTooLarge`
+/// error code, to bodies over the operation's limit.
+fn too_large(e: &azure_core::Error, size: u64, max_size: u64) -> Option<BackendError> {
+    match e.kind() {
+        ErrorKind::HttpResponse { status, error_code }
+            if *status == StatusCode::PayloadTooLarge
+                || error_code.as_deref() == Some("RequestBodyTooLarge") =>
+        {
+            Some(BackendError::EntityTooLarge { size, max_size })
+        }
+        _ => None,
+    }
+}
+
 #[async_trait]
 impl Backend for AzureBackend {
+    fn backend_type(&self) -> &'static str {
+        "azure"
+    }
+
     async fn init(&self) -> BackendResult<()> {
         match self.client.get_properties().await {
             Ok(_) => {
@@ -145,52 +310,69 @@ impl Backend for AzureBackend {
         custom_metadata: HashMap<String, String>,
     ) -> BackendResult<ObjectMetadata> {
         let blob_client = self.client.blob_client(key);
+        let threshold = usize::try_from(STREAMING_THRESHOLD_BYTES).unwrap_or(usize::MAX);
 
-        // Collect stream into bytes while computing hash
+        // Buffer up to the threshold; larger bodies are staged as blocks while
+        // the rest of the stream is read.
         let mut hasher = Sha256::new();
         let mut data = Vec::new();
-
-        while let Some(chunk_result) = stream.next().await {
-            let chunk = chunk_result
-                .map_err(|e| BackendError::Provider(format!("Failed to read stream: {}", e)))?;
-
-            hasher.update(&chunk);
-            data.extend_from_slice(&chunk);
-        }
-
-        let size = data.len();
-        let etag = hex::encode(hasher.finalize());
-
-        let mut request = blob_client.put_block_blob(data);
-
-        if let Some(ct) = content_type.as_ref() {
-            request = request.content_type(ct.clone());
+        let mut exhausted = false;
+        while data.len() <= threshold {
+            match stream.next().await {
+                Some(chunk) => {
+                    let chunk = chunk.map_err(|e| BackendError::BodyStream(e.to_string()))?;
+                    hasher.update(&chunk);
+                    data.extend_from_slice(&chunk);
+                }
+                None => {
+                    exhausted = true;
+                    break;
+                }
+            }
         }
 
         let mut metadata_obj = azure_core::request_options::Metadata::new();
         for (k, v) in custom_metadata.iter() {
             metadata_obj.insert(k.clone(), v.clone());
         }
-        request = request.metadata(metadata_obj);
 
-        match request.await {
-            Ok(_) => {
+        let result = if exhausted {
+            let size = data.len() as u64;
+            let mut request = blob_client.put_block_blob(data).metadata(metadata_obj);
+            if let Some(ct) = content_type.as_ref() {
+                request = request.content_type(ct.clone());
+            }
+            request.await.map(|_| size).map_err(|e| {
+                too_large(&e, size, AZURE_MAX_PUT_BLOB_BYTES)
+                    .unwrap_or_else(|| upload_failed(key, e.to_string()))
+            })
+        } else {
+            Self::upload_blocks(
+                &blob_client,
+                data,
+                stream,
+                &mut hasher,
+                content_type.clone(),
+                metadata_obj,
+            )
+            .await
+        };
+
+        match result {
+            Ok(size) => {
                 debug!("Uploaded blob to Azure: {} ({} bytes)", key, size);
                 Ok(ObjectMetadata {
                     key: key.to_string(),
-                    size: size as u64,
+                    size,
                     content_type,
                     last_modified: Utc::now(),
-                    etag,
+                    etag: hex::encode(hasher.finalize()),
                     custom_metadata,
                 })
             }
             Err(e) => {
-                warn!("Failed to upload blob to Azure: {}: {:?}", key, e);
-                Err(BackendError::Provider(format!(
-                    "Failed to upload blob '{}': {}",
-                    key, e
-                )))
+                warn!("Failed to upload blob to Azure: {}: {}", key, e);
+                Err(e)
             }
         }
     }
@@ -216,18 +398,10 @@ impl Backend for AzureBackend {
                     },
                 };
 
-                // Convert data to stream
-                let stream: ByteStream =
-                    Box::pin(futures::stream::once(async move { Ok(Bytes::from(data)) }));
-
-                Ok(ObjectData { metadata, stream })
+                Ok(ObjectData::from_bytes(metadata, Bytes::from(data)))
             }
             Err(e) => {
-                let error_msg = format!("{:?}", e);
-                if error_msg.contains("404")
-                    || error_msg.contains("NotFound")
-                    || error_msg.contains("BlobNotFound")
-                {
+                if is_not_found(&e) {
                     Err(BackendError::NotFound(key.to_string()))
                 } else {
                     warn!("Failed to get blob from Azure: {}: {:?}", key, e);
@@ -240,6 +414,31 @@ impl Backend for AzureBackend {
         }
     }
 
+    async fn get_object_range(&self, key: &str, start: u64, len: u64) -> BackendResult<ObjectData> {
+        let metadata = self.head_object(key).await?;
+        // Azure refuses a range that starts past the end
+        if len == 0 || start >= metadata.size {
+            return Ok(ObjectData::from_bytes(metadata, Bytes::new()));
+        }
+        let end = start.saturating_add(len).min(metadata.size);
+
+        debug!(
+            "Streaming bytes {}..{} of blob {} from Azure",
+            start, end, key
+        );
+        let stream: ByteStream = Box::pin(
+            self.client
+                .blob_client(key)
+                .get()
+                .range(start..end)
+                .into_stream()
+                .then(|response| async move { response?.data.collect().await })
+                .map(|result| result.map_err(std::io::Error::other)),
+        );
+
+        Ok(ObjectData { metadata, stream })
+    }
+
     async fn head_object(&self, key: &str) -> BackendResult<ObjectMetadata> {
         let blob_client = self.client.blob_client(key);
 
@@ -248,7 +447,7 @@ impl Backend for AzureBackend {
                 let metadata_map: HashMap<String, String> =
                     properties.blob.metadata.clone().unwrap_or_default();
 
-                let etag_str = format!("{:?}", properties.blob.properties.etag);
+                let etag_str = properties.blob.properties.etag.to_string();
 
                 Ok(Self::azure_metadata_to_object_metadata(
                     key.to_string(),
@@ -260,11 +459,7 @@ impl Backend for AzureBackend {
                 ))
             }
             Err(e) => {
-                let error_msg = format!("{:?}", e);
-                if error_msg.contains("404")
-                    || error_msg.contains("NotFound")
-                    || error_msg.contains("BlobNotFound")
-                {
+                if is_not_found(&e) {
                     Err(BackendError::NotFound(key.to_string()))
                 } else {
                     warn!("Failed to get blob properties from Azure: {}: {:?}", key, e);
@@ -277,6 +472,13 @@ impl Backend for AzureBackend {
         }
     }
 
+    async fn object_exists(&self, key: &str) -> BackendResult<bool> {
+        self.client.blob_client(key).exists().await.map_err(|e| {
+            warn!("Failed to check blob existence in Azure: {}: {:?}", key, e);
+            BackendError::Provider(format!("Failed to check blob '{}': {}", key, e))
+        })
+    }
+
     async fn delete_object(&self, key: &str) -> BackendResult<()> {
         let blob_client = self.client.blob_client(key);
 
@@ -306,65 +508,71 @@ impl Backend for AzureBackend {
             request = request.prefix(p.to_string());
         }
 
+        // Azure caps each page at 5000 blobs whatever max_results asks for
         if let Some(max) = max_keys {
-            if let Some(max_nz) = std::num::NonZeroU32::new(max as u32) {
+            if let Some(max_nz) = std::num::NonZeroU32::new(max.min(AZURE_MAX_PAGE_SIZE) as u32) {
                 request = request.max_results(max_nz);
             }
         }
 
-        match request.into_stream().next().await {
-            Some(Ok(response)) => {
-                let objects: Vec<ObjectMetadata> = response
-                    .blobs
-                    .items
-                    .into_iter()
-                    .filter_map(|item| {
-                        use azure_storage_blobs::container::operations::BlobItem;
-                        if let BlobItem::Blob(blob) = item {
-                            let metadata_map: HashMap<String, String> =
-                                blob.metadata.clone().unwrap_or_default();
-
-                            let etag_str = format!("{:?}", blob.properties.etag);
-
-                            Some(Self::azure_metadata_to_object_metadata(
-                                blob.name,
-                                blob.properties.content_length,
-                                blob.properties.last_modified,
-                                Some(etag_str),
-                                Some(blob.properties.content_type),
-                                metadata_map,
-                            ))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-
-                debug!(
-                    "Listed {} blobs from Azure with prefix: {:?}",
-                    objects.len(),
-                    prefix
-                );
-
-                Ok(objects)
-            }
-            Some(Err(e)) => {
-                let error_msg = format!("{:?}", e);
-                if error_msg.contains("404") || error_msg.contains("ContainerNotFound") {
-                    Err(BackendError::NotFound(format!(
-                        "container:{}",
-                        self.container_name
-                    )))
-                } else {
+        let mut objects = Vec::new();
+        let mut pages = request.into_stream();
+        while let Some(page) = pages.next().await {
+            let response = match page {
+                Ok(response) => response,
+                Err(e) => {
+                    let error_msg = format!("{:?}", e);
+                    if error_msg.contains("404") || error_msg.contains("ContainerNotFound") {
+                        return Err(BackendError::NotFound(format!(
+                            "container:{}",
+                            self.container_name
+                        )));
+                    }
                     warn!("Failed to list blobs from Azure: {:?}", e);
-                    Err(BackendError::Provider(format!(
+                    return Err(BackendError::Provider(format!(
                         "Failed to list blobs: {}",
                         e
-                    )))
+                    )));
+                }
+            };
+
+            objects.extend(response.blobs.items.into_iter().filter_map(|item| {
+                use azure_storage_blobs::container::operations::BlobItem;
+                if let BlobItem::Blob(blob) = item {
+                    let metadata_map: HashMap<String, String> =
+                        blob.metadata.clone().unwrap_or_default();
+
+                    let etag_str = blob.properties.etag.to_string();
+
+                    Some(Self::azure_metadata_to_object_metadata(
+                        blob.name,
+                        blob.properties.content_length,
+                        blob.properties.last_modified,
+                        Some(etag_str),
+                        Some(blob.properties.content_type),
+                        metadata_map,
+                    ))
+                } else {
+                    None
+                }
+            }));
+
+            // Stop before fetching pages the caller won't use
+            if let Some(max) = max_keys {
+                if objects.len() >= max {
+                    objects.truncate(max);
+                    break;
                 }
             }
-            None => Ok(Vec::new()),
         }
+
+        debug!(
+            "Listed {} blobs from Azure with prefix: {:?}",
+            objects.len(),
+            prefix
+        );
+
+        Ok(objects)
     }
 
     async fn get_public_url(
@@ -372,10 +580,20 @@ impl Backend for AzureBackend {
         key: &str,
         expiration_secs: u64,
         purpose: PublicUrlPurpose,
+        _content_type: Option<&str>,
     ) -> BackendResult<String> {
+        // Service SAS has no way to constrain the content type of an upload, so
+        // the hint is not enforced here.
         use azure_storage::shared_access_signature::service_sas::BlobSasPermissions;
         use time::{Duration, OffsetDateTime};
 
+        if self.access_key.is_none() {
+            return Err(BackendError::Configuration(
+                "Presigned URLs need an Azure account key, which Managed Identity does not provide"
+                    .to_string(),
+            ));
+        }
+
         let expiry = OffsetDateTime::now_utc() + Duration::seconds(expiration_secs as i64);
 
         let permissions = match purpose {
@@ -413,3 +631,333 @@ impl Backend for AzureBackend {
         Ok(url)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azure_storage::CloudLocation;
+    use mockito::Matcher;
+
+    const ACCOUNT: &str = "devaccount";
+
+    fn mock_backend(server: &mockito::Server) -> AzureBackend {
+        let access_key = "dGVzdC1rZXk=".to_string();
+        let credentials =
+            StorageCredentials::access_key(ACCOUNT.to_string(), Secret::new(access_key.clone()));
+        let location = CloudLocation::Custom {
+            account: ACCOUNT.to_string(),
+            uri: format!("{}/{}", server.url(), ACCOUNT),
+        };
+
+        AzureBackend {
+            client: ClientBuilder::with_location(location, credentials).container_client("test"),
+            container_name: "test".to_string(),
+            account: ACCOUNT.to_string(),
+            access_key: Some(access_key),
+        }
+    }
+
+    fn list_page(range: std::ops::Range<usize>, next_marker: Option<&str>) -> String {
+        let blobs: String = range
+            .map(|i| {
+                format!(
+                    "<Blob><Name>blob-{:05}</Name><Properties>\
+                     <Creation-Time>Tue, 13 Sep 2022 08:20:48 GMT</Creation-Time>\
+                     <Last-Modified>Tue, 13 Sep 2022 08:20:48 GMT</Last-Modified>\
+                     <Etag>0x8DA9560DD170CFD</Etag>\
+                     <Content-Length>19</Content-Length>\
+                     <Content-Type>text/plain</Content-Type>\
+                     <BlobType>BlockBlob</BlobType>\
+                     </Properties></Blob>",
+                    i
+                )
+            })
+            .collect();
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+             <EnumerationResults ContainerName=\"test\">\
+             <Blobs>{}</Blobs><NextMarker>{}</NextMarker></EnumerationResults>",
+            blobs,
+            next_marker.unwrap_or_default()
+        )
+    }
+
+    async fn mock_list_page(
+        server: &mut mockito::Server,
+        query: Matcher,
+        body: String,
+        expected_hits: usize,
+    ) -> mockito::Mock {
+        server
+            .mock("GET", "/devaccount/test")
+            .match_query(query)
+            .with_header("x-ms-request-id", "00000000-0000-0000-0000-000000000000")
+            .with_header("date", "Tue, 13 Sep 2022 08:20:48 GMT")
+            .with_body(body)
+            .expect(expected_hits)
+            .create_async()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_upload_url_grants_create_and_write() {
+        let server = mockito::Server::new_async().await;
+        let backend = mock_backend(&server);
+
+        let url = backend
+            .get_public_url(
+                "photo.png",
+                300,
+                PublicUrlPurpose::Upload,
+                Some("image/png"),
+            )
+            .await
+            .unwrap();
+        assert!(url.starts_with("https://devaccount.blob.core.windows.net/test/photo.png?"));
+        assert!(url.contains("sp=cw"), "{}", url);
+
+        let url = backend
+            .get_public_url("photo.png", 300, PublicUrlPurpose::Retrieve, None)
+            .await
+            .unwrap();
+        assert!(url.contains("sp=r&"), "{}", url);
+    }
+
+    #[tokio::test]
+    async fn test_object_exists() {
+        let mut server = mockito::Server::new_async().await;
+        let present = server
+            .mock("HEAD", "/devaccount/test/present.txt")
+            .with_header("x-ms-request-id", "00000000-0000-0000-0000-000000000000")
+            .with_header("date", "Tue, 13 Sep 2022 08:20:48 GMT")
+            .with_header("x-ms-creation-time", "Tue, 13 Sep 2022 08:20:48 GMT")
+            .with_header("last-modified", "Tue, 13 Sep 2022 08:20:48 GMT")
+            .with_header("etag", "\"0x8DA9560DD170CFD\"")
+            .with_header("content-length", "19")
+            .with_header("content-type", "text/plain")
+            .with_header("x-ms-blob-type", "BlockBlob")
+            .with_header("x-ms-server-encrypted", "true")
+            .expect(2)
+            .create_async()
+            .await;
+        let missing = server
+            .mock("HEAD", "/devaccount/test/missing.txt")
+            .with_status(404)
+            .with_header("x-ms-error-code", "BlobNotFound")
+            .create_async()
+            .await;
+
+        let backend = mock_backend(&server);
+        assert!(backend.object_exists("present.txt").await.unwrap());
+        assert!(!backend.object_exists("missing.txt").await.unwrap());
+        let metadata = backend.head_object("present.txt").await.unwrap();
+        assert_eq!(metadata.etag, "0x8DA9560DD170CFD");
+        present.assert_async().await;
+        missing.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_missing_blob_maps_to_not_found() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/devaccount/test/missing.txt")
+            .with_status(404)
+            .with_header("x-ms-error-code", "BlobNotFound")
+            .with_header("content-type", "application/xml")
+            .with_body(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?><Error><Code>BlobNotFound</Code>\
+                 <Message>The specified blob does not exist.</Message></Error>",
+            )
+            .create_async()
+            .await;
+        // Get Blob Properties is a HEAD request, so there is no error body
+        server
+            .mock("HEAD", "/devaccount/test/missing.txt")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let backend = mock_backend(&server);
+        match backend.get_object("missing.txt").await {
+            Err(BackendError::NotFound(key)) => assert_eq!(key, "missing.txt"),
+            other => panic!("expected not found, got {:?}", other.err()),
+        }
+        match backend.head_object("missing.txt").await {
+            Err(BackendError::NotFound(key)) => assert_eq!(key, "missing.txt"),
+            other => panic!("expected not found, got {:?}", other.err()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_follows_every_page() {
+        let mut server = mockito::Server::new_async().await;
+        // Azure returns at most 5000 blobs per page
+        let first = mock_list_page(
+            &mut server,
+            Matcher::Regex("comp=list".to_string()),
+            list_page(0..5000, Some("page-2")),
+            1,
+        )
+        .await;
+        let second = mock_list_page(
+            &mut server,
+            Matcher::UrlEncoded("marker".into(), "page-2".into()),
+            list_page(5000..6000, None),
+            1,
+        )
+        .await;
+
+        let objects = mock_backend(&server)
+            .list_objects(None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(objects.len(), 6000);
+        assert_eq!(objects[0].key, "blob-00000");
+        assert_eq!(objects[5999].key, "blob-05999");
+        first.assert_async().await;
+        second.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_stops_at_max_keys() {
+        let mut server = mockito::Server::new_async().await;
+        let first = mock_list_page(
+            &mut server,
+            Matcher::Regex("comp=list".to_string()),
+            list_page(0..3, Some("page-2")),
+            1,
+        )
+        .await;
+        let second = mock_list_page(
+            &mut server,
+            Matcher::UrlEncoded("marker".into(), "page-2".into()),
+            list_page(3..6, None),
+            0,
+        )
+        .await;
+
+        let objects = mock_backend(&server)
+            .list_objects(None, Some(2))
+            .await
+            .unwrap();
+
+        assert_eq!(objects.len(), 2);
+        first.assert_async().await;
+        second.assert_async().await;
+    }
+
+    fn mock_put(server: &mut mockito::Server, query: Matcher) -> mockito::Mock {
+        server
+            .mock("PUT", "/devaccount/test/big.bin")
+            .match_query(query)
+            .with_status(201)
+            .with_header("x-ms-request-id", "00000000-0000-0000-0000-000000000000")
+            .with_header("date", "Tue, 13 Sep 2022 08:20:48 GMT")
+            .with_header("etag", "0x8DA9560DD170CFD")
+            .with_header("last-modified", "Tue, 13 Sep 2022 08:20:48 GMT")
+            .with_header("x-ms-request-server-encrypted", "true")
+    }
+
+    #[tokio::test]
+    async fn test_large_upload_is_staged_as_blocks() {
+        let mut server = mockito::Server::new_async().await;
+        let total = 50 * 1024 * 1024;
+        let payload: Vec<u8> = (0..total).map(|i| (i % 251) as u8).collect();
+
+        let full_blocks = mock_put(
+            &mut server,
+            Matcher::UrlEncoded("comp".into(), "block".into()),
+        )
+        .match_header("content-length", AZURE_BLOCK_BYTES.to_string().as_str())
+        .expect(12)
+        .create_async()
+        .await;
+        let last_block = mock_put(
+            &mut server,
+            Matcher::UrlEncoded("comp".into(), "block".into()),
+        )
+        .match_header("content-length", (2 * 1024 * 1024).to_string().as_str())
+        .expect(1)
+        .create_async()
+        .await;
+        // Block IDs are the base64 of the zero-padded index, "00000012" last
+        let commit = mock_put(
+            &mut server,
+            Matcher::UrlEncoded("comp".into(), "blocklist".into()),
+        )
+        .match_header("x-ms-blob-content-type", "application/octet-stream")
+        .match_body(Matcher::Regex(
+            "<Uncommitted>MDAwMDAwMTI=</Uncommitted>\n</BlockList>".to_string(),
+        ))
+        .expect(1)
+        .create_async()
+        .await;
+
+        let chunks: Vec<Result<Bytes, std::io::Error>> = payload
+            .chunks(1024 * 1024)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        let metadata = mock_backend(&server)
+            .put_object(
+                "big.bin",
+                Box::pin(futures::stream::iter(chunks)),
+                Some("application/octet-stream".to_string()),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(metadata.size, total as u64);
+        assert_eq!(metadata.etag, hex::encode(Sha256::digest(&payload)));
+        full_blocks.assert_async().await;
+        last_block.assert_async().await;
+        commit.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_small_upload_is_a_single_put_blob() {
+        let mut server = mockito::Server::new_async().await;
+        let put_blob = mock_put(&mut server, Matcher::Missing)
+            .match_header("x-ms-blob-type", "BlockBlob")
+            .match_body("hello")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let stream: ByteStream =
+            Box::pin(futures::stream::once(async { Ok(Bytes::from("hello")) }));
+        let metadata = mock_backend(&server)
+            .put_object("big.bin", stream, None, HashMap::new())
+            .await
+            .unwrap();
+
+        assert_eq!(metadata.size, 5);
+        assert_eq!(metadata.etag, hex::encode(Sha256::digest(b"hello")));
+        put_blob.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_oversized_blob_maps_to_entity_too_large() {
+        let mut server = mockito::Server::new_async().await;
+        mock_put(&mut server, Matcher::Missing)
+            .with_status(413)
+            .with_header("x-ms-error-code", "RequestBodyTooLarge")
+            .create_async()
+            .await;
+
+        let stream: ByteStream =
+            Box::pin(futures::stream::once(async { Ok(Bytes::from("hello")) }));
+        match mock_backend(&server)
+            .put_object("big.bin", stream, None, HashMap::new())
+            .await
+        {
+            Err(BackendError::EntityTooLarge { size, max_size }) => {
+                assert_eq!(size, 5);
+                assert_eq!(max_size, AZURE_MAX_PUT_BLOB_BYTES);
+            }
+            other => panic!("expected entity too large, got {:?}", other.err()),
+        }
+    }
+}
@@ -1,15 +1,21 @@
 use async_trait::async_trait;
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::pin::Pin;
+use tokio::io::AsyncRead;
+use tokio_util::io::ReaderStream;
 
 use crate::error::BackendResult;
 
 pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>>;
 
+/// Uploads larger than this are sent in parts as they are read instead of
+/// being buffered whole, by backends whose provider supports it.
+pub const STREAMING_THRESHOLD_BYTES: u64 = 8 * 1024 * 1024;
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum PublicUrlPurpose {
@@ -18,6 +24,18 @@ pub enum PublicUrlPurpose {
     Upload,
 }
 
+/// Storage tier an object is kept in. Backends without tiers keep every object
+/// in `Standard` and ignore transitions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageClass {
+    #[default]
+    Standard,
+    /// Cheaper, slower storage for objects that are rarely read, such as S3
+    /// Glacier.
+    Archive,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ObjectMetadata {
     pub key: String,
@@ -28,11 +46,75 @@ pub struct ObjectMetadata {
     pub custom_metadata: HashMap<String, String>,
 }
 
+impl ObjectMetadata {
+    /// The ETag without surrounding double quotes, for comparing ETags that
+    /// did not come from a backend, such as `If-None-Match` values.
+    pub fn etag_normalized(&self) -> &str {
+        unquote_etag(&self.etag)
+    }
+}
+
+/// The part of [`ObjectMetadata`] a listing can return without reading each
+/// object's content type and custom metadata.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectMetadataSummary {
+    pub key: String,
+    pub size: u64,
+    /// Empty where the backend can only get it by reading the object's metadata.
+    pub etag: String,
+    pub last_modified: DateTime<Utc>,
+}
+
+impl From<ObjectMetadata> for ObjectMetadataSummary {
+    fn from(metadata: ObjectMetadata) -> Self {
+        Self {
+            key: metadata.key,
+            size: metadata.size,
+            etag: metadata.etag,
+            last_modified: metadata.last_modified,
+        }
+    }
+}
+
+/// Files removed (or, in a dry run, that would be removed) by [`Backend::compact`].
+/// Paths are relative to the backend's storage root for the physical bucket.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompactReport {
+    /// Data files with no metadata file.
+    pub orphaned_data: Vec<String>,
+    /// Metadata files whose data file is missing.
+    pub orphaned_meta: Vec<String>,
+    pub reclaimed_bytes: u64,
+}
+
 pub struct ObjectData {
     pub metadata: ObjectMetadata,
     pub stream: ByteStream,
 }
 
+impl ObjectData {
+    /// An object whose whole body is already in memory.
+    pub fn from_bytes(metadata: ObjectMetadata, data: Bytes) -> Self {
+        Self {
+            metadata,
+            stream: Box::pin(futures::stream::once(async move { Ok(data) })),
+        }
+    }
+
+    pub fn into_parts(self) -> (ObjectMetadata, ByteStream) {
+        (self.metadata, self.stream)
+    }
+}
+
+impl std::fmt::Debug for ObjectData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObjectData")
+            .field("metadata", &self.metadata)
+            .field("stream", &format_args!("<ByteStream>"))
+            .finish()
+    }
+}
+
 #[async_trait]
 pub trait Backend: Send + Sync {
     async fn init(&self) -> BackendResult<()>;
@@ -47,16 +129,110 @@ pub trait Backend: Send + Sync {
 
     async fn get_object(&self, key: &str) -> BackendResult<ObjectData>;
 
+    /// Up to `len` bytes of the object starting at offset `start`, with the
+    /// metadata of the whole object. A range starting at or past the end
+    /// yields no bytes. The default reads the object from the beginning and
+    /// skips to `start`; backends that can seek or send a ranged request
+    /// override it.
+    async fn get_object_range(&self, key: &str, start: u64, len: u64) -> BackendResult<ObjectData> {
+        let object = self.get_object(key).await?;
+        Ok(ObjectData {
+            metadata: object.metadata,
+            stream: slice_stream(object.stream, start, len),
+        })
+    }
+
     async fn head_object(&self, key: &str) -> BackendResult<ObjectMetadata>;
 
     async fn delete_object(&self, key: &str) -> BackendResult<()>;
 
+    /// Like [`delete_object`](Self::delete_object), treating a key that is
+    /// already gone as deleted. Backends differ on whether deleting a missing
+    /// key is an error: S3 succeeds, the local backend returns `NotFound`.
+    async fn delete_object_if_exists(&self, key: &str) -> BackendResult<()> {
+        match self.delete_object(key).await {
+            Err(crate::error::BackendError::NotFound(_)) => Ok(()),
+            result => result,
+        }
+    }
+
     async fn list_objects(
         &self,
         prefix: Option<&str>,
         max_keys: Option<usize>,
     ) -> BackendResult<Vec<ObjectMetadata>>;
 
+    /// Like [`list_objects`](Self::list_objects), without content types and
+    /// custom metadata. Backends that read those per object should override
+    /// this to skip them.
+    async fn list_objects_metadata_only(
+        &self,
+        prefix: Option<&str>,
+        max_keys: Option<usize>,
+    ) -> BackendResult<Vec<ObjectMetadataSummary>> {
+        Ok(self
+            .list_objects(prefix, max_keys)
+            .await?
+            .into_iter()
+            .map(ObjectMetadataSummary::from)
+            .collect())
+    }
+
+    /// Up to `max_keys` objects under `prefix` whose keys sort after
+    /// `start_after`, in key order, so a listing can be read a page at a time
+    /// by passing the last key of the previous page. The default lists the
+    /// whole prefix for every page; backends that can start a listing at a key
+    /// override it.
+    async fn list_objects_after(
+        &self,
+        prefix: Option<&str>,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> BackendResult<Vec<ObjectMetadata>> {
+        let mut objects = self.list_objects(prefix, None).await?;
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        if let Some(start_after) = start_after {
+            objects.retain(|obj| obj.key.as_str() > start_after);
+        }
+        objects.truncate(max_keys);
+        Ok(objects)
+    }
+
+    /// Number of objects under `prefix`. Backends that can count without
+    /// building every object's metadata should override this.
+    async fn count_objects(&self, prefix: Option<&str>) -> BackendResult<u64> {
+        Ok(self.list_objects(prefix, None).await?.len() as u64)
+    }
+
+    /// Copies the object at `from` to `to`, replacing its content type and
+    /// custom metadata with the overrides that are `Some`. The default reads
+    /// the object back and uploads it again; backends that can copy in place
+    /// override it.
+    async fn copy_object(
+        &self,
+        from: &str,
+        to: &str,
+        content_type: Option<String>,
+        metadata: Option<HashMap<String, String>>,
+    ) -> BackendResult<ObjectMetadata> {
+        let source = self.get_object(from).await?;
+        self.put_object(
+            to,
+            source.stream,
+            content_type.or(source.metadata.content_type),
+            metadata.unwrap_or(source.metadata.custom_metadata),
+        )
+        .await
+    }
+
+    /// Moves the object at `from` to `to`, replacing any object there. The
+    /// default copies then deletes, so for a moment the object exists under
+    /// both keys; backends that can rename in place override it.
+    async fn rename_object(&self, from: &str, to: &str) -> BackendResult<()> {
+        self.copy_object(from, to, None, None).await?;
+        self.delete_object(from).await
+    }
+
     async fn object_exists(&self, key: &str) -> BackendResult<bool> {
         match self.head_object(key).await {
             Ok(_) => Ok(true),
@@ -65,17 +241,158 @@ pub trait Backend: Send + Sync {
         }
     }
 
+    /// `content_type` restricts upload URLs to that `Content-Type` on
+    /// backends whose signatures can cover it; others ignore it.
     async fn get_public_url(
         &self,
         key: &str,
         expiration_secs: u64,
         purpose: PublicUrlPurpose,
+        content_type: Option<&str>,
     ) -> BackendResult<String>;
+
+    /// Checks that the backend can currently serve requests. The default lists a
+    /// single key, which any reachable, authorized backend answers cheaply.
+    async fn health_check(&self) -> BackendResult<()> {
+        self.list_objects(None, Some(1)).await.map(|_| ())
+    }
+
+    /// Short name of the storage behind the backend, such as `"s3"`, for
+    /// health reports.
+    fn backend_type(&self) -> &'static str {
+        "custom"
+    }
+
+    /// Moves the object to `class`. The default does nothing, for backends that
+    /// have a single storage tier.
+    async fn set_storage_class(&self, _key: &str, _class: StorageClass) -> BackendResult<()> {
+        Ok(())
+    }
+
+    /// Removes storage left inconsistent by interrupted writes. Only backends
+    /// that keep data and metadata in separate files need this.
+    async fn compact(&self, _dry_run: bool) -> BackendResult<CompactReport> {
+        Err(crate::error::BackendError::Configuration(
+            "Compaction is not supported by this backend".to_string(),
+        ))
+    }
+}
+
+/// Skips the first `start` bytes of `stream` and ends it after `len` more.
+pub(crate) fn slice_stream(stream: ByteStream, start: u64, len: u64) -> ByteStream {
+    let sliced = stream
+        .scan((start, len), |(skip, remaining), item| {
+            if *remaining == 0 {
+                return futures::future::ready(None);
+            }
+            let item = item.map(|chunk| {
+                let skipped = (*skip).min(chunk.len() as u64);
+                *skip -= skipped;
+                let chunk = chunk.slice(skipped as usize..);
+                let taken = (*remaining).min(chunk.len() as u64);
+                *remaining -= taken;
+                chunk.slice(..taken as usize)
+            });
+            futures::future::ready(Some(item))
+        })
+        .filter(|item| futures::future::ready(!matches!(item, Ok(chunk) if chunk.is_empty())));
+    Box::pin(sliced)
+}
+
+/// Conveniences built on [`Backend`], available on every backend including
+/// `dyn Backend`. Generic methods would make `Backend` unusable as a trait
+/// object, so they live here.
+#[async_trait]
+pub trait BackendExt: Backend {
+    /// Like [`Backend::put_object`], reading the body from `reader`, such as a
+    /// `tokio::fs::File` or a `&'static [u8]`.
+    async fn put_object_reader<R: AsyncRead + Send + 'static>(
+        &self,
+        key: &str,
+        reader: R,
+        content_type: Option<String>,
+        metadata: HashMap<String, String>,
+    ) -> BackendResult<ObjectMetadata> {
+        self.put_object(
+            key,
+            Box::pin(ReaderStream::new(reader)),
+            content_type,
+            metadata,
+        )
+        .await
+    }
 }
 
+impl<B: Backend + ?Sized> BackendExt for B {}
+
 pub fn compute_etag(data: &[u8]) -> String {
     use sha2::{Digest, Sha256};
     let mut hasher = Sha256::new();
     hasher.update(data);
     hex::encode(hasher.finalize())
 }
+
+/// Strips the double quotes S3 and Azure put around ETags, so every backend
+/// stores them in [`ObjectMetadata::etag`] the way the local backend does.
+pub fn normalize_etag(etag: &str) -> String {
+    unquote_etag(etag).to_string()
+}
+
+fn unquote_etag(etag: &str) -> &str {
+    let etag = etag.trim();
+    etag.strip_prefix('"')
+        .and_then(|e| e.strip_suffix('"'))
+        .unwrap_or(etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(key: &str) -> ObjectMetadata {
+        ObjectMetadata {
+            key: key.to_string(),
+            size: 5,
+            content_type: Some("text/plain".to_string()),
+            etag: compute_etag(b"hello"),
+            last_modified: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            custom_metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_object_data_debug_elides_stream() {
+        let object = ObjectData::from_bytes(metadata("a.txt"), Bytes::from_static(b"hello"));
+        let debug = format!("{:?}", object);
+        assert!(debug.starts_with("ObjectData { metadata: ObjectMetadata { key: \"a.txt\""));
+        assert!(debug.contains("content_type: Some(\"text/plain\")"));
+        assert!(debug.ends_with("stream: <ByteStream> }"));
+        assert!(!debug.contains("hello"));
+    }
+
+    #[tokio::test]
+    async fn test_object_data_into_parts_round_trip() {
+        use futures::TryStreamExt;
+
+        let object = ObjectData::from_bytes(metadata("a.txt"), Bytes::from_static(b"hello"));
+        let (metadata, stream) = object.into_parts();
+        assert_eq!(metadata.key, "a.txt");
+        assert_eq!(metadata.etag, compute_etag(b"hello"));
+
+        let rebuilt = ObjectData { metadata, stream };
+        let chunks: Vec<Bytes> = rebuilt.stream.try_collect().await.unwrap();
+        assert_eq!(chunks.concat(), b"hello");
+    }
+
+    #[test]
+    fn test_normalize_etag() {
+        assert_eq!(normalize_etag("\"abc123\""), "abc123");
+        assert_eq!(normalize_etag("abc123"), "abc123");
+        assert_eq!(
+            normalize_etag("\"0x8DA9560DD170CFD\" "),
+            "0x8DA9560DD170CFD"
+        );
+        // Only a surrounding pair is a quoted ETag
+        assert_eq!(normalize_etag("\"abc123"), "\"abc123");
+    }
+}
@@ -0,0 +1,508 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::backend::{
+    Backend, ByteStream, CompactReport, ObjectData, ObjectMetadata, ObjectMetadataSummary,
+    PublicUrlPurpose, StorageClass,
+};
+use crate::error::{BackendError, BackendResult};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failed calls that open the circuit.
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// How long an open circuit rejects calls before letting trial calls through.
+    #[serde(default = "default_open_duration_secs")]
+    pub open_duration_secs: u64,
+    /// Consecutive successful trial calls that close the circuit again.
+    #[serde(default = "default_success_threshold")]
+    pub success_threshold: u32,
+}
+
+fn default_failure_threshold() -> u32 {
+    5
+}
+
+fn default_open_duration_secs() -> u64 {
+    30
+}
+
+fn default_success_threshold() -> u32 {
+    2
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_failure_threshold(),
+            open_duration_secs: default_open_duration_secs(),
+            success_threshold: default_success_threshold(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open { until: Instant },
+    HalfOpen,
+}
+
+struct BreakerState {
+    state: CircuitState,
+    consecutive_failures: u32,
+    consecutive_successes: u32,
+}
+
+/// Counts consecutive backend failures and, past the threshold, rejects calls
+/// for a while so a struggling backend gets room to recover instead of every
+/// request waiting on it. Once the open period ends calls are let through
+/// again; enough successes close the circuit and any failure reopens it.
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                consecutive_successes: 0,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.state.lock().unwrap().state
+    }
+
+    /// Admits a call, or fails with `CircuitOpen` while the circuit is open.
+    pub fn try_acquire(&self) -> BackendResult<()> {
+        let mut state = self.state.lock().unwrap();
+        if let CircuitState::Open { until } = state.state {
+            let now = Instant::now();
+            if now < until {
+                return Err(BackendError::CircuitOpen {
+                    retry_after_secs: (until - now).as_secs_f64().ceil() as u64,
+                });
+            }
+            info!("Circuit half-open, letting trial backend calls through");
+            state.state = CircuitState::HalfOpen;
+            state.consecutive_successes = 0;
+        }
+        Ok(())
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = 0;
+        if state.state == CircuitState::HalfOpen {
+            state.consecutive_successes += 1;
+            if state.consecutive_successes >= self.config.success_threshold {
+                info!("Circuit closed, backend recovered");
+                state.state = CircuitState::Closed;
+            }
+        }
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        match state.state {
+            CircuitState::HalfOpen => self.open(&mut state),
+            CircuitState::Closed => {
+                state.consecutive_failures += 1;
+                if state.consecutive_failures >= self.config.failure_threshold {
+                    self.open(&mut state);
+                }
+            }
+            CircuitState::Open { .. } => {}
+        }
+    }
+
+    fn open(&self, state: &mut BreakerState) {
+        warn!(
+            "Circuit opened for {}s after {} consecutive backend failures",
+            self.config.open_duration_secs,
+            state.consecutive_failures.max(1)
+        );
+        state.state = CircuitState::Open {
+            until: Instant::now() + Duration::from_secs(self.config.open_duration_secs),
+        };
+        state.consecutive_failures = 0;
+        state.consecutive_successes = 0;
+    }
+}
+
+/// Whether an error says the backend itself is unhealthy, as opposed to the
+/// request being wrong (missing key, bad path, permissions).
+fn is_backend_failure(error: &BackendError) -> bool {
+    matches!(
+        error,
+        BackendError::Io { .. }
+            | BackendError::Provider(_)
+            | BackendError::RateLimited { .. }
+            | BackendError::RequestLimitExceeded
+            | BackendError::Internal(_)
+    )
+}
+
+/// Runs every call to `inner` through a [`CircuitBreaker`].
+pub struct CircuitBreakerBackend<B: ?Sized = dyn Backend> {
+    inner: Arc<B>,
+    breaker: CircuitBreaker,
+}
+
+impl<B: Backend + ?Sized> CircuitBreakerBackend<B> {
+    pub fn new(inner: Arc<B>, config: CircuitBreakerConfig) -> Self {
+        Self {
+            inner,
+            breaker: CircuitBreaker::new(config),
+        }
+    }
+
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    async fn call<T>(&self, operation: impl Future<Output = BackendResult<T>>) -> BackendResult<T> {
+        self.breaker.try_acquire()?;
+        let result = operation.await;
+        match &result {
+            // The caller's upload body failed, which says nothing either way
+            // about the backend
+            Err(e) if matches!(e.root(), BackendError::BodyStream(_)) => {}
+            Err(e) if is_backend_failure(e) => self.breaker.record_failure(),
+            _ => self.breaker.record_success(),
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl<B: Backend + ?Sized> Backend for CircuitBreakerBackend<B> {
+    async fn init(&self) -> BackendResult<()> {
+        self.call(self.inner.init()).await
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        stream: ByteStream,
+        content_type: Option<String>,
+        metadata: HashMap<String, String>,
+    ) -> BackendResult<ObjectMetadata> {
+        self.call(self.inner.put_object(key, stream, content_type, metadata))
+            .await
+    }
+
+    async fn get_object(&self, key: &str) -> BackendResult<ObjectData> {
+        self.call(self.inner.get_object(key)).await
+    }
+
+    async fn get_object_range(&self, key: &str, start: u64, len: u64) -> BackendResult<ObjectData> {
+        self.call(self.inner.get_object_range(key, start, len))
+            .await
+    }
+
+    async fn head_object(&self, key: &str) -> BackendResult<ObjectMetadata> {
+        self.call(self.inner.head_object(key)).await
+    }
+
+    async fn copy_object(
+        &self,
+        from: &str,
+        to: &str,
+        content_type: Option<String>,
+        metadata: Option<HashMap<String, String>>,
+    ) -> BackendResult<ObjectMetadata> {
+        self.call(self.inner.copy_object(from, to, content_type, metadata))
+            .await
+    }
+
+    async fn rename_object(&self, from: &str, to: &str) -> BackendResult<()> {
+        self.call(self.inner.rename_object(from, to)).await
+    }
+
+    async fn delete_object(&self, key: &str) -> BackendResult<()> {
+        self.call(self.inner.delete_object(key)).await
+    }
+
+    async fn list_objects(
+        &self,
+        prefix: Option<&str>,
+        max_keys: Option<usize>,
+    ) -> BackendResult<Vec<ObjectMetadata>> {
+        self.call(self.inner.list_objects(prefix, max_keys)).await
+    }
+
+    async fn list_objects_metadata_only(
+        &self,
+        prefix: Option<&str>,
+        max_keys: Option<usize>,
+    ) -> BackendResult<Vec<ObjectMetadataSummary>> {
+        self.call(self.inner.list_objects_metadata_only(prefix, max_keys))
+            .await
+    }
+
+    async fn list_objects_after(
+        &self,
+        prefix: Option<&str>,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> BackendResult<Vec<ObjectMetadata>> {
+        self.call(self.inner.list_objects_after(prefix, start_after, max_keys))
+            .await
+    }
+
+    async fn count_objects(&self, prefix: Option<&str>) -> BackendResult<u64> {
+        self.call(self.inner.count_objects(prefix)).await
+    }
+
+    async fn object_exists(&self, key: &str) -> BackendResult<bool> {
+        self.call(self.inner.object_exists(key)).await
+    }
+
+    async fn get_public_url(
+        &self,
+        key: &str,
+        expiration_secs: u64,
+        purpose: PublicUrlPurpose,
+        content_type: Option<&str>,
+    ) -> BackendResult<String> {
+        self.call(
+            self.inner
+                .get_public_url(key, expiration_secs, purpose, content_type),
+        )
+        .await
+    }
+
+    async fn health_check(&self) -> BackendResult<()> {
+        self.call(self.inner.health_check()).await
+    }
+
+    fn backend_type(&self) -> &'static str {
+        self.inner.backend_type()
+    }
+
+    async fn set_storage_class(&self, key: &str, class: StorageClass) -> BackendResult<()> {
+        self.call(self.inner.set_storage_class(key, class)).await
+    }
+
+    async fn compact(&self, dry_run: bool) -> BackendResult<CompactReport> {
+        self.call(self.inner.compact(dry_run)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryBackend;
+    use bytes::Bytes;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// Memory backend that fails every call with a provider error while `down`.
+    #[derive(Default)]
+    struct FlakyBackend {
+        inner: MemoryBackend,
+        down: AtomicBool,
+        calls: AtomicUsize,
+    }
+
+    impl FlakyBackend {
+        fn check(&self) -> BackendResult<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.down.load(Ordering::SeqCst) {
+                return Err(BackendError::Provider(
+                    "503 Service Unavailable".to_string(),
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Backend for FlakyBackend {
+        async fn init(&self) -> BackendResult<()> {
+            Ok(())
+        }
+
+        async fn put_object(
+            &self,
+            key: &str,
+            stream: ByteStream,
+            content_type: Option<String>,
+            metadata: HashMap<String, String>,
+        ) -> BackendResult<ObjectMetadata> {
+            self.check()?;
+            self.inner
+                .put_object(key, stream, content_type, metadata)
+                .await
+        }
+
+        async fn get_object(&self, key: &str) -> BackendResult<ObjectData> {
+            self.check()?;
+            self.inner.get_object(key).await
+        }
+
+        async fn head_object(&self, key: &str) -> BackendResult<ObjectMetadata> {
+            self.check()?;
+            self.inner.head_object(key).await
+        }
+
+        async fn delete_object(&self, key: &str) -> BackendResult<()> {
+            self.check()?;
+            self.inner.delete_object(key).await
+        }
+
+        async fn list_objects(
+            &self,
+            prefix: Option<&str>,
+            max_keys: Option<usize>,
+        ) -> BackendResult<Vec<ObjectMetadata>> {
+            self.check()?;
+            self.inner.list_objects(prefix, max_keys).await
+        }
+
+        async fn get_public_url(
+            &self,
+            key: &str,
+            expiration_secs: u64,
+            purpose: PublicUrlPurpose,
+            content_type: Option<&str>,
+        ) -> BackendResult<String> {
+            self.inner
+                .get_public_url(key, expiration_secs, purpose, content_type)
+                .await
+        }
+    }
+
+    fn breaker_backend() -> (Arc<FlakyBackend>, CircuitBreakerBackend<FlakyBackend>) {
+        let flaky = Arc::new(FlakyBackend::default());
+        let backend = CircuitBreakerBackend::new(
+            flaky.clone(),
+            CircuitBreakerConfig {
+                failure_threshold: 3,
+                open_duration_secs: 10,
+                success_threshold: 2,
+            },
+        );
+        (flaky, backend)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_opens_after_consecutive_failures_and_recovers() {
+        let (flaky, backend) = breaker_backend();
+        let body: ByteStream =
+            Box::pin(futures::stream::iter(vec![Ok(Bytes::from_static(b"data"))]));
+        backend
+            .put_object("bucket/file.txt", body, None, HashMap::new())
+            .await
+            .unwrap();
+
+        // A missing key is the caller's problem, not a backend failure
+        for _ in 0..5 {
+            let missing = backend.head_object("bucket/missing.txt").await;
+            assert!(matches!(missing, Err(BackendError::NotFound(_))));
+        }
+        assert_eq!(backend.breaker().state(), CircuitState::Closed);
+
+        flaky.down.store(true, Ordering::SeqCst);
+        for _ in 0..3 {
+            let result = backend.head_object("bucket/file.txt").await;
+            assert!(matches!(result, Err(BackendError::Provider(_))));
+        }
+        assert!(matches!(
+            backend.breaker().state(),
+            CircuitState::Open { .. }
+        ));
+
+        // While open the inner backend is not called at all
+        let calls = flaky.calls.load(Ordering::SeqCst);
+        let result = backend.head_object("bucket/file.txt").await;
+        assert!(matches!(
+            result,
+            Err(BackendError::CircuitOpen {
+                retry_after_secs: 10
+            })
+        ));
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), calls);
+
+        // After the open period a failing trial call reopens the circuit
+        tokio::time::advance(Duration::from_secs(10)).await;
+        let result = backend.head_object("bucket/file.txt").await;
+        assert!(matches!(result, Err(BackendError::Provider(_))));
+        assert!(matches!(
+            backend.head_object("bucket/file.txt").await,
+            Err(BackendError::CircuitOpen { .. })
+        ));
+
+        // Two successful trial calls close it again
+        flaky.down.store(false, Ordering::SeqCst);
+        tokio::time::advance(Duration::from_secs(10)).await;
+        backend.head_object("bucket/file.txt").await.unwrap();
+        assert_eq!(backend.breaker().state(), CircuitState::HalfOpen);
+        backend.head_object("bucket/file.txt").await.unwrap();
+        assert_eq!(backend.breaker().state(), CircuitState::Closed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failed_upload_bodies_do_not_open_circuit() {
+        let (flaky, backend) = breaker_backend();
+
+        for _ in 0..5 {
+            let body: ByteStream = Box::pin(futures::stream::iter(vec![
+                Ok(Bytes::from_static(b"partial")),
+                Err(std::io::Error::other("object size limit exceeded")),
+            ]));
+            let result = backend
+                .put_object("bucket/file.txt", body, None, HashMap::new())
+                .await;
+            assert!(matches!(result, Err(BackendError::BodyStream(_))));
+        }
+        assert_eq!(backend.breaker().state(), CircuitState::Closed);
+
+        // Nor do they count as the successes that close a half-open circuit
+        flaky.down.store(true, Ordering::SeqCst);
+        for _ in 0..3 {
+            assert!(backend.head_object("bucket/file.txt").await.is_err());
+        }
+        flaky.down.store(false, Ordering::SeqCst);
+        tokio::time::advance(Duration::from_secs(10)).await;
+        backend.head_object("bucket/missing.txt").await.unwrap_err();
+        assert_eq!(backend.breaker().state(), CircuitState::HalfOpen);
+        let body: ByteStream = Box::pin(futures::stream::iter(vec![Err(std::io::Error::other(
+            "connection reset",
+        ))]));
+        assert!(backend
+            .put_object("bucket/file.txt", body, None, HashMap::new())
+            .await
+            .is_err());
+        assert_eq!(backend.breaker().state(), CircuitState::HalfOpen);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_success_resets_failure_count() {
+        let (flaky, backend) = breaker_backend();
+
+        for _ in 0..4 {
+            flaky.down.store(true, Ordering::SeqCst);
+            for _ in 0..2 {
+                assert!(backend.list_objects(None, None).await.is_err());
+            }
+            flaky.down.store(false, Ordering::SeqCst);
+            backend.list_objects(None, None).await.unwrap();
+        }
+
+        assert_eq!(backend.breaker().state(), CircuitState::Closed);
+    }
+}
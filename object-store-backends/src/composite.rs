@@ -0,0 +1,474 @@
+use async_trait::async_trait;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::backend::{
+    Backend, ByteStream, CompactReport, ObjectData, ObjectMetadata, ObjectMetadataSummary,
+    PublicUrlPurpose, StorageClass,
+};
+use crate::error::{BackendError, BackendResult};
+
+/// Sends each operation to a backend chosen by bucket, so cheap buckets can
+/// live on local disk and premium ones on S3 behind the same service.
+///
+/// Keys are `{bucket}/{key}`, so the bucket is the first path segment, or the
+/// one after a prefix registered with
+/// [`with_bucket_prefix`](Self::with_bucket_prefix). Keys that match no route,
+/// including the service's own `.metadata/` records, go to the default
+/// backend.
+pub struct CompositeBackend {
+    routes: Vec<(Regex, Arc<dyn Backend>)>,
+    bucket_prefixes: Vec<String>,
+    default: Arc<dyn Backend>,
+}
+
+impl CompositeBackend {
+    pub fn new(default: Arc<dyn Backend>) -> Self {
+        Self {
+            routes: Vec::new(),
+            bucket_prefixes: Vec::new(),
+            default,
+        }
+    }
+
+    /// Routes keys of the form `{prefix}/{bucket}/...` by that bucket, for
+    /// copies of a bucket's objects kept outside it, such as a recycle bin or
+    /// uploads staged before they replace an object.
+    pub fn with_bucket_prefix(mut self, prefix: &str) -> Self {
+        self.bucket_prefixes
+            .push(prefix.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Routes buckets whose whole name matches the `bucket_pattern` regex to
+    /// `backend`. Routes are tried in the order they were added.
+    pub fn with_route(
+        mut self,
+        bucket_pattern: &str,
+        backend: Arc<dyn Backend>,
+    ) -> BackendResult<Self> {
+        let pattern = Regex::new(&format!("^(?:{})$", bucket_pattern)).map_err(|e| {
+            BackendError::Configuration(format!(
+                "Invalid bucket pattern '{}': {}",
+                bucket_pattern, e
+            ))
+        })?;
+        self.routes.push((pattern, backend));
+        Ok(self)
+    }
+
+    fn route(&self, key: &str) -> &Arc<dyn Backend> {
+        let bucket = self
+            .bucket_of(key)
+            .unwrap_or_else(|| key.split('/').next().unwrap_or(key));
+        self.backend_for(bucket)
+    }
+
+    fn backend_for(&self, bucket: &str) -> &Arc<dyn Backend> {
+        self.routes
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(bucket))
+            .map(|(_, backend)| backend)
+            .unwrap_or(&self.default)
+    }
+
+    /// The bucket every key starting with `prefix` belongs to, or `None` when
+    /// they can belong to several, as under `.recyclebin/` or `.metadata/`
+    /// when those hold bucket prefixes.
+    fn bucket_of<'a>(&self, prefix: &'a str) -> Option<&'a str> {
+        for bucket_prefix in &self.bucket_prefixes {
+            if let Some(rest) = prefix
+                .strip_prefix(bucket_prefix.as_str())
+                .and_then(|rest| rest.strip_prefix('/'))
+            {
+                return rest.split_once('/').map(|(bucket, _)| bucket);
+            }
+            if bucket_prefix.starts_with(prefix) {
+                return None;
+            }
+        }
+        prefix.split_once('/').map(|(bucket, _)| bucket)
+    }
+
+    fn backends(&self) -> impl Iterator<Item = &Arc<dyn Backend>> {
+        std::iter::once(&self.default).chain(self.routes.iter().map(|(_, backend)| backend))
+    }
+}
+
+#[async_trait]
+impl Backend for CompositeBackend {
+    async fn init(&self) -> BackendResult<()> {
+        for backend in self.backends() {
+            backend.init().await?;
+        }
+        Ok(())
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        stream: ByteStream,
+        content_type: Option<String>,
+        metadata: HashMap<String, String>,
+    ) -> BackendResult<ObjectMetadata> {
+        self.route(key)
+            .put_object(key, stream, content_type, metadata)
+            .await
+    }
+
+    async fn get_object(&self, key: &str) -> BackendResult<ObjectData> {
+        self.route(key).get_object(key).await
+    }
+
+    async fn get_object_range(&self, key: &str, start: u64, len: u64) -> BackendResult<ObjectData> {
+        self.route(key).get_object_range(key, start, len).await
+    }
+
+    async fn head_object(&self, key: &str) -> BackendResult<ObjectMetadata> {
+        self.route(key).head_object(key).await
+    }
+
+    /// Copies within one backend when both keys route to it, so it can copy
+    /// in place; otherwise streams the object from one backend to the other.
+    async fn copy_object(
+        &self,
+        from: &str,
+        to: &str,
+        content_type: Option<String>,
+        metadata: Option<HashMap<String, String>>,
+    ) -> BackendResult<ObjectMetadata> {
+        let (source, destination) = (self.route(from), self.route(to));
+        if Arc::ptr_eq(source, destination) {
+            return source.copy_object(from, to, content_type, metadata).await;
+        }
+
+        let object = source.get_object(from).await?;
+        destination
+            .put_object(
+                to,
+                object.stream,
+                content_type.or(object.metadata.content_type),
+                metadata.unwrap_or(object.metadata.custom_metadata),
+            )
+            .await
+    }
+
+    async fn rename_object(&self, from: &str, to: &str) -> BackendResult<()> {
+        let source = self.route(from);
+        if Arc::ptr_eq(source, self.route(to)) {
+            return source.rename_object(from, to).await;
+        }
+
+        self.copy_object(from, to, None, None).await?;
+        source.delete_object(from).await
+    }
+
+    async fn delete_object(&self, key: &str) -> BackendResult<()> {
+        self.route(key).delete_object(key).await
+    }
+
+    async fn list_objects(
+        &self,
+        prefix: Option<&str>,
+        max_keys: Option<usize>,
+    ) -> BackendResult<Vec<ObjectMetadata>> {
+        // A prefix inside one bucket is served entirely by that bucket's backend
+        if let Some(bucket) = prefix.and_then(|p| self.bucket_of(p)) {
+            return self
+                .backend_for(bucket)
+                .list_objects(prefix, max_keys)
+                .await;
+        }
+
+        let mut objects = Vec::new();
+        for backend in self.backends() {
+            objects.extend(backend.list_objects(prefix, max_keys).await?);
+        }
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        if let Some(max) = max_keys {
+            objects.truncate(max);
+        }
+
+        debug!(
+            "Listed {} objects across {} backends with prefix: {:?}",
+            objects.len(),
+            self.routes.len() + 1,
+            prefix
+        );
+
+        Ok(objects)
+    }
+
+    async fn list_objects_metadata_only(
+        &self,
+        prefix: Option<&str>,
+        max_keys: Option<usize>,
+    ) -> BackendResult<Vec<ObjectMetadataSummary>> {
+        if let Some(bucket) = prefix.and_then(|p| self.bucket_of(p)) {
+            return self
+                .backend_for(bucket)
+                .list_objects_metadata_only(prefix, max_keys)
+                .await;
+        }
+
+        let mut objects = Vec::new();
+        for backend in self.backends() {
+            objects.extend(backend.list_objects_metadata_only(prefix, max_keys).await?);
+        }
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        if let Some(max) = max_keys {
+            objects.truncate(max);
+        }
+        Ok(objects)
+    }
+
+    async fn list_objects_after(
+        &self,
+        prefix: Option<&str>,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> BackendResult<Vec<ObjectMetadata>> {
+        if let Some(bucket) = prefix.and_then(|p| self.bucket_of(p)) {
+            return self
+                .backend_for(bucket)
+                .list_objects_after(prefix, start_after, max_keys)
+                .await;
+        }
+
+        let mut objects = Vec::new();
+        for backend in self.backends() {
+            objects.extend(
+                backend
+                    .list_objects_after(prefix, start_after, max_keys)
+                    .await?,
+            );
+        }
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        objects.truncate(max_keys);
+        Ok(objects)
+    }
+
+    async fn count_objects(&self, prefix: Option<&str>) -> BackendResult<u64> {
+        if let Some(bucket) = prefix.and_then(|p| self.bucket_of(p)) {
+            return self.backend_for(bucket).count_objects(prefix).await;
+        }
+
+        let mut count = 0;
+        for backend in self.backends() {
+            count += backend.count_objects(prefix).await?;
+        }
+        Ok(count)
+    }
+
+    async fn object_exists(&self, key: &str) -> BackendResult<bool> {
+        self.route(key).object_exists(key).await
+    }
+
+    async fn get_public_url(
+        &self,
+        key: &str,
+        expiration_secs: u64,
+        purpose: PublicUrlPurpose,
+        content_type: Option<&str>,
+    ) -> BackendResult<String> {
+        self.route(key)
+            .get_public_url(key, expiration_secs, purpose, content_type)
+            .await
+    }
+
+    async fn set_storage_class(&self, key: &str, class: StorageClass) -> BackendResult<()> {
+        self.route(key).set_storage_class(key, class).await
+    }
+
+    async fn health_check(&self) -> BackendResult<()> {
+        for backend in self.backends() {
+            backend.health_check().await?;
+        }
+        Ok(())
+    }
+
+    fn backend_type(&self) -> &'static str {
+        "composite"
+    }
+
+    /// Compacts every backend that supports it and merges the reports.
+    async fn compact(&self, dry_run: bool) -> BackendResult<CompactReport> {
+        let mut merged: Option<CompactReport> = None;
+        let mut unsupported = None;
+
+        for backend in self.backends() {
+            match backend.compact(dry_run).await {
+                Ok(report) => {
+                    let merged = merged.get_or_insert_with(CompactReport::default);
+                    merged.orphaned_data.extend(report.orphaned_data);
+                    merged.orphaned_meta.extend(report.orphaned_meta);
+                    merged.reclaimed_bytes += report.reclaimed_bytes;
+                }
+                Err(e @ BackendError::Configuration(_)) => unsupported = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+
+        match (merged, unsupported) {
+            (Some(report), _) => Ok(report),
+            (None, Some(e)) => Err(e),
+            (None, None) => Ok(CompactReport::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryBackend;
+    use bytes::Bytes;
+
+    fn body(data: &'static [u8]) -> ByteStream {
+        Box::pin(futures::stream::iter(vec![Ok(Bytes::from_static(data))]))
+    }
+
+    #[tokio::test]
+    async fn test_routes_by_bucket_pattern() {
+        let hot = Arc::new(MemoryBackend::new());
+        let cold = Arc::new(MemoryBackend::new());
+        let fallback = Arc::new(MemoryBackend::new());
+        let composite = CompositeBackend::new(fallback.clone())
+            .with_route("hot-.*", hot.clone())
+            .unwrap()
+            .with_route("cold-.*", cold.clone())
+            .unwrap();
+
+        for key in [
+            "hot-a/one.txt",
+            "hot-b/two.txt",
+            "cold-a/three.txt",
+            "not-hot/four.txt",
+        ] {
+            composite
+                .put_object(key, body(b"data"), None, HashMap::new())
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(hot.len(), 2);
+        assert_eq!(cold.len(), 1);
+        assert_eq!(fallback.len(), 1);
+        assert!(hot.object_exists("hot-a/one.txt").await.unwrap());
+        assert!(cold.object_exists("cold-a/three.txt").await.unwrap());
+        // The pattern has to match the whole bucket name
+        assert!(fallback.object_exists("not-hot/four.txt").await.unwrap());
+
+        assert!(composite.object_exists("cold-a/three.txt").await.unwrap());
+        assert!(!composite.object_exists("hot-a/three.txt").await.unwrap());
+
+        let in_bucket = composite.list_objects(Some("hot-a/"), None).await.unwrap();
+        assert_eq!(in_bucket.len(), 1);
+
+        let keys: Vec<String> = composite
+            .list_objects(None, Some(3))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|obj| obj.key)
+            .collect();
+        assert_eq!(keys, ["cold-a/three.txt", "hot-a/one.txt", "hot-b/two.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_bucket_prefixes_route_by_bucket() {
+        let hot = Arc::new(MemoryBackend::new());
+        let fallback = Arc::new(MemoryBackend::new());
+        let composite = CompositeBackend::new(fallback.clone())
+            .with_route("hot-.*", hot.clone())
+            .unwrap()
+            .with_bucket_prefix(".recyclebin")
+            .with_bucket_prefix(".metadata/staging");
+
+        for key in [
+            "hot-a/one.txt",
+            ".recyclebin/hot-a/one.txt#1",
+            ".metadata/staging/hot-a/two.txt",
+            ".recyclebin/other/one.txt#1",
+            ".metadata/locks/hot-a",
+        ] {
+            composite
+                .put_object(key, body(b"data"), None, HashMap::new())
+                .await
+                .unwrap();
+        }
+        assert_eq!(hot.len(), 3);
+        assert_eq!(fallback.len(), 2);
+
+        composite
+            .rename_object(".metadata/staging/hot-a/two.txt", "hot-a/two.txt")
+            .await
+            .unwrap();
+        assert!(hot.object_exists("hot-a/two.txt").await.unwrap());
+
+        let keys = |objects: Vec<ObjectMetadata>| -> Vec<String> {
+            objects.into_iter().map(|obj| obj.key).collect()
+        };
+        let in_bin = composite
+            .list_objects(Some(".recyclebin/hot-a/"), None)
+            .await
+            .unwrap();
+        assert_eq!(keys(in_bin), [".recyclebin/hot-a/one.txt#1"]);
+        // Prefixes above the bucket's segment span every backend
+        let whole_bin = composite
+            .list_objects(Some(".recyclebin/"), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            keys(whole_bin),
+            [".recyclebin/hot-a/one.txt#1", ".recyclebin/other/one.txt#1"]
+        );
+        assert_eq!(
+            composite.count_objects(Some(".metadata/")).await.unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_copy_between_backends() {
+        let hot = Arc::new(MemoryBackend::new());
+        let fallback = Arc::new(MemoryBackend::new());
+        let composite = CompositeBackend::new(fallback.clone())
+            .with_route("hot-.*", hot.clone())
+            .unwrap();
+
+        composite
+            .put_object(
+                "hot-a/one.txt",
+                body(b"data"),
+                Some("text/plain".to_string()),
+                HashMap::from([("owner".to_string(), "alice".to_string())]),
+            )
+            .await
+            .unwrap();
+
+        let copied = composite
+            .copy_object("hot-a/one.txt", "archive/one.txt", None, None)
+            .await
+            .unwrap();
+        assert_eq!(copied.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(copied.custom_metadata["owner"], "alice");
+        assert!(fallback.object_exists("archive/one.txt").await.unwrap());
+
+        composite
+            .copy_object("hot-a/one.txt", "hot-b/one.txt", None, Some(HashMap::new()))
+            .await
+            .unwrap();
+        assert_eq!(hot.len(), 2);
+        let copied = hot.head_object("hot-b/one.txt").await.unwrap();
+        assert!(copied.custom_metadata.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_bucket_pattern() {
+        let result = CompositeBackend::new(Arc::new(MemoryBackend::new()))
+            .with_route("(", Arc::new(MemoryBackend::new()));
+        assert!(matches!(result, Err(BackendError::Configuration(_))));
+    }
+}
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -5,12 +6,20 @@ pub enum BackendError {
     #[error("Object not found: {0}")]
     NotFound(String),
 
-    #[error("IO error: {0}")]
-    Io(#[from] std::io::Error),
+    #[error("IO error on {}: {source}", path.display())]
+    Io {
+        source: std::io::Error,
+        path: PathBuf,
+    },
 
     #[error("Provider error: {0}")]
     Provider(String),
 
+    /// The upload body the caller sent could not be read: the client went
+    /// away, the body was cut off at a size limit, or it failed to decode.
+    #[error("Failed to read upload body: {0}")]
+    BodyStream(String),
+
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
@@ -20,8 +29,94 @@ pub enum BackendError {
     #[error("Configuration error: {0}")]
     Configuration(String),
 
+    #[error("Checksum mismatch for {key}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        key: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[error("Rate limited by backend")]
+    RateLimited { retry_after_secs: Option<u64> },
+
+    /// Sent the backend more requests than its account or API quota allows.
+    #[error("Request limit exceeded by backend")]
+    RequestLimitExceeded,
+
+    /// An upload of `size` bytes was refused for being over the backend's
+    /// `max_size`.
+    #[error("Object of {size} bytes exceeds the backend's limit of {max_size} bytes")]
+    EntityTooLarge { size: u64, max_size: u64 },
+
+    #[error("Backend unavailable: circuit open, retry in {retry_after_secs}s")]
+    CircuitOpen { retry_after_secs: u64 },
+
     #[error("Internal error: {0}")]
     Internal(String),
+
+    #[error(transparent)]
+    Context(Box<ContextError>),
+}
+
+/// An error with a note on what was being done, added by
+/// [`BackendResultExt::context`].
+#[derive(Error, Debug)]
+#[error("{context}: {source}")]
+pub struct ContextError {
+    pub context: String,
+    pub source: BackendError,
+}
+
+impl BackendError {
+    /// For `map_err`: wraps an I/O error with the path it happened on.
+    pub fn io(path: impl Into<PathBuf>) -> impl FnOnce(std::io::Error) -> Self {
+        let path = path.into();
+        move |source| Self::Io { source, path }
+    }
+
+    /// The error underneath any [`BackendError::Context`], which is what
+    /// decides how a failure is handled.
+    pub fn root(&self) -> &BackendError {
+        match self {
+            Self::Context(e) => e.source.root(),
+            other => other,
+        }
+    }
+
+    pub fn into_root(self) -> BackendError {
+        match self {
+            Self::Context(e) => e.source.into_root(),
+            other => other,
+        }
+    }
+}
+
+/// Adds context to a failed backend call, like `anyhow::Context`. The original
+/// error stays reachable through [`std::error::Error::source`] and
+/// [`BackendError::root`].
+pub trait BackendResultExt<T> {
+    fn context(self, context: impl Into<String>) -> BackendResult<T>;
+
+    /// Like [`context`](Self::context), but only builds the message on failure.
+    fn with_context<C: Into<String>>(self, f: impl FnOnce() -> C) -> BackendResult<T>;
+}
+
+impl<T> BackendResultExt<T> for BackendResult<T> {
+    fn context(self, context: impl Into<String>) -> BackendResult<T> {
+        self.with_context(|| context)
+    }
+
+    fn with_context<C: Into<String>>(self, f: impl FnOnce() -> C) -> BackendResult<T> {
+        self.map_err(|source| {
+            BackendError::Context(Box::new(ContextError {
+                context: f().into(),
+                source,
+            }))
+        })
+    }
 }
 
 pub type BackendResult<T> = Result<T, BackendError>;
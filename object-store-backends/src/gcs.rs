@@ -2,22 +2,65 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use futures::StreamExt;
+use google_cloud_storage::client::google_cloud_auth::credentials::CredentialsFile;
 use google_cloud_storage::client::{Client, ClientConfig};
 use google_cloud_storage::http::objects::delete::DeleteObjectRequest;
 use google_cloud_storage::http::objects::download::Range;
 use google_cloud_storage::http::objects::get::GetObjectRequest;
 use google_cloud_storage::http::objects::list::ListObjectsRequest;
 use google_cloud_storage::http::objects::upload::{Media, UploadObjectRequest, UploadType};
-use sha2::{Digest, Sha256};
+use google_cloud_storage::http::resumable_upload_client::{
+    ChunkSize, ResumableUploadClient, UploadStatus,
+};
+use google_cloud_storage::http::Error as GcsError;
+use google_cloud_storage::sign::{SignBy, SignedURLMethod, SignedURLOptions};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tracing::{debug, info, warn};
 
-use crate::backend::{Backend, ByteStream, ObjectData, ObjectMetadata, PublicUrlPurpose};
+use crate::backend::{
+    Backend, ByteStream, ObjectData, ObjectMetadata, PublicUrlPurpose, STREAMING_THRESHOLD_BYTES,
+};
 use crate::error::{BackendError, BackendResult};
 
+/// Size of each chunk sent in a resumable upload. GCS requires every chunk but
+/// the last to be a multiple of 256 KiB.
+const RESUMABLE_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+
+/// Attempts per chunk before a resumable upload is abandoned.
+const RESUMABLE_CHUNK_ATTEMPTS: u32 = 3;
+
+/// Objects requested per `list_objects` page, the most GCS returns at once.
+const LIST_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcsConfig {
+    /// Objects larger than this are sent with a resumable upload instead of
+    /// being buffered whole in memory.
+    #[serde(default = "default_resumable_threshold_bytes")]
+    pub resumable_threshold_bytes: u64,
+}
+
+pub fn default_resumable_threshold_bytes() -> u64 {
+    STREAMING_THRESHOLD_BYTES
+}
+
+impl Default for GcsConfig {
+    fn default() -> Self {
+        Self {
+            resumable_threshold_bytes: default_resumable_threshold_bytes(),
+        }
+    }
+}
+
 pub struct GcsBackend {
     client: Client,
     bucket_name: String,
+    config: GcsConfig,
+    /// Service account email and signer used for signed URLs. `SignBy::SignBytes`
+    /// delegates to the IAM `signBlob` API, which is how Workload Identity signs
+    /// without holding a private key.
+    signing: Option<(String, SignBy)>,
 }
 
 impl GcsBackend {
@@ -26,12 +69,15 @@ impl GcsBackend {
             BackendError::Configuration(format!("Failed to initialize GCS auth: {}", e))
         })?;
 
+        let signing = Self::default_signing(&config);
         let client = Client::new(config);
 
         info!("Initialized GCS backend with bucket: {}", bucket_name);
         Ok(Self {
             client,
             bucket_name,
+            config: GcsConfig::default(),
+            signing,
         })
     }
 
@@ -45,6 +91,7 @@ impl GcsBackend {
             BackendError::Configuration(format!("Failed to initialize GCS with credentials: {}", e))
         })?;
 
+        let signing = Self::default_signing(&config);
         let client = Client::new(config);
 
         info!(
@@ -54,14 +101,89 @@ impl GcsBackend {
         Ok(Self {
             client,
             bucket_name,
+            config: GcsConfig::default(),
+            signing,
         })
     }
 
-    fn calculate_etag(data: &[u8]) -> String {
-        use sha2::{Digest, Sha256};
-        let mut hasher = Sha256::new();
-        hasher.update(data);
-        hex::encode(hasher.finalize())
+    /// Authenticates and signs URLs with a service account JSON key, regardless
+    /// of the ambient credentials.
+    pub async fn new_with_signing_key(
+        bucket_name: String,
+        sa_key_path: String,
+    ) -> BackendResult<Self> {
+        let credentials = CredentialsFile::new_from_file(sa_key_path.clone())
+            .await
+            .map_err(|e| {
+                BackendError::Configuration(format!(
+                    "Failed to read GCS service account key '{}': {}",
+                    sa_key_path, e
+                ))
+            })?;
+
+        let (Some(client_email), Some(private_key)) = (
+            credentials.client_email.clone(),
+            credentials.private_key.clone(),
+        ) else {
+            return Err(BackendError::Configuration(format!(
+                "GCS service account key '{}' has no client_email or private_key",
+                sa_key_path
+            )));
+        };
+
+        let config = ClientConfig::default()
+            .with_credentials(credentials)
+            .await
+            .map_err(|e| {
+                BackendError::Configuration(format!(
+                    "Failed to initialize GCS with signing key: {}",
+                    e
+                ))
+            })?;
+
+        let client = Client::new(config);
+
+        info!(
+            "Initialized GCS backend with bucket: {} signing as: {}",
+            bucket_name, client_email
+        );
+        Ok(Self {
+            client,
+            bucket_name,
+            config: GcsConfig::default(),
+            signing: Some((client_email, SignBy::PrivateKey(private_key.into_bytes()))),
+        })
+    }
+
+    /// Builds the backend from a prepared client config, such as one pointed at
+    /// an emulator through `storage_endpoint`.
+    pub fn from_client_config(bucket_name: String, config: ClientConfig) -> Self {
+        let signing = Self::default_signing(&config);
+        Self {
+            client: Client::new(config),
+            bucket_name,
+            config: GcsConfig::default(),
+            signing,
+        }
+    }
+
+    pub fn with_config(mut self, config: GcsConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Signs URLs through the IAM `signBlob` API as the given service account.
+    /// The backend's own credentials need `iam.serviceAccounts.signBlob` on it.
+    pub fn with_sign_blob(mut self, service_account_email: String) -> Self {
+        self.signing = Some((service_account_email, SignBy::SignBytes));
+        self
+    }
+
+    fn default_signing(config: &ClientConfig) -> Option<(String, SignBy)> {
+        match (&config.default_google_access_id, &config.default_sign_by) {
+            (Some(access_id), Some(sign_by)) => Some((access_id.clone(), sign_by.clone())),
+            _ => None,
+        }
     }
 
     fn gcs_metadata_to_object_metadata(
@@ -92,8 +214,224 @@ impl GcsBackend {
     }
 }
 
+impl GcsBackend {
+    /// Streams the rest of an object through a resumable upload session, holding
+    /// at most one chunk plus the latest stream item in memory.
+    async fn upload_resumable(
+        &self,
+        key: &str,
+        request: &UploadObjectRequest,
+        upload_type: &UploadType,
+        mut buffer: Vec<u8>,
+        mut stream: ByteStream,
+    ) -> BackendResult<google_cloud_storage::http::objects::Object> {
+        let session = self
+            .client
+            .prepare_resumable_upload(request, upload_type)
+            .await
+            .map_err(|e| upload_failed(key, format!("failed to start resumable upload: {}", e)))?;
+
+        let mut offset = 0u64;
+        let mut exhausted = false;
+
+        loop {
+            while !exhausted && buffer.len() < RESUMABLE_CHUNK_BYTES {
+                match stream.next().await {
+                    Some(chunk) => buffer.extend_from_slice(
+                        &chunk.map_err(|e| BackendError::BodyStream(e.to_string()))?,
+                    ),
+                    None => exhausted = true,
+                }
+            }
+
+            if exhausted && buffer.len() <= RESUMABLE_CHUNK_BYTES {
+                let total = offset + buffer.len() as u64;
+                let range = ChunkSize::new(offset, total.saturating_sub(1), Some(total));
+                let status = Self::upload_chunk(&session, buffer, &range)
+                    .await
+                    .map_err(|e| upload_failed(key, e))?;
+                return match status {
+                    UploadStatus::Ok(object) => {
+                        debug!(
+                            "Uploaded object to GCS via resumable upload: {} ({} bytes)",
+                            key, total
+                        );
+                        Ok(object)
+                    }
+                    UploadStatus::ResumeIncomplete => Err(upload_failed(
+                        key,
+                        "upload incomplete after final chunk".to_string(),
+                    )),
+                };
+            }
+
+            let rest = buffer.split_off(RESUMABLE_CHUNK_BYTES);
+            let chunk = std::mem::replace(&mut buffer, rest);
+            let range = ChunkSize::new(offset, offset + chunk.len() as u64 - 1, None);
+            offset += chunk.len() as u64;
+            Self::upload_chunk(&session, chunk, &range)
+                .await
+                .map_err(|e| upload_failed(key, e))?;
+        }
+    }
+
+    async fn upload_chunk(
+        session: &ResumableUploadClient,
+        chunk: Vec<u8>,
+        range: &ChunkSize,
+    ) -> Result<UploadStatus, String> {
+        let chunk = Bytes::from(chunk);
+        let mut attempt = 1;
+        loop {
+            match session.upload_multiple_chunk(chunk.clone(), range).await {
+                Ok(status) => return Ok(status),
+                Err(e) if attempt < RESUMABLE_CHUNK_ATTEMPTS && is_retryable(&e) => {
+                    warn!(
+                        "Resumable upload chunk {} failed (attempt {}): {}",
+                        range, attempt, e
+                    );
+                    attempt += 1;
+                }
+                Err(e) => return Err(format!("chunk {} failed: {}", range, e)),
+            }
+        }
+    }
+
+    /// Streams the object `metadata` describes, or the `range` of it.
+    async fn stream_object(
+        &self,
+        metadata: ObjectMetadata,
+        range: &Range,
+    ) -> BackendResult<ObjectData> {
+        let key = metadata.key.as_str();
+        let request = GetObjectRequest {
+            bucket: self.bucket_name.clone(),
+            object: key.to_string(),
+            ..Default::default()
+        };
+
+        match self.client.download_streamed_object(&request, range).await {
+            Ok(body) => {
+                debug!(
+                    "Streaming object from GCS: {} ({} bytes)",
+                    key, metadata.size
+                );
+                let stream: ByteStream =
+                    Box::pin(body.map(|chunk| chunk.map_err(std::io::Error::other)));
+
+                Ok(ObjectData { metadata, stream })
+            }
+            Err(e) => {
+                let error_msg = format!("{:?}", e);
+                if error_msg.contains("404") || error_msg.contains("NotFound") {
+                    Err(BackendError::NotFound(key.to_string()))
+                } else {
+                    warn!("Failed to get object from GCS: {}: {:?}", key, e);
+                    Err(BackendError::Provider(format!(
+                        "Failed to get object '{}': {}",
+                        key, e
+                    )))
+                }
+            }
+        }
+    }
+
+    // Lists from `start_offset` on, inclusive, following page tokens
+    async fn list_from(
+        &self,
+        prefix: Option<&str>,
+        start_offset: Option<&str>,
+        max_keys: Option<usize>,
+    ) -> BackendResult<Vec<ObjectMetadata>> {
+        let mut objects = Vec::new();
+        let mut page_token = None;
+
+        // GCS answers at most LIST_PAGE_SIZE objects per request, so keep
+        // following next_page_token until it runs out or max_keys is reached.
+        while max_keys.is_none_or(|max| objects.len() < max) {
+            let page_size = max_keys.map_or(LIST_PAGE_SIZE, |max| {
+                (max - objects.len()).min(LIST_PAGE_SIZE)
+            });
+            let request = ListObjectsRequest {
+                bucket: self.bucket_name.clone(),
+                prefix: prefix.map(str::to_string),
+                max_results: Some(page_size as i32),
+                page_token: page_token.take(),
+                start_offset: start_offset.map(str::to_string),
+                ..Default::default()
+            };
+
+            let response = match self.client.list_objects(&request).await {
+                Ok(response) => response,
+                Err(e) => {
+                    let error_msg = format!("{:?}", e);
+                    return if error_msg.contains("404") {
+                        Err(BackendError::NotFound(format!(
+                            "bucket:{}",
+                            self.bucket_name
+                        )))
+                    } else {
+                        warn!("Failed to list objects from GCS: {:?}", e);
+                        Err(BackendError::Provider(format!(
+                            "Failed to list objects: {}",
+                            e
+                        )))
+                    };
+                }
+            };
+
+            objects.extend(response.items.unwrap_or_default().into_iter().map(|obj| {
+                Self::gcs_metadata_to_object_metadata(
+                    obj.name,
+                    obj.size,
+                    obj.updated,
+                    obj.md5_hash,
+                    obj.content_type,
+                    obj.metadata.unwrap_or_default(),
+                )
+            }));
+
+            page_token = response.next_page_token;
+            if page_token.is_none() {
+                break;
+            }
+        }
+
+        if let Some(max) = max_keys {
+            objects.truncate(max);
+        }
+
+        debug!(
+            "Listed {} objects from GCS with prefix: {:?}",
+            objects.len(),
+            prefix
+        );
+
+        Ok(objects)
+    }
+}
+
+fn upload_failed(key: &str, detail: String) -> BackendError {
+    BackendError::Provider(format!("Failed to upload object '{}': {}", key, detail))
+}
+
+fn is_retryable(err: &GcsError) -> bool {
+    let retryable_status = |code: u16| code == 408 || code == 429 || code >= 500;
+    match err {
+        GcsError::Response(e) => retryable_status(e.code),
+        // No status means the request never completed (connection reset, timeout)
+        GcsError::HttpClient(e) => e.status().is_none_or(|s| retryable_status(s.as_u16())),
+        GcsError::HttpMiddleware(_) => true,
+        GcsError::TokenSource(_) => false,
+    }
+}
+
 #[async_trait]
 impl Backend for GcsBackend {
+    fn backend_type(&self) -> &'static str {
+        "gcs"
+    }
+
     async fn init(&self) -> BackendResult<()> {
         match self
             .client
@@ -125,102 +463,77 @@ impl Backend for GcsBackend {
         content_type: Option<String>,
         custom_metadata: HashMap<String, String>,
     ) -> BackendResult<ObjectMetadata> {
-        let key_owned = key.to_string();
+        let threshold =
+            usize::try_from(self.config.resumable_threshold_bytes).unwrap_or(usize::MAX);
 
-        // Collect stream into bytes while computing hash
-        let mut hasher = Sha256::new();
+        // Buffer up to the threshold; only objects that outgrow it pay for the
+        // extra round trips of a resumable session.
         let mut data = Vec::new();
-
-        while let Some(chunk_result) = stream.next().await {
-            let chunk = chunk_result
-                .map_err(|e| BackendError::Provider(format!("Failed to read stream: {}", e)))?;
-
-            hasher.update(&chunk);
-            data.extend_from_slice(&chunk);
+        let mut exhausted = false;
+        while data.len() <= threshold {
+            match stream.next().await {
+                Some(chunk) => data.extend_from_slice(
+                    &chunk.map_err(|e| BackendError::BodyStream(e.to_string()))?,
+                ),
+                None => {
+                    exhausted = true;
+                    break;
+                }
+            }
         }
 
-        let size = data.len();
-
-        let upload_type = UploadType::Simple(Media::new(key_owned.clone()));
+        let mut media = Media::new(key.to_string());
+        if let Some(ct) = content_type.as_ref() {
+            media.content_type = ct.clone().into();
+        }
+        let upload_type = UploadType::Simple(media);
         let request = UploadObjectRequest {
             bucket: self.bucket_name.clone(),
             ..Default::default()
         };
 
-        match self
-            .client
-            .upload_object(&request, data, &upload_type)
-            .await
-        {
-            Ok(object) => {
-                debug!("Uploaded object to GCS: {} ({} bytes)", key, size);
-                Ok(Self::gcs_metadata_to_object_metadata(
-                    object.name,
-                    object.size,
-                    object.updated,
-                    object.md5_hash,
-                    content_type,
-                    custom_metadata,
-                ))
-            }
+        let result = if exhausted {
+            let size = data.len();
+            self.client
+                .upload_object(&request, data, &upload_type)
+                .await
+                .inspect(|_| debug!("Uploaded object to GCS: {} ({} bytes)", key, size))
+                .map_err(|e| upload_failed(key, e.to_string()))
+        } else {
+            self.upload_resumable(key, &request, &upload_type, data, stream)
+                .await
+        };
+
+        match result {
+            Ok(object) => Ok(Self::gcs_metadata_to_object_metadata(
+                object.name,
+                object.size,
+                object.updated,
+                object.md5_hash,
+                content_type,
+                custom_metadata,
+            )),
             Err(e) => {
-                warn!("Failed to upload object to GCS: {}: {:?}", key, e);
-                Err(BackendError::Provider(format!(
-                    "Failed to upload object '{}': {}",
-                    key, e
-                )))
+                warn!("Failed to upload object to GCS: {}", e);
+                Err(e)
             }
         }
     }
 
     async fn get_object(&self, key: &str) -> BackendResult<ObjectData> {
-        let request = GetObjectRequest {
-            bucket: self.bucket_name.clone(),
-            object: key.to_string(),
-            ..Default::default()
-        };
-
-        match self
-            .client
-            .download_object(&request, &Range::default())
-            .await
-        {
-            Ok(data) => {
-                let size = data.len();
-                debug!("Retrieved object from GCS: {} ({} bytes)", key, size);
-
-                // Get metadata
-                let metadata = match self.head_object(key).await {
-                    Ok(meta) => meta,
-                    Err(_) => ObjectMetadata {
-                        key: key.to_string(),
-                        size: size as u64,
-                        content_type: None,
-                        last_modified: Utc::now(),
-                        etag: Self::calculate_etag(&data),
-                        custom_metadata: HashMap::new(),
-                    },
-                };
-
-                // Convert data to stream
-                let stream: ByteStream =
-                    Box::pin(futures::stream::once(async move { Ok(Bytes::from(data)) }));
+        let metadata = self.head_object(key).await?;
+        self.stream_object(metadata, &Range::default()).await
+    }
 
-                Ok(ObjectData { metadata, stream })
-            }
-            Err(e) => {
-                let error_msg = format!("{:?}", e);
-                if error_msg.contains("404") || error_msg.contains("NotFound") {
-                    Err(BackendError::NotFound(key.to_string()))
-                } else {
-                    warn!("Failed to get object from GCS: {}: {:?}", key, e);
-                    Err(BackendError::Provider(format!(
-                        "Failed to get object '{}': {}",
-                        key, e
-                    )))
-                }
-            }
+    async fn get_object_range(&self, key: &str, start: u64, len: u64) -> BackendResult<ObjectData> {
+        let metadata = self.head_object(key).await?;
+        // GCS refuses a range that starts past the end
+        if len == 0 || start >= metadata.size {
+            return Ok(ObjectData::from_bytes(metadata, Bytes::new()));
         }
+        let last = start.saturating_add(len - 1).min(metadata.size - 1);
+        self.stream_object(metadata, &Range(Some(start), Some(last)))
+            .await
     }
 
     async fn head_object(&self, key: &str) -> BackendResult<ObjectMetadata> {
@@ -281,61 +594,25 @@ impl Backend for GcsBackend {
         prefix: Option<&str>,
         max_keys: Option<usize>,
     ) -> BackendResult<Vec<ObjectMetadata>> {
-        let mut request = ListObjectsRequest {
-            bucket: self.bucket_name.clone(),
-            ..Default::default()
-        };
-
-        if let Some(p) = prefix {
-            request.prefix = Some(p.to_string());
-        }
-
-        if let Some(max) = max_keys {
-            request.max_results = Some(max as i32);
-        }
-
-        match self.client.list_objects(&request).await {
-            Ok(response) => {
-                let objects: Vec<ObjectMetadata> = response
-                    .items
-                    .unwrap_or_default()
-                    .into_iter()
-                    .map(|obj| {
-                        Self::gcs_metadata_to_object_metadata(
-                            obj.name,
-                            obj.size,
-                            obj.updated,
-                            obj.md5_hash,
-                            obj.content_type,
-                            obj.metadata.unwrap_or_default(),
-                        )
-                    })
-                    .collect();
-
-                debug!(
-                    "Listed {} objects from GCS with prefix: {:?}",
-                    objects.len(),
-                    prefix
-                );
+        self.list_from(prefix, None, max_keys).await
+    }
 
-                Ok(objects)
-            }
-            Err(e) => {
-                let error_msg = format!("{:?}", e);
-                if error_msg.contains("404") {
-                    Err(BackendError::NotFound(format!(
-                        "bucket:{}",
-                        self.bucket_name
-                    )))
-                } else {
-                    warn!("Failed to list objects from GCS: {:?}", e);
-                    Err(BackendError::Provider(format!(
-                        "Failed to list objects: {}",
-                        e
-                    )))
-                }
-            }
+    async fn list_objects_after(
+        &self,
+        prefix: Option<&str>,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> BackendResult<Vec<ObjectMetadata>> {
+        // startOffset is inclusive, so ask for one more in case the cursor
+        // itself comes back
+        let mut objects = self
+            .list_from(prefix, start_after, Some(max_keys + 1))
+            .await?;
+        if let Some(start_after) = start_after {
+            objects.retain(|obj| obj.key.as_str() > start_after);
         }
+        objects.truncate(max_keys);
+        Ok(objects)
     }
 
     async fn get_public_url(
@@ -343,29 +620,40 @@ impl Backend for GcsBackend {
         key: &str,
         expiration_secs: u64,
         purpose: PublicUrlPurpose,
+        content_type: Option<&str>,
     ) -> BackendResult<String> {
-        let expiration = std::time::Duration::from_secs(expiration_secs);
-
-        use google_cloud_storage::sign::{SignedURLMethod, SignedURLOptions};
-
-        let method = match purpose {
-            PublicUrlPurpose::Retrieve => SignedURLMethod::GET,
-            PublicUrlPurpose::Upload => SignedURLMethod::PUT,
+        let Some((access_id, sign_by)) = self.signing.clone() else {
+            return Err(BackendError::Configuration(
+                "Signing not configured".to_string(),
+            ));
         };
 
-        let url_options = SignedURLOptions {
-            method,
-            expires: expiration,
+        let mut url_options = SignedURLOptions {
+            method: SignedURLMethod::GET,
+            expires: std::time::Duration::from_secs(expiration_secs),
             ..Default::default()
         };
 
+        // Upload URLs are bound to the content type GCS will store, so a client
+        // cannot use one to upload something else under the same key. Without an
+        // explicit type, it is guessed from the key.
+        if matches!(purpose, PublicUrlPurpose::Upload) {
+            url_options.method = SignedURLMethod::PUT;
+            url_options.content_type =
+                Some(content_type.map(str::to_string).unwrap_or_else(|| {
+                    mime_guess::from_path(key)
+                        .first_or_octet_stream()
+                        .to_string()
+                }));
+        }
+
         let url_for = self
             .client
             .signed_url(
                 &self.bucket_name,
                 key,
-                None, // headers
-                None, // sign_by
+                Some(access_id),
+                Some(sign_by),
                 url_options,
             )
             .await
@@ -387,3 +675,305 @@ impl Backend for GcsBackend {
         Ok(url_for)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+
+    fn backend_for(server: &mockito::Server) -> GcsBackend {
+        let config = ClientConfig {
+            storage_endpoint: server.url(),
+            ..Default::default()
+        };
+        GcsBackend {
+            client: Client::new(config.anonymous()),
+            bucket_name: "test-bucket".to_string(),
+            config: GcsConfig::default(),
+            signing: None,
+        }
+    }
+
+    // The JSON API encodes 64-bit integers as strings
+    fn object_json(name: &str, size: usize) -> serde_json::Value {
+        serde_json::json!({
+            "id": format!("test-bucket/{}/1", name),
+            "selfLink": "",
+            "mediaLink": "",
+            "etag": "CAE=",
+            "name": name,
+            "bucket": "test-bucket",
+            "size": size.to_string(),
+            "generation": "1",
+            "metageneration": "1",
+        })
+    }
+
+    fn uploaded_object(size: usize) -> String {
+        object_json("big.bin", size).to_string()
+    }
+
+    fn list_page(names: &[&str], next_page_token: Option<&str>) -> String {
+        let items: Vec<_> = names.iter().map(|name| object_json(name, 1)).collect();
+        serde_json::json!({ "items": items, "nextPageToken": next_page_token }).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_follows_page_tokens() {
+        let mut server = mockito::Server::new_async().await;
+
+        let first = server
+            .mock("GET", "/storage/v1/b/test-bucket/o")
+            .match_query(Matcher::UrlEncoded("maxResults".into(), "1000".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(list_page(&["a", "b"], Some("page-2")))
+            .expect(1)
+            .create_async()
+            .await;
+        let second = server
+            .mock("GET", "/storage/v1/b/test-bucket/o")
+            .match_query(Matcher::UrlEncoded("pageToken".into(), "page-2".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(list_page(&["c"], None))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let objects = backend_for(&server).list_objects(None, None).await.unwrap();
+
+        let keys: Vec<_> = objects.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(keys, ["a", "b", "c"]);
+        first.assert_async().await;
+        second.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_stops_at_max_keys() {
+        let mut server = mockito::Server::new_async().await;
+
+        let first = server
+            .mock("GET", "/storage/v1/b/test-bucket/o")
+            .match_query(Matcher::UrlEncoded("maxResults".into(), "3".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(list_page(&["a", "b"], Some("page-2")))
+            .expect(1)
+            .create_async()
+            .await;
+        // Only the one object still missing is asked for, and the token it
+        // returns is not followed
+        let second = server
+            .mock("GET", "/storage/v1/b/test-bucket/o")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("maxResults".into(), "1".into()),
+                Matcher::UrlEncoded("pageToken".into(), "page-2".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(list_page(&["c"], Some("page-3")))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let objects = backend_for(&server)
+            .list_objects(None, Some(3))
+            .await
+            .unwrap();
+
+        assert_eq!(objects.len(), 3);
+        first.assert_async().await;
+        second.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_object_streams_body() {
+        let mut server = mockito::Server::new_async().await;
+        let body: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8).collect();
+
+        server
+            .mock("GET", "/storage/v1/b/test-bucket/o/big.bin")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(uploaded_object(body.len()))
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/storage/v1/b/test-bucket/o/big.bin")
+            .match_query(Matcher::UrlEncoded("alt".into(), "media".into()))
+            .with_status(200)
+            .with_body(body.clone())
+            .create_async()
+            .await;
+
+        let object = backend_for(&server).get_object("big.bin").await.unwrap();
+        assert_eq!(object.metadata.size, body.len() as u64);
+
+        let chunks: Vec<Bytes> = object.stream.map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(chunks.concat(), body);
+    }
+
+    #[tokio::test]
+    async fn test_get_missing_object_is_not_found() {
+        let mut server = mockito::Server::new_async().await;
+
+        server
+            .mock("GET", "/storage/v1/b/test-bucket/o/missing.txt")
+            .with_status(404)
+            .with_body(r#"{"error":{"code":404,"message":"No such object"}}"#)
+            .create_async()
+            .await;
+
+        let err = backend_for(&server)
+            .get_object("missing.txt")
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, BackendError::NotFound(ref key) if key == "missing.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_resumable_upload_large_object() {
+        let mut server = mockito::Server::new_async().await;
+        let total = 20 * 1024 * 1024;
+
+        let session = server
+            .mock("POST", "/upload/storage/v1/b/test-bucket/o")
+            .match_query(Matcher::UrlEncoded("uploadType".into(), "resumable".into()))
+            .with_status(200)
+            .with_header("location", &format!("{}/session/1", server.url()))
+            .create_async()
+            .await;
+        // The first chunk fails once and must be retried
+        let flaky = server
+            .mock("PUT", "/session/1")
+            .match_header("content-range", "bytes 0-8388607/*")
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+        let intermediate = server
+            .mock("PUT", "/session/1")
+            .match_header(
+                "content-range",
+                Matcher::Regex(r"^bytes \d+-\d+/\*$".into()),
+            )
+            .with_status(308)
+            .expect(2)
+            .create_async()
+            .await;
+        let last = server
+            .mock("PUT", "/session/1")
+            .match_header(
+                "content-range",
+                format!("bytes 16777216-{}/{}", total - 1, total).as_str(),
+            )
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(uploaded_object(total))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let chunks: Vec<Result<Bytes, std::io::Error>> = (0..20)
+            .map(|_| Ok(Bytes::from(vec![7u8; 1024 * 1024])))
+            .collect();
+        let stream: ByteStream = Box::pin(futures::stream::iter(chunks));
+
+        let metadata = backend_for(&server)
+            .put_object("big.bin", stream, None, HashMap::new())
+            .await
+            .unwrap();
+
+        assert_eq!(metadata.size, total as u64);
+        session.assert_async().await;
+        flaky.assert_async().await;
+        intermediate.assert_async().await;
+        last.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_small_object_uses_simple_upload() {
+        let mut server = mockito::Server::new_async().await;
+
+        let simple = server
+            .mock("POST", "/upload/storage/v1/b/test-bucket/o")
+            .match_query(Matcher::UrlEncoded("uploadType".into(), "media".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(uploaded_object(11))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let stream: ByteStream = Box::pin(futures::stream::once(async {
+            Ok(Bytes::from("hello world"))
+        }));
+        let metadata = backend_for(&server)
+            .put_object("big.bin", stream, None, HashMap::new())
+            .await
+            .unwrap();
+
+        assert_eq!(metadata.size, 11);
+        simple.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_signed_url_without_signing_configured() {
+        let server = mockito::Server::new_async().await;
+
+        let err = backend_for(&server)
+            .get_public_url("a.txt", 60, PublicUrlPurpose::Retrieve, None)
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(err, BackendError::Configuration(ref msg) if msg == "Signing not configured")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_signed_upload_url_via_iam_sign_blob() {
+        let mut server = mockito::Server::new_async().await;
+        let account = "signer@project.iam.gserviceaccount.com";
+
+        let sign_blob = server
+            .mock(
+                "POST",
+                format!("/v1/projects/-/serviceAccounts/{}:signBlob", account).as_str(),
+            )
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"keyId":"1","signedBlob":"c2lnbmVk"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = ClientConfig {
+            storage_endpoint: server.url(),
+            service_account_endpoint: server.url(),
+            ..Default::default()
+        };
+        let backend = GcsBackend {
+            client: Client::new(config.anonymous()),
+            bucket_name: "test-bucket".to_string(),
+            config: GcsConfig::default(),
+            signing: None,
+        }
+        .with_sign_blob(account.to_string());
+
+        let url = backend
+            .get_public_url("photos/cat.png", 300, PublicUrlPurpose::Upload, None)
+            .await
+            .unwrap();
+
+        sign_blob.assert_async().await;
+        // The signature is the hex encoding of the signed blob ("signed")
+        assert!(url.contains(&format!("X-Goog-Signature={}", hex::encode("signed"))));
+        assert!(url.contains("X-Goog-Credential=signer%40project.iam.gserviceaccount.com"));
+        assert!(url.contains("X-Goog-SignedHeaders=content-type%3Bhost"));
+        assert!(url.contains("X-Goog-Expires=300"));
+    }
+}
@@ -1,9 +1,17 @@
 pub mod azure;
 pub mod backend;
+pub mod circuit_breaker;
+pub mod composite;
 pub mod error;
 pub mod gcs;
 pub mod local;
+pub mod memory;
+pub mod migrate;
 pub mod s3;
 
-pub use backend::{Backend, ByteStream, ObjectData, ObjectMetadata, PublicUrlPurpose};
-pub use error::{BackendError, BackendResult};
+pub use backend::{
+    normalize_etag, Backend, BackendExt, ByteStream, CompactReport, ObjectData, ObjectMetadata,
+    ObjectMetadataSummary, PublicUrlPurpose, StorageClass, STREAMING_THRESHOLD_BYTES,
+};
+pub use error::{BackendError, BackendResult, BackendResultExt, ContextError};
+pub use migrate::{migrate_backend, MigrateProgress, MigrateReport};
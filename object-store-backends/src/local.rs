@@ -1,290 +1,1870 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
 use async_trait::async_trait;
-use chrono::Utc;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
 use futures::StreamExt;
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::{Mutex, OwnedMutexGuard, RwLock};
 use tokio_util::io::ReaderStream;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
-use crate::backend::{Backend, ByteStream, ObjectData, ObjectMetadata, PublicUrlPurpose};
+use crate::backend::{
+    slice_stream, Backend, ByteStream, CompactReport, ObjectData, ObjectMetadata,
+    ObjectMetadataSummary, PublicUrlPurpose,
+};
 use crate::error::{BackendError, BackendResult};
 
-pub struct LocalBackend {
-    root_path: PathBuf,
-    bucket_name: String,
+const MAX_TRACKED_KEY_LOCKS: usize = 1024;
+
+/// Ends the name of the file an upload is written to before it replaces the
+/// object. See [`upload_temp_path`].
+const UPLOAD_TEMP_SUFFIX: &str = ".tmp";
+
+/// Files in the rotation directory: the re-encrypted data file being staged,
+/// and the [`PendingSidecar`] to install once it has been renamed into place.
+const ROTATION_STAGED_DATA: &str = "object";
+const ROTATION_PENDING_SIDECAR: &str = "sidecar.json";
+
+const XATTR_ETAG: &str = "user.object.etag";
+const XATTR_SIZE: &str = "user.object.size";
+const XATTR_CONTENT_TYPE: &str = "user.object.content_type";
+const XATTR_LAST_MODIFIED: &str = "user.object.last_modified";
+const XATTR_HAS_CUSTOM_METADATA: &str = "user.object.has_custom_metadata";
+const XATTRS: [&str; 5] = [
+    XATTR_ETAG,
+    XATTR_SIZE,
+    XATTR_CONTENT_TYPE,
+    XATTR_LAST_MODIFIED,
+    XATTR_HAS_CUSTOM_METADATA,
+];
+
+/// Encrypted objects are written as frames of at most this much plaintext, so
+/// reads never buffer more than one frame however the upload was chunked.
+const ENCRYPTED_FRAME_SIZE: usize = 64 * 1024;
+const GCM_TAG_LEN: usize = 16;
+const FRAME_DATA: u8 = 0;
+const FRAME_FINAL: u8 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalBackendConfig {
+    /// Also keep the hot metadata fields in extended attributes on the data
+    /// file, so `head_object` and listings can skip reading the `.meta.json`
+    /// sidecar for objects without custom metadata. The sidecar is always
+    /// written and is used whenever the attributes are missing, e.g. on
+    /// filesystems without `user.*` xattr support.
+    #[serde(default)]
+    pub use_xattrs: bool,
+    /// Seconds a `list_objects` result is served from memory instead of walking
+    /// the directory tree; 0 disables the cache. Writes through this backend
+    /// invalidate the affected listings right away, files changed on disk by
+    /// anything else show up once the entry expires.
+    #[serde(default)]
+    pub list_cache_ttl_secs: u64,
+    /// Most listings kept, evicting the least recently used.
+    #[serde(default = "default_list_cache_capacity")]
+    pub list_cache_capacity: usize,
+    /// Objects whose metadata is kept in memory, evicting the least recently
+    /// used; 0, the default, disables the cache. An entry is used while the
+    /// data file keeps the size and modification time it had when the sidecar
+    /// was read, so `head_object` costs one `stat` instead of reading the
+    /// sidecar. Not used with encryption.
+    #[serde(default)]
+    pub metadata_cache_capacity: usize,
+    /// Mode for every directory the backend creates, including the bucket
+    /// directory, e.g. `0o700`. Still subject to the process umask; ignored
+    /// with a warning on non-Unix targets. Strings are read as octal.
+    #[serde(default, deserialize_with = "deserialize_dir_permissions")]
+    pub dir_permissions: Option<u32>,
+    /// Makes [`Backend::init`] fail with a configuration error instead of
+    /// starting on a root or bucket path that is a symlink or not a directory,
+    /// or a bucket directory the process cannot write to.
+    #[serde(default)]
+    pub strict_init: bool,
+    /// With `strict_init`, the least free space the bucket directory's
+    /// filesystem must report at startup.
+    #[serde(default)]
+    pub min_free_bytes: Option<u64>,
 }
 
-impl LocalBackend {
-    pub fn new(root_path: PathBuf, bucket_name: String) -> Self {
-        Self {
-            root_path,
-            bucket_name,
+pub fn default_list_cache_capacity() -> usize {
+    256
+}
+
+/// Accepts a number, such as TOML's `0o700`, or an octal string like `"700"`
+/// or `"0o700"`, which is how the value arrives from an environment variable.
+pub fn deserialize_dir_permissions<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Mode {
+        Number(u32),
+        Octal(String),
+    }
+
+    match Option::<Mode>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Mode::Number(mode)) => Ok(Some(mode)),
+        Some(Mode::Octal(s)) => {
+            let digits = s.strip_prefix("0o").unwrap_or(&s);
+            u32::from_str_radix(digits, 8).map(Some).map_err(|_| {
+                serde::de::Error::custom(format!("invalid octal directory mode '{}'", s))
+            })
         }
     }
+}
 
-    fn get_full_path(&self, key: &str) -> BackendResult<PathBuf> {
-        if key.contains("..") || key.starts_with('/') {
-            return Err(BackendError::InvalidPath(format!("Invalid key: {}", key)));
+impl Default for LocalBackendConfig {
+    fn default() -> Self {
+        Self {
+            use_xattrs: false,
+            list_cache_ttl_secs: 0,
+            list_cache_capacity: default_list_cache_capacity(),
+            metadata_cache_capacity: 0,
+            dir_permissions: None,
+            strict_init: false,
+            min_free_bytes: None,
         }
+    }
+}
 
-        let path = self.root_path.join(&self.bucket_name).join(key);
-        Ok(path)
+/// AES-256-GCM encryption at rest, e.g. `encryption = { key_hex = "..." }`.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocalBackendEncryption {
+    /// The 32-byte key as 64 hex digits.
+    pub key_hex: String,
+}
+
+impl LocalBackendEncryption {
+    pub fn key(&self) -> BackendResult<EncryptionKey> {
+        EncryptionKey::from_hex(&self.key_hex)
     }
+}
 
-    fn get_metadata_path(&self, key: &str) -> BackendResult<PathBuf> {
-        let object_path = self.get_full_path(key)?;
-        Ok(object_path.with_extension("meta.json"))
+impl std::fmt::Debug for LocalBackendEncryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalBackendEncryption")
+            .field("key_hex", &"<redacted>")
+            .finish()
     }
+}
 
-    async fn read_metadata(&self, key: &str) -> BackendResult<ObjectMetadata> {
-        let meta_path = self.get_metadata_path(key)?;
+/// An AES-256-GCM key. Encrypted sidecars record its id, a truncated SHA-256
+/// of the key, so reading with the wrong key fails as such rather than as
+/// corrupt data.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    id: String,
+    cipher: Aes256Gcm,
+}
 
-        if !meta_path.exists() {
-            return Err(BackendError::NotFound(key.to_string()));
+impl EncryptionKey {
+    pub fn from_bytes(key: &[u8; 32]) -> Self {
+        Self {
+            id: hex::encode(&Sha256::digest(key)[..8]),
+            cipher: Aes256Gcm::new(key.into()),
         }
+    }
 
-        let content = fs::read_to_string(&meta_path).await?;
-        let metadata: ObjectMetadata = serde_json::from_str(&content)?;
-        Ok(metadata)
+    pub fn from_hex(key_hex: &str) -> BackendResult<Self> {
+        let bytes = hex::decode(key_hex.trim()).map_err(|_| {
+            BackendError::Configuration("Encryption key is not valid hex".to_string())
+        })?;
+        let key: [u8; 32] = bytes.as_slice().try_into().map_err(|_| {
+            BackendError::Configuration(format!(
+                "Encryption key must be 32 bytes, got {}",
+                bytes.len()
+            ))
+        })?;
+        Ok(Self::from_bytes(&key))
     }
 
-    async fn write_metadata(&self, metadata: &ObjectMetadata) -> BackendResult<()> {
-        let meta_path = self.get_metadata_path(&metadata.key)?;
+    pub fn id(&self) -> &str {
+        &self.id
+    }
 
-        if let Some(parent) = meta_path.parent() {
-            fs::create_dir_all(parent).await?;
-        }
+    fn seal_frame(&self, nonce: &[u8; 12], index: u64, flag: u8, plaintext: &[u8]) -> Vec<u8> {
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &frame_nonce(nonce, index),
+                Payload {
+                    msg: plaintext,
+                    aad: &[flag],
+                },
+            )
+            .expect("AES-GCM encryption of a bounded frame cannot fail");
 
-        let content = serde_json::to_string_pretty(metadata)?;
-        fs::write(&meta_path, content).await?;
-        Ok(())
+        let mut frame = Vec::with_capacity(5 + ciphertext.len());
+        frame.push(flag);
+        frame.extend_from_slice(&(ciphertext.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&ciphertext);
+        frame
+    }
+
+    fn open_frame(
+        &self,
+        nonce: &[u8; 12],
+        index: u64,
+        flag: u8,
+        ciphertext: &[u8],
+    ) -> Option<Vec<u8>> {
+        self.cipher
+            .decrypt(
+                &frame_nonce(nonce, index),
+                Payload {
+                    msg: ciphertext,
+                    aad: &[flag],
+                },
+            )
+            .ok()
     }
 }
 
-#[async_trait]
-impl Backend for LocalBackend {
-    async fn init(&self) -> BackendResult<()> {
-        let bucket_path = self.root_path.join(&self.bucket_name);
-        fs::create_dir_all(&bucket_path).await?;
-        info!("Initialized local backend at {:?}", bucket_path);
-        Ok(())
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptionKey")
+            .field("id", &self.id)
+            .finish()
     }
+}
 
-    async fn put_object(
-        &self,
-        key: &str,
-        mut stream: ByteStream,
-        content_type: Option<String>,
-        custom_metadata: HashMap<String, String>,
-    ) -> BackendResult<ObjectMetadata> {
-        debug!("Putting object: {}", key);
+/// Each frame of an object gets its own nonce: the object's random nonce with
+/// the frame index mixed into its last 8 bytes.
+fn frame_nonce(nonce: &[u8; 12], index: u64) -> Nonce<<Aes256Gcm as AeadCore>::NonceSize> {
+    let mut frame_nonce = *nonce;
+    for (byte, counter) in frame_nonce[4..].iter_mut().zip(index.to_be_bytes()) {
+        *byte ^= counter;
+    }
+    frame_nonce.into()
+}
 
-        let object_path = self.get_full_path(key)?;
+fn random_nonce() -> [u8; 12] {
+    Aes256Gcm::generate_nonce(&mut OsRng).into()
+}
 
-        if let Some(parent) = object_path.parent() {
-            fs::create_dir_all(parent).await?;
-        }
+/// The keys in use. `previous` is only set while [`LocalBackend::rotate_key`]
+/// runs, for the objects it has not rewritten yet.
+#[derive(Clone)]
+struct EncryptionKeys {
+    current: Arc<EncryptionKey>,
+    previous: Option<Arc<EncryptionKey>>,
+}
 
-        let mut file = fs::File::create(&object_path).await?;
-        let mut hasher = Sha256::new();
-        let mut total_size = 0u64;
+impl EncryptionKeys {
+    fn find(&self, id: &str) -> Option<&Arc<EncryptionKey>> {
+        std::iter::once(&self.current)
+            .chain(self.previous.as_ref())
+            .find(|key| key.id == id)
+    }
+}
 
-        // Stream data to file while computing hash
-        while let Some(chunk_result) = stream.next().await {
-            let chunk = chunk_result
-                .map_err(|e| BackendError::Provider(format!("Failed to read stream: {}", e)))?;
+/// The key and nonce an object's data was encrypted with.
+type ObjectEncryption = (Arc<EncryptionKey>, [u8; 12]);
 
-            hasher.update(&chunk);
-            total_size += chunk.len() as u64;
+/// The sidecar describing a re-encrypted data file, written durably before
+/// the file replaces the object so a crash in between can be finished.
+#[derive(Serialize, Deserialize)]
+struct PendingSidecar {
+    key: String,
+    sidecar: String,
+}
 
-            file.write_all(&chunk).await?;
-        }
+/// A `.meta.json` sidecar of an encrypted backend. The ciphertext holds a
+/// [`StoredMetadata`].
+#[derive(Serialize, Deserialize)]
+struct EncryptedSidecar {
+    key_id: String,
+    nonce: String,
+    ciphertext: String,
+}
 
-        file.sync_all().await?;
+#[derive(Serialize, Deserialize)]
+struct StoredMetadata {
+    #[serde(flatten)]
+    metadata: ObjectMetadata,
+    /// Hex nonce the data file was encrypted with.
+    encryption_nonce: String,
+}
 
-        let etag = hex::encode(hasher.finalize());
+/// Reads a sidecar in either format. Plaintext sidecars, from before
+/// encryption was enabled, describe plaintext data.
+fn decode_sidecar(
+    keys: Option<&EncryptionKeys>,
+    content: &str,
+) -> BackendResult<(ObjectMetadata, Option<ObjectEncryption>)> {
+    let value: serde_json::Value = serde_json::from_str(content)?;
+    if value.get("ciphertext").is_none() {
+        return Ok((serde_json::from_value(value)?, None));
+    }
 
-        let metadata = ObjectMetadata {
-            key: key.to_string(),
-            size: total_size,
-            content_type,
-            etag: etag.clone(),
-            last_modified: Utc::now(),
-            custom_metadata,
-        };
+    let sidecar: EncryptedSidecar = serde_json::from_value(value)?;
+    let keys = keys.ok_or_else(|| {
+        BackendError::Configuration(
+            "Object metadata is encrypted but no encryption key is configured".to_string(),
+        )
+    })?;
+    let key = keys.find(&sidecar.key_id).ok_or_else(|| {
+        BackendError::Configuration(format!(
+            "Object metadata is encrypted with key {}, not the configured key",
+            sidecar.key_id
+        ))
+    })?;
+
+    let decrypt_error = || BackendError::Internal("Failed to decrypt object metadata".to_string());
+    let nonce = decode_nonce(&sidecar.nonce).ok_or_else(decrypt_error)?;
+    let ciphertext = hex::decode(&sidecar.ciphertext).map_err(|_| decrypt_error())?;
+    let plaintext = key
+        .cipher
+        .decrypt(&nonce.into(), ciphertext.as_slice())
+        .map_err(|_| decrypt_error())?;
+
+    let stored: StoredMetadata = serde_json::from_slice(&plaintext)?;
+    let data_nonce = decode_nonce(&stored.encryption_nonce).ok_or_else(decrypt_error)?;
+    Ok((stored.metadata, Some((key.clone(), data_nonce))))
+}
 
-        self.write_metadata(&metadata).await?;
+fn decode_nonce(nonce_hex: &str) -> Option<[u8; 12]> {
+    hex::decode(nonce_hex).ok()?.try_into().ok()
+}
 
-        info!(
-            "Object stored: {} (etag: {}, {} bytes)",
-            key, etag, total_size
-        );
-        Ok(metadata)
-    }
+fn encode_sidecar(
+    metadata: &ObjectMetadata,
+    encryption: Option<(&EncryptionKey, &[u8; 12])>,
+) -> BackendResult<String> {
+    let Some((key, data_nonce)) = encryption else {
+        return Ok(serde_json::to_string_pretty(metadata)?);
+    };
+
+    let plaintext = serde_json::to_vec(&StoredMetadata {
+        metadata: metadata.clone(),
+        encryption_nonce: hex::encode(data_nonce),
+    })?;
+    let nonce = random_nonce();
+    let ciphertext = key
+        .cipher
+        .encrypt(&nonce.into(), plaintext.as_slice())
+        .map_err(|_| BackendError::Internal("Failed to encrypt object metadata".to_string()))?;
+
+    Ok(serde_json::to_string_pretty(&EncryptedSidecar {
+        key_id: key.id.clone(),
+        nonce: hex::encode(nonce),
+        ciphertext: hex::encode(ciphertext),
+    })?)
+}
 
-    async fn get_object(&self, key: &str) -> BackendResult<ObjectData> {
-        debug!("Getting object: {}", key);
+/// Writes a stream as encrypted frames, ending with an empty final frame so a
+/// truncated file is detected on read.
+struct FrameWriter<'a> {
+    key: &'a EncryptionKey,
+    nonce: [u8; 12],
+    index: u64,
+}
 
-        let object_path = self.get_full_path(key)?;
+impl<'a> FrameWriter<'a> {
+    fn new(key: &'a EncryptionKey) -> Self {
+        Self {
+            key,
+            nonce: random_nonce(),
+            index: 0,
+        }
+    }
 
-        if !object_path.exists() {
-            return Err(BackendError::NotFound(key.to_string()));
+    async fn write(&mut self, file: &mut fs::File, chunk: &[u8]) -> std::io::Result<()> {
+        for plaintext in chunk.chunks(ENCRYPTED_FRAME_SIZE) {
+            let frame = self
+                .key
+                .seal_frame(&self.nonce, self.index, FRAME_DATA, plaintext);
+            self.index += 1;
+            file.write_all(&frame).await?;
         }
+        Ok(())
+    }
 
-        let file = fs::File::open(&object_path).await?;
-        let metadata = self.read_metadata(key).await?;
+    async fn finish(self, file: &mut fs::File) -> std::io::Result<[u8; 12]> {
+        let frame = self
+            .key
+            .seal_frame(&self.nonce, self.index, FRAME_FINAL, &[]);
+        file.write_all(&frame).await?;
+        Ok(self.nonce)
+    }
+}
 
-        // Convert file to stream
-        let stream: ByteStream =
-            Box::pin(ReaderStream::new(file).map(|result| result.map_err(std::io::Error::other)));
+/// Decrypts a data file written by [`FrameWriter`] as it is read.
+fn decrypt_stream(file: fs::File, encryption: ObjectEncryption) -> ByteStream {
+    let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+    let reader = tokio::io::BufReader::new(file);
+
+    Box::pin(futures::stream::try_unfold(
+        (reader, 0u64, encryption),
+        move |(mut reader, index, (key, nonce))| async move {
+            let flag = match reader.read_u8().await {
+                Ok(flag) => flag,
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    return Err(invalid("encrypted object is truncated"));
+                }
+                Err(e) => return Err(e),
+            };
+            let len = reader.read_u32().await? as usize;
+            if len > ENCRYPTED_FRAME_SIZE + GCM_TAG_LEN {
+                return Err(invalid("encrypted object has an oversized frame"));
+            }
+            let mut ciphertext = vec![0; len];
+            reader.read_exact(&mut ciphertext).await?;
+
+            let plaintext = key
+                .open_frame(&nonce, index, flag, &ciphertext)
+                .ok_or_else(|| invalid("failed to decrypt object data"))?;
+            if flag == FRAME_FINAL {
+                if reader.read_u8().await.is_ok() {
+                    return Err(invalid("encrypted object has data after its final frame"));
+                }
+                return Ok(None);
+            }
+            Ok(Some((
+                Bytes::from(plaintext),
+                (reader, index + 1, (key, nonce)),
+            )))
+        },
+    ))
+}
 
-        Ok(ObjectData { metadata, stream })
-    }
+type ListCacheKey = (String, Option<usize>);
 
-    async fn head_object(&self, key: &str) -> BackendResult<ObjectMetadata> {
-        debug!("Getting object metadata: {}", key);
-        self.read_metadata(key).await
+/// Recent `list_objects` results, keyed on prefix and `max_keys`.
+struct ListCache {
+    entries: LruCache<ListCacheKey, (Vec<ObjectMetadata>, Instant)>,
+    ttl: Duration,
+    /// Bumped by every invalidation. A listing is only stored if no write
+    /// finished while it was walking the tree, since it may have missed it.
+    generation: u64,
+}
+
+impl ListCache {
+    fn new(ttl: Duration, capacity: NonZeroUsize) -> Self {
+        Self {
+            entries: LruCache::new(capacity),
+            ttl,
+            generation: 0,
+        }
     }
 
-    async fn delete_object(&self, key: &str) -> BackendResult<()> {
-        debug!("Deleting object: {}", key);
+    fn get(&mut self, key: &ListCacheKey) -> Option<Vec<ObjectMetadata>> {
+        match self.entries.get(key) {
+            Some((objects, cached_at)) if cached_at.elapsed() < self.ttl => Some(objects.clone()),
+            Some(_) => {
+                self.entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
 
-        let object_path = self.get_full_path(key)?;
-        let meta_path = self.get_metadata_path(key)?;
+    fn insert(&mut self, key: ListCacheKey, objects: Vec<ObjectMetadata>, generation: u64) {
+        if generation == self.generation {
+            self.entries.put(key, (objects, Instant::now()));
+        }
+    }
 
-        if !object_path.exists() {
-            return Err(BackendError::NotFound(key.to_string()));
+    /// Drops every listing whose prefix covers `changed_key`.
+    fn invalidate(&mut self, changed_key: &str) {
+        self.generation += 1;
+        let stale: Vec<ListCacheKey> = self
+            .entries
+            .iter()
+            .filter(|((prefix, _), _)| changed_key.starts_with(prefix.as_str()))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in stale {
+            self.entries.pop(&key);
         }
+    }
+}
 
-        fs::remove_file(&object_path).await?;
+/// Size and modification time of a data file when its sidecar was read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
 
-        if meta_path.exists() {
-            fs::remove_file(&meta_path).await?;
+impl FileStamp {
+    fn of(stat: &std::fs::Metadata) -> Self {
+        Self {
+            len: stat.len(),
+            modified: stat.modified().ok(),
         }
-
-        info!("Object deleted: {}", key);
-        Ok(())
     }
+}
 
-    async fn list_objects(
-        &self,
-        prefix: Option<&str>,
-        max_keys: Option<usize>,
-    ) -> BackendResult<Vec<ObjectMetadata>> {
-        debug!("Listing objects with prefix: {:?}", prefix);
+/// Recently read object metadata, keyed on object key.
+struct MetadataCache {
+    entries: LruCache<String, (ObjectMetadata, FileStamp)>,
+    /// Bumped by every invalidation, like [`ListCache::generation`], so a read
+    /// racing a write can't store the sidecar the write replaced.
+    generation: u64,
+}
 
-        let bucket_path = self.root_path.join(&self.bucket_name);
-        let mut results = Vec::new();
+impl MetadataCache {
+    fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            entries: LruCache::new(capacity),
+            generation: 0,
+        }
+    }
 
-        let prefix_str = prefix.unwrap_or("");
-        let search_path = if prefix_str.is_empty() {
-            bucket_path.clone()
-        } else {
-            bucket_path.join(prefix_str)
-        };
+    fn get(&mut self, key: &str, stamp: FileStamp) -> Option<ObjectMetadata> {
+        match self.entries.get(key) {
+            Some((metadata, cached)) if *cached == stamp => Some(metadata.clone()),
+            Some(_) => {
+                self.entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
 
-        self.list_recursive(
-            &bucket_path,
-            &search_path,
-            prefix_str,
-            &mut results,
-            max_keys,
-        )
-        .await?;
+    fn insert(&mut self, key: &str, metadata: ObjectMetadata, stamp: FileStamp, generation: u64) {
+        if generation == self.generation {
+            self.entries.put(key.to_string(), (metadata, stamp));
+        }
+    }
 
-        Ok(results)
+    fn invalidate(&mut self, changed_key: &str) {
+        self.generation += 1;
+        self.entries.pop(changed_key);
     }
+}
 
-    async fn get_public_url(
-        &self,
-        _key: &str,
-        _expiration_secs: u64,
-        _purpose: PublicUrlPurpose,
-    ) -> BackendResult<String> {
-        Err(BackendError::Provider(
-            "Public URL generation is not supported for local backend".to_string(),
-        ))
+/// Invalidates cached listings covering `key`, and its cached metadata, when
+/// dropped, so a write that fails halfway through still invalidates them.
+struct InvalidateCaches<'a> {
+    backend: &'a LocalBackend,
+    key: &'a str,
+}
+
+impl Drop for InvalidateCaches<'_> {
+    fn drop(&mut self) {
+        if let Some(cache) = &self.backend.list_cache {
+            cache.lock().unwrap().invalidate(self.key);
+        }
+        if let Some(cache) = &self.backend.metadata_cache {
+            cache.lock().unwrap().invalidate(self.key);
+        }
     }
 }
 
-impl LocalBackend {
-    fn list_recursive<'a>(
-        &'a self,
-        bucket_path: &'a Path,
-        current_path: &'a Path,
-        prefix: &'a str,
-        results: &'a mut Vec<ObjectMetadata>,
-        max_keys: Option<usize>,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = BackendResult<()>> + Send + 'a>> {
-        Box::pin(async move {
-            if let Some(max) = max_keys {
-                if results.len() >= max {
-                    return Ok(());
-                }
-            }
+struct KeyLockEntry {
+    lock: Arc<Mutex<()>>,
+    last_used: Instant,
+}
 
-            if !current_path.exists() {
-                return Ok(());
-            }
+/// Per-key write locks, created lazily and dropped once no writer holds or
+/// waits on them. Idle entries are evicted oldest-first past `capacity`.
+struct KeyLocks {
+    locks: DashMap<String, KeyLockEntry>,
+    capacity: usize,
+}
 
-            if current_path.is_file() {
-                if current_path.extension().and_then(|s| s.to_str()) == Some("json") {
-                    return Ok(());
-                }
+struct KeyLockGuard<'a> {
+    locks: &'a KeyLocks,
+    key: String,
+    _guard: OwnedMutexGuard<()>,
+}
 
-                if let Ok(relative) = current_path.strip_prefix(bucket_path) {
-                    let key = relative.to_string_lossy().to_string();
+impl KeyLocks {
+    fn new(capacity: usize) -> Self {
+        Self {
+            locks: DashMap::new(),
+            capacity,
+        }
+    }
 
-                    if key.starts_with(prefix) {
-                        if let Ok(metadata) = self.read_metadata(&key).await {
-                            results.push(metadata);
-                        }
-                    }
-                }
-                return Ok(());
-            }
+    async fn acquire(&self, key: &str) -> KeyLockGuard<'_> {
+        if self.locks.len() >= self.capacity && !self.locks.contains_key(key) {
+            self.evict_idle();
+        }
 
-            let mut entries = fs::read_dir(current_path).await?;
-            while let Some(entry) = entries.next_entry().await? {
-                let path = entry.path();
+        let lock = {
+            let mut entry = self
+                .locks
+                .entry(key.to_string())
+                .or_insert_with(|| KeyLockEntry {
+                    lock: Arc::new(Mutex::new(())),
+                    last_used: Instant::now(),
+                });
+            entry.last_used = Instant::now();
+            entry.lock.clone()
+        };
 
-                if path.is_dir() {
-                    self.list_recursive(bucket_path, &path, prefix, results, max_keys)
-                        .await?;
-                } else if !path.to_string_lossy().ends_with(".meta.json") {
-                    if let Some(max) = max_keys {
-                        if results.len() >= max {
-                            break;
-                        }
-                    }
+        KeyLockGuard {
+            locks: self,
+            key: key.to_string(),
+            _guard: lock.lock_owned().await,
+        }
+    }
 
-                    if let Ok(relative) = path.strip_prefix(bucket_path) {
-                        let key = relative.to_string_lossy().to_string();
+    fn evict_idle(&self) {
+        let mut idle: Vec<(String, Instant)> = self
+            .locks
+            .iter()
+            .filter(|entry| Arc::strong_count(&entry.lock) == 1)
+            .map(|entry| (entry.key().clone(), entry.last_used))
+            .collect();
+        idle.sort_by_key(|(_, last_used)| *last_used);
+
+        let excess = (self.locks.len() + 1).saturating_sub(self.capacity);
+        for (key, _) in idle.into_iter().take(excess) {
+            self.locks
+                .remove_if(&key, |_, entry| Arc::strong_count(&entry.lock) == 1);
+        }
+    }
 
-                        if key.starts_with(prefix) {
-                            if let Ok(metadata) = self.read_metadata(&key).await {
-                                results.push(metadata);
-                            }
-                        }
-                    }
-                }
-            }
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.locks.len()
+    }
+}
 
-            Ok(())
-        })
+impl Drop for KeyLockGuard<'_> {
+    fn drop(&mut self) {
+        // The map and this guard each hold one reference; anything more means
+        // another writer is waiting and still needs the entry.
+        self.locks
+            .locks
+            .remove_if(&self.key, |_, entry| Arc::strong_count(&entry.lock) <= 2);
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use bytes::Bytes;
-    use tempfile::TempDir;
+pub struct LocalBackend {
+    root_path: PathBuf,
+    bucket_name: String,
+    config: LocalBackendConfig,
+    key_locks: KeyLocks,
+    list_cache: Option<std::sync::Mutex<ListCache>>,
+    metadata_cache: Option<std::sync::Mutex<MetadataCache>>,
+    /// Writes hold a read lock for their whole duration, so a key rotation
+    /// switching keys waits for uploads already using the old one.
+    encryption: RwLock<Option<EncryptionKeys>>,
+}
 
-    #[tokio::test]
+impl LocalBackend {
+    pub fn new(root_path: PathBuf, bucket_name: String) -> Self {
+        Self::new_with_config(root_path, bucket_name, LocalBackendConfig::default())
+    }
+
+    /// Creates every directory, starting with the bucket directory in
+    /// [`Backend::init`], with `dir_mode`.
+    pub fn new_with_permissions(root_path: PathBuf, bucket_name: String, dir_mode: u32) -> Self {
+        Self::new_with_config(
+            root_path,
+            bucket_name,
+            LocalBackendConfig {
+                dir_permissions: Some(dir_mode),
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn new_with_config(
+        root_path: PathBuf,
+        bucket_name: String,
+        config: LocalBackendConfig,
+    ) -> Self {
+        #[cfg(not(unix))]
+        if let Some(mode) = config.dir_permissions {
+            warn!(
+                "Ignoring dir_permissions {:o}: directory modes are only supported on Unix",
+                mode
+            );
+        }
+
+        let list_cache = NonZeroUsize::new(config.list_cache_capacity)
+            .filter(|_| config.list_cache_ttl_secs > 0)
+            .map(|capacity| {
+                std::sync::Mutex::new(ListCache::new(
+                    Duration::from_secs(config.list_cache_ttl_secs),
+                    capacity,
+                ))
+            });
+        let metadata_cache = NonZeroUsize::new(config.metadata_cache_capacity)
+            .map(|capacity| std::sync::Mutex::new(MetadataCache::new(capacity)));
+        Self {
+            root_path,
+            bucket_name,
+            config,
+            key_locks: KeyLocks::new(MAX_TRACKED_KEY_LOCKS),
+            list_cache,
+            metadata_cache,
+            encryption: RwLock::new(None),
+        }
+    }
+
+    /// Encrypts object data and `.meta.json` sidecars with AES-256-GCM. Objects
+    /// stored before encryption was enabled stay readable as they are.
+    /// Extended attributes would keep metadata in the clear, so `use_xattrs` is
+    /// ignored.
+    pub fn with_encryption(self, key: EncryptionKey) -> Self {
+        if self.config.use_xattrs {
+            warn!("Ignoring use_xattrs: metadata is only stored encrypted");
+        }
+        Self {
+            encryption: RwLock::new(Some(EncryptionKeys {
+                current: Arc::new(key),
+                previous: None,
+            })),
+            ..self
+        }
+    }
+
+    /// Re-encrypts every object encrypted with `old_key`, which must be the
+    /// backend's key, with `new_key` and returns how many were rewritten.
+    /// Writes use `new_key` as soon as the rotation starts, and objects not yet
+    /// rewritten stay readable until it finishes. A rotation that fails partway
+    /// can be finished by calling this again with the same keys; an object cut
+    /// off between its data and sidecar being replaced is completed by the
+    /// next call or by [`Backend::init`].
+    pub async fn rotate_key(
+        &self,
+        old_key: EncryptionKey,
+        new_key: EncryptionKey,
+    ) -> BackendResult<usize> {
+        let old_key = Arc::new(old_key);
+        let new_key = Arc::new(new_key);
+        {
+            let mut keys = self.encryption.write().await;
+            let Some(keys) = keys.as_mut() else {
+                return Err(BackendError::Configuration(
+                    "Key rotation needs encryption to be configured".to_string(),
+                ));
+            };
+            let resuming = keys.current.id == new_key.id
+                && keys.previous.as_ref().is_some_and(|p| p.id == old_key.id);
+            if keys.current.id != old_key.id && !resuming {
+                return Err(BackendError::Configuration(
+                    "The old key is not the backend's encryption key".to_string(),
+                ));
+            }
+            *keys = EncryptionKeys {
+                current: new_key.clone(),
+                previous: Some(old_key.clone()),
+            };
+        }
+
+        self.recover_rotation().await?;
+        let staging_dir = self.rotation_dir();
+        self.create_dir_all(&staging_dir).await?;
+
+        let mut rotated = 0;
+        for path in self.bucket_files().await? {
+            if !path.to_string_lossy().ends_with(".meta.json") {
+                continue;
+            }
+            let key = match fs::read_to_string(&path).await {
+                Ok(content) => {
+                    match decode_sidecar(self.encryption_keys().await.as_ref(), &content) {
+                        Ok((metadata, Some((key, _)))) if key.id == old_key.id => metadata.key,
+                        Ok(_) => continue,
+                        Err(e) => {
+                            warn!("Skipping unreadable metadata file {:?}: {}", path, e);
+                            continue;
+                        }
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(BackendError::io(&path)(e)),
+            };
+
+            // Read again under the lock, the object may have been replaced
+            let _lock = self.key_locks.acquire(&key).await;
+            let (metadata, encryption) = match self.read_stored_metadata(&key).await {
+                Ok(stored) => stored,
+                Err(BackendError::NotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            let Some(encryption) = encryption.filter(|(key, _)| key.id == old_key.id) else {
+                continue;
+            };
+
+            let pending = self
+                .swap_in_rotated_data(&metadata, encryption, &new_key)
+                .await?;
+            self.install_pending_sidecar(&pending).await?;
+            rotated += 1;
+        }
+
+        let _ = fs::remove_dir_all(&staging_dir).await;
+        if let Some(keys) = self.encryption.write().await.as_mut() {
+            if keys.current.id == new_key.id {
+                keys.previous = None;
+            }
+        }
+
+        info!("Re-encrypted {} objects with key {}", rotated, new_key.id);
+        Ok(rotated)
+    }
+
+    fn rotation_dir(&self) -> PathBuf {
+        self.root_path
+            .join(format!(".{}.rotating", self.bucket_name))
+    }
+
+    /// Re-encrypts an object's data with `new_key` in the rotation directory,
+    /// records the matching sidecar there, and only then renames the data over
+    /// the object. The returned sidecar still has to be installed.
+    async fn swap_in_rotated_data(
+        &self,
+        metadata: &ObjectMetadata,
+        encryption: ObjectEncryption,
+        new_key: &EncryptionKey,
+    ) -> BackendResult<PendingSidecar> {
+        let staging_dir = self.rotation_dir();
+        let staging_path = staging_dir.join(ROTATION_STAGED_DATA);
+        let object_path = self.get_full_path(&metadata.key)?;
+        let source = fs::File::open(&object_path)
+            .await
+            .map_err(BackendError::io(&object_path))?;
+        let mut plaintext = decrypt_stream(source, encryption);
+        let mut staged = fs::File::create(&staging_path)
+            .await
+            .map_err(BackendError::io(&staging_path))?;
+        let mut writer = FrameWriter::new(new_key);
+        while let Some(chunk) = plaintext.next().await {
+            let chunk = chunk.map_err(BackendError::io(&object_path))?;
+            writer
+                .write(&mut staged, &chunk)
+                .await
+                .map_err(BackendError::io(&staging_path))?;
+        }
+        let nonce = writer
+            .finish(&mut staged)
+            .await
+            .map_err(BackendError::io(&staging_path))?;
+        staged
+            .sync_all()
+            .await
+            .map_err(BackendError::io(&staging_path))?;
+
+        let pending = PendingSidecar {
+            key: metadata.key.clone(),
+            sidecar: encode_sidecar(metadata, Some((new_key, &nonce)))?,
+        };
+        let pending_path = staging_dir.join(ROTATION_PENDING_SIDECAR);
+        let mut file = fs::File::create(&pending_path)
+            .await
+            .map_err(BackendError::io(&pending_path))?;
+        file.write_all(&serde_json::to_vec(&pending)?)
+            .await
+            .map_err(BackendError::io(&pending_path))?;
+        file.sync_all()
+            .await
+            .map_err(BackendError::io(&pending_path))?;
+
+        fs::rename(&staging_path, &object_path)
+            .await
+            .map_err(BackendError::io(&object_path))?;
+        Ok(pending)
+    }
+
+    /// Writes a re-encrypted object's sidecar over the old one, then drops the
+    /// record of it from the rotation directory.
+    async fn install_pending_sidecar(&self, pending: &PendingSidecar) -> BackendResult<()> {
+        let meta_path = self.get_metadata_path(&pending.key)?;
+        fs::write(&meta_path, &pending.sidecar)
+            .await
+            .map_err(BackendError::io(&meta_path))?;
+        let pending_path = self.rotation_dir().join(ROTATION_PENDING_SIDECAR);
+        fs::remove_file(&pending_path)
+            .await
+            .map_err(BackendError::io(&pending_path))
+    }
+
+    /// Finishes an object whose re-encrypted data was renamed into place but
+    /// whose sidecar was not written, which would otherwise describe the old
+    /// encryption. A staged data file still in the rotation directory never
+    /// replaced its object, so it and its sidecar are dropped.
+    async fn recover_rotation(&self) -> BackendResult<()> {
+        let staging_dir = self.rotation_dir();
+        let pending_path = staging_dir.join(ROTATION_PENDING_SIDECAR);
+        let staged_data = staging_dir.join(ROTATION_STAGED_DATA);
+        match fs::read(&pending_path).await {
+            Ok(content) if !fs::try_exists(&staged_data).await.unwrap_or(true) => {
+                let pending: PendingSidecar = serde_json::from_slice(&content)?;
+                let _lock = self.key_locks.acquire(&pending.key).await;
+                self.install_pending_sidecar(&pending).await?;
+                info!("Finished the interrupted re-encryption of {}", pending.key);
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(BackendError::io(&pending_path)(e)),
+        }
+
+        match fs::remove_dir_all(&staging_dir).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(BackendError::io(&staging_dir)(e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn encryption_keys(&self) -> Option<EncryptionKeys> {
+        self.encryption.read().await.clone()
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> BackendResult<()> {
+        #[cfg(unix)]
+        if let Some(mode) = self.config.dir_permissions {
+            use std::os::unix::fs::DirBuilderExt;
+
+            let mut builder = std::fs::DirBuilder::new();
+            builder.recursive(true).mode(mode);
+            let owned = path.to_path_buf();
+            return tokio::task::spawn_blocking(move || builder.create(owned))
+                .await
+                .map_err(|e| BackendError::Internal(format!("Directory task failed: {}", e)))?
+                .map_err(BackendError::io(path));
+        }
+
+        fs::create_dir_all(path)
+            .await
+            .map_err(BackendError::io(path))
+    }
+
+    fn get_full_path(&self, key: &str) -> BackendResult<PathBuf> {
+        if key.contains("..") || key.starts_with('/') {
+            return Err(BackendError::InvalidPath(format!("Invalid key: {}", key)));
+        }
+
+        let path = self.root_path.join(&self.bucket_name).join(key);
+        Ok(path)
+    }
+
+    fn get_metadata_path(&self, key: &str) -> BackendResult<PathBuf> {
+        let object_path = self.get_full_path(key)?;
+        Ok(object_path.with_extension("meta.json"))
+    }
+
+    async fn read_metadata(&self, key: &str) -> BackendResult<ObjectMetadata> {
+        Ok(self.read_stored_metadata(key).await?.0)
+    }
+
+    /// For summaries the size and modification time come from the data file
+    /// and the sidecar is only checked for, not parsed, so the ETag is left
+    /// empty unless extended attributes hold it. Encrypted files are larger
+    /// than the objects in them, so those are always read in full.
+    async fn read_listing_entry(&self, key: &str, summary: bool) -> BackendResult<ObjectMetadata> {
+        if !summary || self.encryption_keys().await.is_some() {
+            return self.read_metadata(key).await;
+        }
+
+        let object_path = self.get_full_path(key)?;
+        if self.config.use_xattrs {
+            let path = object_path.clone();
+            let owned_key = key.to_string();
+            let cached = tokio::task::spawn_blocking(move || read_xattrs(&path, owned_key)).await;
+            if let Ok(Some(metadata)) = cached {
+                return Ok(metadata);
+            }
+        }
+
+        // Objects without a sidecar are still being written, or were never finished
+        if !self.get_metadata_path(key)?.exists() {
+            return Err(BackendError::NotFound(key.to_string()));
+        }
+        let stat = fs::metadata(&object_path)
+            .await
+            .map_err(io_or_not_found(key, &object_path))?;
+        Ok(ObjectMetadata {
+            key: key.to_string(),
+            size: stat.len(),
+            content_type: None,
+            etag: String::new(),
+            last_modified: stat
+                .modified()
+                .map(DateTime::<Utc>::from)
+                .unwrap_or_else(|_| Utc::now()),
+            custom_metadata: HashMap::new(),
+        })
+    }
+
+    /// The object's metadata, and how its data is encrypted if it is.
+    async fn read_stored_metadata(
+        &self,
+        key: &str,
+    ) -> BackendResult<(ObjectMetadata, Option<ObjectEncryption>)> {
+        let keys = self.encryption_keys().await;
+        let Some(cache) = self.metadata_cache.as_ref().filter(|_| keys.is_none()) else {
+            return self.read_metadata_from_disk(key, keys).await;
+        };
+
+        let object_path = self.get_full_path(key)?;
+        let Ok(stat) = fs::metadata(&object_path).await else {
+            // Missing data files are reported by the uncached read
+            return self.read_metadata_from_disk(key, keys).await;
+        };
+        let stamp = FileStamp::of(&stat);
+        let generation = {
+            let mut cache = cache.lock().unwrap();
+            if let Some(metadata) = cache.get(key, stamp) {
+                return Ok((metadata, None));
+            }
+            cache.generation
+        };
+
+        let (metadata, encryption) = self.read_metadata_from_disk(key, keys).await?;
+        cache
+            .lock()
+            .unwrap()
+            .insert(key, metadata.clone(), stamp, generation);
+        Ok((metadata, encryption))
+    }
+
+    async fn read_metadata_from_disk(
+        &self,
+        key: &str,
+        keys: Option<EncryptionKeys>,
+    ) -> BackendResult<(ObjectMetadata, Option<ObjectEncryption>)> {
+        if self.config.use_xattrs && keys.is_none() {
+            let object_path = self.get_full_path(key)?;
+            let owned_key = key.to_string();
+            let cached =
+                tokio::task::spawn_blocking(move || read_xattrs(&object_path, owned_key)).await;
+            if let Ok(Some(metadata)) = cached {
+                return Ok((metadata, None));
+            }
+        }
+
+        let meta_path = self.get_metadata_path(key)?;
+        let content = fs::read_to_string(&meta_path)
+            .await
+            .map_err(io_or_not_found(key, &meta_path))?;
+        decode_sidecar(keys.as_ref(), &content)
+    }
+
+    /// Writes the sidecar, encrypted when the data was encrypted with `encryption`'s
+    /// key and nonce.
+    async fn write_metadata(
+        &self,
+        metadata: &ObjectMetadata,
+        encryption: Option<(&EncryptionKey, &[u8; 12])>,
+    ) -> BackendResult<()> {
+        let meta_path = self.get_metadata_path(&metadata.key)?;
+
+        if let Some(parent) = meta_path.parent() {
+            self.create_dir_all(parent).await?;
+        }
+
+        let content = encode_sidecar(metadata, encryption)?;
+        fs::write(&meta_path, content)
+            .await
+            .map_err(BackendError::io(&meta_path))?;
+
+        if self.config.use_xattrs && encryption.is_none() {
+            let object_path = self.get_full_path(&metadata.key)?;
+            let owned = metadata.clone();
+            let written = tokio::task::spawn_blocking(move || {
+                let result = write_xattrs(&object_path, &owned);
+                if result.is_err() {
+                    // Never leave a partial set behind; reads fall back to the sidecar
+                    for name in XATTRS {
+                        let _ = xattr::remove(&object_path, name);
+                    }
+                }
+                result
+            })
+            .await
+            .map_err(|e| BackendError::Internal(format!("xattr task failed: {}", e)))?;
+
+            if let Err(e) = written {
+                warn!(
+                    "Failed to write extended attributes for {}, using the JSON sidecar only: {}",
+                    metadata.key, e
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn write_xattrs(path: &Path, metadata: &ObjectMetadata) -> std::io::Result<()> {
+    xattr::set(path, XATTR_ETAG, metadata.etag.as_bytes())?;
+    xattr::set(path, XATTR_SIZE, metadata.size.to_string().as_bytes())?;
+    xattr::set(
+        path,
+        XATTR_CONTENT_TYPE,
+        metadata.content_type.as_deref().unwrap_or("").as_bytes(),
+    )?;
+    xattr::set(
+        path,
+        XATTR_LAST_MODIFIED,
+        metadata.last_modified.to_rfc3339().as_bytes(),
+    )?;
+    let has_custom = if metadata.custom_metadata.is_empty() {
+        "false"
+    } else {
+        "true"
+    };
+    xattr::set(path, XATTR_HAS_CUSTOM_METADATA, has_custom.as_bytes())
+}
+
+/// Rebuilds metadata from the data file's extended attributes. Returns `None`
+/// when any attribute is missing or unreadable, or when the object carries
+/// custom metadata, which only the JSON sidecar holds.
+fn read_xattrs(path: &Path, key: String) -> Option<ObjectMetadata> {
+    let get = |name: &str| -> Option<String> {
+        xattr::get(path, name)
+            .ok()
+            .flatten()
+            .and_then(|value| String::from_utf8(value).ok())
+    };
+
+    if get(XATTR_HAS_CUSTOM_METADATA)? != "false" {
+        return None;
+    }
+    let content_type = get(XATTR_CONTENT_TYPE)?;
+
+    Some(ObjectMetadata {
+        key,
+        size: get(XATTR_SIZE)?.parse().ok()?,
+        content_type: (!content_type.is_empty()).then_some(content_type),
+        etag: get(XATTR_ETAG)?,
+        last_modified: chrono::DateTime::parse_from_rfc3339(&get(XATTR_LAST_MODIFIED)?)
+            .ok()?
+            .with_timezone(&Utc),
+        custom_metadata: HashMap::new(),
+    })
+}
+
+/// For `map_err` on a file of `key`: a missing file means the object is
+/// missing, possibly because it was deleted concurrently, along with its
+/// directory.
+fn io_or_not_found(key: &str, path: &Path) -> impl FnOnce(std::io::Error) -> BackendError {
+    let key = key.to_string();
+    let io = BackendError::io(path.to_path_buf());
+    move |e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            BackendError::NotFound(key)
+        } else {
+            io(e)
+        }
+    }
+}
+
+/// Free space, in bytes, on the filesystem holding `path`.
+pub async fn check_available_space(path: &Path) -> BackendResult<u64> {
+    let owned = path.to_path_buf();
+    tokio::task::spawn_blocking(move || fs4::available_space(owned))
+        .await
+        .map_err(|e| BackendError::Internal(format!("Disk space task failed: {}", e)))?
+        .map_err(BackendError::io(path))
+}
+
+/// Removes the directories between `path` and `stop_at` that are left empty,
+/// innermost first. `stop_at` itself is kept.
+pub async fn cleanup_empty_parents(path: &Path, stop_at: &Path) -> BackendResult<()> {
+    let mut dir = path.parent();
+    while let Some(current) = dir {
+        if current == stop_at || !current.starts_with(stop_at) {
+            break;
+        }
+        match fs::remove_dir(current).await {
+            Ok(()) => {}
+            // Still holds other objects, or another delete got there first
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::DirectoryNotEmpty | std::io::ErrorKind::NotFound
+                ) =>
+            {
+                break
+            }
+            Err(e) => return Err(BackendError::io(current)(e)),
+        }
+        dir = current.parent();
+    }
+    Ok(())
+}
+
+// A missing path is fine: init creates it
+async fn check_directory(path: &Path) -> BackendResult<()> {
+    let metadata = match fs::symlink_metadata(path).await {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(BackendError::io(path)(e)),
+    };
+    if metadata.file_type().is_symlink() {
+        return Err(BackendError::Configuration(format!(
+            "{:?} is a symlink; point the backend at the directory itself",
+            path
+        )));
+    }
+    if !metadata.is_dir() {
+        return Err(BackendError::Configuration(format!(
+            "{:?} exists but is not a directory",
+            path
+        )));
+    }
+    Ok(())
+}
+
+async fn check_writable(dir: &Path) -> BackendResult<()> {
+    let canary = dir.join(format!(".init-canary-{}", std::process::id()));
+    fs::write(&canary, b"")
+        .await
+        .map_err(|e| BackendError::Configuration(format!("{:?} is not writable: {}", dir, e)))?;
+    fs::remove_file(&canary)
+        .await
+        .map_err(BackendError::io(&canary))
+}
+
+/// Where an upload to `object_path` is written before it is renamed into
+/// place: `.<name>.<uuid>.tmp` in the same directory, so the rename never
+/// crosses a filesystem.
+fn upload_temp_path(object_path: &Path) -> PathBuf {
+    let name = object_path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    object_path.with_file_name(format!(
+        ".{}.{}{}",
+        name,
+        uuid::Uuid::new_v4().simple(),
+        UPLOAD_TEMP_SUFFIX
+    ))
+}
+
+/// Whether `path` is an in-flight upload's temporary file rather than an
+/// object.
+fn is_upload_temp_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix('.'))
+        .and_then(|name| name.strip_suffix(UPLOAD_TEMP_SUFFIX))
+        .and_then(|name| name.rsplit_once('.'))
+        .is_some_and(|(_, id)| id.len() == 32 && uuid::Uuid::try_parse(id).is_ok())
+}
+
+async fn remove_upload_temp(temp_path: &Path) {
+    if let Err(e) = fs::remove_file(temp_path).await {
+        warn!("Failed to remove upload temp file {:?}: {}", temp_path, e);
+    }
+}
+
+/// Streams an upload into `file`, encrypting it when `frame_writer` is set,
+/// and syncs it. Returns the hash of the plaintext, its size and the nonce of
+/// an encrypted upload.
+async fn write_upload(
+    mut file: fs::File,
+    path: &Path,
+    stream: &mut ByteStream,
+    mut frame_writer: Option<FrameWriter<'_>>,
+) -> BackendResult<(Sha256, u64, Option<[u8; 12]>)> {
+    let mut hasher = Sha256::new();
+    let mut total_size = 0u64;
+
+    while let Some(chunk_result) = stream.next().await {
+        let chunk = chunk_result.map_err(|e| BackendError::BodyStream(e.to_string()))?;
+
+        hasher.update(&chunk);
+        total_size += chunk.len() as u64;
+
+        match &mut frame_writer {
+            Some(writer) => writer.write(&mut file, &chunk).await,
+            None => file.write_all(&chunk).await,
+        }
+        .map_err(BackendError::io(path))?;
+    }
+
+    let nonce = match frame_writer {
+        Some(writer) => Some(
+            writer
+                .finish(&mut file)
+                .await
+                .map_err(BackendError::io(path))?,
+        ),
+        None => None,
+    };
+    file.sync_all().await.map_err(BackendError::io(path))?;
+    Ok((hasher, total_size, nonce))
+}
+
+#[async_trait]
+impl Backend for LocalBackend {
+    fn backend_type(&self) -> &'static str {
+        "local"
+    }
+
+    async fn init(&self) -> BackendResult<()> {
+        let bucket_path = self.root_path.join(&self.bucket_name);
+        if self.config.strict_init {
+            check_directory(&self.root_path).await?;
+            check_directory(&bucket_path).await?;
+        }
+        self.create_dir_all(&bucket_path).await?;
+        self.recover_rotation().await?;
+        if self.config.strict_init {
+            check_writable(&bucket_path).await?;
+            if let Some(min_free_bytes) = self.config.min_free_bytes {
+                let available = check_available_space(&bucket_path).await?;
+                if available < min_free_bytes {
+                    return Err(BackendError::Configuration(format!(
+                        "{:?} has {} bytes free, less than min_free_bytes {}",
+                        bucket_path, available, min_free_bytes
+                    )));
+                }
+            }
+        }
+        info!("Initialized local backend at {:?}", bucket_path);
+        Ok(())
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        mut stream: ByteStream,
+        content_type: Option<String>,
+        custom_metadata: HashMap<String, String>,
+    ) -> BackendResult<ObjectMetadata> {
+        debug!("Putting object: {}", key);
+
+        let object_path = self.get_full_path(key)?;
+        let _lock = self.key_locks.acquire(key).await;
+        let _invalidate = InvalidateCaches { backend: self, key };
+        let keys = self.encryption.read().await;
+        let encryption_key = keys.as_ref().map(|keys| keys.current.clone());
+        let frame_writer = encryption_key.as_deref().map(FrameWriter::new);
+
+        if let Some(parent) = object_path.parent() {
+            self.create_dir_all(parent).await?;
+        }
+
+        // Written beside the object and renamed over it once complete, so a
+        // failed upload leaves the previous version in place
+        let temp_path = upload_temp_path(&object_path);
+        let file = match fs::File::create(&temp_path).await {
+            // A delete of the directory's last object removed it in between
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if let Some(parent) = object_path.parent() {
+                    self.create_dir_all(parent).await?;
+                }
+                fs::File::create(&temp_path).await
+            }
+            created => created,
+        }
+        .map_err(BackendError::io(&temp_path))?;
+
+        let written = write_upload(file, &temp_path, &mut stream, frame_writer).await;
+        let (hasher, total_size, nonce) = match written {
+            Ok(written) => written,
+            Err(e) => {
+                remove_upload_temp(&temp_path).await;
+                return Err(e);
+            }
+        };
+
+        // The temp file is a new inode, so extended attributes of the previous
+        // version go with it
+        if let Err(e) = fs::rename(&temp_path, &object_path).await {
+            remove_upload_temp(&temp_path).await;
+            return Err(BackendError::io(&object_path)(e));
+        }
+
+        let etag = hex::encode(hasher.finalize());
+
+        let metadata = ObjectMetadata {
+            key: key.to_string(),
+            size: total_size,
+            content_type,
+            etag: etag.clone(),
+            last_modified: Utc::now(),
+            custom_metadata,
+        };
+
+        self.write_metadata(&metadata, encryption_key.as_deref().zip(nonce.as_ref()))
+            .await?;
+
+        info!(
+            "Object stored: {} (etag: {}, {} bytes)",
+            key, etag, total_size
+        );
+        Ok(metadata)
+    }
+
+    async fn get_object(&self, key: &str) -> BackendResult<ObjectData> {
+        debug!("Getting object: {}", key);
+
+        let object_path = self.get_full_path(key)?;
+        let file = fs::File::open(&object_path)
+            .await
+            .map_err(io_or_not_found(key, &object_path))?;
+        let (metadata, encryption) = self.read_stored_metadata(key).await?;
+
+        // Convert file to stream
+        let stream: ByteStream = match encryption {
+            Some(encryption) => decrypt_stream(file, encryption),
+            None => Box::pin(
+                ReaderStream::new(file).map(|result| result.map_err(std::io::Error::other)),
+            ),
+        };
+
+        Ok(ObjectData { metadata, stream })
+    }
+
+    /// Seeks to `start` in plaintext files. Encrypted files are decrypted from
+    /// the first frame, since each frame's nonce depends on the ones before.
+    async fn get_object_range(&self, key: &str, start: u64, len: u64) -> BackendResult<ObjectData> {
+        debug!(
+            "Getting object range: {} ({} bytes from {})",
+            key, len, start
+        );
+
+        let object_path = self.get_full_path(key)?;
+        let mut file = fs::File::open(&object_path)
+            .await
+            .map_err(io_or_not_found(key, &object_path))?;
+        let (metadata, encryption) = self.read_stored_metadata(key).await?;
+
+        let stream: ByteStream = match encryption {
+            Some(encryption) => slice_stream(decrypt_stream(file, encryption), start, len),
+            None => {
+                file.seek(std::io::SeekFrom::Start(start))
+                    .await
+                    .map_err(BackendError::io(&object_path))?;
+                Box::pin(
+                    ReaderStream::new(file.take(len))
+                        .map(|result| result.map_err(std::io::Error::other)),
+                )
+            }
+        };
+
+        Ok(ObjectData { metadata, stream })
+    }
+
+    async fn head_object(&self, key: &str) -> BackendResult<ObjectMetadata> {
+        debug!("Getting object metadata: {}", key);
+        self.read_metadata(key).await
+    }
+
+    /// Checks for the data and metadata files without reading either. Like the
+    /// other existence checks here this stats inline: `tokio::fs::try_exists`
+    /// hops to the blocking pool, which costs more than the stat itself.
+    async fn object_exists(&self, key: &str) -> BackendResult<bool> {
+        let meta_path = self.get_metadata_path(key)?;
+        if !meta_path
+            .try_exists()
+            .map_err(BackendError::io(&meta_path))?
+        {
+            return Ok(false);
+        }
+        let object_path = self.get_full_path(key)?;
+        object_path
+            .try_exists()
+            .map_err(BackendError::io(&object_path))
+    }
+
+    async fn delete_object(&self, key: &str) -> BackendResult<()> {
+        debug!("Deleting object: {}", key);
+
+        let object_path = self.get_full_path(key)?;
+        let meta_path = self.get_metadata_path(key)?;
+        let _lock = self.key_locks.acquire(key).await;
+        let _invalidate = InvalidateCaches { backend: self, key };
+
+        fs::remove_file(&object_path)
+            .await
+            .map_err(io_or_not_found(key, &object_path))?;
+        match fs::remove_file(&meta_path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(BackendError::io(&meta_path)(e)),
+        }
+
+        // The object is gone either way, so a directory that can't be removed
+        // is only worth a warning
+        let bucket_path = self.root_path.join(&self.bucket_name);
+        if let Err(e) = cleanup_empty_parents(&object_path, &bucket_path).await {
+            warn!("Failed to remove empty directories above {}: {}", key, e);
+        }
+
+        info!("Object deleted: {}", key);
+        Ok(())
+    }
+
+    /// Moves the data file with a single `rename`, so it is never visible
+    /// under both keys or half-written under either. The sidecar is then
+    /// rewritten under the new key, since it records the key.
+    async fn rename_object(&self, from: &str, to: &str) -> BackendResult<()> {
+        debug!("Renaming object: {} -> {}", from, to);
+
+        let from_path = self.get_full_path(from)?;
+        let to_path = self.get_full_path(to)?;
+        if from == to {
+            return self.head_object(from).await.map(|_| ());
+        }
+        // Taken in key order, so renames in opposite directions can't deadlock
+        let (first, second) = if from < to { (from, to) } else { (to, from) };
+        let _first = self.key_locks.acquire(first).await;
+        let _second = self.key_locks.acquire(second).await;
+        let _invalidate_from = InvalidateCaches {
+            backend: self,
+            key: from,
+        };
+        let _invalidate_to = InvalidateCaches {
+            backend: self,
+            key: to,
+        };
+        // Held like a write, so a key rotation doesn't re-encrypt in between
+        let keys = self.encryption.read().await;
+
+        let (mut metadata, encryption) = self.read_metadata_from_disk(from, keys.clone()).await?;
+        metadata.key = to.to_string();
+
+        if let Some(parent) = to_path.parent() {
+            self.create_dir_all(parent).await?;
+        }
+        fs::rename(&from_path, &to_path)
+            .await
+            .map_err(io_or_not_found(from, &from_path))?;
+        self.write_metadata(
+            &metadata,
+            encryption
+                .as_ref()
+                .map(|(key, nonce)| (key.as_ref(), nonce)),
+        )
+        .await?;
+
+        let from_meta_path = self.get_metadata_path(from)?;
+        match fs::remove_file(&from_meta_path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(BackendError::io(&from_meta_path)(e)),
+        }
+        let bucket_path = self.root_path.join(&self.bucket_name);
+        if let Err(e) = cleanup_empty_parents(&from_path, &bucket_path).await {
+            warn!("Failed to remove empty directories above {}: {}", from, e);
+        }
+
+        info!("Object renamed: {} -> {}", from, to);
+        Ok(())
+    }
+
+    async fn list_objects(
+        &self,
+        prefix: Option<&str>,
+        max_keys: Option<usize>,
+    ) -> BackendResult<Vec<ObjectMetadata>> {
+        debug!("Listing objects with prefix: {:?}", prefix);
+
+        let prefix_str = prefix.unwrap_or("");
+        let cache_key = (prefix_str.to_string(), max_keys);
+        let generation = match &self.list_cache {
+            Some(cache) => {
+                let mut cache = cache.lock().unwrap();
+                if let Some(objects) = cache.get(&cache_key) {
+                    return Ok(objects);
+                }
+                Some(cache.generation)
+            }
+            None => None,
+        };
+
+        let bucket_path = self.root_path.join(&self.bucket_name);
+        let mut results = Vec::new();
+
+        let search_path = if prefix_str.is_empty() {
+            bucket_path.clone()
+        } else {
+            bucket_path.join(prefix_str)
+        };
+
+        self.list_recursive(
+            &bucket_path,
+            &search_path,
+            prefix_str,
+            &mut results,
+            max_keys,
+            false,
+        )
+        .await?;
+
+        if let (Some(cache), Some(generation)) = (&self.list_cache, generation) {
+            cache
+                .lock()
+                .unwrap()
+                .insert(cache_key, results.clone(), generation);
+        }
+        Ok(results)
+    }
+
+    async fn list_objects_metadata_only(
+        &self,
+        prefix: Option<&str>,
+        max_keys: Option<usize>,
+    ) -> BackendResult<Vec<ObjectMetadataSummary>> {
+        let prefix_str = prefix.unwrap_or("");
+        if let Some(cache) = &self.list_cache {
+            if let Some(objects) = cache
+                .lock()
+                .unwrap()
+                .get(&(prefix_str.to_string(), max_keys))
+            {
+                return Ok(objects.into_iter().map(Into::into).collect());
+            }
+        }
+
+        let bucket_path = self.root_path.join(&self.bucket_name);
+        let search_path = bucket_path.join(prefix_str);
+        let mut results = Vec::new();
+        self.list_recursive(
+            &bucket_path,
+            &search_path,
+            prefix_str,
+            &mut results,
+            max_keys,
+            true,
+        )
+        .await?;
+        Ok(results.into_iter().map(Into::into).collect())
+    }
+
+    async fn get_public_url(
+        &self,
+        _key: &str,
+        _expiration_secs: u64,
+        _purpose: PublicUrlPurpose,
+        _content_type: Option<&str>,
+    ) -> BackendResult<String> {
+        Err(BackendError::Provider(
+            "Public URL generation is not supported for local backend".to_string(),
+        ))
+    }
+
+    async fn health_check(&self) -> BackendResult<()> {
+        let bucket_path = self.root_path.join(&self.bucket_name);
+        let metadata = fs::metadata(&bucket_path)
+            .await
+            .map_err(BackendError::io(&bucket_path))?;
+        if !metadata.is_dir() {
+            return Err(BackendError::Configuration(format!(
+                "Storage path {} is not a directory",
+                bucket_path.display()
+            )));
+        }
+        Ok(())
+    }
+
+    async fn compact(&self, dry_run: bool) -> BackendResult<CompactReport> {
+        let bucket_path = self.root_path.join(&self.bucket_name);
+        let mut report = CompactReport::default();
+        let keys = self.encryption_keys().await;
+
+        for path in self.bucket_files().await? {
+            // Uploads in progress, or left by a crash before the rename
+            if is_upload_temp_file(&path) {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(&bucket_path) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().to_string();
+
+            // A metadata file names its object, so check that object's data;
+            // a data file's metadata path is derived from its key.
+            let (key, orphaned_data) = if relative.ends_with(".meta.json") {
+                match fs::read_to_string(&path)
+                    .await
+                    .ok()
+                    .and_then(|content| decode_sidecar(keys.as_ref(), &content).ok())
+                {
+                    Some((metadata, _)) => (metadata.key, false),
+                    None => {
+                        warn!(
+                            "Skipping unreadable metadata file during compaction: {}",
+                            relative
+                        );
+                        continue;
+                    }
+                }
+            } else {
+                (relative.clone(), true)
+            };
+
+            // Writers hold the key lock from the data write through the
+            // metadata write, so an in-flight upload never looks orphaned.
+            let _lock = self.key_locks.acquire(&key).await;
+            let orphaned = if orphaned_data {
+                !self.get_metadata_path(&key)?.exists()
+            } else {
+                !self.get_full_path(&key)?.exists()
+            };
+            if !orphaned {
+                continue;
+            }
+
+            let size = match fs::metadata(&path).await {
+                Ok(meta) => meta.len(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(BackendError::io(&path)(e)),
+            };
+            if !dry_run {
+                fs::remove_file(&path)
+                    .await
+                    .map_err(BackendError::io(&path))?;
+            }
+
+            report.reclaimed_bytes += size;
+            if orphaned_data {
+                report.orphaned_data.push(relative);
+            } else {
+                report.orphaned_meta.push(relative);
+            }
+        }
+
+        info!(
+            "Compaction {}: {} orphaned data files, {} orphaned metadata files, {} bytes",
+            if dry_run { "dry run" } else { "complete" },
+            report.orphaned_data.len(),
+            report.orphaned_meta.len(),
+            report.reclaimed_bytes
+        );
+        Ok(report)
+    }
+}
+
+impl LocalBackend {
+    /// Every file under the bucket directory, sorted by path.
+    async fn bucket_files(&self) -> BackendResult<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.root_path.join(&self.bucket_name)];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(BackendError::io(&dir)(e)),
+            };
+            while let Some(entry) = entries.next_entry().await.map_err(BackendError::io(&dir))? {
+                let file_type = entry
+                    .file_type()
+                    .await
+                    .map_err(BackendError::io(entry.path()))?;
+                if file_type.is_dir() {
+                    dirs.push(entry.path());
+                } else {
+                    files.push(entry.path());
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    fn list_recursive<'a>(
+        &'a self,
+        bucket_path: &'a Path,
+        current_path: &'a Path,
+        prefix: &'a str,
+        results: &'a mut Vec<ObjectMetadata>,
+        max_keys: Option<usize>,
+        summary: bool,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = BackendResult<()>> + Send + 'a>> {
+        Box::pin(async move {
+            if let Some(max) = max_keys {
+                if results.len() >= max {
+                    return Ok(());
+                }
+            }
+
+            if !current_path.exists() {
+                return Ok(());
+            }
+
+            if current_path.is_file() {
+                // Only sidecars and upload temp files are skipped; objects may
+                // be named `*.json`
+                if current_path.to_string_lossy().ends_with(".meta.json")
+                    || is_upload_temp_file(current_path)
+                {
+                    return Ok(());
+                }
+
+                if let Ok(relative) = current_path.strip_prefix(bucket_path) {
+                    let key = relative.to_string_lossy().to_string();
+
+                    if key.starts_with(prefix) {
+                        if let Ok(metadata) = self.read_listing_entry(&key, summary).await {
+                            results.push(metadata);
+                        }
+                    }
+                }
+                return Ok(());
+            }
+
+            // read_dir yields entries in inode order; sort each level so listings
+            // (and the max_keys cutoff) are the same on every call
+            let mut read_dir = fs::read_dir(current_path)
+                .await
+                .map_err(BackendError::io(current_path))?;
+            let mut entries = Vec::new();
+            while let Some(entry) = read_dir
+                .next_entry()
+                .await
+                .map_err(BackendError::io(current_path))?
+            {
+                entries.push(entry);
+            }
+            entries.sort_by_key(|entry| entry.file_name());
+
+            for entry in entries {
+                // Checked before every entry, so a subdirectory that filled the
+                // page ends this level too
+                if max_keys.is_some_and(|max| results.len() >= max) {
+                    break;
+                }
+                let path = entry.path();
+
+                if path.is_dir() {
+                    self.list_recursive(bucket_path, &path, prefix, results, max_keys, summary)
+                        .await?;
+                } else if !path.to_string_lossy().ends_with(".meta.json")
+                    && !is_upload_temp_file(&path)
+                {
+                    if let Ok(relative) = path.strip_prefix(bucket_path) {
+                        let key = relative.to_string_lossy().to_string();
+
+                        if key.starts_with(prefix) {
+                            if let Ok(metadata) = self.read_listing_entry(&key, summary).await {
+                                results.push(metadata);
+                            }
+                        }
+                    }
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use tempfile::TempDir;
+
+    #[tokio::test]
     async fn test_local_backend_put_get() {
         use futures::stream;
 
@@ -293,77 +1873,1251 @@ mod tests {
 
         backend.init().await.unwrap();
 
-        let data = b"Hello, World!".to_vec();
-        let data_clone = data.clone();
+        let data = b"Hello, World!".to_vec();
+        let data_clone = data.clone();
+
+        // Create a stream from the data
+        let stream: ByteStream = Box::pin(stream::iter(vec![Ok(Bytes::from(data.clone()))]));
+
+        let metadata = backend
+            .put_object(
+                "test.txt",
+                stream,
+                Some("text/plain".to_string()),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(metadata.key, "test.txt");
+        assert_eq!(metadata.size, 13);
+
+        let mut obj = backend.get_object("test.txt").await.unwrap();
+
+        // Collect stream back to Vec<u8>
+        let mut collected = Vec::new();
+        while let Some(chunk) = obj.stream.next().await {
+            collected.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(collected, data_clone);
+    }
+
+    #[tokio::test]
+    async fn test_put_object_reader_matches_stream_etag() {
+        use crate::backend::compute_etag;
+        use crate::BackendExt;
+        use futures::stream;
+
+        let temp_dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        backend.init().await.unwrap();
+
+        // Large enough for the reader to yield many chunks
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+
+        let stream: ByteStream = Box::pin(stream::iter(vec![Ok(Bytes::from(data.clone()))]));
+        let from_stream = backend
+            .put_object("stream.bin", stream, None, HashMap::new())
+            .await
+            .unwrap();
+        let from_reader = backend
+            .put_object_reader(
+                "reader.bin",
+                std::io::Cursor::new(data.clone()),
+                None,
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(from_reader.size, data.len() as u64);
+        assert_eq!(from_reader.etag, from_stream.etag);
+        assert_eq!(from_reader.etag, compute_etag(&data));
+    }
+
+    #[tokio::test]
+    async fn test_failed_overwrite_keeps_previous_object() {
+        use futures::stream;
+
+        let temp_dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        backend.init().await.unwrap();
+
+        let stream: ByteStream = Box::pin(stream::iter(vec![Ok(Bytes::from("previous"))]));
+        let previous = backend
+            .put_object("dir/doc.txt", stream, None, HashMap::new())
+            .await
+            .unwrap();
+
+        // The client goes away halfway through the new version
+        let stream: ByteStream = Box::pin(stream::iter(vec![
+            Ok(Bytes::from("replace")),
+            Err(std::io::Error::other("connection reset")),
+        ]));
+        let result = backend
+            .put_object("dir/doc.txt", stream, None, HashMap::new())
+            .await;
+        assert!(matches!(result, Err(BackendError::BodyStream(_))));
+
+        assert_eq!(
+            backend.head_object("dir/doc.txt").await.unwrap().etag,
+            previous.etag
+        );
+        let mut obj = backend.get_object("dir/doc.txt").await.unwrap();
+        let mut collected = Vec::new();
+        while let Some(chunk) = obj.stream.next().await {
+            collected.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(collected, b"previous");
+
+        // The temp file is gone and never shows up as an object
+        let files: Vec<_> = std::fs::read_dir(temp_dir.path().join("test-bucket/dir"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(files.len(), 2, "{:?}", files);
+        let keys: Vec<_> = backend
+            .list_objects(None, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|obj| obj.key)
+            .collect();
+        assert_eq!(keys, vec!["dir/doc.txt"]);
+    }
+
+    #[test]
+    fn test_upload_temp_files_are_recognized() {
+        let temp_path = upload_temp_path(Path::new("/data/bucket/dir/doc.txt"));
+        assert_eq!(temp_path.parent(), Some(Path::new("/data/bucket/dir")));
+        assert!(is_upload_temp_file(&temp_path));
+        assert!(!is_upload_temp_file(Path::new("/data/bucket/dir/doc.txt")));
+        assert!(!is_upload_temp_file(Path::new("/data/bucket/.notes.tmp")));
+    }
+
+    #[tokio::test]
+    async fn test_local_backend_delete() {
+        use futures::stream;
+
+        let temp_dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+
+        backend.init().await.unwrap();
+
+        let data = b"Hello, World!".to_vec();
+        let stream: ByteStream = Box::pin(stream::iter(vec![Ok(Bytes::from(data))]));
+
+        backend
+            .put_object(
+                "test.txt",
+                stream,
+                Some("text/plain".to_string()),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+
+        backend.delete_object("test.txt").await.unwrap();
+
+        let result = backend.get_object("test.txt").await;
+        assert!(matches!(result, Err(BackendError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rename_moves_data_and_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = cached_backend(temp_dir.path());
+        backend.init().await.unwrap();
+        put_text(&backend, "old/nested/a.txt").await;
+        put_text(&backend, "b.txt").await;
+        assert_eq!(list_keys(&backend, None).await.len(), 2);
+
+        // Replaces the object already at the new key
+        backend
+            .rename_object("old/nested/a.txt", "b.txt")
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            backend.head_object("old/nested/a.txt").await,
+            Err(BackendError::NotFound(_))
+        ));
+        assert_eq!(backend.head_object("b.txt").await.unwrap().key, "b.txt");
+        assert_eq!(list_keys(&backend, None).await, vec!["b.txt"]);
+        assert!(!temp_dir.path().join("test-bucket/old").exists());
+
+        assert!(matches!(
+            backend.rename_object("missing.txt", "c.txt").await,
+            Err(BackendError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_delete_removes_empty_parent_directories() {
+        use crate::BackendExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        backend.init().await.unwrap();
+        let bucket_path = temp_dir.path().join("test-bucket");
+
+        for key in ["2024/01/01/log.txt", "2024/01/02/log.txt"] {
+            backend
+                .put_object_reader(key, &b"line"[..], None, HashMap::new())
+                .await
+                .unwrap();
+        }
+        assert!(bucket_path.join("2024/01/01/log.txt").exists());
+
+        backend.delete_object("2024/01/01/log.txt").await.unwrap();
+        assert!(!bucket_path.join("2024/01/01").exists());
+        // Still holds the other day
+        assert!(bucket_path.join("2024/01/02").exists());
+
+        backend.delete_object("2024/01/02/log.txt").await.unwrap();
+        assert!(!bucket_path.join("2024").exists());
+        assert!(bucket_path.exists());
+
+        // Missing directories read as a missing object, not an IO error
+        assert!(matches!(
+            backend.head_object("2024/01/01/log.txt").await,
+            Err(BackendError::NotFound(_))
+        ));
+        assert!(matches!(
+            backend.read_metadata("2024/01/01/log.txt").await,
+            Err(BackendError::NotFound(_))
+        ));
+
+        // The directories come back on the next write
+        backend
+            .put_object_reader("2024/01/01/log.txt", &b"again"[..], None, HashMap::new())
+            .await
+            .unwrap();
+        assert!(bucket_path.join("2024/01/01/log.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_metadata_only() {
+        use crate::BackendExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        backend.init().await.unwrap();
+
+        for (key, body) in [("logs/a.txt", "alpha"), ("logs/b.txt", "bravo!")] {
+            backend
+                .put_object_reader(
+                    key,
+                    body.as_bytes(),
+                    Some("text/plain".to_string()),
+                    HashMap::from([("owner".to_string(), "alice".to_string())]),
+                )
+                .await
+                .unwrap();
+        }
+        // A data file whose upload never wrote its sidecar
+        fs::write(temp_dir.path().join("test-bucket/logs/c.txt"), b"partial")
+            .await
+            .unwrap();
+
+        let full = backend.list_objects(Some("logs/"), None).await.unwrap();
+        let summaries = backend
+            .list_objects_metadata_only(Some("logs/"), None)
+            .await
+            .unwrap();
+        assert_eq!(summaries.len(), 2);
+        for (summary, full) in summaries.iter().zip(&full) {
+            assert_eq!(summary.key, full.key);
+            assert_eq!(summary.size, full.size);
+            // Only in the sidecar
+            assert_eq!(summary.etag, "");
+            assert!(
+                (summary.last_modified - full.last_modified)
+                    .num_seconds()
+                    .abs()
+                    <= 1
+            );
+        }
+
+        let page = backend
+            .list_objects_metadata_only(Some("logs/"), Some(1))
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].key, "logs/a.txt");
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_empty_parents_stops_at_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("bucket");
+        let nested = root.join("a/b/c");
+        fs::create_dir_all(&nested).await.unwrap();
+        fs::write(root.join("a/keep.txt"), b"").await.unwrap();
+
+        cleanup_empty_parents(&nested.join("gone.txt"), &root)
+            .await
+            .unwrap();
+        assert!(!root.join("a/b").exists());
+        assert!(root.join("a/keep.txt").exists());
+
+        fs::remove_file(root.join("a/keep.txt")).await.unwrap();
+        cleanup_empty_parents(&root.join("a/gone.txt"), &root)
+            .await
+            .unwrap();
+        assert!(!root.join("a").exists());
+        assert!(root.exists());
+
+        // Paths outside the root are never touched
+        let outside = temp_dir.path().join("other/dir");
+        fs::create_dir_all(&outside).await.unwrap();
+        cleanup_empty_parents(&outside.join("x"), &root)
+            .await
+            .unwrap();
+        assert!(outside.exists());
+    }
+
+    #[tokio::test]
+    async fn test_path_traversal_prevention() {
+        use futures::stream;
+
+        let temp_dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+
+        backend.init().await.unwrap();
+
+        let stream: ByteStream = Box::pin(stream::iter(vec![Ok(Bytes::from(vec![1, 2, 3]))]));
+
+        let result = backend
+            .put_object("../etc/passwd", stream, None, HashMap::new())
+            .await;
+        assert!(matches!(result, Err(BackendError::InvalidPath(_))));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_puts_same_key() {
+        use futures::stream;
+
+        let temp_dir = TempDir::new().unwrap();
+        let backend = Arc::new(LocalBackend::new(
+            temp_dir.path().to_path_buf(),
+            "test-bucket".to_string(),
+        ));
+
+        backend.init().await.unwrap();
+
+        let payloads: Vec<Vec<u8>> = (0..50u8).map(|i| vec![i; 64 * 1024]).collect();
+
+        let handles: Vec<_> = payloads
+            .iter()
+            .cloned()
+            .map(|payload| {
+                let backend = backend.clone();
+                tokio::spawn(async move {
+                    // Split into several chunks so unsynchronized writers would interleave
+                    let chunks: Vec<Result<Bytes, std::io::Error>> = payload
+                        .chunks(4096)
+                        .map(|c| Ok(Bytes::copy_from_slice(c)))
+                        .collect();
+                    let stream: ByteStream = Box::pin(stream::iter(chunks));
+                    backend
+                        .put_object("shared.bin", stream, None, HashMap::new())
+                        .await
+                })
+            })
+            .collect();
+
+        for handle in futures::future::join_all(handles).await {
+            handle.unwrap().unwrap();
+        }
+
+        let mut obj = backend.get_object("shared.bin").await.unwrap();
+        let mut collected = Vec::new();
+        while let Some(chunk) = obj.stream.next().await {
+            collected.extend_from_slice(&chunk.unwrap());
+        }
+
+        assert!(payloads.contains(&collected));
+        assert_eq!(obj.metadata.etag, crate::backend::compute_etag(&collected));
+        assert_eq!(backend.key_locks.len(), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_delete_and_get_report_not_found() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = Arc::new(LocalBackend::new(
+            temp_dir.path().to_path_buf(),
+            "test-bucket".to_string(),
+        ));
+        backend.init().await.unwrap();
+
+        // Each key in its own directory, which the delete removes too
+        let keys: Vec<String> = (0..50).map(|i| format!("dir-{}/object.txt", i)).collect();
+        for key in &keys {
+            put_text(&backend, key).await;
+        }
+
+        let handles: Vec<_> = keys
+            .iter()
+            .flat_map(|key| {
+                let (get, head, delete, delete_again) = (
+                    backend.clone(),
+                    backend.clone(),
+                    backend.clone(),
+                    backend.clone(),
+                );
+                let (k1, k2, k3, k4) = (key.clone(), key.clone(), key.clone(), key.clone());
+                [
+                    tokio::spawn(async move { get.get_object(&k1).await.map(|_| ()) }),
+                    tokio::spawn(async move { head.head_object(&k2).await.map(|_| ()) }),
+                    tokio::spawn(async move { delete.delete_object(&k3).await }),
+                    tokio::spawn(async move { delete_again.delete_object(&k4).await }),
+                ]
+            })
+            .collect();
+
+        for result in futures::future::join_all(handles).await {
+            match result.unwrap() {
+                Ok(()) | Err(BackendError::NotFound(_)) => {}
+                Err(e) => panic!("expected a clean NotFound, got {:?}", e),
+            }
+        }
+        for key in &keys {
+            assert!(matches!(
+                backend.get_object(key).await,
+                Err(BackendError::NotFound(_))
+            ));
+            assert!(matches!(
+                backend.delete_object(key).await,
+                Err(BackendError::NotFound(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_key_locks_bounded() {
+        let locks = KeyLocks::new(4);
+
+        let held = locks.acquire("held").await;
+        for i in 0..10 {
+            let key = format!("key-{}", i);
+            let _guard = locks.acquire(&key).await;
+            assert!(locks.len() <= 4);
+        }
+
+        assert!(locks.locks.contains_key("held"));
+        drop(held);
+        assert_eq!(locks.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_compact_removes_orphans() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        backend.init().await.unwrap();
+
+        let put = |key: &'static str| {
+            let stream: ByteStream =
+                Box::pin(futures::stream::once(async { Ok(Bytes::from("data")) }));
+            backend.put_object(key, stream, None, HashMap::new())
+        };
+        put("keep.txt").await.unwrap();
+        put("dir/meta-only.txt").await.unwrap();
+
+        let bucket_path = temp_dir.path().join("test-bucket");
+        // Data written by a put that crashed before its metadata
+        fs::create_dir_all(bucket_path.join("dir")).await.unwrap();
+        fs::write(bucket_path.join("dir/data-only.bin"), b"orphan")
+            .await
+            .unwrap();
+        // Metadata whose data file has gone missing
+        fs::remove_file(bucket_path.join("dir/meta-only.txt"))
+            .await
+            .unwrap();
+        let meta_len = fs::metadata(bucket_path.join("dir/meta-only.meta.json"))
+            .await
+            .unwrap()
+            .len();
+
+        let expected = CompactReport {
+            orphaned_data: vec!["dir/data-only.bin".to_string()],
+            orphaned_meta: vec!["dir/meta-only.meta.json".to_string()],
+            reclaimed_bytes: 6 + meta_len,
+        };
+
+        let dry_run = backend.compact(true).await.unwrap();
+        assert_eq!(dry_run, expected);
+        assert!(bucket_path.join("dir/data-only.bin").exists());
+        assert!(bucket_path.join("dir/meta-only.meta.json").exists());
+
+        let report = backend.compact(false).await.unwrap();
+        assert_eq!(report, expected);
+        assert!(!bucket_path.join("dir/data-only.bin").exists());
+        assert!(!bucket_path.join("dir/meta-only.meta.json").exists());
+
+        // Healthy objects are untouched
+        assert!(backend.object_exists("keep.txt").await.unwrap());
+        assert!(bucket_path.join("keep.txt").exists());
+        assert_eq!(
+            backend.compact(false).await.unwrap(),
+            CompactReport::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_object_exists_needs_data_and_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        backend.init().await.unwrap();
+        put(
+            &backend,
+            "whole.txt",
+            "text/plain".to_string(),
+            HashMap::new(),
+        )
+        .await;
+        assert!(backend.object_exists("whole.txt").await.unwrap());
+        assert!(!backend.object_exists("missing.txt").await.unwrap());
+
+        // An upload that never got its metadata written does not exist yet
+        let bucket_path = temp_dir.path().join("test-bucket");
+        std::fs::write(bucket_path.join("partial.txt"), b"partial").unwrap();
+        assert!(!backend.object_exists("partial.txt").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_io_errors_name_the_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        backend.init().await.unwrap();
+
+        // A directory where the metadata file should be cannot be read
+        let meta_path = temp_dir.path().join("test-bucket/broken.meta.json");
+        std::fs::create_dir_all(&meta_path).unwrap();
 
-        // Create a stream from the data
-        let stream: ByteStream = Box::pin(stream::iter(vec![Ok(Bytes::from(data.clone()))]));
+        let err = backend.head_object("broken").await.unwrap_err();
+        match &err {
+            BackendError::Io { path, .. } => assert_eq!(path, &meta_path),
+            other => panic!("expected an I/O error, got {:?}", other),
+        }
+        assert!(
+            err.to_string().contains(&meta_path.display().to_string()),
+            "{}",
+            err
+        );
+    }
 
-        let metadata = backend
-            .put_object(
-                "test.txt",
-                stream,
-                Some("text/plain".to_string()),
+    fn cached_backend(root: &Path) -> LocalBackend {
+        LocalBackend::new_with_config(
+            root.to_path_buf(),
+            "test-bucket".to_string(),
+            LocalBackendConfig {
+                list_cache_ttl_secs: 60,
+                ..Default::default()
+            },
+        )
+    }
+
+    async fn put_text(backend: &LocalBackend, key: &str) {
+        use crate::BackendExt;
+        backend
+            .put_object_reader(key, &b"data"[..], None, HashMap::new())
+            .await
+            .unwrap();
+    }
+
+    async fn list_keys(backend: &LocalBackend, prefix: Option<&str>) -> Vec<String> {
+        let objects = backend.list_objects(prefix, None).await.unwrap();
+        objects.into_iter().map(|o| o.key).collect()
+    }
+
+    #[tokio::test]
+    async fn test_metadata_cache_follows_the_data_file() {
+        use crate::BackendExt;
+        let temp_dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new_with_config(
+            temp_dir.path().to_path_buf(),
+            "test-bucket".to_string(),
+            LocalBackendConfig {
+                metadata_cache_capacity: 16,
+                ..Default::default()
+            },
+        );
+        backend.init().await.unwrap();
+        let put = |content_type: &'static str, data: &'static [u8]| {
+            backend.put_object_reader(
+                "doc.txt",
+                data,
+                Some(content_type.to_string()),
                 HashMap::new(),
             )
+        };
+        let content_type = || async {
+            let metadata = backend.head_object("doc.txt").await.unwrap();
+            metadata.content_type.unwrap()
+        };
+
+        put("text/plain", b"first").await.unwrap();
+        assert_eq!(content_type().await, "text/plain");
+
+        // The sidecar isn't read again while the data file is unchanged
+        let meta_path = temp_dir.path().join("test-bucket/doc.meta.json");
+        let sidecar = std::fs::read_to_string(&meta_path).unwrap();
+        std::fs::write(&meta_path, sidecar.replace("text/plain", "text/csv")).unwrap();
+        assert_eq!(content_type().await, "text/plain");
+
+        // Writes through the backend replace the entry
+        put("text/markdown", b"second").await.unwrap();
+        assert_eq!(content_type().await, "text/markdown");
+
+        // So does a data file changed behind the backend's back
+        let sidecar = std::fs::read_to_string(&meta_path).unwrap();
+        std::fs::write(&meta_path, sidecar.replace("text/markdown", "text/html")).unwrap();
+        std::fs::write(temp_dir.path().join("test-bucket/doc.txt"), b"rewritten").unwrap();
+        assert_eq!(content_type().await, "text/html");
+
+        backend.delete_object("doc.txt").await.unwrap();
+        assert!(matches!(
+            backend.head_object("doc.txt").await,
+            Err(BackendError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_cache_invalidated_by_delete() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = cached_backend(temp_dir.path());
+        backend.init().await.unwrap();
+
+        for key in ["dir/a.txt", "dir/b.txt", "other.txt"] {
+            put_text(&backend, key).await;
+        }
+        assert_eq!(list_keys(&backend, Some("dir/")).await.len(), 2);
+        assert_eq!(list_keys(&backend, None).await.len(), 3);
+        assert_eq!(
+            backend
+                .list_objects(Some("dir/"), Some(1))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        backend.delete_object("dir/a.txt").await.unwrap();
+        assert_eq!(list_keys(&backend, Some("dir/")).await, vec!["dir/b.txt"]);
+        assert_eq!(
+            list_keys(&backend, None).await,
+            vec!["dir/b.txt", "other.txt"]
+        );
+        let page = backend.list_objects(Some("dir/"), Some(1)).await.unwrap();
+        assert_eq!(page[0].key, "dir/b.txt");
+
+        backend.delete_object("dir/b.txt").await.unwrap();
+        assert!(list_keys(&backend, Some("dir/")).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_cache_invalidates_only_covering_prefixes() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = cached_backend(temp_dir.path());
+        backend.init().await.unwrap();
+
+        put_text(&backend, "a/1.txt").await;
+        put_text(&backend, "b/1.txt").await;
+        assert_eq!(list_keys(&backend, Some("a/")).await, vec!["a/1.txt"]);
+        assert_eq!(list_keys(&backend, Some("b/")).await, vec!["b/1.txt"]);
+
+        // Written behind the backend's back, so only a fresh walk sees it
+        let uncached = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        put_text(&uncached, "b/2.txt").await;
+
+        put_text(&backend, "a/2.txt").await;
+        assert_eq!(
+            list_keys(&backend, Some("a/")).await,
+            vec!["a/1.txt", "a/2.txt"]
+        );
+        assert_eq!(list_keys(&backend, Some("b/")).await, vec!["b/1.txt"]);
+        assert_eq!(list_keys(&uncached, Some("b/")).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_sorted_pages() {
+        use futures::stream;
+
+        let temp_dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        backend.init().await.unwrap();
+
+        // Write out of order so inode order differs from name order
+        for i in (1..=200).rev() {
+            let stream: ByteStream = Box::pin(stream::iter(vec![Ok(Bytes::from_static(b"x"))]));
+            backend
+                .put_object(&format!("file-{:03}.txt", i), stream, None, HashMap::new())
+                .await
+                .unwrap();
+        }
+
+        let mut seen: Vec<String> = Vec::new();
+        for page in 1..=4 {
+            let listed: Vec<String> = backend
+                .list_objects(None, Some(page * 50))
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|obj| obj.key)
+                .collect();
+            assert_eq!(listed.len(), page * 50);
+            // Earlier pages are a stable prefix of later ones
+            assert_eq!(&listed[..seen.len()], &seen[..]);
+            seen.extend_from_slice(&listed[seen.len()..]);
+        }
+
+        let expected: Vec<String> = (1..=200).map(|i| format!("file-{:03}.txt", i)).collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_stops_at_max_keys_across_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        backend.init().await.unwrap();
+
+        for key in [
+            "a/1.txt",
+            "a/2.txt",
+            "a/3.txt",
+            "a/4.txt",
+            "a/5.txt",
+            "b.txt",
+            "report.json",
+        ] {
+            put_text(&backend, key).await;
+        }
+
+        let page = |max_keys| backend.list_objects(None, Some(max_keys));
+        let keys = |objects: Vec<ObjectMetadata>| -> Vec<String> {
+            objects.into_iter().map(|o| o.key).collect()
+        };
+        assert_eq!(keys(page(1).await.unwrap()), vec!["a/1.txt"]);
+        assert_eq!(page(5).await.unwrap().len(), 5);
+        assert_eq!(keys(page(6).await.unwrap())[5], "b.txt");
+
+        // JSON objects are listed, their sidecars never are
+        let all = list_keys(&backend, None).await;
+        assert_eq!(all.len(), 7);
+        assert!(all.iter().all(|key| !key.ends_with(".meta.json")));
+        assert_eq!(
+            list_keys(&backend, Some("report.json")).await,
+            vec!["report.json"]
+        );
+    }
+
+    fn xattr_backend(temp_dir: &TempDir) -> LocalBackend {
+        LocalBackend::new_with_config(
+            temp_dir.path().to_path_buf(),
+            "test-bucket".to_string(),
+            LocalBackendConfig {
+                use_xattrs: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn xattrs_supported(temp_dir: &TempDir) -> bool {
+        let probe = temp_dir.path().join("xattr-probe");
+        std::fs::write(&probe, b"").unwrap();
+        let supported = xattr::set(&probe, "user.probe", b"1").is_ok();
+        std::fs::remove_file(&probe).unwrap();
+        supported
+    }
+
+    async fn put(
+        backend: &LocalBackend,
+        key: &str,
+        content_type: String,
+        custom_metadata: HashMap<String, String>,
+    ) -> ObjectMetadata {
+        let stream: ByteStream = Box::pin(futures::stream::iter(vec![Ok(Bytes::from_static(
+            b"hello",
+        ))]));
+        backend
+            .put_object(key, stream, Some(content_type), custom_metadata)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_xattrs_serve_hot_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        if !xattrs_supported(&temp_dir) {
+            return;
+        }
+        let backend = xattr_backend(&temp_dir);
+        backend.init().await.unwrap();
+
+        let stored = put(
+            &backend,
+            "plain.txt",
+            "text/plain".to_string(),
+            HashMap::new(),
+        )
+        .await;
+        let data_path = backend.get_full_path("plain.txt").unwrap();
+        assert_eq!(
+            xattr::get(&data_path, XATTR_ETAG).unwrap(),
+            Some(stored.etag.clone().into_bytes())
+        );
+
+        // With the sidecar gone the attributes alone answer head_object
+        fs::remove_file(backend.get_metadata_path("plain.txt").unwrap())
             .await
             .unwrap();
+        let head = backend.head_object("plain.txt").await.unwrap();
+        assert_eq!(head.etag, stored.etag);
+        assert_eq!(head.size, 5);
+        assert_eq!(head.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(head.last_modified, stored.last_modified);
+
+        // Custom metadata lives only in the sidecar
+        let custom = HashMap::from([("owner".to_string(), "alice".to_string())]);
+        put(
+            &backend,
+            "custom.txt",
+            "text/plain".to_string(),
+            custom.clone(),
+        )
+        .await;
+        assert_eq!(
+            backend
+                .head_object("custom.txt")
+                .await
+                .unwrap()
+                .custom_metadata,
+            custom
+        );
+    }
 
-        assert_eq!(metadata.key, "test.txt");
-        assert_eq!(metadata.size, 13);
+    #[tokio::test]
+    async fn test_xattrs_fall_back_to_sidecar() {
+        let temp_dir = TempDir::new().unwrap();
+        let plain = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        plain.init().await.unwrap();
+        let stored = put(&plain, "old.txt", "text/plain".to_string(), HashMap::new()).await;
 
-        let mut obj = backend.get_object("test.txt").await.unwrap();
+        // Objects written before the option was enabled have no attributes
+        let head = xattr_backend(&temp_dir)
+            .head_object("old.txt")
+            .await
+            .unwrap();
+        assert_eq!(head.etag, stored.etag);
+        assert_eq!(head.content_type.as_deref(), Some("text/plain"));
 
-        // Collect stream back to Vec<u8>
+        let missing = xattr_backend(&temp_dir).head_object("missing.txt").await;
+        assert!(matches!(missing, Err(BackendError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_xattr_write_failure_keeps_sidecar() {
+        let temp_dir = TempDir::new().unwrap();
+        if !xattrs_supported(&temp_dir) {
+            return;
+        }
+        let backend = xattr_backend(&temp_dir);
+        backend.init().await.unwrap();
+        put(
+            &backend,
+            "big.txt",
+            "text/plain".to_string(),
+            HashMap::new(),
+        )
+        .await;
+
+        // Attribute values are capped at 64 KiB, so this one cannot be stored.
+        // The previous version's attributes must not answer for it either.
+        let content_type = format!("text/{}", "x".repeat(70 * 1024));
+        let stored = put(&backend, "big.txt", content_type.clone(), HashMap::new()).await;
+
+        let data_path = backend.get_full_path("big.txt").unwrap();
+        assert_eq!(xattr::get(&data_path, XATTR_ETAG).unwrap(), None);
+
+        let head = backend.head_object("big.txt").await.unwrap();
+        assert_eq!(head.etag, stored.etag);
+        assert_eq!(head.content_type, Some(content_type));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_directories_created_with_configured_mode() {
+        use crate::BackendExt;
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new_with_permissions(
+            temp_dir.path().join("root"),
+            "test-bucket".to_string(),
+            0o700,
+        );
+        backend.init().await.unwrap();
+        backend
+            .put_object_reader("nested/dir/a.txt", &b"a"[..], None, HashMap::new())
+            .await
+            .unwrap();
+
+        let bucket_path = temp_dir.path().join("root/test-bucket");
+        for dir in [
+            temp_dir.path().join("root"),
+            bucket_path.clone(),
+            bucket_path.join("nested"),
+            bucket_path.join("nested/dir"),
+        ] {
+            let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700, "{}", dir.display());
+        }
+    }
+
+    fn encrypted_backend(temp_dir: &TempDir, key: u8) -> LocalBackend {
+        LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string())
+            .with_encryption(EncryptionKey::from_bytes(&[key; 32]))
+    }
+
+    async fn read_all(backend: &LocalBackend, key: &str) -> BackendResult<Vec<u8>> {
+        let mut obj = backend.get_object(key).await?;
         let mut collected = Vec::new();
         while let Some(chunk) = obj.stream.next().await {
-            collected.extend_from_slice(&chunk.unwrap());
+            collected.extend_from_slice(&chunk.map_err(BackendError::io(key))?);
         }
-        assert_eq!(collected, data_clone);
+        Ok(collected)
     }
 
     #[tokio::test]
-    async fn test_local_backend_delete() {
-        use futures::stream;
+    async fn test_encrypted_objects_need_the_right_key() {
+        use crate::BackendExt;
 
         let temp_dir = TempDir::new().unwrap();
-        let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        let backend = encrypted_backend(&temp_dir, 1);
+        backend.init().await.unwrap();
+
+        // Several frames, the last one partial
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let custom = HashMap::from([("owner".to_string(), "alice".to_string())]);
+        let stored = backend
+            .put_object_reader(
+                "secret.bin",
+                std::io::Cursor::new(data.clone()),
+                Some("application/x-secret".to_string()),
+                custom.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(stored.etag, crate::backend::compute_etag(&data));
+
+        assert_eq!(read_all(&backend, "secret.bin").await.unwrap(), data);
+        let head = backend.head_object("secret.bin").await.unwrap();
+        assert_eq!(head.size, data.len() as u64);
+        assert_eq!(head.custom_metadata, custom);
+        assert_eq!(list_keys(&backend, None).await, vec!["secret.bin"]);
+
+        let bucket_path = temp_dir.path().join("test-bucket");
+        let on_disk = std::fs::read(bucket_path.join("secret.bin")).unwrap();
+        assert!(!on_disk.windows(64).any(|w| w == &data[..64]));
+        let sidecar = std::fs::read_to_string(bucket_path.join("secret.meta.json")).unwrap();
+        assert!(!sidecar.contains("application/x-secret"));
+        assert!(!sidecar.contains("alice"));
+
+        let wrong_key = encrypted_backend(&temp_dir, 2);
+        assert!(matches!(
+            wrong_key.get_object("secret.bin").await,
+            Err(BackendError::Configuration(_))
+        ));
+        assert!(wrong_key.head_object("secret.bin").await.is_err());
+        let no_key = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        assert!(no_key.get_object("secret.bin").await.is_err());
+
+        // A cut-off data file fails the read instead of returning less data
+        std::fs::write(
+            bucket_path.join("secret.bin"),
+            &on_disk[..on_disk.len() - 30],
+        )
+        .unwrap();
+        assert!(read_all(&backend, "secret.bin").await.is_err());
+    }
 
+    #[tokio::test]
+    async fn test_rename_keeps_encrypted_objects_readable() {
+        use crate::BackendExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let backend = encrypted_backend(&temp_dir, 1);
         backend.init().await.unwrap();
+        backend
+            .put_object_reader("a.txt", &b"secret"[..], None, HashMap::new())
+            .await
+            .unwrap();
 
-        let data = b"Hello, World!".to_vec();
-        let stream: ByteStream = Box::pin(stream::iter(vec![Ok(Bytes::from(data))]));
+        backend.rename_object("a.txt", "b.txt").await.unwrap();
+
+        assert_eq!(read_all(&backend, "b.txt").await.unwrap(), b"secret");
+        assert_eq!(backend.head_object("b.txt").await.unwrap().key, "b.txt");
+        assert!(!backend.object_exists("a.txt").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_rotate_key_reencrypts_objects() {
+        let temp_dir = TempDir::new().unwrap();
+        let plain = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        plain.init().await.unwrap();
+        put_text(&plain, "plain.txt").await;
+
+        let backend = encrypted_backend(&temp_dir, 1);
+        for key in ["a.txt", "dir/b.txt"] {
+            put_text(&backend, key).await;
+        }
+
+        let wrong_old = backend
+            .rotate_key(
+                EncryptionKey::from_bytes(&[3; 32]),
+                EncryptionKey::from_bytes(&[2; 32]),
+            )
+            .await;
+        assert!(matches!(wrong_old, Err(BackendError::Configuration(_))));
+
+        let rotated = backend
+            .rotate_key(
+                EncryptionKey::from_bytes(&[1; 32]),
+                EncryptionKey::from_bytes(&[2; 32]),
+            )
+            .await
+            .unwrap();
+        // Plaintext objects are left as they are
+        assert_eq!(rotated, 2);
+
+        let new_key = encrypted_backend(&temp_dir, 2);
+        let old_key = encrypted_backend(&temp_dir, 1);
+        for key in ["a.txt", "dir/b.txt"] {
+            assert_eq!(read_all(&backend, key).await.unwrap(), b"data");
+            assert_eq!(read_all(&new_key, key).await.unwrap(), b"data");
+            assert!(old_key.get_object(key).await.is_err());
+        }
+        assert_eq!(read_all(&new_key, "plain.txt").await.unwrap(), b"data");
+        assert!(!temp_dir.path().join(".test-bucket.rotating").exists());
+        assert_eq!(
+            backend.compact(true).await.unwrap(),
+            CompactReport::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_object_range() {
+        use crate::BackendExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let plain = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        let encrypted = encrypted_backend(&temp_dir, 1);
+        let data: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        for (backend, key) in [(&plain, "plain.bin"), (&encrypted, "secret.bin")] {
+            backend.init().await.unwrap();
+            backend
+                .put_object_reader(
+                    key,
+                    std::io::Cursor::new(data.clone()),
+                    None,
+                    HashMap::new(),
+                )
+                .await
+                .unwrap();
+
+            let read_range = |start, len| async move {
+                let mut object = backend.get_object_range(key, start, len).await.unwrap();
+                assert_eq!(object.metadata.size, 200_000);
+                let mut collected = Vec::new();
+                while let Some(chunk) = object.stream.next().await {
+                    collected.extend_from_slice(&chunk.unwrap());
+                }
+                collected
+            };
+            assert_eq!(read_range(150_000, 10).await, &data[150_000..150_010]);
+            assert_eq!(read_range(150_000, u64::MAX).await, &data[150_000..]);
+            assert!(read_range(200_000, 10).await.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rotation_interrupted_after_data_swap_recovers() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = encrypted_backend(&temp_dir, 1);
+        backend.init().await.unwrap();
+        put_text(&backend, "a.txt").await;
+        put_text(&backend, "b.txt").await;
 
+        // Crash after a's data is replaced, before its sidecar is
+        std::fs::create_dir_all(backend.rotation_dir()).unwrap();
+        let (metadata, encryption) = backend.read_stored_metadata("a.txt").await.unwrap();
         backend
-            .put_object(
-                "test.txt",
-                stream,
-                Some("text/plain".to_string()),
-                HashMap::new(),
+            .swap_in_rotated_data(
+                &metadata,
+                encryption.unwrap(),
+                &EncryptionKey::from_bytes(&[2; 32]),
+            )
+            .await
+            .unwrap();
+        drop(backend);
+        assert!(read_all(&encrypted_backend(&temp_dir, 1), "a.txt")
+            .await
+            .is_err());
+
+        let restarted = encrypted_backend(&temp_dir, 1);
+        restarted.init().await.unwrap();
+        assert!(!restarted.rotation_dir().exists());
+        let resumed = restarted
+            .rotate_key(
+                EncryptionKey::from_bytes(&[1; 32]),
+                EncryptionKey::from_bytes(&[2; 32]),
             )
             .await
             .unwrap();
+        assert_eq!(resumed, 1);
+        for key in ["a.txt", "b.txt"] {
+            assert_eq!(
+                read_all(&encrypted_backend(&temp_dir, 2), key)
+                    .await
+                    .unwrap(),
+                b"data"
+            );
+        }
 
-        backend.delete_object("test.txt").await.unwrap();
+        // Staged data that never replaced its object is dropped
+        let backend = encrypted_backend(&temp_dir, 2);
+        std::fs::create_dir_all(backend.rotation_dir()).unwrap();
+        std::fs::write(backend.rotation_dir().join(ROTATION_STAGED_DATA), b"x").unwrap();
+        std::fs::write(
+            backend.rotation_dir().join(ROTATION_PENDING_SIDECAR),
+            b"{\"key\":\"a.txt\",\"sidecar\":\"garbage\"}",
+        )
+        .unwrap();
+        backend.init().await.unwrap();
+        assert!(!backend.rotation_dir().exists());
+        assert_eq!(read_all(&backend, "a.txt").await.unwrap(), b"data");
+    }
 
-        let result = backend.get_object("test.txt").await;
-        assert!(matches!(result, Err(BackendError::NotFound(_))));
+    #[test]
+    fn test_encryption_key_parsing() {
+        let key = LocalBackendEncryption {
+            key_hex: "ab".repeat(32),
+        };
+        assert_eq!(
+            key.key().unwrap().id(),
+            EncryptionKey::from_bytes(&[0xab; 32]).id()
+        );
+        assert!(!format!("{:?}", key).contains("abab"));
+
+        for key_hex in ["ab".repeat(16), "zz".repeat(32)] {
+            let result = LocalBackendEncryption { key_hex }.key();
+            assert!(matches!(result, Err(BackendError::Configuration(_))));
+        }
+    }
+
+    #[test]
+    fn test_dir_permissions_parsing() {
+        let parse = |json: &str| {
+            serde_json::from_str::<LocalBackendConfig>(json).map(|c| c.dir_permissions)
+        };
+
+        assert_eq!(parse("{}").unwrap(), None);
+        assert_eq!(parse(r#"{"dir_permissions": 448}"#).unwrap(), Some(0o700));
+        assert_eq!(
+            parse(r#"{"dir_permissions": "0o750"}"#).unwrap(),
+            Some(0o750)
+        );
+        assert_eq!(parse(r#"{"dir_permissions": "700"}"#).unwrap(), Some(0o700));
+        assert!(parse(r#"{"dir_permissions": "0o789"}"#).is_err());
+    }
+
+    fn strict_backend(root: &Path, min_free_bytes: Option<u64>) -> LocalBackend {
+        LocalBackend::new_with_config(
+            root.to_path_buf(),
+            "test-bucket".to_string(),
+            LocalBackendConfig {
+                strict_init: true,
+                min_free_bytes,
+                ..Default::default()
+            },
+        )
     }
 
     #[tokio::test]
-    async fn test_path_traversal_prevention() {
-        use futures::stream;
+    async fn test_strict_init_accepts_usable_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        strict_backend(temp_dir.path(), Some(1))
+            .init()
+            .await
+            .unwrap();
+        assert!(temp_dir.path().join("test-bucket").is_dir());
+
+        // The canary file does not stay behind
+        let mut entries = std::fs::read_dir(temp_dir.path().join("test-bucket")).unwrap();
+        assert!(entries.next().is_none());
+    }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_strict_init_rejects_symlink() {
         let temp_dir = TempDir::new().unwrap();
-        let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "test-bucket".to_string());
+        let target = temp_dir.path().join("elsewhere");
+        std::fs::create_dir(&target).unwrap();
+        let root = temp_dir.path().join("root");
+        std::os::unix::fs::symlink(&target, &root).unwrap();
 
-        backend.init().await.unwrap();
+        let result = strict_backend(&root, None).init().await;
+        assert!(matches!(result, Err(BackendError::Configuration(_))));
 
-        let stream: ByteStream = Box::pin(stream::iter(vec![Ok(Bytes::from(vec![1, 2, 3]))]));
+        // Only strict mode looks
+        LocalBackend::new(root, "test-bucket".to_string())
+            .init()
+            .await
+            .unwrap();
+    }
 
-        let result = backend
-            .put_object("../etc/passwd", stream, None, HashMap::new())
-            .await;
-        assert!(matches!(result, Err(BackendError::InvalidPath(_))));
+    #[tokio::test]
+    async fn test_strict_init_rejects_file() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("test-bucket"), b"not a directory").unwrap();
+
+        let result = strict_backend(temp_dir.path(), None).init().await;
+        assert!(matches!(result, Err(BackendError::Configuration(_))));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_strict_init_rejects_read_only_directory() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let bucket = temp_dir.path().join("test-bucket");
+        std::fs::create_dir(&bucket).unwrap();
+        std::fs::set_permissions(&bucket, std::fs::Permissions::from_mode(0o555)).unwrap();
+
+        // Root ignores directory modes, so there is nothing to detect
+        if std::fs::write(bucket.join("probe"), b"").is_ok() {
+            return;
+        }
+
+        let result = strict_backend(temp_dir.path(), None).init().await;
+        std::fs::set_permissions(&bucket, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(matches!(result, Err(BackendError::Configuration(_))));
+    }
+
+    #[tokio::test]
+    async fn test_strict_init_rejects_low_free_space() {
+        let temp_dir = TempDir::new().unwrap();
+        let available = check_available_space(temp_dir.path()).await.unwrap();
+        assert!(available > 0);
+
+        let result = strict_backend(temp_dir.path(), Some(u64::MAX)).init().await;
+        match result {
+            Err(BackendError::Configuration(message)) => {
+                assert!(message.contains("min_free_bytes"))
+            }
+            other => panic!("expected a configuration error, got {:?}", other),
+        }
     }
 }
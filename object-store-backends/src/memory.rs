@@ -0,0 +1,218 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::Utc;
+use futures::StreamExt;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::sync::RwLock;
+use tracing::debug;
+
+use crate::backend::{
+    compute_etag, Backend, ByteStream, ObjectData, ObjectMetadata, PublicUrlPurpose,
+};
+use crate::error::{BackendError, BackendResult};
+
+/// Keeps every object in process memory. Intended for tests and tooling that
+/// need a throwaway backend; contents are lost when the value is dropped.
+#[derive(Default)]
+pub struct MemoryBackend {
+    objects: RwLock<BTreeMap<String, (ObjectMetadata, Bytes)>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl Backend for MemoryBackend {
+    fn backend_type(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn init(&self) -> BackendResult<()> {
+        Ok(())
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        mut stream: ByteStream,
+        content_type: Option<String>,
+        custom_metadata: HashMap<String, String>,
+    ) -> BackendResult<ObjectMetadata> {
+        let mut data = Vec::new();
+        while let Some(chunk_result) = stream.next().await {
+            let chunk = chunk_result.map_err(|e| BackendError::BodyStream(e.to_string()))?;
+            data.extend_from_slice(&chunk);
+        }
+
+        let metadata = ObjectMetadata {
+            key: key.to_string(),
+            size: data.len() as u64,
+            content_type,
+            etag: compute_etag(&data),
+            last_modified: Utc::now(),
+            custom_metadata,
+        };
+
+        self.objects
+            .write()
+            .unwrap()
+            .insert(key.to_string(), (metadata.clone(), Bytes::from(data)));
+
+        debug!("Stored object in memory: {}", key);
+        Ok(metadata)
+    }
+
+    async fn get_object(&self, key: &str) -> BackendResult<ObjectData> {
+        let (metadata, data) = self
+            .objects
+            .read()
+            .unwrap()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| BackendError::NotFound(key.to_string()))?;
+
+        Ok(ObjectData::from_bytes(metadata, data))
+    }
+
+    async fn head_object(&self, key: &str) -> BackendResult<ObjectMetadata> {
+        self.objects
+            .read()
+            .unwrap()
+            .get(key)
+            .map(|(metadata, _)| metadata.clone())
+            .ok_or_else(|| BackendError::NotFound(key.to_string()))
+    }
+
+    async fn delete_object(&self, key: &str) -> BackendResult<()> {
+        self.objects
+            .write()
+            .unwrap()
+            .remove(key)
+            .map(|_| ())
+            .ok_or_else(|| BackendError::NotFound(key.to_string()))
+    }
+
+    async fn list_objects(
+        &self,
+        prefix: Option<&str>,
+        max_keys: Option<usize>,
+    ) -> BackendResult<Vec<ObjectMetadata>> {
+        let prefix = prefix.unwrap_or("");
+        let objects = self.objects.read().unwrap();
+
+        Ok(objects
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .take(max_keys.unwrap_or(usize::MAX))
+            .map(|(_, (metadata, _))| metadata.clone())
+            .collect())
+    }
+
+    async fn list_objects_after(
+        &self,
+        prefix: Option<&str>,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> BackendResult<Vec<ObjectMetadata>> {
+        let prefix = prefix.unwrap_or("");
+        let start = match start_after {
+            Some(key) if key >= prefix => Bound::Excluded(key.to_string()),
+            _ => Bound::Included(prefix.to_string()),
+        };
+        let objects = self.objects.read().unwrap();
+
+        Ok(objects
+            .range((start, Bound::Unbounded))
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .take(max_keys)
+            .map(|(_, (metadata, _))| metadata.clone())
+            .collect())
+    }
+
+    async fn get_public_url(
+        &self,
+        _key: &str,
+        _expiration_secs: u64,
+        _purpose: PublicUrlPurpose,
+        _content_type: Option<&str>,
+    ) -> BackendResult<String> {
+        Err(BackendError::Provider(
+            "Public URL generation is not supported for memory backend".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_backend_list_prefix() {
+        let backend = MemoryBackend::new();
+
+        for key in ["a/1", "a/2", "b/1"] {
+            let stream: ByteStream =
+                Box::pin(futures::stream::once(async { Ok(Bytes::from("data")) }));
+            backend
+                .put_object(key, stream, None, HashMap::new())
+                .await
+                .unwrap();
+        }
+
+        let listed = backend.list_objects(Some("a/"), None).await.unwrap();
+        let keys: Vec<_> = listed.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(keys, vec!["a/1", "a/2"]);
+
+        backend.delete_object("a/1").await.unwrap();
+        assert!(matches!(
+            backend.get_object("a/1").await,
+            Err(BackendError::NotFound(_))
+        ));
+        assert_eq!(backend.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_memory_backend_list_after() {
+        let backend = MemoryBackend::new();
+
+        for key in ["a/1", "a/2", "a/3", "b/1"] {
+            let stream: ByteStream =
+                Box::pin(futures::stream::once(async { Ok(Bytes::from("data")) }));
+            backend
+                .put_object(key, stream, None, HashMap::new())
+                .await
+                .unwrap();
+        }
+
+        let keys = |listed: Vec<ObjectMetadata>| -> Vec<String> {
+            listed.into_iter().map(|o| o.key).collect()
+        };
+        let first = backend
+            .list_objects_after(Some("a/"), None, 2)
+            .await
+            .unwrap();
+        assert_eq!(keys(first), vec!["a/1", "a/2"]);
+        let next = backend
+            .list_objects_after(Some("a/"), Some("a/2"), 2)
+            .await
+            .unwrap();
+        assert_eq!(keys(next), vec!["a/3"]);
+        let before_prefix = backend
+            .list_objects_after(Some("b/"), Some("a/9"), 2)
+            .await
+            .unwrap();
+        assert_eq!(keys(before_prefix), vec!["b/1"]);
+    }
+}
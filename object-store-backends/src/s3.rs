@@ -1,65 +1,197 @@
 use async_trait::async_trait;
 use aws_config::meta::region::RegionProviderChain;
 use aws_config::BehaviorVersion;
-use aws_sdk_s3::config::Region;
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::config::{Credentials, ProvideCredentials, Region, SharedCredentialsProvider};
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_s3::operation::get_object::GetObjectError;
+use aws_sdk_s3::operation::head_object::HeadObjectError;
 use aws_sdk_s3::presigning::PresigningConfig;
 use aws_sdk_s3::primitives::ByteStream as AwsByteStream;
+use aws_sdk_s3::types::{MetadataDirective, StorageClass as S3StorageClass};
 use aws_sdk_s3::Client;
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use futures::StreamExt;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::Duration;
 use tokio_util::io::ReaderStream;
 use tracing::{debug, info, warn};
 
-use crate::backend::{Backend, ByteStream, ObjectData, ObjectMetadata, PublicUrlPurpose};
+use crate::backend::{
+    normalize_etag, Backend, ByteStream, ObjectData, ObjectMetadata, PublicUrlPurpose, StorageClass,
+};
 use crate::error::{BackendError, BackendResult};
 
+/// Largest object a single PutObject request can store.
+const S3_MAX_PUT_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Custom metadata key under which the base64-encoded CRC32C of an object is stored.
+pub const CRC32C_METADATA_KEY: &str = "crc32c";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    #[default]
+    None,
+    Crc32c,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct S3Config {
+    #[serde(default)]
+    pub checksum_algorithm: ChecksumAlgorithm,
+}
+
+impl S3Config {
+    pub fn checksum_crc32c(&self) -> bool {
+        self.checksum_algorithm == ChecksumAlgorithm::Crc32c
+    }
+}
+
+/// Where the backend connects. `force_path_style` addresses buckets as
+/// `{endpoint}/{bucket}/{key}` rather than `{bucket}.{endpoint}/{key}`, which
+/// S3-compatible servers such as MinIO and LocalStack need.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3BackendConfig {
+    pub bucket_name: String,
+    pub region: String,
+    pub endpoint: Option<String>,
+    pub force_path_style: bool,
+}
+
 pub struct S3Backend {
     client: Client,
     bucket_name: String,
+    config: S3Config,
+    credentials: Option<SharedCredentialsProvider>,
 }
 
 impl S3Backend {
+    /// Credentials come from the default provider chain (environment, profile,
+    /// web identity, ECS container and EC2 instance metadata). Since behavior
+    /// version 2024-03-28 the loaded config wraps that chain in a lazy identity
+    /// cache, which re-resolves credentials shortly before they expire, so IAM
+    /// role credentials on EC2 and ECS rotate for the lifetime of the client.
+    fn sdk_config_loader() -> aws_config::ConfigLoader {
+        aws_config::defaults(BehaviorVersion::latest())
+    }
+
     pub async fn new(bucket_name: String) -> BackendResult<Self> {
-        let config = aws_config::defaults(BehaviorVersion::latest()).load().await;
+        let config = Self::sdk_config_loader().load().await;
         let client = Client::new(&config);
 
         info!("Initialized S3 backend with bucket: {}", bucket_name);
         Ok(Self {
             client,
             bucket_name,
+            config: S3Config::default(),
+            credentials: config.credentials_provider(),
         })
     }
 
-    pub async fn new_with_config(
+    pub async fn new_with_config(config: S3BackendConfig) -> BackendResult<Self> {
+        Self::connect(config, Self::sdk_config_loader()).await
+    }
+
+    /// A backend for a MinIO server at `endpoint`, using path-style addressing
+    /// and the given access key instead of the default credential chain.
+    pub async fn new_for_minio(
         bucket_name: String,
-        region: String,
-        endpoint: Option<String>,
+        endpoint: String,
+        access_key: String,
+        secret_key: String,
     ) -> BackendResult<Self> {
-        let region_provider = RegionProviderChain::first_try(Region::new(region));
+        let credentials = Credentials::new(access_key, secret_key, None, None, "minio");
+        Self::connect(
+            S3BackendConfig {
+                bucket_name,
+                // MinIO's default; it accepts any region unless configured otherwise
+                region: "us-east-1".to_string(),
+                endpoint: Some(endpoint),
+                force_path_style: true,
+            },
+            Self::sdk_config_loader().credentials_provider(credentials),
+        )
+        .await
+    }
 
-        let mut config_loader =
-            aws_config::defaults(BehaviorVersion::latest()).region(region_provider);
+    async fn connect(
+        config: S3BackendConfig,
+        config_loader: aws_config::ConfigLoader,
+    ) -> BackendResult<Self> {
+        let region_provider = RegionProviderChain::first_try(Region::new(config.region));
+        let mut config_loader = config_loader.region(region_provider);
 
-        if let Some(endpoint_url) = endpoint {
-            config_loader = config_loader.endpoint_url(&endpoint_url);
+        if let Some(endpoint_url) = &config.endpoint {
+            config_loader = config_loader.endpoint_url(endpoint_url);
             info!(
-                "Using custom S3 endpoint: {} for bucket: {}",
-                endpoint_url, bucket_name
+                "Using custom S3 endpoint: {} for bucket: {} (path style: {})",
+                endpoint_url, config.bucket_name, config.force_path_style
             );
         }
 
-        let config = config_loader.load().await;
-        let client = Client::new(&config);
+        let sdk_config = config_loader.load().await;
+        let s3_config = aws_sdk_s3::config::Builder::from(&sdk_config)
+            .force_path_style(config.force_path_style)
+            .build();
 
-        info!("Initialized S3 backend with bucket: {}", bucket_name);
+        info!("Initialized S3 backend with bucket: {}", config.bucket_name);
         Ok(Self {
-            client,
-            bucket_name,
+            client: Client::from_conf(s3_config),
+            bucket_name: config.bucket_name,
+            config: S3Config::default(),
+            credentials: sdk_config.credentials_provider(),
         })
     }
 
+    pub fn with_config(mut self, config: S3Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Resolves the current credentials and checks that S3 accepts them for the
+    /// bucket, so expired or revoked role credentials surface in health checks
+    /// instead of as a `403 ExpiredToken` on the next data request.
+    pub async fn credentials_valid(&self) -> BackendResult<()> {
+        let provider = self.credentials.as_ref().ok_or_else(|| {
+            BackendError::Configuration("No S3 credentials provider configured".to_string())
+        })?;
+
+        let credentials = provider.provide_credentials().await.map_err(|e| {
+            BackendError::Configuration(format!("Failed to resolve S3 credentials: {}", e))
+        })?;
+
+        if credentials
+            .expiry()
+            .is_some_and(|expiry| expiry <= std::time::SystemTime::now())
+        {
+            return Err(BackendError::Configuration(
+                "S3 credentials have expired".to_string(),
+            ));
+        }
+
+        self.client
+            .head_bucket()
+            .bucket(&self.bucket_name)
+            .send()
+            .await
+            .map_err(|e| {
+                warn!(
+                    "S3 credential check failed for bucket {}: {:?}",
+                    self.bucket_name, e
+                );
+                BackendError::Configuration(format!(
+                    "S3 rejected credentials for bucket '{}': {}",
+                    self.bucket_name, e
+                ))
+            })?;
+
+        Ok(())
+    }
+
     fn s3_metadata_to_object_metadata(
         key: String,
         size: i64,
@@ -72,7 +204,7 @@ impl S3Backend {
             key: key.clone(),
             size: size as u64,
             content_type,
-            etag: etag.unwrap_or_else(|| {
+            etag: etag.map(|e| normalize_etag(&e)).unwrap_or_else(|| {
                 use sha2::{Digest, Sha256};
                 let mut hasher = Sha256::new();
                 hasher.update(key.as_bytes());
@@ -82,10 +214,200 @@ impl S3Backend {
             custom_metadata: metadata,
         }
     }
+
+    /// GETs the object, or the inclusive byte range `(first, last)` of it.
+    /// Ranged responses carry the whole object's size in `Content-Range`.
+    async fn fetch_object(
+        &self,
+        key: &str,
+        range: Option<(u64, u64)>,
+    ) -> BackendResult<ObjectData> {
+        match self
+            .client
+            .get_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .set_range(range.map(|(first, last)| format!("bytes={}-{}", first, last)))
+            .send()
+            .await
+        {
+            Ok(output) => {
+                let content_type = output.content_type().map(|s| s.to_string());
+                let etag = output.e_tag().map(|s| s.to_string());
+                let size = match range {
+                    Some(_) => output
+                        .content_range()
+                        .and_then(|range| range.rsplit_once('/')?.1.parse().ok())
+                        .unwrap_or(0),
+                    None => output.content_length().unwrap_or(0) as u64,
+                };
+
+                let metadata_map: HashMap<String, String> = output
+                    .metadata()
+                    .map(|m| m.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+                    .unwrap_or_default();
+
+                let last_modified = output
+                    .last_modified()
+                    .and_then(|dt| DateTime::parse_from_rfc3339(&dt.to_string()).ok())
+                    .map(|dt| dt.with_timezone(&Utc));
+
+                debug!("Retrieved object from S3: {} ({} bytes)", key, size);
+
+                let stream: ByteStream = if let Some(expected) = metadata_map
+                    .get(CRC32C_METADATA_KEY)
+                    .filter(|_| range.is_none())
+                {
+                    // The checksum covers the whole body, so it has to be buffered
+                    // before any bytes are handed to the caller. A range can't be
+                    // checked against it.
+                    let data = output
+                        .body
+                        .collect()
+                        .await
+                        .map_err(|e| {
+                            BackendError::Provider(format!(
+                                "Failed to read object '{}': {}",
+                                key, e
+                            ))
+                        })?
+                        .into_bytes();
+                    verify_crc32c(key, expected, &data)?;
+                    Box::pin(futures::stream::once(async move { Ok(data) }))
+                } else {
+                    // Convert AWS ByteStream to our ByteStream via AsyncRead
+                    let async_read = output.body.into_async_read();
+                    Box::pin(
+                        ReaderStream::new(async_read)
+                            .map(|result| result.map_err(std::io::Error::other)),
+                    )
+                };
+
+                Ok(ObjectData {
+                    metadata: Self::s3_metadata_to_object_metadata(
+                        key.to_string(),
+                        size as i64,
+                        last_modified,
+                        etag,
+                        content_type,
+                        metadata_map,
+                    ),
+                    stream,
+                })
+            }
+            // A range that starts past the end is refused with 416
+            Err(e)
+                if range.is_some()
+                    && e.raw_response()
+                        .is_some_and(|response| response.status().as_u16() == 416) =>
+            {
+                Ok(ObjectData::from_bytes(
+                    self.head_object(key).await?,
+                    Bytes::new(),
+                ))
+            }
+            Err(e) => {
+                if let Some(err) = classify_sdk_error(&e) {
+                    return Err(err);
+                }
+                if e.as_service_error()
+                    .is_some_and(GetObjectError::is_no_such_key)
+                {
+                    Err(BackendError::NotFound(key.to_string()))
+                } else {
+                    warn!("Failed to get object from S3: {}: {:?}", key, e);
+                    Err(BackendError::Provider(format!(
+                        "Failed to get object '{}': {}",
+                        key, e
+                    )))
+                }
+            }
+        }
+    }
+
+    // One ListObjectsV2 request, of at most 1000 keys whatever max_keys asks for
+    async fn list_page(
+        &self,
+        prefix: Option<&str>,
+        start_after: Option<&str>,
+        max_keys: Option<usize>,
+    ) -> BackendResult<Vec<ObjectMetadata>> {
+        let mut request = self.client.list_objects_v2().bucket(&self.bucket_name);
+
+        if let Some(p) = prefix {
+            request = request.prefix(p);
+        }
+
+        if let Some(key) = start_after {
+            request = request.start_after(key);
+        }
+
+        if let Some(max) = max_keys {
+            request = request.max_keys(max as i32);
+        }
+
+        match request.send().await {
+            Ok(output) => {
+                let objects = output
+                    .contents()
+                    .iter()
+                    .filter_map(|obj| {
+                        let key = obj.key()?.to_string();
+                        let size = obj.size().unwrap_or(0);
+                        let etag = obj.e_tag().map(|s| s.to_string());
+
+                        let last_modified = obj
+                            .last_modified()
+                            .and_then(|dt| DateTime::parse_from_rfc3339(&dt.to_string()).ok())
+                            .map(|dt| dt.with_timezone(&Utc));
+
+                        Some(Self::s3_metadata_to_object_metadata(
+                            key,
+                            size,
+                            last_modified,
+                            etag,
+                            None,
+                            HashMap::new(),
+                        ))
+                    })
+                    .collect();
+
+                debug!(
+                    "Listed {} objects from S3 with prefix: {:?}",
+                    output.key_count().unwrap_or(0),
+                    prefix
+                );
+
+                Ok(objects)
+            }
+            Err(e) => {
+                if let Some(err) = classify_sdk_error(&e) {
+                    return Err(err);
+                }
+                let error_msg = format!("{:?}", e);
+                if error_msg.contains("NoSuchBucket") {
+                    Err(BackendError::NotFound(format!(
+                        "bucket:{}",
+                        self.bucket_name
+                    )))
+                } else {
+                    warn!("Failed to list objects from S3: {:?}", e);
+                    Err(BackendError::Provider(format!(
+                        "Failed to list objects: {}",
+                        e
+                    )))
+                }
+            }
+        }
+    }
 }
 
 #[async_trait]
 impl Backend for S3Backend {
+    fn backend_type(&self) -> &'static str {
+        "s3"
+    }
+
     async fn init(&self) -> BackendResult<()> {
         match self
             .client
@@ -113,7 +435,7 @@ impl Backend for S3Backend {
         key: &str,
         mut stream: ByteStream,
         content_type: Option<String>,
-        custom_metadata: HashMap<String, String>,
+        mut custom_metadata: HashMap<String, String>,
     ) -> BackendResult<ObjectMetadata> {
         use sha2::{Digest, Sha256};
 
@@ -122,8 +444,7 @@ impl Backend for S3Backend {
         let mut data = Vec::new();
 
         while let Some(chunk_result) = stream.next().await {
-            let chunk = chunk_result
-                .map_err(|e| BackendError::Provider(format!("Failed to read stream: {}", e)))?;
+            let chunk = chunk_result.map_err(|e| BackendError::BodyStream(e.to_string()))?;
 
             hasher.update(&chunk);
             data.extend_from_slice(&chunk);
@@ -132,6 +453,8 @@ impl Backend for S3Backend {
         let size = data.len();
         let etag = hex::encode(hasher.finalize());
 
+        let checksum = self.config.checksum_crc32c().then(|| crc32c_base64(&data));
+
         // Convert to AWS ByteStream
         let body = AwsByteStream::from(data);
 
@@ -146,6 +469,13 @@ impl Backend for S3Backend {
             request = request.content_type(ct);
         }
 
+        if let Some(checksum) = checksum {
+            // S3 verifies the header on receipt; the metadata copy lets get_object
+            // verify the downloaded bytes without a separate GetObjectAttributes call.
+            request = request.checksum_crc32_c(&checksum);
+            custom_metadata.insert(CRC32C_METADATA_KEY.to_string(), checksum);
+        }
+
         for (k, v) in custom_metadata.iter() {
             request = request.metadata(k.clone(), v.clone());
         }
@@ -158,11 +488,22 @@ impl Backend for S3Backend {
                     size: size as u64,
                     content_type,
                     last_modified: Utc::now(),
-                    etag: output.e_tag().map(|s| s.to_string()).unwrap_or(etag),
+                    etag: output.e_tag().map(normalize_etag).unwrap_or(etag),
                     custom_metadata,
                 })
             }
             Err(e) => {
+                match classify_sdk_error(&e) {
+                    // S3 reports the size it was offered; fall back to what was sent
+                    Some(BackendError::EntityTooLarge { size: 0, max_size }) => {
+                        return Err(BackendError::EntityTooLarge {
+                            size: size as u64,
+                            max_size,
+                        })
+                    }
+                    Some(err) => return Err(err),
+                    None => {}
+                }
                 warn!("Failed to upload object to S3: {}: {:?}", key, e);
                 Err(BackendError::Provider(format!(
                     "Failed to upload object '{}': {}",
@@ -173,63 +514,18 @@ impl Backend for S3Backend {
     }
 
     async fn get_object(&self, key: &str) -> BackendResult<ObjectData> {
-        match self
-            .client
-            .get_object()
-            .bucket(&self.bucket_name)
-            .key(key)
-            .send()
-            .await
-        {
-            Ok(output) => {
-                let content_type = output.content_type().map(|s| s.to_string());
-                let etag = output.e_tag().map(|s| s.to_string());
-                let size = output.content_length().unwrap_or(0) as u64;
-
-                let metadata_map = output
-                    .metadata()
-                    .map(|m| m.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
-                    .unwrap_or_default();
-
-                let last_modified = output
-                    .last_modified()
-                    .and_then(|dt| DateTime::parse_from_rfc3339(&dt.to_string()).ok())
-                    .map(|dt| dt.with_timezone(&Utc));
-
-                debug!("Retrieved object from S3: {} ({} bytes)", key, size);
-
-                // Convert AWS ByteStream to our ByteStream via AsyncRead
-                let async_read = output.body.into_async_read();
-                let stream: ByteStream = Box::pin(
-                    ReaderStream::new(async_read)
-                        .map(|result| result.map_err(std::io::Error::other)),
-                );
+        self.fetch_object(key, None).await
+    }
 
-                Ok(ObjectData {
-                    metadata: Self::s3_metadata_to_object_metadata(
-                        key.to_string(),
-                        size as i64,
-                        last_modified,
-                        etag,
-                        content_type,
-                        metadata_map,
-                    ),
-                    stream,
-                })
-            }
-            Err(e) => {
-                let error_msg = format!("{:?}", e);
-                if error_msg.contains("NoSuchKey") || error_msg.contains("NotFound") {
-                    Err(BackendError::NotFound(key.to_string()))
-                } else {
-                    warn!("Failed to get object from S3: {}: {:?}", key, e);
-                    Err(BackendError::Provider(format!(
-                        "Failed to get object '{}': {}",
-                        key, e
-                    )))
-                }
-            }
+    async fn get_object_range(&self, key: &str, start: u64, len: u64) -> BackendResult<ObjectData> {
+        if len == 0 {
+            return Ok(ObjectData::from_bytes(
+                self.head_object(key).await?,
+                Bytes::new(),
+            ));
         }
+        self.fetch_object(key, Some((start, start.saturating_add(len - 1))))
+            .await
     }
 
     async fn head_object(&self, key: &str) -> BackendResult<ObjectMetadata> {
@@ -265,18 +561,68 @@ impl Backend for S3Backend {
                     metadata,
                 ))
             }
-            Err(e) => {
-                let error_msg = format!("{:?}", e);
-                if error_msg.contains("NotFound") {
-                    Err(BackendError::NotFound(key.to_string()))
-                } else {
-                    Err(BackendError::Provider(format!(
-                        "Failed to get metadata for '{}': {}",
-                        key, e
-                    )))
-                }
+            Err(e) => Err(head_error(key, e)),
+        }
+    }
+
+    // A HEAD request is already the cheapest existence check S3 offers; this
+    // only skips building the metadata.
+    async fn object_exists(&self, key: &str) -> BackendResult<bool> {
+        match self
+            .client
+            .head_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if is_missing(&e) => Ok(false),
+            Err(e) => Err(head_error(key, e)),
+        }
+    }
+
+    /// Copies within the bucket without downloading the object. S3 either
+    /// copies both the content type and the metadata or replaces both, so an
+    /// override of one carries the other over from the source.
+    async fn copy_object(
+        &self,
+        from: &str,
+        to: &str,
+        content_type: Option<String>,
+        metadata: Option<HashMap<String, String>>,
+    ) -> BackendResult<ObjectMetadata> {
+        let mut request = self
+            .client
+            .copy_object()
+            .bucket(&self.bucket_name)
+            .key(to)
+            .copy_source(copy_source(&self.bucket_name, from));
+
+        if content_type.is_some() || metadata.is_some() {
+            let source = self.head_object(from).await?;
+            let mut metadata = metadata.unwrap_or_else(|| source.custom_metadata.clone());
+            // The checksum describes the bytes, which the copy keeps
+            if let Some(checksum) = source.custom_metadata.get(CRC32C_METADATA_KEY) {
+                metadata.insert(CRC32C_METADATA_KEY.to_string(), checksum.clone());
             }
+            request = request
+                .metadata_directive(MetadataDirective::Replace)
+                .set_metadata(Some(metadata))
+                .set_content_type(content_type.or(source.content_type));
         }
+
+        request.send().await.map_err(|e| {
+            if e.code() == Some("NoSuchKey") {
+                return BackendError::NotFound(from.to_string());
+            }
+            classify_sdk_error(&e).unwrap_or_else(|| {
+                BackendError::Provider(format!("Failed to copy '{}' to '{}': {}", from, to, e))
+            })
+        })?;
+
+        debug!("Copied S3 object {} to {}", from, to);
+        self.head_object(to).await
     }
 
     async fn delete_object(&self, key: &str) -> BackendResult<()> {
@@ -292,7 +638,21 @@ impl Backend for S3Backend {
                 debug!("Deleted object from S3: {}", key);
                 Ok(())
             }
+            // Unlike the `NotFound` contract of other backends, a missing key is
+            // a successful delete here. S3 itself answers 204 for it; this covers
+            // S3-compatible servers that answer `NoSuchKey` instead. Callers that
+            // need the same behavior everywhere use `delete_object_if_exists`.
+            Err(e)
+                if e.code() == Some("NoSuchKey")
+                    || e.raw_response().map(|r| r.status().as_u16()) == Some(404) =>
+            {
+                debug!("Object already deleted from S3: {}", key);
+                Ok(())
+            }
             Err(e) => {
+                if let Some(err) = classify_sdk_error(&e) {
+                    return Err(err);
+                }
                 warn!("Failed to delete object from S3: {}: {:?}", key, e);
                 Err(BackendError::Provider(format!(
                     "Failed to delete object '{}': {}",
@@ -307,66 +667,44 @@ impl Backend for S3Backend {
         prefix: Option<&str>,
         max_keys: Option<usize>,
     ) -> BackendResult<Vec<ObjectMetadata>> {
-        let mut request = self.client.list_objects_v2().bucket(&self.bucket_name);
-
-        if let Some(p) = prefix {
-            request = request.prefix(p);
-        }
-
-        if let Some(max) = max_keys {
-            request = request.max_keys(max as i32);
-        }
-
-        match request.send().await {
-            Ok(output) => {
-                let objects = output
-                    .contents()
-                    .iter()
-                    .filter_map(|obj| {
-                        let key = obj.key()?.to_string();
-                        let size = obj.size().unwrap_or(0);
-                        let etag = obj.e_tag().map(|s| s.to_string());
-
-                        let last_modified = obj
-                            .last_modified()
-                            .and_then(|dt| DateTime::parse_from_rfc3339(&dt.to_string()).ok())
-                            .map(|dt| dt.with_timezone(&Utc));
+        self.list_page(prefix, None, max_keys).await
+    }
 
-                        Some(Self::s3_metadata_to_object_metadata(
-                            key,
-                            size,
-                            last_modified,
-                            etag,
-                            None,
-                            HashMap::new(),
-                        ))
-                    })
-                    .collect();
+    async fn list_objects_after(
+        &self,
+        prefix: Option<&str>,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> BackendResult<Vec<ObjectMetadata>> {
+        self.list_page(prefix, start_after, Some(max_keys)).await
+    }
 
-                debug!(
-                    "Listed {} objects from S3 with prefix: {:?}",
-                    output.key_count().unwrap_or(0),
-                    prefix
-                );
+    // list_objects reads a single page; counting follows the continuation
+    // tokens and adds up each page's KeyCount, which is exact per page
+    async fn count_objects(&self, prefix: Option<&str>) -> BackendResult<u64> {
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket_name)
+            .set_prefix(prefix.map(str::to_string))
+            .into_paginator()
+            .send();
 
-                Ok(objects)
-            }
-            Err(e) => {
-                let error_msg = format!("{:?}", e);
-                if error_msg.contains("NoSuchBucket") {
-                    Err(BackendError::NotFound(format!(
-                        "bucket:{}",
-                        self.bucket_name
-                    )))
-                } else {
-                    warn!("Failed to list objects from S3: {:?}", e);
-                    Err(BackendError::Provider(format!(
-                        "Failed to list objects: {}",
-                        e
-                    )))
-                }
-            }
+        let mut count = 0u64;
+        while let Some(page) = pages.next().await {
+            let page = page.map_err(|e| {
+                classify_sdk_error(&e).unwrap_or_else(|| {
+                    BackendError::Provider(format!("Failed to count objects: {}", e))
+                })
+            })?;
+            count += page
+                .key_count()
+                .map(|n| n.max(0) as u64)
+                .unwrap_or(page.contents().len() as u64);
         }
+
+        debug!("Counted {} objects in S3 with prefix: {:?}", count, prefix);
+        Ok(count)
     }
 
     async fn get_public_url(
@@ -374,6 +712,7 @@ impl Backend for S3Backend {
         key: &str,
         expiration_secs: u64,
         purpose: PublicUrlPurpose,
+        content_type: Option<&str>,
     ) -> BackendResult<String> {
         let presigning_config = PresigningConfig::expires_in(Duration::from_secs(expiration_secs))
             .map_err(|e| {
@@ -398,11 +737,14 @@ impl Backend for S3Backend {
                         key, e
                     ))
                 })?,
+            // A content type becomes a signed header, so S3 rejects uploads
+            // through this URL that send any other type.
             PublicUrlPurpose::Upload => self
                 .client
                 .put_object()
                 .bucket(&self.bucket_name)
                 .key(key)
+                .set_content_type(content_type.map(str::to_string))
                 .presigned(presigning_config)
                 .await
                 .map_err(|e| {
@@ -423,4 +765,765 @@ impl Backend for S3Backend {
         );
         Ok(presigned_request.uri().to_string())
     }
+
+    async fn health_check(&self) -> BackendResult<()> {
+        self.credentials_valid().await
+    }
+
+    /// Copies the object onto itself with the new storage class, keeping its
+    /// metadata. `Archive` maps to `GLACIER`. S3 can't copy archived objects,
+    /// so moving one back to `Standard` fails until it has been restored.
+    async fn set_storage_class(&self, key: &str, class: StorageClass) -> BackendResult<()> {
+        let target = match class {
+            StorageClass::Standard => S3StorageClass::Standard,
+            StorageClass::Archive => S3StorageClass::Glacier,
+        };
+
+        let head = self
+            .client
+            .head_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| head_error(key, e))?;
+        // HEAD leaves the header out for STANDARD objects
+        if head.storage_class().unwrap_or(&S3StorageClass::Standard) == &target {
+            return Ok(());
+        }
+
+        self.client
+            .copy_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .copy_source(copy_source(&self.bucket_name, key))
+            .storage_class(target.clone())
+            .metadata_directive(MetadataDirective::Copy)
+            .send()
+            .await
+            .map_err(|e| {
+                classify_sdk_error(&e).unwrap_or_else(|| {
+                    BackendError::Provider(format!(
+                        "Failed to change storage class of '{}': {}",
+                        key, e
+                    ))
+                })
+            })?;
+
+        info!("Moved S3 object {} to storage class {}", key, target);
+        Ok(())
+    }
+}
+
+/// Characters escaped in the key part of `x-amz-copy-source`.
+const COPY_SOURCE_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~')
+    .remove(b'/');
+
+/// The `x-amz-copy-source` value naming `key` in `bucket`.
+fn copy_source(bucket: &str, key: &str) -> String {
+    format!(
+        "{}/{}",
+        bucket,
+        utf8_percent_encode(key, COPY_SOURCE_ENCODE_SET)
+    )
+}
+
+fn crc32c_base64(data: &[u8]) -> String {
+    aws_smithy_types::base64::encode(crc32c::crc32c(data).to_be_bytes())
+}
+
+fn verify_crc32c(key: &str, expected: &str, data: &Bytes) -> BackendResult<()> {
+    let actual = crc32c_base64(data);
+    if actual != expected {
+        warn!("CRC32C mismatch for S3 object: {}", key);
+        return Err(BackendError::ChecksumMismatch {
+            key: key.to_string(),
+            expected: expected.to_string(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// HEAD responses have no body to carry `NoSuchKey`, so the SDK reports a
+/// missing key as its typed `NotFound` error, from the 404 status.
+fn is_missing(err: &SdkError<HeadObjectError, HttpResponse>) -> bool {
+    err.as_service_error()
+        .is_some_and(HeadObjectError::is_not_found)
+}
+
+fn head_error(key: &str, err: SdkError<HeadObjectError, HttpResponse>) -> BackendError {
+    if is_missing(&err) {
+        return BackendError::NotFound(key.to_string());
+    }
+    classify_sdk_error(&err).unwrap_or_else(|| {
+        BackendError::Provider(format!("Failed to get metadata for '{}': {}", key, err))
+    })
+}
+
+/// Maps S3 error codes the service forwards to clients as their own status.
+/// Everything else is left to the caller's fallback.
+fn classify_sdk_error<E: ProvideErrorMetadata>(
+    err: &SdkError<E, HttpResponse>,
+) -> Option<BackendError> {
+    let status = err
+        .raw_response()
+        .map(|response| response.status().as_u16());
+    match err.code() {
+        Some("AccessDenied") => Some(BackendError::PermissionDenied(
+            err.message().unwrap_or("Access Denied").to_string(),
+        )),
+        Some("SlowDown") => Some(BackendError::RateLimited {
+            retry_after_secs: err
+                .raw_response()
+                .and_then(|response| response.headers().get("retry-after"))
+                .and_then(|value| value.trim().parse().ok()),
+        }),
+        Some("RequestLimitExceeded") => Some(BackendError::RequestLimitExceeded),
+        Some("EntityTooLarge") => Some(BackendError::EntityTooLarge {
+            size: error_body_number(err, "ProposedSize").unwrap_or(0),
+            max_size: error_body_number(err, "MaxSizeAllowed").unwrap_or(S3_MAX_PUT_BYTES),
+        }),
+        // HEAD responses have no error body, only the status
+        None if status == Some(403) => {
+            Some(BackendError::PermissionDenied("Access Denied".to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// A numeric element of an S3 error body, such as `<ProposedSize>` of an
+/// `EntityTooLarge` error. The SDK only parses the code and message.
+fn error_body_number<E>(err: &SdkError<E, HttpResponse>, element: &str) -> Option<u64> {
+    let body = std::str::from_utf8(err.raw_response()?.body().bytes()?).ok()?;
+    let open = format!("<{}>", element);
+    let start = body.find(&open)? + open.len();
+    let end = start + body[start..].find('<')?;
+    body[start..end].trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_credential_types::credential_fn::provide_credentials_fn;
+    use aws_sdk_s3::config::Credentials;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_crc32c_base64_matches_s3_encoding() {
+        // S3 sends the big-endian CRC32C as base64; "hello world" is 0xc99465aa.
+        assert_eq!(crc32c_base64(b"hello world"), "yZRlqg==");
+    }
+
+    #[test]
+    fn test_verify_crc32c_detects_mismatch() {
+        let data = Bytes::from_static(b"hello world");
+        assert!(verify_crc32c("key", &crc32c_base64(&data), &data).is_ok());
+
+        let wrong = crc32c_base64(b"hello world!");
+        match verify_crc32c("key", &wrong, &data) {
+            Err(BackendError::ChecksumMismatch {
+                key,
+                expected,
+                actual,
+            }) => {
+                assert_eq!(key, "key");
+                assert_eq!(expected, wrong);
+                assert_eq!(actual, "yZRlqg==");
+            }
+            other => panic!("expected checksum mismatch, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_s3_config_deserialize() {
+        let config: S3Config = serde_json::from_str(r#"{"checksum_algorithm": "crc32c"}"#).unwrap();
+        assert!(config.checksum_crc32c());
+        assert!(!S3Config::default().checksum_crc32c());
+    }
+
+    /// Builds a backend against a mock endpoint whose credentials are issued by
+    /// a counting provider and live for `lifetime`.
+    async fn backend_with_rotating_credentials(
+        server: &mockito::Server,
+        lifetime: Duration,
+    ) -> (S3Backend, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let provider = provide_credentials_fn(move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                Ok(Credentials::new(
+                    format!("AKID{}", n),
+                    "secret",
+                    Some("token".to_string()),
+                    Some(std::time::SystemTime::now() + lifetime),
+                    "counting",
+                ))
+            }
+        });
+
+        let sdk_config = S3Backend::sdk_config_loader()
+            .region(Region::new("us-east-1"))
+            .endpoint_url(server.url())
+            .credentials_provider(provider)
+            .load()
+            .await;
+        let s3_config = aws_sdk_s3::config::Builder::from(&sdk_config)
+            .force_path_style(true)
+            .build();
+
+        let backend = S3Backend {
+            client: Client::from_conf(s3_config),
+            bucket_name: "test-bucket".to_string(),
+            config: S3Config::default(),
+            credentials: sdk_config.credentials_provider(),
+        };
+        (backend, calls)
+    }
+
+    #[tokio::test]
+    async fn test_sdk_config_caches_credentials() {
+        let mut server = mockito::Server::new_async().await;
+        let _head = server
+            .mock("HEAD", "/test-bucket/")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let (backend, calls) =
+            backend_with_rotating_credentials(&server, Duration::from_secs(3600)).await;
+        assert!(backend.client.config().identity_cache().is_some());
+
+        backend.init().await.unwrap();
+        backend.init().await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sdk_config_refreshes_expiring_credentials() {
+        let mut server = mockito::Server::new_async().await;
+        let _head = server
+            .mock("HEAD", "/test-bucket/")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        // Credentials inside the cache's refresh buffer are re-resolved per request
+        let (backend, calls) =
+            backend_with_rotating_credentials(&server, Duration::from_secs(1)).await;
+
+        backend.init().await.unwrap();
+        backend.init().await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_credentials_valid() {
+        let mut server = mockito::Server::new_async().await;
+        let (backend, _) =
+            backend_with_rotating_credentials(&server, Duration::from_secs(3600)).await;
+
+        let ok = server
+            .mock("HEAD", "/test-bucket/")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+        backend.credentials_valid().await.unwrap();
+        ok.assert_async().await;
+        ok.remove_async().await;
+
+        server
+            .mock("HEAD", "/test-bucket/")
+            .with_status(403)
+            .create_async()
+            .await;
+        assert!(matches!(
+            backend.credentials_valid().await,
+            Err(BackendError::Configuration(_))
+        ));
+    }
+
+    /// A backend against a mock endpoint that gives up after the first attempt,
+    /// so throttling responses surface without the SDK's retry backoff.
+    async fn backend_without_retries(server: &mockito::Server) -> S3Backend {
+        let sdk_config = S3Backend::sdk_config_loader()
+            .region(Region::new("us-east-1"))
+            .endpoint_url(server.url())
+            .credentials_provider(Credentials::for_tests())
+            .retry_config(aws_sdk_s3::config::retry::RetryConfig::disabled())
+            .load()
+            .await;
+        let s3_config = aws_sdk_s3::config::Builder::from(&sdk_config)
+            .force_path_style(true)
+            .build();
+
+        S3Backend {
+            client: Client::from_conf(s3_config),
+            bucket_name: "test-bucket".to_string(),
+            config: S3Config::default(),
+            credentials: sdk_config.credentials_provider(),
+        }
+    }
+
+    fn s3_error_body(code: &str, message: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <Error><Code>{}</Code><Message>{}</Message><RequestId>TEST</RequestId></Error>",
+            code, message
+        )
+    }
+
+    #[tokio::test]
+    async fn test_access_denied_maps_to_permission_denied() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/test-bucket/secret.txt")
+            .match_query(mockito::Matcher::Any)
+            .with_status(403)
+            .with_header("content-type", "application/xml")
+            .with_body(s3_error_body("AccessDenied", "Access Denied"))
+            .create_async()
+            .await;
+        server
+            .mock("HEAD", "/test-bucket/secret.txt")
+            .match_query(mockito::Matcher::Any)
+            .with_status(403)
+            .create_async()
+            .await;
+
+        let backend = backend_without_retries(&server).await;
+        match backend.get_object("secret.txt").await {
+            Err(BackendError::PermissionDenied(message)) => assert_eq!(message, "Access Denied"),
+            other => panic!("expected permission denied, got {:?}", other.err()),
+        }
+        assert!(matches!(
+            backend.head_object("secret.txt").await,
+            Err(BackendError::PermissionDenied(_))
+        ));
+        assert!(matches!(
+            backend.object_exists("secret.txt").await,
+            Err(BackendError::PermissionDenied(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_etags_are_stored_without_quotes() {
+        let mut server = mockito::Server::new_async().await;
+        let etag = "\"5d41402abc4b2a76b9719d911017c592\"";
+        server
+            .mock("PUT", "/test-bucket/hello.txt")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_header("etag", etag)
+            .create_async()
+            .await;
+        server
+            .mock("HEAD", "/test-bucket/hello.txt")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_header("content-length", "5")
+            .with_header("etag", etag)
+            .create_async()
+            .await;
+
+        let backend = backend_without_retries(&server).await;
+        let put = crate::BackendExt::put_object_reader(
+            &backend,
+            "hello.txt",
+            "hello".as_bytes(),
+            None,
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+        assert!(!put.etag.contains('"'));
+        assert_eq!(put.etag, "5d41402abc4b2a76b9719d911017c592");
+
+        let head = backend.head_object("hello.txt").await.unwrap();
+        assert_eq!(head.etag, put.etag);
+        assert_eq!(head.etag_normalized(), put.etag);
+    }
+
+    fn list_page(keys: &[&str], next_token: Option<&str>) -> String {
+        let contents: String = keys
+            .iter()
+            .map(|key| format!("<Contents><Key>{}</Key><Size>1</Size></Contents>", key))
+            .collect();
+        let continuation = next_token
+            .map(|token| {
+                format!(
+                    "<IsTruncated>true</IsTruncated><NextContinuationToken>{}</NextContinuationToken>",
+                    token
+                )
+            })
+            .unwrap_or_else(|| "<IsTruncated>false</IsTruncated>".to_string());
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <ListBucketResult><Name>test-bucket</Name><Prefix>logs/</Prefix>\
+             <KeyCount>{}</KeyCount><MaxKeys>1000</MaxKeys>{}{}</ListBucketResult>",
+            keys.len(),
+            continuation,
+            contents
+        )
+    }
+
+    #[tokio::test]
+    async fn test_count_objects_follows_continuation_tokens() {
+        let mut server = mockito::Server::new_async().await;
+        // The more specific mock goes first, so only the follow-up request hits it
+        let second = server
+            .mock("GET", "/test-bucket/")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "continuation-token".into(),
+                "page-2".into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body(list_page(&["logs/d", "logs/e"], None))
+            .create_async()
+            .await;
+        let first = server
+            .mock("GET", "/test-bucket/")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("list-type".into(), "2".into()),
+                mockito::Matcher::UrlEncoded("prefix".into(), "logs/".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body(list_page(&["logs/a", "logs/b", "logs/c"], Some("page-2")))
+            .create_async()
+            .await;
+
+        let backend = backend_without_retries(&server).await;
+        assert_eq!(backend.count_objects(Some("logs/")).await.unwrap(), 5);
+        first.assert_async().await;
+        second.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_missing_object_maps_to_not_found() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/test-bucket/missing.txt")
+            .match_query(mockito::Matcher::Any)
+            .with_status(404)
+            .with_header("content-type", "application/xml")
+            .with_body(s3_error_body(
+                "NoSuchKey",
+                "The specified key does not exist.",
+            ))
+            .create_async()
+            .await;
+        server
+            .mock("HEAD", "/test-bucket/missing.txt")
+            .match_query(mockito::Matcher::Any)
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let backend = backend_without_retries(&server).await;
+        match backend.get_object("missing.txt").await {
+            Err(BackendError::NotFound(key)) => assert_eq!(key, "missing.txt"),
+            other => panic!("expected not found, got {:?}", other.err()),
+        }
+        match backend.head_object("missing.txt").await {
+            Err(BackendError::NotFound(key)) => assert_eq!(key, "missing.txt"),
+            other => panic!("expected not found, got {:?}", other.err()),
+        }
+        assert!(!backend.object_exists("missing.txt").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_slow_down_maps_to_rate_limited() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("PUT", "/test-bucket/busy.txt")
+            .match_query(mockito::Matcher::Any)
+            .with_status(503)
+            .with_header("content-type", "application/xml")
+            .with_header("retry-after", "7")
+            .with_body(s3_error_body(
+                "SlowDown",
+                "Please reduce your request rate.",
+            ))
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/test-bucket/")
+            .match_query(mockito::Matcher::UrlEncoded("list-type".into(), "2".into()))
+            .with_status(503)
+            .with_header("content-type", "application/xml")
+            .with_body(s3_error_body(
+                "SlowDown",
+                "Please reduce your request rate.",
+            ))
+            .create_async()
+            .await;
+
+        let backend = backend_without_retries(&server).await;
+        let stream: ByteStream =
+            Box::pin(futures::stream::iter(vec![Ok(Bytes::from_static(b"data"))]));
+        assert!(matches!(
+            backend
+                .put_object("busy.txt", stream, None, HashMap::new())
+                .await,
+            Err(BackendError::RateLimited {
+                retry_after_secs: Some(7)
+            })
+        ));
+        assert!(matches!(
+            backend.list_objects(None, None).await,
+            Err(BackendError::RateLimited {
+                retry_after_secs: None
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_size_and_request_limits_are_classified() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("PUT", "/test-bucket/huge.bin")
+            .match_query(mockito::Matcher::Any)
+            .with_status(400)
+            .with_header("content-type", "application/xml")
+            .with_body(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                 <Error><Code>EntityTooLarge</Code>\
+                 <Message>Your proposed upload exceeds the maximum allowed size</Message>\
+                 <ProposedSize>6442450944</ProposedSize>\
+                 <MaxSizeAllowed>5368709120</MaxSizeAllowed>\
+                 <RequestId>TEST</RequestId></Error>",
+            )
+            .create_async()
+            .await;
+        server
+            .mock("PUT", "/test-bucket/quota.bin")
+            .match_query(mockito::Matcher::Any)
+            .with_status(503)
+            .with_header("content-type", "application/xml")
+            .with_body(s3_error_body(
+                "RequestLimitExceeded",
+                "Request limit exceeded.",
+            ))
+            .create_async()
+            .await;
+
+        let backend = backend_without_retries(&server).await;
+        let put = |key: &'static str| {
+            let stream: ByteStream =
+                Box::pin(futures::stream::iter(vec![Ok(Bytes::from_static(b"data"))]));
+            backend.put_object(key, stream, None, HashMap::new())
+        };
+        match put("huge.bin").await {
+            Err(BackendError::EntityTooLarge { size, max_size }) => {
+                assert_eq!(size, 6_442_450_944);
+                assert_eq!(max_size, S3_MAX_PUT_BYTES);
+            }
+            other => panic!("expected entity too large, got {:?}", other.err()),
+        }
+        assert!(matches!(
+            put("quota.bin").await,
+            Err(BackendError::RequestLimitExceeded)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_presigned_upload_url_signs_content_type() {
+        let server = mockito::Server::new_async().await;
+        let backend = backend_without_retries(&server).await;
+
+        let url = backend
+            .get_public_url(
+                "photo.png",
+                300,
+                PublicUrlPurpose::Upload,
+                Some("image/png"),
+            )
+            .await
+            .unwrap();
+        assert!(
+            url.contains("X-Amz-SignedHeaders=content-type%3Bhost"),
+            "{}",
+            url
+        );
+
+        let url = backend
+            .get_public_url("photo.png", 300, PublicUrlPurpose::Upload, None)
+            .await
+            .unwrap();
+        assert!(url.contains("X-Amz-SignedHeaders=host"), "{}", url);
+    }
+
+    #[tokio::test]
+    async fn test_archive_copies_object_as_glacier() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("HEAD", "/test-bucket/logs/old%20run.txt")
+            .with_status(200)
+            .with_header("content-length", "3")
+            .create_async()
+            .await;
+        let copy = server
+            .mock("PUT", "/test-bucket/logs/old%20run.txt")
+            .match_query(mockito::Matcher::Any)
+            .match_header("x-amz-copy-source", "test-bucket/logs/old%20run.txt")
+            .match_header("x-amz-storage-class", "GLACIER")
+            .match_header("x-amz-metadata-directive", "COPY")
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                 <CopyObjectResult><ETag>\"abc\"</ETag>\
+                 <LastModified>2024-01-01T00:00:00.000Z</LastModified></CopyObjectResult>",
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let backend = backend_without_retries(&server).await;
+        backend
+            .set_storage_class("logs/old run.txt", StorageClass::Archive)
+            .await
+            .unwrap();
+        copy.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_minio_backend_uses_path_style() {
+        let mut server = mockito::Server::new_async().await;
+        let head = server
+            .mock("HEAD", "/test-bucket/dir/file.txt")
+            .match_header(
+                "authorization",
+                mockito::Matcher::Regex("Credential=minioadmin/".to_string()),
+            )
+            .with_status(200)
+            .with_header("content-length", "5")
+            .with_header("etag", "\"abc\"")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let backend = S3Backend::new_for_minio(
+            "test-bucket".to_string(),
+            server.url(),
+            "minioadmin".to_string(),
+            "minioadmin".to_string(),
+        )
+        .await
+        .unwrap();
+        let metadata = backend.head_object("dir/file.txt").await.unwrap();
+        assert_eq!(metadata.size, 5);
+        head.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_copy_with_content_type_override_replaces_metadata() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("HEAD", "/test-bucket/src.txt")
+            .with_status(200)
+            .with_header("content-length", "3")
+            .with_header("content-type", "text/plain")
+            .with_header("x-amz-meta-owner", "alice")
+            .with_header("x-amz-meta-crc32c", "yZRlqg==")
+            .create_async()
+            .await;
+        let copy = server
+            .mock("PUT", "/test-bucket/dst.json")
+            .match_query(mockito::Matcher::Any)
+            .match_header("x-amz-copy-source", "test-bucket/src.txt")
+            .match_header("x-amz-metadata-directive", "REPLACE")
+            .match_header("content-type", "application/json")
+            .match_header("x-amz-meta-owner", "alice")
+            .match_header("x-amz-meta-crc32c", "yZRlqg==")
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                 <CopyObjectResult><ETag>\"abc\"</ETag>\
+                 <LastModified>2024-01-01T00:00:00.000Z</LastModified></CopyObjectResult>",
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("HEAD", "/test-bucket/dst.json")
+            .with_status(200)
+            .with_header("content-length", "3")
+            .with_header("content-type", "application/json")
+            .with_header("etag", "\"abc\"")
+            .create_async()
+            .await;
+
+        let backend = backend_without_retries(&server).await;
+        let copied = backend
+            .copy_object(
+                "src.txt",
+                "dst.json",
+                Some("application/json".to_string()),
+                None,
+            )
+            .await
+            .unwrap();
+        copy.assert_async().await;
+        assert_eq!(copied.key, "dst.json");
+        assert_eq!(copied.etag, "abc");
+        assert_eq!(copied.content_type.as_deref(), Some("application/json"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_missing_object_succeeds() {
+        let mut server = mockito::Server::new_async().await;
+        let delete = server
+            .mock("DELETE", "/test-bucket/gone.txt")
+            .match_query(mockito::Matcher::Any)
+            .with_status(404)
+            .with_header("content-type", "application/xml")
+            .with_body(s3_error_body(
+                "NoSuchKey",
+                "The specified key does not exist.",
+            ))
+            .expect(2)
+            .create_async()
+            .await;
+
+        let backend = backend_without_retries(&server).await;
+        backend.delete_object("gone.txt").await.unwrap();
+        backend.delete_object_if_exists("gone.txt").await.unwrap();
+        delete.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_archived_object_is_not_copied_again() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("HEAD", "/test-bucket/logs/old.txt")
+            .with_status(200)
+            .with_header("content-length", "3")
+            .with_header("x-amz-storage-class", "GLACIER")
+            .create_async()
+            .await;
+        let copy = server
+            .mock("PUT", "/test-bucket/logs/old.txt")
+            .match_query(mockito::Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let backend = backend_without_retries(&server).await;
+        backend
+            .set_storage_class("logs/old.txt", StorageClass::Archive)
+            .await
+            .unwrap();
+        copy.assert_async().await;
+    }
 }
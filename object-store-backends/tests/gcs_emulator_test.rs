@@ -0,0 +1,74 @@
+use futures::StreamExt;
+use google_cloud_storage::client::ClientConfig;
+use google_cloud_storage::http::buckets::insert::{InsertBucketParam, InsertBucketRequest};
+use object_store_backends::gcs::GcsBackend;
+use object_store_backends::{Backend, BackendExt};
+use std::collections::HashMap;
+use testcontainers::core::{IntoContainerPort, WaitFor};
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, GenericImage, ImageExt};
+
+const BUCKET: &str = "test-bucket";
+
+async fn start_fake_gcs() -> (ContainerAsync<GenericImage>, GcsBackend) {
+    let container = GenericImage::new("fsouza/fake-gcs-server", "1.52")
+        .with_exposed_port(4443.tcp())
+        .with_wait_for(WaitFor::message_on_stderr("server started at"))
+        .with_cmd(["-scheme", "http", "-port", "4443"])
+        .start()
+        .await
+        .unwrap();
+    let port = container.get_host_port_ipv4(4443).await.unwrap();
+
+    let config = || {
+        ClientConfig {
+            storage_endpoint: format!("http://127.0.0.1:{}", port),
+            ..Default::default()
+        }
+        .anonymous()
+    };
+    let backend = GcsBackend::from_client_config(BUCKET.to_string(), config());
+
+    google_cloud_storage::client::Client::new(config())
+        .insert_bucket(&InsertBucketRequest {
+            name: BUCKET.to_string(),
+            param: InsertBucketParam {
+                project: "test-project".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    (container, backend)
+}
+
+#[tokio::test]
+#[ignore] // Needs Docker. Run with: cargo test --test gcs_emulator_test -- --ignored
+async fn test_list_objects_returns_every_page() {
+    let (_container, backend) = start_fake_gcs().await;
+
+    futures::stream::iter(0..2000)
+        .for_each_concurrent(32, |i| {
+            let backend = &backend;
+            async move {
+                backend
+                    .put_object_reader(
+                        &format!("objects/{:04}.txt", i),
+                        "x".as_bytes(),
+                        None,
+                        HashMap::new(),
+                    )
+                    .await
+                    .unwrap();
+            }
+        })
+        .await;
+
+    let objects = backend.list_objects(None, None).await.unwrap();
+    assert_eq!(objects.len(), 2000);
+
+    let limited = backend.list_objects(None, Some(1500)).await.unwrap();
+    assert_eq!(limited.len(), 1500);
+}
@@ -0,0 +1,82 @@
+use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+use futures::StreamExt;
+use object_store_backends::s3::S3Backend;
+use object_store_backends::{Backend, BackendExt};
+use std::collections::HashMap;
+use testcontainers::core::{IntoContainerPort, WaitFor};
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, GenericImage, ImageExt};
+
+const BUCKET: &str = "test-bucket";
+const ACCESS_KEY: &str = "minioadmin";
+const SECRET_KEY: &str = "minioadmin";
+
+async fn start_minio() -> (ContainerAsync<GenericImage>, String) {
+    let container = GenericImage::new("minio/minio", "RELEASE.2024-10-13T13-34-11Z")
+        .with_exposed_port(9000.tcp())
+        .with_wait_for(WaitFor::message_on_stdout("API:"))
+        .with_env_var("MINIO_ROOT_USER", ACCESS_KEY)
+        .with_env_var("MINIO_ROOT_PASSWORD", SECRET_KEY)
+        .with_cmd(["server", "/data"])
+        .start()
+        .await
+        .unwrap();
+    let port = container.get_host_port_ipv4(9000).await.unwrap();
+    (container, format!("http://127.0.0.1:{}", port))
+}
+
+// The backend has no way to create buckets, so this goes through the SDK
+async fn create_bucket(endpoint: &str) {
+    let config = aws_sdk_s3::Config::builder()
+        .behavior_version(BehaviorVersion::latest())
+        .region(Region::new("us-east-1"))
+        .endpoint_url(endpoint)
+        .credentials_provider(Credentials::new(ACCESS_KEY, SECRET_KEY, None, None, "test"))
+        .force_path_style(true)
+        .build();
+    aws_sdk_s3::Client::from_conf(config)
+        .create_bucket()
+        .bucket(BUCKET)
+        .send()
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore] // Needs Docker. Run with: cargo test --test minio_test -- --ignored
+async fn test_put_and_get_object_on_minio() {
+    let (_container, endpoint) = start_minio().await;
+    create_bucket(&endpoint).await;
+
+    let backend = S3Backend::new_for_minio(
+        BUCKET.to_string(),
+        endpoint,
+        ACCESS_KEY.to_string(),
+        SECRET_KEY.to_string(),
+    )
+    .await
+    .unwrap();
+    backend.init().await.unwrap();
+
+    backend
+        .put_object_reader(
+            "docs/hello.txt",
+            "hello minio".as_bytes(),
+            Some("text/plain".to_string()),
+            HashMap::from([("owner".to_string(), "alice".to_string())]),
+        )
+        .await
+        .unwrap();
+
+    let mut object = backend.get_object("docs/hello.txt").await.unwrap();
+    let mut data = Vec::new();
+    while let Some(chunk) = object.stream.next().await {
+        data.extend_from_slice(&chunk.unwrap());
+    }
+    assert_eq!(data, b"hello minio");
+    assert_eq!(object.metadata.content_type.as_deref(), Some("text/plain"));
+    assert_eq!(object.metadata.custom_metadata["owner"], "alice");
+
+    let listed = backend.list_objects(Some("docs/"), None).await.unwrap();
+    assert_eq!(listed.len(), 1);
+}
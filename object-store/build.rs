@@ -0,0 +1,10 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Vendored so building doesn't need a system protoc
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    println!("cargo:rerun-if-changed=../proto/object_store.proto");
+    tonic_build::configure()
+        .build_client(false)
+        .bytes(["."])
+        .compile_protos(&["../proto/object_store.proto"], &["../proto"])?;
+    Ok(())
+}
@@ -1,22 +1,96 @@
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
 use axum::body::Body;
-use axum::extract::{Path, Query, State};
-use axum::http::{HeaderMap, StatusCode};
+use axum::extract::{Extension, FromRequest, Path, Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use futures::StreamExt;
+use futures::{StreamExt, TryStreamExt};
+use object_store_backends::{ByteStream, CompactReport, ObjectMetadata};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
+use tokio_util::io::{ReaderStream, StreamReader};
 
-use crate::error::ServiceResult;
-use crate::metadata::Bucket;
-use crate::service::ObjectStoreService;
+use crate::auth::{hash_key, ApiKeyRecord, Permission};
+use crate::config::{MetadataHeaderStyle, AMZ_META_PREFIX, OBJECT_META_PREFIX};
+use crate::error::{ServiceError, ServiceResult};
+use crate::idempotency::IDEMPOTENCY_KEY;
+use crate::metadata::{Bucket, CacheStats, LifecycleRule};
+use crate::middleware::bearer_key;
+use crate::pipeline::PipelineConfig;
+use crate::rate_limit::RateLimitConfig;
+use crate::replication::ReplicationConfig;
+use crate::service::{
+    BucketSortField, ListBucketsOptions, ListObjectsOptions, ObjectStoreService, RecycleBinEntry,
+    SortField, SortOrder,
+};
+use crate::validation::{metadata_error, validate_content_type, validate_metadata};
 
 pub type SharedService = Arc<ObjectStoreService>;
 
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateBucketRequest {
     pub name: String,
+    #[serde(default)]
+    pub max_object_bytes: Option<u64>,
+    #[serde(default)]
+    pub recycle_bin_enabled: Option<bool>,
+    #[serde(default)]
+    pub recycle_bin_retention_days: Option<u32>,
+    #[serde(default)]
+    pub replication: Option<ReplicationConfig>,
+    #[serde(default)]
+    pub deduplication_enabled: Option<bool>,
+    /// Rejected when the server is built without the `hooks` feature.
+    #[serde(default)]
+    pub pipeline: Option<PipelineConfig>,
+}
+
+impl CreateBucketRequest {
+    fn validate(&self, service: &ObjectStoreService) -> ServiceResult<()> {
+        if let Some(replication) = &self.replication {
+            service.validate_replication(replication)?;
+        }
+        if let Some(pipeline) = &self.pipeline {
+            service.validate_pipeline(pipeline)?;
+        }
+        Ok(())
+    }
+
+    fn apply(&self, bucket: &mut Bucket) {
+        if let Some(max) = self.max_object_bytes {
+            bucket.max_object_bytes = Some(max);
+        }
+        if let Some(enabled) = self.recycle_bin_enabled {
+            bucket.recycle_bin_enabled = enabled;
+        }
+        if let Some(days) = self.recycle_bin_retention_days {
+            bucket.recycle_bin_retention_days = days;
+        }
+        if let Some(replication) = &self.replication {
+            bucket.replication = Some(replication.clone());
+        }
+        if let Some(enabled) = self.deduplication_enabled {
+            bucket.deduplication_enabled = enabled;
+        }
+        if let Some(pipeline) = &self.pipeline {
+            bucket.pipeline = pipeline.clone();
+        }
+    }
+}
+
+/// Body of `PATCH /buckets/:bucket`. Omitted fields are left unchanged; tags
+/// are merged into the existing ones and a `null` value removes a tag.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateBucketRequest {
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: HashMap<String, Option<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,6 +98,35 @@ pub struct BucketResponse {
     pub id: String,
     pub name: String,
     pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_object_bytes: Option<u64>,
+    #[serde(default)]
+    pub recycle_bin_enabled: bool,
+    #[serde(default)]
+    pub recycle_bin_retention_days: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lifecycle_rules: Vec<LifecycleRule>,
+    #[serde(default)]
+    pub deduplication_enabled: bool,
+    #[serde(default, skip_serializing_if = "PipelineConfig::is_empty")]
+    pub pipeline: PipelineConfig,
+}
+
+/// Body of `GET` and `PUT /buckets/:bucket/lifecycle`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LifecycleConfiguration {
+    pub rules: Vec<LifecycleRule>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReplicationLagResponse {
+    pub pending_objects: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,18 +147,144 @@ pub struct ObjectMetadataResponse {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ListObjectsResponse {
     pub objects: Vec<ObjectMetadataResponse>,
+    /// Objects matching the query across every page, with `include_count=true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_count: Option<u64>,
+}
+
+/// An entry of a `full_metadata=false` listing.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ObjectSummaryResponse {
+    pub key: String,
+    pub size: u64,
+    pub etag: String,
+    pub last_modified: String,
+}
+
+impl From<ObjectMetadata> for ObjectSummaryResponse {
+    fn from(metadata: ObjectMetadata) -> Self {
+        Self {
+            key: metadata.key,
+            size: metadata.size,
+            etag: metadata.etag,
+            last_modified: metadata.last_modified.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListObjectSummariesResponse {
+    pub objects: Vec<ObjectSummaryResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_count: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ListObjectsQuery {
     pub prefix: Option<String>,
     pub max_keys: Option<usize>,
+    #[serde(default)]
+    pub streaming: bool,
+    pub sort_by: Option<SortField>,
+    pub sort_order: Option<SortOrder>,
+    pub metadata_filter: Option<String>,
+    /// RFC 3339, e.g. `2024-01-31T00:00:00Z`.
+    pub modified_after: Option<chrono::DateTime<chrono::Utc>>,
+    pub modified_before: Option<chrono::DateTime<chrono::Utc>>,
+    pub start_after: Option<String>,
+    #[serde(default)]
+    pub include_count: bool,
+    /// With `false`, objects are listed without content types and custom
+    /// metadata, which is much faster on backends that store those per file.
+    pub full_metadata: Option<bool>,
+}
+
+impl ListObjectsQuery {
+    pub fn to_options(&self) -> ServiceResult<ListObjectsOptions> {
+        Ok(ListObjectsOptions {
+            prefix: self.prefix.clone(),
+            max_keys: self.max_keys,
+            sort_by: self.sort_by,
+            sort_order: self.sort_order,
+            metadata_filter: self
+                .metadata_filter
+                .as_deref()
+                .map(str::parse)
+                .transpose()?,
+            modified_after: self.modified_after,
+            modified_before: self.modified_before,
+            start_after: self.start_after.clone(),
+            include_count: self.include_count,
+            summary_only: self.full_metadata == Some(false),
+        })
+    }
+}
+
+/// Query of `GET /buckets`, e.g. `?sort_by=name&order=asc&prefix=prod-`.
+/// Without `order`, names sort ascending and creation times descending.
+#[derive(Debug, Default, Deserialize)]
+pub struct ListBucketsQuery {
+    pub sort_by: Option<BucketSortField>,
+    pub order: Option<SortOrder>,
+    pub prefix: Option<String>,
+}
+
+impl ListBucketsQuery {
+    pub fn to_options(&self) -> ListBucketsOptions {
+        let sort_by = self.sort_by.unwrap_or_default();
+        ListBucketsOptions {
+            sort_by,
+            sort_order: self.order.unwrap_or_else(|| sort_by.default_order()),
+            name_prefix: self.prefix.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecycleBinEntryResponse {
+    pub key: String,
+    pub version: String,
+    pub size: u64,
+    pub deleted_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListRecycleBinResponse {
+    pub objects: Vec<RecycleBinEntryResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RestoreObjectRequest {
+    pub key: String,
+    pub version: String,
+}
+
+/// Body of `POST /buckets/:bucket/objects/*key/copy`. `content_type` and
+/// `metadata` replace the source's values when given.
+#[derive(Debug, Deserialize)]
+pub struct CopyObjectRequest {
+    pub source_bucket: String,
+    pub source_key: String,
+    pub content_type: Option<String>,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenameObjectRequest {
+    pub new_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PurgeRecycleBinQuery {
+    pub key: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct GetPublicUrlQuery {
     pub expiration_secs: Option<u64>,
     pub purpose: Option<object_store_backends::PublicUrlPurpose>,
+    /// Only honored for upload URLs: the `Content-Type` the upload must use.
+    pub content_type: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -64,12 +293,108 @@ pub struct PublicUrlResponse {
     pub expires_in: u64,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RevokeUrlRequest {
+    pub url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokeUrlResponse {
+    /// When the revocation lapses, because the URL expires then anyway.
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub label: String,
+    #[serde(default = "default_api_key_permissions")]
+    pub permissions: Vec<Permission>,
+    /// Overrides the server's `rate_limit` for this key.
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+fn default_api_key_permissions() -> Vec<Permission> {
+    vec![Permission::Read, Permission::Write]
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateApiKeyResponse {
+    /// The raw key. It is not stored and cannot be retrieved again.
+    pub key: String,
+    #[serde(flatten)]
+    pub info: ApiKeyResponse,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiKeyResponse {
+    pub hash: String,
+    pub label: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub permissions: Vec<Permission>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+impl ApiKeyResponse {
+    fn new(hash: String, record: ApiKeyRecord) -> Self {
+        Self {
+            hash,
+            label: record.label,
+            created_at: record.created_at,
+            last_used_at: record.last_used_at,
+            permissions: record.permissions,
+            rate_limit: record.rate_limit,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListApiKeysResponse {
+    pub api_keys: Vec<ApiKeyResponse>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InventoryFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InventoryQuery {
+    #[serde(default)]
+    pub format: InventoryFormat,
+}
+
 impl From<Bucket> for BucketResponse {
     fn from(bucket: Bucket) -> Self {
         Self {
             id: bucket.id,
             name: bucket.name,
             created_at: bucket.created_at,
+            updated_at: bucket.updated_at,
+            description: bucket.description,
+            tags: bucket.tags,
+            max_object_bytes: bucket.max_object_bytes,
+            recycle_bin_enabled: bucket.recycle_bin_enabled,
+            recycle_bin_retention_days: bucket.recycle_bin_retention_days,
+            lifecycle_rules: bucket.lifecycle_rules,
+            deduplication_enabled: bucket.deduplication_enabled,
+            pipeline: bucket.pipeline,
+        }
+    }
+}
+
+impl From<RecycleBinEntry> for RecycleBinEntryResponse {
+    fn from(entry: RecycleBinEntry) -> Self {
+        Self {
+            key: entry.key,
+            version: entry.version,
+            size: entry.size,
+            deleted_at: entry.deleted_at.to_rfc3339(),
         }
     }
 }
@@ -87,33 +412,206 @@ impl From<object_store_backends::ObjectMetadata> for ObjectMetadataResponse {
     }
 }
 
-pub async fn health_check() -> impl IntoResponse {
+/// What `/ping` reports about the running server, installed on the internal
+/// router as an `Extension`.
+#[derive(Debug, Clone)]
+pub struct ServerInfo {
+    pub started_at: Instant,
+    /// As returned by [`BackendConfig::type_name`](crate::config::BackendConfig::type_name).
+    pub backend_type: &'static str,
+}
+
+impl ServerInfo {
+    pub fn new(started_at: Instant, backend_type: &'static str) -> Self {
+        Self {
+            started_at,
+            backend_type,
+        }
+    }
+}
+
+pub async fn ping(Extension(info): Extension<ServerInfo>) -> impl IntoResponse {
     Json(serde_json::json!({
-        "status": "healthy",
-        "service": "object-store"
+        "version": env!("CARGO_PKG_VERSION"),
+        "uptime_secs": info.started_at.elapsed().as_secs(),
+        "backend_type": info.backend_type,
     }))
 }
 
+/// Backend probes slower than this report the service as degraded.
+const DEGRADED_LATENCY_MS: u64 = 1000;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HealthQuery {
+    /// Also probe the storage backend instead of only reporting that the
+    /// process is up.
+    #[serde(default)]
+    pub deep: bool,
+}
+
+/// A slow, throttling or read-only backend still returns `200` so load
+/// balancers don't pull instances over a blip; only an unreachable backend
+/// returns `503`.
+pub async fn health_check(
+    State(service): State<SharedService>,
+    Query(query): Query<HealthQuery>,
+) -> Response {
+    if !query.deep {
+        return Json(serde_json::json!({
+            "status": "healthy",
+            "service": "object-store"
+        }))
+        .into_response();
+    }
+
+    match service.health_check().await {
+        Ok(backend) => {
+            let status = if !backend.writable || backend.latency_ms > DEGRADED_LATENCY_MS {
+                "degraded"
+            } else {
+                "healthy"
+            };
+            Json(serde_json::json!({
+                "status": status,
+                "service": "object-store",
+                "backend": backend,
+            }))
+            .into_response()
+        }
+        Err(e) => {
+            let status_code = match e {
+                ServiceError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (
+                status_code,
+                Json(serde_json::json!({
+                    "status": "unhealthy",
+                    "service": "object-store",
+                    "error": e.to_string(),
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub async fn metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        crate::metrics::prometheus_handle().render(),
+    )
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScanBucketResponse {
+    pub bucket: String,
+    pub object_count: u64,
+    pub total_bytes: u64,
+}
+
+pub async fn scan_bucket(
+    State(service): State<SharedService>,
+    Path(bucket): Path<String>,
+) -> ServiceResult<Json<ScanBucketResponse>> {
+    let stats = service.scan_bucket(&bucket).await?;
+    Ok(Json(ScanBucketResponse {
+        bucket,
+        object_count: stats.object_count,
+        total_bytes: stats.total_bytes,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompactQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+pub async fn compact_backend(
+    State(service): State<SharedService>,
+    Query(query): Query<CompactQuery>,
+) -> ServiceResult<Json<CompactReport>> {
+    Ok(Json(service.compact_backend(query.dry_run).await?))
+}
+
+pub async fn cache_stats(State(service): State<SharedService>) -> Json<CacheStats> {
+    Json(service.metadata().cache_stats())
+}
+
+pub async fn create_api_key(
+    State(service): State<SharedService>,
+    Json(req): Json<CreateApiKeyRequest>,
+) -> ServiceResult<Json<CreateApiKeyResponse>> {
+    let (key, hash, record) = service
+        .create_api_key(&req.label, req.permissions, req.rate_limit)
+        .await?;
+    Ok(Json(CreateApiKeyResponse {
+        key,
+        info: ApiKeyResponse::new(hash, record),
+    }))
+}
+
+pub async fn list_api_keys(
+    State(service): State<SharedService>,
+) -> ServiceResult<Json<ListApiKeysResponse>> {
+    let keys = service.list_api_keys().await?;
+    Ok(Json(ListApiKeysResponse {
+        api_keys: keys
+            .into_iter()
+            .map(|(hash, record)| ApiKeyResponse::new(hash, record))
+            .collect(),
+    }))
+}
+
+pub async fn delete_api_key(
+    State(service): State<SharedService>,
+    Path(hash): Path<String>,
+) -> ServiceResult<StatusCode> {
+    service.revoke_api_key(&hash).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub async fn create_bucket(
     State(service): State<SharedService>,
+    headers: HeaderMap,
     Json(payload): Json<CreateBucketRequest>,
-) -> ServiceResult<Json<BucketResponse>> {
-    let bucket = service.create_bucket(&payload.name).await?;
-    Ok(Json(bucket.into()))
+) -> ServiceResult<Response> {
+    let idempotency_key = idempotency_key(&headers, &format!("POST /buckets {}", payload.name));
+    if let Some(cached) = idempotency_key
+        .as_deref()
+        .and_then(|key| service.idempotency().get(key))
+    {
+        return Ok(cached.into_response());
+    }
+
+    payload.validate(&service)?;
+    let bucket = service
+        .create_bucket_with(&payload.name, |b| payload.apply(b))
+        .await?;
+    Ok(service.idempotency().respond(
+        idempotency_key,
+        StatusCode::OK,
+        &BucketResponse::from(bucket),
+    )?)
 }
 
 pub async fn upsert_bucket(
     State(service): State<SharedService>,
     Json(payload): Json<CreateBucketRequest>,
 ) -> ServiceResult<Json<BucketResponse>> {
-    let bucket = service.upsert_bucket(&payload.name).await?;
+    payload.validate(&service)?;
+    let bucket = service
+        .upsert_bucket_with(&payload.name, |b| payload.apply(b))
+        .await?;
     Ok(Json(bucket.into()))
 }
 
 pub async fn list_buckets(
     State(service): State<SharedService>,
+    Query(params): Query<ListBucketsQuery>,
 ) -> ServiceResult<Json<ListBucketsResponse>> {
-    let buckets = service.list_buckets().await?;
+    let buckets = service.list_buckets(&params.to_options()).await?;
     let response = ListBucketsResponse {
         buckets: buckets.into_iter().map(|b| b.into()).collect(),
     };
@@ -128,6 +626,45 @@ pub async fn get_bucket_by_id(
     Ok(Json(bucket.into()))
 }
 
+pub async fn update_bucket(
+    State(service): State<SharedService>,
+    Path(bucket): Path<String>,
+    Json(payload): Json<UpdateBucketRequest>,
+) -> ServiceResult<Json<BucketResponse>> {
+    let bucket = service
+        .update_bucket_metadata(&bucket, payload.description, payload.tags)
+        .await?;
+    Ok(Json(bucket.into()))
+}
+
+pub async fn get_lifecycle(
+    State(service): State<SharedService>,
+    Path(bucket): Path<String>,
+) -> ServiceResult<Json<LifecycleConfiguration>> {
+    Ok(Json(LifecycleConfiguration {
+        rules: service.get_lifecycle_rules(&bucket).await?,
+    }))
+}
+
+pub async fn put_lifecycle(
+    State(service): State<SharedService>,
+    Path(bucket): Path<String>,
+    Json(payload): Json<LifecycleConfiguration>,
+) -> ServiceResult<Json<LifecycleConfiguration>> {
+    Ok(Json(LifecycleConfiguration {
+        rules: service.put_lifecycle_rules(&bucket, payload.rules).await?,
+    }))
+}
+
+pub async fn get_replication_lag(
+    State(service): State<SharedService>,
+    Path(bucket): Path<String>,
+) -> ServiceResult<Json<ReplicationLagResponse>> {
+    Ok(Json(ReplicationLagResponse {
+        pending_objects: service.replication_lag(&bucket).await?,
+    }))
+}
+
 pub async fn delete_bucket(
     State(service): State<SharedService>,
     Path(bucket): Path<String>,
@@ -136,51 +673,449 @@ pub async fn delete_bucket(
     Ok(StatusCode::NO_CONTENT)
 }
 
-pub async fn put_object(
-    State(service): State<SharedService>,
-    Path((bucket, key)): Path<(String, String)>,
-    headers: HeaderMap,
-    body: Body,
-) -> ServiceResult<Json<ObjectMetadataResponse>> {
-    // Extract content type from headers
-    let content_type = headers
-        .get("content-type")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.to_string())
-        .or_else(|| {
+/// Upload checks configured in [`ServerConfig`](crate::config::ServerConfig),
+/// installed on the public router as an `Extension`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UploadPolicy {
+    pub strict_content_type: bool,
+    /// The server-wide `max_body_bytes`. The router enforces it on the bytes
+    /// received; uploads hold decoded bodies to it as well.
+    pub max_body_bytes: Option<u64>,
+}
+
+/// Content type, custom metadata and size-limited body of an upload request.
+struct UploadRequest {
+    stream: ByteStream,
+    content_type: Option<String>,
+    metadata: HashMap<String, String>,
+    failure: Arc<UploadFailure>,
+}
+
+/// Custom metadata key recording the `Content-Encoding` an upload was sent with
+/// before it was decoded.
+pub const ORIGINAL_ENCODING_METADATA_KEY: &str = "original-encoding";
+
+/// Upload body encodings that are decoded before the object is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContentEncoding {
+    Gzip,
+    Zstd,
+}
+
+impl ContentEncoding {
+    /// Reads `Content-Encoding`; `None` for a missing or `identity` encoding.
+    fn from_headers(headers: &HeaderMap) -> ServiceResult<Option<Self>> {
+        let Some(value) = headers.get(header::CONTENT_ENCODING) else {
+            return Ok(None);
+        };
+        let value = value.to_str().unwrap_or_default().trim();
+        match value.to_ascii_lowercase().as_str() {
+            "" | "identity" => Ok(None),
+            "gzip" | "x-gzip" => Ok(Some(Self::Gzip)),
+            "zstd" => Ok(Some(Self::Zstd)),
+            _ => Err(ServiceError::InvalidRequest(format!(
+                "Unsupported Content-Encoding: {}",
+                value
+            ))),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    fn decode(self, stream: ByteStream) -> ByteStream {
+        let reader = StreamReader::new(stream);
+        match self {
+            Self::Gzip => Box::pin(ReaderStream::new(GzipDecoder::new(reader))),
+            Self::Zstd => Box::pin(ReaderStream::new(ZstdDecoder::new(reader))),
+        }
+    }
+}
+
+/// Why an upload body stream failed. The backend only sees a generic I/O
+/// error, so the stream records the cause for the handler to report.
+#[derive(Default)]
+struct UploadFailure {
+    limit_exceeded: AtomicBool,
+    body_failed: AtomicBool,
+    invalid_encoding: AtomicBool,
+}
+
+impl UploadRequest {
+    async fn from_parts(
+        service: &ObjectStoreService,
+        policy: UploadPolicy,
+        bucket: &str,
+        key: &str,
+        headers: &HeaderMap,
+        body: Body,
+    ) -> ServiceResult<Self> {
+        // Extract content type from headers
+        let content_type = headers
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+        if let Some(content_type) = &content_type {
+            if policy.strict_content_type && !validate_content_type(content_type) {
+                return Err(ServiceError::InvalidRequest(format!(
+                    "Invalid Content-Type '{}': expected a MIME type such as text/plain",
+                    content_type
+                )));
+            }
+        }
+        let content_type = content_type.or_else(|| {
             // Try to guess content type from file extension
-            mime_guess::from_path(&key).first().map(|m| m.to_string())
+            mime_guess::from_path(key).first().map(|m| m.to_string())
         });
 
-    // Extract custom metadata from headers (x-amz-meta-* pattern)
-    let mut metadata = HashMap::new();
-    for (header_key, value) in headers.iter() {
-        if let Some(meta_key) = header_key.as_str().strip_prefix("x-object-meta-") {
-            if let Ok(meta_value) = value.to_str() {
+        // Custom metadata comes as x-object-meta-* headers, or x-amz-meta-*
+        // from S3-compatible clients. Where both name a key, x-object-meta wins.
+        let mut metadata = HashMap::new();
+        for (header_key, value) in headers.iter() {
+            let Ok(meta_value) = value.to_str() else {
+                continue;
+            };
+            if let Some(meta_key) = header_key.as_str().strip_prefix(OBJECT_META_PREFIX) {
                 metadata.insert(meta_key.to_string(), meta_value.to_string());
+            } else if let Some(meta_key) = header_key.as_str().strip_prefix(AMZ_META_PREFIX) {
+                metadata
+                    .entry(meta_key.to_string())
+                    .or_insert_with(|| meta_value.to_string());
+            }
+        }
+
+        // Compressed bodies are stored decoded, so the object is what the client
+        // compressed rather than the bytes on the wire
+        let encoding = ContentEncoding::from_headers(headers)?;
+        if let Some(encoding) = encoding {
+            metadata.insert(
+                ORIGINAL_ENCODING_METADATA_KEY.to_string(),
+                encoding.as_str().to_string(),
+            );
+        }
+        validate_metadata(&metadata).map_err(metadata_error)?;
+
+        let max_object_bytes = service.get_bucket(bucket).await?.max_object_bytes;
+        // A small compressed body can decode to far more than the server
+        // limit, which only counts the bytes on the wire
+        let max_decoded_bytes = match (max_object_bytes, policy.max_body_bytes) {
+            (Some(bucket_max), Some(server_max)) => Some(bucket_max.min(server_max)),
+            (bucket_max, server_max) => bucket_max.or(server_max),
+        };
+        let content_length = headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+
+        // The Content-Length of an encoded body says nothing about the decoded size
+        if let (Some(max), Some(len), None) = (max_object_bytes, content_length, encoding) {
+            if len > max {
+                return Err(ServiceError::PayloadTooLarge(format!(
+                    "Object is {} bytes, bucket {} allows at most {}",
+                    len, bucket, max
+                )));
             }
         }
+
+        let (stream, failure) = limited_body_stream(body, max_decoded_bytes, encoding);
+
+        Ok(Self {
+            stream,
+            content_type,
+            metadata,
+            failure,
+        })
+    }
+
+    /// Reports a failed upload as 413 when it was the size limit that cut the
+    /// body off, and as 400 when the body could not be decoded.
+    fn map_err(failure: &UploadFailure, bucket: &str, err: ServiceError) -> ServiceError {
+        if failure.limit_exceeded.load(Ordering::Relaxed) {
+            ServiceError::PayloadTooLarge(format!(
+                "Request body exceeds the size limit for bucket {} or the server",
+                bucket
+            ))
+        } else if failure.invalid_encoding.load(Ordering::Relaxed) {
+            ServiceError::InvalidRequest(
+                "Request body does not match its Content-Encoding".to_string(),
+            )
+        } else {
+            err
+        }
     }
+}
 
-    let stream: object_store_backends::ByteStream = Box::pin(
-        body.into_data_stream()
-            .map(|result| result.map_err(std::io::Error::other)),
+pub async fn put_object(
+    State(service): State<SharedService>,
+    Path((bucket, key)): Path<(String, String)>,
+    Extension(policy): Extension<UploadPolicy>,
+    headers: HeaderMap,
+    body: Body,
+) -> ServiceResult<Response> {
+    let idempotency_key = idempotency_key(
+        &headers,
+        &format!("PUT /buckets/{}/objects/{}", bucket, key),
     );
+    if let Some(cached) = idempotency_key
+        .as_deref()
+        .and_then(|key| service.idempotency().get(key))
+    {
+        return Ok(cached.into_response());
+    }
+
+    let upload = UploadRequest::from_parts(&service, policy, &bucket, &key, &headers, body).await?;
 
     let obj_metadata = service
-        .put_object(&bucket, &key, stream, content_type, metadata)
+        .put_object(
+            &bucket,
+            &key,
+            upload.stream,
+            upload.content_type,
+            upload.metadata,
+        )
+        .await
+        .map_err(|e| UploadRequest::map_err(&upload.failure, &bucket, e))?;
+
+    Ok(service.idempotency().respond(
+        idempotency_key,
+        StatusCode::OK,
+        &ObjectMetadataResponse::from(obj_metadata),
+    )?)
+}
+
+/// Handles `POST /buckets/:bucket/objects/*key/copy` and `.../rename`. The
+/// wildcard has to end the route, so the action suffix is taken off the key
+/// here.
+pub async fn post_object(
+    State(service): State<SharedService>,
+    Path((bucket, key)): Path<(String, String)>,
+    request: Request,
+) -> Response {
+    if let Some(key) = key.strip_suffix("/copy") {
+        return match Json::from_request(request, &()).await {
+            Ok(Json(payload)) => copy_object(&service, &bucket, key, payload)
+                .await
+                .into_response(),
+            Err(rejection) => rejection.into_response(),
+        };
+    }
+    if let Some(key) = key.strip_suffix("/rename") {
+        return match Json::from_request(request, &()).await {
+            Ok(Json(payload)) => rename_object(&service, &bucket, key, payload)
+                .await
+                .into_response(),
+            Err(rejection) => rejection.into_response(),
+        };
+    }
+
+    ServiceError::InvalidRequest(format!(
+        "POST is only supported on /buckets/{}/objects/<key>/copy and /rename",
+        bucket
+    ))
+    .into_response()
+}
+
+async fn copy_object(
+    service: &ObjectStoreService,
+    bucket: &str,
+    key: &str,
+    payload: CopyObjectRequest,
+) -> ServiceResult<Json<ObjectMetadataResponse>> {
+    let metadata = service
+        .copy_object(
+            &payload.source_bucket,
+            &payload.source_key,
+            bucket,
+            key,
+            payload.content_type,
+            payload.metadata,
+        )
         .await?;
+    Ok(Json(metadata.into()))
+}
 
-    Ok(Json(obj_metadata.into()))
+async fn rename_object(
+    service: &ObjectStoreService,
+    bucket: &str,
+    key: &str,
+    payload: RenameObjectRequest,
+) -> ServiceResult<Json<ObjectMetadataResponse>> {
+    let metadata = service.rename_object(bucket, key, &payload.new_key).await?;
+    Ok(Json(metadata.into()))
+}
+
+/// Header carrying the ETag the caller expects an existing object to have;
+/// `upsert_object` overwrites the object when it differs.
+const X_EXPECTED_ETAG: &str = "x-expected-etag";
+
+pub async fn upsert_object(
+    State(service): State<SharedService>,
+    Path((bucket, key)): Path<(String, String)>,
+    Extension(policy): Extension<UploadPolicy>,
+    headers: HeaderMap,
+    body: Body,
+) -> ServiceResult<(StatusCode, Json<ObjectMetadataResponse>)> {
+    let expected_etag = header_str(&headers, X_EXPECTED_ETAG).map(unquote_etag);
+    let upload = UploadRequest::from_parts(&service, policy, &bucket, &key, &headers, body).await?;
+
+    let (obj_metadata, created) = service
+        .upsert_object(
+            &bucket,
+            &key,
+            upload.stream,
+            upload.content_type,
+            upload.metadata,
+            expected_etag,
+        )
+        .await
+        .map_err(|e| UploadRequest::map_err(&upload.failure, &bucket, e))?;
+
+    let status = if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, Json(obj_metadata.into())))
+}
+
+/// Converts a request body into a `ByteStream`, decoded according to
+/// `encoding`, that fails once more than `max_bytes` have been produced. Bodies
+/// without a Content-Length can only be checked as they stream, and the backend
+/// sees the failure as a generic I/O error, so the returned [`UploadFailure`]
+/// records whether it was a size limit (per-bucket or server-wide) that tripped
+/// or a body that could not be decoded.
+fn limited_body_stream(
+    body: Body,
+    max_bytes: Option<u64>,
+    encoding: Option<ContentEncoding>,
+) -> (ByteStream, Arc<UploadFailure>) {
+    let failure = Arc::new(UploadFailure::default());
+
+    let flags = failure.clone();
+    let mut stream: ByteStream = Box::pin(body.into_data_stream().map(move |result| {
+        result.map_err(|e| {
+            if is_length_limit_error(&e) {
+                flags.limit_exceeded.store(true, Ordering::Relaxed);
+            }
+            flags.body_failed.store(true, Ordering::Relaxed);
+            std::io::Error::other(e)
+        })
+    }));
+
+    if let Some(encoding) = encoding {
+        let flags = failure.clone();
+        stream = Box::pin(encoding.decode(stream).map(move |result| {
+            result.inspect_err(|_| {
+                if !flags.body_failed.load(Ordering::Relaxed) {
+                    flags.invalid_encoding.store(true, Ordering::Relaxed);
+                }
+            })
+        }));
+    }
+
+    let flags = failure.clone();
+    let mut received = 0u64;
+    let stream = stream.map(move |result| {
+        let chunk = result?;
+        received += chunk.len() as u64;
+        if max_bytes.is_some_and(|max| received > max) {
+            flags.limit_exceeded.store(true, Ordering::Relaxed);
+            return Err(std::io::Error::other("object size limit exceeded"));
+        }
+        Ok(chunk)
+    });
+
+    (Box::pin(stream), failure)
+}
+
+/// Parses an `If-Modified-Since` value. `Last-Modified` is sent as RFC 3339,
+/// like timestamps in JSON bodies, and clients echo it back as is; RFC 2822
+/// dates are what HTTP clients send otherwise.
+fn parse_http_timestamp(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .or_else(|_| chrono::DateTime::parse_from_rfc2822(value))
+        .ok()
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+}
+
+fn is_length_limit_error(err: &axum::Error) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(err);
+    while let Some(e) = source {
+        if e.is::<http_body_util::LengthLimitError>() {
+            return true;
+        }
+        source = e.source();
+    }
+    false
 }
 
 pub async fn get_object(
     State(service): State<SharedService>,
     Path((bucket, key)): Path<(String, String)>,
+    Extension(metadata_style): Extension<MetadataHeaderStyle>,
+    request_headers: HeaderMap,
 ) -> ServiceResult<Response> {
-    let obj_data = service.get_object(&bucket, &key).await?;
+    let range = header_str(&request_headers, header::RANGE).and_then(ByteRange::parse);
+    // A range is resolved against the object's size, and If-Range checked,
+    // before the body is opened, so only the requested bytes are read.
+    let head = match range {
+        Some(_) => Some(service.head_object(&bucket, &key).await?),
+        None => None,
+    };
+    // Unlike If-None-Match, where a match means "send nothing" and gets a 304,
+    // a failed If-Range means "the part I have is stale": the range is dropped
+    // and the whole object is sent with 200 so the client can start over
+    // without another round trip.
+    let range = range.zip(head.as_ref()).filter(|(_, head)| {
+        header_str(&request_headers, header::IF_RANGE)
+            .is_none_or(|validator| if_range_matches(validator, head))
+    });
+    // `Some(None)` when the range lies outside the object, which is answered
+    // with 416 once the conditions are checked, so no bytes are read for it
+    let mut span = range.map(|(range, head)| range.resolve(head.size));
+    let read_range = span.map(|span| span.map_or((0, 0), |(start, end)| (start, end - start + 1)));
+
+    // If-None-Match takes precedence over If-Modified-Since (RFC 9110 13.1.3)
+    let mut obj_data = if let Some(etag) = header_str(&request_headers, header::IF_NONE_MATCH) {
+        let etag = unquote_etag(etag);
+        match service
+            .get_object_if_not_match(&bucket, &key, etag, read_range)
+            .await?
+        {
+            Some(obj_data) => obj_data,
+            None => return Ok(not_modified(Some(etag))),
+        }
+    } else if let Some(since) =
+        header_str(&request_headers, header::IF_MODIFIED_SINCE).and_then(parse_http_timestamp)
+    {
+        match service
+            .get_object_if_modified_since(&bucket, &key, since, read_range)
+            .await?
+        {
+            Some(obj_data) => obj_data,
+            None => return Ok(not_modified(None)),
+        }
+    } else if let Some((start, len)) = read_range {
+        service.get_object_range(&bucket, &key, start, len).await?
+    } else {
+        service.get_object(&bucket, &key).await?
+    };
+
+    // The range was resolved against the object as it was at the HEAD. If it
+    // was replaced since, the whole new object is sent instead.
+    if head.is_some_and(|head| head.etag != obj_data.metadata.etag) {
+        span = None;
+        if read_range.is_some() {
+            obj_data = service.get_object(&bucket, &key).await?;
+        }
+    }
 
     let mut headers = HeaderMap::new();
+    headers.insert(header::ACCEPT_RANGES, "bytes".parse().unwrap());
 
     if let Some(ct) = obj_data.metadata.content_type {
         if let Ok(header_value) = ct.parse() {
@@ -202,7 +1137,7 @@ pub async fn get_object(
         obj_data
             .metadata
             .last_modified
-            .to_rfc2822()
+            .to_rfc3339()
             .parse()
             .unwrap_or_else(|_| "unknown".parse().unwrap()),
     );
@@ -217,19 +1152,122 @@ pub async fn get_object(
             .unwrap_or_else(|_| "0".parse().unwrap()),
     );
 
-    // Add custom metadata as x-object-meta-* headers
-    for (key, value) in obj_data.metadata.custom_metadata.iter() {
-        let header_name = format!("x-object-meta-{}", key);
-        if let Ok(header_value) = value.parse() {
-            if let Ok(header_name) = header_name.parse::<axum::http::HeaderName>() {
-                headers.insert(header_name, header_value);
+    insert_metadata_headers(
+        &mut headers,
+        &obj_data.metadata.custom_metadata,
+        metadata_style.for_request(&request_headers),
+    );
+
+    let size = obj_data.metadata.size;
+    let Some(span) = span else {
+        let body = Body::from_stream(obj_data.stream);
+        return Ok((headers, body).into_response());
+    };
+    let Some((start, end)) = span else {
+        let mut headers = HeaderMap::new();
+        if let Ok(value) = format!("bytes */{}", size).parse() {
+            headers.insert(header::CONTENT_RANGE, value);
+        }
+        return Ok((StatusCode::RANGE_NOT_SATISFIABLE, headers).into_response());
+    };
+
+    headers.insert(header::CONTENT_LENGTH, (end - start + 1).into());
+    if let Ok(value) = format!("bytes {}-{}/{}", start, end, size).parse() {
+        headers.insert(header::CONTENT_RANGE, value);
+    }
+    let body = Body::from_stream(obj_data.stream);
+    Ok((StatusCode::PARTIAL_CONTENT, headers, body).into_response())
+}
+
+/// A single range from a `Range: bytes=...` header. Multiple ranges aren't
+/// supported and, like a malformed header, are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ByteRange {
+    /// `bytes=N-M`, both inclusive
+    Bounded(u64, u64),
+    /// `bytes=N-`
+    From(u64),
+    /// `bytes=-N`, the last N bytes
+    Suffix(u64),
+}
+
+impl ByteRange {
+    fn parse(value: &str) -> Option<Self> {
+        let spec = value.trim().strip_prefix("bytes=")?;
+        if spec.contains(',') {
+            return None;
+        }
+        let (start, end) = spec.split_once('-')?;
+        let (start, end) = (start.trim(), end.trim());
+        match (start.is_empty(), end.is_empty()) {
+            (true, false) => end.parse().ok().map(ByteRange::Suffix),
+            (false, true) => start.parse().ok().map(ByteRange::From),
+            (false, false) => {
+                let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+                (start <= end).then_some(ByteRange::Bounded(start, end))
             }
+            (true, true) => None,
         }
     }
 
-    let body = Body::from_stream(obj_data.stream);
+    /// The inclusive byte offsets to send, or `None` when the range lies
+    /// outside an object of `size` bytes.
+    fn resolve(self, size: u64) -> Option<(u64, u64)> {
+        let last = size.checked_sub(1)?;
+        match self {
+            ByteRange::Bounded(start, end) if start <= last => Some((start, end.min(last))),
+            ByteRange::From(start) if start <= last => Some((start, last)),
+            ByteRange::Suffix(len) if len > 0 => Some((size - len.min(size), last)),
+            _ => None,
+        }
+    }
+}
+
+/// If-Range needs a strong match (RFC 9110 13.1.5): an ETag, quoted or not,
+/// equal to the stored one, or an HTTP date equal to Last-Modified to the
+/// second. Weak ETags never match.
+fn if_range_matches(validator: &str, metadata: &ObjectMetadata) -> bool {
+    let validator = validator.trim();
+    if validator.starts_with("W/") {
+        return false;
+    }
+    if validator.trim_matches('"') == metadata.etag {
+        return true;
+    }
+    parse_http_timestamp(validator)
+        .is_some_and(|date| date.timestamp() == metadata.last_modified.timestamp())
+}
 
-    Ok((headers, body).into_response())
+fn header_str(headers: &HeaderMap, name: impl header::AsHeaderName) -> Option<&str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+// Scoped to the caller's API key and to the request, including the bucket (and
+// object) it names, so a key reused by another caller or for a different
+// bucket or object runs that request instead of replaying another one's
+// response.
+fn idempotency_key(headers: &HeaderMap, request: &str) -> Option<String> {
+    let caller = bearer_key(headers).map(hash_key).unwrap_or_default();
+    header_str(headers, IDEMPOTENCY_KEY)
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(|key| format!("{}\n{}\n{}", caller, request, key))
+}
+
+// ETags are returned unquoted, but clients following the RFC send them back quoted
+// and possibly weak-prefixed.
+fn unquote_etag(etag: &str) -> &str {
+    let etag = etag.trim();
+    let etag = etag.strip_prefix("W/").unwrap_or(etag);
+    etag.trim_matches('"')
+}
+
+fn not_modified(etag: Option<&str>) -> Response {
+    let mut headers = HeaderMap::new();
+    if let Some(etag) = etag.and_then(|e| e.parse().ok()) {
+        headers.insert("etag", etag);
+    }
+    (StatusCode::NOT_MODIFIED, headers).into_response()
 }
 
 pub async fn get_object_info(
@@ -243,6 +1281,8 @@ pub async fn get_object_info(
 pub async fn head_object(
     State(service): State<SharedService>,
     Path((bucket, key)): Path<(String, String)>,
+    Extension(metadata_style): Extension<MetadataHeaderStyle>,
+    request_headers: HeaderMap,
 ) -> ServiceResult<Response> {
     let metadata = service.head_object(&bucket, &key).await?;
 
@@ -266,7 +1306,7 @@ pub async fn head_object(
         "last-modified",
         metadata
             .last_modified
-            .to_rfc2822()
+            .to_rfc3339()
             .parse()
             .unwrap_or_else(|_| "unknown".parse().unwrap()),
     );
@@ -280,17 +1320,45 @@ pub async fn head_object(
             .unwrap_or_else(|_| "0".parse().unwrap()),
     );
 
-    // Add custom metadata as x-object-meta-* headers
-    for (key, value) in metadata.custom_metadata.iter() {
-        let header_name = format!("x-object-meta-{}", key);
-        if let Ok(header_value) = value.parse() {
-            if let Ok(header_name) = header_name.parse::<axum::http::HeaderName>() {
-                headers.insert(header_name, header_value);
-            }
+    insert_metadata_headers(
+        &mut headers,
+        &metadata.custom_metadata,
+        metadata_style.for_request(&request_headers),
+    );
+
+    Ok((StatusCode::OK, headers).into_response())
+}
+
+/// Request header asking for custom metadata as `x-amz-meta-*` headers,
+/// whatever the server's `metadata_header_style`.
+const X_AMZ_COMPAT: &str = "x-amz-compat";
+
+impl MetadataHeaderStyle {
+    fn for_request(self, request_headers: &HeaderMap) -> Self {
+        match header_str(request_headers, X_AMZ_COMPAT) {
+            Some(value) if value.eq_ignore_ascii_case("true") => Self::AmzMeta,
+            _ => self,
         }
     }
+}
 
-    Ok((StatusCode::OK, headers).into_response())
+/// Adds custom metadata as headers under each prefix of `style`. Pairs that
+/// don't make valid headers are left out.
+fn insert_metadata_headers(
+    headers: &mut HeaderMap,
+    metadata: &HashMap<String, String>,
+    style: MetadataHeaderStyle,
+) {
+    for prefix in style.prefixes() {
+        for (key, value) in metadata {
+            let header_name = format!("{}{}", prefix, key);
+            if let Ok(header_value) = value.parse() {
+                if let Ok(header_name) = header_name.parse::<axum::http::HeaderName>() {
+                    headers.insert(header_name, header_value);
+                }
+            }
+        }
+    }
 }
 
 pub async fn delete_object(
@@ -301,20 +1369,169 @@ pub async fn delete_object(
     Ok(StatusCode::NO_CONTENT)
 }
 
+pub async fn list_recycle_bin(
+    State(service): State<SharedService>,
+    Path(bucket): Path<String>,
+) -> ServiceResult<Json<ListRecycleBinResponse>> {
+    let entries = service.list_recycle_bin(&bucket).await?;
+    Ok(Json(ListRecycleBinResponse {
+        objects: entries.into_iter().map(|e| e.into()).collect(),
+    }))
+}
+
+pub async fn restore_object(
+    State(service): State<SharedService>,
+    Path(bucket): Path<String>,
+    Json(payload): Json<RestoreObjectRequest>,
+) -> ServiceResult<Json<ObjectMetadataResponse>> {
+    let metadata = service
+        .restore_object(&bucket, &payload.key, &payload.version)
+        .await?;
+    Ok(Json(metadata.into()))
+}
+
+pub async fn purge_recycle_bin_version(
+    State(service): State<SharedService>,
+    Path((bucket, version)): Path<(String, String)>,
+    Query(params): Query<PurgeRecycleBinQuery>,
+) -> ServiceResult<StatusCode> {
+    service
+        .purge_recycle_bin_version(&bucket, &version, params.key.as_deref())
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub async fn list_objects(
     State(service): State<SharedService>,
     Path(bucket): Path<String>,
     Query(params): Query<ListObjectsQuery>,
-) -> ServiceResult<Json<ListObjectsResponse>> {
-    let objects = service
-        .list_objects(&bucket, params.prefix.as_deref(), params.max_keys)
-        .await?;
+    headers: HeaderMap,
+) -> ServiceResult<Response> {
+    let wants_ndjson = params.streaming
+        || headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.contains(NDJSON_CONTENT_TYPE))
+            .unwrap_or(false);
+
+    let options = params.to_options()?;
+
+    if wants_ndjson {
+        let objects = service.list_objects_stream(&bucket, &options).await?;
+
+        // One JSON document per line. The body has no known length, so hyper
+        // sends it with chunked transfer encoding and no Content-Length.
+        let summary_only = options.summary_only;
+        let lines = objects.map(move |obj| {
+            let obj = obj.map_err(std::io::Error::other)?;
+            let mut line = if summary_only {
+                serde_json::to_vec(&ObjectSummaryResponse::from(obj))?
+            } else {
+                serde_json::to_vec(&ObjectMetadataResponse::from(obj))?
+            };
+            line.push(b'\n');
+            Ok::<_, std::io::Error>(line)
+        });
+
+        return Ok((
+            [(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)],
+            Body::from_stream(lines),
+        )
+            .into_response());
+    }
+
+    let listing = service.list_objects_page(&bucket, &options).await?;
+
+    if options.summary_only {
+        return Ok(Json(ListObjectSummariesResponse {
+            objects: listing.objects.into_iter().map(|o| o.into()).collect(),
+            total_count: listing.total_count,
+        })
+        .into_response());
+    }
 
     let response = ListObjectsResponse {
-        objects: objects.into_iter().map(|o| o.into()).collect(),
+        objects: listing.objects.into_iter().map(|o| o.into()).collect(),
+        total_count: listing.total_count,
     };
 
-    Ok(Json(response))
+    Ok(Json(response).into_response())
+}
+
+pub async fn export_inventory(
+    State(service): State<SharedService>,
+    Path(bucket): Path<String>,
+    Query(params): Query<InventoryQuery>,
+) -> ServiceResult<Response> {
+    let objects = service
+        .list_objects_stream(&bucket, &ListObjectsOptions::default())
+        .await?
+        .map_ok(ObjectMetadataResponse::from)
+        .map_err(std::io::Error::other);
+
+    match params.format {
+        InventoryFormat::Json => {
+            // Stream a JSON array element by element rather than building the
+            // whole document in memory.
+            let open = futures::stream::once(async { Ok(b"[".to_vec()) });
+            let elements = objects.enumerate().map(|(i, obj)| {
+                let mut chunk = if i == 0 { Vec::new() } else { b",".to_vec() };
+                serde_json::to_writer(&mut chunk, &obj?)?;
+                Ok::<_, std::io::Error>(chunk)
+            });
+            let close = futures::stream::once(async { Ok(b"]".to_vec()) });
+            let body = open.chain(elements).chain(close);
+
+            Ok((
+                [(header::CONTENT_TYPE, "application/json")],
+                Body::from_stream(body),
+            )
+                .into_response())
+        }
+        InventoryFormat::Csv => {
+            let header_row = futures::stream::once(async {
+                csv_row(&[
+                    "key",
+                    "size",
+                    "etag",
+                    "content_type",
+                    "last_modified",
+                    "metadata",
+                ])
+            });
+            let rows = objects.map(|obj| {
+                let obj = obj?;
+                let metadata = serde_json::to_string(&obj.metadata)
+                    .map_err(|e| std::io::Error::other(e.to_string()))?;
+                csv_row(&[
+                    obj.key.as_str(),
+                    &obj.size.to_string(),
+                    &obj.etag,
+                    obj.content_type.as_deref().unwrap_or(""),
+                    &obj.last_modified,
+                    &metadata,
+                ])
+            });
+
+            let disposition = format!("attachment; filename=\"{}-inventory.csv\"", bucket);
+            Ok((
+                [
+                    (header::CONTENT_TYPE, "text/csv".to_string()),
+                    (header::CONTENT_DISPOSITION, disposition),
+                ],
+                Body::from_stream(header_row.chain(rows)),
+            )
+                .into_response())
+        }
+    }
+}
+
+fn csv_row(fields: &[&str]) -> std::io::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(fields)?;
+    writer
+        .into_inner()
+        .map_err(|e| std::io::Error::other(e.to_string()))
 }
 
 pub async fn get_public_url(
@@ -331,7 +1548,13 @@ pub async fn get_public_url(
         .unwrap_or(object_store_backends::PublicUrlPurpose::Retrieve);
 
     let url = service
-        .get_public_url(&bucket, &key, expiration_secs, purpose)
+        .get_public_url(
+            &bucket,
+            &key,
+            expiration_secs,
+            purpose,
+            params.content_type.as_deref(),
+        )
         .await?;
 
     Ok(Json(PublicUrlResponse {
@@ -339,3 +1562,14 @@ pub async fn get_public_url(
         expires_in: expiration_secs,
     }))
 }
+
+pub async fn revoke_url(
+    State(service): State<SharedService>,
+    Path(bucket): Path<String>,
+    Json(req): Json<RevokeUrlRequest>,
+) -> ServiceResult<Json<RevokeUrlResponse>> {
+    let revoked = service.revoke_url(&bucket, &req.url).await?;
+    Ok(Json(RevokeUrlResponse {
+        expires_at: revoked.expires_at,
+    }))
+}
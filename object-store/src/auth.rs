@@ -0,0 +1,232 @@
+use axum::http::Method;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::error::{ServiceError, ServiceResult};
+use crate::metadata::MetadataStore;
+use crate::rate_limit::RateLimitConfig;
+
+/// API key records, stored as `{prefix}/{sha256 of the key}.json`. The raw key
+/// itself is never stored.
+pub const API_KEYS_PREFIX: &str = ".metadata/api-keys";
+
+/// How long a validated key is trusted before it is looked up again. Keys
+/// revoked on another instance keep working there for up to this long.
+const API_KEY_CACHE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthConfig {
+    /// Require an API key (`Authorization: Bearer <key>`) on every public API
+    /// request. Keys are managed through `/admin/api-keys` on the internal
+    /// listener.
+    #[serde(default)]
+    pub require_api_key: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// `ReadObject` and `ListObjects` operations.
+    Read,
+    /// Every other operation.
+    Write,
+}
+
+/// What a request does to which kind of resource, reported in
+/// [`ServiceError::PermissionDenied`] so clients can say what was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationType {
+    ReadObject,
+    WriteObject,
+    DeleteObject,
+    ListObjects,
+    CreateBucket,
+    DeleteBucket,
+}
+
+impl OperationType {
+    /// Classifies a REST request by method and matched route. Listings of
+    /// buckets and objects are `ListObjects`; other reads are `ReadObject`,
+    /// other deletes `DeleteObject` and every other change `WriteObject`.
+    pub fn for_request(method: &Method, route: &str) -> Self {
+        match (method.as_str(), route) {
+            ("POST" | "PUT", "/buckets") => OperationType::CreateBucket,
+            ("DELETE", "/buckets/:bucket") => OperationType::DeleteBucket,
+            ("GET", "/buckets" | "/buckets/:bucket/objects") => OperationType::ListObjects,
+            ("GET" | "HEAD", _) => OperationType::ReadObject,
+            ("DELETE", _) => OperationType::DeleteObject,
+            _ => OperationType::WriteObject,
+        }
+    }
+
+    /// The API key permission the operation needs.
+    pub fn permission(self) -> Permission {
+        match self {
+            OperationType::ReadObject | OperationType::ListObjects => Permission::Read,
+            _ => Permission::Write,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OperationType::ReadObject => "read_object",
+            OperationType::WriteObject => "write_object",
+            OperationType::DeleteObject => "delete_object",
+            OperationType::ListObjects => "list_objects",
+            OperationType::CreateBucket => "create_bucket",
+            OperationType::DeleteBucket => "delete_bucket",
+        }
+    }
+}
+
+impl std::fmt::Display for OperationType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    pub label: String,
+    pub created_at: DateTime<Utc>,
+    /// Updated at most once per cache TTL, so it can lag by that much.
+    #[serde(default)]
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub permissions: Vec<Permission>,
+    /// Replaces the server's `rate_limit` for requests made with this key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+pub fn hash_key(raw: &str) -> String {
+    hex::encode(Sha256::digest(raw.as_bytes()))
+}
+
+pub fn is_valid_key_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+pub fn generate_key() -> String {
+    format!(
+        "osk_{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+struct CachedKey {
+    record: ApiKeyRecord,
+    fetched_at: Instant,
+}
+
+/// Checks API keys against the metadata store, caching valid keys for
+/// `API_KEY_CACHE_TTL`. Unknown keys are not cached, so a newly created key
+/// works immediately.
+pub struct ApiKeyAuthenticator {
+    metadata: Arc<MetadataStore>,
+    cache: RwLock<HashMap<String, CachedKey>>,
+}
+
+impl ApiKeyAuthenticator {
+    pub fn new(metadata: Arc<MetadataStore>) -> Self {
+        Self {
+            metadata,
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Checks that the key may perform `operation` on `resource`, a bucket
+    /// name, `bucket/key`, or `*` for requests not about one bucket.
+    pub async fn authenticate(
+        &self,
+        raw: &str,
+        operation: OperationType,
+        resource: &str,
+    ) -> ServiceResult<ApiKeyRecord> {
+        let record = self.lookup(raw).await?;
+        if !record.permissions.contains(&operation.permission()) {
+            return Err(ServiceError::PermissionDenied {
+                operation,
+                resource: resource.to_string(),
+                principal: record.label,
+            });
+        }
+        Ok(record)
+    }
+
+    /// The record of a valid key, whatever its permissions.
+    pub async fn lookup(&self, raw: &str) -> ServiceResult<ApiKeyRecord> {
+        let hash = hash_key(raw);
+        match self.cached(&hash).await {
+            Some(record) => Ok(record),
+            None => self.fetch(&hash).await,
+        }
+    }
+
+    /// The record of a key validated within the cache TTL, without reading
+    /// the metadata store.
+    pub async fn lookup_cached(&self, raw: &str) -> Option<ApiKeyRecord> {
+        self.cached(&hash_key(raw)).await
+    }
+
+    /// Drops a key from the cache so a revocation takes effect immediately on
+    /// this instance.
+    pub async fn invalidate(&self, hash: &str) {
+        self.cache.write().await.remove(hash);
+    }
+
+    async fn cached(&self, hash: &str) -> Option<ApiKeyRecord> {
+        let cache = self.cache.read().await;
+        cache
+            .get(hash)
+            .filter(|cached| cached.fetched_at.elapsed() < API_KEY_CACHE_TTL)
+            .map(|cached| cached.record.clone())
+    }
+
+    async fn fetch(&self, hash: &str) -> ServiceResult<ApiKeyRecord> {
+        let Some(mut record) = self.metadata.get_api_key(hash).await? else {
+            self.invalidate(hash).await;
+            return Err(ServiceError::Unauthorized("Invalid API key".to_string()));
+        };
+
+        let now = Utc::now();
+        if let Err(e) = self.metadata.touch_api_key(hash, now).await {
+            warn!("Failed to record API key use for '{}': {}", record.label, e);
+        }
+        record.last_used_at = Some(now);
+
+        self.cache.write().await.insert(
+            hash.to_string(),
+            CachedKey {
+                record: record.clone(),
+                fetched_at: Instant::now(),
+            },
+        );
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_hashes() {
+        let key = generate_key();
+        assert!(key.starts_with("osk_"));
+        assert_ne!(key, generate_key());
+
+        let hash = hash_key(&key);
+        assert!(is_valid_key_hash(&hash));
+        assert_eq!(hash, hash_key(&key));
+        assert!(!is_valid_key_hash("../buckets/secret"));
+        assert!(!is_valid_key_hash(&hash[..63]));
+    }
+}
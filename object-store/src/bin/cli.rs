@@ -0,0 +1,92 @@
+use object_store::Config;
+use object_store_backends::migrate_backend;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+const USAGE: &str = "Usage: object-store-cli --migrate-to <destination-config> [--from <source-config>] [--prefix <prefix>]
+
+Copies every object from the source backend to the destination backend.
+The source config defaults to $CONFIG_PATH. Only the [backend] section of
+each file is used.";
+
+struct MigrateArgs {
+    from: String,
+    to: String,
+    prefix: Option<String>,
+}
+
+fn parse_args() -> Result<MigrateArgs, String> {
+    let mut from = std::env::var("CONFIG_PATH").ok();
+    let mut to = None;
+    let mut prefix = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--migrate-to" => to = args.next(),
+            "--from" => from = args.next(),
+            "--prefix" => prefix = args.next(),
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            other => return Err(format!("Unknown argument: {}\n\n{}", other, USAGE)),
+        }
+    }
+
+    Ok(MigrateArgs {
+        from: from.ok_or_else(|| format!("Missing source config\n\n{}", USAGE))?,
+        to: to.ok_or_else(|| format!("Missing --migrate-to\n\n{}", USAGE))?,
+        prefix,
+    })
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "object_store=info,object_store_backends=info".into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    let src = Config::backend_from_file(&args.from)?
+        .create_backend()
+        .await?;
+    let dst = Config::backend_from_file(&args.to)?
+        .create_backend()
+        .await?;
+    src.init().await?;
+    dst.init().await?;
+
+    info!("Migrating from {} to {}", args.from, args.to);
+
+    let report = migrate_backend(src.as_ref(), dst.as_ref(), args.prefix.as_deref(), |p| {
+        info!(
+            "Progress: {} copied, {} skipped, {} failed ({} bytes)",
+            p.copied, p.skipped, p.failed, p.total_bytes
+        );
+    })
+    .await?;
+
+    for failure in &report.errors {
+        eprintln!("Failed: {}: {}", failure.key, failure.error);
+    }
+
+    println!(
+        "Migrated {} objects ({} bytes), skipped {}, failed {}",
+        report.copied, report.total_bytes, report.skipped, report.failed
+    );
+
+    if report.failed > 0 {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
@@ -1,10 +1,75 @@
+use object_store_backends::{
+    azure::AzureBackend,
+    circuit_breaker::{CircuitBreakerBackend, CircuitBreakerConfig},
+    composite::CompositeBackend,
+    gcs::{default_resumable_threshold_bytes, GcsBackend, GcsConfig},
+    local::{
+        default_list_cache_capacity, deserialize_dir_permissions, LocalBackend, LocalBackendConfig,
+        LocalBackendEncryption,
+    },
+    s3::{ChecksumAlgorithm, S3Backend, S3BackendConfig, S3Config},
+    Backend, BackendResult,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::auth::AuthConfig;
+use crate::dedup::DeduplicatorConfig;
+use crate::grpc::GrpcConfig;
+use crate::idempotency::IdempotencyConfig;
+use crate::metadata::MetadataStoreConfig;
+use crate::metrics::MetricsConfig;
+use crate::pipeline::HooksConfig;
+use crate::rate_limit::RateLimitConfig;
+use crate::service::{ListingConfig, BUCKET_DATA_PREFIXES};
+use crate::validation::ValidationConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    #[serde(default)]
     pub server: ServerConfig,
+    /// Listener for health, metrics and `/internal/*` endpoints. When unset they
+    /// are served on the public listener.
+    #[serde(default)]
+    pub internal_server: Option<ServerConfig>,
     pub backend: BackendConfig,
+    /// Fails backend calls fast after repeated failures. Off when unset.
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub metadata: MetadataStoreConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub listing: ListingConfig,
+    #[serde(default)]
+    pub validation: ValidationConfig,
+    /// Serializes concurrent uploads to the same key.
+    #[serde(default)]
+    pub deduplicator: DeduplicatorConfig,
+    /// Replays responses to create requests retried with the same
+    /// `Idempotency-Key`.
+    #[serde(default)]
+    pub idempotency: IdempotencyConfig,
+    /// Throttles each API key, or each client IP without keys, with a token
+    /// bucket. Off when unset.
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// gRPC listener serving the same data as the REST API. Off when unset.
+    #[serde(default)]
+    pub grpc: Option<GrpcConfig>,
+    /// Backends buckets can replicate to, by id. Buckets name one as their
+    /// `replication.target`; no other targets are accepted.
+    #[serde(default)]
+    pub replicas: HashMap<String, BackendConfig>,
+    /// Hosts bucket upload hooks may call.
+    #[serde(default)]
+    pub hooks: HooksConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,29 +78,305 @@ pub struct ServerConfig {
     pub host: String,
     #[serde(default = "default_port")]
     pub port: u16,
+    /// Maximum request body size in bytes. Larger requests are rejected with 413.
+    #[serde(default)]
+    pub max_body_bytes: Option<u64>,
+    #[serde(default)]
+    pub url_style: UrlStyle,
+    /// Domain that bucket subdomains hang off with `virtual_hosted` URLs, e.g.
+    /// `storage.example.com` for `my-bucket.storage.example.com`. Required with
+    /// `virtual_hosted` URLs; see [`ServerConfig::validate`].
+    #[serde(default)]
+    pub virtual_host_domain: Option<String>,
+    /// Rejects uploads whose `Content-Type` is not a valid MIME type with 400.
+    /// Off by default, storing whatever value was sent.
+    #[serde(default)]
+    pub strict_content_type: bool,
+    /// Prefix of the custom metadata headers on object responses. Uploads
+    /// accept both prefixes whatever this is set to.
+    #[serde(default)]
+    pub metadata_header_style: MetadataHeaderStyle,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// How buckets appear in object URLs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlStyle {
+    /// `/buckets/{bucket}/objects/{key}` only.
+    #[default]
+    PathStyle,
+    /// Also serve `http://{bucket}.{domain}/{key}`, as S3-compatible tools expect.
+    VirtualHosted,
+}
+
+/// Which headers carry custom metadata on `GET` and `HEAD` object responses.
+/// A request with `x-amz-compat: true` gets `x-amz-meta-*` regardless.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataHeaderStyle {
+    /// `x-object-meta-*`, as the bundled clients expect.
+    #[default]
+    ObjectMeta,
+    /// `x-amz-meta-*`, as S3-compatible tools expect.
+    AmzMeta,
+    /// Every pair under both prefixes.
+    Both,
+}
+
+impl MetadataHeaderStyle {
+    pub fn prefixes(self) -> &'static [&'static str] {
+        match self {
+            Self::ObjectMeta => &[OBJECT_META_PREFIX],
+            Self::AmzMeta => &[AMZ_META_PREFIX],
+            Self::Both => &[OBJECT_META_PREFIX, AMZ_META_PREFIX],
+        }
+    }
+}
+
+pub const OBJECT_META_PREFIX: &str = "x-object-meta-";
+pub const AMZ_META_PREFIX: &str = "x-amz-meta-";
+
+impl ServerConfig {
+    /// Rejects settings that deserialize but cannot be served. Without a
+    /// domain there is no telling a bucket subdomain from the service's own
+    /// host name, so `virtual_hosted` URLs need `virtual_host_domain`.
+    pub fn validate(&self) -> Result<(), config::ConfigError> {
+        if self.url_style == UrlStyle::VirtualHosted && self.virtual_host_domain.is_none() {
+            return Err(config::ConfigError::Message(
+                "server.url_style = \"virtual_hosted\" requires server.virtual_host_domain"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: default_host(),
+            port: default_port(),
+            max_body_bytes: None,
+            url_style: UrlStyle::default(),
+            virtual_host_domain: None,
+            strict_content_type: false,
+            metadata_header_style: MetadataHeaderStyle::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum BackendConfig {
     Local {
         root_path: PathBuf,
         #[serde(default = "default_physical_bucket")]
         physical_bucket: String,
+        #[serde(default)]
+        use_xattrs: bool,
+        #[serde(default)]
+        list_cache_ttl_secs: u64,
+        #[serde(default = "default_list_cache_capacity")]
+        list_cache_capacity: usize,
+        #[serde(default)]
+        metadata_cache_capacity: usize,
+        #[serde(default, deserialize_with = "deserialize_dir_permissions")]
+        dir_permissions: Option<u32>,
+        /// Refuse to start on a symlinked, non-directory or unwritable path.
+        #[serde(default)]
+        strict_init: bool,
+        /// With `strict_init`, the least free space required at startup.
+        #[serde(default)]
+        min_free_bytes: Option<u64>,
+        /// Encrypts stored objects and their metadata. Off when unset.
+        #[serde(default)]
+        encryption: Option<LocalBackendEncryption>,
     },
     S3 {
         region: String,
         physical_bucket: String,
         endpoint: Option<String>,
+        /// Address buckets as `{endpoint}/{bucket}`, as MinIO and LocalStack need.
+        #[serde(default)]
+        force_path_style: bool,
+        #[serde(default)]
+        checksum_algorithm: ChecksumAlgorithm,
     },
     Gcs {
         physical_bucket: String,
+        #[serde(default = "default_resumable_threshold_bytes")]
+        resumable_threshold_bytes: u64,
     },
     Azure {
         account: String,
-        access_key: String,
+        #[serde(alias = "container")]
         physical_bucket: String,
+        auth: AzureAuth,
     },
+    /// Serves each bucket from the first route whose pattern matches its name,
+    /// and every other bucket from `default`.
+    Composite {
+        routes: Vec<BackendRoute>,
+        default: Box<BackendConfig>,
+    },
+}
+
+/// How the Azure backend authenticates, e.g. `auth = "managed_identity"` or
+/// `auth = { access_key = "..." }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AzureAuth {
+    AccessKey(String),
+    /// Its `AccountName` is used in place of the configured account.
+    ConnectionString(String),
+    /// Needs the `azure-managed-identity` feature.
+    ManagedIdentity,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackendRoute {
+    /// Regex that must match the whole bucket name, e.g. `premium-.*`.
+    pub bucket_pattern: String,
+    pub backend: BackendConfig,
+}
+
+impl BackendConfig {
+    /// The `type` tag this backend is configured with, e.g. `"s3"`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            BackendConfig::Local { .. } => "local",
+            BackendConfig::S3 { .. } => "s3",
+            BackendConfig::Gcs { .. } => "gcs",
+            BackendConfig::Azure { .. } => "azure",
+            BackendConfig::Composite { .. } => "composite",
+        }
+    }
+
+    pub async fn create_backend(&self) -> BackendResult<Arc<dyn Backend>> {
+        let backend: Arc<dyn Backend> = match self.clone() {
+            BackendConfig::Local {
+                root_path,
+                physical_bucket,
+                use_xattrs,
+                list_cache_ttl_secs,
+                list_cache_capacity,
+                metadata_cache_capacity,
+                dir_permissions,
+                strict_init,
+                min_free_bytes,
+                encryption,
+            } => {
+                info!("Using local backend at {:?}", root_path);
+                let backend = LocalBackend::new_with_config(
+                    root_path,
+                    physical_bucket,
+                    LocalBackendConfig {
+                        use_xattrs,
+                        list_cache_ttl_secs,
+                        list_cache_capacity,
+                        metadata_cache_capacity,
+                        dir_permissions,
+                        strict_init,
+                        min_free_bytes,
+                    },
+                );
+                match encryption {
+                    Some(encryption) => Arc::new(backend.with_encryption(encryption.key()?)),
+                    None => Arc::new(backend),
+                }
+            }
+            BackendConfig::S3 {
+                region,
+                physical_bucket,
+                endpoint,
+                force_path_style,
+                checksum_algorithm,
+            } => {
+                info!(
+                    "Using S3 backend with bucket: {}, region: {}, endpoint: {:?}",
+                    physical_bucket, region, endpoint
+                );
+                Arc::new(
+                    S3Backend::new_with_config(S3BackendConfig {
+                        bucket_name: physical_bucket,
+                        region,
+                        endpoint,
+                        force_path_style,
+                    })
+                    .await?
+                    .with_config(S3Config { checksum_algorithm }),
+                )
+            }
+            BackendConfig::Gcs {
+                physical_bucket,
+                resumable_threshold_bytes,
+            } => {
+                info!("Using GCS backend with bucket: {}", physical_bucket);
+                // Without an explicit key, signed URLs use the ambient credentials
+                // (a key file or the IAM signBlob API on Google Cloud).
+                let backend = match std::env::var("GCS_SA_KEY_PATH") {
+                    Ok(sa_key_path) => {
+                        GcsBackend::new_with_signing_key(physical_bucket, sa_key_path).await?
+                    }
+                    Err(_) => GcsBackend::new(physical_bucket).await?,
+                };
+                Arc::new(backend.with_config(GcsConfig {
+                    resumable_threshold_bytes,
+                }))
+            }
+            BackendConfig::Azure {
+                account,
+                physical_bucket,
+                auth,
+            } => {
+                info!(
+                    "Using Azure backend with account: {}, container: {}",
+                    account, physical_bucket
+                );
+                match auth {
+                    AzureAuth::AccessKey(access_key) => {
+                        Arc::new(AzureBackend::new(account, access_key, physical_bucket)?)
+                    }
+                    AzureAuth::ConnectionString(connection_string) => {
+                        Arc::new(AzureBackend::new_from_connection_string(
+                            connection_string,
+                            physical_bucket,
+                        )?)
+                    }
+                    #[cfg(feature = "azure-managed-identity")]
+                    AzureAuth::ManagedIdentity => Arc::new(
+                        AzureBackend::new_with_managed_identity(account, physical_bucket)?,
+                    ),
+                    #[cfg(not(feature = "azure-managed-identity"))]
+                    AzureAuth::ManagedIdentity => {
+                        return Err(object_store_backends::BackendError::Configuration(
+                            "Azure Managed Identity needs the service built with the \
+                             azure-managed-identity feature"
+                                .to_string(),
+                        ))
+                    }
+                }
+            }
+            BackendConfig::Composite { routes, default } => {
+                let mut composite =
+                    CompositeBackend::new(Box::pin(default.create_backend()).await?);
+                for prefix in BUCKET_DATA_PREFIXES {
+                    composite = composite.with_bucket_prefix(prefix);
+                }
+                for route in routes {
+                    info!(
+                        "Routing buckets matching {} to their own backend",
+                        route.bucket_pattern
+                    );
+                    let backend = Box::pin(route.backend.create_backend()).await?;
+                    composite = composite.with_route(&route.bucket_pattern, backend)?;
+                }
+                Arc::new(composite)
+            }
+        };
+
+        Ok(backend)
+    }
 }
 
 fn default_host() -> String {
@@ -53,33 +394,409 @@ fn default_physical_bucket() -> String {
 impl Default for Config {
     fn default() -> Self {
         Self {
-            server: ServerConfig {
-                host: default_host(),
-                port: default_port(),
-            },
+            server: ServerConfig::default(),
+            internal_server: None,
             backend: BackendConfig::Local {
                 root_path: PathBuf::from("./data"),
                 physical_bucket: default_physical_bucket(),
+                use_xattrs: false,
+                list_cache_ttl_secs: 0,
+                list_cache_capacity: default_list_cache_capacity(),
+                metadata_cache_capacity: 0,
+                dir_permissions: None,
+                strict_init: false,
+                min_free_bytes: None,
+                encryption: None,
             },
+            circuit_breaker: None,
+            metrics: MetricsConfig::default(),
+            metadata: MetadataStoreConfig::default(),
+            auth: AuthConfig::default(),
+            listing: ListingConfig::default(),
+            validation: ValidationConfig::default(),
+            deduplicator: DeduplicatorConfig::default(),
+            idempotency: IdempotencyConfig::default(),
+            rate_limit: None,
+            grpc: None,
+            replicas: HashMap::new(),
+            hooks: HooksConfig::default(),
         }
     }
 }
 
 impl Config {
+    /// Creates the configured backend, behind a circuit breaker when one is
+    /// configured.
+    pub async fn create_backend(&self) -> BackendResult<Arc<dyn Backend>> {
+        let backend = self.backend.create_backend().await?;
+        Ok(match &self.circuit_breaker {
+            Some(breaker) => {
+                info!(
+                    "Circuit breaker enabled: opens for {}s after {} consecutive failures",
+                    breaker.open_duration_secs, breaker.failure_threshold
+                );
+                Arc::new(CircuitBreakerBackend::new(backend, breaker.clone()))
+            }
+            None => backend,
+        })
+    }
+
     pub fn from_file(path: &str) -> Result<Self, config::ConfigError> {
         let settings = config::Config::builder()
             .add_source(config::File::with_name(path))
-            .add_source(config::Environment::with_prefix("OBJECT_STORE").separator("__"))
+            .add_source(Self::env_source())
             .build()?;
 
         settings.try_deserialize()
     }
 
+    /// Reads only the `[backend]` section of a config file, without environment
+    /// overrides, so two files can describe two different backends.
+    pub fn backend_from_file(path: &str) -> Result<BackendConfig, config::ConfigError> {
+        config::Config::builder()
+            .add_source(config::File::with_name(path))
+            .build()?
+            .get("backend")
+    }
+
+    /// `OBJECT_STORE__SECTION__FIELD` variables, e.g. `OBJECT_STORE__BACKEND__REGION`
+    /// for `backend.region`. Values stay strings until deserialized, so numeric
+    /// bucket names and keys are not mangled; list fields can only come from a file.
+    fn env_source() -> config::Environment {
+        config::Environment::with_prefix("OBJECT_STORE").separator("__")
+    }
+
+    /// Reads the whole configuration from `OBJECT_STORE__*` variables, with
+    /// `__` between nested names. `OBJECT_STORE__BACKEND__TYPE` picks the
+    /// backend and decides which other backend variables are read:
+    ///
+    /// | Variable | Type | Example |
+    /// |----------|------|---------|
+    /// | `OBJECT_STORE__SERVER__HOST` | string | `0.0.0.0` |
+    /// | `OBJECT_STORE__SERVER__PORT` | u16 | `8080` |
+    /// | `OBJECT_STORE__SERVER__MAX_BODY_BYTES` | u64 | `104857600` |
+    /// | `OBJECT_STORE__BACKEND__TYPE` | `local`, `s3`, `gcs` or `azure` | `s3` |
+    ///
+    /// `local`:
+    ///
+    /// | Variable | Type | Example |
+    /// |----------|------|---------|
+    /// | `OBJECT_STORE__BACKEND__ROOT_PATH` | path, required | `/app/data` |
+    /// | `OBJECT_STORE__BACKEND__PHYSICAL_BUCKET` | string | `object-store-data` |
+    /// | `OBJECT_STORE__BACKEND__USE_XATTRS` | bool | `true` |
+    /// | `OBJECT_STORE__BACKEND__LIST_CACHE_TTL_SECS` | u64 | `30` |
+    /// | `OBJECT_STORE__BACKEND__LIST_CACHE_CAPACITY` | usize | `256` |
+    /// | `OBJECT_STORE__BACKEND__METADATA_CACHE_CAPACITY` | usize | `100000` |
+    /// | `OBJECT_STORE__BACKEND__DIR_PERMISSIONS` | octal mode | `750` |
+    /// | `OBJECT_STORE__BACKEND__STRICT_INIT` | bool | `true` |
+    /// | `OBJECT_STORE__BACKEND__MIN_FREE_BYTES` | u64 | `1073741824` |
+    /// | `OBJECT_STORE__BACKEND__ENCRYPTION__KEY_HEX` | 64 hex digits | `00112233...` |
+    ///
+    /// `s3`:
+    ///
+    /// | Variable | Type | Example |
+    /// |----------|------|---------|
+    /// | `OBJECT_STORE__BACKEND__REGION` | string, required | `us-east-1` |
+    /// | `OBJECT_STORE__BACKEND__PHYSICAL_BUCKET` | string, required | `my-bucket` |
+    /// | `OBJECT_STORE__BACKEND__ENDPOINT` | URL | `http://localhost:9000` |
+    /// | `OBJECT_STORE__BACKEND__FORCE_PATH_STYLE` | bool | `true` |
+    /// | `OBJECT_STORE__BACKEND__CHECKSUM_ALGORITHM` | `none` or `crc32c` | `crc32c` |
+    ///
+    /// `gcs`:
+    ///
+    /// | Variable | Type | Example |
+    /// |----------|------|---------|
+    /// | `OBJECT_STORE__BACKEND__PHYSICAL_BUCKET` | string, required | `my-bucket` |
+    /// | `OBJECT_STORE__BACKEND__RESUMABLE_THRESHOLD_BYTES` | u64 | `8388608` |
+    ///
+    /// `azure`, with exactly one of the `AUTH` variables:
+    ///
+    /// | Variable | Type | Example |
+    /// |----------|------|---------|
+    /// | `OBJECT_STORE__BACKEND__ACCOUNT` | string, required | `myaccount` |
+    /// | `OBJECT_STORE__BACKEND__PHYSICAL_BUCKET` (or `__CONTAINER`) | string, required | `mycontainer` |
+    /// | `OBJECT_STORE__BACKEND__AUTH__ACCESS_KEY` | string | `a2V5` |
+    /// | `OBJECT_STORE__BACKEND__AUTH__CONNECTION_STRING` | string | `AccountName=...;AccountKey=...` |
+    /// | `OBJECT_STORE__BACKEND__AUTH` | `managed_identity` | `managed_identity` |
+    ///
+    /// Sections other than `backend` take their fields the same way, e.g.
+    /// `OBJECT_STORE__METADATA__CACHE_TTL_SECS=300`. A `composite` backend has
+    /// nested routes and can only be configured in a file.
     pub fn from_env() -> Result<Self, config::ConfigError> {
         let settings = config::Config::builder()
-            .add_source(config::Environment::with_prefix("OBJECT_STORE").separator("__"))
+            .add_source(Self::env_source())
             .build()?;
 
-        settings.try_deserialize()
+        Self::deserialize_with_typed_backend(settings)
+    }
+
+    /// Every environment value is a string, and serde reads the fields of the
+    /// `type`-tagged [`BackendConfig`] without converting them, so numbers and
+    /// booleans in the backend section would be rejected. Builds the backend
+    /// one field at a time instead, which converts each value, and swaps it in
+    /// for the untyped `backend` table.
+    fn deserialize_with_typed_backend(
+        settings: config::Config,
+    ) -> Result<Self, config::ConfigError> {
+        use config::Source;
+
+        let Some(backend) = BackendConfig::from_settings(&settings)? else {
+            return settings.try_deserialize();
+        };
+
+        #[derive(Serialize)]
+        struct Typed<'a> {
+            backend: &'a BackendConfig,
+        }
+        let mut table = settings.collect()?;
+        table.extend(config::Config::try_from(&Typed { backend: &backend })?.collect()?);
+        Self::deserialize(config::Value::new(None, table))
+    }
+}
+
+impl BackendConfig {
+    /// Builds the variant named by `backend.type` from the other `backend.*`
+    /// settings. `None` when there is no type, and for `composite` or an
+    /// unknown type, which are left to serde to build or reject.
+    fn from_settings(settings: &config::Config) -> Result<Option<Self>, config::ConfigError> {
+        let backend_type = match settings.get_string("backend.type") {
+            Ok(backend_type) => backend_type,
+            Err(config::ConfigError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let field = |name: &str| format!("backend.{}", name);
+        let required = |name: &str| settings.get_string(&field(name));
+
+        let backend = match backend_type.as_str() {
+            "local" => BackendConfig::Local {
+                root_path: settings.get(&field("root_path"))?,
+                physical_bucket: optional(settings, &field("physical_bucket"))?
+                    .unwrap_or_else(default_physical_bucket),
+                use_xattrs: optional(settings, &field("use_xattrs"))?.unwrap_or_default(),
+                list_cache_ttl_secs: optional(settings, &field("list_cache_ttl_secs"))?
+                    .unwrap_or_default(),
+                list_cache_capacity: optional(settings, &field("list_cache_capacity"))?
+                    .unwrap_or_else(default_list_cache_capacity),
+                metadata_cache_capacity: optional(settings, &field("metadata_cache_capacity"))?
+                    .unwrap_or_default(),
+                dir_permissions: optional::<DirPermissions>(settings, &field("dir_permissions"))?
+                    .and_then(|mode| mode.0),
+                strict_init: optional(settings, &field("strict_init"))?.unwrap_or_default(),
+                min_free_bytes: optional(settings, &field("min_free_bytes"))?,
+                encryption: optional(settings, &field("encryption"))?,
+            },
+            "s3" => BackendConfig::S3 {
+                region: required("region")?,
+                physical_bucket: required("physical_bucket")?,
+                endpoint: optional(settings, &field("endpoint"))?,
+                force_path_style: optional(settings, &field("force_path_style"))?
+                    .unwrap_or_default(),
+                checksum_algorithm: optional(settings, &field("checksum_algorithm"))?
+                    .unwrap_or_default(),
+            },
+            "gcs" => BackendConfig::Gcs {
+                physical_bucket: required("physical_bucket")?,
+                resumable_threshold_bytes: optional(settings, &field("resumable_threshold_bytes"))?
+                    .unwrap_or_else(default_resumable_threshold_bytes),
+            },
+            "azure" => BackendConfig::Azure {
+                account: required("account")?,
+                physical_bucket: match optional(settings, &field("physical_bucket"))? {
+                    Some(physical_bucket) => physical_bucket,
+                    None => required("container")?,
+                },
+                auth: settings.get(&field("auth"))?,
+            },
+            _ => return Ok(None),
+        };
+        Ok(Some(backend))
+    }
+}
+
+/// `dir_permissions` read through its octal-aware deserializer.
+#[derive(Deserialize)]
+struct DirPermissions(#[serde(deserialize_with = "deserialize_dir_permissions")] Option<u32>);
+
+fn optional<T: serde::de::DeserializeOwned>(
+    settings: &config::Config,
+    key: &str,
+) -> Result<Option<T>, config::ConfigError> {
+    match settings.get(key) {
+        Ok(value) => Ok(Some(value)),
+        Err(config::ConfigError::NotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENV_VARS: &[(&str, &str)] = &[
+        ("OBJECT_STORE__SERVER__HOST", "0.0.0.0"),
+        ("OBJECT_STORE__SERVER__PORT", "9000"),
+        ("OBJECT_STORE__SERVER__MAX_BODY_BYTES", "1048576"),
+        ("OBJECT_STORE__BACKEND__TYPE", "s3"),
+        ("OBJECT_STORE__BACKEND__REGION", "us-east-1"),
+        // Numeric, to check values are not coerced away from strings
+        ("OBJECT_STORE__BACKEND__PHYSICAL_BUCKET", "2024"),
+        ("OBJECT_STORE__BACKEND__ENDPOINT", "http://localhost:9000"),
+        ("OBJECT_STORE__BACKEND__CHECKSUM_ALGORITHM", "crc32c"),
+        ("OBJECT_STORE__METRICS__STATS_CACHE_TTL_SECONDS", "30"),
+        ("OBJECT_STORE__CIRCUIT_BREAKER__FAILURE_THRESHOLD", "3"),
+    ];
+
+    // One test, so no other test observes the process environment mid-change
+    #[test]
+    fn test_config_from_env_and_precedence() {
+        for (name, value) in ENV_VARS {
+            std::env::set_var(name, value);
+        }
+
+        let config = Config::from_env().unwrap();
+        assert_eq!(config.server.host, "0.0.0.0");
+        assert_eq!(config.server.port, 9000);
+        assert_eq!(config.server.max_body_bytes, Some(1048576));
+        assert_eq!(config.metrics.stats_cache_ttl_seconds, 30);
+        let breaker = config.circuit_breaker.as_ref().unwrap();
+        assert_eq!(breaker.failure_threshold, 3);
+        assert_eq!(breaker.open_duration_secs, 30);
+        match &config.backend {
+            BackendConfig::S3 {
+                region,
+                physical_bucket,
+                endpoint,
+                checksum_algorithm,
+                ..
+            } => {
+                assert_eq!(region, "us-east-1");
+                assert_eq!(physical_bucket, "2024");
+                assert_eq!(endpoint.as_deref(), Some("http://localhost:9000"));
+                assert_eq!(*checksum_algorithm, ChecksumAlgorithm::Crc32c);
+            }
+            other => panic!("expected S3 backend, got {:?}", other),
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            r#"
+[server]
+host = "127.0.0.1"
+port = 8080
+
+[backend]
+type = "s3"
+region = "eu-west-1"
+physical_bucket = "file-bucket"
+"#,
+        )
+        .unwrap();
+
+        let config = Config::from_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(config.server.host, "0.0.0.0");
+        assert_eq!(config.server.port, 9000);
+        match &config.backend {
+            BackendConfig::S3 {
+                region,
+                physical_bucket,
+                ..
+            } => {
+                assert_eq!(region, "us-east-1");
+                assert_eq!(physical_bucket, "2024");
+            }
+            other => panic!("expected S3 backend, got {:?}", other),
+        }
+
+        for (name, _) in ENV_VARS {
+            std::env::remove_var(name);
+        }
+    }
+
+    #[test]
+    fn test_local_dir_permissions_in_octal() {
+        // Built without the environment source, which the test above changes
+        let backend: BackendConfig = config::Config::builder()
+            .add_source(config::File::from_str(
+                "type = \"local\"\nroot_path = \"./data\"\ndir_permissions = 0o700\n",
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+
+        match backend {
+            BackendConfig::Local {
+                dir_permissions, ..
+            } => assert_eq!(dir_permissions, Some(0o700)),
+            other => panic!("expected local backend, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_virtual_hosted_urls_need_a_domain() {
+        let mut server = ServerConfig {
+            url_style: UrlStyle::VirtualHosted,
+            ..Default::default()
+        };
+        assert!(server.validate().is_err());
+
+        server.virtual_host_domain = Some("storage.example.com".to_string());
+        assert!(server.validate().is_ok());
+        assert!(ServerConfig::default().validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_azure_auth_variants() {
+        let parse = |toml: &str| -> BackendConfig {
+            config::Config::builder()
+                .add_source(config::File::from_str(toml, config::FileFormat::Toml))
+                .build()
+                .unwrap()
+                .try_deserialize()
+                .unwrap()
+        };
+        let auth = |backend: BackendConfig| match backend {
+            BackendConfig::Azure {
+                physical_bucket,
+                auth,
+                ..
+            } => {
+                assert_eq!(physical_bucket, "mycontainer");
+                auth
+            }
+            other => panic!("expected Azure backend, got {:?}", other),
+        };
+
+        let access_key = parse(
+            "type = \"azure\"\naccount = \"myaccount\"\nphysical_bucket = \"mycontainer\"\n\
+             auth = { access_key = \"a2V5\" }\n",
+        );
+        assert_eq!(auth(access_key), AzureAuth::AccessKey("a2V5".to_string()));
+
+        let connection_string = parse(
+            "type = \"azure\"\naccount = \"myaccount\"\ncontainer = \"mycontainer\"\n\
+             [auth]\nconnection_string = \"AccountName=myaccount;AccountKey=a2V5\"\n",
+        );
+        assert_eq!(
+            auth(connection_string),
+            AzureAuth::ConnectionString("AccountName=myaccount;AccountKey=a2V5".to_string())
+        );
+
+        let managed_identity = parse(
+            "type = \"azure\"\naccount = \"myaccount\"\nphysical_bucket = \"mycontainer\"\n\
+             auth = \"managed_identity\"\n",
+        );
+        let result = managed_identity.create_backend().await;
+        assert_eq!(auth(managed_identity), AzureAuth::ManagedIdentity);
+        #[cfg(not(feature = "azure-managed-identity"))]
+        assert!(matches!(
+            result,
+            Err(object_store_backends::BackendError::Configuration(_))
+        ));
+        #[cfg(feature = "azure-managed-identity")]
+        assert!(result.is_ok());
     }
 }
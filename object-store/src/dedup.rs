@@ -0,0 +1,162 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeduplicatorConfig {
+    /// Keys with a lock kept around before idle ones are dropped. Keys being
+    /// uploaded are never dropped, so this can be exceeded while more uploads
+    /// than this are in flight.
+    #[serde(default = "default_max_tracked_keys")]
+    pub max_tracked_keys: usize,
+    /// How long a key's lock is kept after its last upload.
+    #[serde(default = "default_key_ttl_secs")]
+    pub key_ttl_secs: u64,
+}
+
+fn default_max_tracked_keys() -> usize {
+    10_000
+}
+
+fn default_key_ttl_secs() -> u64 {
+    300
+}
+
+impl Default for DeduplicatorConfig {
+    fn default() -> Self {
+        Self {
+            max_tracked_keys: default_max_tracked_keys(),
+            key_ttl_secs: default_key_ttl_secs(),
+        }
+    }
+}
+
+struct TrackedKey {
+    lock: Arc<Mutex<()>>,
+    last_used: Instant,
+}
+
+impl TrackedKey {
+    /// Held or waited on by an upload. The map's own reference is the only
+    /// other one, and new references are only taken under the map's shard lock.
+    fn in_use(&self) -> bool {
+        Arc::strong_count(&self.lock) > 1
+    }
+}
+
+/// Per-key locks that serialize concurrent uploads to the same `bucket/key`
+/// within this process, so one write finishes before the next starts and the
+/// last writer's object is stored whole.
+pub struct UploadDeduplicator {
+    keys: DashMap<String, TrackedKey>,
+    max_tracked_keys: usize,
+    key_ttl: Duration,
+    last_prune: StdMutex<Instant>,
+}
+
+impl UploadDeduplicator {
+    pub fn new(config: &DeduplicatorConfig) -> Self {
+        Self {
+            keys: DashMap::new(),
+            max_tracked_keys: config.max_tracked_keys,
+            key_ttl: Duration::from_secs(config.key_ttl_secs),
+            last_prune: StdMutex::new(Instant::now()),
+        }
+    }
+
+    /// Takes the lock for `key` if no other upload holds it.
+    pub fn try_lock(&self, key: &str) -> Option<OwnedMutexGuard<()>> {
+        self.lock_for(key).try_lock_owned().ok()
+    }
+
+    /// Waits until no other upload holds the lock for `key` and takes it.
+    pub async fn lock(&self, key: &str) -> OwnedMutexGuard<()> {
+        self.lock_for(key).lock_owned().await
+    }
+
+    /// Number of keys with a lock, in use or idle.
+    pub fn tracked_keys(&self) -> usize {
+        self.keys.len()
+    }
+
+    fn lock_for(&self, key: &str) -> Arc<Mutex<()>> {
+        if let Some(mut tracked) = self.keys.get_mut(key) {
+            tracked.last_used = Instant::now();
+            return tracked.lock.clone();
+        }
+
+        self.prune_if_due();
+        self.keys
+            .entry(key.to_string())
+            .or_insert_with(|| TrackedKey {
+                lock: Arc::new(Mutex::new(())),
+                last_used: Instant::now(),
+            })
+            .lock
+            .clone()
+    }
+
+    /// Drops idle locks older than the TTL, at most once per TTL unless the
+    /// map is full. A full map also loses its other idle locks.
+    fn prune_if_due(&self) {
+        let now = Instant::now();
+        let full = self.keys.len() >= self.max_tracked_keys;
+        {
+            let mut last_prune = self.last_prune.lock().unwrap();
+            if !full && now.duration_since(*last_prune) < self.key_ttl {
+                return;
+            }
+            *last_prune = now;
+        }
+
+        self.keys.retain(|_, tracked| {
+            tracked.in_use() || now.duration_since(tracked.last_used) < self.key_ttl
+        });
+        if self.keys.len() >= self.max_tracked_keys {
+            self.keys.retain(|_, tracked| tracked.in_use());
+        }
+    }
+}
+
+impl Default for UploadDeduplicator {
+    fn default() -> Self {
+        Self::new(&DeduplicatorConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_same_key_waits_for_the_holder() {
+        let dedup = UploadDeduplicator::default();
+
+        let held = dedup.lock("bucket/key").await;
+        assert!(dedup.try_lock("bucket/key").is_none());
+        assert!(dedup.try_lock("bucket/other").is_some());
+
+        drop(held);
+        assert!(dedup.try_lock("bucket/key").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_idle_keys_are_dropped_when_full() {
+        let dedup = UploadDeduplicator::new(&DeduplicatorConfig {
+            max_tracked_keys: 2,
+            key_ttl_secs: 300,
+        });
+
+        let held = dedup.lock("bucket/a").await;
+        drop(dedup.lock("bucket/b").await);
+        assert_eq!(dedup.tracked_keys(), 2);
+
+        // b is idle, so it makes room for c; a is still held and stays
+        let _c = dedup.lock("bucket/c").await;
+        assert_eq!(dedup.tracked_keys(), 2);
+        assert!(dedup.try_lock("bucket/a").is_none());
+        drop(held);
+    }
+}
@@ -1,13 +1,17 @@
-use axum::http::StatusCode;
+use axum::http::{header, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
+use object_store_backends::BackendError;
 use serde_json::json;
 use thiserror::Error;
 
+use crate::auth::OperationType;
+use crate::middleware::RequestId;
+
 #[derive(Error, Debug)]
 pub enum ServiceError {
     #[error("Backend error: {0}")]
-    Backend(#[from] object_store_backends::BackendError),
+    Backend(#[from] BackendError),
 
     #[error("Bucket not found: {0}")]
     BucketNotFound(String),
@@ -35,6 +39,42 @@ pub enum ServiceError {
 
     #[error("Lock acquisition error: {0}")]
     LockAcquisition(String),
+
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Rejected by hook: {0}")]
+    HookRejected(String),
+
+    #[error("URL revoked: {0}")]
+    UrlRevoked(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Permission denied: '{principal}' may not {operation} on '{resource}'")]
+    PermissionDenied {
+        operation: OperationType,
+        resource: String,
+        principal: String,
+    },
+
+    #[error("API key not found: {0}")]
+    ApiKeyNotFound(String),
+
+    #[error("Rename failed: {0}")]
+    RenameFailed(String),
+
+    /// The client spent its rate limit burst.
+    #[error("Too many requests, retry in {retry_after_secs}s")]
+    TooManyRequests { retry_after_secs: u64 },
+
+    /// The backend can't be reached, so nothing can be served right now.
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
 }
 
 impl From<serde_json::Error> for ServiceError {
@@ -45,26 +85,106 @@ impl From<serde_json::Error> for ServiceError {
 
 impl IntoResponse for ServiceError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            ServiceError::BucketNotFound(_) | ServiceError::ObjectNotFound(_) => {
-                (StatusCode::NOT_FOUND, self.to_string())
+        let message = self.to_string();
+        // Context only adds to the message; the wrapped error decides the status
+        let err = match self {
+            ServiceError::Backend(e) => ServiceError::Backend(e.into_root()),
+            other => other,
+        };
+
+        let retry_after = match &err {
+            ServiceError::Backend(BackendError::RateLimited { retry_after_secs }) => {
+                *retry_after_secs
+            }
+            ServiceError::Backend(BackendError::CircuitOpen { retry_after_secs }) => {
+                Some(*retry_after_secs)
+            }
+            ServiceError::TooManyRequests { retry_after_secs } => Some(*retry_after_secs),
+            _ => None,
+        };
+
+        let unauthorized = matches!(err, ServiceError::Unauthorized(_));
+        let max_size = match &err {
+            ServiceError::Backend(BackendError::EntityTooLarge { max_size, .. }) => Some(*max_size),
+            _ => None,
+        };
+        let denied = match &err {
+            ServiceError::PermissionDenied {
+                operation,
+                resource,
+                principal,
+            } => Some((*operation, resource.clone(), principal.clone())),
+            _ => None,
+        };
+
+        let (status, error_message) = match err {
+            ServiceError::BucketNotFound(_)
+            | ServiceError::ObjectNotFound(_)
+            | ServiceError::ApiKeyNotFound(_) => (StatusCode::NOT_FOUND, message),
+            ServiceError::BucketAlreadyExists(_) => (StatusCode::CONFLICT, message),
+            ServiceError::InvalidBucketName(_)
+            | ServiceError::InvalidObjectKey(_)
+            | ServiceError::InvalidRequest(_)
+            | ServiceError::Backend(BackendError::BodyStream(_)) => {
+                (StatusCode::BAD_REQUEST, message)
             }
-            ServiceError::BucketAlreadyExists(_) => (StatusCode::CONFLICT, self.to_string()),
-            ServiceError::InvalidBucketName(_) | ServiceError::InvalidObjectKey(_) => {
-                (StatusCode::BAD_REQUEST, self.to_string())
+            ServiceError::PayloadTooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, message),
+            ServiceError::HookRejected(_) | ServiceError::PermissionDenied { .. } => {
+                (StatusCode::FORBIDDEN, message)
             }
-            ServiceError::Backend(object_store_backends::BackendError::NotFound(key)) => {
+            ServiceError::Unauthorized(_) => (StatusCode::UNAUTHORIZED, message),
+            ServiceError::UrlRevoked(_) => (StatusCode::GONE, message),
+            ServiceError::Unavailable(_) => (StatusCode::SERVICE_UNAVAILABLE, message),
+            ServiceError::TooManyRequests { .. } => (StatusCode::TOO_MANY_REQUESTS, message),
+            // For paths that don't convert a missing key to ObjectNotFound
+            ServiceError::Backend(BackendError::NotFound(key)) => {
                 (StatusCode::NOT_FOUND, format!("Object not found: {}", key))
             }
-            ServiceError::Backend(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            _ => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
+            ServiceError::Backend(BackendError::PermissionDenied(_)) => {
+                (StatusCode::FORBIDDEN, message)
+            }
+            ServiceError::Backend(BackendError::RateLimited { .. })
+            | ServiceError::Backend(BackendError::RequestLimitExceeded) => {
+                (StatusCode::TOO_MANY_REQUESTS, message)
+            }
+            ServiceError::Backend(BackendError::EntityTooLarge { .. }) => {
+                (StatusCode::PAYLOAD_TOO_LARGE, message)
+            }
+            ServiceError::Backend(BackendError::CircuitOpen { .. }) => {
+                (StatusCode::SERVICE_UNAVAILABLE, message)
+            }
+            ServiceError::Backend(_) => (StatusCode::INTERNAL_SERVER_ERROR, message),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, message),
         };
 
-        let body = Json(json!({
+        let mut body = json!({
             "error": error_message,
-        }));
+        });
+        if let Some(max_size) = max_size {
+            body["max_size"] = json!(max_size);
+        }
+        if let Some((operation, resource, principal)) = denied {
+            body["operation"] = json!(operation);
+            body["resource"] = json!(resource);
+            body["principal"] = json!(principal);
+        }
+        if let Some(request_id) = RequestId::current() {
+            body["request_id"] = json!(request_id);
+        }
+        let body = Json(body);
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        if unauthorized {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        }
+        response
     }
 }
 
@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use object_store_backends::ObjectMetadata;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventType {
+    /// Stored by `put_object` or as part of an upsert.
+    ObjectCreated,
+    /// Deleted, or moved to the recycle bin.
+    ObjectDeleted,
+    /// Copied back from the recycle bin.
+    ObjectRestored,
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectEvent {
+    pub event_type: EventType,
+    pub bucket: String,
+    pub key: String,
+    /// The object as stored, or as it was before a delete. `metadata.key` is
+    /// the key within the bucket, like `key`.
+    pub metadata: ObjectMetadata,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl ObjectEvent {
+    pub fn new(event_type: EventType, bucket: &str, mut metadata: ObjectMetadata) -> Self {
+        if let Some(key) = metadata.key.strip_prefix(&format!("{}/", bucket)) {
+            metadata.key = key.to_string();
+        }
+        Self {
+            event_type,
+            bucket: bucket.to_string(),
+            key: metadata.key.clone(),
+            metadata,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// In-process fan-out of object events to components that react to them, such
+/// as background replication and post-upload hooks. Each subscriber gets every
+/// event published after it subscribed. Its queue is unbounded, so a subscriber
+/// that falls behind holds events in memory instead of missing any; a missed
+/// upload would leave a replica behind for good.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Mutex<Vec<UnboundedSender<ObjectEvent>>>,
+}
+
+impl EventBus {
+    /// Returns how many subscribers the event was delivered to.
+    pub fn publish(&self, event: ObjectEvent) -> usize {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        // Dropped receivers are forgotten
+        subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        subscribers.len()
+    }
+
+    pub fn subscribe(&self) -> UnboundedReceiver<ObjectEvent> {
+        let (sender, receiver) = unbounded_channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn created(key: &str) -> ObjectEvent {
+        ObjectEvent::new(
+            EventType::ObjectCreated,
+            "bucket",
+            ObjectMetadata {
+                key: format!("bucket/{}", key),
+                size: 0,
+                content_type: None,
+                etag: String::new(),
+                last_modified: Utc::now(),
+                custom_metadata: HashMap::new(),
+            },
+        )
+    }
+
+    #[test]
+    fn test_slow_subscribers_miss_nothing() {
+        let bus = EventBus::default();
+        let mut events = bus.subscribe();
+        let dropped = bus.subscribe();
+        drop(dropped);
+
+        // Far more than the old bounded channel held
+        for i in 0..10_000 {
+            bus.publish(created(&i.to_string()));
+        }
+        assert_eq!(bus.publish(created("last")), 1);
+
+        for i in 0..10_000 {
+            assert_eq!(events.try_recv().unwrap().key, i.to_string());
+        }
+        assert_eq!(events.try_recv().unwrap().key, "last");
+        assert!(events.try_recv().is_err());
+    }
+}
@@ -0,0 +1,359 @@
+// tonic requires `Result<_, Status>` everywhere, large as `Status` is
+#![allow(clippy::result_large_err)]
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use object_store_backends::{BackendError, ByteStream};
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::metadata::MetadataMap;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::auth::OperationType;
+use crate::error::ServiceError;
+use crate::metadata::Bucket;
+use crate::service::{ListBucketsOptions, ListObjectsOptions, ObjectStoreService};
+
+pub mod proto {
+    tonic::include_proto!("object_store.v1");
+}
+
+use proto::object_store_server::{ObjectStore, ObjectStoreServer};
+use proto::{get_object_response, put_object_request};
+
+/// Largest chunk sent in a `GetObject` response, well under tonic's default
+/// 4 MiB message limit.
+const MAX_CHUNK_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrpcConfig {
+    /// Port of the gRPC listener, bound on the same host as `[server]`.
+    pub port: u16,
+}
+
+/// Serves the gRPC API on `listener` until the server fails.
+pub async fn serve(
+    service: Arc<ObjectStoreService>,
+    listener: TcpListener,
+) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(ObjectStoreServer::new(GrpcService::new(service)))
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await
+}
+
+/// The `ObjectStore` gRPC service, a thin layer over [`ObjectStoreService`].
+pub struct GrpcService {
+    service: Arc<ObjectStoreService>,
+}
+
+impl GrpcService {
+    pub fn new(service: Arc<ObjectStoreService>) -> Self {
+        Self { service }
+    }
+
+    /// gRPC callers authenticate like REST callers, with an
+    /// `authorization: Bearer <key>` metadata entry.
+    async fn authorize(
+        &self,
+        metadata: &MetadataMap,
+        operation: OperationType,
+        resource: &str,
+    ) -> Result<(), Status> {
+        if !self.service.requires_api_key() {
+            return Ok(());
+        }
+
+        let key = metadata
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| Status::unauthenticated("Missing API key"))?;
+        self.service.authenticate(key, operation, resource).await?;
+        Ok(())
+    }
+}
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl ObjectStore for GrpcService {
+    async fn create_bucket(
+        &self,
+        request: Request<proto::CreateBucketRequest>,
+    ) -> Result<Response<proto::Bucket>, Status> {
+        self.authorize(
+            request.metadata(),
+            OperationType::CreateBucket,
+            &request.get_ref().name,
+        )
+        .await?;
+        let bucket = self
+            .service
+            .create_bucket(&request.into_inner().name)
+            .await?;
+        Ok(Response::new(bucket.into()))
+    }
+
+    async fn list_buckets(
+        &self,
+        request: Request<proto::ListBucketsRequest>,
+    ) -> Result<Response<proto::ListBucketsResponse>, Status> {
+        self.authorize(request.metadata(), OperationType::ListObjects, "*")
+            .await?;
+        let buckets = self
+            .service
+            .list_buckets(&ListBucketsOptions::default())
+            .await?;
+        Ok(Response::new(proto::ListBucketsResponse {
+            buckets: buckets.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn put_object(
+        &self,
+        request: Request<Streaming<proto::PutObjectRequest>>,
+    ) -> Result<Response<proto::ObjectMetadata>, Status> {
+        let auth = request.metadata().clone();
+        let mut parts = request.into_inner();
+
+        let header = match parts.message().await? {
+            Some(proto::PutObjectRequest {
+                part: Some(put_object_request::Part::Header(header)),
+            }) => header,
+            _ => {
+                return Err(Status::invalid_argument(
+                    "The first PutObject message must be a header",
+                ))
+            }
+        };
+        self.authorize(
+            &auth,
+            OperationType::WriteObject,
+            &format!("{}/{}", header.bucket, header.key),
+        )
+        .await?;
+        let max_object_bytes = self
+            .service
+            .get_bucket(&header.bucket)
+            .await?
+            .max_object_bytes;
+        let content_type = header.content_type.or_else(|| {
+            mime_guess::from_path(&header.key)
+                .first()
+                .map(|m| m.to_string())
+        });
+
+        let limit_exceeded = Arc::new(AtomicBool::new(false));
+        let flag = limit_exceeded.clone();
+        let mut received = 0u64;
+        let stream: ByteStream = Box::pin(parts.map(move |message| {
+            let chunk = match message.map_err(std::io::Error::other)?.part {
+                Some(put_object_request::Part::Chunk(chunk)) => chunk,
+                _ => return Err(std::io::Error::other("expected an object chunk")),
+            };
+            received += chunk.len() as u64;
+            if max_object_bytes.is_some_and(|max| received > max) {
+                flag.store(true, Ordering::Relaxed);
+                return Err(std::io::Error::other("object size limit exceeded"));
+            }
+            Ok(chunk)
+        }));
+
+        let metadata = self
+            .service
+            .put_object(
+                &header.bucket,
+                &header.key,
+                stream,
+                content_type,
+                header.metadata,
+            )
+            .await
+            .map_err(|e| {
+                if limit_exceeded.load(Ordering::Relaxed) {
+                    Status::resource_exhausted(format!(
+                        "Object exceeds the size limit of bucket {}",
+                        header.bucket
+                    ))
+                } else {
+                    e.into()
+                }
+            })?;
+        Ok(Response::new(metadata.into()))
+    }
+
+    type GetObjectStream = ResponseStream<proto::GetObjectResponse>;
+
+    async fn get_object(
+        &self,
+        request: Request<proto::GetObjectRequest>,
+    ) -> Result<Response<Self::GetObjectStream>, Status> {
+        let resource = format!("{}/{}", request.get_ref().bucket, request.get_ref().key);
+        self.authorize(request.metadata(), OperationType::ReadObject, &resource)
+            .await?;
+        let request = request.into_inner();
+        let object = self
+            .service
+            .get_object(&request.bucket, &request.key)
+            .await?;
+
+        let metadata = proto::GetObjectResponse {
+            part: Some(get_object_response::Part::Metadata(object.metadata.into())),
+        };
+        let chunks = object.stream.flat_map(|chunk| {
+            let messages: Vec<Result<proto::GetObjectResponse, Status>> = match chunk {
+                Ok(chunk) => split_chunk(chunk)
+                    .into_iter()
+                    .map(|chunk| {
+                        Ok(proto::GetObjectResponse {
+                            part: Some(get_object_response::Part::Chunk(chunk)),
+                        })
+                    })
+                    .collect(),
+                Err(e) => vec![Err(Status::internal(format!(
+                    "Failed to read object: {}",
+                    e
+                )))],
+            };
+            futures::stream::iter(messages)
+        });
+
+        Ok(Response::new(Box::pin(
+            futures::stream::once(async { Ok(metadata) }).chain(chunks),
+        )))
+    }
+
+    async fn delete_object(
+        &self,
+        request: Request<proto::DeleteObjectRequest>,
+    ) -> Result<Response<proto::DeleteObjectResponse>, Status> {
+        let resource = format!("{}/{}", request.get_ref().bucket, request.get_ref().key);
+        self.authorize(request.metadata(), OperationType::DeleteObject, &resource)
+            .await?;
+        let request = request.into_inner();
+        self.service
+            .delete_object(&request.bucket, &request.key)
+            .await?;
+        Ok(Response::new(proto::DeleteObjectResponse {}))
+    }
+
+    type ListObjectsStream = ResponseStream<proto::ObjectMetadata>;
+
+    async fn list_objects(
+        &self,
+        request: Request<proto::ListObjectsRequest>,
+    ) -> Result<Response<Self::ListObjectsStream>, Status> {
+        self.authorize(
+            request.metadata(),
+            OperationType::ListObjects,
+            &request.get_ref().bucket,
+        )
+        .await?;
+        let request = request.into_inner();
+        let options = ListObjectsOptions::new(
+            request.prefix.as_deref(),
+            request.max_keys.map(|max| max as usize),
+        );
+        let objects = self
+            .service
+            .list_objects_stream(&request.bucket, &options)
+            .await?;
+        Ok(Response::new(Box::pin(objects.map(|metadata| {
+            metadata.map(Into::into).map_err(Status::from)
+        }))))
+    }
+
+    async fn head_object(
+        &self,
+        request: Request<proto::HeadObjectRequest>,
+    ) -> Result<Response<proto::ObjectMetadata>, Status> {
+        let resource = format!("{}/{}", request.get_ref().bucket, request.get_ref().key);
+        self.authorize(request.metadata(), OperationType::ReadObject, &resource)
+            .await?;
+        let request = request.into_inner();
+        let metadata = self
+            .service
+            .head_object(&request.bucket, &request.key)
+            .await?;
+        Ok(Response::new(metadata.into()))
+    }
+}
+
+fn split_chunk(mut chunk: Bytes) -> Vec<Bytes> {
+    let mut chunks = Vec::with_capacity(chunk.len() / MAX_CHUNK_BYTES + 1);
+    while chunk.len() > MAX_CHUNK_BYTES {
+        chunks.push(chunk.split_to(MAX_CHUNK_BYTES));
+    }
+    chunks.push(chunk);
+    chunks
+}
+
+impl From<ServiceError> for Status {
+    fn from(err: ServiceError) -> Self {
+        let message = err.to_string();
+        let err = match err {
+            ServiceError::Backend(e) => ServiceError::Backend(e.into_root()),
+            other => other,
+        };
+        match err {
+            ServiceError::BucketNotFound(_)
+            | ServiceError::ObjectNotFound(_)
+            | ServiceError::ApiKeyNotFound(_)
+            | ServiceError::Backend(BackendError::NotFound(_)) => Status::not_found(message),
+            ServiceError::BucketAlreadyExists(_) => Status::already_exists(message),
+            ServiceError::InvalidBucketName(_)
+            | ServiceError::InvalidObjectKey(_)
+            | ServiceError::InvalidRequest(_)
+            | ServiceError::Backend(BackendError::BodyStream(_)) => {
+                Status::invalid_argument(message)
+            }
+            ServiceError::PayloadTooLarge(_)
+            | ServiceError::Backend(BackendError::EntityTooLarge { .. })
+            | ServiceError::Backend(BackendError::RateLimited { .. })
+            | ServiceError::Backend(BackendError::RequestLimitExceeded)
+            | ServiceError::TooManyRequests { .. } => Status::resource_exhausted(message),
+            ServiceError::HookRejected(_)
+            | ServiceError::PermissionDenied { .. }
+            | ServiceError::Backend(BackendError::PermissionDenied(_)) => {
+                Status::permission_denied(message)
+            }
+            ServiceError::Unauthorized(_) => Status::unauthenticated(message),
+            ServiceError::UrlRevoked(_) => Status::failed_precondition(message),
+            ServiceError::Backend(BackendError::CircuitOpen { .. }) => Status::unavailable(message),
+            _ => Status::internal(message),
+        }
+    }
+}
+
+impl From<Bucket> for proto::Bucket {
+    fn from(bucket: Bucket) -> Self {
+        Self {
+            id: bucket.id,
+            name: bucket.name,
+            created_at: bucket.created_at,
+            description: bucket.description,
+            tags: bucket.tags,
+            max_object_bytes: bucket.max_object_bytes,
+        }
+    }
+}
+
+impl From<object_store_backends::ObjectMetadata> for proto::ObjectMetadata {
+    fn from(metadata: object_store_backends::ObjectMetadata) -> Self {
+        Self {
+            key: metadata.key,
+            size: metadata.size,
+            content_type: metadata.content_type,
+            etag: metadata.etag,
+            last_modified: metadata.last_modified.to_rfc3339(),
+            metadata: metadata.custom_metadata,
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Request header naming a create request, so a retry of one that already
+/// succeeded gets the original response back instead of running again.
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// Set to `true` on responses replayed from the cache.
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdempotencyConfig {
+    /// How long a response is replayed for after the first request.
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Responses kept before the least recently used are dropped.
+    #[serde(default = "default_cache_capacity")]
+    pub cache_capacity: usize,
+}
+
+fn default_ttl_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_cache_capacity() -> usize {
+    10_000
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: default_ttl_secs(),
+            cache_capacity: default_cache_capacity(),
+        }
+    }
+}
+
+/// A response as it was first sent for an idempotency key.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub status: StatusCode,
+    pub body: serde_json::Value,
+    stored_at: Instant,
+}
+
+impl IntoResponse for CachedResponse {
+    fn into_response(self) -> Response {
+        let mut response = (self.status, Json(self.body)).into_response();
+        response
+            .headers_mut()
+            .insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+/// Recent successful responses by idempotency key, kept in this process only.
+/// Failed requests are not stored, so retrying one runs it again.
+#[derive(Clone)]
+pub struct IdempotencyCache {
+    entries: Arc<Mutex<LruCache<String, CachedResponse>>>,
+    ttl: Duration,
+}
+
+impl IdempotencyCache {
+    pub fn new(config: &IdempotencyConfig) -> Self {
+        let capacity = NonZeroUsize::new(config.cache_capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            entries: Arc::new(Mutex::new(LruCache::new(capacity))),
+            ttl: Duration::from_secs(config.ttl_secs),
+        }
+    }
+
+    /// The response stored for `key`, unless it has expired.
+    pub fn get(&self, key: &str) -> Option<CachedResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(cached) if cached.stored_at.elapsed() < self.ttl => Some(cached.clone()),
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Sends `body` with `status`, storing it for `key` first when there is one.
+    pub fn respond<T: Serialize>(
+        &self,
+        key: Option<String>,
+        status: StatusCode,
+        body: &T,
+    ) -> Result<Response, serde_json::Error> {
+        let body = serde_json::to_value(body)?;
+        if let Some(key) = key {
+            self.entries.lock().unwrap().put(
+                key,
+                CachedResponse {
+                    status,
+                    body: body.clone(),
+                    stored_at: Instant::now(),
+                },
+            );
+        }
+        Ok((status, Json(body)).into_response())
+    }
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(&IdempotencyConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expired_responses_are_not_replayed() {
+        let cache = IdempotencyCache::new(&IdempotencyConfig {
+            ttl_secs: 0,
+            cache_capacity: 10,
+        });
+        cache
+            .respond(Some("key".to_string()), StatusCode::OK, &"body")
+            .unwrap();
+        assert!(cache.get("key").is_none());
+
+        let cache = IdempotencyCache::new(&IdempotencyConfig {
+            ttl_secs: 60,
+            cache_capacity: 1,
+        });
+        cache
+            .respond(Some("first".to_string()), StatusCode::OK, &1)
+            .unwrap();
+        cache
+            .respond(Some("second".to_string()), StatusCode::CREATED, &2)
+            .unwrap();
+        assert!(cache.get("first").is_none());
+        let cached = cache.get("second").unwrap();
+        assert_eq!(cached.status, StatusCode::CREATED);
+        assert_eq!(cached.body, serde_json::json!(2));
+    }
+}
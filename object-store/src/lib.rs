@@ -1,9 +1,23 @@
 pub mod api;
+pub mod auth;
 pub mod config;
+pub mod dedup;
 pub mod error;
+pub mod events;
+pub mod grpc;
+pub mod idempotency;
 pub mod metadata;
+pub mod metrics;
+pub mod middleware;
+pub mod pipeline;
+pub mod rate_limit;
+pub mod replication;
+pub mod revocation;
 pub mod router;
 pub mod service;
+pub mod validation;
+#[cfg(feature = "wal")]
+pub mod wal;
 
 pub use config::Config;
 pub use error::{ServiceError, ServiceResult};
@@ -1,15 +1,19 @@
+use object_store::api::ServerInfo;
 use object_store::{Config, ObjectStoreService};
-use object_store_backends::{
-    azure::AzureBackend, gcs::GcsBackend, local::LocalBackend, s3::S3Backend, Backend,
-};
+use once_cell::sync::Lazy;
 use std::sync::Arc;
+use std::time::Instant;
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use object_store::metadata::MetadataStore;
 
+static STARTED_AT: Lazy<Instant> = Lazy::new(Instant::now);
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    Lazy::force(&STARTED_AT);
+
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
@@ -23,51 +27,31 @@ async fn main() -> anyhow::Result<()> {
     } else {
         Config::from_env().unwrap_or_else(|_| Config::default())
     };
+    config.server.validate()?;
 
     info!("Starting object storage service with config: {:?}", config);
 
-    let backend: Arc<dyn Backend> = match config.backend {
-        object_store::config::BackendConfig::Local {
-            root_path,
-            physical_bucket,
-        } => {
-            info!("Using local backend at {:?}", root_path);
-            Arc::new(LocalBackend::new(root_path, physical_bucket))
-        }
-        object_store::config::BackendConfig::S3 {
-            region,
-            physical_bucket,
-            endpoint,
-        } => {
-            info!(
-                "Using S3 backend with bucket: {}, region: {}, endpoint: {:?}",
-                physical_bucket, region, endpoint
-            );
-            Arc::new(S3Backend::new_with_config(physical_bucket, region, endpoint).await?)
-        }
-        object_store::config::BackendConfig::Gcs { physical_bucket } => {
-            info!("Using GCS backend with bucket: {}", physical_bucket);
-            Arc::new(GcsBackend::new(physical_bucket).await?)
-        }
-        object_store::config::BackendConfig::Azure {
-            account,
-            access_key,
-            physical_bucket,
-        } => {
-            info!(
-                "Using Azure backend with account: {}, container: {}",
-                account, physical_bucket
-            );
-            Arc::new(AzureBackend::new(account, access_key, physical_bucket)?)
-        }
-    };
+    let backend = config.create_backend().await?;
 
     backend.init().await?;
 
-    let metadata = Arc::new(MetadataStore::new(backend.clone()).await?);
-    let service = Arc::new(ObjectStoreService::new(backend, metadata.clone()));
+    let metadata =
+        Arc::new(MetadataStore::new_with_config(backend.clone(), config.metadata.clone()).await?);
+    let service = Arc::new(
+        ObjectStoreService::new(backend, metadata.clone())
+            .with_metrics_config(&config.metrics)
+            .with_auth_config(&config.auth)
+            .with_listing_config(&config.listing)
+            .with_validation_config(&config.validation)
+            .with_deduplicator_config(&config.deduplicator)
+            .with_idempotency_config(&config.idempotency)
+            .with_rate_limit_config(config.rate_limit.as_ref())
+            .with_replica_targets(&config.replicas)
+            .with_hooks_config(&config.hooks),
+    );
 
     let metadata_clone = metadata.clone();
+    let service_clone = service.clone();
     tokio::spawn(async move {
         let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
         loop {
@@ -75,17 +59,99 @@ async fn main() -> anyhow::Result<()> {
             if let Err(e) = metadata_clone.cleanup_expired_locks().await {
                 tracing::error!("Failed to cleanup expired locks: {}", e);
             }
+            if let Err(e) = service_clone
+                .purge_expired_recycle_bin(chrono::Utc::now())
+                .await
+            {
+                tracing::error!("Failed to purge expired recycle bin entries: {}", e);
+            }
+            if let Err(e) = service_clone
+                .apply_lifecycle_rules(chrono::Utc::now())
+                .await
+            {
+                tracing::error!("Failed to apply lifecycle rules: {}", e);
+            }
+            if let Err(e) = service_clone.retry_pending_replications().await {
+                tracing::error!("Failed to retry pending replications: {}", e);
+            }
+            if let Err(e) = service_clone
+                .purge_expired_revocations(chrono::Utc::now())
+                .await
+            {
+                tracing::error!("Failed to purge expired URL revocations: {}", e);
+            }
+        }
+    });
+
+    // Reloads the bucket cache before it expires, so requests rarely find it
+    // stale and have to wait for a reload themselves
+    let refresh_metadata = metadata.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(refresh_metadata.background_refresh_interval());
+        // The first tick is immediate, and the store has just loaded the cache
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = refresh_metadata.force_refresh().await {
+                tracing::error!("Failed to refresh bucket cache: {}", e);
+            }
         }
     });
 
-    let app = object_store::router::create_router(service);
+    let stats_service = service.clone();
+    let stats_interval = config.metrics.stats_cache_ttl_seconds.max(1);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(stats_interval));
+        loop {
+            interval.tick().await;
+            if let Err(e) = stats_service.refresh_bucket_metrics().await {
+                tracing::error!("Failed to refresh bucket metrics: {}", e);
+            }
+        }
+    });
+
+    if let Some(grpc) = &config.grpc {
+        let grpc_addr = format!("{}:{}", config.server.host, grpc.port);
+        let grpc_listener = tokio::net::TcpListener::bind(&grpc_addr).await?;
+        info!("gRPC API listening on {}", grpc_addr);
+        let grpc_service = service.clone();
+        tokio::spawn(async move {
+            if let Err(e) = object_store::grpc::serve(grpc_service, grpc_listener).await {
+                tracing::error!("gRPC server failed: {}", e);
+            }
+        });
+    }
+
+    let app = object_store::router::create_router_with_config(service.clone(), &config.server);
+    let server_info = ServerInfo::new(*STARTED_AT, config.backend.type_name());
+    let internal_app = object_store::router::create_internal_router_with_info(service, server_info);
+
+    let app = match &config.internal_server {
+        Some(internal) => {
+            let internal_addr = format!("{}:{}", internal.host, internal.port);
+            let internal_listener = tokio::net::TcpListener::bind(&internal_addr).await?;
+            info!("Internal endpoints listening on {}", internal_addr);
+            tokio::spawn(async move {
+                if let Err(e) = axum::serve(internal_listener, internal_app).await {
+                    tracing::error!("Internal server failed: {}", e);
+                }
+            });
+            app
+        }
+        None => app.merge(internal_app),
+    };
 
     let addr = format!("{}:{}", config.server.host, config.server.port);
     let listener = tokio::net::TcpListener::bind(&addr).await?;
 
     info!("Object storage service listening on {}", addr);
 
-    axum::serve(listener, app).await?;
+    // Peer addresses identify clients to the rate limiter when there are no API keys
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
@@ -1,26 +1,137 @@
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use futures::StreamExt;
-use object_store_backends::{Backend, BackendError};
+use object_store_backends::{Backend, BackendError, StorageClass};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::{RwLock, Semaphore};
 use tracing::{debug, info, warn};
 
+use crate::auth::{ApiKeyRecord, API_KEYS_PREFIX};
 use crate::error::{ServiceError, ServiceResult};
+use crate::pipeline::PipelineConfig;
+use crate::replication::ReplicationConfig;
+#[cfg(feature = "wal")]
+use crate::wal::{WalOp, WriteAheadLog, WAL_KEY};
 
+const METADATA_PREFIX: &str = ".metadata";
 const BUCKETS_PREFIX: &str = ".metadata/buckets";
 const LOCKS_PREFIX: &str = ".metadata/locks";
-const CACHE_TTL_SECONDS: i64 = 60;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Held by whichever instance is sweeping expired locks.
+const CLEANUP_LOCK: &str = "__cleanup__";
+const HASHES_PREFIX: &str = ".metadata/hashes";
+/// Which content hash each object of a deduplicating bucket was stored under,
+/// filed by bucket so deleting the bucket purges them.
+const CONTENT_KEYS_PREFIX: &str = ".metadata/content-keys";
+const DEFAULT_CACHE_TTL_SECS: u64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Bucket {
     pub id: String,
     pub name: String,
     pub created_at: String,
+    /// When the bucket's settings last changed. Buckets stored before this was
+    /// recorded read it as their `created_at`.
+    #[serde(default)]
+    pub updated_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Free-form key/value labels such as `team=data-eng`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tags: HashMap<String, String>,
+    /// Per-bucket upload limit, checked in addition to the server-wide body limit.
+    #[serde(default)]
+    pub max_object_bytes: Option<u64>,
+    /// When set, deleted objects are moved to the recycle bin instead of removed.
+    #[serde(default)]
+    pub recycle_bin_enabled: bool,
+    /// Days a recycled object is kept before the cleanup job purges it; 0 keeps
+    /// entries until they are deleted explicitly.
+    #[serde(default = "default_recycle_bin_retention_days")]
+    pub recycle_bin_retention_days: u32,
+    /// Copies every uploaded object to a second backend as well.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replication: Option<ReplicationConfig>,
+    /// Applied to the bucket's objects by the background lifecycle job.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lifecycle_rules: Vec<LifecycleRule>,
+    /// Uploads whose content is already stored in the bucket keep a pointer to
+    /// the existing copy instead of a second one.
+    #[serde(default)]
+    pub deduplication_enabled: bool,
+    /// Kept in builds without the `hooks` feature too, so saving the bucket
+    /// doesn't drop it; such builds don't run the hooks.
+    #[serde(default, skip_serializing_if = "PipelineConfig::is_empty")]
+    pub pipeline: PipelineConfig,
+}
+
+impl Bucket {
+    fn from_json(data: &[u8]) -> serde_json::Result<Self> {
+        let mut bucket: Bucket = serde_json::from_slice(data)?;
+        if bucket.updated_at.is_empty() {
+            bucket.updated_at = bucket.created_at.clone();
+        }
+        Ok(bucket)
+    }
+
+    fn updated_after(&self, other: &Bucket) -> bool {
+        let parse = |at: &str| DateTime::parse_from_rfc3339(at).ok();
+        match (parse(&self.updated_at), parse(&other.updated_at)) {
+            (Some(this), Some(other)) => this > other,
+            _ => false,
+        }
+    }
+}
+
+/// Deduplicated objects are stored once under their canonical key, so moving
+/// that copy to the recycle bin or replicating a pointer would lose the data.
+fn validate_deduplication(bucket: &Bucket) -> ServiceResult<()> {
+    if bucket.deduplication_enabled && bucket.recycle_bin_enabled {
+        return Err(ServiceError::InvalidRequest(
+            "Deduplication cannot be combined with the recycle bin".to_string(),
+        ));
+    }
+    if bucket.deduplication_enabled && bucket.replication.is_some() {
+        return Err(ServiceError::InvalidRequest(
+            "Deduplication cannot be combined with replication".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// The stored copy of some content and the keys pointing at it. Keys include
+/// their bucket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HashRecord {
+    pub canonical_key: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<String>,
+}
+
+fn default_recycle_bin_retention_days() -> u32 {
+    30
+}
+
+/// What the lifecycle job does with objects whose key starts with `prefix`.
+/// Objects older than `expiration_days` are deleted, through the recycle bin
+/// when it is enabled; younger ones are moved to `transition_to`. A rule needs
+/// at least one of the two.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleRule {
+    pub id: String,
+    /// Empty applies the rule to the whole bucket.
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiration_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transition_to: Option<StorageClass>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,28 +145,40 @@ struct Lock {
 #[derive(Debug, Clone)]
 struct BucketCache {
     buckets: HashMap<String, Bucket>,
-    last_refresh: DateTime<Utc>,
+    /// `None` until the first load.
+    last_refresh: Option<DateTime<Utc>>,
+    ttl: chrono::Duration,
 }
 
 impl BucketCache {
-    fn new() -> Self {
+    fn new(ttl_secs: u64) -> Self {
         Self {
             buckets: HashMap::new(),
-            last_refresh: Utc::now() - chrono::Duration::seconds(CACHE_TTL_SECONDS + 1),
+            last_refresh: None,
+            ttl: chrono::Duration::seconds(ttl_secs.min(i64::MAX as u64 / 1000) as i64),
         }
     }
 
     fn is_expired(&self) -> bool {
-        let age = Utc::now() - self.last_refresh;
-        age.num_seconds() > CACHE_TTL_SECONDS
+        match self.last_refresh {
+            Some(last_refresh) => Utc::now() - last_refresh > self.ttl,
+            None => true,
+        }
     }
 
+    /// Replaces the cached buckets with those loaded from the backend. A cached
+    /// copy updated after the loaded one, by an update that landed while the
+    /// backend was being read, is kept instead.
     fn update(&mut self, buckets: Vec<Bucket>) {
-        self.buckets.clear();
-        for bucket in buckets {
+        let mut cached = std::mem::take(&mut self.buckets);
+        for loaded in buckets {
+            let bucket = match cached.remove(&loaded.name) {
+                Some(cached) if cached.id == loaded.id && cached.updated_after(&loaded) => cached,
+                _ => loaded,
+            };
             self.buckets.insert(bucket.name.clone(), bucket);
         }
-        self.last_refresh = Utc::now();
+        self.last_refresh = Some(Utc::now());
     }
 
     fn get(&self, name: &str) -> Option<&Bucket> {
@@ -75,34 +198,253 @@ impl BucketCache {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetadataStoreConfig {
+    /// Number of independently locked pieces the bucket cache is split into, so
+    /// updates to one bucket don't hold up reads of the others.
+    #[serde(default = "default_shard_count")]
+    pub shard_count: usize,
+    /// How IDs are assigned to new buckets. Existing buckets keep the ID they
+    /// were stored with until [`MetadataStore::migrate_bucket_ids`] is run.
+    #[serde(default)]
+    pub id_strategy: BucketIdStrategy,
+    /// Seconds the bucket cache is trusted before listings and lookups by ID
+    /// reload it from the backend. Lookups by name that miss the cache always
+    /// check the backend.
+    #[serde(default = "default_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+    /// Lowercases bucket names before they are checked or looked up, so
+    /// `MyBucket` creates and finds the bucket `mybucket`. Off by default,
+    /// which rejects names with uppercase letters.
+    #[serde(default)]
+    pub normalize_bucket_names: bool,
+    /// Seconds the lock taken by [`MetadataStore::cleanup_expired_locks`]
+    /// lasts, so an instance that dies mid-sweep only blocks the others until
+    /// then.
+    #[serde(default = "default_cleanup_lock_ttl_seconds")]
+    pub cleanup_lock_ttl_seconds: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BucketIdStrategy {
+    /// A random UUID, e.g. `3f2c6a1e-8a4b-4f0e-9d53-6c1b2f7e9a10`.
+    #[default]
+    UuidV4,
+    /// The first `len` hex digits of the SHA-256 of the bucket name, so the
+    /// same name always gets the same ID. At most 64 digits are used.
+    Sha256Prefix(usize),
+    /// IDs from a function of the bucket name. Only available in code, not in
+    /// configuration files.
+    #[serde(skip)]
+    Custom(fn(&str) -> String),
+}
+
+impl BucketIdStrategy {
+    pub fn generate(&self, name: &str) -> String {
+        match self {
+            Self::UuidV4 => uuid::Uuid::new_v4().to_string(),
+            Self::Sha256Prefix(len) => {
+                use sha2::{Digest, Sha256};
+                let mut digest = hex::encode(Sha256::digest(name.as_bytes()));
+                digest.truncate(*len);
+                digest
+            }
+            Self::Custom(generate) => generate(name),
+        }
+    }
+}
+
+fn default_shard_count() -> usize {
+    16
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    DEFAULT_CACHE_TTL_SECS
+}
+
+fn default_cleanup_lock_ttl_seconds() -> i64 {
+    30
+}
+
+impl Default for MetadataStoreConfig {
+    fn default() -> Self {
+        Self {
+            shard_count: default_shard_count(),
+            id_strategy: BucketIdStrategy::default(),
+            cache_ttl_secs: default_cache_ttl_secs(),
+            normalize_bucket_names: false,
+            cleanup_lock_ttl_seconds: default_cleanup_lock_ttl_seconds(),
+        }
+    }
+}
+
+/// Bucket cache counters since the store was created, from
+/// [`MetadataStore::cache_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    /// Bucket lookups answered from the cache.
+    pub hit_count: u64,
+    /// Bucket lookups that had to go to the backend.
+    pub miss_count: u64,
+    /// Full reloads of the cache from the backend.
+    pub refresh_count: u64,
+}
+
+#[derive(Default)]
+struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    refreshes: AtomicU64,
+    /// Milliseconds since the Unix epoch.
+    last_refresh_at: AtomicI64,
+}
+
 pub struct MetadataStore {
     backend: Arc<dyn Backend>,
-    cache: Arc<RwLock<BucketCache>>,
+    /// Each bucket lives in the shard picked by the hash of its name. Code that
+    /// needs every shard locks them in index order, so it cannot deadlock.
+    shards: Vec<RwLock<BucketCache>>,
+    id_strategy: BucketIdStrategy,
+    counters: CacheCounters,
+    /// One permit, so concurrent requests that find the cache expired wait for
+    /// a single reload instead of each reading every bucket from the backend.
+    refresh_permit: Semaphore,
+    cache_ttl_secs: u64,
+    normalize_bucket_names: bool,
+    /// Object counts by bucket and listing prefix, trusted for the cache TTL
+    /// or until an object in the bucket changes.
+    object_counts: Mutex<HashMap<(String, String), (u64, Instant)>>,
+    /// Held by whoever reads and rewrites the content hash index.
+    hash_lock: tokio::sync::Mutex<()>,
+    /// Held while a lock record is checked and written, so callers in this
+    /// process can't both take the same lock. Other instances sharing the
+    /// backend aren't held back by it.
+    lock_records: tokio::sync::Mutex<()>,
+    cleanup_lock_ttl_seconds: i64,
+    /// Names this instance as the owner of the cleanup lock.
+    instance_id: String,
+    /// Bucket record writes in flight, finished or undone on the next start.
+    #[cfg(feature = "wal")]
+    wal: WriteAheadLog,
 }
 
 impl MetadataStore {
     pub async fn new(backend: Arc<dyn Backend>) -> ServiceResult<Self> {
+        Self::new_with_config(backend, MetadataStoreConfig::default()).await
+    }
+
+    pub async fn new_with_config(
+        backend: Arc<dyn Backend>,
+        config: MetadataStoreConfig,
+    ) -> ServiceResult<Self> {
         let store = Self {
+            #[cfg(feature = "wal")]
+            wal: WriteAheadLog::new(backend.clone()),
             backend,
-            cache: Arc::new(RwLock::new(BucketCache::new())),
+            shards: (0..config.shard_count.max(1))
+                .map(|_| RwLock::new(BucketCache::new(config.cache_ttl_secs)))
+                .collect(),
+            id_strategy: config.id_strategy,
+            counters: CacheCounters::default(),
+            refresh_permit: Semaphore::new(1),
+            cache_ttl_secs: config.cache_ttl_secs,
+            normalize_bucket_names: config.normalize_bucket_names,
+            object_counts: Mutex::new(HashMap::new()),
+            hash_lock: tokio::sync::Mutex::new(()),
+            lock_records: tokio::sync::Mutex::new(()),
+            cleanup_lock_ttl_seconds: config.cleanup_lock_ttl_seconds,
+            instance_id: format!("{}:{}", hostname(), std::process::id()),
         };
 
+        #[cfg(feature = "wal")]
+        store.recover_from_wal().await?;
         store.refresh_cache().await?;
 
-        info!("Initialized metadata store (folder-based with caching)");
+        info!(
+            "Initialized metadata store (folder-based with caching, {} shards, {}s TTL)",
+            store.shards.len(),
+            config.cache_ttl_secs
+        );
         Ok(store)
     }
 
-    fn bucket_key(name: &str) -> String {
-        format!("{}/{}.json", BUCKETS_PREFIX, name)
+    /// `name` as buckets are stored: lowercased if
+    /// [`MetadataStoreConfig::normalize_bucket_names`] is set, unchanged
+    /// otherwise.
+    pub fn normalize_bucket_name<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        if self.normalize_bucket_names && name.chars().any(char::is_uppercase) {
+            Cow::Owned(name.to_lowercase())
+        } else {
+            Cow::Borrowed(name)
+        }
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        CacheStats {
+            hit_count: self.counters.hits.load(Ordering::Relaxed),
+            miss_count: self.counters.misses.load(Ordering::Relaxed),
+            refresh_count: self.counters.refreshes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// When the cache was last reloaded from the backend.
+    pub fn last_refresh_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(self.counters.last_refresh_at.load(Ordering::Relaxed))
+            .unwrap_or_default()
+    }
+
+    /// How often a background task should call [`force_refresh`](Self::force_refresh)
+    /// so requests find the cache fresh: half the TTL, and at least a second.
+    pub fn background_refresh_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs((self.cache_ttl_secs / 2).max(1))
+    }
+
+    /// The number of objects in `bucket` under `prefix`, if it was counted
+    /// within the cache TTL and nothing in the bucket has changed since.
+    pub fn cached_object_count(&self, bucket: &str, prefix: &str) -> Option<u64> {
+        let counts = self.object_counts.lock().unwrap();
+        let (count, counted_at) = counts.get(&(bucket.to_string(), prefix.to_string()))?;
+        (counted_at.elapsed().as_secs() < self.cache_ttl_secs).then_some(*count)
+    }
+
+    pub fn cache_object_count(&self, bucket: &str, prefix: &str, count: u64) {
+        let mut counts = self.object_counts.lock().unwrap();
+        counts.retain(|_, (_, counted_at)| counted_at.elapsed().as_secs() < self.cache_ttl_secs);
+        counts.insert(
+            (bucket.to_string(), prefix.to_string()),
+            (count, Instant::now()),
+        );
+    }
+
+    /// Drops every cached count for `bucket`, after an object in it changed.
+    pub fn invalidate_object_counts(&self, bucket: &str) {
+        self.object_counts
+            .lock()
+            .unwrap()
+            .retain(|(counted, _), _| counted != bucket);
     }
 
-    fn generate_bucket_id(name: &str) -> String {
+    fn shard_index(&self, name: &str) -> usize {
         let mut hasher = DefaultHasher::new();
         name.hash(&mut hasher);
-        let hash = hasher.finish();
+        (hasher.finish() % self.shards.len() as u64) as usize
+    }
 
-        format!("bucket-{:016x}", hash)
+    fn shard(&self, name: &str) -> &RwLock<BucketCache> {
+        &self.shards[self.shard_index(name)]
+    }
+
+    async fn all_buckets(&self) -> Vec<Bucket> {
+        let mut buckets = Vec::new();
+        for shard in &self.shards {
+            buckets.extend(shard.read().await.all_buckets());
+        }
+        buckets
+    }
+
+    fn bucket_key(name: &str) -> String {
+        format!("{}/{}.json", BUCKETS_PREFIX, name)
     }
 
     async fn load_buckets_from_backend(&self) -> ServiceResult<Vec<Bucket>> {
@@ -122,7 +464,7 @@ impl MetadataStore {
                                 }
                             }
 
-                            match serde_json::from_slice::<Bucket>(&data) {
+                            match Bucket::from_json(&data) {
                                 Ok(bucket) => buckets.push(bucket),
                                 Err(e) => {
                                     warn!("Failed to parse bucket {}: {}", obj.key, e);
@@ -159,18 +501,72 @@ impl MetadataStore {
     }
 
     async fn refresh_cache(&self) -> ServiceResult<()> {
+        let _permit = self.acquire_refresh_permit().await?;
+        self.reload_cache().await
+    }
+
+    /// Reloads the cache unless another reload finished after `started`, a
+    /// timestamp in milliseconds taken before the caller missed. Misses that
+    /// queue behind one reload then share it instead of each running their own.
+    async fn refresh_cache_since(&self, started: i64) -> ServiceResult<()> {
+        let _permit = self.acquire_refresh_permit().await?;
+        if self.counters.last_refresh_at.load(Ordering::Relaxed) > started {
+            return Ok(());
+        }
+        self.reload_cache().await
+    }
+
+    async fn acquire_refresh_permit(&self) -> ServiceResult<tokio::sync::SemaphorePermit<'_>> {
+        self.refresh_permit
+            .acquire()
+            .await
+            .map_err(|e| ServiceError::Internal(format!("Cache refresh unavailable: {}", e)))
+    }
+
+    /// Callers hold the refresh permit.
+    async fn reload_cache(&self) -> ServiceResult<()> {
         let buckets = self.load_buckets_from_backend().await?;
-        let mut cache = self.cache.write().await;
-        cache.update(buckets);
-        debug!("Refreshed bucket cache ({} buckets)", cache.buckets.len());
+        let count = buckets.len();
+
+        let mut partitions: Vec<Vec<Bucket>> = self.shards.iter().map(|_| Vec::new()).collect();
+        for bucket in buckets {
+            let index = self.shard_index(&bucket.name);
+            partitions[index].push(bucket);
+        }
+
+        // All shards at once, so no reader sees a mix of old and new shards
+        let mut guards = Vec::with_capacity(self.shards.len());
+        for shard in &self.shards {
+            guards.push(shard.write().await);
+        }
+        for (cache, buckets) in guards.iter_mut().zip(partitions) {
+            cache.update(buckets);
+        }
+        self.counters.refreshes.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .last_refresh_at
+            .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+        debug!("Refreshed bucket cache ({} buckets)", count);
         Ok(())
     }
 
+    async fn cache_expired(&self) -> bool {
+        for shard in &self.shards {
+            if shard.read().await.is_expired() {
+                return true;
+            }
+        }
+        false
+    }
+
     async fn ensure_cache_fresh(&self) -> ServiceResult<()> {
-        let cache = self.cache.read().await;
-        if cache.is_expired() {
-            drop(cache); // Release read lock
-            self.refresh_cache().await?;
+        if !self.cache_expired().await {
+            return Ok(());
+        }
+        let _permit = self.acquire_refresh_permit().await?;
+        // Whoever held the permit before may have just reloaded it
+        if self.cache_expired().await {
+            self.reload_cache().await?;
         }
         Ok(())
     }
@@ -186,8 +582,7 @@ impl MetadataStore {
                     data.extend_from_slice(&chunk);
                 }
 
-                let bucket: Bucket = serde_json::from_slice(&data)?;
-                Ok(Some(bucket))
+                Ok(Some(Bucket::from_json(&data)?))
             }
             Err(BackendError::NotFound(_)) => Ok(None),
             Err(e) => Err(ServiceError::Backend(e)),
@@ -221,6 +616,22 @@ impl MetadataStore {
     }
 
     pub async fn create_bucket(&self, name: &str) -> ServiceResult<Bucket> {
+        self.create_bucket_with(name, |_| {}).await
+    }
+
+    /// Creates a bucket, letting the caller set optional bucket settings before
+    /// the record is first persisted.
+    pub async fn create_bucket_with(
+        &self,
+        name: &str,
+        configure: impl FnOnce(&mut Bucket),
+    ) -> ServiceResult<Bucket> {
+        let original = name;
+        let name = self.normalize_bucket_name(name);
+        let name = name.as_ref();
+        if name != original {
+            warn!("BucketNameNormalized: {} is created as {}", original, name);
+        }
         if !is_valid_bucket_name(name) {
             return Err(ServiceError::InvalidBucketName(format!(
                 "Invalid bucket name: {}",
@@ -228,21 +639,35 @@ impl MetadataStore {
             )));
         }
 
-        let bucket = Bucket {
-            id: Self::generate_bucket_id(name),
+        let created_at = Utc::now().to_rfc3339();
+        let mut bucket = Bucket {
+            id: self.id_strategy.generate(name),
             name: name.to_string(),
-            created_at: Utc::now().to_rfc3339(),
+            created_at: created_at.clone(),
+            updated_at: created_at,
+            description: None,
+            tags: HashMap::new(),
+            max_object_bytes: None,
+            recycle_bin_enabled: false,
+            recycle_bin_retention_days: default_recycle_bin_retention_days(),
+            replication: None,
+            lifecycle_rules: Vec::new(),
+            deduplication_enabled: false,
+            pipeline: PipelineConfig::default(),
         };
+        configure(&mut bucket);
+        validate_deduplication(&bucket)?;
 
+        let shard = self.shard(name);
         {
-            let cache = self.cache.read().await;
+            let cache = shard.read().await;
             if cache.get(name).is_some() {
                 return Err(ServiceError::BucketAlreadyExists(name.to_string()));
             }
         }
 
         if let Some(_existing) = self.load_bucket_from_backend(name).await? {
-            let mut cache = self.cache.write().await;
+            let mut cache = shard.write().await;
             if let Some(existing) = self.load_bucket_from_backend(name).await? {
                 cache.insert(existing);
             }
@@ -250,10 +675,18 @@ impl MetadataStore {
             return Err(ServiceError::BucketAlreadyExists(name.to_string()));
         }
 
-        self.save_bucket(&bucket).await?;
+        #[cfg(feature = "wal")]
+        let wal_id = self
+            .wal
+            .begin(WalOp::CreateBucket, name, Some(&bucket))
+            .await?;
+        let saved = self.save_bucket(&bucket).await;
+        #[cfg(feature = "wal")]
+        self.wal.finish(&wal_id, name, &saved).await?;
+        saved?;
 
         {
-            let mut cache = self.cache.write().await;
+            let mut cache = shard.write().await;
             cache.insert(bucket.clone());
         }
 
@@ -262,56 +695,66 @@ impl MetadataStore {
     }
 
     pub async fn get_bucket(&self, name: &str) -> ServiceResult<Bucket> {
+        let name = self.normalize_bucket_name(name);
+        let name = name.as_ref();
+        let shard = self.shard(name);
+
         // Try cache first
         {
-            let cache = self.cache.read().await;
+            let cache = shard.read().await;
             if let Some(bucket) = cache.get(name) {
+                self.counters.hits.fetch_add(1, Ordering::Relaxed);
                 return Ok(bucket.clone());
             }
         }
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
+        let missed_at = Utc::now().timestamp_millis();
 
         // Not in cache - try direct backend lookup
         debug!("Bucket {} not in cache, checking backend", name);
         if let Some(bucket) = self.load_bucket_from_backend(name).await? {
             // Update cache with discovered bucket
-            let mut cache = self.cache.write().await;
+            let mut cache = shard.write().await;
             cache.insert(bucket.clone());
             return Ok(bucket);
         }
 
         // Still not found - refresh entire cache and try again
         debug!("Bucket {} not found, refreshing cache", name);
-        self.refresh_cache().await?;
+        self.refresh_cache_since(missed_at).await?;
 
-        let cache = self.cache.read().await;
+        let cache = shard.read().await;
         cache
             .get(name)
             .cloned()
             .ok_or_else(|| ServiceError::BucketNotFound(name.to_string()))
     }
 
+    /// The bucket if it is cached, without going to the backend. A bucket that
+    /// [`get_bucket`](Self::get_bucket) has resolved is cached.
+    pub async fn cached_bucket(&self, name: &str) -> Option<Bucket> {
+        let name = self.normalize_bucket_name(name);
+        let name = name.as_ref();
+        self.shard(name).read().await.get(name).cloned()
+    }
+
     pub async fn get_bucket_by_id(&self, id: &str) -> ServiceResult<Bucket> {
         // Ensure cache is fresh
         self.ensure_cache_fresh().await?;
+        let started = Utc::now().timestamp_millis();
 
         // Search cache for bucket by ID
-        {
-            let cache = self.cache.read().await;
-            for bucket in cache.all_buckets() {
-                if bucket.id == id {
-                    return Ok(bucket);
-                }
-            }
+        if let Some(bucket) = self.all_buckets().await.into_iter().find(|b| b.id == id) {
+            self.counters.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(bucket);
         }
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
 
         // Not found in cache - refresh and try again
-        self.refresh_cache().await?;
+        self.refresh_cache_since(started).await?;
 
-        let cache = self.cache.read().await;
-        for bucket in cache.all_buckets() {
-            if bucket.id == id {
-                return Ok(bucket);
-            }
+        if let Some(bucket) = self.all_buckets().await.into_iter().find(|b| b.id == id) {
+            return Ok(bucket);
         }
 
         Err(ServiceError::BucketNotFound(format!("id: {}", id)))
@@ -320,26 +763,211 @@ impl MetadataStore {
     pub async fn list_buckets(&self) -> ServiceResult<Vec<Bucket>> {
         self.ensure_cache_fresh().await?;
 
-        let cache = self.cache.read().await;
-        let mut buckets = cache.all_buckets();
+        // Shards are read one at a time, in index order
+        let mut buckets = self.all_buckets().await;
         buckets.sort_by(|a, b| b.created_at.cmp(&a.created_at));
         Ok(buckets)
     }
 
+    /// Applies `update` to the stored bucket record and persists the result.
+    pub async fn update_bucket(
+        &self,
+        name: &str,
+        update: impl FnOnce(&mut Bucket),
+    ) -> ServiceResult<Bucket> {
+        let name = self.normalize_bucket_name(name);
+        let name = name.as_ref();
+        let mut bucket = self
+            .load_bucket_from_backend(name)
+            .await?
+            .ok_or_else(|| ServiceError::BucketNotFound(name.to_string()))?;
+
+        let deduplicated = bucket.deduplication_enabled;
+        update(&mut bucket);
+        // Pointers already stored would no longer be looked after
+        if deduplicated && !bucket.deduplication_enabled {
+            return Err(ServiceError::InvalidRequest(format!(
+                "Deduplication can't be turned off for bucket {}",
+                name
+            )));
+        }
+        validate_deduplication(&bucket)?;
+        bucket.updated_at = Utc::now().to_rfc3339();
+        #[cfg(feature = "wal")]
+        let wal_id = self
+            .wal
+            .begin(WalOp::UpdateBucket, name, Some(&bucket))
+            .await?;
+        let saved = self.save_bucket(&bucket).await;
+        #[cfg(feature = "wal")]
+        self.wal.finish(&wal_id, name, &saved).await?;
+        saved?;
+
+        {
+            let mut cache = self.shard(name).write().await;
+            cache.insert(bucket.clone());
+        }
+
+        info!("Bucket updated: {}", name);
+        Ok(bucket)
+    }
+
+    /// Replaces the bucket's description, if given, and merges `tags` into its
+    /// existing tags. An empty description clears it and a `None` tag value
+    /// removes that tag.
+    pub async fn update_bucket_metadata(
+        &self,
+        name: &str,
+        description: Option<String>,
+        tags: HashMap<String, Option<String>>,
+    ) -> ServiceResult<Bucket> {
+        self.update_bucket(name, |bucket| {
+            if let Some(description) = description {
+                bucket.description = (!description.is_empty()).then_some(description);
+            }
+            for (key, value) in tags {
+                match value {
+                    Some(value) => bucket.tags.insert(key, value),
+                    None => bucket.tags.remove(&key),
+                };
+            }
+        })
+        .await
+    }
+
     pub async fn delete_bucket(&self, name: &str) -> ServiceResult<()> {
+        let name = self.normalize_bucket_name(name);
+        let name = name.as_ref();
         self.get_bucket(name).await?;
 
-        self.delete_bucket_object(name).await?;
+        #[cfg(feature = "wal")]
+        let wal_id = self.wal.begin(WalOp::DeleteBucket, name, None).await?;
+        let deleted = self.delete_bucket_object(name).await;
+        #[cfg(feature = "wal")]
+        if deleted.is_err() {
+            self.wal.abort(&wal_id, name).await?;
+        }
+        deleted?;
 
         {
-            let mut cache = self.cache.write().await;
+            let mut cache = self.shard(name).write().await;
             cache.remove(name);
         }
 
+        // The bucket is gone either way; leftovers only cost storage and would
+        // be swept up by deleting the bucket again.
+        if let Err(e) = self.purge_bucket_metadata(name).await {
+            warn!("Failed to purge metadata of deleted bucket {}: {}", name, e);
+        }
+        #[cfg(feature = "wal")]
+        self.wal.commit(&wal_id, name).await?;
+
         info!("Bucket deleted: {}", name);
         Ok(())
     }
 
+    /// Deletes everything filed under the bucket in any `.metadata/<kind>/`
+    /// namespace: `<name>`, `<name>.<ext>` and anything below `<name>/`, such
+    /// as its definition, locks and replication markers. Returns how many
+    /// objects were removed.
+    pub async fn purge_bucket_metadata(&self, name: &str) -> ServiceResult<usize> {
+        let name = self.normalize_bucket_name(name);
+        let name = name.as_ref();
+        let objects = match self.backend.list_objects(Some(METADATA_PREFIX), None).await {
+            Ok(objects) => objects,
+            Err(BackendError::NotFound(_)) => return Ok(0),
+            Err(e) => return Err(ServiceError::Backend(e)),
+        };
+
+        let mut purged = 0;
+        for obj in objects {
+            if !is_bucket_metadata_key(&obj.key, name) {
+                continue;
+            }
+            // `pending.jsonl` looks like the file of a bucket named "pending"
+            #[cfg(feature = "wal")]
+            if obj.key == WAL_KEY {
+                continue;
+            }
+            match self.backend.delete_object(&obj.key).await {
+                Ok(()) | Err(BackendError::NotFound(_)) => purged += 1,
+                Err(e) => return Err(ServiceError::Backend(e)),
+            }
+        }
+
+        if purged > 0 {
+            debug!("Purged {} metadata objects of bucket {}", purged, name);
+        }
+        Ok(purged)
+    }
+
+    /// Gives every stored bucket a new ID from `strategy` and returns how many
+    /// were rewritten. New buckets still use the configured strategy.
+    pub async fn migrate_bucket_ids(&self, strategy: BucketIdStrategy) -> ServiceResult<usize> {
+        let buckets = self.load_buckets_from_backend().await?;
+        let mut migrated = 0;
+        for mut bucket in buckets {
+            let id = strategy.generate(&bucket.name);
+            if id == bucket.id {
+                continue;
+            }
+
+            info!(
+                "Migrating bucket {} id: {} -> {}",
+                bucket.name, bucket.id, id
+            );
+            bucket.id = id;
+            #[cfg(feature = "wal")]
+            let wal_id = self
+                .wal
+                .begin(WalOp::UpdateBucket, &bucket.name, Some(&bucket))
+                .await?;
+            let saved = self.save_bucket(&bucket).await;
+            #[cfg(feature = "wal")]
+            self.wal.finish(&wal_id, &bucket.name, &saved).await?;
+            saved?;
+            self.shard(&bucket.name).write().await.insert(bucket);
+            migrated += 1;
+        }
+        Ok(migrated)
+    }
+
+    /// Settles every bucket write the write-ahead log shows as begun but not
+    /// committed, i.e. cut short by a crash. The client never saw such a write
+    /// succeed, so a create is undone, while updates and deletes are run again:
+    /// the log holds the full updated record, and finishing a delete is safer
+    /// than leaving a bucket with half its metadata purged.
+    #[cfg(feature = "wal")]
+    async fn recover_from_wal(&self) -> ServiceResult<()> {
+        let entries = self.wal.uncommitted().await?;
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        for entry in &entries {
+            warn!(
+                "Recovering interrupted {:?} of bucket {} from {}",
+                entry.op, entry.bucket_name, entry.timestamp
+            );
+            let key = Self::bucket_key(&entry.bucket_name);
+            match (entry.op, &entry.bucket) {
+                (WalOp::CreateBucket, _) => self.backend.delete_object_if_exists(&key).await?,
+                (WalOp::UpdateBucket, Some(bucket)) => self.save_bucket(bucket).await?,
+                (WalOp::DeleteBucket, _) => {
+                    self.backend.delete_object_if_exists(&key).await?;
+                    self.purge_bucket_metadata(&entry.bucket_name).await?;
+                }
+                (WalOp::UpdateBucket, None) | (WalOp::Committed | WalOp::Aborted, _) => {}
+            }
+        }
+
+        self.wal.clear().await?;
+        info!("Recovered {} interrupted bucket writes", entries.len());
+        Ok(())
+    }
+
+    /// Reloads the cache now, whether or not it has expired. Waits for a reload
+    /// that is already running rather than starting a second one alongside it.
     pub async fn force_refresh(&self) -> ServiceResult<()> {
         self.refresh_cache().await
     }
@@ -351,6 +979,7 @@ impl MetadataStore {
         ttl_seconds: i64,
     ) -> ServiceResult<bool> {
         let lock_key = format!("{}/{}", LOCKS_PREFIX, resource);
+        let _guard = self.lock_records.lock().await;
         let now = Utc::now();
         let expires_at = now + chrono::Duration::seconds(ttl_seconds);
 
@@ -436,7 +1065,32 @@ impl MetadataStore {
         Ok(())
     }
 
+    /// Deletes every lock that has expired and returns how many. Runs under a
+    /// lock of its own, and if another holder has it, this round is skipped and
+    /// returns 0. Only sweeps within this process are strictly serialized:
+    /// [`try_acquire_lock`](Self::try_acquire_lock) reads the lock record and
+    /// then writes it, and backends have no conditional write, so two instances
+    /// sharing a backend can both take the lock if they check it at the same
+    /// moment.
     pub async fn cleanup_expired_locks(&self) -> ServiceResult<u64> {
+        if !self
+            .try_acquire_lock(
+                CLEANUP_LOCK,
+                &self.instance_id,
+                self.cleanup_lock_ttl_seconds,
+            )
+            .await?
+        {
+            debug!("Lock cleanup is running elsewhere, skipping");
+            return Ok(0);
+        }
+
+        let result = self.delete_expired_locks().await;
+        self.release_lock(CLEANUP_LOCK, &self.instance_id).await?;
+        result
+    }
+
+    async fn delete_expired_locks(&self) -> ServiceResult<u64> {
         let now = Utc::now();
         let mut cleaned = 0u64;
 
@@ -477,6 +1131,237 @@ impl MetadataStore {
 
         Ok(cleaned)
     }
+
+    fn hash_record_key(sha256: &str) -> String {
+        format!("{}/{}.json", HASHES_PREFIX, sha256)
+    }
+
+    fn content_key_key(key: &str) -> String {
+        format!("{}/{}", CONTENT_KEYS_PREFIX, key)
+    }
+
+    /// Serializes changes to the content hash index. Callers hold the guard
+    /// from reading a record until the objects it describes are written.
+    pub async fn lock_hashes(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.hash_lock.lock().await
+    }
+
+    /// The key holding the stored copy of content with this SHA-256, if any.
+    pub async fn find_by_hash(&self, sha256: &str) -> ServiceResult<Option<String>> {
+        Ok(self
+            .hash_record(sha256)
+            .await?
+            .map(|record| record.canonical_key))
+    }
+
+    pub async fn hash_record(&self, sha256: &str) -> ServiceResult<Option<HashRecord>> {
+        self.read_json(&Self::hash_record_key(sha256)).await
+    }
+
+    pub async fn save_hash_record(&self, sha256: &str, record: &HashRecord) -> ServiceResult<()> {
+        self.write_json(&Self::hash_record_key(sha256), record)
+            .await
+    }
+
+    pub async fn remove_hash_record(&self, sha256: &str) -> ServiceResult<()> {
+        match self
+            .backend
+            .delete_object(&Self::hash_record_key(sha256))
+            .await
+        {
+            Ok(()) | Err(BackendError::NotFound(_)) => Ok(()),
+            Err(e) => Err(ServiceError::Backend(e)),
+        }
+    }
+
+    /// Records `canonical_key` as the stored copy of content with this SHA-256.
+    pub async fn record_hash(&self, sha256: &str, canonical_key: &str) -> ServiceResult<()> {
+        let record = HashRecord {
+            canonical_key: canonical_key.to_string(),
+            references: Vec::new(),
+        };
+        self.save_hash_record(sha256, &record).await?;
+        self.write_json(&Self::content_key_key(canonical_key), &sha256)
+            .await
+    }
+
+    /// Records that `key` points at the stored copy of content with this SHA-256.
+    pub async fn add_hash_reference(&self, sha256: &str, key: &str) -> ServiceResult<()> {
+        let mut record = self.hash_record(sha256).await?.ok_or_else(|| {
+            ServiceError::Internal(format!("No stored copy for content hash {}", sha256))
+        })?;
+        if !record.references.iter().any(|r| r == key) {
+            record.references.push(key.to_string());
+        }
+        self.save_hash_record(sha256, &record).await?;
+        self.write_json(&Self::content_key_key(key), &sha256).await
+    }
+
+    /// The SHA-256 `key` was stored under, if it is in the index.
+    pub async fn content_hash_of(&self, key: &str) -> ServiceResult<Option<String>> {
+        self.read_json(&Self::content_key_key(key)).await
+    }
+
+    pub async fn forget_content_hash(&self, key: &str) -> ServiceResult<()> {
+        match self
+            .backend
+            .delete_object(&Self::content_key_key(key))
+            .await
+        {
+            Ok(()) | Err(BackendError::NotFound(_)) => Ok(()),
+            Err(e) => Err(ServiceError::Backend(e)),
+        }
+    }
+
+    fn api_key_key(hash: &str) -> String {
+        format!("{}/{}.json", API_KEYS_PREFIX, hash)
+    }
+
+    // Kept apart from the record so recording a use can never recreate a key
+    // that was revoked in the meantime
+    fn api_key_last_used_key(hash: &str) -> String {
+        format!("{}/{}.last-used", API_KEYS_PREFIX, hash)
+    }
+
+    async fn read_json<T: serde::de::DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> ServiceResult<Option<T>> {
+        match self.backend.get_object(key).await {
+            Ok(mut obj_data) => {
+                // Collect stream to bytes
+                let mut data = Vec::new();
+                while let Some(chunk) = obj_data.stream.next().await {
+                    let chunk = chunk.map_err(|e| ServiceError::Internal(e.to_string()))?;
+                    data.extend_from_slice(&chunk);
+                }
+                Ok(Some(serde_json::from_slice(&data)?))
+            }
+            Err(BackendError::NotFound(_)) => Ok(None),
+            Err(e) => Err(ServiceError::Backend(e)),
+        }
+    }
+
+    async fn write_json<T: Serialize>(&self, key: &str, value: &T) -> ServiceResult<()> {
+        let data = serde_json::to_vec(value)?;
+        let stream: object_store_backends::ByteStream =
+            Box::pin(futures::stream::once(async move { Ok(Bytes::from(data)) }));
+
+        self.backend
+            .put_object(
+                key,
+                stream,
+                Some("application/json".to_string()),
+                HashMap::new(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn save_api_key(&self, hash: &str, record: &ApiKeyRecord) -> ServiceResult<()> {
+        self.write_json(&Self::api_key_key(hash), record).await
+    }
+
+    pub async fn get_api_key(&self, hash: &str) -> ServiceResult<Option<ApiKeyRecord>> {
+        let Some(mut record) = self
+            .read_json::<ApiKeyRecord>(&Self::api_key_key(hash))
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        if let Some(last_used_at) = self
+            .read_json::<DateTime<Utc>>(&Self::api_key_last_used_key(hash))
+            .await?
+        {
+            record.last_used_at = Some(last_used_at);
+        }
+        Ok(Some(record))
+    }
+
+    pub async fn touch_api_key(&self, hash: &str, at: DateTime<Utc>) -> ServiceResult<()> {
+        self.write_json(&Self::api_key_last_used_key(hash), &at)
+            .await
+    }
+
+    /// All API keys, as `(hash, record)` pairs sorted by creation time.
+    pub async fn list_api_keys(&self) -> ServiceResult<Vec<(String, ApiKeyRecord)>> {
+        let objects = match self.backend.list_objects(Some(API_KEYS_PREFIX), None).await {
+            Ok(objects) => objects,
+            Err(BackendError::NotFound(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(ServiceError::Backend(e)),
+        };
+
+        let mut keys = Vec::new();
+        for obj in objects {
+            let Some(hash) = obj
+                .key
+                .strip_prefix(API_KEYS_PREFIX)
+                .and_then(|rest| rest.strip_prefix('/'))
+                .and_then(|name| name.strip_suffix(".json"))
+            else {
+                continue;
+            };
+            if let Some(record) = self.get_api_key(hash).await? {
+                keys.push((hash.to_string(), record));
+            }
+        }
+
+        keys.sort_by_key(|(_, record)| record.created_at);
+        Ok(keys)
+    }
+
+    /// Deletes an API key. Returns `false` if there was no such key.
+    pub async fn delete_api_key(&self, hash: &str) -> ServiceResult<bool> {
+        let key = Self::api_key_key(hash);
+        // Deleting a missing object succeeds on some backends
+        if !self.backend.object_exists(&key).await? {
+            return Ok(false);
+        }
+        match self.backend.delete_object(&key).await {
+            Ok(()) => {}
+            Err(BackendError::NotFound(_)) => return Ok(false),
+            Err(e) => return Err(ServiceError::Backend(e)),
+        }
+        match self
+            .backend
+            .delete_object(&Self::api_key_last_used_key(hash))
+            .await
+        {
+            Ok(()) | Err(BackendError::NotFound(_)) => Ok(true),
+            Err(e) => Err(ServiceError::Backend(e)),
+        }
+    }
+}
+
+/// Whether `key` is `.metadata/<kind>/<name>`, `.metadata/<kind>/<name>.<ext>`
+/// or below `.metadata/<kind>/<name>/`. Bucket names contain no dots, so a
+/// bucket never matches another one's `<name>.<ext>` file.
+fn is_bucket_metadata_key(key: &str, name: &str) -> bool {
+    let Some(rest) = key
+        .strip_prefix(METADATA_PREFIX)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .and_then(|rest| rest.split_once('/'))
+        .and_then(|(_, rest)| rest.strip_prefix(name))
+    else {
+        return false;
+    };
+
+    match rest.chars().next() {
+        None | Some('/') => true,
+        Some('.') => !rest[1..].contains(['.', '/']),
+        Some(_) => false,
+    }
+}
+
+/// The machine's host name, for naming this instance as a lock owner.
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
 }
 
 fn is_valid_bucket_name(name: &str) -> bool {
@@ -521,16 +1406,496 @@ mod tests {
         assert!(!is_valid_bucket_name("bucket_name")); // Underscore
     }
 
+    #[test]
+    fn test_bucket_metadata_keys() {
+        assert!(is_bucket_metadata_key(
+            ".metadata/buckets/logs.json",
+            "logs"
+        ));
+        assert!(is_bucket_metadata_key(".metadata/locks/logs", "logs"));
+        assert!(is_bucket_metadata_key(
+            ".metadata/replication-pending/logs/a/b.txt",
+            "logs"
+        ));
+
+        assert!(!is_bucket_metadata_key(
+            ".metadata/buckets/logs-archive.json",
+            "logs"
+        ));
+        assert!(!is_bucket_metadata_key(
+            ".metadata/locks/logs.a.json",
+            "logs"
+        ));
+        assert!(!is_bucket_metadata_key(".metadata/logs", "logs"));
+        assert!(!is_bucket_metadata_key("logs/.metadata/locks/logs", "logs"));
+    }
+
     #[test]
     fn test_deterministic_bucket_id() {
-        let id1 = MetadataStore::generate_bucket_id("my-bucket");
-        let id2 = MetadataStore::generate_bucket_id("my-bucket");
+        let strategy = BucketIdStrategy::Sha256Prefix(16);
+        let id1 = strategy.generate("my-bucket");
+        let id2 = strategy.generate("my-bucket");
         assert_eq!(id1, id2, "Same bucket name should generate same ID");
+        assert_eq!(id1.len(), 16);
+        assert!(id1.chars().all(|c| c.is_ascii_hexdigit()));
 
-        let id3 = MetadataStore::generate_bucket_id("other-bucket");
+        let id3 = strategy.generate("other-bucket");
         assert_ne!(
             id1, id3,
             "Different bucket names should generate different IDs"
         );
     }
+
+    #[test]
+    fn test_uuid_bucket_ids() {
+        let id1 = BucketIdStrategy::UuidV4.generate("my-bucket");
+        let id2 = BucketIdStrategy::UuidV4.generate("my-bucket");
+        assert_ne!(id1, id2);
+        assert_eq!(
+            uuid::Uuid::parse_str(&id1).unwrap().get_version(),
+            Some(uuid::Version::Random)
+        );
+    }
+
+    #[test]
+    fn test_cache_keeps_newer_cached_bucket() {
+        let bucket = |description: &str, updated_at: &str| {
+            Bucket::from_json(
+                serde_json::json!({
+                    "id": "id-1",
+                    "name": "logs",
+                    "created_at": "2024-01-01T00:00:00+00:00",
+                    "updated_at": updated_at,
+                    "description": description,
+                })
+                .to_string()
+                .as_bytes(),
+            )
+            .unwrap()
+        };
+
+        let mut cache = BucketCache::new(DEFAULT_CACHE_TTL_SECS);
+        cache.update(vec![bucket("patched", "2024-01-03T00:00:00+00:00")]);
+
+        // A load that read the record before the patch landed
+        cache.update(vec![bucket("stale", "2024-01-02T00:00:00+00:00")]);
+        assert_eq!(
+            cache.get("logs").unwrap().description.as_deref(),
+            Some("patched")
+        );
+
+        cache.update(vec![bucket("newer", "2024-01-04T00:00:00+00:00")]);
+        assert_eq!(
+            cache.get("logs").unwrap().description.as_deref(),
+            Some("newer")
+        );
+
+        cache.update(Vec::new());
+        assert!(cache.get("logs").is_none());
+    }
+
+    #[test]
+    fn test_bucket_without_updated_at_reads_created_at() {
+        let bucket = Bucket::from_json(
+            br#"{"id": "id-1", "name": "logs", "created_at": "2024-01-01T00:00:00+00:00"}"#,
+        )
+        .unwrap();
+        assert_eq!(bucket.updated_at, bucket.created_at);
+    }
+
+    #[test]
+    fn test_cache_expires_after_configured_ttl() {
+        let refreshed_ago = |ttl_secs: u64, millis: i64| {
+            let mut cache = BucketCache::new(ttl_secs);
+            assert!(cache.is_expired());
+            cache.update(Vec::new());
+            assert!(!cache.is_expired());
+            cache.last_refresh = Some(Utc::now() - chrono::Duration::milliseconds(millis));
+            cache.is_expired()
+        };
+
+        assert!(!refreshed_ago(1, 500));
+        assert!(refreshed_ago(1, 1_500));
+        assert!(!refreshed_ago(60, 1_500));
+        assert!(!refreshed_ago(60, 59_000));
+        assert!(refreshed_ago(60, 61_000));
+    }
+
+    #[tokio::test]
+    async fn test_cache_ttl_and_stats() {
+        use object_store_backends::memory::MemoryBackend;
+
+        let backend: Arc<dyn Backend> = Arc::new(MemoryBackend::new());
+        let store = |cache_ttl_secs| {
+            MetadataStore::new_with_config(
+                backend.clone(),
+                MetadataStoreConfig {
+                    cache_ttl_secs,
+                    ..Default::default()
+                },
+            )
+        };
+        let short = store(1).await.unwrap();
+        let long = store(60).await.unwrap();
+        assert_eq!(
+            short.cache_stats(),
+            CacheStats {
+                refresh_count: 1,
+                ..Default::default()
+            }
+        );
+
+        // Created through another store, so only a reload finds it in listings
+        let writer = store(60).await.unwrap();
+        writer.create_bucket("logs").await.unwrap();
+        assert!(short.list_buckets().await.unwrap().is_empty());
+        assert!(long.list_buckets().await.unwrap().is_empty());
+
+        tokio::time::sleep(std::time::Duration::from_millis(1_100)).await;
+        assert_eq!(short.list_buckets().await.unwrap().len(), 1);
+        assert!(long.list_buckets().await.unwrap().is_empty());
+        assert_eq!(short.cache_stats().refresh_count, 2);
+        assert_eq!(long.cache_stats().refresh_count, 1);
+
+        short.get_bucket("logs").await.unwrap();
+        assert!(long.get_bucket("missing").await.is_err());
+        long.get_bucket("logs").await.unwrap();
+        long.get_bucket("logs").await.unwrap();
+        assert_eq!(
+            short.cache_stats(),
+            CacheStats {
+                hit_count: 1,
+                miss_count: 0,
+                refresh_count: 2,
+            }
+        );
+        assert_eq!(
+            long.cache_stats(),
+            CacheStats {
+                hit_count: 2,
+                miss_count: 1,
+                refresh_count: 2,
+            }
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_expired_cache_is_reloaded_once() {
+        use object_store_backends::memory::MemoryBackend;
+
+        let store = Arc::new(
+            MetadataStore::new_with_config(
+                Arc::new(MemoryBackend::new()),
+                MetadataStoreConfig {
+                    cache_ttl_secs: 1,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap(),
+        );
+        let loaded_at = store.last_refresh_at();
+        assert!(Utc::now() - loaded_at < chrono::Duration::seconds(5));
+        assert_eq!(
+            store.background_refresh_interval(),
+            std::time::Duration::from_secs(1)
+        );
+
+        tokio::time::sleep(std::time::Duration::from_millis(1_100)).await;
+        let lookups: Vec<_> = (0..100)
+            .map(|_| {
+                let store = store.clone();
+                tokio::spawn(async move { store.list_buckets().await })
+            })
+            .collect();
+        for lookup in futures::future::join_all(lookups).await {
+            lookup.unwrap().unwrap();
+        }
+
+        assert_eq!(store.cache_stats().refresh_count, 2);
+        assert!(store.last_refresh_at() > loaded_at);
+
+        store.force_refresh().await.unwrap();
+        assert_eq!(store.cache_stats().refresh_count, 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_missed_lookups_share_a_reload() {
+        use object_store_backends::memory::MemoryBackend;
+
+        let store = Arc::new(
+            MetadataStore::new_with_config(
+                Arc::new(MemoryBackend::new()),
+                MetadataStoreConfig::default(),
+            )
+            .await
+            .unwrap(),
+        );
+        assert_eq!(store.cache_stats().refresh_count, 1);
+
+        // Queue the misses behind a reload that finishes after they started
+        let permit = store.acquire_refresh_permit().await.unwrap();
+        let lookups: Vec<_> = (0..20)
+            .map(|i| {
+                let store = store.clone();
+                tokio::spawn(async move { store.get_bucket(&format!("missing-{}", i)).await })
+            })
+            .collect();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        store.reload_cache().await.unwrap();
+        drop(permit);
+
+        for lookup in futures::future::join_all(lookups).await {
+            assert!(matches!(
+                lookup.unwrap(),
+                Err(ServiceError::BucketNotFound(_))
+            ));
+        }
+        assert_eq!(store.cache_stats().refresh_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_lock_cleanups_take_turns() {
+        use object_store_backends::memory::MemoryBackend;
+
+        let backend: Arc<dyn Backend> = Arc::new(MemoryBackend::new());
+        let store = MetadataStore::new(backend.clone()).await.unwrap();
+        let expire = |resource: &'static str| {
+            let now = Utc::now();
+            let lock = Lock {
+                resource: resource.to_string(),
+                owner: "gone".to_string(),
+                acquired_at: now - chrono::Duration::seconds(120),
+                expires_at: now - chrono::Duration::seconds(60),
+            };
+            let store = &store;
+            async move {
+                store
+                    .write_json(&format!("{}/{}", LOCKS_PREFIX, resource), &lock)
+                    .await
+                    .unwrap()
+            }
+        };
+        expire("a").await;
+        expire("b").await;
+
+        let (first, second) =
+            tokio::join!(store.cleanup_expired_locks(), store.cleanup_expired_locks());
+        let mut cleaned = [first.unwrap(), second.unwrap()];
+        cleaned.sort();
+        assert_eq!(cleaned, [0, 2]);
+        // Including the cleanup lock, which was released
+        assert!(backend
+            .list_objects(Some(LOCKS_PREFIX), None)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Another instance is sweeping, so this round is skipped
+        expire("c").await;
+        assert!(store
+            .try_acquire_lock(CLEANUP_LOCK, "other-host:1", 30)
+            .await
+            .unwrap());
+        assert_eq!(store.cleanup_expired_locks().await.unwrap(), 0);
+        assert!(backend
+            .object_exists(&format!("{}/c", LOCKS_PREFIX))
+            .await
+            .unwrap());
+
+        store
+            .release_lock(CLEANUP_LOCK, "other-host:1")
+            .await
+            .unwrap();
+        assert_eq!(store.cleanup_expired_locks().await.unwrap(), 1);
+    }
+
+    /// Fails writes and deletes of bucket records while `refuse` is set.
+    #[cfg(feature = "wal")]
+    struct RefusingRecords {
+        inner: object_store_backends::memory::MemoryBackend,
+        refuse: std::sync::atomic::AtomicBool,
+    }
+
+    #[cfg(feature = "wal")]
+    impl RefusingRecords {
+        fn check(&self, key: &str) -> object_store_backends::BackendResult<()> {
+            if key.starts_with(BUCKETS_PREFIX) && self.refuse.load(Ordering::SeqCst) {
+                return Err(BackendError::Provider("refused".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "wal")]
+    #[async_trait::async_trait]
+    impl Backend for RefusingRecords {
+        async fn init(&self) -> object_store_backends::BackendResult<()> {
+            self.inner.init().await
+        }
+
+        async fn put_object(
+            &self,
+            key: &str,
+            stream: object_store_backends::ByteStream,
+            content_type: Option<String>,
+            metadata: HashMap<String, String>,
+        ) -> object_store_backends::BackendResult<object_store_backends::ObjectMetadata> {
+            self.check(key)?;
+            self.inner
+                .put_object(key, stream, content_type, metadata)
+                .await
+        }
+
+        async fn get_object(
+            &self,
+            key: &str,
+        ) -> object_store_backends::BackendResult<object_store_backends::ObjectData> {
+            self.inner.get_object(key).await
+        }
+
+        async fn head_object(
+            &self,
+            key: &str,
+        ) -> object_store_backends::BackendResult<object_store_backends::ObjectMetadata> {
+            self.inner.head_object(key).await
+        }
+
+        async fn delete_object(&self, key: &str) -> object_store_backends::BackendResult<()> {
+            self.check(key)?;
+            self.inner.delete_object(key).await
+        }
+
+        async fn list_objects(
+            &self,
+            prefix: Option<&str>,
+            max_keys: Option<usize>,
+        ) -> object_store_backends::BackendResult<Vec<object_store_backends::ObjectMetadata>>
+        {
+            self.inner.list_objects(prefix, max_keys).await
+        }
+
+        async fn get_public_url(
+            &self,
+            key: &str,
+            expiration_secs: u64,
+            purpose: object_store_backends::PublicUrlPurpose,
+            content_type: Option<&str>,
+        ) -> object_store_backends::BackendResult<String> {
+            self.inner
+                .get_public_url(key, expiration_secs, purpose, content_type)
+                .await
+        }
+    }
+
+    #[cfg(feature = "wal")]
+    #[tokio::test]
+    async fn test_wal_ignores_failed_writes() {
+        let backend = Arc::new(RefusingRecords {
+            inner: object_store_backends::memory::MemoryBackend::new(),
+            refuse: std::sync::atomic::AtomicBool::new(false),
+        });
+        let store = MetadataStore::new(backend.clone()).await.unwrap();
+        store.create_bucket("kept").await.unwrap();
+
+        // Both failures are reported, so neither may be acted on later
+        backend.refuse.store(true, Ordering::SeqCst);
+        assert!(store.create_bucket("retried").await.is_err());
+        assert!(store.delete_bucket("kept").await.is_err());
+        assert!(store.wal.uncommitted().await.unwrap().is_empty());
+
+        backend.refuse.store(false, Ordering::SeqCst);
+        store.create_bucket("retried").await.unwrap();
+        drop(store);
+
+        let recovered = MetadataStore::new(backend.clone()).await.unwrap();
+        let mut names: Vec<_> = recovered
+            .list_buckets()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|b| b.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["kept", "retried"]);
+    }
+
+    #[cfg(feature = "wal")]
+    #[tokio::test]
+    async fn test_wal_recovers_interrupted_writes() {
+        use object_store_backends::memory::MemoryBackend;
+
+        let backend: Arc<dyn Backend> = Arc::new(MemoryBackend::new());
+        let store = MetadataStore::new(backend.clone()).await.unwrap();
+        store.create_bucket("kept").await.unwrap();
+        store.create_bucket("doomed").await.unwrap();
+        store
+            .write_json(".metadata/locks/doomed", &1)
+            .await
+            .unwrap();
+        assert!(!backend.object_exists(WAL_KEY).await.unwrap());
+
+        // Crash after the create's record was written but before its commit
+        let mut half_created = store
+            .load_bucket_from_backend("kept")
+            .await
+            .unwrap()
+            .unwrap();
+        half_created.name = "half-created".to_string();
+        store
+            .wal
+            .begin(WalOp::CreateBucket, "half-created", Some(&half_created))
+            .await
+            .unwrap();
+        store.save_bucket(&half_created).await.unwrap();
+
+        // Crash before an update reached the backend
+        let mut updated = store
+            .load_bucket_from_backend("kept")
+            .await
+            .unwrap()
+            .unwrap();
+        updated.description = Some("after the crash".to_string());
+        store
+            .wal
+            .begin(WalOp::UpdateBucket, "kept", Some(&updated))
+            .await
+            .unwrap();
+
+        // Crash after the record was deleted but before its locks were purged
+        store
+            .wal
+            .begin(WalOp::DeleteBucket, "doomed", None)
+            .await
+            .unwrap();
+        store.delete_bucket_object("doomed").await.unwrap();
+        drop(store);
+
+        let recovered = MetadataStore::new(backend.clone()).await.unwrap();
+        let names: Vec<_> = recovered
+            .list_buckets()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|b| b.name)
+            .collect();
+        assert_eq!(names, vec!["kept"]);
+        assert_eq!(
+            recovered.get_bucket("kept").await.unwrap().description,
+            Some("after the crash".to_string())
+        );
+        assert_eq!(
+            recovered.load_bucket_from_backend("kept").await.unwrap(),
+            Some(recovered.get_bucket("kept").await.unwrap())
+        );
+        assert!(!backend
+            .object_exists(".metadata/locks/doomed")
+            .await
+            .unwrap());
+        assert!(!backend.object_exists(WAL_KEY).await.unwrap());
+
+        // Writes that committed leave nothing behind to replay
+        recovered.delete_bucket("kept").await.unwrap();
+        assert!(recovered.wal.uncommitted().await.unwrap().is_empty());
+    }
 }
@@ -0,0 +1,188 @@
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+use crate::service::BucketStats;
+
+/// Most distinct `bucket` label values emitted; further buckets are reported
+/// under [`OVERFLOW_BUCKET_LABEL`] so series count stays bounded.
+pub const MAX_BUCKET_LABELS: usize = 1000;
+
+pub const OVERFLOW_BUCKET_LABEL: &str = "__overflow__";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    /// How often the background job recomputes per-bucket object counts and sizes.
+    #[serde(default = "default_stats_cache_ttl_seconds")]
+    pub stats_cache_ttl_seconds: u64,
+    /// Buckets that get their own `bucket` label. When unset, the first
+    /// `MAX_BUCKET_LABELS` buckets seen are labeled.
+    #[serde(default)]
+    pub metric_bucket_allowlist: Option<Vec<String>>,
+}
+
+fn default_stats_cache_ttl_seconds() -> u64 {
+    60
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            stats_cache_ttl_seconds: default_stats_cache_ttl_seconds(),
+            metric_bucket_allowlist: None,
+        }
+    }
+}
+
+/// The process-wide Prometheus recorder, installed on first use.
+pub fn prometheus_handle() -> &'static PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE.get_or_init(|| {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        if let Err(e) = metrics::set_global_recorder(recorder) {
+            tracing::warn!("Another metrics recorder is already installed: {}", e);
+        }
+        handle
+    })
+}
+
+/// Maps bucket names to the `bucket` label values used in metrics.
+pub struct ServiceMetrics {
+    allowlist: Option<HashSet<String>>,
+    labeled: Mutex<HashSet<String>>,
+}
+
+impl ServiceMetrics {
+    pub fn new(config: &MetricsConfig) -> Self {
+        // Install the recorder before anything is recorded
+        prometheus_handle();
+
+        Self {
+            allowlist: config
+                .metric_bucket_allowlist
+                .as_ref()
+                .map(|buckets| buckets.iter().cloned().collect()),
+            labeled: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn bucket_label(&self, bucket: &str) -> String {
+        if let Some(allowlist) = &self.allowlist {
+            return if allowlist.contains(bucket) {
+                bucket.to_string()
+            } else {
+                OVERFLOW_BUCKET_LABEL.to_string()
+            };
+        }
+
+        let mut labeled = self.labeled.lock().unwrap();
+        if labeled.contains(bucket) || labeled.len() < MAX_BUCKET_LABELS {
+            labeled.insert(bucket.to_string());
+            bucket.to_string()
+        } else {
+            OVERFLOW_BUCKET_LABEL.to_string()
+        }
+    }
+
+    /// Counts a request to `bucket`, or to a bucket that does not exist when it
+    /// is `None`. Those are reported under the overflow label so that requests
+    /// naming arbitrary buckets cannot use up the labels.
+    pub fn record_request(&self, bucket: Option<&str>, operation: &'static str, status: u16) {
+        let label = bucket.map_or_else(
+            || OVERFLOW_BUCKET_LABEL.to_string(),
+            |bucket| self.bucket_label(bucket),
+        );
+        metrics::counter!(
+            "object_store_requests_total",
+            "bucket" => label,
+            "operation" => operation,
+            "status" => status.to_string(),
+        )
+        .increment(1);
+    }
+
+    /// Counter for background replications of `bucket` that failed and were
+    /// queued for a retry.
+    pub fn replication_failures(&self, bucket: &str) -> metrics::Counter {
+        metrics::counter!(
+            "object_store_replication_failures_total",
+            "bucket" => self.bucket_label(bucket),
+        )
+    }
+
+    /// Counter for uploads to `bucket` that waited for another upload to the
+    /// same key to finish.
+    pub fn upload_collisions(&self, bucket: &str) -> metrics::Counter {
+        metrics::counter!(
+            "object_store_upload_collisions_total",
+            "bucket" => self.bucket_label(bucket),
+        )
+    }
+
+    /// Frees the label of a deleted bucket for another bucket and zeroes its
+    /// gauges, which the stats job no longer updates.
+    pub fn release_bucket(&self, bucket: &str) {
+        let labeled = match &self.allowlist {
+            Some(allowlist) => allowlist.contains(bucket),
+            None => self.labeled.lock().unwrap().remove(bucket),
+        };
+        if labeled {
+            metrics::gauge!("object_store_bucket_object_count", "bucket" => bucket.to_string())
+                .set(0.0);
+            metrics::gauge!("object_store_bucket_size_bytes", "bucket" => bucket.to_string())
+                .set(0.0);
+        }
+    }
+
+    /// Publishes per-bucket gauges. Buckets without their own label are summed
+    /// into the overflow series.
+    pub fn record_bucket_stats(&self, stats: &[(String, BucketStats)]) {
+        let mut by_label: HashMap<String, BucketStats> = HashMap::new();
+        for (bucket, bucket_stats) in stats {
+            let entry = by_label.entry(self.bucket_label(bucket)).or_default();
+            entry.object_count += bucket_stats.object_count;
+            entry.total_bytes += bucket_stats.total_bytes;
+        }
+
+        metrics::gauge!("object_store_buckets").set(stats.len() as f64);
+        for (label, bucket_stats) in by_label {
+            metrics::gauge!("object_store_bucket_object_count", "bucket" => label.clone())
+                .set(bucket_stats.object_count as f64);
+            metrics::gauge!("object_store_bucket_size_bytes", "bucket" => label)
+                .set(bucket_stats.total_bytes as f64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_labels_capped() {
+        let metrics = ServiceMetrics::new(&MetricsConfig::default());
+        for i in 0..MAX_BUCKET_LABELS {
+            assert_eq!(metrics.bucket_label(&format!("b{}", i)), format!("b{}", i));
+        }
+        assert_eq!(metrics.bucket_label("one-too-many"), OVERFLOW_BUCKET_LABEL);
+        // Buckets that already have a label keep it
+        assert_eq!(metrics.bucket_label("b0"), "b0");
+
+        // A deleted bucket's label goes to the next bucket
+        metrics.release_bucket("b1");
+        assert_eq!(metrics.bucket_label("one-too-many"), "one-too-many");
+        assert_eq!(metrics.bucket_label("b1"), OVERFLOW_BUCKET_LABEL);
+    }
+
+    #[test]
+    fn test_bucket_label_allowlist() {
+        let metrics = ServiceMetrics::new(&MetricsConfig {
+            metric_bucket_allowlist: Some(vec!["important".to_string()]),
+            ..Default::default()
+        });
+        assert_eq!(metrics.bucket_label("important"), "important");
+        assert_eq!(metrics.bucket_label("other"), OVERFLOW_BUCKET_LABEL);
+    }
+}
@@ -0,0 +1,292 @@
+use axum::body::Body;
+use axum::extract::{Host, MatchedPath, OriginalUri, RawPathParams, State};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, Request, Response, Uri};
+use axum::middleware::Next;
+use axum::response::IntoResponse;
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+use crate::auth::OperationType;
+use crate::error::ServiceError;
+use crate::revocation::{is_presigned, parse_url};
+use crate::service::ObjectStoreService;
+
+pub static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+pub static X_SERVER_VERSION: HeaderName = HeaderName::from_static("x-server-version");
+
+/// Longest client-supplied request ID that is reused instead of replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: String;
+}
+
+/// Correlation ID for a single HTTP request, available to handlers as
+/// `Extension<RequestId>` and echoed back in the `X-Request-Id` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// The ID of the request being handled on the current task, if any. Used
+    /// where the request itself isn't reachable, such as error responses.
+    pub fn current() -> Option<String> {
+        CURRENT_REQUEST_ID.try_with(|id| id.clone()).ok()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn from_request<B>(req: &Request<B>) -> Self {
+        // Reuse an ID set by a proxy or the caller so logs line up end to end
+        let incoming = req
+            .headers()
+            .get(&X_REQUEST_ID)
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| !v.is_empty() && v.len() <= MAX_REQUEST_ID_LEN);
+
+        match incoming {
+            Some(id) => Self(id.to_string()),
+            None => Self(uuid::Uuid::new_v4().to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RequestIdLayer;
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestIdService<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for RequestIdService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let request_id = RequestId::from_request(&req);
+        req.extensions_mut().insert(request_id.clone());
+
+        let id = request_id.0;
+        let future = CURRENT_REQUEST_ID.sync_scope(id.clone(), || self.inner.call(req));
+
+        Box::pin(CURRENT_REQUEST_ID.scope(id.clone(), async move {
+            let mut response = future.await?;
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                response.headers_mut().insert(X_REQUEST_ID.clone(), value);
+            }
+            Ok(response)
+        }))
+    }
+}
+
+/// Rewrites requests addressed to a bucket subdomain onto the path-style
+/// routes: `GET http://my-bucket.{domain}/a/b.txt` is handled as
+/// `GET /buckets/my-bucket/objects/a/b.txt`, and `/` lists the bucket. Requests
+/// to the bare domain, or to hosts outside it, pass through unchanged.
+pub async fn route_virtual_host(
+    State(domain): State<Arc<str>>,
+    Host(host): Host,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    if let Some(bucket) = virtual_host_bucket(&host, &domain) {
+        let path = request.uri().path().trim_start_matches('/');
+        let mut rewritten = if path.is_empty() {
+            format!("/buckets/{}/objects", bucket)
+        } else {
+            format!("/buckets/{}/objects/{}", bucket, path)
+        };
+        if let Some(query) = request.uri().query() {
+            rewritten.push('?');
+            rewritten.push_str(query);
+        }
+
+        if let Ok(uri) = rewritten.parse::<Uri>() {
+            *request.uri_mut() = uri;
+        }
+    }
+
+    next.run(request).await
+}
+
+fn virtual_host_bucket<'a>(host: &'a str, domain: &str) -> Option<&'a str> {
+    // IPv6 literals and IPv4 addresses never carry a bucket
+    if host.starts_with('[') {
+        return None;
+    }
+    let host = host.split(':').next().unwrap_or(host);
+    if host.parse::<std::net::Ipv4Addr>().is_ok() {
+        return None;
+    }
+    let bucket = host.strip_suffix(domain)?.strip_suffix('.')?;
+    (!bucket.is_empty() && !bucket.contains('.')).then_some(bucket)
+}
+
+/// Answers `410 Gone` to presigned URLs that point at this service and were
+/// revoked. The URL is checked as the client sent it, before any virtual-host
+/// rewrite; `X-Forwarded-Proto` gives the scheme behind a TLS-terminating proxy.
+pub async fn reject_revoked_urls(
+    State(service): State<Arc<ObjectStoreService>>,
+    host: Option<Host>,
+    OriginalUri(uri): OriginalUri,
+    request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    // Without a host there is no URL to compare against
+    let Some(Host(host)) = host else {
+        return next.run(request).await;
+    };
+    let scheme = request
+        .headers()
+        .get("x-forwarded-proto")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("http");
+    let path = uri.path_and_query().map_or("/", |p| p.as_str());
+    let url = format!("{}://{}{}", scheme, host, path);
+
+    if parse_url(&url).is_some_and(|parsed| is_presigned(&parsed)) {
+        match service.is_url_revoked(&url).await {
+            Ok(false) => {}
+            Ok(true) => {
+                return ServiceError::UrlRevoked("This URL has been revoked".to_string())
+                    .into_response()
+            }
+            Err(e) => return e.into_response(),
+        }
+    }
+
+    next.run(request).await
+}
+
+/// Rejects requests without a valid API key with 401 when `auth.require_api_key`
+/// is set, and keys without the permission the request's [`OperationType`]
+/// needs with 403. Must be installed with `route_layer` so the matched route is
+/// known.
+pub async fn require_api_key(
+    State(service): State<Arc<ObjectStoreService>>,
+    matched_path: MatchedPath,
+    params: RawPathParams,
+    request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    if !service.requires_api_key() {
+        return next.run(request).await;
+    }
+
+    let Some(key) = bearer_key(request.headers()) else {
+        return ServiceError::Unauthorized("Missing API key".to_string()).into_response();
+    };
+
+    let operation = OperationType::for_request(request.method(), matched_path.as_str());
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(param, _)| *param == name)
+            .map(|(_, value)| percent_encoding::percent_decode_str(value).decode_utf8_lossy())
+    };
+    let resource = match (param("bucket"), param("key")) {
+        (Some(bucket), Some(key)) => format!("{}/{}", bucket, key),
+        (Some(bucket), None) => bucket.to_string(),
+        _ => "*".to_string(),
+    };
+
+    match service.authenticate(key, operation, &resource).await {
+        Ok(_) => next.run(request).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+/// The API key in an `Authorization: Bearer` header, if there is one.
+pub(crate) fn bearer_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
+/// Stamps every response with the server's crate version.
+pub async fn add_server_version(request: Request<Body>, next: Next) -> Response<Body> {
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(
+        X_SERVER_VERSION.clone(),
+        HeaderValue::from_static(env!("CARGO_PKG_VERSION")),
+    );
+    response
+}
+
+/// Counts each bucket-scoped request in `object_store_requests_total`. Must be
+/// installed with `route_layer` so the matched route is known.
+pub async fn track_request_metrics(
+    State(service): State<Arc<ObjectStoreService>>,
+    matched_path: MatchedPath,
+    params: RawPathParams,
+    request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    let bucket = params
+        .iter()
+        .find(|(name, _)| *name == "bucket")
+        .map(|(_, value)| value.to_string());
+    let operation = operation_name(request.method(), matched_path.as_str());
+
+    let response = next.run(request).await;
+
+    if let Some(bucket) = bucket {
+        // Only buckets that exist get a label of their own. The handler has
+        // looked the bucket up, so a cached lookup is enough and requests for
+        // missing buckets don't go to the backend again.
+        let bucket = service.metadata().cached_bucket(&bucket).await;
+        service.metrics().record_request(
+            bucket.as_ref().map(|b| b.name.as_str()),
+            operation,
+            response.status().as_u16(),
+        );
+    }
+    response
+}
+
+fn operation_name(method: &Method, route: &str) -> &'static str {
+    match (method.as_str(), route) {
+        ("GET", "/buckets/:bucket") => "get_bucket",
+        ("DELETE", "/buckets/:bucket") => "delete_bucket",
+        ("PATCH", "/buckets/:bucket") => "update_bucket",
+        ("PUT", "/buckets/:bucket/objects/*key") => "put_object",
+        ("GET", "/buckets/:bucket/objects/*key") => "get_object",
+        ("HEAD", "/buckets/:bucket/objects/*key") => "head_object",
+        ("DELETE", "/buckets/:bucket/objects/*key") => "delete_object",
+        ("PUT", "/buckets/:bucket/upsert/*key") => "upsert_object",
+        ("GET", "/buckets/:bucket/objects") => "list_objects",
+        ("GET", "/buckets/:bucket/inventory") => "export_inventory",
+        ("GET", "/buckets/:bucket/replication-lag") => "get_replication_lag",
+        ("GET", "/buckets/:bucket/recyclebin") => "list_recycle_bin",
+        ("POST", "/buckets/:bucket/recyclebin/restore") => "restore_object",
+        ("DELETE", "/buckets/:bucket/recyclebin/:version") => "purge_recycle_bin_version",
+        ("GET", "/buckets/:bucket/object-info/*key") => "get_object_info",
+        ("GET", "/buckets/:bucket/public-url/*key") => "get_public_url",
+        ("POST", "/buckets/:bucket/revoke-url") => "revoke_url",
+        _ => "other",
+    }
+}
@@ -0,0 +1,251 @@
+#[cfg(feature = "hooks")]
+use reqwest::StatusCode;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "hooks")]
+use std::collections::HashMap;
+#[cfg(feature = "hooks")]
+use std::sync::Arc;
+#[cfg(feature = "hooks")]
+use std::time::Duration;
+#[cfg(feature = "hooks")]
+use tokio::sync::mpsc::UnboundedReceiver;
+#[cfg(feature = "hooks")]
+use tracing::{debug, warn};
+
+#[cfg(feature = "hooks")]
+use crate::error::{ServiceError, ServiceResult};
+#[cfg(feature = "hooks")]
+use crate::events::{EventType, ObjectEvent};
+#[cfg(feature = "hooks")]
+use crate::metadata::MetadataStore;
+
+/// Where upload hooks may send requests. Hook URLs come from whoever
+/// configures a bucket, so without this the server could be made to call any
+/// host it can reach.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HooksConfig {
+    /// Hosts hooks may call, as `host` for any port or `host:port`. Empty, the
+    /// default, allows none.
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+}
+
+impl HooksConfig {
+    /// Checks every hook of `pipeline`, describing the first one not allowed.
+    pub fn check(&self, pipeline: &PipelineConfig) -> Result<(), String> {
+        pipeline
+            .pre_upload
+            .iter()
+            .chain(&pipeline.post_upload)
+            .try_for_each(|hook| self.check_url(&hook.url))
+    }
+
+    pub fn check_url(&self, url: &str) -> Result<(), String> {
+        let parsed = Url::parse(url).map_err(|e| format!("Invalid hook URL {}: {}", url, e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!("Hook URL {} must use http or https", url));
+        }
+        let host = parsed.host_str().unwrap_or_default();
+        let with_port = format!("{}:{}", host, parsed.port_or_known_default().unwrap_or(0));
+        let allowed = self.allowed_hosts.iter().any(|entry| {
+            let entry = entry.to_ascii_lowercase();
+            entry == host || entry == with_port
+        });
+        if !allowed {
+            return Err(format!("Hook URL {} is not on an allowed host", url));
+        }
+        Ok(())
+    }
+}
+
+/// Webhooks called around uploads to a bucket.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PipelineConfig {
+    /// Called before an object is stored; any hook can reject the upload.
+    #[serde(default)]
+    pub pre_upload: Vec<HookConfig>,
+    /// Called after an object is stored. Failures are logged and ignored.
+    #[serde(default)]
+    pub post_upload: Vec<HookConfig>,
+}
+
+impl PipelineConfig {
+    pub fn is_empty(&self) -> bool {
+        self.pre_upload.is_empty() && self.post_upload.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookConfig {
+    pub url: String,
+    #[serde(default = "default_hook_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_hook_timeout_ms() -> u64 {
+    5000
+}
+
+#[cfg(feature = "hooks")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    PreUpload,
+    PostUpload,
+}
+
+/// JSON body posted to a hook. Hooks receive object metadata only, never the body.
+#[cfg(feature = "hooks")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookPayload {
+    pub event: HookEvent,
+    pub bucket: String,
+    pub key: String,
+    pub content_type: Option<String>,
+    /// Stored size and ETag; only set for post-upload events, since neither is
+    /// known until the body has been written.
+    pub size: Option<u64>,
+    pub etag: Option<String>,
+    pub metadata: HashMap<String, String>,
+}
+
+/// Invokes pipeline hooks with its own HTTP client, so hook timeouts and
+/// connection pools are independent of anything else the service calls.
+/// Hooks are checked against the allow-list again before each call, covering
+/// buckets configured before it changed, and redirects aren't followed.
+#[cfg(feature = "hooks")]
+#[derive(Clone)]
+pub struct PipelineRunner {
+    client: reqwest::Client,
+    hooks: HooksConfig,
+}
+
+#[cfg(feature = "hooks")]
+impl PipelineRunner {
+    pub fn new(hooks: &HooksConfig) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .unwrap_or_default(),
+            hooks: hooks.clone(),
+        }
+    }
+
+    /// Calls each pre-upload hook in order. A `403` rejects the upload; any
+    /// other failure rejects it too, so an unreachable scanner never lets
+    /// content through.
+    pub async fn run_pre_upload(
+        &self,
+        hooks: &[HookConfig],
+        payload: &HookPayload,
+    ) -> ServiceResult<()> {
+        for hook in hooks {
+            let response = self.invoke(hook, payload).await.map_err(|e| {
+                warn!("Pre-upload hook {} failed: {}", hook.url, e);
+                ServiceError::HookRejected(format!("pre-upload hook {} failed: {}", hook.url, e))
+            })?;
+
+            match response.status() {
+                status if status.is_success() => {
+                    debug!(
+                        "Pre-upload hook {} accepted {}/{}",
+                        hook.url, payload.bucket, payload.key
+                    );
+                }
+                StatusCode::FORBIDDEN => {
+                    let reason = response.text().await.unwrap_or_default();
+                    return Err(ServiceError::HookRejected(if reason.is_empty() {
+                        format!("upload rejected by {}", hook.url)
+                    } else {
+                        format!("upload rejected by {}: {}", hook.url, reason)
+                    }));
+                }
+                status => {
+                    warn!("Pre-upload hook {} returned {}", hook.url, status);
+                    return Err(ServiceError::HookRejected(format!(
+                        "pre-upload hook {} returned {}",
+                        hook.url, status
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Spawns the post-upload hooks without waiting for them.
+    pub fn run_post_upload(&self, hooks: &[HookConfig], payload: HookPayload) {
+        for hook in hooks.iter().cloned() {
+            let runner = self.clone();
+            let payload = payload.clone();
+            tokio::spawn(async move {
+                match runner.invoke(&hook, &payload).await {
+                    Ok(response) if response.status().is_success() => {}
+                    Ok(response) => warn!(
+                        "Post-upload hook {} returned {}",
+                        hook.url,
+                        response.status()
+                    ),
+                    Err(e) => warn!("Post-upload hook {} failed: {}", hook.url, e),
+                }
+            });
+        }
+    }
+
+    /// Calls the post-upload hooks of the bucket of every created object.
+    pub async fn run_post_upload_hooks(
+        self,
+        mut events: UnboundedReceiver<ObjectEvent>,
+        metadata: Arc<MetadataStore>,
+    ) {
+        while let Some(event) = events.recv().await {
+            if event.event_type != EventType::ObjectCreated {
+                continue;
+            }
+
+            let hooks = match metadata.get_bucket(&event.bucket).await {
+                Ok(bucket) => bucket.pipeline.post_upload,
+                Err(e) => {
+                    warn!(
+                        "Skipping post-upload hooks for {}/{}: {}",
+                        event.bucket, event.key, e
+                    );
+                    continue;
+                }
+            };
+            if hooks.is_empty() {
+                continue;
+            }
+
+            self.run_post_upload(
+                &hooks,
+                HookPayload {
+                    event: HookEvent::PostUpload,
+                    bucket: event.bucket,
+                    key: event.key,
+                    content_type: event.metadata.content_type,
+                    size: Some(event.metadata.size),
+                    etag: Some(event.metadata.etag),
+                    metadata: event.metadata.custom_metadata,
+                },
+            );
+        }
+    }
+
+    async fn invoke(
+        &self,
+        hook: &HookConfig,
+        payload: &HookPayload,
+    ) -> Result<reqwest::Response, String> {
+        self.hooks.check_url(&hook.url)?;
+        self.client
+            .post(&hook.url)
+            .timeout(Duration::from_millis(hook.timeout_ms))
+            .json(payload)
+            .send()
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
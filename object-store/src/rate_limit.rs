@@ -0,0 +1,217 @@
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::Request;
+use axum::response::{IntoResponse, Response};
+use futures::future::BoxFuture;
+use governor::clock::{Clock, DefaultClock};
+use governor::{DefaultDirectRateLimiter, Quota};
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+use crate::auth::hash_key;
+use crate::error::ServiceError;
+use crate::middleware::bearer_key;
+use crate::service::ObjectStoreService;
+
+/// Clients whose buckets are tracked at once. Past this the least recently
+/// seen is forgotten, which only gives it a full burst again.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Rate the bucket refills at. Zero is treated as one.
+    pub requests_per_second: u32,
+    /// Requests allowed back to back once the bucket is full. Zero is treated
+    /// as one.
+    pub burst: u32,
+}
+
+impl RateLimitConfig {
+    fn quota(&self) -> Quota {
+        let rate = NonZeroU32::new(self.requests_per_second).unwrap_or(NonZeroU32::MIN);
+        let burst = NonZeroU32::new(self.burst).unwrap_or(NonZeroU32::MIN);
+        Quota::per_second(rate).allow_burst(burst)
+    }
+}
+
+/// A token bucket per client, sized by the client's own limit or the default.
+pub struct RateLimiter {
+    default: RateLimitConfig,
+    clock: DefaultClock,
+    buckets: Mutex<LruCache<String, (RateLimitConfig, Arc<DefaultDirectRateLimiter>)>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            default: *config,
+            clock: DefaultClock::default(),
+            buckets: Mutex::new(LruCache::new(
+                NonZeroUsize::new(MAX_TRACKED_CLIENTS).unwrap(),
+            )),
+        }
+    }
+
+    /// Takes a token from `client`'s bucket. When it is empty, returns how many
+    /// seconds to wait for the next one. `limit` overrides the default; a
+    /// bucket whose limit changed starts over full.
+    pub fn check(&self, client: &str, limit: Option<&RateLimitConfig>) -> Result<(), u64> {
+        let config = limit.copied().unwrap_or(self.default);
+        let limiter = {
+            let mut buckets = self.buckets.lock().unwrap();
+            match buckets.get(client) {
+                Some((current, limiter)) if *current == config => limiter.clone(),
+                _ => {
+                    let limiter = Arc::new(DefaultDirectRateLimiter::direct(config.quota()));
+                    buckets.put(client.to_string(), (config, limiter.clone()));
+                    limiter
+                }
+            }
+        };
+
+        limiter.check().map_err(|not_until| {
+            let wait = not_until.wait_time_from(self.clock.now());
+            // Rounded up, so a client that waits as told is let through
+            wait.as_secs() + u64::from(wait.subsec_nanos() > 0)
+        })
+    }
+}
+
+/// Throttles each client to its [`RateLimitConfig`], answering `429 Too Many
+/// Requests` with `Retry-After` once its burst is spent. With
+/// `auth.require_api_key` set clients are told apart by API key, and use the
+/// key's own limit when it has one; otherwise, and for invalid keys, by peer IP.
+/// A key that isn't cached as valid is charged to its IP before it is looked
+/// up, so requests with made-up keys are throttled without reaching the
+/// metadata store.
+/// The IP comes from `ConnectInfo`, so the router must be served with
+/// `into_make_service_with_connect_info`.
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    service: Arc<ObjectStoreService>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitLayer {
+    pub fn new(service: Arc<ObjectStoreService>, limiter: Arc<RateLimiter>) -> Self {
+        Self { service, limiter }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            service: self.service.clone(),
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    service: Arc<ObjectStoreService>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<S> Service<Request<Body>> for RateLimitService<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        // The clone hasn't been polled ready, so this call keeps the one that was
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let service = self.service.clone();
+        let limiter = self.limiter.clone();
+
+        let key = bearer_key(req.headers()).map(str::to_string);
+        let ip = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map_or_else(|| "unknown".to_string(), |info| info.0.ip().to_string());
+
+        Box::pin(async move {
+            let throttled = |retry_after_secs| {
+                ServiceError::TooManyRequests { retry_after_secs }.into_response()
+            };
+            let ip_client = format!("ip:{}", ip);
+
+            let record = match key.as_deref() {
+                Some(key) if service.requires_api_key() => {
+                    match service.cached_api_key(key).await {
+                        Some(record) => Some(record),
+                        None => {
+                            if let Err(retry_after_secs) = limiter.check(&ip_client, None) {
+                                return Ok(throttled(retry_after_secs));
+                            }
+                            match service.api_key(key).await {
+                                Ok(record) => Some(record),
+                                // Already charged to the IP
+                                Err(_) => return inner.call(req).await,
+                            }
+                        }
+                    }
+                }
+                _ => None,
+            };
+
+            let (client, limit) = match (key, record) {
+                (Some(key), Some(record)) => (format!("key:{}", hash_key(&key)), record.rate_limit),
+                _ => (ip_client, None),
+            };
+            if let Err(retry_after_secs) = limiter.check(&client, limit.as_ref()) {
+                return Ok(throttled(retry_after_secs));
+            }
+            inner.call(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_is_allowed_then_throttled() {
+        let limiter = RateLimiter::new(&RateLimitConfig {
+            requests_per_second: 1,
+            burst: 3,
+        });
+
+        for _ in 0..3 {
+            assert!(limiter.check("a", None).is_ok());
+        }
+        assert_eq!(limiter.check("a", None), Err(1));
+
+        // Buckets are per client
+        assert!(limiter.check("b", None).is_ok());
+
+        // A client's own limit replaces the default
+        let generous = RateLimitConfig {
+            requests_per_second: 1,
+            burst: 10,
+        };
+        for _ in 0..10 {
+            assert!(limiter.check("a", Some(&generous)).is_ok());
+        }
+        assert!(limiter.check("a", Some(&generous)).is_err());
+    }
+}
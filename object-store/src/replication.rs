@@ -0,0 +1,156 @@
+use bytes::Bytes;
+use object_store_backends::{Backend, BackendError, BackendResult, ByteStream, ObjectMetadata};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::config::BackendConfig;
+use crate::events::{EventType, ObjectEvent};
+use crate::metadata::MetadataStore;
+use crate::metrics::ServiceMetrics;
+
+/// Markers for background replications that failed and still need a retry,
+/// stored as `{prefix}/{bucket}/{key}` on the primary backend.
+pub const REPLICATION_PENDING_PREFIX: &str = ".metadata/replication-pending";
+
+/// Uploads to buckets with synchronous replication are written here first,
+/// as `{prefix}/{bucket}/{key}`, and only moved over the object once the
+/// replica has them.
+pub const REPLICATION_STAGING_PREFIX: &str = ".metadata/replication-staging";
+
+/// Copies every object uploaded to a bucket to a second backend.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplicationConfig {
+    /// Id of a backend configured under `replicas` in the server config.
+    pub target: String,
+    /// Replicate in the background instead of before the upload returns.
+    /// Failed background copies are retried until they succeed.
+    #[serde(default)]
+    pub r#async: bool,
+}
+
+/// The replication targets from the server config, by id. Each backend is
+/// created on first use and shared by every bucket that replicates to it.
+#[derive(Default)]
+pub struct ReplicaBackends {
+    targets: HashMap<String, BackendConfig>,
+    backends: Mutex<HashMap<String, Arc<dyn Backend>>>,
+}
+
+impl ReplicaBackends {
+    pub fn new(targets: HashMap<String, BackendConfig>) -> Self {
+        Self {
+            targets,
+            backends: Mutex::default(),
+        }
+    }
+
+    pub fn contains(&self, target: &str) -> bool {
+        self.targets.contains_key(target)
+    }
+
+    pub async fn get(&self, target: &str) -> BackendResult<Arc<dyn Backend>> {
+        let Some(config) = self.targets.get(target) else {
+            return Err(BackendError::Configuration(format!(
+                "No replication target {}",
+                target
+            )));
+        };
+        let mut backends = self.backends.lock().await;
+        if let Some(backend) = backends.get(target) {
+            return Ok(backend.clone());
+        }
+
+        let backend = config.create_backend().await?;
+        backend.init().await?;
+        info!("Initialized replication target {}", target);
+        backends.insert(target.to_string(), backend.clone());
+        Ok(backend)
+    }
+}
+
+pub fn pending_marker_key(full_key: &str) -> String {
+    format!("{}/{}", REPLICATION_PENDING_PREFIX, full_key)
+}
+
+pub fn staging_key(full_key: &str) -> String {
+    format!("{}/{}", REPLICATION_STAGING_PREFIX, full_key)
+}
+
+/// Copies `source_key` from `source` to `full_key` on `target`.
+pub async fn replicate_object(
+    source: &dyn Backend,
+    source_key: &str,
+    target: &dyn Backend,
+    full_key: &str,
+) -> BackendResult<ObjectMetadata> {
+    let object = source.get_object(source_key).await?;
+    target
+        .put_object(
+            full_key,
+            object.stream,
+            object.metadata.content_type,
+            object.metadata.custom_metadata,
+        )
+        .await
+}
+
+pub async fn mark_pending(backend: &dyn Backend, full_key: &str) -> BackendResult<()> {
+    let stream: ByteStream = Box::pin(futures::stream::once(async { Ok(Bytes::new()) }));
+    backend
+        .put_object(&pending_marker_key(full_key), stream, None, HashMap::new())
+        .await?;
+    Ok(())
+}
+
+/// Replicates objects created in buckets with async replication, one task per
+/// object. Copies that fail are marked pending for
+/// [`retry_pending_replications`](crate::ObjectStoreService::retry_pending_replications).
+pub async fn replicate_events(
+    mut events: UnboundedReceiver<ObjectEvent>,
+    source: Arc<dyn Backend>,
+    metadata: Arc<MetadataStore>,
+    replicas: Arc<ReplicaBackends>,
+    metrics: Arc<ServiceMetrics>,
+) {
+    while let Some(event) = events.recv().await {
+        if event.event_type != EventType::ObjectCreated {
+            continue;
+        }
+
+        let replication = match metadata.get_bucket(&event.bucket).await {
+            Ok(bucket) => bucket.replication.filter(|r| r.r#async),
+            Err(e) => {
+                warn!(
+                    "Skipping replication of {}/{}: {}",
+                    event.bucket, event.key, e
+                );
+                continue;
+            }
+        };
+        let Some(replication) = replication else {
+            continue;
+        };
+
+        let source = source.clone();
+        let replicas = replicas.clone();
+        let failures = metrics.replication_failures(&event.bucket);
+        let full_key = format!("{}/{}", event.bucket, event.key);
+        tokio::spawn(async move {
+            let result = match replicas.get(&replication.target).await {
+                Ok(target) => replicate_object(&*source, &full_key, &*target, &full_key).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                warn!("Replication of {} failed, will retry: {}", full_key, e);
+                failures.increment(1);
+                if let Err(e) = mark_pending(&*source, &full_key).await {
+                    error!("Failed to queue replication retry for {}: {}", full_key, e);
+                }
+            }
+        });
+    }
+}
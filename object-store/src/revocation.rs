@@ -0,0 +1,210 @@
+use bytes::Bytes;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use futures::StreamExt;
+use object_store_backends::{Backend, BackendError, BackendResult, ByteStream};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use url::Url;
+
+/// Revoked presigned URLs, stored as `{prefix}/{sha256 of the URL}` on the
+/// primary backend until the URL would have expired anyway. Each object with a
+/// revoked URL also has `{prefix}/{bucket}/{sha256 of the key}`, holding the
+/// revocation that lasts longest, so issuing a URL reads one record.
+pub const REVOKED_URLS_PREFIX: &str = ".metadata/revoked-urls";
+
+/// How long a revocation is kept when the URL's expiry cannot be read from it,
+/// the longest lifetime S3 and GCS allow for a presigned URL.
+const MAX_PRESIGNED_URL_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Query parameters that carry the signature of an S3, GCS or Azure
+/// presigned URL.
+const SIGNATURE_PARAMS: &[&str] = &["x-amz-signature", "x-goog-signature", "signature", "sig"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RevokedUrl {
+    pub bucket: String,
+    /// Decoded path of the revoked URL.
+    pub path: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl RevokedUrl {
+    pub fn is_live(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at > now
+    }
+}
+
+/// The key in `bucket` a URL path points at. URLs served by this service use
+/// `/buckets/{bucket}/objects/{key}`; backends put `{bucket}/{key}` first or
+/// after the one segment naming their own bucket or container. The bucket
+/// has to match a whole segment.
+pub fn object_key<'a>(path: &'a str, bucket: &str) -> Option<&'a str> {
+    let after_bucket = |rest: &'a str| {
+        rest.strip_prefix(bucket)?
+            .strip_prefix('/')
+            .filter(|key| !key.is_empty())
+    };
+
+    if let Some(rest) = path.strip_prefix("/buckets/") {
+        if let Some(key) = after_bucket(rest).and_then(|rest| rest.strip_prefix("objects/")) {
+            return (!key.is_empty()).then_some(key);
+        }
+    }
+    let path = path.strip_prefix('/')?;
+    after_bucket(path).or_else(|| after_bucket(path.split_once('/')?.1))
+}
+
+/// Parses `url` into the form its revocation hash is computed from, so a URL
+/// matches however its scheme and host were capitalized.
+pub fn parse_url(url: &str) -> Option<Url> {
+    Url::parse(url.trim()).ok()
+}
+
+pub fn url_hash(url: &Url) -> String {
+    hex::encode(Sha256::digest(url.as_str().as_bytes()))
+}
+
+pub fn revocation_key(url: &Url) -> String {
+    format!("{}/{}", REVOKED_URLS_PREFIX, url_hash(url))
+}
+
+/// Where the longest-lasting revocation of a URL for `key` in `bucket` is
+/// kept. Filed under the bucket, so deleting the bucket drops it.
+pub fn object_revocation_key(bucket: &str, key: &str) -> String {
+    format!(
+        "{}/{}/{}",
+        REVOKED_URLS_PREFIX,
+        bucket,
+        hex::encode(Sha256::digest(key.as_bytes()))
+    )
+}
+
+pub fn decoded_path(url: &Url) -> String {
+    percent_encoding::percent_decode_str(url.path())
+        .decode_utf8_lossy()
+        .into_owned()
+}
+
+pub fn is_presigned(url: &Url) -> bool {
+    url.query_pairs()
+        .any(|(name, _)| SIGNATURE_PARAMS.contains(&name.to_ascii_lowercase().as_str()))
+}
+
+/// When a presigned URL stops working: SigV4-style `X-Amz-Date`/`X-Amz-Expires`
+/// and `X-Goog-Date`/`X-Goog-Expires` pairs, an Azure SAS `se`, or a SigV2-style
+/// `Expires` timestamp. Falls back to the longest presigned URL lifetime.
+pub fn url_expiry(url: &Url, now: DateTime<Utc>) -> DateTime<Utc> {
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.into_owned())
+    };
+
+    let signed_at_plus = |date: &str, expires: &str| {
+        let signed = NaiveDateTime::parse_from_str(&param(date)?, "%Y%m%dT%H%M%SZ").ok()?;
+        let secs: i64 = param(expires)?.parse().ok()?;
+        Some(signed.and_utc() + Duration::seconds(secs))
+    };
+
+    signed_at_plus("X-Amz-Date", "X-Amz-Expires")
+        .or_else(|| signed_at_plus("X-Goog-Date", "X-Goog-Expires"))
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(&param("se")?)
+                .ok()
+                .map(|t| t.with_timezone(&Utc))
+        })
+        .or_else(|| DateTime::from_timestamp(param("Expires")?.parse().ok()?, 0))
+        .unwrap_or(now + Duration::seconds(MAX_PRESIGNED_URL_SECS))
+}
+
+pub async fn write_revocation(
+    backend: &dyn Backend,
+    key: &str,
+    revoked: &RevokedUrl,
+) -> BackendResult<()> {
+    let data = serde_json::to_vec(revoked)?;
+    let stream: ByteStream = Box::pin(futures::stream::once(async move { Ok(Bytes::from(data)) }));
+    backend
+        .put_object(
+            key,
+            stream,
+            Some("application/json".to_string()),
+            HashMap::new(),
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn read_revocation(
+    backend: &dyn Backend,
+    key: &str,
+) -> BackendResult<Option<RevokedUrl>> {
+    let mut object = match backend.get_object(key).await {
+        Ok(object) => object,
+        Err(BackendError::NotFound(_)) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut data = Vec::new();
+    while let Some(chunk) = object.stream.next().await {
+        data.extend_from_slice(&chunk.map_err(|e| BackendError::Internal(e.to_string()))?);
+    }
+    Ok(Some(serde_json::from_slice(&data)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_expiry_per_provider() {
+        let now = Utc::now();
+        let expiry = |url: &str| url_expiry(&parse_url(url).unwrap(), now).to_rfc3339();
+
+        assert_eq!(
+            expiry("https://s3.amazonaws.com/b/k?X-Amz-Date=20240101T000000Z&X-Amz-Expires=3600&X-Amz-Signature=s"),
+            "2024-01-01T01:00:00+00:00"
+        );
+        assert_eq!(
+            expiry("https://storage.googleapis.com/b/k?X-Goog-Date=20240101T000000Z&X-Goog-Expires=60&X-Goog-Signature=s"),
+            "2024-01-01T00:01:00+00:00"
+        );
+        assert_eq!(
+            expiry("https://a.blob.core.windows.net/c/k?se=2024-01-02T00%3A00%3A00Z&sig=s"),
+            "2024-01-02T00:00:00+00:00"
+        );
+        assert_eq!(
+            expiry("https://s3.amazonaws.com/b/k?Expires=1704067200&Signature=s"),
+            "2024-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            expiry("https://example.com/b/k?sig=s"),
+            (now + Duration::seconds(MAX_PRESIGNED_URL_SECS)).to_rfc3339()
+        );
+    }
+
+    #[test]
+    fn test_object_key_matches_whole_bucket_segment() {
+        assert_eq!(
+            object_key("/buckets/logs/objects/a/b.txt", "logs"),
+            Some("a/b.txt")
+        );
+        assert_eq!(object_key("/logs/a/b.txt", "logs"), Some("a/b.txt"));
+        assert_eq!(
+            object_key("/physical/logs/a/b.txt", "logs"),
+            Some("a/b.txt")
+        );
+
+        assert_eq!(
+            object_key("/buckets/other/objects/logs/a.txt", "logs"),
+            None
+        );
+        assert_eq!(
+            object_key("/buckets/logs-archive/objects/a.txt", "logs"),
+            None
+        );
+        assert_eq!(object_key("/physical/other/logs/a.txt", "logs"), None);
+        assert_eq!(object_key("/physical/logs-archive/a.txt", "logs"), None);
+        assert_eq!(object_key("/physical/logs/", "logs"), None);
+    }
+}
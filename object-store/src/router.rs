@@ -1,36 +1,166 @@
-use axum::routing::{delete, get, head, post, put};
-use axum::Router;
+use axum::routing::{delete, get, head, patch, post, put};
+use axum::{Extension, Router};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
+use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
+use tracing::Span;
 
 use crate::api::*;
+use crate::config::{ServerConfig, UrlStyle};
+use crate::middleware::{
+    add_server_version, reject_revoked_urls, require_api_key, route_virtual_host,
+    track_request_metrics, RequestId, RequestIdLayer,
+};
+use crate::rate_limit::RateLimitLayer;
 use crate::service::ObjectStoreService;
 
+/// Builds the public data API. Health, metrics and admin endpoints live on the
+/// router from [`create_internal_router`] instead.
 pub fn create_router(service: Arc<ObjectStoreService>) -> Router {
-    Router::new()
-        .route("/health", get(health_check))
-        .route("/ping", get(health_check))
+    create_router_with_config(service, &ServerConfig::default())
+}
+
+pub fn create_router_with_config(
+    service: Arc<ObjectStoreService>,
+    config: &ServerConfig,
+) -> Router {
+    let router = Router::new()
         .route("/buckets", post(create_bucket))
         .route("/buckets", put(upsert_bucket))
         .route("/buckets", get(list_buckets))
         .route("/buckets/:bucket", get(get_bucket_by_id))
         .route("/buckets/:bucket", delete(delete_bucket))
+        .route("/buckets/:bucket", patch(update_bucket))
         .route("/buckets/:bucket/objects/*key", put(put_object))
+        .route("/buckets/:bucket/upsert/*key", put(upsert_object))
         .route("/buckets/:bucket/objects/*key", get(get_object))
         .route("/buckets/:bucket/objects/*key", head(head_object))
         .route("/buckets/:bucket/objects/*key", delete(delete_object))
+        .route("/buckets/:bucket/objects/*key", post(post_object))
         .route("/buckets/:bucket/objects", get(list_objects))
+        .route("/buckets/:bucket/inventory", get(export_inventory))
+        .route("/buckets/:bucket/lifecycle", get(get_lifecycle))
+        .route("/buckets/:bucket/lifecycle", put(put_lifecycle))
+        .route("/buckets/:bucket/replication-lag", get(get_replication_lag))
+        .route("/buckets/:bucket/recyclebin", get(list_recycle_bin))
+        .route("/buckets/:bucket/recyclebin/restore", post(restore_object))
+        .route(
+            "/buckets/:bucket/recyclebin/:version",
+            delete(purge_recycle_bin_version),
+        )
         .route("/buckets/:bucket/object-info/*key", get(get_object_info))
         .route("/buckets/:bucket/public-url/*key", get(get_public_url))
+        .route("/buckets/:bucket/revoke-url", post(revoke_url))
+        .route_layer(axum::middleware::from_fn_with_state(
+            service.clone(),
+            reject_revoked_urls,
+        ))
+        .route_layer(axum::middleware::from_fn_with_state(
+            service.clone(),
+            require_api_key,
+        ))
+        .route_layer(axum::middleware::from_fn_with_state(
+            service.clone(),
+            track_request_metrics,
+        ));
+
+    let router = match service.rate_limiter() {
+        Some(limiter) => router.layer(RateLimitLayer::new(service.clone(), limiter)),
+        None => router,
+    };
+
+    // Requests with a Content-Length over the limit are rejected with 413 before
+    // the handler runs; chunked bodies are cut off once they cross it.
+    let router = match config.max_body_bytes {
+        Some(limit) => router.layer(RequestBodyLimitLayer::new(
+            usize::try_from(limit).unwrap_or(usize::MAX),
+        )),
+        None => router,
+    };
+
+    let router = router
         .layer(
             ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
+                .layer(RequestIdLayer)
+                .layer(axum::middleware::from_fn(add_server_version))
+                .layer(TraceLayer::new_for_http().make_span_with(make_request_span))
                 .layer(CorsLayer::permissive())
-                .layer(TimeoutLayer::new(Duration::from_secs(60))),
+                .layer(TimeoutLayer::new(Duration::from_secs(60)))
+                .layer(Extension(UploadPolicy {
+                    strict_content_type: config.strict_content_type,
+                    max_body_bytes: config.max_body_bytes,
+                }))
+                .layer(Extension(config.metadata_header_style)),
+        )
+        .with_state(service);
+
+    match (config.url_style, config.virtual_host_domain.as_deref()) {
+        // Layers on a router run after routing, so the rewrite wraps the whole
+        // router as a fallback to take effect before routes are matched.
+        (UrlStyle::VirtualHosted, Some(domain)) => {
+            Router::new()
+                .fallback_service(router)
+                .layer(axum::middleware::from_fn_with_state(
+                    Arc::<str>::from(domain),
+                    route_virtual_host,
+                ))
+        }
+        // `ServerConfig::validate` refuses virtual-hosted URLs without a
+        // domain, so only path-style routes are served here
+        (UrlStyle::VirtualHosted, None) | (UrlStyle::PathStyle, _) => router,
+    }
+}
+
+/// Builds the operator-facing router: health checks, metrics, `/internal/*` and
+/// `/admin/*` endpoints. Bind it to an address that is only reachable in-cluster.
+///
+/// `/ping` counts uptime from when this router was built and reports the
+/// backend type as `unknown`; use [`create_internal_router_with_info`] to
+/// supply both.
+pub fn create_internal_router(service: Arc<ObjectStoreService>) -> Router {
+    create_internal_router_with_info(service, ServerInfo::new(Instant::now(), "unknown"))
+}
+
+pub fn create_internal_router_with_info(
+    service: Arc<ObjectStoreService>,
+    info: ServerInfo,
+) -> Router {
+    Router::new()
+        .route("/health", get(health_check))
+        .route("/ping", get(ping))
+        .route("/metrics", get(metrics))
+        .route("/internal/scan-bucket/:bucket", get(scan_bucket))
+        .route("/internal/compact-backend", post(compact_backend))
+        .route("/internal/cache-stats", get(cache_stats))
+        .route("/admin/api-keys", post(create_api_key))
+        .route("/admin/api-keys", get(list_api_keys))
+        .route("/admin/api-keys/:hash", delete(delete_api_key))
+        .layer(
+            ServiceBuilder::new()
+                .layer(RequestIdLayer)
+                .layer(axum::middleware::from_fn(add_server_version))
+                .layer(TraceLayer::new_for_http().make_span_with(make_request_span))
+                .layer(TimeoutLayer::new(Duration::from_secs(60)))
+                .layer(Extension(info)),
         )
         .with_state(service)
 }
+
+fn make_request_span<B>(request: &axum::http::Request<B>) -> Span {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .map(|id| id.as_str().to_string())
+        .unwrap_or_default();
+
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id = %request_id,
+    )
+}
@@ -1,20 +1,447 @@
 use bytes::Bytes;
-use object_store_backends::{Backend, ByteStream, ObjectData, ObjectMetadata, PublicUrlPurpose};
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use object_store_backends::{
+    Backend, BackendError, BackendResult, BackendResultExt, ByteStream, CompactReport, ObjectData,
+    ObjectMetadata, PublicUrlPurpose,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
-use std::sync::Arc;
-use tracing::{debug, info};
+use std::str::FromStr;
+use std::sync::{Arc, Once};
+use tokio::io::AsyncRead;
+use tokio::sync::OwnedMutexGuard;
+use tokio_util::io::ReaderStream;
+use tracing::{debug, error, info, warn};
 
+use crate::auth::{
+    generate_key, hash_key, is_valid_key_hash, ApiKeyAuthenticator, ApiKeyRecord, AuthConfig,
+    OperationType, Permission,
+};
+use crate::config::BackendConfig;
+use crate::dedup::{DeduplicatorConfig, UploadDeduplicator};
 use crate::error::{ServiceError, ServiceResult};
-use crate::metadata::{Bucket, MetadataStore};
+use crate::events::{EventBus, EventType, ObjectEvent};
+use crate::idempotency::{IdempotencyCache, IdempotencyConfig};
+use crate::metadata::{Bucket, LifecycleRule, MetadataStore};
+use crate::metrics::{MetricsConfig, ServiceMetrics};
+#[cfg(feature = "hooks")]
+use crate::pipeline::{HookEvent, HookPayload, PipelineRunner};
+use crate::pipeline::{HooksConfig, PipelineConfig};
+use crate::rate_limit::{RateLimitConfig, RateLimiter};
+use crate::replication::{
+    pending_marker_key, replicate_events, replicate_object, staging_key, ReplicaBackends,
+    ReplicationConfig, REPLICATION_PENDING_PREFIX, REPLICATION_STAGING_PREFIX,
+};
+use crate::revocation::{
+    decoded_path, object_key, object_revocation_key, parse_url, read_revocation, revocation_key,
+    url_expiry, write_revocation, RevokedUrl, REVOKED_URLS_PREFIX,
+};
+use crate::validation::{metadata_error, validate_metadata, ValidationConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    Key,
+    Size,
+    LastModified,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BucketSortField {
+    Name,
+    #[default]
+    CreatedAt,
+}
+
+impl BucketSortField {
+    /// Order used when none is given: names read A to Z, newest buckets first.
+    pub fn default_order(self) -> SortOrder {
+        match self {
+            BucketSortField::Name => SortOrder::Asc,
+            BucketSortField::CreatedAt => SortOrder::Desc,
+        }
+    }
+}
+
+/// Ordering and filtering for [`ObjectStoreService::list_buckets`]. The
+/// default lists every bucket, newest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListBucketsOptions {
+    pub sort_by: BucketSortField,
+    pub sort_order: SortOrder,
+    /// Only buckets whose name starts with this.
+    pub name_prefix: Option<String>,
+}
+
+impl Default for ListBucketsOptions {
+    fn default() -> Self {
+        Self {
+            sort_by: BucketSortField::CreatedAt,
+            sort_order: BucketSortField::CreatedAt.default_order(),
+            name_prefix: None,
+        }
+    }
+}
+
+const RECYCLE_BIN_PREFIX: &str = ".recyclebin";
+
+/// Custom metadata of an empty object that stands for content stored under
+/// another key of a deduplicating bucket. Metadata keys given by users can't
+/// contain dots, so these never clash with theirs.
+const DEDUP_SHA256_KEY: &str = "object-store.dedup-sha256";
+const DEDUP_SIZE_KEY: &str = "object-store.dedup-size";
+const DEDUP_ETAG_KEY: &str = "object-store.dedup-etag";
+
+/// Uploads to deduplicating buckets are written here first, as
+/// `{prefix}/{bucket}/{key}`. The object they replace may hold content other
+/// keys point at, and it is only handed on once the upload has succeeded.
+const DEDUP_STAGING_PREFIX: &str = ".metadata/dedup-staging";
+
+/// Prefixes the service keeps copies of a bucket's objects under, as
+/// `{prefix}/{bucket}/...`. A composite backend has to store them with the
+/// bucket's own objects, so moving them in and out stays on one backend.
+pub const BUCKET_DATA_PREFIXES: [&str; 3] = [
+    RECYCLE_BIN_PREFIX,
+    DEDUP_STAGING_PREFIX,
+    REPLICATION_STAGING_PREFIX,
+];
+
+/// A soft-deleted object. `version` is the deletion time in milliseconds since
+/// the Unix epoch and, together with `key`, identifies the entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecycleBinEntry {
+    pub key: String,
+    pub version: String,
+    pub size: u64,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Object count and total size of a bucket, computed from a full listing.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct BucketStats {
+    pub object_count: u64,
+    pub total_bytes: u64,
+}
+
+/// Outcome of a deep health check against a reachable backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackendHealthStatus {
+    /// As returned by [`Backend::backend_type`].
+    pub backend_type: String,
+    /// Time taken by the whole check, including the canary write.
+    pub latency_ms: u64,
+    /// Whether a canary object could be written and deleted.
+    pub writable: bool,
+}
+
+/// Where the health check writes its canary when there are no buckets yet.
+const HEALTH_CHECK_BUCKET: &str = "__health__";
+
+/// Listings above this size without a prefix log a warning when a metadata
+/// filter forces a `head_object` call per object.
+const METADATA_FILTER_WARN_THRESHOLD: usize = 1000;
+
+/// How many `head_object` calls a metadata filter keeps in flight at once.
+const METADATA_FILTER_CONCURRENCY: usize = 16;
+
+/// Objects read from the backend per request by a streamed listing.
+const LIST_STREAM_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListingConfig {
+    /// Listings without a prefix that scan more objects than this to apply a
+    /// modification date filter log a warning.
+    #[serde(default = "default_date_filter_warn_threshold")]
+    pub date_filter_warn_threshold: usize,
+}
+
+fn default_date_filter_warn_threshold() -> usize {
+    10_000
+}
+
+impl Default for ListingConfig {
+    fn default() -> Self {
+        Self {
+            date_filter_warn_threshold: default_date_filter_warn_threshold(),
+        }
+    }
+}
+
+/// A `key=value` match against an object's custom metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataFilter {
+    pub key: String,
+    pub value: String,
+}
+
+impl MetadataFilter {
+    fn matches(&self, metadata: &HashMap<String, String>) -> bool {
+        metadata.get(&self.key) == Some(&self.value)
+    }
+}
+
+impl FromStr for MetadataFilter {
+    type Err = ServiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok(Self {
+                key: key.to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err(ServiceError::InvalidRequest(format!(
+                "Invalid metadata filter '{}', expected key=value",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ListObjectsOptions {
+    pub prefix: Option<String>,
+    pub max_keys: Option<usize>,
+    pub sort_by: Option<SortField>,
+    pub sort_order: Option<SortOrder>,
+    pub metadata_filter: Option<MetadataFilter>,
+    /// Only objects modified strictly after this time.
+    pub modified_after: Option<DateTime<Utc>>,
+    /// Only objects modified strictly before this time.
+    pub modified_before: Option<DateTime<Utc>>,
+    /// Only keys that sort after this one, so a listing can be read page by
+    /// page by passing the last key of the previous page. Implies key order.
+    pub start_after: Option<String>,
+    /// Also count every object matching the prefix and filters, across all
+    /// pages, see [`ObjectListing::total_count`].
+    pub include_count: bool,
+    /// List only keys, sizes, ETags and modification times, through
+    /// [`Backend::list_objects_metadata_only`]. Content types and custom
+    /// metadata come back empty.
+    pub summary_only: bool,
+}
+
+/// One page of a listing.
+#[derive(Debug, Clone)]
+pub struct ObjectListing {
+    pub objects: Vec<ObjectMetadata>,
+    /// Objects matching the prefix and filters, ignoring `max_keys` and
+    /// `start_after`. Only set when [`ListObjectsOptions::include_count`] is.
+    pub total_count: Option<u64>,
+}
+
+impl ListObjectsOptions {
+    pub fn new(prefix: Option<&str>, max_keys: Option<usize>) -> Self {
+        Self {
+            prefix: prefix.map(|p| p.to_string()),
+            max_keys,
+            ..Default::default()
+        }
+    }
+
+    // Sorting and filtering must see the whole listing before max_keys is applied
+    fn needs_full_listing(&self) -> bool {
+        self.sort_by.is_some()
+            || self.metadata_filter.is_some()
+            || self.has_date_filter()
+            || self.start_after.is_some()
+    }
+
+    fn sort_field(&self) -> ServiceResult<Option<SortField>> {
+        if self.start_after.is_none() {
+            return Ok(self.sort_by);
+        }
+        match (self.sort_by, self.sort_order) {
+            (None | Some(SortField::Key), None | Some(SortOrder::Asc)) => Ok(Some(SortField::Key)),
+            _ => Err(ServiceError::InvalidRequest(
+                "start_after requires listing in ascending key order".to_string(),
+            )),
+        }
+    }
+
+    fn has_filter(&self) -> bool {
+        self.metadata_filter.is_some() || self.has_date_filter()
+    }
+
+    fn has_date_filter(&self) -> bool {
+        self.modified_after.is_some() || self.modified_before.is_some()
+    }
+
+    fn matches_dates(&self, obj: &ObjectMetadata) -> bool {
+        self.modified_after
+            .is_none_or(|after| obj.last_modified > after)
+            && self
+                .modified_before
+                .is_none_or(|before| obj.last_modified < before)
+    }
+}
 
 pub struct ObjectStoreService {
     backend: Arc<dyn Backend>,
     metadata: Arc<MetadataStore>,
+    metrics: Arc<ServiceMetrics>,
+    replicas: Arc<ReplicaBackends>,
+    auth: AuthConfig,
+    api_keys: ApiKeyAuthenticator,
+    listing: ListingConfig,
+    validation: ValidationConfig,
+    uploads: UploadDeduplicator,
+    idempotency: IdempotencyCache,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    events: Arc<EventBus>,
+    subscribers_started: Once,
+    hooks: HooksConfig,
+    #[cfg(feature = "hooks")]
+    pipeline: PipelineRunner,
 }
 
 impl ObjectStoreService {
     pub fn new(backend: Arc<dyn Backend>, metadata: Arc<MetadataStore>) -> Self {
-        Self { backend, metadata }
+        Self {
+            backend,
+            api_keys: ApiKeyAuthenticator::new(metadata.clone()),
+            metadata,
+            metrics: Arc::new(ServiceMetrics::new(&MetricsConfig::default())),
+            replicas: Arc::new(ReplicaBackends::default()),
+            auth: AuthConfig::default(),
+            listing: ListingConfig::default(),
+            validation: ValidationConfig::default(),
+            uploads: UploadDeduplicator::default(),
+            idempotency: IdempotencyCache::default(),
+            rate_limiter: None,
+            events: Arc::new(EventBus::default()),
+            subscribers_started: Once::new(),
+            hooks: HooksConfig::default(),
+            #[cfg(feature = "hooks")]
+            pipeline: PipelineRunner::new(&HooksConfig::default()),
+        }
+    }
+
+    pub fn with_metrics_config(mut self, config: &MetricsConfig) -> Self {
+        self.metrics = Arc::new(ServiceMetrics::new(config));
+        self
+    }
+
+    pub fn with_auth_config(mut self, config: &AuthConfig) -> Self {
+        self.auth = config.clone();
+        self
+    }
+
+    pub fn with_listing_config(mut self, config: &ListingConfig) -> Self {
+        self.listing = config.clone();
+        self
+    }
+
+    pub fn with_validation_config(mut self, config: &ValidationConfig) -> Self {
+        self.validation = config.clone();
+        self
+    }
+
+    pub fn validation_config(&self) -> &ValidationConfig {
+        &self.validation
+    }
+
+    pub fn with_deduplicator_config(mut self, config: &DeduplicatorConfig) -> Self {
+        self.uploads = UploadDeduplicator::new(config);
+        self
+    }
+
+    pub fn with_idempotency_config(mut self, config: &IdempotencyConfig) -> Self {
+        self.idempotency = IdempotencyCache::new(config);
+        self
+    }
+
+    /// Throttles each client of the public router. Off when `None`.
+    pub fn with_rate_limit_config(mut self, config: Option<&RateLimitConfig>) -> Self {
+        self.rate_limiter = config.map(|config| Arc::new(RateLimiter::new(config)));
+        self
+    }
+
+    pub fn rate_limiter(&self) -> Option<Arc<RateLimiter>> {
+        self.rate_limiter.clone()
+    }
+
+    /// The backends buckets may replicate to, by the id their
+    /// [`ReplicationConfig::target`] names.
+    pub fn with_replica_targets(mut self, targets: &HashMap<String, BackendConfig>) -> Self {
+        self.replicas = Arc::new(ReplicaBackends::new(targets.clone()));
+        self
+    }
+
+    /// Hosts upload hooks may call. None are allowed by default.
+    pub fn with_hooks_config(mut self, config: &HooksConfig) -> Self {
+        self.hooks = config.clone();
+        #[cfg(feature = "hooks")]
+        {
+            self.pipeline = PipelineRunner::new(config);
+        }
+        self
+    }
+
+    /// Rejects hooks calling hosts outside the allow-list, and any hooks at all
+    /// in builds without the `hooks` feature, which would never run them.
+    pub fn validate_pipeline(&self, pipeline: &PipelineConfig) -> ServiceResult<()> {
+        if pipeline.is_empty() {
+            return Ok(());
+        }
+        if !cfg!(feature = "hooks") {
+            return Err(ServiceError::InvalidRequest(
+                "Upload hooks are not supported by this server".to_string(),
+            ));
+        }
+        self.hooks
+            .check(pipeline)
+            .map_err(ServiceError::InvalidRequest)
+    }
+
+    /// Rejects replication to a target that isn't configured on the server.
+    pub fn validate_replication(&self, replication: &ReplicationConfig) -> ServiceResult<()> {
+        if !self.replicas.contains(&replication.target) {
+            return Err(ServiceError::InvalidRequest(format!(
+                "Unknown replication target {}",
+                replication.target
+            )));
+        }
+        Ok(())
+    }
+
+    /// Object events published by this service, for internal consumers.
+    pub fn events(&self) -> Arc<EventBus> {
+        self.events.clone()
+    }
+
+    fn publish(&self, event_type: EventType, bucket: &str, metadata: ObjectMetadata) {
+        self.metadata.invalidate_object_counts(bucket);
+        // Started on first use rather than in `new`, so they run inside the
+        // runtime and see the configuration set by the `with_*` builders
+        self.subscribers_started.call_once(|| {
+            tokio::spawn(replicate_events(
+                self.events.subscribe(),
+                self.backend.clone(),
+                self.metadata.clone(),
+                self.replicas.clone(),
+                self.metrics.clone(),
+            ));
+            #[cfg(feature = "hooks")]
+            tokio::spawn(
+                self.pipeline
+                    .clone()
+                    .run_post_upload_hooks(self.events.subscribe(), self.metadata.clone()),
+            );
+        });
+        self.events
+            .publish(ObjectEvent::new(event_type, bucket, metadata));
     }
 
     pub async fn init(&self) -> ServiceResult<()> {
@@ -24,9 +451,25 @@ impl ObjectStoreService {
     }
 
     pub async fn create_bucket(&self, name: &str) -> ServiceResult<Bucket> {
-        let bucket = self.metadata.create_bucket(name).await?;
+        self.create_bucket_with(name, |_| {}).await
+    }
 
-        let bucket_marker = format!("{}/.bucket", name);
+    pub async fn create_bucket_with(
+        &self,
+        name: &str,
+        configure: impl FnOnce(&mut Bucket),
+    ) -> ServiceResult<Bucket> {
+        let chars = name.chars().count();
+        if chars > self.validation.max_bucket_name_chars {
+            return Err(ServiceError::InvalidBucketName(format!(
+                "Bucket name is {} characters, at most {} are allowed",
+                chars, self.validation.max_bucket_name_chars
+            )));
+        }
+
+        let bucket = self.metadata.create_bucket_with(name, configure).await?;
+
+        let bucket_marker = format!("{}/.bucket", bucket.name);
 
         // Create empty stream for bucket marker
         let stream: ByteStream = Box::pin(futures::stream::once(async { Ok(Bytes::new()) }));
@@ -35,23 +478,58 @@ impl ObjectStoreService {
             .put_object(&bucket_marker, stream, None, HashMap::new())
             .await?;
 
-        info!("Created bucket: {}", name);
+        info!("Created bucket: {}", bucket.name);
         Ok(bucket)
     }
 
     pub async fn upsert_bucket(&self, name: &str) -> ServiceResult<Bucket> {
+        self.upsert_bucket_with(name, |_| {}).await
+    }
+
+    /// Like `upsert_bucket`, but `configure` is applied to an existing bucket as
+    /// well as a new one, so settings converge to what the caller asked for.
+    pub async fn upsert_bucket_with(
+        &self,
+        name: &str,
+        configure: impl FnOnce(&mut Bucket),
+    ) -> ServiceResult<Bucket> {
         // Try to get existing bucket first
-        if let Ok(bucket) = self.metadata.get_bucket(name).await {
-            debug!("Bucket {} already exists, returning existing", name);
-            return Ok(bucket);
+        if let Ok(existing) = self.metadata.get_bucket(name).await {
+            let mut updated = existing.clone();
+            configure(&mut updated);
+            if updated == existing {
+                debug!("Bucket {} already exists, returning existing", name);
+                return Ok(existing);
+            }
+            return self.metadata.update_bucket(name, |b| *b = updated).await;
         }
 
         // Bucket doesn't exist, create it
-        self.create_bucket(name).await
+        self.create_bucket_with(name, configure).await
     }
 
-    pub async fn list_buckets(&self) -> ServiceResult<Vec<Bucket>> {
-        self.metadata.list_buckets().await
+    pub async fn get_bucket(&self, name: &str) -> ServiceResult<Bucket> {
+        self.metadata.get_bucket(name).await
+    }
+
+    pub async fn update_bucket_metadata(
+        &self,
+        name: &str,
+        description: Option<String>,
+        tags: HashMap<String, Option<String>>,
+    ) -> ServiceResult<Bucket> {
+        self.metadata
+            .update_bucket_metadata(name, description, tags)
+            .await
+    }
+
+    pub async fn list_buckets(&self, options: &ListBucketsOptions) -> ServiceResult<Vec<Bucket>> {
+        let mut buckets = self.metadata.list_buckets().await?;
+        if let Some(prefix) = &options.name_prefix {
+            buckets.retain(|b| b.name.starts_with(prefix.as_str()));
+        }
+        sort_buckets(&mut buckets, options.sort_by, options.sort_order);
+        Ok(buckets)
     }
 
     pub async fn get_bucket_by_id(&self, id: &str) -> ServiceResult<Bucket> {
@@ -59,6 +537,8 @@ impl ObjectStoreService {
     }
 
     pub async fn delete_bucket(&self, name: &str) -> ServiceResult<()> {
+        let name = self.metadata.normalize_bucket_name(name);
+        let name = name.as_ref();
         self.metadata.get_bucket(name).await?;
 
         // List all objects in the bucket to see if it's empty
@@ -70,77 +550,1020 @@ impl ObjectStoreService {
             )));
         }
 
-        // Delete the bucket marker
-        let bucket_marker = format!("{}/.bucket", name);
-        let _ = self.backend.delete_object(&bucket_marker).await;
+        // Delete the bucket marker
+        let bucket_marker = format!("{}/.bucket", name);
+        let _ = self.backend.delete_object(&bucket_marker).await;
+
+        // Recycled objects live outside the bucket prefix and would otherwise be
+        // orphaned, or restored into a same-named bucket created later.
+        for entry in self.list_recycle_bin(name).await? {
+            let key = recycle_bin_key(name, &entry.key, &entry.version);
+            let _ = self.backend.delete_object(&key).await;
+        }
+
+        // Delete from metadata
+        self.metadata.delete_bucket(name).await?;
+        self.metrics.release_bucket(name);
+
+        info!("Deleted bucket: {}", name);
+        Ok(())
+    }
+
+    pub async fn put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        stream: ByteStream,
+        content_type: Option<String>,
+        metadata: HashMap<String, String>,
+    ) -> ServiceResult<ObjectMetadata> {
+        let bucket = self.metadata.normalize_bucket_name(bucket);
+        let bucket = bucket.as_ref();
+        let bucket_meta = self
+            .check_upload(bucket, key, &content_type, &metadata)
+            .await?;
+
+        // Held until the object and its synchronous replica are written, so the
+        // next upload to this key starts from a complete object
+        let upload = self.lock_upload(bucket, key).await;
+        let obj_metadata = self
+            .write_object(bucket, &bucket_meta, key, stream, content_type, metadata)
+            .await?;
+        drop(upload);
+        // Async replication and post-upload hooks react to the event
+        self.publish(EventType::ObjectCreated, bucket, obj_metadata.clone());
+
+        debug!("Put object: {}/{}", bucket, key);
+        Ok(obj_metadata)
+    }
+
+    /// Looks up the bucket for an upload, validates the key and metadata and
+    /// runs the bucket's pre-upload hooks.
+    #[cfg_attr(not(feature = "hooks"), allow(unused_variables))]
+    async fn check_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: &Option<String>,
+        metadata: &HashMap<String, String>,
+    ) -> ServiceResult<Bucket> {
+        let bucket_meta = self.metadata.get_bucket(bucket).await?;
+
+        validate_object_key(key, &self.validation)?;
+        validate_metadata(metadata).map_err(metadata_error)?;
+
+        // Stored by a build with hooks; failing closed, as an unreachable hook would
+        #[cfg(not(feature = "hooks"))]
+        if !bucket_meta.pipeline.pre_upload.is_empty() {
+            return Err(ServiceError::HookRejected(format!(
+                "bucket {} has pre-upload hooks, which this server doesn't run",
+                bucket
+            )));
+        }
+        #[cfg(feature = "hooks")]
+        if !bucket_meta.pipeline.pre_upload.is_empty() {
+            let payload = HookPayload {
+                event: HookEvent::PreUpload,
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                content_type: content_type.clone(),
+                size: None,
+                etag: None,
+                metadata: metadata.clone(),
+            };
+            self.pipeline
+                .run_pre_upload(&bucket_meta.pipeline.pre_upload, &payload)
+                .await?;
+        }
+        Ok(bucket_meta)
+    }
+
+    /// Takes the lock uploads to `bucket/key` hold while writing, counting an
+    /// upload collision when another upload has it.
+    async fn lock_upload(&self, bucket: &str, key: &str) -> OwnedMutexGuard<()> {
+        let full_key = format!("{}/{}", bucket, key);
+        match self.uploads.try_lock(&full_key) {
+            Some(upload) => upload,
+            None => {
+                self.metrics.upload_collisions(bucket).increment(1);
+                debug!("Waiting for concurrent upload of {}/{}", bucket, key);
+                self.uploads.lock(&full_key).await
+            }
+        }
+    }
+
+    /// Stores an upload checked by [`check_upload`](Self::check_upload),
+    /// deduplicating and synchronously replicating it as the bucket asks.
+    /// Callers hold the key's upload lock and publish the event.
+    async fn write_object(
+        &self,
+        bucket: &str,
+        bucket_meta: &Bucket,
+        key: &str,
+        stream: ByteStream,
+        content_type: Option<String>,
+        metadata: HashMap<String, String>,
+    ) -> ServiceResult<ObjectMetadata> {
+        let full_key = format!("{}/{}", bucket, key);
+
+        let hasher = bucket_meta
+            .deduplication_enabled
+            .then(|| Arc::new(std::sync::Mutex::new(Sha256::new())));
+        let stream: ByteStream = match &hasher {
+            Some(hasher) => {
+                let hasher = hasher.clone();
+                Box::pin(stream.inspect(move |chunk| {
+                    if let Ok(chunk) = chunk {
+                        hasher.lock().unwrap().update(chunk);
+                    }
+                }))
+            }
+            None => stream,
+        };
+
+        let sync_replication = bucket_meta.replication.as_ref().filter(|r| !r.r#async);
+        let write_key = if sync_replication.is_some() {
+            staging_key(&full_key)
+        } else if hasher.is_some() {
+            format!("{}/{}", DEDUP_STAGING_PREFIX, full_key)
+        } else {
+            full_key.clone()
+        };
+        let mut obj_metadata = self
+            .backend
+            .put_object(&write_key, stream, content_type, metadata)
+            .await
+            .with_context(|| format!("put_object bucket={} key={}", bucket, key))?;
+
+        if let Some(hasher) = hasher {
+            let sha256 = hex::encode(hasher.lock().unwrap().finalize_reset());
+            let _hashes = self.metadata.lock_hashes().await;
+            if let Err(e) = self.release_content(&full_key).await {
+                if let Err(e) = self.backend.delete_object_if_exists(&write_key).await {
+                    error!("Failed to remove staged upload {}: {}", write_key, e);
+                }
+                return Err(e);
+            }
+            self.backend.rename_object(&write_key, &full_key).await?;
+            obj_metadata.key = full_key.clone();
+            obj_metadata = self.deduplicate(&full_key, &sha256, obj_metadata).await?;
+        }
+
+        if let Some(replication) = sync_replication {
+            self.replicate_staged(&full_key, replication).await?;
+            obj_metadata.key = full_key;
+        }
+        Ok(obj_metadata)
+    }
+
+    /// Replaces a freshly stored object with a pointer when the same content is
+    /// already stored under another key, or records it as the stored copy.
+    /// Returns the metadata of the object as uploaded. Callers hold the hash
+    /// index lock.
+    async fn deduplicate(
+        &self,
+        full_key: &str,
+        sha256: &str,
+        stored: ObjectMetadata,
+    ) -> ServiceResult<ObjectMetadata> {
+        match self.metadata.find_by_hash(sha256).await? {
+            Some(canonical) if canonical == full_key => {}
+            Some(canonical) => {
+                let mut pointer_metadata = stored.custom_metadata.clone();
+                pointer_metadata.insert(DEDUP_SHA256_KEY.to_string(), sha256.to_string());
+                pointer_metadata.insert(DEDUP_SIZE_KEY.to_string(), stored.size.to_string());
+                pointer_metadata.insert(DEDUP_ETAG_KEY.to_string(), stored.etag.clone());
+                let empty: ByteStream = Box::pin(futures::stream::once(async { Ok(Bytes::new()) }));
+                self.backend
+                    .put_object(
+                        full_key,
+                        empty,
+                        stored.content_type.clone(),
+                        pointer_metadata,
+                    )
+                    .await?;
+                self.metadata.add_hash_reference(sha256, full_key).await?;
+                debug!("Deduplicated {} against {}", full_key, canonical);
+            }
+            None => self.metadata.record_hash(sha256, full_key).await?,
+        }
+        Ok(stored)
+    }
+
+    /// Takes `full_key` out of the content hash index before it is deleted, or
+    /// once the upload replacing it has been staged. If other keys point at its content, the content is copied to
+    /// the first of them, which becomes the stored copy. Callers hold the hash
+    /// index lock.
+    async fn release_content(&self, full_key: &str) -> ServiceResult<()> {
+        let Some(sha256) = self.metadata.content_hash_of(full_key).await? else {
+            return Ok(());
+        };
+
+        if let Some(mut record) = self.metadata.hash_record(&sha256).await? {
+            if record.canonical_key != full_key {
+                record.references.retain(|r| r != full_key);
+                self.metadata.save_hash_record(&sha256, &record).await?;
+            } else if record.references.is_empty() {
+                self.metadata.remove_hash_record(&sha256).await?;
+            } else {
+                let successor = record.references.remove(0);
+                let mut pointer = self.backend.head_object(&successor).await?;
+                resolve_dedup_pointer(&mut pointer);
+                self.backend
+                    .copy_object(
+                        full_key,
+                        &successor,
+                        pointer.content_type,
+                        Some(pointer.custom_metadata),
+                    )
+                    .await?;
+                debug!("Moved stored copy of {} to {}", full_key, successor);
+                record.canonical_key = successor;
+                self.metadata.save_hash_record(&sha256, &record).await?;
+            }
+        }
+
+        self.metadata.forget_content_hash(full_key).await
+    }
+
+    /// Like [`put_object`](Self::put_object), reading the body from `reader`.
+    pub async fn put_object_reader<R: AsyncRead + Send + 'static>(
+        &self,
+        bucket: &str,
+        key: &str,
+        reader: R,
+        content_type: Option<String>,
+        metadata: HashMap<String, String>,
+    ) -> ServiceResult<ObjectMetadata> {
+        self.put_object(
+            bucket,
+            key,
+            Box::pin(ReaderStream::new(reader)),
+            content_type,
+            metadata,
+        )
+        .await
+    }
+
+    /// Like [`put_object`](Self::put_object), with a body held in memory, such as
+    /// a `Vec<u8>` or `Bytes`.
+    pub async fn put_object_bytes(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: impl Into<Bytes>,
+        content_type: Option<String>,
+        metadata: HashMap<String, String>,
+    ) -> ServiceResult<ObjectMetadata> {
+        let data = data.into();
+        self.put_object(
+            bucket,
+            key,
+            Box::pin(futures::stream::once(async move { Ok(data) })),
+            content_type,
+            metadata,
+        )
+        .await
+    }
+
+    /// Like [`put_object_bytes`](Self::put_object_bytes), storing `data` as UTF-8.
+    pub async fn put_object_str(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: &str,
+        content_type: Option<String>,
+        metadata: HashMap<String, String>,
+    ) -> ServiceResult<ObjectMetadata> {
+        self.put_object_bytes(
+            bucket,
+            key,
+            Bytes::copy_from_slice(data.as_bytes()),
+            content_type,
+            metadata,
+        )
+        .await
+    }
+
+    /// Copies `source_key` to `full_key` on the bucket's replication target.
+    async fn replicate(
+        &self,
+        source_key: &str,
+        full_key: &str,
+        replication: &ReplicationConfig,
+    ) -> BackendResult<()> {
+        let target = self.replicas.get(&replication.target).await?;
+        replicate_object(&*self.backend, source_key, &*target, full_key).await?;
+        Ok(())
+    }
+
+    /// Copies an object written under its [`staging_key`] to the bucket's
+    /// replication target, then moves it over `full_key`, before the upload
+    /// returns. A failed copy drops the staged object and fails the upload,
+    /// leaving any object already under `full_key` as it was. Async
+    /// replication runs off the event bus instead, see [`replicate_events`].
+    async fn replicate_staged(
+        &self,
+        full_key: &str,
+        replication: &ReplicationConfig,
+    ) -> ServiceResult<()> {
+        let staged = staging_key(full_key);
+        if let Err(e) = self.replicate(&staged, full_key, replication).await {
+            warn!(
+                "Replication of {} failed, discarding the upload: {}",
+                full_key, e
+            );
+            if let Err(e) = self.backend.delete_object_if_exists(&staged).await {
+                error!(
+                    "Failed to remove staged upload {} after replication failure: {}",
+                    staged, e
+                );
+            }
+            return Err(e.into());
+        }
+        self.backend.rename_object(&staged, full_key).await?;
+        Ok(())
+    }
+
+    /// Retries background replications that failed earlier and returns how many
+    /// went through. Markers for objects, buckets or replication settings that
+    /// no longer exist are dropped.
+    pub async fn retry_pending_replications(&self) -> ServiceResult<u64> {
+        let markers = match self
+            .backend
+            .list_objects(Some(REPLICATION_PENDING_PREFIX), None)
+            .await
+        {
+            Ok(markers) => markers,
+            Err(BackendError::NotFound(_)) => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut replicated = 0;
+        for marker in markers {
+            let Some(full_key) = marker
+                .key
+                .strip_prefix(REPLICATION_PENDING_PREFIX)
+                .and_then(|k| k.strip_prefix('/'))
+            else {
+                continue;
+            };
+            let bucket = full_key.split('/').next().unwrap_or(full_key);
+
+            let replication = match self.metadata.get_bucket(bucket).await {
+                Ok(bucket) => bucket.replication,
+                Err(ServiceError::BucketNotFound(_)) => None,
+                Err(e) => return Err(e),
+            };
+            let done = match replication {
+                Some(replication) if self.backend.object_exists(full_key).await? => {
+                    match self.replicate(full_key, full_key, &replication).await {
+                        Ok(_) => {
+                            replicated += 1;
+                            true
+                        }
+                        Err(e) => {
+                            warn!("Replication retry for {} failed: {}", full_key, e);
+                            false
+                        }
+                    }
+                }
+                _ => true,
+            };
+
+            if done {
+                self.backend
+                    .delete_object(&pending_marker_key(full_key))
+                    .await?;
+            }
+        }
+
+        if replicated > 0 {
+            info!("Caught up {} pending replications", replicated);
+        }
+        Ok(replicated)
+    }
+
+    /// Number of objects in `bucket` still waiting to be replicated.
+    pub async fn replication_lag(&self, bucket: &str) -> ServiceResult<u64> {
+        let bucket = self.metadata.normalize_bucket_name(bucket);
+        let bucket = bucket.as_ref();
+        self.metadata.get_bucket(bucket).await?;
+
+        let prefix = format!("{}/{}/", REPLICATION_PENDING_PREFIX, bucket);
+        match self.backend.list_objects(Some(&prefix), None).await {
+            Ok(markers) => Ok(markers.len() as u64),
+            Err(BackendError::NotFound(_)) => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Stores an object only if the key is free, returning the metadata and
+    /// whether a new object was created. An existing object is left alone unless
+    /// `expected_etag` is given and differs from its ETag, in which case it is
+    /// overwritten.
+    pub async fn upsert_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        stream: ByteStream,
+        content_type: Option<String>,
+        metadata: HashMap<String, String>,
+        expected_etag: Option<&str>,
+    ) -> ServiceResult<(ObjectMetadata, bool)> {
+        let bucket = self.metadata.normalize_bucket_name(bucket);
+        let bucket = bucket.as_ref();
+
+        // Held from the check through the write, so a concurrent upload can't
+        // land in between and be overwritten or skipped on stale information
+        let upload = self.lock_upload(bucket, key).await;
+        let created = match self.head_object(bucket, key).await {
+            Ok(existing) if expected_etag.is_none_or(|etag| etag == existing.etag) => {
+                debug!("Object already exists, skipping upload: {}/{}", bucket, key);
+                return Ok((existing, false));
+            }
+            Ok(_) => false,
+            Err(ServiceError::ObjectNotFound(_)) => true,
+            Err(e) => return Err(e),
+        };
+
+        let bucket_meta = self
+            .check_upload(bucket, key, &content_type, &metadata)
+            .await?;
+        let obj_metadata = self
+            .write_object(bucket, &bucket_meta, key, stream, content_type, metadata)
+            .await?;
+        drop(upload);
+        self.publish(EventType::ObjectCreated, bucket, obj_metadata.clone());
+
+        debug!("Upserted object: {}/{}", bucket, key);
+        Ok((obj_metadata, created))
+    }
+
+    /// Copies an object without the caller re-uploading it. The copy keeps the
+    /// source's content type and custom metadata unless overridden; a metadata
+    /// override replaces the whole map.
+    pub async fn copy_object(
+        &self,
+        src_bucket: &str,
+        src_key: &str,
+        dst_bucket: &str,
+        dst_key: &str,
+        content_type_override: Option<String>,
+        metadata_override: Option<HashMap<String, String>>,
+    ) -> ServiceResult<ObjectMetadata> {
+        let src_bucket = self.metadata.normalize_bucket_name(src_bucket);
+        let src_bucket = src_bucket.as_ref();
+        let dst_bucket = self.metadata.normalize_bucket_name(dst_bucket);
+        let dst_bucket = dst_bucket.as_ref();
+        let src_meta = self.metadata.get_bucket(src_bucket).await?;
+        let dst_meta = self.metadata.get_bucket(dst_bucket).await?;
+
+        validate_object_key(src_key, &self.validation)?;
+        validate_object_key(dst_key, &self.validation)?;
+        if let Some(metadata) = &metadata_override {
+            validate_metadata(metadata).map_err(metadata_error)?;
+        }
+
+        let src_full_key = format!("{}/{}", src_bucket, src_key);
+        let dst_full_key = format!("{}/{}", dst_bucket, dst_key);
+
+        let upload = self.uploads.lock(&dst_full_key).await;
+        let _hashes = if src_meta.deduplication_enabled || dst_meta.deduplication_enabled {
+            Some(self.metadata.lock_hashes().await)
+        } else {
+            None
+        };
+
+        let sha256 = if src_meta.deduplication_enabled {
+            self.metadata.content_hash_of(&src_full_key).await?
+        } else {
+            None
+        };
+        // A pointer is copied from the stored content it stands for
+        let mut source = src_full_key.clone();
+        let (mut content_type_override, mut metadata_override) =
+            (content_type_override, metadata_override);
+        if let Some(sha256) = &sha256 {
+            if let Some(record) = self
+                .metadata
+                .hash_record(sha256)
+                .await?
+                .filter(|record| record.canonical_key != src_full_key)
+            {
+                let mut pointer = self.backend.head_object(&src_full_key).await?;
+                resolve_dedup_pointer(&mut pointer);
+                content_type_override = content_type_override.or(pointer.content_type);
+                metadata_override = metadata_override.or(Some(pointer.custom_metadata));
+                source = record.canonical_key;
+            }
+        }
+        if dst_meta.deduplication_enabled && dst_full_key != src_full_key {
+            self.release_content(&dst_full_key).await?;
+        }
+
+        let sync_replication = dst_meta.replication.as_ref().filter(|r| !r.r#async);
+        let write_key = match sync_replication {
+            Some(_) => staging_key(&dst_full_key),
+            None => dst_full_key.clone(),
+        };
+        let mut copied = match self
+            .backend
+            .copy_object(
+                &source,
+                &write_key,
+                content_type_override,
+                metadata_override,
+            )
+            .await
+        {
+            Err(BackendError::NotFound(_)) => {
+                return Err(ServiceError::ObjectNotFound(format!(
+                    "{}/{}",
+                    src_bucket, src_key
+                )))
+            }
+            result => result.with_context(|| {
+                format!("copy_object from={} to={}", src_full_key, dst_full_key)
+            })?,
+        };
+        if let Some(sha256) = sha256.filter(|_| dst_meta.deduplication_enabled) {
+            copied = self.deduplicate(&dst_full_key, &sha256, copied).await?;
+        }
+
+        if let Some(replication) = sync_replication {
+            self.replicate_staged(&dst_full_key, replication).await?;
+        }
+        drop(upload);
+        self.publish(EventType::ObjectCreated, dst_bucket, copied.clone());
+
+        copied.key = dst_key.to_string();
+        debug!(
+            "Copied object {}/{} to {}/{}",
+            src_bucket, src_key, dst_bucket, dst_key
+        );
+        Ok(copied)
+    }
+
+    /// Moves an object to `new_key` in the same bucket, replacing any object
+    /// there. The backend renames it in place where it can. Deduplicated
+    /// buckets copy and delete instead, which keeps content hash references
+    /// pointing at stored content.
+    pub async fn rename_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        new_key: &str,
+    ) -> ServiceResult<ObjectMetadata> {
+        let bucket = self.metadata.normalize_bucket_name(bucket);
+        let bucket = bucket.as_ref();
+        let bucket_info = self.metadata.get_bucket(bucket).await?;
+
+        validate_object_key(key, &self.validation)?;
+        validate_object_key(new_key, &self.validation)?;
+        if key == new_key {
+            return Err(ServiceError::InvalidRequest(format!(
+                "Object {} already has that key",
+                key
+            )));
+        }
+
+        if bucket_info.deduplication_enabled {
+            let renamed = self
+                .copy_object(bucket, key, bucket, new_key, None, None)
+                .await?;
+            self.delete_object(bucket, key).await?;
+            return Ok(renamed);
+        }
+
+        let full_key = format!("{}/{}", bucket, key);
+        let new_full_key = format!("{}/{}", bucket, new_key);
+        // Taken in key order, so renames in opposite directions can't deadlock
+        let (first, second) = if full_key < new_full_key {
+            (&full_key, &new_full_key)
+        } else {
+            (&new_full_key, &full_key)
+        };
+        let first = self.uploads.lock(first).await;
+        let second = self.uploads.lock(second).await;
+
+        let existing = match self.backend.head_object(&full_key).await {
+            Err(BackendError::NotFound(_)) => {
+                return Err(ServiceError::ObjectNotFound(format!("{}/{}", bucket, key)))
+            }
+            result => result?,
+        };
+        // Replicated first, so a failed copy leaves both keys as they were
+        if let Some(replication) = bucket_info.replication.as_ref().filter(|r| !r.r#async) {
+            self.replicate(&full_key, &new_full_key, replication)
+                .await?;
+        }
+        match self.backend.rename_object(&full_key, &new_full_key).await {
+            Ok(()) => {}
+            Err(BackendError::NotFound(_)) => {
+                return Err(ServiceError::ObjectNotFound(format!("{}/{}", bucket, key)))
+            }
+            Err(e) => {
+                return Err(ServiceError::RenameFailed(format!(
+                    "{}/{} to {}: {}",
+                    bucket, key, new_key, e
+                )))
+            }
+        }
+        let mut renamed = self.backend.head_object(&new_full_key).await?;
+        drop((first, second));
+        self.publish(EventType::ObjectDeleted, bucket, existing);
+        self.publish(EventType::ObjectCreated, bucket, renamed.clone());
+
+        renamed.key = new_key.to_string();
+        info!("Renamed object {}/{} to {}", bucket, key, new_key);
+        Ok(renamed)
+    }
+
+    pub async fn get_object(&self, bucket: &str, key: &str) -> ServiceResult<ObjectData> {
+        self.read_object(bucket, key, None).await
+    }
+
+    /// Up to `len` bytes of the object from offset `start`, with the metadata
+    /// of the whole object. Only the requested bytes are read from backends
+    /// that support ranged reads.
+    pub async fn get_object_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        start: u64,
+        len: u64,
+    ) -> ServiceResult<ObjectData> {
+        self.read_object(bucket, key, Some((start, len))).await
+    }
+
+    /// Returns `None` without fetching the body when the stored ETag matches
+    /// `etag`. Otherwise reads the object, or the `(start, len)` range of it.
+    pub async fn get_object_if_not_match(
+        &self,
+        bucket: &str,
+        key: &str,
+        etag: &str,
+        range: Option<(u64, u64)>,
+    ) -> ServiceResult<Option<ObjectData>> {
+        let metadata = self.head_object(bucket, key).await?;
+        if metadata.etag == etag {
+            debug!("Object not modified (etag match): {}/{}", bucket, key);
+            return Ok(None);
+        }
+
+        self.read_object(bucket, key, range).await.map(Some)
+    }
+
+    /// Returns `None` without fetching the body when the object has not changed
+    /// since `since`. HTTP dates only carry whole seconds, so sub-second precision
+    /// of the stored timestamp is ignored. Otherwise reads the object, or the
+    /// `(start, len)` range of it.
+    pub async fn get_object_if_modified_since(
+        &self,
+        bucket: &str,
+        key: &str,
+        since: DateTime<Utc>,
+        range: Option<(u64, u64)>,
+    ) -> ServiceResult<Option<ObjectData>> {
+        let metadata = self.head_object(bucket, key).await?;
+        if metadata.last_modified.timestamp() <= since.timestamp() {
+            debug!("Object not modified since {}: {}/{}", since, bucket, key);
+            return Ok(None);
+        }
+
+        self.read_object(bucket, key, range).await.map(Some)
+    }
+
+    async fn read_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        range: Option<(u64, u64)>,
+    ) -> ServiceResult<ObjectData> {
+        let bucket = self.metadata.normalize_bucket_name(bucket);
+        let bucket = bucket.as_ref();
+        self.metadata.get_bucket(bucket).await?;
+
+        validate_object_key(key, &self.validation)?;
+
+        let full_key = format!("{}/{}", bucket, key);
+        let read = |backend_key: String| async move {
+            match range {
+                Some((start, len)) => {
+                    self.backend
+                        .get_object_range(&backend_key, start, len)
+                        .await
+                }
+                None => self.backend.get_object(&backend_key).await,
+            }
+            .map_err(|e| convert_backend_not_found(e, bucket, key))
+        };
+
+        let mut obj_data = read(full_key).await?;
+        if let Some(sha256) = resolve_dedup_pointer(&mut obj_data.metadata) {
+            let canonical = self.metadata.find_by_hash(&sha256).await?.ok_or_else(|| {
+                ServiceError::Internal(format!("No stored copy for content hash {}", sha256))
+            })?;
+            obj_data.stream = read(canonical).await?.stream;
+        }
+
+        debug!("Got object: {}/{}", bucket, key);
+        Ok(obj_data)
+    }
+
+    pub async fn head_object(&self, bucket: &str, key: &str) -> ServiceResult<ObjectMetadata> {
+        let bucket = self.metadata.normalize_bucket_name(bucket);
+        let bucket = bucket.as_ref();
+        self.metadata.get_bucket(bucket).await?;
+
+        validate_object_key(key, &self.validation)?;
+
+        let full_key = format!("{}/{}", bucket, key);
+
+        let mut metadata = self
+            .backend
+            .head_object(&full_key)
+            .await
+            .map_err(|e| convert_backend_not_found(e, bucket, key))?;
+        resolve_dedup_pointer(&mut metadata);
+
+        debug!("Got object metadata: {}/{}", bucket, key);
+        Ok(metadata)
+    }
+
+    pub async fn delete_object(&self, bucket: &str, key: &str) -> ServiceResult<()> {
+        let bucket = self.metadata.normalize_bucket_name(bucket);
+        let bucket = bucket.as_ref();
+        let bucket_info = self.metadata.get_bucket(bucket).await?;
+
+        validate_object_key(key, &self.validation)?;
+
+        let full_key = format!("{}/{}", bucket, key);
+
+        if bucket_info.recycle_bin_enabled {
+            let version = Utc::now().timestamp_millis().to_string();
+            let recycled_key = recycle_bin_key(bucket, key, &version);
+            let mut recycled = match self.copy_backend_object(&full_key, &recycled_key).await {
+                Err(ServiceError::Backend(e)) => {
+                    return Err(convert_backend_not_found(e, bucket, key))
+                }
+                result => result?,
+            };
+            self.backend
+                .delete_object(&full_key)
+                .await
+                .map_err(|e| convert_backend_not_found(e, bucket, key))?;
+            recycled.key = key.to_string();
+            self.publish(EventType::ObjectDeleted, bucket, recycled);
+
+            info!(
+                "Moved object to recycle bin: {}/{}#{}",
+                bucket, key, version
+            );
+            return Ok(());
+        }
+
+        // Read first so the event can carry what was deleted
+        let mut existing = self.backend.head_object(&full_key).await.ok();
+        if let Some(existing) = &mut existing {
+            resolve_dedup_pointer(existing);
+        }
+        let hashes = if bucket_info.deduplication_enabled {
+            Some(self.metadata.lock_hashes().await)
+        } else {
+            None
+        };
+        if hashes.is_some() {
+            self.release_content(&full_key).await?;
+        }
+        self.backend.delete_object_if_exists(&full_key).await?;
+        drop(hashes);
+        if let Some(existing) = existing {
+            self.publish(EventType::ObjectDeleted, bucket, existing);
+        }
+
+        info!("Deleted object: {}/{}", bucket, key);
+        Ok(())
+    }
+
+    pub async fn list_recycle_bin(&self, bucket: &str) -> ServiceResult<Vec<RecycleBinEntry>> {
+        let bucket = self.metadata.normalize_bucket_name(bucket);
+        let bucket = bucket.as_ref();
+        self.metadata.get_bucket(bucket).await?;
 
-        // Delete from metadata
-        self.metadata.delete_bucket(name).await?;
+        let prefix = format!("{}/{}/", RECYCLE_BIN_PREFIX, bucket);
+        let objects = match self.backend.list_objects(Some(&prefix), None).await {
+            Ok(objects) => objects,
+            Err(BackendError::NotFound(_)) => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
 
-        info!("Deleted bucket: {}", name);
-        Ok(())
+        let entries: Vec<RecycleBinEntry> = objects
+            .into_iter()
+            .filter_map(|obj| {
+                let (key, version) = obj.key.strip_prefix(&prefix)?.rsplit_once('#')?;
+                let deleted_at = DateTime::from_timestamp_millis(version.parse().ok()?)?;
+                Some(RecycleBinEntry {
+                    key: key.to_string(),
+                    version: version.to_string(),
+                    size: obj.size,
+                    deleted_at,
+                })
+            })
+            .collect();
+
+        debug!(
+            "Listed {} recycle bin entries in bucket: {}",
+            entries.len(),
+            bucket
+        );
+        Ok(entries)
     }
 
-    pub async fn put_object(
+    /// Moves a recycled object back to its original key, overwriting any object
+    /// that has been written there since.
+    pub async fn restore_object(
         &self,
         bucket: &str,
         key: &str,
-        stream: ByteStream,
-        content_type: Option<String>,
-        metadata: HashMap<String, String>,
+        version: &str,
     ) -> ServiceResult<ObjectMetadata> {
+        let bucket = self.metadata.normalize_bucket_name(bucket);
+        let bucket = bucket.as_ref();
         self.metadata.get_bucket(bucket).await?;
 
-        validate_object_key(key)?;
+        validate_object_key(key, &self.validation)?;
+        validate_recycle_bin_version(version)?;
 
+        let recycled_key = recycle_bin_key(bucket, key, version);
         let full_key = format!("{}/{}", bucket, key);
 
-        let obj_metadata = self
-            .backend
-            .put_object(&full_key, stream, content_type, metadata)
-            .await?;
+        let mut restored = match self.copy_backend_object(&recycled_key, &full_key).await {
+            Err(ServiceError::Backend(BackendError::NotFound(_))) => {
+                return Err(ServiceError::ObjectNotFound(format!(
+                    "{}/{}#{} in recycle bin",
+                    bucket, key, version
+                )))
+            }
+            result => result?,
+        };
+        self.backend.delete_object(&recycled_key).await?;
 
-        debug!("Put object: {}/{}", bucket, key);
-        Ok(obj_metadata)
+        restored.key = key.to_string();
+        self.publish(EventType::ObjectRestored, bucket, restored.clone());
+        info!(
+            "Restored object from recycle bin: {}/{}#{}",
+            bucket, key, version
+        );
+        Ok(restored)
     }
 
-    pub async fn get_object(&self, bucket: &str, key: &str) -> ServiceResult<ObjectData> {
-        self.metadata.get_bucket(bucket).await?;
-
-        validate_object_key(key)?;
+    /// Permanently deletes recycle bin entries with the given version, limited to
+    /// `key` when one is given. Returns the number of entries removed.
+    pub async fn purge_recycle_bin_version(
+        &self,
+        bucket: &str,
+        version: &str,
+        key: Option<&str>,
+    ) -> ServiceResult<u64> {
+        let bucket = self.metadata.normalize_bucket_name(bucket);
+        let bucket = bucket.as_ref();
+        validate_recycle_bin_version(version)?;
 
-        let full_key = format!("{}/{}", bucket, key);
+        let mut purged = 0;
+        for entry in self.list_recycle_bin(bucket).await? {
+            if entry.version != version || key.is_some_and(|k| k != entry.key) {
+                continue;
+            }
+            self.backend
+                .delete_object(&recycle_bin_key(bucket, &entry.key, &entry.version))
+                .await?;
+            purged += 1;
+        }
 
-        let obj_data = self.backend.get_object(&full_key).await?;
+        if purged == 0 {
+            return Err(ServiceError::ObjectNotFound(format!(
+                "version {} in recycle bin of {}",
+                version, bucket
+            )));
+        }
 
-        debug!("Got object: {}/{}", bucket, key);
-        Ok(obj_data)
+        info!(
+            "Permanently deleted {} recycle bin entries: {}#{}",
+            purged, bucket, version
+        );
+        Ok(purged)
     }
 
-    pub async fn head_object(&self, bucket: &str, key: &str) -> ServiceResult<ObjectMetadata> {
-        self.metadata.get_bucket(bucket).await?;
+    /// Removes recycle bin entries older than their bucket's retention period,
+    /// measured against `now`. Returns the number of entries removed.
+    pub async fn purge_expired_recycle_bin(&self, now: DateTime<Utc>) -> ServiceResult<u64> {
+        let mut purged = 0;
 
-        validate_object_key(key)?;
+        for bucket in self.metadata.list_buckets().await? {
+            if bucket.recycle_bin_retention_days == 0 {
+                continue;
+            }
+            let cutoff = now - chrono::Duration::days(i64::from(bucket.recycle_bin_retention_days));
 
-        let full_key = format!("{}/{}", bucket, key);
+            for entry in self.list_recycle_bin(&bucket.name).await? {
+                if entry.deleted_at > cutoff {
+                    continue;
+                }
+                let key = recycle_bin_key(&bucket.name, &entry.key, &entry.version);
+                if self.backend.delete_object(&key).await.is_ok() {
+                    purged += 1;
+                    debug!("Purged expired recycle bin entry: {}", key);
+                }
+            }
+        }
 
-        let metadata = self.backend.head_object(&full_key).await?;
+        if purged > 0 {
+            info!("Purged {} expired recycle bin entries", purged);
+        }
 
-        debug!("Got object metadata: {}/{}", bucket, key);
-        Ok(metadata)
+        Ok(purged)
     }
 
-    pub async fn delete_object(&self, bucket: &str, key: &str) -> ServiceResult<()> {
-        self.metadata.get_bucket(bucket).await?;
+    pub async fn get_lifecycle_rules(&self, bucket: &str) -> ServiceResult<Vec<LifecycleRule>> {
+        Ok(self.metadata.get_bucket(bucket).await?.lifecycle_rules)
+    }
 
-        validate_object_key(key)?;
+    /// Replaces the bucket's lifecycle rules. An empty list removes them.
+    pub async fn put_lifecycle_rules(
+        &self,
+        bucket: &str,
+        rules: Vec<LifecycleRule>,
+    ) -> ServiceResult<Vec<LifecycleRule>> {
+        validate_lifecycle_rules(&rules)?;
 
-        let full_key = format!("{}/{}", bucket, key);
+        let updated = self
+            .metadata
+            .update_bucket(bucket, |b| b.lifecycle_rules = rules)
+            .await?;
+        info!(
+            "Set {} lifecycle rules on bucket: {}",
+            updated.lifecycle_rules.len(),
+            bucket
+        );
+        Ok(updated.lifecycle_rules)
+    }
 
-        self.backend.delete_object(&full_key).await?;
+    /// Evaluates every bucket's lifecycle rules against its objects as of `now`,
+    /// deleting expired objects and transitioning the rest. Failures on single
+    /// objects are logged and retried on the next run. Returns the number of
+    /// objects deleted or transitioned.
+    pub async fn apply_lifecycle_rules(&self, now: DateTime<Utc>) -> ServiceResult<u64> {
+        let mut expired = 0;
+        let mut transitioned = 0;
 
-        info!("Deleted object: {}/{}", bucket, key);
-        Ok(())
+        for bucket in self.metadata.list_buckets().await? {
+            for rule in &bucket.lifecycle_rules {
+                let prefix = (!rule.prefix.is_empty()).then_some(rule.prefix.as_str());
+                let cutoff = rule
+                    .expiration_days
+                    .map(|days| now - chrono::Duration::days(i64::from(days)));
+
+                for object in self.list_objects(&bucket.name, prefix, None).await? {
+                    if cutoff.is_some_and(|cutoff| object.last_modified <= cutoff) {
+                        match self.delete_object(&bucket.name, &object.key).await {
+                            Ok(()) => expired += 1,
+                            Err(e) => warn!(
+                                "Lifecycle rule {} failed to delete {}/{}: {}",
+                                rule.id, bucket.name, object.key, e
+                            ),
+                        }
+                        continue;
+                    }
+
+                    let Some(class) = rule.transition_to else {
+                        continue;
+                    };
+                    let full_key = format!("{}/{}", bucket.name, object.key);
+                    match self.backend.set_storage_class(&full_key, class).await {
+                        Ok(()) => transitioned += 1,
+                        Err(e) => warn!(
+                            "Lifecycle rule {} failed to transition {}: {}",
+                            rule.id, full_key, e
+                        ),
+                    }
+                }
+            }
+        }
+
+        if expired > 0 {
+            info!("Lifecycle rules deleted {} expired objects", expired);
+        }
+        if transitioned > 0 {
+            debug!("Lifecycle rules transitioned {} objects", transitioned);
+        }
+
+        Ok(expired + transitioned)
+    }
+
+    async fn copy_backend_object(&self, from: &str, to: &str) -> ServiceResult<ObjectMetadata> {
+        Ok(self.backend.copy_object(from, to, None, None).await?)
     }
 
     pub async fn list_objects(
@@ -149,39 +1572,313 @@ impl ObjectStoreService {
         prefix: Option<&str>,
         max_keys: Option<usize>,
     ) -> ServiceResult<Vec<ObjectMetadata>> {
+        self.list_objects_with_options(bucket, &ListObjectsOptions::new(prefix, max_keys))
+            .await
+    }
+
+    pub async fn list_objects_with_options(
+        &self,
+        bucket: &str,
+        options: &ListObjectsOptions,
+    ) -> ServiceResult<Vec<ObjectMetadata>> {
+        Ok(self.list_objects_page(bucket, options).await?.objects)
+    }
+
+    /// Like [`list_objects_with_options`](Self::list_objects_with_options),
+    /// with the total count when `options.include_count` is set.
+    pub async fn list_objects_page(
+        &self,
+        bucket: &str,
+        options: &ListObjectsOptions,
+    ) -> ServiceResult<ObjectListing> {
+        let bucket = self.metadata.normalize_bucket_name(bucket);
+        let bucket = bucket.as_ref();
         self.metadata.get_bucket(bucket).await?;
+        let sort_by = options.sort_field()?;
 
-        let full_prefix = if let Some(p) = prefix {
+        let full_prefix = if let Some(p) = options.prefix.as_deref() {
             format!("{}/{}", bucket, p)
         } else {
             format!("{}/", bucket)
         };
 
-        let objects = self
-            .backend
-            .list_objects(Some(&full_prefix), max_keys)
-            .await?;
+        let backend_max_keys = if options.needs_full_listing() {
+            None
+        } else {
+            options.max_keys
+        };
+
+        let objects = if options.summary_only {
+            self.backend
+                .list_objects_metadata_only(Some(&full_prefix), backend_max_keys)
+                .await?
+                .into_iter()
+                .map(|summary| ObjectMetadata {
+                    key: summary.key,
+                    size: summary.size,
+                    content_type: None,
+                    etag: summary.etag,
+                    last_modified: summary.last_modified,
+                    custom_metadata: HashMap::new(),
+                })
+                .collect()
+        } else {
+            self.backend
+                .list_objects(Some(&full_prefix), backend_max_keys)
+                .await?
+        };
 
         let bucket_prefix = format!("{}/", bucket);
-        let filtered: Vec<ObjectMetadata> = objects
+        let mut filtered: Vec<ObjectMetadata> = objects
             .into_iter()
             .filter(|obj| !obj.key.ends_with("/.bucket"))
             .map(|mut obj| {
                 if let Some(stripped) = obj.key.strip_prefix(&bucket_prefix) {
                     obj.key = stripped.to_string();
                 }
+                resolve_dedup_pointer(&mut obj);
                 obj
             })
             .collect();
 
+        // Filtered counts come from the full listing, so the cursor has to wait
+        // until they are taken
+        let count_filtered = options.include_count && options.has_filter();
+        let mut total_count = None;
+        if options.include_count && !options.has_filter() {
+            total_count = Some(self.count_objects(bucket, &full_prefix).await?);
+        }
+        if let Some(start_after) = options.start_after.as_deref().filter(|_| !count_filtered) {
+            filtered.retain(|obj| obj.key.as_str() > start_after);
+        }
+
+        // No backend can list by modification time (S3 has no such filter and
+        // `start-after` works on keys), so dates are always filtered here.
+        if options.has_date_filter() {
+            if options.prefix.is_none() && filtered.len() > self.listing.date_filter_warn_threshold
+            {
+                warn!(
+                    "Date filter on bucket {} without a prefix scans {} objects",
+                    bucket,
+                    filtered.len()
+                );
+            }
+            filtered.retain(|obj| options.matches_dates(obj));
+        }
+
+        if let Some(filter) = &options.metadata_filter {
+            if options.prefix.is_none() && filtered.len() > METADATA_FILTER_WARN_THRESHOLD {
+                warn!(
+                    "Metadata filter on bucket {} without a prefix fetches metadata for {} objects",
+                    bucket,
+                    filtered.len()
+                );
+            }
+            filtered = filter_by_metadata(self.backend.as_ref(), bucket, filtered, filter).await?;
+        }
+
+        if count_filtered {
+            total_count = Some(filtered.len() as u64);
+            if let Some(start_after) = options.start_after.as_deref() {
+                filtered.retain(|obj| obj.key.as_str() > start_after);
+            }
+        }
+
+        if let Some(field) = sort_by {
+            sort_objects(&mut filtered, field, options.sort_order.unwrap_or_default());
+        }
+
+        if options.needs_full_listing() {
+            if let Some(max) = options.max_keys {
+                filtered.truncate(max);
+            }
+        }
+
         debug!("Listed {} objects in bucket: {}", filtered.len(), bucket);
-        Ok(filtered)
+        Ok(ObjectListing {
+            objects: filtered,
+            total_count,
+        })
+    }
+
+    async fn count_objects(&self, bucket: &str, full_prefix: &str) -> ServiceResult<u64> {
+        if let Some(count) = self.metadata.cached_object_count(bucket, full_prefix) {
+            return Ok(count);
+        }
+
+        let mut count = self.backend.count_objects(Some(full_prefix)).await?;
+        // The bucket marker sits under the bucket's prefix but is never listed
+        let marker = format!("{}/.bucket", bucket);
+        if marker.starts_with(full_prefix) && self.backend.object_exists(&marker).await? {
+            count = count.saturating_sub(1);
+        }
+
+        self.metadata.cache_object_count(bucket, full_prefix, count);
+        Ok(count)
+    }
+
+    /// Lists objects as a stream so callers can serialize them one at a time
+    /// instead of building the whole response body. Listings in key order are
+    /// read from the backend a page at a time with
+    /// [`Backend::list_objects_after`], each page filtered and yielded as it
+    /// arrives; other sort orders have to see every object first, so those are
+    /// listed in full before the stream starts.
+    pub async fn list_objects_stream(
+        &self,
+        bucket: &str,
+        options: &ListObjectsOptions,
+    ) -> ServiceResult<BoxStream<'static, ServiceResult<ObjectMetadata>>> {
+        if !matches!(
+            (options.sort_field()?, options.sort_order),
+            (None | Some(SortField::Key), None | Some(SortOrder::Asc))
+        ) {
+            let objects = self.list_objects_with_options(bucket, options).await?;
+            return Ok(futures::stream::iter(objects.into_iter().map(Ok)).boxed());
+        }
+
+        let bucket = self.metadata.normalize_bucket_name(bucket).into_owned();
+        self.metadata.get_bucket(&bucket).await?;
+
+        let bucket_prefix = format!("{}/", bucket);
+        let full_prefix = format!(
+            "{}{}",
+            bucket_prefix,
+            options.prefix.as_deref().unwrap_or("")
+        );
+        let start_after = options
+            .start_after
+            .as_deref()
+            .map(|key| format!("{}{}", bucket_prefix, key));
+        let max_keys = options.max_keys.unwrap_or(usize::MAX);
+        let backend = self.backend.clone();
+        let options = options.clone();
+
+        let pages = futures::stream::try_unfold(Some(start_after), {
+            let bucket = bucket.clone();
+            move |cursor| {
+                let backend = backend.clone();
+                let bucket = bucket.clone();
+                let bucket_prefix = bucket_prefix.clone();
+                let full_prefix = full_prefix.clone();
+                let options = options.clone();
+                async move {
+                    let Some(cursor) = cursor else {
+                        return Ok(None);
+                    };
+                    let page = backend
+                        .list_objects_after(
+                            Some(&full_prefix),
+                            cursor.as_deref(),
+                            LIST_STREAM_PAGE_SIZE,
+                        )
+                        .await?;
+                    // A short page is the end of the listing
+                    let next = (page.len() == LIST_STREAM_PAGE_SIZE)
+                        .then(|| page.last().map(|obj| obj.key.clone()));
+
+                    let mut objects: Vec<ObjectMetadata> = page
+                        .into_iter()
+                        .filter(|obj| !obj.key.ends_with("/.bucket"))
+                        .map(|mut obj| {
+                            if let Some(stripped) = obj.key.strip_prefix(&bucket_prefix) {
+                                obj.key = stripped.to_string();
+                            }
+                            resolve_dedup_pointer(&mut obj);
+                            obj
+                        })
+                        .filter(|obj| options.matches_dates(obj))
+                        .collect();
+                    if let Some(filter) = &options.metadata_filter {
+                        objects =
+                            filter_by_metadata(backend.as_ref(), &bucket, objects, filter).await?;
+                    }
+                    Ok::<_, ServiceError>(Some((objects, next)))
+                }
+            }
+        });
+
+        let objects = pages
+            .map_ok(|objects| futures::stream::iter(objects.into_iter().map(Ok)))
+            .try_flatten()
+            .inspect_err(move |e| warn!("Streamed listing of {} failed: {}", bucket, e))
+            .take(max_keys);
+        Ok(objects.boxed())
+    }
+
+    pub async fn scan_bucket(&self, bucket: &str) -> ServiceResult<BucketStats> {
+        let objects = self.list_objects(bucket, None, None).await?;
+        Ok(BucketStats {
+            object_count: objects.len() as u64,
+            total_bytes: objects.iter().map(|obj| obj.size).sum(),
+        })
+    }
+
+    pub async fn compact_backend(&self, dry_run: bool) -> ServiceResult<CompactReport> {
+        Ok(self.backend.compact(dry_run).await?)
+    }
+
+    /// Probes the backend, then writes and deletes a `.health-check-<uuid>`
+    /// canary in the first bucket, or under `__health__` before any bucket
+    /// exists. Fails with [`ServiceError::Unavailable`] when the backend can't
+    /// be reached. A throttling backend is up but not taking writes, so it is
+    /// reported as not writable without trying one.
+    pub async fn health_check(&self) -> ServiceResult<BackendHealthStatus> {
+        let started = std::time::Instant::now();
+        let writable = match self.backend.health_check().await {
+            Ok(()) => self.write_health_canary().await,
+            Err(BackendError::RateLimited { .. } | BackendError::RequestLimitExceeded) => false,
+            Err(e) => {
+                warn!("Backend health check failed: {}", e);
+                return Err(ServiceError::Unavailable(e.to_string()));
+            }
+        };
+
+        Ok(BackendHealthStatus {
+            backend_type: self.backend.backend_type().to_string(),
+            latency_ms: started.elapsed().as_millis() as u64,
+            writable,
+        })
+    }
+
+    async fn write_health_canary(&self) -> bool {
+        let bucket = match self.metadata.list_buckets().await {
+            Ok(buckets) => buckets.into_iter().next().map(|b| b.name),
+            Err(e) => {
+                warn!("Health check could not list buckets: {}", e);
+                None
+            }
+        };
+        let key = format!(
+            "{}/.health-check-{}",
+            bucket.as_deref().unwrap_or(HEALTH_CHECK_BUCKET),
+            uuid::Uuid::new_v4()
+        );
+
+        let result = async {
+            let stream: ByteStream = Box::pin(futures::stream::once(async {
+                Ok(Bytes::from_static(b"ok"))
+            }));
+            self.backend
+                .put_object(&key, stream, None, HashMap::new())
+                .await?;
+            self.backend.delete_object(&key).await
+        }
+        .await;
+        if let Some(bucket) = &bucket {
+            self.metadata.invalidate_object_counts(bucket);
+        }
+        if let Err(e) = &result {
+            warn!("Health check canary write to {} failed: {}", key, e);
+        }
+        result.is_ok()
     }
 
     pub async fn object_exists(&self, bucket: &str, key: &str) -> ServiceResult<bool> {
+        let bucket = self.metadata.normalize_bucket_name(bucket);
+        let bucket = bucket.as_ref();
         self.metadata.get_bucket(bucket).await?;
 
-        validate_object_key(key)?;
+        validate_object_key(key, &self.validation)?;
 
         let full_key = format!("{}/{}", bucket, key);
 
@@ -194,35 +1891,393 @@ impl ObjectStoreService {
         self.metadata.clone()
     }
 
+    pub fn metrics(&self) -> &ServiceMetrics {
+        &self.metrics
+    }
+
+    /// Responses stored for requests sent with an `Idempotency-Key`.
+    pub fn idempotency(&self) -> &IdempotencyCache {
+        &self.idempotency
+    }
+
+    /// Recomputes every bucket's object count and size for the metrics gauges.
+    /// This lists each bucket in full, so it runs on a timer rather than per scrape.
+    pub async fn refresh_bucket_metrics(&self) -> ServiceResult<()> {
+        let buckets = self.list_buckets(&ListBucketsOptions::default()).await?;
+        let mut stats = Vec::with_capacity(buckets.len());
+        for bucket in buckets {
+            match self.scan_bucket(&bucket.name).await {
+                Ok(bucket_stats) => stats.push((bucket.name, bucket_stats)),
+                // Deleted since it was listed
+                Err(ServiceError::BucketNotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.metrics.record_bucket_stats(&stats);
+        Ok(())
+    }
+
     pub async fn get_public_url(
         &self,
         bucket: &str,
         key: &str,
         expiration_secs: u64,
         purpose: PublicUrlPurpose,
+        content_type: Option<&str>,
     ) -> ServiceResult<String> {
-        self.metadata.get_bucket(bucket).await?;
+        let bucket = self.metadata.normalize_bucket_name(bucket);
+        let bucket = bucket.as_ref();
+        let bucket_meta = self.metadata.get_bucket(bucket).await?;
 
-        validate_object_key(key)?;
+        validate_object_key(key, &self.validation)?;
 
-        let full_key = format!("{}/{}", bucket, key);
+        if read_revocation(&*self.backend, &object_revocation_key(bucket, key))
+            .await?
+            .is_some_and(|revoked| revoked.is_live(Utc::now()))
+        {
+            return Err(ServiceError::UrlRevoked(format!(
+                "A URL for {}/{} was revoked until it expires",
+                bucket, key
+            )));
+        }
+
+        let mut full_key = format!("{}/{}", bucket, key);
+        if bucket_meta.deduplication_enabled {
+            // Uploads through the URL would bypass the content hash index
+            if matches!(purpose, PublicUrlPurpose::Upload) {
+                return Err(ServiceError::InvalidRequest(format!(
+                    "Bucket {} deduplicates uploads, which presigned upload URLs can't do",
+                    bucket
+                )));
+            }
+            if let Some(sha256) = self.metadata.content_hash_of(&full_key).await? {
+                if let Some(canonical) = self.metadata.find_by_hash(&sha256).await? {
+                    full_key = canonical;
+                }
+            }
+        }
 
         let url = self
             .backend
-            .get_public_url(&full_key, expiration_secs, purpose)
+            .get_public_url(&full_key, expiration_secs, purpose, content_type)
             .await?;
 
         Ok(url)
     }
+
+    /// Revokes a presigned URL for an object in `bucket` until it would have
+    /// expired. Until then no new URLs are issued for that object either.
+    pub async fn revoke_url(&self, bucket: &str, url: &str) -> ServiceResult<RevokedUrl> {
+        let bucket = self.metadata.normalize_bucket_name(bucket);
+        let bucket = bucket.as_ref();
+        self.metadata.get_bucket(bucket).await?;
+
+        let parsed = parse_url(url)
+            .ok_or_else(|| ServiceError::InvalidRequest(format!("Invalid URL: {}", url)))?;
+        let path = decoded_path(&parsed);
+        let Some(key) = object_key(&path, bucket).map(str::to_string) else {
+            return Err(ServiceError::InvalidRequest(format!(
+                "URL does not point at an object in bucket {}",
+                bucket
+            )));
+        };
+
+        let revoked = RevokedUrl {
+            bucket: bucket.to_string(),
+            path,
+            expires_at: url_expiry(&parsed, Utc::now()),
+        };
+        write_revocation(&*self.backend, &revocation_key(&parsed), &revoked).await?;
+
+        // Concurrent revocations must not replace a longer one with a shorter one
+        let object_key = object_revocation_key(bucket, &key);
+        let _guard = self.uploads.lock(&object_key).await;
+        let outlasted = read_revocation(&*self.backend, &object_key)
+            .await?
+            .is_some_and(|existing| existing.expires_at >= revoked.expires_at);
+        if !outlasted {
+            write_revocation(&*self.backend, &object_key, &revoked).await?;
+        }
+
+        info!("Revoked a presigned URL for bucket {}", bucket);
+        Ok(revoked)
+    }
+
+    /// Whether `url`, as requested, was revoked and has not expired yet.
+    pub async fn is_url_revoked(&self, url: &str) -> ServiceResult<bool> {
+        let Some(parsed) = parse_url(url) else {
+            return Ok(false);
+        };
+        Ok(read_revocation(&*self.backend, &revocation_key(&parsed))
+            .await?
+            .is_some_and(|revoked| revoked.is_live(Utc::now())))
+    }
+
+    /// Drops revocations of URLs that have expired on their own, so the list
+    /// stays bounded.
+    pub async fn purge_expired_revocations(&self, now: DateTime<Utc>) -> ServiceResult<u64> {
+        let entries = match self
+            .backend
+            .list_objects(Some(REVOKED_URLS_PREFIX), None)
+            .await
+        {
+            Ok(entries) => entries,
+            Err(BackendError::NotFound(_)) => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut purged = 0;
+        for entry in entries {
+            let expired = match read_revocation(&*self.backend, &entry.key).await {
+                Ok(Some(revoked)) => !revoked.is_live(now),
+                Ok(None) => false,
+                Err(e) => {
+                    warn!("Unreadable URL revocation {}: {}", entry.key, e);
+                    false
+                }
+            };
+            if expired {
+                self.backend.delete_object(&entry.key).await?;
+                purged += 1;
+            }
+        }
+
+        if purged > 0 {
+            info!("Purged {} expired URL revocations", purged);
+        }
+        Ok(purged)
+    }
+
+    pub fn requires_api_key(&self) -> bool {
+        self.auth.require_api_key
+    }
+
+    /// Creates an API key. The raw key is returned only here; afterwards the
+    /// key is known by its hash alone.
+    pub async fn create_api_key(
+        &self,
+        label: &str,
+        permissions: Vec<Permission>,
+        rate_limit: Option<RateLimitConfig>,
+    ) -> ServiceResult<(String, String, ApiKeyRecord)> {
+        if label.is_empty() {
+            return Err(ServiceError::InvalidRequest(
+                "API key label must not be empty".to_string(),
+            ));
+        }
+        if permissions.is_empty() {
+            return Err(ServiceError::InvalidRequest(
+                "API key needs at least one permission".to_string(),
+            ));
+        }
+
+        let key = generate_key();
+        let hash = hash_key(&key);
+        let record = ApiKeyRecord {
+            label: label.to_string(),
+            created_at: Utc::now(),
+            last_used_at: None,
+            permissions,
+            rate_limit,
+        };
+        self.metadata.save_api_key(&hash, &record).await?;
+
+        info!("Created API key '{}'", label);
+        Ok((key, hash, record))
+    }
+
+    pub async fn list_api_keys(&self) -> ServiceResult<Vec<(String, ApiKeyRecord)>> {
+        self.metadata.list_api_keys().await
+    }
+
+    pub async fn revoke_api_key(&self, hash: &str) -> ServiceResult<()> {
+        if !is_valid_key_hash(hash) {
+            return Err(ServiceError::InvalidRequest(format!(
+                "Invalid API key hash: {}",
+                hash
+            )));
+        }
+        if !self.metadata.delete_api_key(hash).await? {
+            return Err(ServiceError::ApiKeyNotFound(hash.to_string()));
+        }
+        self.api_keys.invalidate(hash).await;
+
+        info!("Revoked API key {}", hash);
+        Ok(())
+    }
+
+    pub async fn authenticate(
+        &self,
+        key: &str,
+        operation: OperationType,
+        resource: &str,
+    ) -> ServiceResult<ApiKeyRecord> {
+        self.api_keys.authenticate(key, operation, resource).await
+    }
+
+    /// The record of a valid API key, without checking its permissions.
+    pub async fn api_key(&self, key: &str) -> ServiceResult<ApiKeyRecord> {
+        self.api_keys.lookup(key).await
+    }
+
+    /// Like [`api_key`](Self::api_key), but only answers from the cache of
+    /// recently validated keys.
+    pub async fn cached_api_key(&self, key: &str) -> Option<ApiKeyRecord> {
+        self.api_keys.lookup_cached(key).await
+    }
+}
+
+/// Reports a missing object as [`ServiceError::ObjectNotFound`], named by
+/// bucket and key rather than the backend's key. Other errors pass through.
+fn convert_backend_not_found(err: BackendError, bucket: &str, key: &str) -> ServiceError {
+    match err.root() {
+        BackendError::NotFound(_) => ServiceError::ObjectNotFound(format!("{}/{}", bucket, key)),
+        _ => ServiceError::Backend(err),
+    }
+}
+
+// Names are unique, so breaking creation-time ties on them keeps the order
+// stable between calls. Timestamps are parsed because RFC 3339 strings with
+// different fractional-second precision don't compare correctly as text.
+fn sort_buckets(buckets: &mut [Bucket], field: BucketSortField, order: SortOrder) {
+    let created = |b: &Bucket| DateTime::parse_from_rfc3339(&b.created_at).ok();
+    buckets.sort_by(|a, b| {
+        let ordering = match field {
+            BucketSortField::Name => a.name.cmp(&b.name),
+            BucketSortField::CreatedAt => created(a)
+                .cmp(&created(b))
+                .then_with(|| a.name.cmp(&b.name)),
+        };
+        match order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    });
+}
+
+fn sort_objects(objects: &mut [ObjectMetadata], field: SortField, order: SortOrder) {
+    // Keys are unique, and ties on size or time have no meaningful order, so an
+    // unstable sort avoids the extra allocation of a stable one.
+    objects.sort_unstable_by(|a, b| {
+        let ordering = match field {
+            SortField::Key => a.key.cmp(&b.key),
+            SortField::Size => a.size.cmp(&b.size),
+            SortField::LastModified => a.last_modified.cmp(&b.last_modified),
+        };
+        match order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    });
+}
+
+// Listings don't reliably include custom metadata (S3 omits it), so each
+// candidate is re-read with head_object before the filter is applied.
+async fn filter_by_metadata(
+    backend: &dyn Backend,
+    bucket: &str,
+    objects: Vec<ObjectMetadata>,
+    filter: &MetadataFilter,
+) -> ServiceResult<Vec<ObjectMetadata>> {
+    let mut heads = futures::stream::iter(objects)
+        .map(|obj| async move {
+            let full_key = format!("{}/{}", bucket, obj.key);
+            match backend.head_object(&full_key).await {
+                Ok(mut head) => {
+                    head.key = obj.key;
+                    resolve_dedup_pointer(&mut head);
+                    Ok(Some(head))
+                }
+                // Deleted between the listing and the head request
+                Err(BackendError::NotFound(_)) => Ok(None),
+                Err(e) => Err(ServiceError::Backend(e)),
+            }
+        })
+        .buffered(METADATA_FILTER_CONCURRENCY);
+
+    let mut matched = Vec::new();
+    while let Some(head) = heads.next().await {
+        if let Some(head) = head? {
+            if filter.matches(&head.custom_metadata) {
+                matched.push(head);
+            }
+        }
+    }
+
+    Ok(matched)
+}
+
+/// Shows a deduplication pointer as the object it stands for, with the size
+/// and ETag of its content and without the bookkeeping metadata. Returns the
+/// content hash for pointers and `None` for other objects.
+fn resolve_dedup_pointer(metadata: &mut ObjectMetadata) -> Option<String> {
+    let sha256 = metadata.custom_metadata.remove(DEDUP_SHA256_KEY)?;
+    if let Some(size) = metadata
+        .custom_metadata
+        .remove(DEDUP_SIZE_KEY)
+        .and_then(|size| size.parse().ok())
+    {
+        metadata.size = size;
+    }
+    if let Some(etag) = metadata.custom_metadata.remove(DEDUP_ETAG_KEY) {
+        metadata.etag = etag;
+    }
+    Some(sha256)
+}
+
+fn recycle_bin_key(bucket: &str, key: &str, version: &str) -> String {
+    format!("{}/{}/{}#{}", RECYCLE_BIN_PREFIX, bucket, key, version)
+}
+
+fn validate_lifecycle_rules(rules: &[LifecycleRule]) -> ServiceResult<()> {
+    let mut ids = std::collections::HashSet::new();
+    for rule in rules {
+        if rule.id.is_empty() {
+            return Err(ServiceError::InvalidRequest(
+                "Lifecycle rule id cannot be empty".to_string(),
+            ));
+        }
+        if !ids.insert(rule.id.as_str()) {
+            return Err(ServiceError::InvalidRequest(format!(
+                "Duplicate lifecycle rule id: {}",
+                rule.id
+            )));
+        }
+        if rule.expiration_days.is_none() && rule.transition_to.is_none() {
+            return Err(ServiceError::InvalidRequest(format!(
+                "Lifecycle rule {} needs expiration_days or transition_to",
+                rule.id
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn validate_recycle_bin_version(version: &str) -> ServiceResult<()> {
+    if version.is_empty() || !version.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(ServiceError::InvalidRequest(format!(
+            "Invalid recycle bin version: {}",
+            version
+        )));
+    }
+    Ok(())
 }
 
-fn validate_object_key(key: &str) -> ServiceResult<()> {
+fn validate_object_key(key: &str, config: &ValidationConfig) -> ServiceResult<()> {
     if key.is_empty() {
         return Err(ServiceError::InvalidObjectKey(
             "Key cannot be empty".to_string(),
         ));
     }
 
+    if key.len() > config.max_key_bytes {
+        return Err(ServiceError::InvalidObjectKey(format!(
+            "Key is {} bytes, at most {} are allowed",
+            key.len(),
+            config.max_key_bytes
+        )));
+    }
+
     if key.contains("..") || key.starts_with('/') {
         return Err(ServiceError::InvalidObjectKey(format!(
             "Invalid key: {}",
@@ -245,11 +2300,80 @@ mod tests {
 
     #[test]
     fn test_validate_object_key() {
-        assert!(validate_object_key("valid/key.txt").is_ok());
-        assert!(validate_object_key("another-valid-key").is_ok());
-        assert!(validate_object_key("").is_err());
-        assert!(validate_object_key("../etc/passwd").is_err());
-        assert!(validate_object_key("/etc/passwd").is_err());
-        assert!(validate_object_key(".bucket").is_err());
+        let config = ValidationConfig::default();
+        assert!(validate_object_key("valid/key.txt", &config).is_ok());
+        assert!(validate_object_key("another-valid-key", &config).is_ok());
+        assert!(validate_object_key("", &config).is_err());
+        assert!(validate_object_key("../etc/passwd", &config).is_err());
+        assert!(validate_object_key("/etc/passwd", &config).is_err());
+        assert!(validate_object_key(".bucket", &config).is_err());
+    }
+
+    #[test]
+    fn test_validate_object_key_length() {
+        let config = ValidationConfig::default();
+        let key = "k".repeat(1024);
+        assert!(validate_object_key(&key, &config).is_ok());
+        assert!(matches!(
+            validate_object_key(&format!("{}k", key), &config),
+            Err(ServiceError::InvalidObjectKey(_))
+        ));
+
+        // 'é' is two bytes: 512 of them fill the limit in half as many characters
+        let accents = "é".repeat(512);
+        assert_eq!(accents.len(), 1024);
+        assert!(validate_object_key(&accents, &config).is_ok());
+        let over = "é".repeat(513);
+        assert_eq!(over.chars().count(), 513);
+        assert!(validate_object_key(&over, &config).is_err());
+
+        // Three-byte characters: 342 of them are 1026 bytes
+        assert!(validate_object_key(&"€".repeat(341), &config).is_ok());
+        assert!(validate_object_key(&"€".repeat(342), &config).is_err());
+
+        let config = ValidationConfig {
+            max_key_bytes: 8,
+            ..ValidationConfig::default()
+        };
+        assert!(validate_object_key("12345678", &config).is_ok());
+        assert!(validate_object_key("123456789", &config).is_err());
+    }
+
+    fn object(key: &str, size: u64) -> ObjectMetadata {
+        ObjectMetadata {
+            key: key.to_string(),
+            size,
+            content_type: None,
+            etag: String::new(),
+            last_modified: chrono::Utc::now(),
+            custom_metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_metadata_filter() {
+        let filter: MetadataFilter = "env=prod".parse().unwrap();
+        assert_eq!(filter.key, "env");
+        assert_eq!(filter.value, "prod");
+
+        // Only the first '=' separates key from value
+        let filter: MetadataFilter = "query=a=b".parse().unwrap();
+        assert_eq!(filter.value, "a=b");
+
+        assert!("env".parse::<MetadataFilter>().is_err());
+        assert!("=prod".parse::<MetadataFilter>().is_err());
+    }
+
+    #[test]
+    fn test_sort_objects() {
+        let mut objects = vec![object("b", 3), object("a", 1), object("c", 2)];
+
+        sort_objects(&mut objects, SortField::Size, SortOrder::Desc);
+        let keys: Vec<_> = objects.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(keys, vec!["b", "c", "a"]);
+
+        sort_objects(&mut objects, SortField::Key, SortOrder::Asc);
+        let keys: Vec<_> = objects.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(keys, vec!["a", "b", "c"]);
     }
 }
@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::error::ServiceError;
+
+pub const MAX_METADATA_KEY_BYTES: usize = 128;
+pub const MAX_METADATA_VALUE_BYTES: usize = 2048;
+pub const MAX_METADATA_PAIRS: usize = 50;
+
+/// Length limits on names, checked before anything reaches the backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationConfig {
+    /// Longest object key in UTF-8 bytes. S3 allows 1024.
+    #[serde(default = "default_max_key_bytes")]
+    pub max_key_bytes: usize,
+    /// Longest bucket name in characters. Bucket names also have to follow the
+    /// S3 naming rules, so raising this above 63 has no effect.
+    #[serde(default = "default_max_bucket_name_chars")]
+    pub max_bucket_name_chars: usize,
+}
+
+fn default_max_key_bytes() -> usize {
+    1024
+}
+
+fn default_max_bucket_name_chars() -> usize {
+    63
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            max_key_bytes: default_max_key_bytes(),
+            max_bucket_name_chars: default_max_bucket_name_chars(),
+        }
+    }
+}
+
+/// Checks custom object metadata: keys are `[a-z0-9-]+` of at most
+/// `MAX_METADATA_KEY_BYTES`, values at most `MAX_METADATA_VALUE_BYTES`, and no
+/// more than `MAX_METADATA_PAIRS` pairs. Returns every violation found.
+pub fn validate_metadata(metadata: &HashMap<String, String>) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+
+    if metadata.len() > MAX_METADATA_PAIRS {
+        errors.push(format!(
+            "{} metadata pairs given, at most {} are allowed",
+            metadata.len(),
+            MAX_METADATA_PAIRS
+        ));
+    }
+
+    let mut keys: Vec<&String> = metadata.keys().collect();
+    keys.sort();
+    for key in keys {
+        if key.is_empty()
+            || !key
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        {
+            errors.push(format!(
+                "metadata key '{}' may only contain a-z, 0-9 and '-'",
+                key
+            ));
+        }
+        if key.len() > MAX_METADATA_KEY_BYTES {
+            errors.push(format!(
+                "metadata key '{}' is {} bytes, at most {} are allowed",
+                key,
+                key.len(),
+                MAX_METADATA_KEY_BYTES
+            ));
+        }
+        let value = &metadata[key];
+        if value.len() > MAX_METADATA_VALUE_BYTES {
+            errors.push(format!(
+                "metadata value for '{}' is {} bytes, at most {} are allowed",
+                key,
+                value.len(),
+                MAX_METADATA_VALUE_BYTES
+            ));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Whether `content_type` is a well-formed MIME type: `type/subtype` with
+/// optional `; name=value` parameters, e.g. `application/json; charset=utf-8`.
+pub fn validate_content_type(content_type: &str) -> bool {
+    // The parser accepts an empty subtype, as in `text/`
+    content_type
+        .parse::<mime::Mime>()
+        .is_ok_and(|mime| !mime.subtype().as_str().is_empty())
+}
+
+pub(crate) fn metadata_error(errors: Vec<String>) -> ServiceError {
+    ServiceError::InvalidRequest(format!("Invalid metadata: {}", errors.join("; ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single(key: &str, value: &str) -> HashMap<String, String> {
+        HashMap::from([(key.to_string(), value.to_string())])
+    }
+
+    #[test]
+    fn test_content_types() {
+        for valid in [
+            "text/plain",
+            "application/json; charset=utf-8",
+            "application/vnd.api+json",
+            "multipart/form-data; boundary=abc123",
+        ] {
+            assert!(validate_content_type(valid), "{:?}", valid);
+        }
+        for invalid in ["", "foo", "not/valid/mime", "text/", "/plain", "text plain"] {
+            assert!(!validate_content_type(invalid), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_metadata_key_characters() {
+        assert!(validate_metadata(&single("content-owner-2", "x")).is_ok());
+        for key in ["Owner", "owner_id", "owner.id", "owner id", ""] {
+            assert!(validate_metadata(&single(key, "x")).is_err(), "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn test_metadata_size_limits() {
+        let key = "k".repeat(MAX_METADATA_KEY_BYTES);
+        assert!(validate_metadata(&single(&key, "x")).is_ok());
+        assert!(validate_metadata(&single(&format!("{}k", key), "x")).is_err());
+
+        let value = "v".repeat(MAX_METADATA_VALUE_BYTES);
+        assert!(validate_metadata(&single("key", &value)).is_ok());
+        assert!(validate_metadata(&single("key", &format!("{}v", value))).is_err());
+    }
+
+    #[test]
+    fn test_metadata_pair_limit() {
+        let mut metadata: HashMap<String, String> = (0..MAX_METADATA_PAIRS)
+            .map(|i| (format!("key-{}", i), "x".to_string()))
+            .collect();
+        assert!(validate_metadata(&metadata).is_ok());
+
+        metadata.insert("one-more".to_string(), "x".to_string());
+        assert_eq!(validate_metadata(&metadata).unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn test_metadata_reports_every_violation() {
+        let metadata = HashMap::from([
+            ("Bad_Key".to_string(), "x".to_string()),
+            ("good".to_string(), "v".repeat(MAX_METADATA_VALUE_BYTES + 1)),
+        ]);
+        assert_eq!(validate_metadata(&metadata).unwrap_err().len(), 2);
+    }
+}
@@ -0,0 +1,229 @@
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use object_store_backends::{Backend, BackendError, ByteStream};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::error::{ServiceError, ServiceResult};
+use crate::metadata::Bucket;
+
+/// Bucket record writes that have started, one JSON entry per line. Backends
+/// can't append, so each entry rewrites the whole file; it is deleted once
+/// every operation in it has committed, which keeps it to a few lines.
+pub const WAL_KEY: &str = ".metadata/wal/pending.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WalOp {
+    CreateBucket,
+    DeleteBucket,
+    UpdateBucket,
+    /// Closes the entry with the same `id` once its write has finished.
+    Committed,
+    /// Closes the entry with the same `id` after its write failed. The failure
+    /// was reported to the caller, so recovery leaves it alone.
+    Aborted,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalEntry {
+    /// Shared by a write's entry and the one closing it. Empty in logs written
+    /// before ids were added, where a close matches the oldest open entry of
+    /// its bucket.
+    #[serde(default)]
+    pub id: String,
+    pub op: WalOp,
+    pub bucket_name: String,
+    pub timestamp: DateTime<Utc>,
+    /// The record being written by a create or update, so it can be written
+    /// again on recovery.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bucket: Option<Bucket>,
+}
+
+impl WalEntry {
+    pub fn new(id: &str, op: WalOp, bucket_name: &str, bucket: Option<&Bucket>) -> Self {
+        Self {
+            id: id.to_string(),
+            op,
+            bucket_name: bucket_name.to_string(),
+            timestamp: Utc::now(),
+            bucket: bucket.cloned(),
+        }
+    }
+}
+
+pub struct WriteAheadLog {
+    backend: Arc<dyn Backend>,
+    /// Appends read and rewrite the file, so they take turns.
+    lock: Mutex<()>,
+}
+
+impl WriteAheadLog {
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        Self {
+            backend,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Records that `op` on `bucket_name` is about to be written. Returns the
+    /// entry's id, to [`commit`](Self::commit) or [`abort`](Self::abort) it by.
+    pub async fn begin(
+        &self,
+        op: WalOp,
+        bucket_name: &str,
+        bucket: Option<&Bucket>,
+    ) -> ServiceResult<String> {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.append(WalEntry::new(&id, op, bucket_name, bucket))
+            .await?;
+        Ok(id)
+    }
+
+    /// Records that the write begun as `id` has finished.
+    pub async fn commit(&self, id: &str, bucket_name: &str) -> ServiceResult<()> {
+        self.append(WalEntry::new(id, WalOp::Committed, bucket_name, None))
+            .await
+    }
+
+    /// Records that the write begun as `id` failed, so it isn't replayed.
+    pub async fn abort(&self, id: &str, bucket_name: &str) -> ServiceResult<()> {
+        self.append(WalEntry::new(id, WalOp::Aborted, bucket_name, None))
+            .await
+    }
+
+    /// Commits or aborts `id` as `result` says.
+    pub async fn finish<T>(
+        &self,
+        id: &str,
+        bucket_name: &str,
+        result: &ServiceResult<T>,
+    ) -> ServiceResult<()> {
+        match result {
+            Ok(_) => self.commit(id, bucket_name).await,
+            Err(_) => self.abort(id, bucket_name).await,
+        }
+    }
+
+    async fn append(&self, entry: WalEntry) -> ServiceResult<()> {
+        let _guard = self.lock.lock().await;
+        let mut entries = self.read().await?;
+        entries.push(entry);
+        if uncommitted(entries.clone()).is_empty() {
+            return self.remove().await;
+        }
+        self.write(&entries).await
+    }
+
+    /// Entries begun without a matching commit, oldest first.
+    pub async fn uncommitted(&self) -> ServiceResult<Vec<WalEntry>> {
+        let _guard = self.lock.lock().await;
+        Ok(uncommitted(self.read().await?))
+    }
+
+    /// Drops every entry, once recovery has dealt with them.
+    pub async fn clear(&self) -> ServiceResult<()> {
+        let _guard = self.lock.lock().await;
+        self.remove().await
+    }
+
+    async fn read(&self) -> ServiceResult<Vec<WalEntry>> {
+        let mut obj_data = match self.backend.get_object(WAL_KEY).await {
+            Ok(obj_data) => obj_data,
+            Err(BackendError::NotFound(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(ServiceError::Backend(e)),
+        };
+        let mut data = Vec::new();
+        while let Some(chunk) = obj_data.stream.next().await {
+            let chunk = chunk.map_err(|e| ServiceError::Internal(e.to_string()))?;
+            data.extend_from_slice(&chunk);
+        }
+
+        // A line torn by a crash is skipped; the write it described never began
+        let mut entries = Vec::new();
+        for line in data.split(|b| *b == b'\n').filter(|line| !line.is_empty()) {
+            match serde_json::from_slice(line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!("Skipping unreadable write-ahead log entry: {}", e),
+            }
+        }
+        Ok(entries)
+    }
+
+    async fn write(&self, entries: &[WalEntry]) -> ServiceResult<()> {
+        let mut data = Vec::new();
+        for entry in entries {
+            serde_json::to_writer(&mut data, entry)?;
+            data.push(b'\n');
+        }
+        let stream: ByteStream =
+            Box::pin(futures::stream::once(async move { Ok(Bytes::from(data)) }));
+        self.backend
+            .put_object(
+                WAL_KEY,
+                stream,
+                Some("application/x-ndjson".to_string()),
+                HashMap::new(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn remove(&self) -> ServiceResult<()> {
+        match self.backend.delete_object(WAL_KEY).await {
+            Ok(()) | Err(BackendError::NotFound(_)) => Ok(()),
+            Err(e) => Err(ServiceError::Backend(e)),
+        }
+    }
+}
+
+fn uncommitted(entries: Vec<WalEntry>) -> Vec<WalEntry> {
+    let mut open: Vec<WalEntry> = Vec::new();
+    for entry in entries {
+        if !matches!(entry.op, WalOp::Committed | WalOp::Aborted) {
+            open.push(entry);
+        } else if let Some(index) = open
+            .iter()
+            .position(|e| e.id == entry.id && e.bucket_name == entry.bucket_name)
+        {
+            open.remove(index);
+        }
+    }
+    open
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commits_and_aborts_close_their_own_entry() {
+        let entries = vec![
+            WalEntry::new("1", WalOp::CreateBucket, "a", None),
+            WalEntry::new("2", WalOp::UpdateBucket, "a", None),
+            WalEntry::new("3", WalOp::DeleteBucket, "b", None),
+            WalEntry::new("4", WalOp::CreateBucket, "c", None),
+            WalEntry::new("1", WalOp::Aborted, "a", None),
+            WalEntry::new("4", WalOp::Committed, "c", None),
+            // Nothing open with this id
+            WalEntry::new("5", WalOp::Committed, "a", None),
+        ];
+
+        let open: Vec<_> = uncommitted(entries)
+            .into_iter()
+            .map(|e| (e.op, e.bucket_name))
+            .collect();
+        assert_eq!(
+            open,
+            vec![
+                (WalOp::UpdateBucket, "a".to_string()),
+                (WalOp::DeleteBucket, "b".to_string()),
+            ]
+        );
+    }
+}
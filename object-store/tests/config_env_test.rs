@@ -0,0 +1,170 @@
+use object_store::config::{AzureAuth, BackendConfig, Config};
+use object_store_backends::s3::ChecksumAlgorithm;
+use std::path::PathBuf;
+
+const KEY_HEX: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+fn set_env(vars: &[(&str, &str)]) {
+    for (name, _) in std::env::vars() {
+        if name.starts_with("OBJECT_STORE__") {
+            std::env::remove_var(name);
+        }
+    }
+    for (name, value) in vars {
+        std::env::set_var(name, value);
+    }
+}
+
+fn configure(vars: &[(&str, &str)]) -> Config {
+    set_env(vars);
+    Config::from_env().unwrap()
+}
+
+// One test so the variables of one backend never leak into another.
+#[test]
+fn test_every_backend_from_env_alone() {
+    let config = configure(&[
+        ("OBJECT_STORE__SERVER__PORT", "9100"),
+        ("OBJECT_STORE__BACKEND__TYPE", "local"),
+        ("OBJECT_STORE__BACKEND__ROOT_PATH", "/var/lib/object-store"),
+        ("OBJECT_STORE__BACKEND__PHYSICAL_BUCKET", "2024"),
+        ("OBJECT_STORE__BACKEND__USE_XATTRS", "true"),
+        ("OBJECT_STORE__BACKEND__LIST_CACHE_TTL_SECS", "30"),
+        ("OBJECT_STORE__BACKEND__LIST_CACHE_CAPACITY", "64"),
+        ("OBJECT_STORE__BACKEND__METADATA_CACHE_CAPACITY", "10000"),
+        ("OBJECT_STORE__BACKEND__DIR_PERMISSIONS", "750"),
+        ("OBJECT_STORE__BACKEND__STRICT_INIT", "true"),
+        ("OBJECT_STORE__BACKEND__MIN_FREE_BYTES", "1073741824"),
+        ("OBJECT_STORE__BACKEND__ENCRYPTION__KEY_HEX", KEY_HEX),
+        ("OBJECT_STORE__METADATA__CACHE_TTL_SECS", "300"),
+    ]);
+    assert_eq!(config.server.port, 9100);
+    assert_eq!(config.metadata.cache_ttl_secs, 300);
+    match &config.backend {
+        BackendConfig::Local {
+            root_path,
+            physical_bucket,
+            use_xattrs,
+            list_cache_ttl_secs,
+            list_cache_capacity,
+            metadata_cache_capacity,
+            dir_permissions,
+            strict_init,
+            min_free_bytes,
+            encryption,
+        } => {
+            assert_eq!(root_path, &PathBuf::from("/var/lib/object-store"));
+            assert_eq!(physical_bucket, "2024");
+            assert!(use_xattrs);
+            assert_eq!(*list_cache_ttl_secs, 30);
+            assert_eq!(*list_cache_capacity, 64);
+            assert_eq!(*metadata_cache_capacity, 10_000);
+            assert_eq!(*dir_permissions, Some(0o750));
+            assert!(strict_init);
+            assert_eq!(*min_free_bytes, Some(1 << 30));
+            assert_eq!(encryption.as_ref().unwrap().key_hex, KEY_HEX);
+        }
+        other => panic!("expected local backend, got {:?}", other),
+    }
+
+    let config = configure(&[
+        ("OBJECT_STORE__BACKEND__TYPE", "local"),
+        ("OBJECT_STORE__BACKEND__ROOT_PATH", "./data"),
+    ]);
+    match &config.backend {
+        BackendConfig::Local {
+            physical_bucket,
+            use_xattrs,
+            dir_permissions,
+            strict_init,
+            min_free_bytes,
+            encryption,
+            ..
+        } => {
+            assert_eq!(physical_bucket, "object-store-data");
+            assert!(!use_xattrs);
+            assert_eq!(*dir_permissions, None);
+            assert!(!strict_init);
+            assert_eq!(*min_free_bytes, None);
+            assert!(encryption.is_none());
+        }
+        other => panic!("expected local backend, got {:?}", other),
+    }
+
+    let config = configure(&[
+        ("OBJECT_STORE__BACKEND__TYPE", "s3"),
+        ("OBJECT_STORE__BACKEND__REGION", "eu-central-1"),
+        ("OBJECT_STORE__BACKEND__PHYSICAL_BUCKET", "my-bucket"),
+        ("OBJECT_STORE__BACKEND__ENDPOINT", "http://localhost:9000"),
+        ("OBJECT_STORE__BACKEND__FORCE_PATH_STYLE", "true"),
+        ("OBJECT_STORE__BACKEND__CHECKSUM_ALGORITHM", "crc32c"),
+    ]);
+    assert_eq!(
+        config.backend,
+        BackendConfig::S3 {
+            region: "eu-central-1".to_string(),
+            physical_bucket: "my-bucket".to_string(),
+            endpoint: Some("http://localhost:9000".to_string()),
+            force_path_style: true,
+            checksum_algorithm: ChecksumAlgorithm::Crc32c,
+        }
+    );
+
+    let config = configure(&[
+        ("OBJECT_STORE__BACKEND__TYPE", "gcs"),
+        ("OBJECT_STORE__BACKEND__PHYSICAL_BUCKET", "my-bucket"),
+        (
+            "OBJECT_STORE__BACKEND__RESUMABLE_THRESHOLD_BYTES",
+            "1048576",
+        ),
+    ]);
+    assert_eq!(
+        config.backend,
+        BackendConfig::Gcs {
+            physical_bucket: "my-bucket".to_string(),
+            resumable_threshold_bytes: 1048576,
+        }
+    );
+
+    let config = configure(&[
+        ("OBJECT_STORE__BACKEND__TYPE", "azure"),
+        ("OBJECT_STORE__BACKEND__ACCOUNT", "myaccount"),
+        ("OBJECT_STORE__BACKEND__CONTAINER", "mycontainer"),
+        ("OBJECT_STORE__BACKEND__AUTH__ACCESS_KEY", "a2V5"),
+    ]);
+    assert_eq!(
+        config.backend,
+        BackendConfig::Azure {
+            account: "myaccount".to_string(),
+            physical_bucket: "mycontainer".to_string(),
+            auth: AzureAuth::AccessKey("a2V5".to_string()),
+        }
+    );
+
+    let config = configure(&[
+        ("OBJECT_STORE__BACKEND__TYPE", "azure"),
+        ("OBJECT_STORE__BACKEND__ACCOUNT", "myaccount"),
+        ("OBJECT_STORE__BACKEND__PHYSICAL_BUCKET", "mycontainer"),
+        ("OBJECT_STORE__BACKEND__AUTH", "managed_identity"),
+    ]);
+    assert_eq!(
+        config.backend,
+        BackendConfig::Azure {
+            account: "myaccount".to_string(),
+            physical_bucket: "mycontainer".to_string(),
+            auth: AzureAuth::ManagedIdentity,
+        }
+    );
+
+    for vars in [
+        &[("OBJECT_STORE__BACKEND__TYPE", "s3")][..],
+        &[
+            ("OBJECT_STORE__BACKEND__TYPE", "local"),
+            ("OBJECT_STORE__BACKEND__ROOT_PATH", "./data"),
+            ("OBJECT_STORE__BACKEND__LIST_CACHE_CAPACITY", "lots"),
+        ][..],
+    ] {
+        set_env(vars);
+        assert!(Config::from_env().is_err(), "{:?} should be rejected", vars);
+    }
+}
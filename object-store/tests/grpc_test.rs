@@ -0,0 +1,145 @@
+use object_store::metadata::MetadataStore;
+use object_store::service::ObjectStoreService;
+use object_store_backends::{local::LocalBackend, Backend};
+use object_store_client::grpc::GrpcClient;
+use object_store_client::{Error, ObjectStoreClient};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+/// Serves one service over both REST and gRPC on ephemeral ports and returns
+/// their base URLs.
+async fn start_servers() -> (String, String, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let backend = Arc::new(LocalBackend::new(
+        temp_dir.path().to_path_buf(),
+        "test-physical-bucket".to_string(),
+    ));
+    backend.init().await.unwrap();
+    let metadata = Arc::new(MetadataStore::new(backend.clone()).await.unwrap());
+    let service = Arc::new(ObjectStoreService::new(backend, metadata));
+
+    let http_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let http_url = format!("http://{}", http_listener.local_addr().unwrap());
+    let app = object_store::router::create_router(service.clone());
+    tokio::spawn(async move { axum::serve(http_listener, app).await.unwrap() });
+
+    let grpc_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let grpc_url = format!("http://{}", grpc_listener.local_addr().unwrap());
+    tokio::spawn(object_store::grpc::serve(service, grpc_listener));
+
+    (http_url, grpc_url, temp_dir)
+}
+
+#[tokio::test]
+async fn test_grpc_upload_http_download() {
+    let (http_url, grpc_url, _temp_dir) = start_servers().await;
+    let grpc = GrpcClient::connect(grpc_url).await.unwrap();
+    let http = ObjectStoreClient::new(http_url);
+
+    grpc.create_bucket("grpc-bucket").await.unwrap();
+    assert!(matches!(
+        grpc.create_bucket("grpc-bucket").await,
+        Err(Error::AlreadyExists(_))
+    ));
+    assert_eq!(http.list_buckets().await.unwrap()[0].name, "grpc-bucket");
+
+    // Large enough to be sent, and streamed back, in several chunks
+    let data: Vec<u8> = (0..3 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let metadata = HashMap::from([("author".to_string(), "grpc".to_string())]);
+    let uploaded = grpc
+        .put_object(
+            "grpc-bucket",
+            "data/blob.bin",
+            data.clone(),
+            Some("application/octet-stream"),
+            Some(metadata),
+        )
+        .await
+        .unwrap();
+    assert_eq!(uploaded.size, data.len() as u64);
+
+    let downloaded = http
+        .get_object("grpc-bucket", "data/blob.bin")
+        .await
+        .unwrap();
+    assert_eq!(downloaded.data, data);
+    assert_eq!(downloaded.metadata.etag, uploaded.etag);
+    let head = http
+        .head_object("grpc-bucket", "data/blob.bin")
+        .await
+        .unwrap();
+    assert_eq!(head.metadata["author"], "grpc");
+
+    // And the other way round
+    http.put_object("grpc-bucket", "data/hello.txt", "hello", None, None)
+        .await
+        .unwrap();
+    let hello = grpc
+        .get_object("grpc-bucket", "data/hello.txt")
+        .await
+        .unwrap();
+    assert_eq!(hello.data, "hello");
+    assert_eq!(hello.metadata.content_type.as_deref(), Some("text/plain"));
+
+    let listed = grpc
+        .list_objects("grpc-bucket", Some("data/"), None)
+        .await
+        .unwrap();
+    let keys: Vec<_> = listed.iter().map(|o| o.key.as_str()).collect();
+    assert_eq!(keys, ["data/blob.bin", "data/hello.txt"]);
+
+    grpc.delete_object("grpc-bucket", "data/blob.bin")
+        .await
+        .unwrap();
+    assert!(matches!(
+        grpc.head_object("grpc-bucket", "data/blob.bin").await,
+        Err(Error::NotFound(_))
+    ));
+    assert!(matches!(
+        http.get_object("grpc-bucket", "data/blob.bin").await,
+        Err(Error::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_http_keys_with_reserved_characters_round_trip() {
+    let (http_url, _grpc_url, _temp_dir) = start_servers().await;
+    let http = ObjectStoreClient::new(http_url);
+    http.create_bucket("keys").await.unwrap();
+
+    let keys = [
+        "hello world.txt",
+        "tags/#1.txt",
+        "search?q=a&b.txt",
+        "100%.txt",
+        "%20literal.txt",
+        "a+b.txt",
+        "unicode/日本語 ü.txt",
+    ];
+    for key in keys {
+        http.put_object("keys", key, key, None, None).await.unwrap();
+
+        let object = http.get_object("keys", key).await.unwrap();
+        assert_eq!(object.data, key, "{}", key);
+        assert_eq!(object.metadata.key, key);
+        assert_eq!(http.head_object("keys", key).await.unwrap().key, key);
+    }
+
+    let mut listed: Vec<_> = http
+        .list_objects("keys", None, None)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|o| o.key)
+        .collect();
+    let mut expected: Vec<_> = keys.iter().map(|k| k.to_string()).collect();
+    listed.sort();
+    expected.sort();
+    assert_eq!(listed, expected);
+
+    for key in keys {
+        http.delete_object("keys", key).await.unwrap();
+        assert!(!http.object_exists("keys", key).await.unwrap(), "{}", key);
+    }
+}
@@ -3,14 +3,22 @@ use axum::http::{Request, StatusCode};
 use bytes::Bytes;
 use futures::stream;
 use object_store::metadata::MetadataStore;
-use object_store::service::ObjectStoreService;
-use object_store_backends::{local::LocalBackend, Backend};
+use object_store::service::{ListBucketsOptions, ObjectStoreService};
+use object_store_backends::{local::LocalBackend, Backend, BackendError, BackendExt};
 use serde_json::json;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tempfile::TempDir;
 use tower::ServiceExt as TowerServiceExt;
 
 async fn setup_test_service() -> (Arc<ObjectStoreService>, TempDir) {
+    setup_test_service_with(|service| service).await
+}
+
+/// Like `setup_test_service`, with `configure` applied to the service.
+async fn setup_test_service_with(
+    configure: impl FnOnce(ObjectStoreService) -> ObjectStoreService,
+) -> (Arc<ObjectStoreService>, TempDir) {
     let temp_dir = TempDir::new().unwrap();
     let backend = Arc::new(LocalBackend::new(
         temp_dir.path().to_path_buf(),
@@ -21,7 +29,7 @@ async fn setup_test_service() -> (Arc<ObjectStoreService>, TempDir) {
 
     let metadata = Arc::new(MetadataStore::new(backend.clone()).await.unwrap());
 
-    let service = Arc::new(ObjectStoreService::new(backend, metadata));
+    let service = Arc::new(configure(ObjectStoreService::new(backend, metadata)));
 
     (service, temp_dir)
 }
@@ -29,7 +37,7 @@ async fn setup_test_service() -> (Arc<ObjectStoreService>, TempDir) {
 #[tokio::test]
 async fn test_health_check() {
     let (service, _temp_dir) = setup_test_service().await;
-    let app = object_store::router::create_router(service);
+    let app = object_store::router::create_internal_router(service);
 
     let response = app
         .oneshot(
@@ -44,6 +52,47 @@ async fn test_health_check() {
     assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[tokio::test]
+async fn test_ping_reports_version() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let info = object_store::api::ServerInfo::new(std::time::Instant::now(), "local");
+    let app = object_store::router::create_internal_router_with_info(service.clone(), info);
+
+    let response = app
+        .oneshot(Request::builder().uri("/ping").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["x-server-version"],
+        env!("CARGO_PKG_VERSION")
+    );
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(json["backend_type"], "local");
+    assert_eq!(json["uptime_secs"], 0);
+
+    // Public responses carry the header too, errors included
+    let response = object_store::router::create_router(service)
+        .oneshot(
+            Request::builder()
+                .uri("/buckets/missing")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.headers()["x-server-version"],
+        env!("CARGO_PKG_VERSION")
+    );
+}
+
 #[tokio::test]
 async fn test_create_and_list_buckets() {
     let (service, _temp_dir) = setup_test_service().await;
@@ -92,6 +141,89 @@ async fn test_create_and_list_buckets() {
     assert_eq!(json["buckets"][0]["name"], "test-bucket");
 }
 
+#[tokio::test]
+async fn test_list_buckets_sorted_and_filtered() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    // Creation order deliberately disagrees with name order
+    for name in ["prod-c", "dev-a", "prod-a", "prod-b"] {
+        service.create_bucket(name).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+
+    async fn listed(app: &axum::Router, query: &str) -> Vec<String> {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/buckets?{}", query))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        json["buckets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    // Newest first when nothing is given
+    assert_eq!(
+        listed(&app, "").await,
+        vec!["prod-b", "prod-a", "dev-a", "prod-c"]
+    );
+    assert_eq!(
+        listed(&app, "sort_by=created_at&order=asc").await,
+        vec!["prod-c", "dev-a", "prod-a", "prod-b"]
+    );
+    assert_eq!(
+        listed(&app, "sort_by=name").await,
+        vec!["dev-a", "prod-a", "prod-b", "prod-c"]
+    );
+    assert_eq!(
+        listed(&app, "sort_by=name&order=desc").await,
+        vec!["prod-c", "prod-b", "prod-a", "dev-a"]
+    );
+    assert_eq!(
+        listed(&app, "sort_by=name&order=asc&prefix=prod-").await,
+        vec!["prod-a", "prod-b", "prod-c"]
+    );
+    assert!(listed(&app, "prefix=staging-").await.is_empty());
+
+    // Repeated listings come back in the same order
+    for query in [
+        "",
+        "sort_by=name",
+        "sort_by=created_at&order=asc&prefix=prod-",
+    ] {
+        let first = listed(&app, query).await;
+        for _ in 0..5 {
+            assert_eq!(listed(&app, query).await, first);
+        }
+    }
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/buckets?sort_by=size")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
 #[tokio::test]
 async fn test_put_and_get_object() {
     let (service, _temp_dir) = setup_test_service().await;
@@ -136,6 +268,358 @@ async fn test_put_and_get_object() {
     assert_eq!(&body[..], data);
 }
 
+#[tokio::test]
+async fn test_put_object_decodes_content_encoding() {
+    use std::io::Write;
+
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+    service.create_bucket("encoded").await.unwrap();
+
+    let data = b"Hello, compressed World! ".repeat(100);
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(&data).unwrap();
+    let gzipped = gzip.finish().unwrap();
+    let zstded = zstd::encode_all(&data[..], 0).unwrap();
+
+    let put = |key: &str, encoding: &str, body: Vec<u8>| {
+        app.clone().oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/buckets/encoded/objects/{}", key))
+                .header("content-type", "text/plain")
+                .header("content-encoding", encoding)
+                .body(Body::from(body))
+                .unwrap(),
+        )
+    };
+
+    for (key, encoding, body) in [
+        ("a.txt", "gzip", gzipped.clone()),
+        ("b.txt", "zstd", zstded),
+    ] {
+        let response = put(key, encoding, body).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", encoding);
+
+        let object = service.get_object("encoded", key).await.unwrap();
+        assert_eq!(object.metadata.size, data.len() as u64);
+        assert_eq!(
+            object.metadata.custom_metadata["original-encoding"],
+            encoding
+        );
+        let stored: Vec<u8> = futures::StreamExt::collect::<Vec<_>>(object.stream)
+            .await
+            .into_iter()
+            .flat_map(Result::unwrap)
+            .collect();
+        assert_eq!(stored, data);
+    }
+
+    let response = put("c.txt", "br", gzipped.clone()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // A body that is not actually gzip is the client's fault too
+    let response = put("d.txt", "gzip", data.clone()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(!service.object_exists("encoded", "d.txt").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_decoded_body_is_held_to_server_body_limit() {
+    use std::io::Write;
+
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("bombs").await.unwrap();
+    let app = object_store::router::create_router_with_config(
+        service.clone(),
+        &object_store::config::ServerConfig {
+            max_body_bytes: Some(64 * 1024),
+            ..Default::default()
+        },
+    );
+
+    // A few hundred bytes on the wire, a megabyte once decoded
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+    gzip.write_all(&vec![0u8; 1024 * 1024]).unwrap();
+    let bomb = gzip.finish().unwrap();
+    assert!(bomb.len() < 64 * 1024);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/buckets/bombs/objects/bomb.bin")
+                .header("content-encoding", "gzip")
+                .body(Body::from(bomb))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(!service.object_exists("bombs", "bomb.bin").await.unwrap());
+}
+
+#[tokio::test]
+async fn test_put_object_from_memory() {
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("in-memory").await.unwrap();
+
+    let text = "same body";
+    let from_vec = service
+        .put_object_bytes(
+            "in-memory",
+            "vec.txt",
+            text.as_bytes().to_vec(),
+            None,
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    let from_bytes = service
+        .put_object_bytes(
+            "in-memory",
+            "bytes.txt",
+            Bytes::from_static(text.as_bytes()),
+            None,
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    let from_str = service
+        .put_object_str(
+            "in-memory",
+            "str.txt",
+            text,
+            Some("text/plain".to_string()),
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(from_vec.etag, from_bytes.etag);
+    assert_eq!(from_vec.etag, from_str.etag);
+    assert_eq!(from_str.size, text.len() as u64);
+    assert_eq!(from_str.content_type.as_deref(), Some("text/plain"));
+
+    let object = service.get_object("in-memory", "str.txt").await.unwrap();
+    let body: Vec<Bytes> = futures::StreamExt::collect::<Vec<_>>(object.stream)
+        .await
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(body.concat(), text.as_bytes());
+}
+
+#[tokio::test]
+async fn test_concurrent_uploads_to_one_key_are_serialized() {
+    const WRITERS: u8 = 20;
+    const CHUNK_BYTES: usize = 8 * 1024;
+    const CHUNKS: usize = 32;
+
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("contended").await.unwrap();
+
+    let uploads = (0..WRITERS).map(|writer| {
+        let service = service.clone();
+        tokio::spawn(async move {
+            // Yields between chunks so the writers interleave
+            let body: object_store_backends::ByteStream = Box::pin(futures::StreamExt::then(
+                stream::iter(0..CHUNKS),
+                move |_| async move {
+                    tokio::task::yield_now().await;
+                    Ok(Bytes::from(vec![b'a' + writer; CHUNK_BYTES]))
+                },
+            ));
+            service
+                .put_object("contended", "shared.bin", body, None, HashMap::new())
+                .await
+        })
+    });
+    for upload in futures::future::join_all(uploads).await {
+        upload.unwrap().unwrap();
+    }
+
+    let object = service.get_object("contended", "shared.bin").await.unwrap();
+    let chunks: Vec<_> = futures::StreamExt::collect(object.stream).await;
+    let body: Vec<u8> = chunks.into_iter().flat_map(Result::unwrap).collect();
+    assert_eq!(body.len(), CHUNK_BYTES * CHUNKS);
+    let first = body[0];
+    assert!((b'a'..b'a' + WRITERS).contains(&first));
+    assert!(
+        body.iter().all(|&b| b == first),
+        "bytes of two uploads mixed"
+    );
+    assert_eq!(
+        object.metadata.etag,
+        object_store_backends::backend::compute_etag(&body)
+    );
+
+    let rendered = object_store::metrics::prometheus_handle().render();
+    assert!(
+        rendered.contains("object_store_upload_collisions_total{bucket=\"contended\"}"),
+        "{}",
+        rendered
+    );
+}
+
+#[tokio::test]
+async fn test_conditional_get_object() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("test-bucket").await.unwrap();
+
+    let put = |data: &'static [u8]| {
+        app.clone().oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/buckets/test-bucket/objects/cached.txt")
+                .body(Body::from(data))
+                .unwrap(),
+        )
+    };
+    let get = |header: &'static str, value: String| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri("/buckets/test-bucket/objects/cached.txt")
+                .header(header, value)
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+
+    let response = put(b"version one").await.unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let etag = json["etag"].as_str().unwrap().to_string();
+    let last_modified = json["last_modified"].as_str().unwrap().to_string();
+
+    // Matching ETag (quoted, as browsers send it) is not modified
+    let response = get("if-none-match", format!("\"{}\"", etag)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(body.is_empty());
+
+    // A date in the future means nothing has changed since
+    let future = (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc2822();
+    let response = get("if-modified-since", future).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let past = (chrono::Utc::now() - chrono::Duration::hours(1)).to_rfc2822();
+    let response = get("if-modified-since", past).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["last-modified"], last_modified.as_str());
+
+    // Echoing Last-Modified back, as caches do, works with its RFC 3339 format
+    let response = get("if-modified-since", last_modified).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // After an overwrite, the old ETag no longer matches
+    put(b"version two").await.unwrap();
+    let response = get("if-none-match", etag).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"version two");
+}
+
+#[tokio::test]
+async fn test_get_object_if_range() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("test-bucket").await.unwrap();
+    let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    let metadata = service
+        .put_object_reader(
+            "test-bucket",
+            "resumable.bin",
+            std::io::Cursor::new(data.clone()),
+            None,
+            Default::default(),
+        )
+        .await
+        .unwrap();
+
+    let get = |headers: Vec<(&'static str, String)>| {
+        let mut request = Request::builder().uri("/buckets/test-bucket/objects/resumable.bin");
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+    async fn body_of(response: axum::response::Response) -> Vec<u8> {
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    // Matching ETag: only the requested range
+    let response = get(vec![
+        ("range", "bytes=100000-".to_string()),
+        ("if-range", format!("\"{}\"", metadata.etag)),
+    ])
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        response.headers()["content-range"],
+        "bytes 100000-199999/200000"
+    );
+    assert_eq!(response.headers()["content-length"], "100000");
+    assert_eq!(body_of(response).await, &data[100_000..]);
+
+    // Stale ETag: the range is ignored and the whole object comes back
+    let response = get(vec![
+        ("range", "bytes=100000-".to_string()),
+        ("if-range", "\"stale-etag\"".to_string()),
+    ])
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("content-range").is_none());
+    assert_eq!(response.headers()["content-length"], "200000");
+    assert_eq!(body_of(response).await, data);
+
+    // No range: If-Range alone changes nothing
+    let response = get(vec![("if-range", metadata.etag.clone())])
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["accept-ranges"], "bytes");
+    assert_eq!(body_of(response).await, data);
+
+    // A range without If-Range, bounded and suffix forms
+    let response = get(vec![("range", "bytes=10-19".to_string())])
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(body_of(response).await, &data[10..20]);
+    let response = get(vec![("range", "bytes=-5".to_string())]).await.unwrap();
+    assert_eq!(body_of(response).await, &data[199_995..]);
+
+    // Weak ETags never satisfy If-Range
+    let response = get(vec![
+        ("range", "bytes=0-9".to_string()),
+        ("if-range", format!("W/\"{}\"", metadata.etag)),
+    ])
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = get(vec![("range", "bytes=300000-".to_string())])
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(response.headers()["content-range"], "bytes */200000");
+}
+
 #[tokio::test]
 async fn test_delete_object() {
     let (service, _temp_dir) = setup_test_service().await;
@@ -143,14 +627,11 @@ async fn test_delete_object() {
 
     // Create bucket and put object
     service.create_bucket("test-bucket").await.unwrap();
-    let data = b"Hello".to_vec();
-    let stream: object_store_backends::ByteStream =
-        Box::pin(stream::once(async move { Ok(Bytes::from(data)) }));
     service
-        .put_object(
+        .put_object_reader(
             "test-bucket",
             "test.txt",
-            stream,
+            &b"Hello"[..],
             Some("text/plain".to_string()),
             Default::default(),
         )
@@ -186,6 +667,43 @@ async fn test_delete_object() {
     assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
+#[tokio::test]
+async fn test_delete_missing_object() {
+    let (service, temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+    service.create_bucket("test-bucket").await.unwrap();
+
+    // The local backend keeps reporting missing keys...
+    let backend = LocalBackend::new(
+        temp_dir.path().to_path_buf(),
+        "test-physical-bucket".to_string(),
+    );
+    assert!(matches!(
+        backend.delete_object("test-bucket/never.txt").await,
+        Err(BackendError::NotFound(_))
+    ));
+    backend
+        .delete_object_if_exists("test-bucket/never.txt")
+        .await
+        .unwrap();
+
+    // ...while deleting through the service is idempotent
+    for _ in 0..2 {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/buckets/test-bucket/objects/never.txt")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+}
+
 #[tokio::test]
 async fn test_list_objects() {
     let (service, _temp_dir) = setup_test_service().await;
@@ -194,42 +712,33 @@ async fn test_list_objects() {
     // Create bucket and put multiple objects
     service.create_bucket("test-bucket").await.unwrap();
 
-    let data1 = b"Data1".to_vec();
-    let stream1: object_store_backends::ByteStream =
-        Box::pin(stream::once(async move { Ok(Bytes::from(data1)) }));
     service
-        .put_object(
+        .put_object_reader(
             "test-bucket",
             "file1.txt",
-            stream1,
+            &b"Data1"[..],
             None,
             Default::default(),
         )
         .await
         .unwrap();
 
-    let data2 = b"Data2".to_vec();
-    let stream2: object_store_backends::ByteStream =
-        Box::pin(stream::once(async move { Ok(Bytes::from(data2)) }));
     service
-        .put_object(
+        .put_object_reader(
             "test-bucket",
             "file2.txt",
-            stream2,
+            &b"Data2"[..],
             None,
             Default::default(),
         )
         .await
         .unwrap();
 
-    let data3 = b"Data3".to_vec();
-    let stream3: object_store_backends::ByteStream =
-        Box::pin(stream::once(async move { Ok(Bytes::from(data3)) }));
     service
-        .put_object(
+        .put_object_reader(
             "test-bucket",
             "subdir/file3.txt",
-            stream3,
+            &b"Data3"[..],
             None,
             Default::default(),
         )
@@ -285,14 +794,11 @@ async fn test_head_object() {
 
     // Create bucket and put object
     service.create_bucket("test-bucket").await.unwrap();
-    let data = b"Hello, World!".to_vec();
-    let stream: object_store_backends::ByteStream =
-        Box::pin(stream::once(async move { Ok(Bytes::from(data)) }));
     service
-        .put_object(
+        .put_object_reader(
             "test-bucket",
             "test.txt",
-            stream,
+            &b"Hello, World!"[..],
             Some("text/plain".to_string()),
             Default::default(),
         )
@@ -317,6 +823,16 @@ async fn test_head_object() {
         response.headers().get("content-type").unwrap(),
         "text/plain"
     );
+    // Same RFC 3339 format as `last_modified` in JSON bodies
+    let last_modified = response.headers()["last-modified"].to_str().unwrap();
+    let head = service
+        .head_object("test-bucket", "test.txt")
+        .await
+        .unwrap();
+    assert_eq!(
+        chrono::DateTime::parse_from_rfc3339(last_modified).unwrap(),
+        head.last_modified
+    );
 }
 
 #[tokio::test]
@@ -363,21 +879,63 @@ async fn test_object_not_found() {
     assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
 
+#[tokio::test]
+async fn test_missing_objects_are_object_not_found() {
+    use object_store::ServiceError;
+
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("test-bucket").await.unwrap();
+    service
+        .create_bucket_with("recycled", |bucket| bucket.recycle_bin_enabled = true)
+        .await
+        .unwrap();
+
+    let err = service
+        .get_object("test-bucket", "nonexistent.txt")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, ServiceError::ObjectNotFound(key) if key == "test-bucket/nonexistent.txt")
+    );
+    let err = service
+        .head_object("test-bucket", "nonexistent.txt")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ServiceError::ObjectNotFound(_)));
+    let err = service
+        .delete_object("recycled", "nonexistent.txt")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ServiceError::ObjectNotFound(_)));
+
+    // A missing bucket is reported before the object is looked for
+    let err = service
+        .get_object("no-such-bucket", "nonexistent.txt")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ServiceError::BucketNotFound(_)));
+
+    // Paths that pass the backend's error through still answer 404
+    let err = ServiceError::Backend(object_store_backends::BackendError::NotFound(
+        "test-bucket/nonexistent.txt".to_string(),
+    ));
+    assert_eq!(
+        axum::response::IntoResponse::into_response(err).status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
 #[tokio::test]
 async fn test_path_traversal_protection() {
     let (service, _temp_dir) = setup_test_service().await;
 
     service.create_bucket("test-bucket").await.unwrap();
 
-    let data = b"malicious".to_vec();
-    let stream: object_store_backends::ByteStream =
-        Box::pin(stream::once(async move { Ok(Bytes::from(data)) }));
-
     let result = service
-        .put_object(
+        .put_object_reader(
             "test-bucket",
             "../etc/passwd",
-            stream,
+            &b"malicious"[..],
             None,
             Default::default(),
         )
@@ -412,10 +970,4016 @@ async fn test_concurrent_bucket_creation() {
     assert_eq!(success_count, 10, "All 10 bucket creations should succeed");
 
     // Verify all buckets exist
-    let buckets = service.list_buckets().await.unwrap();
+    let buckets = service
+        .list_buckets(&ListBucketsOptions::default())
+        .await
+        .unwrap();
     assert_eq!(buckets.len(), 10, "Should have 10 buckets");
 
     // Try creating duplicate - should fail
     let result = service.create_bucket("concurrent-0").await;
     assert!(result.is_err(), "Duplicate bucket creation should fail");
 }
+
+#[tokio::test]
+async fn test_list_objects_ndjson() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("test-bucket").await.unwrap();
+
+    for i in 0..25 {
+        let data = format!("Data{}", i).into_bytes();
+        service
+            .put_object_reader(
+                "test-bucket",
+                &format!("file-{}.txt", i),
+                std::io::Cursor::new(data),
+                None,
+                Default::default(),
+            )
+            .await
+            .unwrap();
+    }
+
+    // Accept header selects the streaming path
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/buckets/test-bucket/objects")
+                .header("accept", "application/x-ndjson")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/x-ndjson"
+    );
+    assert!(response.headers().get("content-length").is_none());
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let lines: Vec<serde_json::Value> = body
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice(line).unwrap())
+        .collect();
+
+    assert_eq!(lines.len(), 25);
+    assert!(lines.iter().all(|obj| obj["key"].is_string()));
+
+    // The query flag does the same, and non-streaming stays the default
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/buckets/test-bucket/objects?streaming=true")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(
+        body.split(|b| *b == b'\n')
+            .filter(|l| !l.is_empty())
+            .count(),
+        25
+    );
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/buckets/test-bucket/objects")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/json"
+    );
+}
+
+#[tokio::test]
+async fn test_list_objects_stream_pages_through_backend() {
+    use futures::TryStreamExt;
+    use object_store::service::ListObjectsOptions;
+
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("test-bucket").await.unwrap();
+
+    // More than one backend page of 1000
+    for i in 0..1005 {
+        service
+            .put_object_reader(
+                "test-bucket",
+                &format!("file-{:04}", i),
+                std::io::Cursor::new(b"data".to_vec()),
+                None,
+                Default::default(),
+            )
+            .await
+            .unwrap();
+    }
+
+    let keys = |options: ListObjectsOptions| {
+        let service = service.clone();
+        async move {
+            service
+                .list_objects_stream("test-bucket", &options)
+                .await
+                .unwrap()
+                .map_ok(|obj| obj.key)
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap()
+        }
+    };
+
+    let all = keys(ListObjectsOptions::default()).await;
+    let expected: Vec<_> = (0..1005).map(|i| format!("file-{:04}", i)).collect();
+    assert_eq!(all, expected);
+
+    let options = ListObjectsOptions {
+        start_after: Some("file-0002".to_string()),
+        max_keys: Some(1001),
+        ..Default::default()
+    };
+    let page = keys(options).await;
+    assert_eq!(page.len(), 1001);
+    assert_eq!(page.first().unwrap(), "file-0003");
+    assert_eq!(page.last().unwrap(), "file-1003");
+}
+
+#[tokio::test]
+async fn test_list_objects_sorted() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("test-bucket").await.unwrap();
+
+    // Names and sizes deliberately disagree so each sort field is observable
+    for i in 0..20usize {
+        let data = vec![b'x'; (i * 7) % 20 + 1];
+        service
+            .put_object_reader(
+                "test-bucket",
+                &format!("obj-{:02}", 19 - i),
+                std::io::Cursor::new(data),
+                None,
+                Default::default(),
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn listed(app: &axum::Router, query: &str) -> Vec<(String, u64)> {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/buckets/test-bucket/objects?{}", query))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        json["objects"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|o| {
+                (
+                    o["key"].as_str().unwrap().to_string(),
+                    o["size"].as_u64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    let by_size_asc = listed(&app, "sort_by=size&sort_order=asc").await;
+    assert_eq!(by_size_asc.len(), 20);
+    let sizes: Vec<u64> = by_size_asc.iter().map(|(_, s)| *s).collect();
+    assert_eq!(sizes, (1..=20).collect::<Vec<u64>>());
+
+    let by_size_desc = listed(&app, "sort_by=size&sort_order=desc").await;
+    let sizes: Vec<u64> = by_size_desc.iter().map(|(_, s)| *s).collect();
+    assert_eq!(sizes, (1..=20).rev().collect::<Vec<u64>>());
+
+    let by_key_asc = listed(&app, "sort_by=key").await;
+    let keys: Vec<String> = by_key_asc.iter().map(|(k, _)| k.clone()).collect();
+    let expected: Vec<String> = (0..20).map(|i| format!("obj-{:02}", i)).collect();
+    assert_eq!(keys, expected);
+
+    let by_key_desc = listed(&app, "sort_by=key&sort_order=desc&max_keys=5").await;
+    let keys: Vec<String> = by_key_desc.iter().map(|(k, _)| k.clone()).collect();
+    let expected: Vec<String> = (15..20).rev().map(|i| format!("obj-{:02}", i)).collect();
+    assert_eq!(keys, expected);
+}
+
+#[tokio::test]
+async fn test_list_objects_start_after_pages_in_key_order() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("test-bucket").await.unwrap();
+    for i in (0..12).rev() {
+        service
+            .put_object_str(
+                "test-bucket",
+                &format!("obj-{:02}", i),
+                "x",
+                None,
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+    }
+
+    let list = |query: String| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri(format!("/buckets/test-bucket/objects?{}", query))
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+
+    let mut keys = Vec::new();
+    let mut start_after: Option<String> = None;
+    loop {
+        let query = match &start_after {
+            Some(key) => format!("max_keys=5&start_after={}", key),
+            None => "max_keys=5&sort_by=key".to_string(),
+        };
+        let response = list(query).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let page: Vec<String> = json["objects"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|o| o["key"].as_str().unwrap().to_string())
+            .collect();
+
+        keys.extend(page.iter().cloned());
+        if page.len() < 5 {
+            break;
+        }
+        start_after = page.last().cloned();
+    }
+
+    let expected: Vec<String> = (0..12).map(|i| format!("obj-{:02}", i)).collect();
+    assert_eq!(keys, expected);
+
+    let response = list("start_after=obj-03&sort_by=size".to_string())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_list_objects_total_count() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("counted").await.unwrap();
+    for key in ["a/1", "a/2", "a/3", "b/1", "b/2"] {
+        let owner = if key.starts_with("a/") {
+            "alice"
+        } else {
+            "bob"
+        };
+        service
+            .put_object_str(
+                "counted",
+                key,
+                "x",
+                None,
+                HashMap::from([("owner".to_string(), owner.to_string())]),
+            )
+            .await
+            .unwrap();
+    }
+
+    let list = |query: &str| {
+        let app = app.clone();
+        let uri = format!("/buckets/counted/objects?{}", query);
+        async move {
+            let response = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        }
+    };
+
+    let json = list("sort_by=key&max_keys=2").await;
+    assert_eq!(json["objects"].as_array().unwrap().len(), 2);
+    assert!(json.get("total_count").is_none());
+
+    let json = list("sort_by=key&max_keys=2&include_count=true").await;
+    assert_eq!(json["objects"].as_array().unwrap().len(), 2);
+    assert_eq!(json["total_count"], 5);
+
+    let json = list("prefix=a/&include_count=true").await;
+    assert_eq!(json["total_count"], 3);
+
+    // The count covers every page, not just the ones after the cursor
+    let json = list("max_keys=2&start_after=a/2&include_count=true").await;
+    assert_eq!(json["objects"][0]["key"], "a/3");
+    assert_eq!(json["total_count"], 5);
+
+    let json = list("metadata_filter=owner=bob&max_keys=1&include_count=true").await;
+    assert_eq!(json["objects"].as_array().unwrap().len(), 1);
+    assert_eq!(json["total_count"], 2);
+    let json = list("metadata_filter=owner=alice&start_after=a/1&include_count=true").await;
+    assert_eq!(json["objects"].as_array().unwrap().len(), 2);
+    assert_eq!(json["total_count"], 3);
+
+    // Writes drop the cached count
+    service
+        .put_object_str("counted", "c/1", "x", None, HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(list("include_count=true").await["total_count"], 6);
+    service.delete_object("counted", "a/1").await.unwrap();
+    assert_eq!(list("include_count=true").await["total_count"], 5);
+}
+
+#[tokio::test]
+async fn test_list_objects_without_full_metadata() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("summaries").await.unwrap();
+    assert_eq!(put_text(&app, "summaries", "a.txt").await, StatusCode::OK);
+    assert_eq!(put_text(&app, "summaries", "b.txt").await, StatusCode::OK);
+
+    let list = |query: &str| {
+        let app = app.clone();
+        let uri = format!("/buckets/summaries/objects?{}", query);
+        async move {
+            let response = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        }
+    };
+
+    let full = list("sort_by=key").await;
+    assert_eq!(full["objects"][0]["content_type"], "text/plain");
+    assert_eq!(full["objects"][0]["metadata"]["owner"], "alice");
+
+    let summary = list("sort_by=key&full_metadata=false&include_count=true").await;
+    let objects = summary["objects"].as_array().unwrap();
+    assert_eq!(objects.len(), 2);
+    assert_eq!(objects[0]["key"], "a.txt");
+    assert_eq!(objects[0]["size"], 5);
+    assert!(objects[0]["last_modified"].is_string());
+    assert!(objects[0].get("content_type").is_none());
+    assert!(objects[0].get("metadata").is_none());
+    assert_eq!(summary["total_count"], 2);
+
+    // Filtering still sees the metadata
+    let filtered = list("full_metadata=false&metadata_filter=owner%3Dalice").await;
+    assert_eq!(filtered["objects"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_export_inventory_csv() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("test-bucket").await.unwrap();
+
+    for i in 0..100 {
+        let data = vec![b'x'; i];
+        service
+            .put_object_reader(
+                "test-bucket",
+                &format!("dir/file, \"{}\".txt", i),
+                std::io::Cursor::new(data),
+                Some("text/plain".to_string()),
+                HashMap::from([("index".to_string(), i.to_string())]),
+            )
+            .await
+            .unwrap();
+    }
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/buckets/test-bucket/inventory?format=csv")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/csv");
+    assert_eq!(
+        response.headers()["content-disposition"],
+        "attachment; filename=\"test-bucket-inventory.csv\""
+    );
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let mut reader = csv::Reader::from_reader(&body[..]);
+    assert_eq!(
+        reader.headers().unwrap(),
+        vec![
+            "key",
+            "size",
+            "etag",
+            "content_type",
+            "last_modified",
+            "metadata"
+        ]
+    );
+
+    let mut seen = HashMap::new();
+    for record in reader.records() {
+        let record = record.unwrap();
+        *seen.entry(record[0].to_string()).or_insert(0) += 1;
+
+        let index: usize = record[0]
+            .trim_start_matches("dir/file, \"")
+            .trim_end_matches("\".txt")
+            .parse()
+            .unwrap();
+        assert_eq!(record[1].parse::<usize>().unwrap(), index);
+        assert_eq!(&record[3], "text/plain");
+        let metadata: HashMap<String, String> = serde_json::from_str(&record[5]).unwrap();
+        assert_eq!(metadata["index"], index.to_string());
+    }
+
+    assert_eq!(seen.len(), 100);
+    assert!(seen.values().all(|count| *count == 1));
+}
+
+#[tokio::test]
+async fn test_export_inventory_json() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("test-bucket").await.unwrap();
+
+    let request = || {
+        Request::builder()
+            .uri("/buckets/test-bucket/inventory")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    // An empty bucket is still a valid JSON array
+    let response = app.clone().oneshot(request()).await.unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"[]");
+
+    for key in ["a.txt", "b.txt", "c.txt"] {
+        service
+            .put_object_reader("test-bucket", key, "data".as_bytes(), None, HashMap::new())
+            .await
+            .unwrap();
+    }
+
+    let response = app.oneshot(request()).await.unwrap();
+    assert_eq!(response.headers()["content-type"], "application/json");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let objects: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(objects.len(), 3);
+    assert!(objects.iter().all(|o| o["size"] == 4));
+}
+
+#[tokio::test]
+async fn test_virtual_hosted_bucket_urls() {
+    use object_store::config::{ServerConfig, UrlStyle};
+
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("mybucket").await.unwrap();
+    service.create_bucket("otherbucket").await.unwrap();
+
+    let request = |app: &axum::Router, method: &str, host: &str, uri: &str, body: &'static str| {
+        app.clone().oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("host", host)
+                .body(Body::from(body))
+                .unwrap(),
+        )
+    };
+
+    let config = ServerConfig {
+        url_style: UrlStyle::VirtualHosted,
+        virtual_host_domain: Some("localhost".to_string()),
+        ..Default::default()
+    };
+    let app = object_store::router::create_router_with_config(service.clone(), &config);
+
+    let response = request(
+        &app,
+        "PUT",
+        "mybucket.localhost",
+        "/docs/hello.txt",
+        "hello",
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(service
+        .object_exists("mybucket", "docs/hello.txt")
+        .await
+        .unwrap());
+
+    let response = request(
+        &app,
+        "GET",
+        "mybucket.localhost:8080",
+        "/docs/hello.txt",
+        "",
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"hello");
+
+    // The bucket root lists its objects, and query strings are kept
+    let response = request(&app, "GET", "mybucket.localhost", "/?prefix=docs/", "")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let listing: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(listing["objects"].as_array().unwrap().len(), 1);
+    assert_eq!(listing["objects"][0]["key"], "docs/hello.txt");
+
+    let response = request(&app, "GET", "otherbucket.localhost", "/docs/hello.txt", "")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // The bare host still serves the path-style API
+    let response = request(
+        &app,
+        "GET",
+        "localhost",
+        "/buckets/mybucket/objects/docs/hello.txt",
+        "",
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let config = ServerConfig {
+        url_style: UrlStyle::VirtualHosted,
+        virtual_host_domain: Some("storage.example.com".to_string()),
+        ..Default::default()
+    };
+    let app = object_store::router::create_router_with_config(service.clone(), &config);
+
+    let response = request(
+        &app,
+        "GET",
+        "mybucket.storage.example.com",
+        "/docs/hello.txt",
+        "",
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = request(&app, "GET", "mybucket.elsewhere.com", "/docs/hello.txt", "")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // The service's own host name is not read as a bucket
+    service.create_bucket("storage").await.unwrap();
+    let response = request(&app, "GET", "storage.example.com", "/", "")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Path style, the default, ignores the Host header
+    let app = object_store::router::create_router(service.clone());
+    let response = request(&app, "GET", "mybucket.localhost", "/docs/hello.txt", "")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_request_body_limit() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let config = object_store::config::ServerConfig {
+        max_body_bytes: Some(10),
+        ..Default::default()
+    };
+    let app = object_store::router::create_router_with_config(service.clone(), &config);
+
+    service.create_bucket("test-bucket").await.unwrap();
+
+    let put = |key: &str, body: Body| {
+        app.clone().oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/buckets/test-bucket/objects/{}", key))
+                .body(body)
+                .unwrap(),
+        )
+    };
+
+    let response = put("small.txt", Body::from(vec![b'a'; 9])).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = put("large.txt", Body::from(vec![b'a'; 11])).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Without a Content-Length the limit is enforced while streaming
+    let chunks: Vec<Result<Bytes, std::io::Error>> = vec![
+        Ok(Bytes::from(vec![b'a'; 6])),
+        Ok(Bytes::from(vec![b'a'; 5])),
+    ];
+    let response = put("chunked.txt", Body::from_stream(stream::iter(chunks)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    assert!(!service
+        .object_exists("test-bucket", "large.txt")
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_bucket_max_object_bytes() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/buckets")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"name": "limited-bucket", "max_object_bytes": 10}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let bucket: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(bucket["max_object_bytes"], 10);
+
+    let put = |body: Body| {
+        app.clone().oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/buckets/limited-bucket/objects/file.bin")
+                .body(body)
+                .unwrap(),
+        )
+    };
+
+    let response = put(Body::from(vec![0u8; 9])).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = put(Body::from(vec![0u8; 11])).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let chunks: Vec<Result<Bytes, std::io::Error>> =
+        vec![Ok(Bytes::from(vec![1u8; 6])), Ok(Bytes::from(vec![1u8; 5]))];
+    let response = put(Body::from_stream(stream::iter(chunks))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // The rejected uploads left the stored object as it was
+    let object = service
+        .get_object("limited-bucket", "file.bin")
+        .await
+        .unwrap();
+    let chunks: Vec<_> = futures::StreamExt::collect(object.stream).await;
+    let body: Vec<u8> = chunks.into_iter().flat_map(Result::unwrap).collect();
+    assert_eq!(body, vec![0u8; 9]);
+}
+
+#[tokio::test]
+async fn test_idempotency_key_replays_create_responses() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    async fn send(
+        app: &axum::Router,
+        request: Request<Body>,
+    ) -> (StatusCode, bool, serde_json::Value) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let replayed = response.headers().get("idempotent-replayed").is_some();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, replayed, serde_json::from_slice(&body).unwrap())
+    }
+    let create_named = |name: &str, key: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/buckets")
+            .header("content-type", "application/json")
+            .header("idempotency-key", key)
+            .body(Body::from(json!({ "name": name }).to_string()))
+            .unwrap()
+    };
+    let create = |key: &str| create_named("retried-bucket", key);
+
+    let (status, replayed, first) = send(&app, create("create-1")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!replayed);
+
+    // The bucket exists and has since been changed, so running the request
+    // again would conflict and report the new description
+    service
+        .update_bucket_metadata(
+            "retried-bucket",
+            Some("changed".to_string()),
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    let (status, replayed, second) = send(&app, create("create-1")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(replayed);
+    assert_eq!(second, first);
+
+    let (status, _, _) = send(&app, create("create-2")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // The same key naming another bucket is a different request
+    let (status, replayed, other) = send(&app, create_named("other-bucket", "create-1")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!replayed);
+    assert_eq!(other["name"], "other-bucket");
+    service.get_bucket("other-bucket").await.unwrap();
+
+    // And so is the same key sent by another caller
+    let mut request = create("create-1");
+    request.headers_mut().insert(
+        "authorization",
+        "Bearer osk_another_caller".parse().unwrap(),
+    );
+    let (status, replayed, _) = send(&app, request).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(!replayed);
+
+    let put = |key: &str, body: &'static str| {
+        Request::builder()
+            .method("PUT")
+            .uri(format!("/buckets/retried-bucket/objects/{}", key))
+            .header("idempotency-key", "upload-1")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    let (status, _, first) = send(&app, put("file.txt", "first")).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, replayed, second) = send(&app, put("file.txt", "second")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(replayed);
+    assert_eq!(second, first);
+
+    let object = service
+        .get_object("retried-bucket", "file.txt")
+        .await
+        .unwrap();
+    let body: Vec<Bytes> = futures::StreamExt::collect::<Vec<_>>(object.stream)
+        .await
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(body.concat(), b"first");
+
+    // The same key on another object is a different request
+    let (status, replayed, other) = send(&app, put("other.txt", "second")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!replayed);
+    assert_ne!(other["etag"], first["etag"]);
+}
+
+#[tokio::test]
+async fn test_update_bucket_description_and_tags() {
+    let (service, temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+    let created = service.create_bucket("tagged-bucket").await.unwrap();
+    assert_eq!(created.updated_at, created.created_at);
+
+    let patch = |body: serde_json::Value| {
+        app.clone().oneshot(
+            Request::builder()
+                .method("PATCH")
+                .uri("/buckets/tagged-bucket")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+    };
+
+    let response = patch(json!({
+        "description": "Warehouse exports",
+        "tags": {"team": "data-eng", "env": "staging"}
+    }))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Tags merge, null removes one, and an omitted description is kept
+    let response = patch(json!({"tags": {"env": "prod", "team": null, "tier": "gold"}}))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let bucket: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(bucket["description"], "Warehouse exports");
+    assert_eq!(bucket["tags"], json!({"env": "prod", "tier": "gold"}));
+    let timestamp = |field: &str| {
+        chrono::DateTime::parse_from_rfc3339(bucket[field].as_str().unwrap()).unwrap()
+    };
+    assert_eq!(bucket["created_at"], created.created_at.as_str());
+    assert!(timestamp("updated_at") > timestamp("created_at"));
+
+    let expected_tags = HashMap::from([
+        ("env".to_string(), "prod".to_string()),
+        ("tier".to_string(), "gold".to_string()),
+    ]);
+
+    // Reloaded from the stored record once the cache refreshes
+    service.metadata().force_refresh().await.unwrap();
+    let bucket = service.get_bucket("tagged-bucket").await.unwrap();
+    assert_eq!(bucket.description.as_deref(), Some("Warehouse exports"));
+    assert_eq!(bucket.tags, expected_tags);
+
+    // And by a fresh service over the same storage, as after a restart
+    let backend = Arc::new(LocalBackend::new(
+        temp_dir.path().to_path_buf(),
+        "test-physical-bucket".to_string(),
+    ));
+    let metadata = Arc::new(MetadataStore::new(backend.clone()).await.unwrap());
+    let restarted = ObjectStoreService::new(backend, metadata);
+    let bucket = restarted.get_bucket("tagged-bucket").await.unwrap();
+    assert_eq!(bucket.description.as_deref(), Some("Warehouse exports"));
+    assert_eq!(bucket.tags, expected_tags);
+
+    let response = patch(json!({"description": ""})).await.unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let bucket: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(bucket.get("description").is_none());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PATCH")
+                .uri("/buckets/missing-bucket")
+                .header("content-type", "application/json")
+                .body(Body::from("{}"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_list_objects_metadata_filter() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("test-bucket").await.unwrap();
+
+    for i in 0..50 {
+        let env = if i % 2 == 0 { "prod" } else { "staging" };
+        service
+            .put_object_reader(
+                "test-bucket",
+                &format!("file-{:02}.txt", i),
+                "data".as_bytes(),
+                None,
+                HashMap::from([("env".to_string(), env.to_string())]),
+            )
+            .await
+            .unwrap();
+    }
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/buckets/test-bucket/objects?metadata_filter=env%3Dprod")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let mut keys: Vec<&str> = json["objects"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|o| {
+            assert_eq!(o["metadata"]["env"], "prod");
+            o["key"].as_str().unwrap()
+        })
+        .collect();
+    keys.sort();
+
+    let expected: Vec<String> = (0..50)
+        .step_by(2)
+        .map(|i| format!("file-{:02}.txt", i))
+        .collect();
+    assert_eq!(keys, expected);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/buckets/test-bucket/objects?metadata_filter=env")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+async fn create_recycle_bin_bucket(app: &axum::Router, name: &str, retention_days: u32) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/buckets")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "name": name,
+                        "recycle_bin_enabled": true,
+                        "recycle_bin_retention_days": retention_days,
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+async fn list_recycle_bin(app: &axum::Router, bucket: &str) -> Vec<serde_json::Value> {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/buckets/{}/recyclebin", bucket))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    json["objects"].as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn test_recycle_bin_delete_and_restore() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    create_recycle_bin_bucket(&app, "recycle-bucket", 30).await;
+
+    service
+        .put_object_reader(
+            "recycle-bucket",
+            "docs/report.txt",
+            "precious data".as_bytes(),
+            Some("text/plain".to_string()),
+            HashMap::from([("author".to_string(), "alice".to_string())]),
+        )
+        .await
+        .unwrap();
+
+    service
+        .delete_object("recycle-bucket", "docs/report.txt")
+        .await
+        .unwrap();
+
+    // Gone from the bucket, present in the recycle bin
+    assert!(service
+        .list_objects("recycle-bucket", None, None)
+        .await
+        .unwrap()
+        .is_empty());
+    let entries = list_recycle_bin(&app, "recycle-bucket").await;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["key"], "docs/report.txt");
+    assert_eq!(entries[0]["size"], 13);
+    let version = entries[0]["version"].as_str().unwrap().to_string();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/buckets/recycle-bucket/recyclebin/restore")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"key": "docs/report.txt", "version": version}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut restored = service
+        .get_object("recycle-bucket", "docs/report.txt")
+        .await
+        .unwrap();
+    let mut data = Vec::new();
+    while let Some(chunk) = futures::StreamExt::next(&mut restored.stream).await {
+        data.extend_from_slice(&chunk.unwrap());
+    }
+    assert_eq!(data, b"precious data");
+    assert_eq!(restored.metadata.custom_metadata["author"], "alice");
+    assert!(list_recycle_bin(&app, "recycle-bucket").await.is_empty());
+
+    // Restoring the same version twice fails
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/buckets/recycle-bucket/recyclebin/restore")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"key": "docs/report.txt", "version": version}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_recycle_bin_permanent_delete() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    create_recycle_bin_bucket(&app, "recycle-bucket", 30).await;
+
+    service
+        .put_object_reader(
+            "recycle-bucket",
+            "file.txt",
+            "data".as_bytes(),
+            None,
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    service
+        .delete_object("recycle-bucket", "file.txt")
+        .await
+        .unwrap();
+
+    let entries = list_recycle_bin(&app, "recycle-bucket").await;
+    let version = entries[0]["version"].as_str().unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri(format!("/buckets/recycle-bucket/recyclebin/{}", version))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(list_recycle_bin(&app, "recycle-bucket").await.is_empty());
+}
+
+#[tokio::test]
+async fn test_recycle_bin_retention_expiry() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    create_recycle_bin_bucket(&app, "short-retention", 7).await;
+    create_recycle_bin_bucket(&app, "keep-forever", 0).await;
+
+    for bucket in ["short-retention", "keep-forever"] {
+        service
+            .put_object_reader(bucket, "file.txt", "data".as_bytes(), None, HashMap::new())
+            .await
+            .unwrap();
+        service.delete_object(bucket, "file.txt").await.unwrap();
+    }
+
+    let now = chrono::Utc::now();
+
+    // Still within the retention period
+    let purged = service
+        .purge_expired_recycle_bin(now + chrono::Duration::days(6))
+        .await
+        .unwrap();
+    assert_eq!(purged, 0);
+
+    let purged = service
+        .purge_expired_recycle_bin(now + chrono::Duration::days(8))
+        .await
+        .unwrap();
+    assert_eq!(purged, 1);
+
+    assert!(list_recycle_bin(&app, "short-retention").await.is_empty());
+    assert_eq!(list_recycle_bin(&app, "keep-forever").await.len(), 1);
+}
+
+async fn put_lifecycle(
+    app: &axum::Router,
+    bucket: &str,
+    rules: serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/buckets/{}/lifecycle", bucket))
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "rules": rules }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_lifecycle_rules_round_trip() {
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("lifecycle-bucket").await.unwrap();
+    let app = object_store::router::create_router(service);
+
+    let rules = json!([
+        { "id": "expire-logs", "prefix": "logs/", "expiration_days": 30 },
+        { "id": "archive-reports", "prefix": "reports/", "transition_to": "archive" }
+    ]);
+    let (status, body) = put_lifecycle(&app, "lifecycle-bucket", rules.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["rules"], rules);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/buckets/lifecycle-bucket/lifecycle")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["rules"], rules);
+
+    // A rule has to do something, and ids tell rules apart
+    let (status, _) = put_lifecycle(&app, "lifecycle-bucket", json!([{ "id": "noop" }])).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let duplicate = json!([
+        { "id": "twice", "expiration_days": 1 },
+        { "id": "twice", "expiration_days": 2 }
+    ]);
+    let (status, _) = put_lifecycle(&app, "lifecycle-bucket", duplicate).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = put_lifecycle(&app, "missing-bucket", json!([])).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_lifecycle_expiry_rule_deletes_matching_objects() {
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("expiring").await.unwrap();
+    for key in [
+        "logs/1.txt",
+        "logs/2.txt",
+        "data/keep.txt",
+        "data/archive.txt",
+    ] {
+        service
+            .put_object_reader("expiring", key, "data".as_bytes(), None, HashMap::new())
+            .await
+            .unwrap();
+    }
+
+    let app = object_store::router::create_router(service.clone());
+    let rules = json!([
+        { "id": "expire-logs", "prefix": "logs/", "expiration_days": 0 },
+        { "id": "expire-data", "prefix": "data/", "expiration_days": 30 }
+    ]);
+    let (status, _) = put_lifecycle(&app, "expiring", rules).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let applied = service
+        .apply_lifecycle_rules(chrono::Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(applied, 2);
+
+    let mut remaining: Vec<String> = service
+        .list_objects("expiring", None, None)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|o| o.key)
+        .collect();
+    remaining.sort();
+    assert_eq!(remaining, vec!["data/archive.txt", "data/keep.txt"]);
+
+    // The local backend has a single storage tier, so transitions leave
+    // objects where they are
+    let rules = json!([{ "id": "archive", "prefix": "data/", "transition_to": "archive" }]);
+    let (status, _) = put_lifecycle(&app, "expiring", rules).await;
+    assert_eq!(status, StatusCode::OK);
+    let applied = service
+        .apply_lifecycle_rules(chrono::Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(applied, 2);
+    assert!(service
+        .object_exists("expiring", "data/archive.txt")
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_request_id_in_error_body() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/buckets/missing-bucket/objects/file.txt")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let header_id = response.headers()["x-request-id"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(uuid::Uuid::parse_str(&header_id).is_ok());
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["request_id"], header_id);
+
+    // A caller-supplied ID is kept rather than replaced
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/buckets")
+                .header("x-request-id", "trace-abc")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-request-id"], "trace-abc");
+}
+
+#[tokio::test]
+async fn test_internal_endpoints_not_on_public_router() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let public = object_store::router::create_router(service.clone());
+    let internal = object_store::router::create_internal_router(service.clone());
+
+    service.create_bucket("test").await.unwrap();
+    service
+        .put_object_reader("test", "a.txt", "hello".as_bytes(), None, HashMap::new())
+        .await
+        .unwrap();
+
+    for uri in ["/internal/scan-bucket/test", "/health", "/ping", "/metrics"] {
+        let response = public
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", uri);
+
+        let response = internal
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+    }
+
+    let response = internal
+        .oneshot(
+            Request::builder()
+                .uri("/internal/scan-bucket/test")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["object_count"], 1);
+    assert_eq!(json["total_bytes"], 5);
+
+    // Data endpoints stay on the public router only
+    let response = public
+        .oneshot(
+            Request::builder()
+                .uri("/buckets")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+/// A service whose upload hooks may call local servers, such as mockito's.
+#[cfg(feature = "hooks")]
+async fn setup_hooks_service() -> (Arc<ObjectStoreService>, TempDir) {
+    let hooks = object_store::pipeline::HooksConfig {
+        allowed_hosts: vec!["127.0.0.1".to_string()],
+    };
+    setup_test_service_with(|service| service.with_hooks_config(&hooks)).await
+}
+
+#[cfg(feature = "hooks")]
+async fn create_bucket_with_pipeline(
+    app: &axum::Router,
+    name: &str,
+    pipeline: serde_json::Value,
+) -> StatusCode {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/buckets")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({ "name": name, "pipeline": pipeline }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+async fn put_text(app: &axum::Router, bucket: &str, key: &str) -> StatusCode {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/buckets/{}/objects/{}", bucket, key))
+                .header("content-type", "text/plain")
+                .header("x-object-meta-owner", "alice")
+                .body(Body::from("hello"))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+#[cfg(feature = "hooks")]
+#[tokio::test]
+async fn test_pre_upload_hook_accepts_and_post_upload_hook_fires() {
+    let (service, _temp_dir) = setup_hooks_service().await;
+    let app = object_store::router::create_router(service.clone());
+    let mut hooks = mockito::Server::new_async().await;
+
+    let pre = hooks
+        .mock("POST", "/scan")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "event": "pre_upload",
+            "bucket": "hooked",
+            "key": "a.txt",
+            "content_type": "text/plain",
+            "metadata": { "owner": "alice" },
+        })))
+        .with_status(200)
+        .expect(1)
+        .create_async()
+        .await;
+    let post = hooks
+        .mock("POST", "/notify")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "event": "post_upload",
+            "key": "a.txt",
+            "size": 5,
+        })))
+        .with_status(200)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let pipeline = json!({
+        "pre_upload": [{ "url": format!("{}/scan", hooks.url()), "timeout_ms": 1000 }],
+        "post_upload": [{ "url": format!("{}/notify", hooks.url()) }],
+    });
+    assert_eq!(
+        create_bucket_with_pipeline(&app, "hooked", pipeline).await,
+        StatusCode::OK
+    );
+
+    assert_eq!(put_text(&app, "hooked", "a.txt").await, StatusCode::OK);
+    assert!(service.object_exists("hooked", "a.txt").await.unwrap());
+    pre.assert_async().await;
+
+    // Post-upload hooks run in the background
+    for _ in 0..50 {
+        if post.matched_async().await {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    post.assert_async().await;
+}
+
+#[cfg(feature = "hooks")]
+#[tokio::test]
+async fn test_pre_upload_hook_rejects_upload() {
+    let (service, _temp_dir) = setup_hooks_service().await;
+    let app = object_store::router::create_router(service.clone());
+    let mut hooks = mockito::Server::new_async().await;
+
+    let reject = hooks
+        .mock("POST", "/scan")
+        .with_status(403)
+        .with_body("virus found")
+        .expect(1)
+        .create_async()
+        .await;
+
+    let pipeline = json!({
+        "pre_upload": [{ "url": format!("{}/scan", hooks.url()), "timeout_ms": 1000 }],
+    });
+    create_bucket_with_pipeline(&app, "scanned", pipeline).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/buckets/scanned/objects/bad.txt")
+                .body(Body::from("payload"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(json["error"].as_str().unwrap().contains("virus found"));
+
+    reject.assert_async().await;
+    assert!(!service.object_exists("scanned", "bad.txt").await.unwrap());
+
+    // An unreachable hook fails closed
+    let pipeline = json!({ "pre_upload": [{ "url": "http://127.0.0.1:1/scan" }] });
+    create_bucket_with_pipeline(&app, "unreachable", pipeline).await;
+    assert_eq!(
+        put_text(&app, "unreachable", "a.txt").await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[cfg(feature = "hooks")]
+#[tokio::test]
+async fn test_hook_urls_must_be_on_allowed_hosts() {
+    let hooks = object_store::pipeline::HooksConfig {
+        allowed_hosts: vec![
+            "scanner.internal".to_string(),
+            "indexer.internal:8080".to_string(),
+        ],
+    };
+    let (service, _temp_dir) =
+        setup_test_service_with(|service| service.with_hooks_config(&hooks)).await;
+    let app = object_store::router::create_router(service.clone());
+
+    for (url, allowed) in [
+        ("http://scanner.internal/check", true),
+        ("https://SCANNER.internal:8443/check", true),
+        ("http://indexer.internal:8080/notify", true),
+        ("http://indexer.internal/notify", false),
+        ("http://169.254.169.254/latest/meta-data", false),
+        ("http://scanner.internal.evil.example/check", false),
+        ("file:///etc/passwd", false),
+    ] {
+        let pipeline = json!({ "post_upload": [{ "url": url }] });
+        let status = create_bucket_with_pipeline(&app, "hooked", pipeline).await;
+        let expected = if allowed {
+            StatusCode::OK
+        } else {
+            StatusCode::BAD_REQUEST
+        };
+        assert_eq!(status, expected, "{}", url);
+        if allowed {
+            service.delete_bucket("hooked").await.unwrap();
+        }
+    }
+}
+
+#[cfg(not(feature = "hooks"))]
+#[tokio::test]
+async fn test_pipeline_rejected_without_hooks_feature() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let response = object_store::router::create_router(service.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/buckets")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "name": "hooked",
+                        "pipeline": { "pre_upload": [{ "url": "http://scanner.internal/check" }] },
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(service.get_bucket("hooked").await.is_err());
+}
+
+async fn upsert(
+    app: &axum::Router,
+    key: &str,
+    body: &'static str,
+    expected_etag: Option<&str>,
+) -> (StatusCode, serde_json::Value) {
+    let mut request = Request::builder()
+        .method("PUT")
+        .uri(format!("/buckets/upserts/upsert/{}", key))
+        .header("content-type", "text/plain");
+    if let Some(etag) = expected_etag {
+        request = request.header("x-expected-etag", etag);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body)).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_upsert_object() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+    service.create_bucket("upserts").await.unwrap();
+
+    let read = |key: &'static str| {
+        let service = service.clone();
+        async move {
+            let data = service.get_object("upserts", key).await.unwrap();
+            let chunks: Vec<_> = futures::StreamExt::collect(data.stream).await;
+            chunks
+                .into_iter()
+                .map(|c| c.unwrap())
+                .fold(Vec::new(), |mut acc, c| {
+                    acc.extend_from_slice(&c);
+                    acc
+                })
+        }
+    };
+
+    // Fresh create
+    let (status, created) = upsert(&app, "doc.txt", "first", None).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let etag = created["etag"].as_str().unwrap().to_string();
+
+    // Existing object without an expected ETag is left alone
+    let (status, existing) = upsert(&app, "doc.txt", "second", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(existing["etag"], etag.as_str());
+    assert_eq!(read("doc.txt").await, b"first");
+
+    // Matching ETag (quoted, as HTTP clients send it) is a no-op
+    let (status, _) = upsert(&app, "doc.txt", "third", Some(&format!("\"{}\"", etag))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(read("doc.txt").await, b"first");
+
+    // A different expected ETag forces an overwrite
+    let (status, overwritten) = upsert(&app, "doc.txt", "fourth", Some("stale")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(overwritten["etag"], etag.as_str());
+    assert_eq!(read("doc.txt").await, b"fourth");
+}
+
+#[tokio::test]
+async fn test_concurrent_upserts_create_once() {
+    const WRITERS: u8 = 10;
+
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("upserts").await.unwrap();
+
+    let upserts = (0..WRITERS).map(|writer| {
+        let service = service.clone();
+        tokio::spawn(async move {
+            // Yields between chunks so the writers interleave
+            let body: object_store_backends::ByteStream = Box::pin(futures::StreamExt::then(
+                stream::iter(0..4),
+                move |_| async move {
+                    tokio::task::yield_now().await;
+                    Ok(Bytes::from(vec![b'a' + writer; 1024]))
+                },
+            ));
+            service
+                .upsert_object("upserts", "once.bin", body, None, HashMap::new(), None)
+                .await
+        })
+    });
+    let results: Vec<_> = futures::future::join_all(upserts)
+        .await
+        .into_iter()
+        .map(|upsert| upsert.unwrap().unwrap())
+        .collect();
+
+    // One writer creates the object; the others find it and leave it alone
+    assert_eq!(results.iter().filter(|(_, created)| *created).count(), 1);
+    let stored = service.head_object("upserts", "once.bin").await.unwrap();
+    assert!(results
+        .iter()
+        .all(|(metadata, _)| metadata.etag == stored.etag));
+}
+
+#[tokio::test]
+async fn test_strict_content_type() {
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("types").await.unwrap();
+
+    let upload = |app: axum::Router, content_type: &'static str| {
+        app.oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/buckets/types/objects/doc")
+                .header("content-type", content_type)
+                .body(Body::from("{}"))
+                .unwrap(),
+        )
+    };
+
+    // Lenient by default, so existing clients keep working
+    let lenient = object_store::router::create_router(service.clone());
+    let response = upload(lenient, "not/valid/mime").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let strict = object_store::router::create_router_with_config(
+        service.clone(),
+        &object_store::config::ServerConfig {
+            strict_content_type: true,
+            ..Default::default()
+        },
+    );
+    for invalid in ["foo", "", "not/valid/mime"] {
+        let response = upload(strict.clone(), invalid).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{:?}", invalid);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(
+            String::from_utf8_lossy(&body).contains("Content-Type"),
+            "{:?}",
+            body
+        );
+    }
+
+    let response = upload(strict, "application/json; charset=utf-8")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let head = service.head_object("types", "doc").await.unwrap();
+    assert_eq!(
+        head.content_type.as_deref(),
+        Some("application/json; charset=utf-8")
+    );
+}
+
+#[tokio::test]
+async fn test_amz_meta_headers() {
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("amz").await.unwrap();
+    let app = object_store::router::create_router(service.clone());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/buckets/amz/objects/doc.txt")
+                .header("content-type", "text/plain")
+                .header("x-amz-meta-owner", "alice")
+                .header("x-amz-meta-team", "storage")
+                .header("x-object-meta-team", "platform")
+                .body(Body::from("hello"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Either prefix is stored, with x-object-meta winning a clash
+    let head = service.head_object("amz", "doc.txt").await.unwrap();
+    assert_eq!(head.custom_metadata["owner"], "alice");
+    assert_eq!(head.custom_metadata["team"], "platform");
+
+    let fetch = |app: axum::Router, method: &'static str, compat: bool| {
+        let mut request = Request::builder()
+            .method(method)
+            .uri("/buckets/amz/objects/doc.txt");
+        if compat {
+            request = request.header("x-amz-compat", "true");
+        }
+        app.oneshot(request.body(Body::empty()).unwrap())
+    };
+
+    for method in ["GET", "HEAD"] {
+        let response = fetch(app.clone(), method, false).await.unwrap();
+        assert_eq!(response.headers()["x-object-meta-owner"], "alice");
+        assert!(response.headers().get("x-amz-meta-owner").is_none());
+
+        let response = fetch(app.clone(), method, true).await.unwrap();
+        assert_eq!(response.headers()["x-amz-meta-owner"], "alice");
+        assert_eq!(response.headers()["x-amz-meta-team"], "platform");
+        assert!(response.headers().get("x-object-meta-owner").is_none());
+    }
+
+    let with_style = |style| {
+        object_store::router::create_router_with_config(
+            service.clone(),
+            &object_store::config::ServerConfig {
+                metadata_header_style: style,
+                ..Default::default()
+            },
+        )
+    };
+
+    let amz = with_style(object_store::config::MetadataHeaderStyle::AmzMeta);
+    let response = fetch(amz, "GET", false).await.unwrap();
+    assert_eq!(response.headers()["x-amz-meta-owner"], "alice");
+    assert!(response.headers().get("x-object-meta-owner").is_none());
+
+    let both = with_style(object_store::config::MetadataHeaderStyle::Both);
+    let response = fetch(both, "HEAD", false).await.unwrap();
+    assert_eq!(response.headers()["x-amz-meta-owner"], "alice");
+    assert_eq!(response.headers()["x-object-meta-owner"], "alice");
+}
+
+#[tokio::test]
+async fn test_compact_backend_endpoint() {
+    let (service, temp_dir) = setup_test_service().await;
+    let internal = object_store::router::create_internal_router(service.clone());
+    service.create_bucket("compact").await.unwrap();
+
+    let orphan = temp_dir
+        .path()
+        .join("test-physical-bucket/compact/orphan.bin");
+    std::fs::write(&orphan, b"leftover").unwrap();
+
+    let compact = |dry_run: bool| {
+        internal.clone().oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/internal/compact-backend?dry_run={}", dry_run))
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+
+    let response = compact(true).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(report["orphaned_data"], json!(["compact/orphan.bin"]));
+    assert_eq!(report["reclaimed_bytes"], 8);
+    assert!(orphan.exists());
+
+    let response = compact(false).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!orphan.exists());
+}
+
+#[tokio::test]
+async fn test_cache_stats_endpoint() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let internal = object_store::router::create_internal_router(service.clone());
+    service.create_bucket("cached").await.unwrap();
+    service.get_bucket("cached").await.unwrap();
+
+    let response = internal
+        .oneshot(
+            Request::builder()
+                .uri("/internal/cache-stats")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(stats["hit_count"], 1);
+    assert_eq!(stats["miss_count"], 0);
+    assert_eq!(stats["refresh_count"], 1);
+}
+
+/// Parses Prometheus text exposition into `series -> value`, where a series is
+/// the metric name followed by its labels as written, e.g. `m{bucket="a"}`.
+fn parse_prometheus(text: &str) -> HashMap<String, f64> {
+    text.lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (series, value) = line.rsplit_once(' ')?;
+            Some((series.to_string(), value.parse().ok()?))
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_per_bucket_metrics() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+    let internal = object_store::router::create_internal_router(service.clone());
+
+    let buckets = ["metrics-a", "metrics-b", "metrics-c"];
+    for bucket in buckets {
+        service.create_bucket(bucket).await.unwrap();
+        for key in ["one.txt", "two.txt"] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("PUT")
+                        .uri(format!("/buckets/{}/objects/{}", bucket, key))
+                        .body(Body::from("12345"))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+    }
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/buckets/metrics-a/objects/missing.txt")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    service.refresh_bucket_metrics().await.unwrap();
+
+    let response = internal
+        .oneshot(
+            Request::builder()
+                .uri("/metrics")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let metrics = parse_prometheus(std::str::from_utf8(&body).unwrap());
+
+    for bucket in buckets {
+        assert_eq!(
+            metrics[&format!("object_store_bucket_object_count{{bucket=\"{}\"}}", bucket)],
+            2.0
+        );
+        assert_eq!(
+            metrics[&format!("object_store_bucket_size_bytes{{bucket=\"{}\"}}", bucket)],
+            10.0
+        );
+        assert_eq!(
+            metrics[&format!(
+                "object_store_requests_total{{bucket=\"{}\",operation=\"put_object\",status=\"200\"}}",
+                bucket
+            )],
+            2.0
+        );
+    }
+    assert_eq!(
+        metrics[r#"object_store_requests_total{bucket="metrics-a",operation="get_object",status="404"}"#],
+        1.0
+    );
+
+    // Requests naming a bucket that doesn't exist don't get a label of their own
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/buckets/metrics-missing/objects/file.txt")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // A deleted bucket's gauges are zeroed
+    for key in ["one.txt", "two.txt"] {
+        service.delete_object("metrics-c", key).await.unwrap();
+    }
+    service.delete_bucket("metrics-c").await.unwrap();
+
+    let metrics = parse_prometheus(&object_store::metrics::prometheus_handle().render());
+    assert!(!metrics
+        .keys()
+        .any(|series| series.contains(r#"bucket="metrics-missing""#)));
+    assert_eq!(
+        metrics[r#"object_store_bucket_object_count{bucket="metrics-c"}"#],
+        0.0
+    );
+    assert_eq!(
+        metrics[r#"object_store_bucket_size_bytes{bucket="metrics-c"}"#],
+        0.0
+    );
+}
+
+/// Serves from a `LocalBackend` but fails reads of keys named after a backend
+/// error, hands out presigned-style URLs that point back at the service, and
+/// answers health checks with `health`.
+struct RefusingBackend {
+    inner: LocalBackend,
+    health: fn() -> object_store_backends::BackendResult<()>,
+}
+
+impl RefusingBackend {
+    async fn service(
+        health: fn() -> object_store_backends::BackendResult<()>,
+    ) -> (Arc<ObjectStoreService>, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = Arc::new(RefusingBackend {
+            inner: LocalBackend::new(
+                temp_dir.path().to_path_buf(),
+                "test-physical-bucket".to_string(),
+            ),
+            health,
+        });
+        backend.init().await.unwrap();
+        let metadata = Arc::new(MetadataStore::new(backend.clone()).await.unwrap());
+        (
+            Arc::new(ObjectStoreService::new(backend, metadata)),
+            temp_dir,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl Backend for RefusingBackend {
+    async fn init(&self) -> object_store_backends::BackendResult<()> {
+        self.inner.init().await
+    }
+
+    fn backend_type(&self) -> &'static str {
+        self.inner.backend_type()
+    }
+
+    async fn put_object(
+        &self,
+        key: &str,
+        stream: object_store_backends::ByteStream,
+        content_type: Option<String>,
+        metadata: HashMap<String, String>,
+    ) -> object_store_backends::BackendResult<object_store_backends::ObjectMetadata> {
+        if key.ends_with("/slow-down") {
+            return Err(BackendError::RateLimited {
+                retry_after_secs: Some(5),
+            });
+        }
+        if key.ends_with("/too-large") {
+            return Err(BackendError::EntityTooLarge {
+                size: 6 * 1024 * 1024 * 1024,
+                max_size: 5 * 1024 * 1024 * 1024,
+            });
+        }
+        if key.starts_with("read-only/.health-check-") {
+            return Err(BackendError::PermissionDenied("Access Denied".to_string()));
+        }
+        self.inner
+            .put_object(key, stream, content_type, metadata)
+            .await
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+    ) -> object_store_backends::BackendResult<object_store_backends::ObjectData> {
+        match key.rsplit('/').next() {
+            Some("access-denied") => {
+                Err(BackendError::PermissionDenied("Access Denied".to_string()))
+            }
+            Some("slow-down") => Err(BackendError::RateLimited {
+                retry_after_secs: Some(5),
+            }),
+            _ => self.inner.get_object(key).await,
+        }
+    }
+
+    async fn head_object(
+        &self,
+        key: &str,
+    ) -> object_store_backends::BackendResult<object_store_backends::ObjectMetadata> {
+        self.inner.head_object(key).await
+    }
+
+    async fn delete_object(&self, key: &str) -> object_store_backends::BackendResult<()> {
+        self.inner.delete_object(key).await
+    }
+
+    async fn list_objects(
+        &self,
+        prefix: Option<&str>,
+        max_keys: Option<usize>,
+    ) -> object_store_backends::BackendResult<Vec<object_store_backends::ObjectMetadata>> {
+        self.inner.list_objects(prefix, max_keys).await
+    }
+
+    async fn get_public_url(
+        &self,
+        key: &str,
+        expiration_secs: u64,
+        _purpose: object_store_backends::PublicUrlPurpose,
+        _content_type: Option<&str>,
+    ) -> object_store_backends::BackendResult<String> {
+        let (bucket, key) = key.split_once('/').unwrap();
+        let now = chrono::Utc::now();
+        Ok(format!(
+            "http://localhost/buckets/{}/objects/{}?X-Amz-Date={}&X-Amz-Expires={}&X-Amz-Signature={}",
+            bucket,
+            key,
+            now.format("%Y%m%dT%H%M%SZ"),
+            expiration_secs,
+            now.timestamp_nanos_opt().unwrap()
+        ))
+    }
+
+    async fn health_check(&self) -> object_store_backends::BackendResult<()> {
+        (self.health)()
+    }
+}
+
+#[tokio::test]
+async fn test_backend_permission_and_throttling_errors_forwarded() {
+    let (service, _temp_dir) = RefusingBackend::service(|| Ok(())).await;
+    service.create_bucket("refusing").await.unwrap();
+    let app = object_store::router::create_router(service);
+
+    let get = |key: &str| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri(format!("/buckets/refusing/objects/{}", key))
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+
+    let response = get("access-denied").await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(response.headers().get("retry-after").is_none());
+
+    let response = get("slow-down").await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["retry-after"], "5");
+
+    // Context added to a failed upload doesn't change how it is reported
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/buckets/refusing/objects/slow-down")
+                .body(Body::from("data"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["retry-after"], "5");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        json["error"],
+        "Backend error: put_object bucket=refusing key=slow-down: Rate limited by backend"
+    );
+}
+
+#[tokio::test]
+async fn test_backend_size_limit_returns_413() {
+    let (service, _temp_dir) = RefusingBackend::service(|| Ok(())).await;
+    service.create_bucket("refusing").await.unwrap();
+    let app = object_store::router::create_router(service);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/buckets/refusing/objects/too-large")
+                .body(Body::from("data"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["max_size"], 5u64 * 1024 * 1024 * 1024);
+    assert_eq!(
+        json["error"],
+        "Backend error: put_object bucket=refusing key=too-large: \
+         Object of 6442450944 bytes exceeds the backend's limit of 5368709120 bytes"
+    );
+}
+
+#[tokio::test]
+async fn test_backend_error_chain_names_the_operation() {
+    use std::error::Error as _;
+
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("test").await.unwrap();
+    service
+        .put_object_reader("test", "file", "x".as_bytes(), None, HashMap::new())
+        .await
+        .unwrap();
+
+    // "file" is a regular file, so nothing can be stored below it
+    let err = service
+        .put_object_reader(
+            "test",
+            "file/nested.txt",
+            "x".as_bytes(),
+            None,
+            HashMap::new(),
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("Backend error: put_object bucket=test key=file/nested.txt: IO error on "),
+        "{}",
+        err
+    );
+
+    let context = err.source().unwrap();
+    assert!(matches!(
+        context.downcast_ref::<BackendError>(),
+        Some(BackendError::Context(e)) if e.context == "put_object bucket=test key=file/nested.txt"
+    ));
+    let io = context.source().unwrap();
+    assert!(matches!(
+        io.downcast_ref::<BackendError>(),
+        Some(BackendError::Io { .. })
+    ));
+    assert!(io.source().unwrap().is::<std::io::Error>());
+
+    let object_store::ServiceError::Backend(backend_err) = err else {
+        panic!("expected a backend error");
+    };
+    assert!(matches!(backend_err.root(), BackendError::Io { .. }));
+}
+
+#[tokio::test]
+async fn test_open_circuit_returns_service_unavailable() {
+    use object_store_backends::circuit_breaker::{CircuitBreakerBackend, CircuitBreakerConfig};
+
+    let temp_dir = TempDir::new().unwrap();
+    let refusing = Arc::new(RefusingBackend {
+        inner: LocalBackend::new(
+            temp_dir.path().to_path_buf(),
+            "test-physical-bucket".to_string(),
+        ),
+        health: || Ok(()),
+    });
+    let backend = Arc::new(CircuitBreakerBackend::new(
+        refusing,
+        CircuitBreakerConfig {
+            failure_threshold: 2,
+            open_duration_secs: 60,
+            success_threshold: 1,
+        },
+    ));
+    backend.init().await.unwrap();
+    let metadata = Arc::new(MetadataStore::new(backend.clone()).await.unwrap());
+    let service = Arc::new(ObjectStoreService::new(backend, metadata));
+    service.create_bucket("refusing").await.unwrap();
+    let app = object_store::router::create_router(service);
+
+    let get = |key: &str| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri(format!("/buckets/refusing/objects/{}", key))
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+
+    for _ in 0..2 {
+        let response = get("slow-down").await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    // Every backend call now fails fast, including ones that would succeed
+    let response = get("missing.txt").await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let retry_after: u64 = response.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= 60);
+}
+
+async fn deep_health(service: Arc<ObjectStoreService>) -> (StatusCode, serde_json::Value) {
+    let response = object_store::router::create_internal_router(service)
+        .oneshot(
+            Request::builder()
+                .uri("/health?deep=true")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_deep_health_check() {
+    let (service, _temp_dir) = RefusingBackend::service(|| Ok(())).await;
+    let (status, body) = deep_health(service.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "healthy");
+    assert_eq!(body["backend"]["backend_type"], "local");
+    assert_eq!(body["backend"]["writable"], true);
+    assert!(body["backend"]["latency_ms"].is_u64());
+
+    // The canary goes into the first bucket and is removed again
+    service.create_bucket("logs").await.unwrap();
+    let (_, body) = deep_health(service.clone()).await;
+    assert_eq!(body["backend"]["writable"], true);
+    assert!(service
+        .list_objects("logs", None, None)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Reachable but refusing writes
+    let (service, _temp_dir) = RefusingBackend::service(|| Ok(())).await;
+    service.create_bucket("read-only").await.unwrap();
+    let (status, body) = deep_health(service).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "degraded");
+    assert_eq!(body["backend"]["writable"], false);
+
+    // Throttling means the backend is up but struggling
+    let (service, _temp_dir) = RefusingBackend::service(|| {
+        Err(BackendError::RateLimited {
+            retry_after_secs: None,
+        })
+    })
+    .await;
+    let (status, body) = deep_health(service).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "degraded");
+    assert_eq!(body["backend"]["writable"], false);
+
+    let (service, _temp_dir) =
+        RefusingBackend::service(|| Err(BackendError::Provider("connection refused".to_string())))
+            .await;
+    let (status, body) = deep_health(service.clone()).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["status"], "unhealthy");
+    assert!(body["error"]
+        .as_str()
+        .unwrap()
+        .contains("connection refused"));
+    assert!(matches!(
+        service.health_check().await,
+        Err(object_store::ServiceError::Unavailable(_))
+    ));
+
+    // The shallow check never touches the backend
+    let response = object_store::router::create_internal_router(service)
+        .oneshot(
+            Request::builder()
+                .uri("/health")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_put_object_rejects_invalid_metadata() {
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("meta").await.unwrap();
+    let app = object_store::router::create_router(service.clone());
+
+    let put = |name: &str, value: String| {
+        app.clone().oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/buckets/meta/objects/file.txt")
+                .header(format!("x-object-meta-{}", name), value)
+                .body(Body::from("hello"))
+                .unwrap(),
+        )
+    };
+
+    let response = put("owner_id", "alice".to_string()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(error["error"].as_str().unwrap().contains("owner_id"));
+
+    let response = put("owner", "a".repeat(2049)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(!service.object_exists("meta", "file.txt").await.unwrap());
+
+    let response = put("owner", "a".repeat(2048)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+/// A service that can replicate to a local backend at each of `replicas`,
+/// under the paired id.
+async fn setup_replicating_service(
+    replicas: &[(&str, &std::path::Path)],
+) -> (Arc<ObjectStoreService>, TempDir) {
+    let targets = replicas
+        .iter()
+        .map(|(id, root)| {
+            let config = serde_json::from_value(json!({"type": "local", "root_path": root}));
+            (id.to_string(), config.unwrap())
+        })
+        .collect();
+    setup_test_service_with(|service| service.with_replica_targets(&targets)).await
+}
+
+async fn create_bucket_request(
+    service: &Arc<ObjectStoreService>,
+    body: serde_json::Value,
+) -> StatusCode {
+    object_store::router::create_router(service.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/buckets")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+async fn create_replicated_bucket(
+    service: &Arc<ObjectStoreService>,
+    name: &str,
+    target: &str,
+    r#async: bool,
+) {
+    let status = create_bucket_request(
+        service,
+        json!({
+            "name": name,
+            "replication": {"target": target, "async": r#async},
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+async fn replication_lag(service: &Arc<ObjectStoreService>, bucket: &str) -> u64 {
+    let response = object_store::router::create_router(service.clone())
+        .oneshot(
+            Request::builder()
+                .uri(format!("/buckets/{}/replication-lag", bucket))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let lag: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    lag["pending_objects"].as_u64().unwrap()
+}
+
+async fn replica_object(root: &std::path::Path, full_key: &str) -> Option<Bytes> {
+    let replica = LocalBackend::new(root.to_path_buf(), "object-store-data".to_string());
+    let object = replica.get_object(full_key).await.ok()?;
+    let chunks: Vec<_> = futures::StreamExt::collect(object.stream).await;
+    Some(
+        chunks
+            .into_iter()
+            .flat_map(Result::unwrap)
+            .collect::<Vec<u8>>()
+            .into(),
+    )
+}
+
+#[tokio::test]
+async fn test_sync_replication() {
+    let replica_dir = TempDir::new().unwrap();
+    let flaky_root = replica_dir.path().join("flaky");
+    let (service, temp_dir) =
+        setup_replicating_service(&[("mirror", replica_dir.path()), ("flaky", &flaky_root)]).await;
+    create_replicated_bucket(&service, "mirrored", "mirror", false).await;
+
+    service
+        .put_object_reader(
+            "mirrored",
+            "a.txt",
+            &b"replicated"[..],
+            None,
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        replica_object(replica_dir.path(), "mirrored/a.txt")
+            .await
+            .as_deref(),
+        Some(&b"replicated"[..])
+    );
+
+    create_replicated_bucket(&service, "flaky", "flaky", false).await;
+    service
+        .put_object_str("flaky", "a.txt", "first", None, HashMap::new())
+        .await
+        .unwrap();
+
+    // A target that can no longer be written fails the upload, keeping the
+    // object it would have replaced
+    std::fs::remove_dir_all(&flaky_root).unwrap();
+    std::fs::write(&flaky_root, b"").unwrap();
+    for (key, data) in [("a.txt", "second"), ("b.txt", "never stored")] {
+        let result = service
+            .put_object_str("flaky", key, data, None, HashMap::new())
+            .await;
+        assert!(result.is_err());
+    }
+    let object = service.get_object("flaky", "a.txt").await.unwrap();
+    let data: Vec<_> = futures::StreamExt::collect(object.stream).await;
+    assert_eq!(
+        data.into_iter()
+            .flat_map(Result::unwrap)
+            .collect::<Vec<u8>>(),
+        b"first"
+    );
+    assert!(!service.object_exists("flaky", "b.txt").await.unwrap());
+    assert!(!temp_dir
+        .path()
+        .join("test-physical-bucket/.metadata/replication-staging/flaky/a.txt")
+        .exists());
+}
+
+#[tokio::test]
+async fn test_replication_target_must_be_configured() {
+    let replica_dir = TempDir::new().unwrap();
+    let (service, _temp_dir) = setup_replicating_service(&[("mirror", replica_dir.path())]).await;
+
+    let status = create_bucket_request(
+        &service,
+        json!({"name": "elsewhere", "replication": {"target": "nowhere"}}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Backends can't be given inline
+    let status = create_bucket_request(
+        &service,
+        json!({
+            "name": "elsewhere",
+            "replication": {"target_backend": {"type": "local", "root_path": replica_dir.path()}},
+        }),
+    )
+    .await;
+    assert!(status.is_client_error(), "{}", status);
+    assert!(service.get_bucket("elsewhere").await.is_err());
+}
+
+#[tokio::test]
+async fn test_async_replication_retries_failed_copies() {
+    let replica_dir = TempDir::new().unwrap();
+    let broken_root = replica_dir.path().join("later");
+    let (service, _temp_dir) =
+        setup_replicating_service(&[("background", replica_dir.path()), ("later", &broken_root)])
+            .await;
+    create_replicated_bucket(&service, "background", "background", true).await;
+
+    service
+        .put_object_reader(
+            "background",
+            "a.txt",
+            &b"eventually"[..],
+            None,
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    let mut replicated = None;
+    for _ in 0..100 {
+        replicated = replica_object(replica_dir.path(), "background/a.txt").await;
+        if replicated.is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(replicated.as_deref(), Some(&b"eventually"[..]));
+
+    // The upload succeeds even though the target is unusable, leaving a marker
+    std::fs::write(&broken_root, b"").unwrap();
+    create_replicated_bucket(&service, "lagging", "later", true).await;
+
+    service
+        .put_object_reader("lagging", "b.txt", &b"retried"[..], None, HashMap::new())
+        .await
+        .unwrap();
+    assert!(service.object_exists("lagging", "b.txt").await.unwrap());
+    let mut lag = 0;
+    for _ in 0..100 {
+        lag = replication_lag(&service, "lagging").await;
+        if lag > 0 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(lag, 1);
+    assert_eq!(replication_lag(&service, "background").await, 0);
+
+    // Once the target is usable again the retry catches up
+    std::fs::remove_file(&broken_root).unwrap();
+    std::fs::create_dir(&broken_root).unwrap();
+    assert_eq!(service.retry_pending_replications().await.unwrap(), 1);
+    assert_eq!(replication_lag(&service, "lagging").await, 0);
+    assert_eq!(
+        replica_object(&broken_root, "lagging/b.txt")
+            .await
+            .as_deref(),
+        Some(&b"retried"[..])
+    );
+}
+
+#[tokio::test]
+async fn test_revoked_presigned_url_returns_gone() {
+    let (service, _temp_dir) = RefusingBackend::service(|| Ok(())).await;
+    let app = object_store::router::create_router(service.clone());
+    service.create_bucket("signed").await.unwrap();
+    for key in ["a.txt", "b.txt"] {
+        service
+            .put_object_reader("signed", key, &b"secret"[..], None, HashMap::new())
+            .await
+            .unwrap();
+    }
+
+    let send = |method: &str, uri: &str, body: Option<serde_json::Value>| {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("host", "localhost");
+        let body = match body {
+            Some(body) => {
+                request = request.header("content-type", "application/json");
+                Body::from(body.to_string())
+            }
+            None => Body::empty(),
+        };
+        app.clone().oneshot(request.body(body).unwrap())
+    };
+    let public_url = |key: &str| send("GET", &format!("/buckets/signed/public-url/{}", key), None);
+
+    let response = public_url("a.txt").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let url = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["url"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let path = url.strip_prefix("http://localhost").unwrap().to_string();
+    assert_eq!(
+        send("GET", &path, None).await.unwrap().status(),
+        StatusCode::OK
+    );
+
+    let response = send(
+        "POST",
+        "/buckets/signed/revoke-url",
+        Some(json!({ "url": url })),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let expires_at: chrono::DateTime<chrono::Utc> = serde_json::from_value(
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()["expires_at"].clone(),
+    )
+    .unwrap();
+    // The revocation lapses when the URL's own hour is up
+    let remaining = expires_at - chrono::Utc::now();
+    assert!(remaining > chrono::Duration::minutes(59) && remaining <= chrono::Duration::hours(1));
+
+    assert_eq!(
+        send("GET", &path, None).await.unwrap().status(),
+        StatusCode::GONE
+    );
+    assert_eq!(
+        public_url("a.txt").await.unwrap().status(),
+        StatusCode::GONE
+    );
+    assert_eq!(public_url("b.txt").await.unwrap().status(), StatusCode::OK);
+
+    // URLs for other buckets are refused, even with the bucket name in the key
+    for other in [
+        "http://localhost/buckets/other/objects/a.txt?X-Amz-Signature=1",
+        "http://localhost/buckets/other/objects/signed/a.txt?X-Amz-Signature=1",
+        "http://localhost/buckets/signed-old/objects/a.txt?X-Amz-Signature=1",
+    ] {
+        let response = send(
+            "POST",
+            "/buckets/signed/revoke-url",
+            Some(json!({ "url": other })),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    assert_eq!(
+        public_url("a.txt").await.unwrap().status(),
+        StatusCode::GONE
+    );
+
+    // Once the URL would have expired the revocation and its object's index
+    // entry are dropped
+    let later = chrono::Utc::now() + chrono::Duration::hours(2);
+    assert_eq!(service.purge_expired_revocations(later).await.unwrap(), 2);
+    assert_eq!(public_url("a.txt").await.unwrap().status(), StatusCode::OK);
+}
+
+async fn setup_authenticated_service() -> (Arc<ObjectStoreService>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let backend = Arc::new(LocalBackend::new(
+        temp_dir.path().to_path_buf(),
+        "test-physical-bucket".to_string(),
+    ));
+    backend.init().await.unwrap();
+    let metadata = Arc::new(MetadataStore::new(backend.clone()).await.unwrap());
+    let service = Arc::new(ObjectStoreService::new(backend, metadata).with_auth_config(
+        &object_store::auth::AuthConfig {
+            require_api_key: true,
+        },
+    ));
+    (service, temp_dir)
+}
+
+async fn create_api_key(
+    internal: &axum::Router,
+    label: &str,
+    permissions: serde_json::Value,
+) -> serde_json::Value {
+    let response = internal
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/api-keys")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({ "label": label, "permissions": permissions }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn list_api_keys(internal: &axum::Router) -> Vec<serde_json::Value> {
+    let response = internal
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/admin/api-keys")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    json["api_keys"].as_array().unwrap().clone()
+}
+
+async fn list_buckets_with_key(app: &axum::Router, key: Option<&str>) -> StatusCode {
+    let mut request = Request::builder().uri("/buckets");
+    if let Some(key) = key {
+        request = request.header("authorization", format!("Bearer {}", key));
+    }
+    app.clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn test_api_key_create_and_list() {
+    let (service, _temp_dir) = setup_authenticated_service().await;
+    let internal = object_store::router::create_internal_router(service.clone());
+
+    let created = create_api_key(&internal, "ci", json!(["read"])).await;
+    let key = created["key"].as_str().unwrap();
+    let hash = created["hash"].as_str().unwrap();
+    assert_eq!(hash, object_store::auth::hash_key(key));
+    assert_eq!(created["permissions"], json!(["read"]));
+
+    let keys = list_api_keys(&internal).await;
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0]["hash"], hash);
+    assert_eq!(keys[0]["label"], "ci");
+    assert!(keys[0]["last_used_at"].is_null());
+    // The raw key is only ever returned on creation
+    assert!(keys[0].get("key").is_none());
+    assert!(!serde_json::to_string(&keys).unwrap().contains(key));
+
+    let response = internal
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/api-keys")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "label": "" }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_api_key_authenticates_requests() {
+    let (service, _temp_dir) = setup_authenticated_service().await;
+    let public = object_store::router::create_router(service.clone());
+    let internal = object_store::router::create_internal_router(service.clone());
+
+    assert_eq!(
+        list_buckets_with_key(&public, None).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        list_buckets_with_key(&public, Some("osk_not-a-key")).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    let reader = create_api_key(&internal, "reader", json!(["read"])).await;
+    let reader = reader["key"].as_str().unwrap();
+    assert_eq!(
+        list_buckets_with_key(&public, Some(reader)).await,
+        StatusCode::OK
+    );
+
+    // A read-only key cannot create buckets
+    let create = |key: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/buckets")
+            .header("authorization", format!("Bearer {}", key))
+            .header("content-type", "application/json")
+            .body(Body::from(json!({ "name": "secured" }).to_string()))
+            .unwrap()
+    };
+    let response = public.clone().oneshot(create(reader)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let writer = create_api_key(&internal, "writer", json!(["read", "write"])).await;
+    let response = public
+        .clone()
+        .oneshot(create(writer["key"].as_str().unwrap()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let keys = list_api_keys(&internal).await;
+    assert!(keys.iter().all(|k| !k["last_used_at"].is_null()));
+}
+
+#[tokio::test]
+async fn test_permission_denied_body() {
+    let (service, _temp_dir) = setup_authenticated_service().await;
+    let public = object_store::router::create_router(service.clone());
+    let internal = object_store::router::create_internal_router(service.clone());
+    service.create_bucket("reports").await.unwrap();
+
+    let reader = create_api_key(&internal, "dashboard", json!(["read"])).await;
+    let reader = reader["key"].as_str().unwrap().to_string();
+    let denied = |method: &str, uri: &str| {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("authorization", format!("Bearer {}", reader))
+            .body(Body::from("data"))
+            .unwrap();
+        let public = public.clone();
+        async move {
+            let response = public.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let mut body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert!(body["request_id"].is_string());
+            body.as_object_mut().unwrap().remove("request_id");
+            body
+        }
+    };
+
+    assert_eq!(
+        denied("PUT", "/buckets/reports/objects/2024/q1%20summary.txt").await,
+        json!({
+            "error": "Permission denied: 'dashboard' may not write_object on 'reports/2024/q1 summary.txt'",
+            "operation": "write_object",
+            "resource": "reports/2024/q1 summary.txt",
+            "principal": "dashboard",
+        })
+    );
+    assert_eq!(
+        denied("DELETE", "/buckets/reports/objects/old.txt").await,
+        json!({
+            "error": "Permission denied: 'dashboard' may not delete_object on 'reports/old.txt'",
+            "operation": "delete_object",
+            "resource": "reports/old.txt",
+            "principal": "dashboard",
+        })
+    );
+    assert_eq!(
+        denied("DELETE", "/buckets/reports").await,
+        json!({
+            "error": "Permission denied: 'dashboard' may not delete_bucket on 'reports'",
+            "operation": "delete_bucket",
+            "resource": "reports",
+            "principal": "dashboard",
+        })
+    );
+    assert_eq!(
+        denied("POST", "/buckets").await,
+        json!({
+            "error": "Permission denied: 'dashboard' may not create_bucket on '*'",
+            "operation": "create_bucket",
+            "resource": "*",
+            "principal": "dashboard",
+        })
+    );
+}
+
+#[test]
+fn test_operation_type_for_request() {
+    use axum::http::Method;
+    use object_store::auth::{OperationType, Permission};
+
+    let cases = [
+        (Method::GET, "/buckets", OperationType::ListObjects),
+        (Method::PUT, "/buckets", OperationType::CreateBucket),
+        (
+            Method::DELETE,
+            "/buckets/:bucket",
+            OperationType::DeleteBucket,
+        ),
+        (
+            Method::PATCH,
+            "/buckets/:bucket",
+            OperationType::WriteObject,
+        ),
+        (
+            Method::GET,
+            "/buckets/:bucket/objects",
+            OperationType::ListObjects,
+        ),
+        (
+            Method::HEAD,
+            "/buckets/:bucket/objects/*key",
+            OperationType::ReadObject,
+        ),
+        (
+            Method::POST,
+            "/buckets/:bucket/objects/*key",
+            OperationType::WriteObject,
+        ),
+        (
+            Method::DELETE,
+            "/buckets/:bucket/objects/*key",
+            OperationType::DeleteObject,
+        ),
+    ];
+    for (method, route, operation) in cases {
+        assert_eq!(
+            OperationType::for_request(&method, route),
+            operation,
+            "{} {}",
+            method,
+            route
+        );
+    }
+    assert_eq!(OperationType::ListObjects.permission(), Permission::Read);
+    assert_eq!(OperationType::DeleteObject.permission(), Permission::Write);
+}
+
+#[tokio::test]
+async fn test_revoked_api_key_returns_unauthorized() {
+    let (service, _temp_dir) = setup_authenticated_service().await;
+    let public = object_store::router::create_router(service.clone());
+    let internal = object_store::router::create_internal_router(service.clone());
+
+    let created = create_api_key(&internal, "temporary", json!(["read"])).await;
+    let key = created["key"].as_str().unwrap();
+    let hash = created["hash"].as_str().unwrap();
+    // Cached as valid by this request
+    assert_eq!(
+        list_buckets_with_key(&public, Some(key)).await,
+        StatusCode::OK
+    );
+
+    let revoke = || {
+        Request::builder()
+            .method("DELETE")
+            .uri(format!("/admin/api-keys/{}", hash))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let response = internal.clone().oneshot(revoke()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = public
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/buckets")
+                .header("authorization", format!("Bearer {}", key))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers()["www-authenticate"], "Bearer");
+    assert!(list_api_keys(&internal).await.is_empty());
+
+    let response = internal.clone().oneshot(revoke()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = internal
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/admin/api-keys/not-a-hash")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+fn list_buckets_from(ip: [u8; 4], key: Option<&str>) -> Request<Body> {
+    let mut request = Request::builder().uri("/buckets");
+    if let Some(key) = key {
+        request = request.header("authorization", format!("Bearer {}", key));
+    }
+    let mut request = request.body(Body::empty()).unwrap();
+    request
+        .extensions_mut()
+        .insert(axum::extract::ConnectInfo(std::net::SocketAddr::from((
+            ip, 40000,
+        ))));
+    request
+}
+
+#[tokio::test]
+async fn test_rate_limit_allows_burst_then_throttles_per_ip() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let service = Arc::new(
+        Arc::into_inner(service)
+            .unwrap()
+            .with_rate_limit_config(Some(&object_store::rate_limit::RateLimitConfig {
+                requests_per_second: 1,
+                burst: 3,
+            })),
+    );
+    let app = object_store::router::create_router(service);
+
+    for _ in 0..3 {
+        let response = app
+            .clone()
+            .oneshot(list_buckets_from([10, 0, 0, 1], None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = app
+        .clone()
+        .oneshot(list_buckets_from([10, 0, 0, 1], None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["retry-after"], "1");
+
+    // Another client has its own bucket
+    let response = app
+        .oneshot(list_buckets_from([10, 0, 0, 2], None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_rate_limit_per_api_key() {
+    let (service, _temp_dir) = setup_authenticated_service().await;
+    let service = Arc::new(
+        Arc::into_inner(service)
+            .unwrap()
+            .with_rate_limit_config(Some(&object_store::rate_limit::RateLimitConfig {
+                requests_per_second: 1,
+                burst: 1,
+            })),
+    );
+    let public = object_store::router::create_router(service.clone());
+    let internal = object_store::router::create_internal_router(service);
+
+    let response = internal
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/api-keys")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "label": "batch",
+                        "permissions": ["read"],
+                        "rate_limit": { "requests_per_second": 1, "burst": 3 },
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let batch: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(batch["rate_limit"]["burst"], 3);
+    let batch = batch["key"].as_str().unwrap();
+    let default = create_api_key(&internal, "default", json!(["read"])).await;
+    let default = default["key"].as_str().unwrap();
+
+    // The first request spends the IP's only token looking the key up; after
+    // that the key is limited on its own
+    let from = |ip, key| list_buckets_from([10, 0, 0, ip], Some(key));
+    for _ in 0..3 {
+        let response = public.clone().oneshot(from(1, batch)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = public.clone().oneshot(from(1, batch)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // A key not seen yet waits for its IP's bucket
+    let response = public.clone().oneshot(from(1, default)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let response = public.clone().oneshot(from(2, default)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = public.clone().oneshot(from(2, default)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Made-up keys are throttled by IP before they are looked up
+    let response = public
+        .clone()
+        .oneshot(from(3, "osk_unknown"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = public.oneshot(from(3, "osk_made_up")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_migrate_bucket_ids_to_uuid() {
+    use object_store::metadata::{BucketIdStrategy, MetadataStoreConfig};
+
+    let temp_dir = TempDir::new().unwrap();
+    let backend = Arc::new(LocalBackend::new(
+        temp_dir.path().to_path_buf(),
+        "test-physical-bucket".to_string(),
+    ));
+    backend.init().await.unwrap();
+
+    let metadata = MetadataStore::new_with_config(
+        backend.clone(),
+        MetadataStoreConfig {
+            id_strategy: BucketIdStrategy::Sha256Prefix(16),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    let hashed = metadata.create_bucket("hashed-bucket").await.unwrap();
+    assert_eq!(hashed.id.len(), 16);
+    assert!(hashed.id.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(
+        metadata.get_bucket_by_id(&hashed.id).await.unwrap().name,
+        "hashed-bucket"
+    );
+
+    // A store with the default strategy still serves the old-style bucket
+    let metadata = MetadataStore::new(backend.clone()).await.unwrap();
+    assert_eq!(
+        metadata.get_bucket_by_id(&hashed.id).await.unwrap().name,
+        "hashed-bucket"
+    );
+    let fresh = metadata.create_bucket("fresh-bucket").await.unwrap();
+    assert!(uuid::Uuid::parse_str(&fresh.id).is_ok());
+
+    let migrated = metadata
+        .migrate_bucket_ids(BucketIdStrategy::UuidV4)
+        .await
+        .unwrap();
+    assert_eq!(migrated, 2);
+
+    let bucket = metadata.get_bucket("hashed-bucket").await.unwrap();
+    assert!(uuid::Uuid::parse_str(&bucket.id).is_ok());
+    assert!(metadata.get_bucket_by_id(&hashed.id).await.is_err());
+
+    // The new IDs were persisted, not just cached
+    let reloaded = MetadataStore::new(backend).await.unwrap();
+    assert_eq!(
+        reloaded.get_bucket_by_id(&bucket.id).await.unwrap().name,
+        "hashed-bucket"
+    );
+}
+
+#[tokio::test]
+async fn test_list_objects_modified_range() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("test-bucket").await.unwrap();
+
+    let mut cutoffs = Vec::new();
+    for key in ["old.txt", "middle.txt", "new.txt"] {
+        service
+            .put_object_reader("test-bucket", key, "data".as_bytes(), None, HashMap::new())
+            .await
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        cutoffs.push(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true));
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    let list = |query: String| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri(format!("/buckets/test-bucket/objects?{}", query))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            if status != StatusCode::OK {
+                return (status, vec![]);
+            }
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let mut keys: Vec<String> = json["objects"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|o| o["key"].as_str().unwrap().to_string())
+                .collect();
+            keys.sort();
+            (status, keys)
+        }
+    };
+
+    let (_, keys) = list(format!("modified_after={}", cutoffs[0])).await;
+    assert_eq!(keys, vec!["middle.txt", "new.txt"]);
+
+    let (_, keys) = list(format!("modified_before={}", cutoffs[0])).await;
+    assert_eq!(keys, vec!["old.txt"]);
+
+    let (_, keys) = list(format!(
+        "modified_after={}&modified_before={}&max_keys=5",
+        cutoffs[0], cutoffs[1]
+    ))
+    .await;
+    assert_eq!(keys, vec!["middle.txt"]);
+
+    let (status, _) = list("modified_after=yesterday".to_string()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_event_bus_delivers_every_put() {
+    use object_store::events::EventType;
+
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("events").await.unwrap();
+    let mut events = service.events().subscribe();
+
+    for i in 0..100 {
+        service
+            .put_object_reader(
+                "events",
+                &format!("file-{:03}.txt", i),
+                "data".as_bytes(),
+                None,
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+    }
+    service
+        .delete_object("events", "file-000.txt")
+        .await
+        .unwrap();
+
+    for i in 0..100 {
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.event_type, EventType::ObjectCreated);
+        assert_eq!(event.bucket, "events");
+        assert_eq!(event.key, format!("file-{:03}.txt", i));
+        assert_eq!(event.metadata.key, event.key);
+        assert_eq!(event.metadata.size, 4);
+    }
+    let deleted = events.recv().await.unwrap();
+    assert_eq!(deleted.event_type, EventType::ObjectDeleted);
+    assert_eq!(deleted.key, "file-000.txt");
+    assert!(events.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_delete_bucket_purges_its_metadata() {
+    let temp_dir = TempDir::new().unwrap();
+    let backend = Arc::new(LocalBackend::new(
+        temp_dir.path().to_path_buf(),
+        "test-physical-bucket".to_string(),
+    ));
+    backend.init().await.unwrap();
+    let metadata = Arc::new(MetadataStore::new(backend.clone()).await.unwrap());
+    let service = ObjectStoreService::new(backend.clone(), metadata.clone());
+
+    for name in ["doomed", "doomed-neighbour"] {
+        service.create_bucket(name).await.unwrap();
+        service
+            .update_bucket_metadata(
+                name,
+                None,
+                HashMap::from([("team".to_string(), Some("storage".to_string()))]),
+            )
+            .await
+            .unwrap();
+        for lock in ["compaction", "scan"] {
+            assert!(metadata
+                .try_acquire_lock(&format!("{}/{}", name, lock), "owner", 60)
+                .await
+                .unwrap());
+        }
+        backend
+            .put_object_reader(
+                &format!(".metadata/replication-pending/{}/a.txt", name),
+                &b""[..],
+                None,
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+    }
+
+    service.delete_bucket("doomed").await.unwrap();
+
+    let keys: Vec<String> = backend
+        .list_objects(Some(".metadata/"), None)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|o| o.key)
+        .collect();
+    assert!(
+        keys.iter()
+            .all(|key| key.contains("doomed-neighbour") || !key.contains("doomed")),
+        "{:?}",
+        keys
+    );
+    // A bucket whose name starts with the deleted one keeps everything
+    assert_eq!(
+        keys.iter()
+            .filter(|k| k.contains("doomed-neighbour"))
+            .count(),
+        4,
+        "{:?}",
+        keys
+    );
+    assert!(service.get_bucket("doomed-neighbour").await.is_ok());
+}
+
+async fn copy_object_request(
+    app: &axum::Router,
+    uri: &str,
+    body: serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_copy_object_preserves_metadata() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("source").await.unwrap();
+    service.create_bucket("archive").await.unwrap();
+    service
+        .put_object_reader(
+            "source",
+            "docs/report.txt",
+            "quarterly numbers".as_bytes(),
+            Some("text/plain".to_string()),
+            HashMap::from([("author".to_string(), "alice".to_string())]),
+        )
+        .await
+        .unwrap();
+
+    let (status, body) = copy_object_request(
+        &app,
+        "/buckets/archive/objects/2024/report.txt/copy",
+        json!({"source_bucket": "source", "source_key": "docs/report.txt"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["key"], "2024/report.txt");
+    assert_eq!(body["content_type"], "text/plain");
+    assert_eq!(body["metadata"]["author"], "alice");
+
+    let copy = service
+        .get_object("archive", "2024/report.txt")
+        .await
+        .unwrap();
+    let (metadata, stream) = copy.into_parts();
+    let data: Vec<Bytes> = futures::TryStreamExt::try_collect(stream).await.unwrap();
+    assert_eq!(data.concat(), b"quarterly numbers");
+    assert_eq!(metadata.content_type.as_deref(), Some("text/plain"));
+    assert_eq!(metadata.custom_metadata["author"], "alice");
+
+    // The source is left in place
+    assert!(service
+        .object_exists("source", "docs/report.txt")
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_copy_object_with_overrides() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("source").await.unwrap();
+    service
+        .put_object_reader(
+            "source",
+            "data.txt",
+            "{\"a\":1}".as_bytes(),
+            Some("text/plain".to_string()),
+            HashMap::from([("author".to_string(), "alice".to_string())]),
+        )
+        .await
+        .unwrap();
+
+    let (status, body) = copy_object_request(
+        &app,
+        "/buckets/source/objects/converted.json/copy",
+        json!({
+            "source_bucket": "source",
+            "source_key": "data.txt",
+            "content_type": "application/json",
+            "metadata": {"reviewed": "yes"}
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["content_type"], "application/json");
+    assert_eq!(body["metadata"], json!({"reviewed": "yes"}));
+
+    let copied = service
+        .head_object("source", "converted.json")
+        .await
+        .unwrap();
+    assert_eq!(copied.content_type.as_deref(), Some("application/json"));
+    assert_eq!(
+        copied.custom_metadata,
+        HashMap::from([("reviewed".to_string(), "yes".to_string())])
+    );
+
+    // Only the content type is overridden; the metadata is kept
+    let (_, body) = copy_object_request(
+        &app,
+        "/buckets/source/objects/converted-again.json/copy",
+        json!({
+            "source_bucket": "source",
+            "source_key": "data.txt",
+            "content_type": "application/json"
+        }),
+    )
+    .await;
+    assert_eq!(body["content_type"], "application/json");
+    assert_eq!(body["metadata"]["author"], "alice");
+
+    let original = service.head_object("source", "data.txt").await.unwrap();
+    assert_eq!(original.content_type.as_deref(), Some("text/plain"));
+}
+
+#[tokio::test]
+async fn test_copy_object_errors() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("source").await.unwrap();
+    service
+        .put_object_bytes(
+            "source",
+            "a.txt",
+            Bytes::from_static(b"a"),
+            None,
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+    let (status, _) = copy_object_request(
+        &app,
+        "/buckets/missing/objects/a.txt/copy",
+        json!({"source_bucket": "source", "source_key": "a.txt"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = copy_object_request(
+        &app,
+        "/buckets/source/objects/b.txt/copy",
+        json!({"source_bucket": "missing", "source_key": "a.txt"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = copy_object_request(
+        &app,
+        "/buckets/source/objects/b.txt/copy",
+        json!({"source_bucket": "source", "source_key": "nope.txt"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(body["error"].as_str().unwrap().contains("source/nope.txt"));
+
+    let (status, _) = copy_object_request(
+        &app,
+        "/buckets/source/objects/../b.txt/copy",
+        json!({"source_bucket": "source", "source_key": "a.txt"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = copy_object_request(
+        &app,
+        "/buckets/source/objects/b.txt",
+        json!({"source_bucket": "source", "source_key": "a.txt"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_rename_object() {
+    let (service, _temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+
+    service.create_bucket("docs").await.unwrap();
+    let mut metadata = HashMap::new();
+    metadata.insert("author".to_string(), "alice".to_string());
+    service
+        .put_object_bytes(
+            "docs",
+            "drafts/report.txt",
+            Bytes::from_static(b"quarterly numbers"),
+            Some("text/plain".to_string()),
+            metadata,
+        )
+        .await
+        .unwrap();
+
+    let (status, body) = copy_object_request(
+        &app,
+        "/buckets/docs/objects/drafts/report.txt/rename",
+        json!({"new_key": "final/report.txt"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["key"], "final/report.txt");
+    assert_eq!(body["metadata"]["author"], "alice");
+
+    assert!(!service
+        .object_exists("docs", "drafts/report.txt")
+        .await
+        .unwrap());
+    let renamed = service
+        .get_object("docs", "final/report.txt")
+        .await
+        .unwrap();
+    let (metadata, stream) = renamed.into_parts();
+    let data: Vec<Bytes> = futures::TryStreamExt::try_collect(stream).await.unwrap();
+    assert_eq!(data.concat(), b"quarterly numbers");
+    assert_eq!(metadata.content_type.as_deref(), Some("text/plain"));
+
+    let (status, _) = copy_object_request(
+        &app,
+        "/buckets/docs/objects/drafts/report.txt/rename",
+        json!({"new_key": "again.txt"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = copy_object_request(
+        &app,
+        "/buckets/docs/objects/final/report.txt/rename",
+        json!({"new_key": "../escape.txt"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_key_and_bucket_name_length_limits() {
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("long-keys").await.unwrap();
+    let app = object_store::router::create_router(service.clone());
+
+    // Directories keep each path component under the filesystem's 255 bytes
+    let at_limit = format!(
+        "{}{}",
+        format!("{}/", "d".repeat(99)).repeat(10),
+        "k".repeat(24)
+    );
+    assert_eq!(at_limit.len(), 1024);
+    service
+        .put_object_bytes("long-keys", &at_limit, "x", None, HashMap::new())
+        .await
+        .unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/buckets/long-keys/objects/{}k", at_limit))
+                .body(Body::from("x"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Two-byte characters reach the limit in 515 characters
+    let multi_byte = format!(
+        "{}{}x",
+        format!("{}/", "é".repeat(100)).repeat(5),
+        "é".repeat(9)
+    );
+    assert_eq!(multi_byte.len(), 1024);
+    assert_eq!(multi_byte.chars().count(), 515);
+    service
+        .put_object_bytes("long-keys", &multi_byte, "x", None, HashMap::new())
+        .await
+        .unwrap();
+    let err = service
+        .put_object_bytes(
+            "long-keys",
+            &format!("{}é", multi_byte),
+            "x",
+            None,
+            HashMap::new(),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        object_store::ServiceError::InvalidObjectKey(_)
+    ));
+
+    let (backend, _temp_dir) = {
+        let temp_dir = TempDir::new().unwrap();
+        let backend: Arc<dyn Backend> = Arc::new(LocalBackend::new(
+            temp_dir.path().to_path_buf(),
+            "test-physical-bucket".to_string(),
+        ));
+        backend.init().await.unwrap();
+        (backend, temp_dir)
+    };
+    let metadata = Arc::new(MetadataStore::new(backend.clone()).await.unwrap());
+    let service = ObjectStoreService::new(backend, metadata).with_validation_config(
+        &object_store::validation::ValidationConfig {
+            max_bucket_name_chars: 8,
+            ..Default::default()
+        },
+    );
+    service.create_bucket("eight-ch").await.unwrap();
+    let err = service.create_bucket("nine-char").await.unwrap_err();
+    assert!(matches!(
+        err,
+        object_store::ServiceError::InvalidBucketName(_)
+    ));
+}
+
+// Files below `dir` whose content is exactly `data`
+fn copies_on_disk(dir: &std::path::Path, data: &[u8]) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .map(|entry| entry.unwrap().path())
+        .map(|path| {
+            if path.is_dir() {
+                copies_on_disk(&path, data)
+            } else {
+                usize::from(std::fs::read(&path).unwrap() == data)
+            }
+        })
+        .sum()
+}
+
+#[tokio::test]
+async fn test_deduplicated_uploads_share_one_copy() {
+    let (service, temp_dir) = setup_test_service().await;
+    let app = object_store::router::create_router(service.clone());
+    let data = b"the same report, uploaded twice";
+    let root = temp_dir.path().join("test-physical-bucket");
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/buckets")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"name": "dedup", "deduplication_enabled": true}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let bucket: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(bucket["deduplication_enabled"], true);
+
+    let first = service
+        .put_object_bytes(
+            "dedup",
+            "a.txt",
+            &data[..],
+            Some("text/plain".to_string()),
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    let second = service
+        .put_object_bytes(
+            "dedup",
+            "b.txt",
+            &data[..],
+            Some("text/csv".to_string()),
+            HashMap::from([("owner".to_string(), "bob".to_string())]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(second.size, data.len() as u64);
+    assert_eq!(second.etag, first.etag);
+    assert_eq!(copies_on_disk(&root.join("dedup"), data), 1);
+
+    // The second key reads like a normal object
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/buckets/dedup/objects/b.txt")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/csv");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], data);
+
+    let head = service.head_object("dedup", "b.txt").await.unwrap();
+    assert_eq!(head.size, data.len() as u64);
+    assert_eq!(head.etag, first.etag);
+    assert_eq!(
+        head.custom_metadata,
+        HashMap::from([("owner".to_string(), "bob".to_string())])
+    );
+    let listed = service.list_objects("dedup", None, None).await.unwrap();
+    assert_eq!(listed.len(), 2);
+    assert!(listed.iter().all(|obj| obj.size == data.len() as u64));
+
+    // Deleting the stored copy moves it to the key still pointing at it
+    service.delete_object("dedup", "a.txt").await.unwrap();
+    assert_eq!(copies_on_disk(&root.join("dedup"), data), 1);
+    let mut object = service.get_object("dedup", "b.txt").await.unwrap();
+    let mut body = Vec::new();
+    while let Some(chunk) = futures::StreamExt::next(&mut object.stream).await {
+        body.extend_from_slice(&chunk.unwrap());
+    }
+    assert_eq!(body, data);
+    assert_eq!(object.metadata.content_type.as_deref(), Some("text/csv"));
+    assert_eq!(object.metadata.custom_metadata["owner"], "bob");
+
+    // A failed overwrite of a pointer leaves it pointing at the stored copy,
+    // so the copy moves to it when the stored key is deleted
+    service
+        .put_object_bytes("dedup", "a.txt", &data[..], None, HashMap::new())
+        .await
+        .unwrap();
+    let failing: object_store_backends::ByteStream = Box::pin(stream::iter(vec![
+        Ok(Bytes::from_static(b"partial")),
+        Err(std::io::Error::other("connection reset")),
+    ]));
+    service
+        .put_object("dedup", "a.txt", failing, None, HashMap::new())
+        .await
+        .unwrap_err();
+    service.delete_object("dedup", "b.txt").await.unwrap();
+    let mut object = service.get_object("dedup", "a.txt").await.unwrap();
+    let mut body = Vec::new();
+    while let Some(chunk) = futures::StreamExt::next(&mut object.stream).await {
+        body.extend_from_slice(&chunk.unwrap());
+    }
+    assert_eq!(body, data);
+    service.delete_object("dedup", "a.txt").await.unwrap();
+
+    // Content stored again after the last key is gone starts over
+    assert_eq!(copies_on_disk(&root, data), 0);
+    let hash_records = std::fs::read_dir(root.join(".metadata/hashes"))
+        .map(|entries| entries.count())
+        .unwrap_or(0);
+    assert_eq!(hash_records, 0);
+    service
+        .put_object_bytes("dedup", "c.txt", &data[..], None, HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(copies_on_disk(&root.join("dedup"), data), 1);
+
+    // Deduplicated buckets can't also recycle or replicate objects
+    let err = service
+        .create_bucket_with("dedup-recycled", |bucket| {
+            bucket.deduplication_enabled = true;
+            bucket.recycle_bin_enabled = true;
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, object_store::ServiceError::InvalidRequest(_)));
+}
+
+#[tokio::test]
+async fn test_bucket_name_normalization() {
+    let temp_dir = TempDir::new().unwrap();
+    let backend: Arc<LocalBackend> = Arc::new(LocalBackend::new(
+        temp_dir.path().to_path_buf(),
+        "test-physical-bucket".to_string(),
+    ));
+    backend.init().await.unwrap();
+    let service_with = |normalize_bucket_names: bool| {
+        let backend = backend.clone();
+        async move {
+            let config = object_store::metadata::MetadataStoreConfig {
+                normalize_bucket_names,
+                ..Default::default()
+            };
+            let metadata = MetadataStore::new_with_config(backend.clone(), config)
+                .await
+                .unwrap();
+            ObjectStoreService::new(backend, Arc::new(metadata))
+        }
+    };
+
+    // Off: uppercase names are rejected and don't find lowercase buckets
+    let strict = service_with(false).await;
+    let err = strict.create_bucket("MyBucket").await.unwrap_err();
+    assert!(matches!(
+        err,
+        object_store::ServiceError::InvalidBucketName(_)
+    ));
+    strict.create_bucket("lowercase").await.unwrap();
+    let err = strict.get_bucket("LowerCase").await.unwrap_err();
+    assert!(matches!(err, object_store::ServiceError::BucketNotFound(_)));
+
+    // On: every spelling names the lowercase bucket
+    let lenient = service_with(true).await;
+    let bucket = lenient.create_bucket("MyBucket").await.unwrap();
+    assert_eq!(bucket.name, "mybucket");
+    assert_eq!(lenient.get_bucket("mybucket").await.unwrap().id, bucket.id);
+    assert_eq!(lenient.get_bucket("MYBUCKET").await.unwrap().id, bucket.id);
+    assert_eq!(
+        lenient.get_bucket("LowerCase").await.unwrap().name,
+        "lowercase"
+    );
+    let err = lenient.create_bucket("mybucket").await.unwrap_err();
+    assert!(matches!(
+        err,
+        object_store::ServiceError::BucketAlreadyExists(_)
+    ));
+
+    lenient
+        .put_object_str("MyBucket", "a.txt", "hello", None, HashMap::new())
+        .await
+        .unwrap();
+    assert!(backend.object_exists("mybucket/a.txt").await.unwrap());
+    let listed = lenient.list_objects("myBUCKET", None, None).await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].key, "a.txt");
+    // Stored under the lowercase name, so the strict service finds it too
+    assert_eq!(
+        strict
+            .list_objects("mybucket", None, None)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    lenient.delete_object("MYBUCKET", "a.txt").await.unwrap();
+    lenient.delete_bucket("MyBucket").await.unwrap();
+    let err = lenient.get_bucket("mybucket").await.unwrap_err();
+    assert!(matches!(err, object_store::ServiceError::BucketNotFound(_)));
+}
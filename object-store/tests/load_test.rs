@@ -1,8 +1,7 @@
-use bytes::Bytes;
-use futures::{stream, StreamExt};
+use futures::StreamExt;
 use object_store::metadata::MetadataStore;
-use object_store::service::ObjectStoreService;
-use object_store_backends::{local::LocalBackend, Backend};
+use object_store::service::{ListBucketsOptions, ObjectStoreService};
+use object_store_backends::{local::LocalBackend, Backend, BackendExt};
 use std::sync::Arc;
 use std::time::Instant;
 use tempfile::TempDir;
@@ -41,14 +40,11 @@ async fn test_concurrent_writes() {
             tokio::spawn(async move {
                 let key = format!("file-{}.txt", i);
                 let data = format!("Data for file {}", i).into_bytes();
-                let stream: object_store_backends::ByteStream =
-                    Box::pin(stream::once(async move { Ok(Bytes::from(data)) }));
-
                 service
-                    .put_object(
+                    .put_object_bytes(
                         "load-test-bucket",
                         &key,
-                        stream,
+                        data,
                         Some("text/plain".to_string()),
                         Default::default(),
                     )
@@ -88,13 +84,11 @@ async fn test_concurrent_reads() {
     for i in 0..num_files {
         let key = format!("file-{}.txt", i);
         let data = format!("Data for file {}", i).into_bytes();
-        let stream: object_store_backends::ByteStream =
-            Box::pin(stream::once(async move { Ok(Bytes::from(data)) }));
         service
-            .put_object(
+            .put_object_reader(
                 "load-test-bucket",
                 &key,
-                stream,
+                std::io::Cursor::new(data),
                 Some("text/plain".to_string()),
                 Default::default(),
             )
@@ -156,13 +150,11 @@ async fn test_mixed_operations() {
                         // Write
                         let key = format!("file-{}.txt", i);
                         let data = format!("Data {}", i).into_bytes();
-                        let stream: object_store_backends::ByteStream =
-                            Box::pin(stream::once(async move { Ok(Bytes::from(data)) }));
                         service
-                            .put_object(
+                            .put_object_reader(
                                 "load-test-bucket",
                                 &key,
-                                stream,
+                                std::io::Cursor::new(data),
                                 Some("text/plain".to_string()),
                                 Default::default(),
                             )
@@ -236,13 +228,11 @@ async fn test_large_file_handling() {
 
         // Write
         let expected_size = data.len();
-        let stream: object_store_backends::ByteStream =
-            Box::pin(stream::once(async move { Ok(Bytes::from(data)) }));
         service
-            .put_object(
+            .put_object_reader(
                 "load-test-bucket",
                 &key,
-                stream,
+                std::io::Cursor::new(data),
                 Some("application/octet-stream".to_string()),
                 Default::default(),
             )
@@ -332,7 +322,7 @@ async fn test_stress_bucket_operations() {
     let list_handles: Vec<_> = (0..50)
         .map(|_| {
             let service = service.clone();
-            tokio::spawn(async move { service.list_buckets().await })
+            tokio::spawn(async move { service.list_buckets(&ListBucketsOptions::default()).await })
         })
         .collect();
 
@@ -353,3 +343,525 @@ async fn test_stress_bucket_operations() {
     assert_eq!(list_count, 50);
     println!("✓ Stress test passed");
 }
+
+#[tokio::test]
+#[ignore]
+async fn test_list_objects_streaming_vs_buffered() {
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt as TowerServiceExt;
+
+    let (service, _temp_dir) = setup_test_service().await;
+    service.create_bucket("load-test-bucket").await.unwrap();
+
+    let num_objects = 10_000;
+    for i in 0..num_objects {
+        let data = format!("Data for file {}", i).into_bytes();
+        service
+            .put_object_reader(
+                "load-test-bucket",
+                &format!("file-{:05}.txt", i),
+                std::io::Cursor::new(data),
+                None,
+                Default::default(),
+            )
+            .await
+            .unwrap();
+    }
+
+    let app = object_store::router::create_router(service.clone());
+
+    // Buffered: the whole JSON document arrives as a single allocation
+    let start = Instant::now();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/buckets/load-test-bucket/objects")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let buffered_duration = start.elapsed();
+    let buffered_peak = body.len();
+
+    // Streaming: consume one frame at a time and track the largest frame
+    let start = Instant::now();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/buckets/load-test-bucket/objects")
+                .header("accept", "application/x-ndjson")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let mut frames = response.into_body().into_data_stream();
+    let mut streaming_peak = 0;
+    let mut lines = 0;
+    while let Some(frame) = frames.next().await {
+        let frame = frame.unwrap();
+        streaming_peak = streaming_peak.max(frame.len());
+        lines += frame.iter().filter(|b| **b == b'\n').count();
+    }
+    let streaming_duration = start.elapsed();
+
+    println!(
+        "Buffered: {} bytes in one body ({:?}); streaming: largest frame {} bytes ({:?})",
+        buffered_peak, buffered_duration, streaming_peak, streaming_duration
+    );
+
+    // The listing is still collected before the first line is sent; only the
+    // serialized body is produced a frame at a time
+    assert_eq!(lines, num_objects);
+    println!("✓ Streaming listing sent {} lines", lines);
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_head_object_xattrs_vs_json() {
+    use object_store_backends::local::LocalBackendConfig;
+
+    let temp_dir = TempDir::new().unwrap();
+    let num_objects = 1_000;
+    let rounds = 10;
+
+    let mut durations = Vec::new();
+    for use_xattrs in [false, true] {
+        let backend = LocalBackend::new_with_config(
+            temp_dir.path().to_path_buf(),
+            format!("xattrs-{}", use_xattrs),
+            LocalBackendConfig {
+                use_xattrs,
+                ..Default::default()
+            },
+        );
+        backend.init().await.unwrap();
+
+        for i in 0..num_objects {
+            backend
+                .put_object_reader(
+                    &format!("file-{:04}.txt", i),
+                    &b"data"[..],
+                    Some("text/plain".to_string()),
+                    Default::default(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let start = Instant::now();
+        for _ in 0..rounds {
+            for i in 0..num_objects {
+                backend
+                    .head_object(&format!("file-{:04}.txt", i))
+                    .await
+                    .unwrap();
+            }
+        }
+        durations.push(start.elapsed());
+    }
+
+    let heads = (num_objects * rounds) as f64;
+    println!(
+        "head_object: JSON sidecar {:?} ({:.2} µs/op), xattrs {:?} ({:.2} µs/op)",
+        durations[0],
+        durations[0].as_micros() as f64 / heads,
+        durations[1],
+        durations[1].as_micros() as f64 / heads
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_head_object_metadata_cache_vs_sidecar() {
+    use object_store_backends::local::LocalBackendConfig;
+
+    let temp_dir = TempDir::new().unwrap();
+    let num_objects = 10_000;
+    let rounds = 5;
+
+    let mut durations = Vec::new();
+    for metadata_cache_capacity in [0, num_objects] {
+        let backend = LocalBackend::new_with_config(
+            temp_dir.path().to_path_buf(),
+            format!("metadata-cache-{}", metadata_cache_capacity),
+            LocalBackendConfig {
+                metadata_cache_capacity,
+                ..Default::default()
+            },
+        );
+        backend.init().await.unwrap();
+
+        for i in 0..num_objects {
+            backend
+                .put_object_reader(
+                    &format!("file-{:05}.txt", i),
+                    &b"data"[..],
+                    Some("text/plain".to_string()),
+                    Default::default(),
+                )
+                .await
+                .unwrap();
+        }
+
+        // The first round fills the cache
+        let start = Instant::now();
+        for _ in 0..rounds {
+            for i in 0..num_objects {
+                backend
+                    .head_object(&format!("file-{:05}.txt", i))
+                    .await
+                    .unwrap();
+            }
+        }
+        durations.push(start.elapsed());
+    }
+
+    let heads = (num_objects * rounds) as f64;
+    println!(
+        "head_object over {} objects: sidecar {:?} ({:.2} µs/op), metadata cache {:?} ({:.2} µs/op)",
+        num_objects,
+        durations[0],
+        durations[0].as_micros() as f64 / heads,
+        durations[1],
+        durations[1].as_micros() as f64 / heads
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_object_exists_override_vs_default() {
+    let temp_dir = TempDir::new().unwrap();
+    let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "exists".to_string());
+    backend.init().await.unwrap();
+
+    let num_objects = 1_000;
+    for i in 0..num_objects {
+        backend
+            .put_object_reader(
+                &format!("file-{:04}.txt", i),
+                &b"data"[..],
+                Some("text/plain".to_string()),
+                Default::default(),
+            )
+            .await
+            .unwrap();
+    }
+
+    for prefix in ["file", "missing"] {
+        let keys: Vec<String> = (0..num_objects)
+            .map(|i| format!("{}-{:04}.txt", prefix, i))
+            .collect();
+        let expected = prefix == "file";
+        // Warm the dentry cache so neither variant pays for the first lookups
+        for key in &keys {
+            backend.object_exists(key).await.unwrap();
+        }
+
+        let start = Instant::now();
+        for key in &keys {
+            assert_eq!(backend.object_exists(key).await.unwrap(), expected);
+        }
+        let overridden = start.elapsed();
+
+        // What the trait's default does: a full metadata read
+        let start = Instant::now();
+        for key in &keys {
+            let exists = match backend.head_object(key).await {
+                Ok(_) => true,
+                Err(object_store_backends::BackendError::NotFound(_)) => false,
+                Err(e) => panic!("{}", e),
+            };
+            assert_eq!(exists, expected);
+        }
+        let default = start.elapsed();
+
+        println!(
+            "object_exists on {} {} objects: override {:?} ({:.2} µs/op), default {:?} ({:.2} µs/op)",
+            num_objects,
+            if expected { "existing" } else { "missing" },
+            overridden,
+            overridden.as_micros() as f64 / num_objects as f64,
+            default,
+            default.as_micros() as f64 / num_objects as f64
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+#[ignore]
+async fn test_sharded_metadata_cache_contention() {
+    use object_store::metadata::MetadataStoreConfig;
+    use std::time::Duration;
+
+    let num_buckets = 1_000;
+    let num_writers = 100;
+
+    for shard_count in [1, 16] {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = Arc::new(LocalBackend::new(
+            temp_dir.path().to_path_buf(),
+            "test-physical-bucket".to_string(),
+        ));
+        backend.init().await.unwrap();
+        let metadata = Arc::new(
+            MetadataStore::new_with_config(
+                backend,
+                MetadataStoreConfig {
+                    shard_count,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap(),
+        );
+
+        for i in 0..num_buckets {
+            metadata
+                .create_bucket(&format!("bucket-{:04}", i))
+                .await
+                .unwrap();
+        }
+
+        // Writers keep taking shard write locks while the reads run
+        let writers: Vec<_> = (0..num_writers)
+            .map(|i| {
+                let metadata = metadata.clone();
+                tokio::spawn(async move {
+                    metadata
+                        .update_bucket(&format!("bucket-{:04}", i * 10), |bucket| {
+                            bucket.description = Some("updated".to_string());
+                        })
+                        .await
+                        .unwrap();
+                })
+            })
+            .collect();
+
+        let start = Instant::now();
+        let readers: Vec<_> = (0..num_buckets)
+            .map(|i| {
+                let metadata = metadata.clone();
+                tokio::spawn(async move {
+                    let start = Instant::now();
+                    metadata
+                        .get_bucket(&format!("bucket-{:04}", i))
+                        .await
+                        .unwrap();
+                    start.elapsed()
+                })
+            })
+            .collect();
+
+        let mut waits: Vec<Duration> = futures::future::join_all(readers)
+            .await
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect();
+        let elapsed = start.elapsed();
+        futures::future::join_all(writers).await;
+
+        waits.sort();
+        let total: Duration = waits.iter().sum();
+        println!(
+            "{} concurrent reads of {} buckets with {} shard(s): {:?} wall, {:?} total under lock, p99 {:?}",
+            num_buckets,
+            num_buckets,
+            shard_count,
+            elapsed,
+            total,
+            waits[waits.len() * 99 / 100]
+        );
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_list_cache_vs_directory_walk() {
+    use object_store_backends::local::LocalBackendConfig;
+
+    let temp_dir = TempDir::new().unwrap();
+    let num_objects = 5_000;
+    let rounds = 20;
+
+    let mut durations = Vec::new();
+    for list_cache_ttl_secs in [0, 60] {
+        let backend = LocalBackend::new_with_config(
+            temp_dir.path().to_path_buf(),
+            format!("list-cache-{}", list_cache_ttl_secs),
+            LocalBackendConfig {
+                list_cache_ttl_secs,
+                ..Default::default()
+            },
+        );
+        backend.init().await.unwrap();
+
+        for i in 0..num_objects {
+            backend
+                .put_object_reader(
+                    &format!("dir-{}/file-{:04}.txt", i % 10, i),
+                    &b"data"[..],
+                    Some("text/plain".to_string()),
+                    Default::default(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let start = Instant::now();
+        for _ in 0..rounds {
+            let objects = backend.list_objects(None, None).await.unwrap();
+            assert_eq!(objects.len(), num_objects);
+        }
+        durations.push(start.elapsed());
+    }
+
+    println!(
+        "list_objects over {} files: directory walk {:?} ({:?}/op), cached {:?} ({:?}/op)",
+        num_objects,
+        durations[0],
+        durations[0] / rounds,
+        durations[1],
+        durations[1] / rounds
+    );
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_list_summary_vs_full_metadata() {
+    let temp_dir = TempDir::new().unwrap();
+    let backend = LocalBackend::new(temp_dir.path().to_path_buf(), "summaries".to_string());
+    backend.init().await.unwrap();
+
+    let num_objects = 1_000;
+    let rounds = 20;
+    for i in 0..num_objects {
+        backend
+            .put_object_reader(
+                &format!("dir-{}/file-{:04}.txt", i % 10, i),
+                &b"data"[..],
+                Some("text/plain".to_string()),
+                [("owner".to_string(), "alice".to_string())].into(),
+            )
+            .await
+            .unwrap();
+    }
+
+    let start = Instant::now();
+    for _ in 0..rounds {
+        let objects = backend.list_objects(None, None).await.unwrap();
+        assert_eq!(objects.len(), num_objects);
+    }
+    let full = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..rounds {
+        let objects = backend
+            .list_objects_metadata_only(None, None)
+            .await
+            .unwrap();
+        assert_eq!(objects.len(), num_objects);
+    }
+    let summary = start.elapsed();
+
+    println!(
+        "list over {} files: full metadata {:?} ({:?}/op), summary {:?} ({:?}/op)",
+        num_objects,
+        full,
+        full / rounds,
+        summary,
+        summary / rounds
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+#[ignore]
+async fn test_background_cache_refresh_under_burst() {
+    use object_store::metadata::MetadataStoreConfig;
+    use std::time::Duration;
+
+    let temp_dir = TempDir::new().unwrap();
+    let backend = Arc::new(LocalBackend::new(
+        temp_dir.path().to_path_buf(),
+        "test-physical-bucket".to_string(),
+    ));
+    backend.init().await.unwrap();
+    let metadata = Arc::new(
+        MetadataStore::new_with_config(
+            backend,
+            MetadataStoreConfig {
+                cache_ttl_secs: 2,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap(),
+    );
+    let mut ids = Vec::new();
+    for i in 0..100 {
+        ids.push(
+            metadata
+                .create_bucket(&format!("bucket-{:03}", i))
+                .await
+                .unwrap()
+                .id,
+        );
+    }
+
+    // Same loop as main.rs
+    let refresher = {
+        let metadata = metadata.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(metadata.background_refresh_interval());
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                metadata.force_refresh().await.unwrap();
+            }
+        })
+    };
+
+    let before = metadata.cache_stats();
+    let start = Instant::now();
+    let num_requests = 1_000;
+    // Spread over several TTLs, so lookups would find the cache expired
+    // without the background refresh
+    let lookups: Vec<_> = (0..num_requests)
+        .map(|i| {
+            let metadata = metadata.clone();
+            let id = ids[i % ids.len()].clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis((i as u64 * 7) % 6_000)).await;
+                metadata.get_bucket_by_id(&id).await.unwrap();
+            })
+        })
+        .collect();
+    for lookup in futures::future::join_all(lookups).await {
+        lookup.unwrap();
+    }
+    let elapsed = start.elapsed();
+    refresher.abort();
+
+    let after = metadata.cache_stats();
+    let refreshes = after.refresh_count - before.refresh_count;
+    let hits = after.hit_count - before.hit_count;
+    println!(
+        "{} bucket lookups in {:?}: {} from cache, {} misses, {} cache reloads (last at {})",
+        num_requests,
+        elapsed,
+        hits,
+        after.miss_count - before.miss_count,
+        refreshes,
+        metadata.last_refresh_at()
+    );
+
+    assert_eq!(hits, num_requests as u64);
+    // Roughly one reload per second of the run, not one per request
+    assert!(refreshes <= elapsed.as_secs() + 2, "{} reloads", refreshes);
+}